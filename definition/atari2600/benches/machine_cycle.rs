@@ -25,7 +25,7 @@ fn criterion_benchmark(c: &mut Criterion) {
 
     let machine: MachineBuilder<TestPlatform> =
         Machine::build(Some(program_specification), program_manager, None, None);
-    let machine = Atari2600.construct(machine).build(());
+    let machine = Atari2600.construct(machine, Default::default()).build(());
 
     let one_second = Duration::from_secs(1);
     c.bench_function("atari_2600_one_second", |b| {
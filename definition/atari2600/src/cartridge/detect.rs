@@ -0,0 +1,178 @@
+//! Guesses the bank switching scheme of a ROM, since 2600 ROMs carry no header
+//!
+//! The signatures are instruction sequences known to access the hotspots of a
+//! scheme, as collected by other emulators
+
+use super::{CartType, dpc::DISPLAY_ROM_SIZE};
+
+const PARKER_BROTHERS_SIGNATURES: &[&[u8]] = &[
+    // STA $1FE0
+    &[0x8d, 0xe0, 0x1f],
+    // STA $5FE0
+    &[0x8d, 0xe0, 0x5f],
+    // STA $FFE9
+    &[0x8d, 0xe9, 0xff],
+    // NOP $1FE0
+    &[0x0c, 0xe0, 0x1f],
+    // LDA $1FE0
+    &[0xad, 0xe0, 0x1f],
+    // LDA $FFE9
+    &[0xad, 0xe9, 0xff],
+    // LDA $FFED
+    &[0xad, 0xed, 0xff],
+    // LDA $BFF3
+    &[0xad, 0xf3, 0xbf],
+];
+
+const ACTIVISION_SIGNATURES: &[&[u8]] = &[
+    // JSR $D000; DEC $C5
+    &[0x20, 0x00, 0xd0, 0xc6, 0xc5],
+    // JSR $F8C3; LDA $82
+    &[0x20, 0xc3, 0xf8, 0xa5, 0x82],
+    // BNE -5; JSR $FE73
+    &[0xd0, 0xfb, 0x20, 0x73, 0xfe],
+    // JSR $F000; STY $D6
+    &[0x20, 0x00, 0xf0, 0x84, 0xd6],
+];
+
+const M_NETWORK_SIGNATURES: &[&[u8]] = &[
+    // LDA $FFE2
+    &[0xad, 0xe2, 0xff],
+    // LDA $FFE5
+    &[0xad, 0xe5, 0xff],
+    // LDA $1FE5
+    &[0xad, 0xe5, 0x1f],
+    // LDA $1FE7
+    &[0xad, 0xe7, 0x1f],
+    // NOP $1FE7
+    &[0x0c, 0xe7, 0x1f],
+    // STA $FFE7
+    &[0x8d, 0xe7, 0xff],
+    // STA $1FE7
+    &[0x8d, 0xe7, 0x1f],
+];
+
+// STA $3F
+const TIGERVISION_SIGNATURE: &[u8] = &[0x85, 0x3f];
+
+/// Size of the Pitfall II ROM, program plus display data
+pub(super) const DPC_ROM_SIZE: usize = 0x2000 + DISPLAY_ROM_SIZE;
+
+pub(super) fn detect_cart_type(rom: &[u8]) -> Result<CartType, String> {
+    let superchip = has_superchip(rom);
+
+    Ok(match rom.len() {
+        0x800 | 0x1000 => CartType::Raw,
+        0x2000 if contains_any(rom, PARKER_BROTHERS_SIGNATURES) => CartType::E0,
+        0x2000 if is_tigervision(rom) => CartType::Tigervision,
+        0x2000 if contains_any(rom, ACTIVISION_SIGNATURES) => CartType::Fe,
+        0x2000 => CartType::F8 { superchip },
+        // Some dumps have 255 junk bytes on the end
+        DPC_ROM_SIZE | 0x28ff => CartType::Dpc,
+        0x4000 if contains_any(rom, M_NETWORK_SIGNATURES) => CartType::E7,
+        0x4000 if is_tigervision(rom) => CartType::Tigervision,
+        0x4000 => CartType::F6 { superchip },
+        0x8000 if is_tigervision(rom) => CartType::Tigervision,
+        0x8000 => CartType::F4 { superchip },
+        length if length.is_power_of_two() && length > 0x8000 && is_tigervision(rom) => {
+            CartType::Tigervision
+        }
+        length => return Err(format!("Could not detect a scheme for a {length} byte ROM")),
+    })
+}
+
+fn count_occurrences(rom: &[u8], signature: &[u8]) -> usize {
+    rom.windows(signature.len())
+        .filter(|window| *window == signature)
+        .count()
+}
+
+fn contains_any(rom: &[u8], signatures: &[&[u8]]) -> bool {
+    signatures
+        .iter()
+        .any(|signature| count_occurrences(rom, signature) != 0)
+}
+
+fn is_tigervision(rom: &[u8]) -> bool {
+    count_occurrences(rom, TIGERVISION_SIGNATURE) >= 2
+}
+
+/// Superchip RAM shadows the first 256 bytes of every bank, which dumps
+/// capture as the read port repeating the write port
+fn has_superchip(rom: &[u8]) -> bool {
+    rom.chunks(0x1000)
+        .all(|bank| bank.len() >= 0x100 && bank[..0x80] == bank[0x80..0x100])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A ROM with no signatures and no superchip RAM
+    fn banked_rom(size: usize) -> Vec<u8> {
+        (0..size).map(|index| index as u8).collect()
+    }
+
+    #[test]
+    fn small_roms_are_raw() {
+        assert_eq!(detect_cart_type(&[0; 0x800]), Ok(CartType::Raw));
+        assert_eq!(detect_cart_type(&[0; 0x1000]), Ok(CartType::Raw));
+    }
+
+    #[test]
+    fn atari_schemes_by_size() {
+        assert_eq!(
+            detect_cart_type(&banked_rom(0x2000)),
+            Ok(CartType::F8 { superchip: false })
+        );
+        assert_eq!(
+            detect_cart_type(&banked_rom(0x4000)),
+            Ok(CartType::F6 { superchip: false })
+        );
+        assert_eq!(
+            detect_cart_type(&banked_rom(0x8000)),
+            Ok(CartType::F4 { superchip: false })
+        );
+    }
+
+    #[test]
+    fn superchip() {
+        assert_eq!(
+            detect_cart_type(&[0; 0x4000]),
+            Ok(CartType::F6 { superchip: true })
+        );
+    }
+
+    #[test]
+    fn signatures() {
+        let mut rom = banked_rom(0x2000);
+        rom[0x100..0x103].copy_from_slice(PARKER_BROTHERS_SIGNATURES[0]);
+        assert_eq!(detect_cart_type(&rom), Ok(CartType::E0));
+
+        let mut rom = banked_rom(0x2000);
+        rom[0x100..0x105].copy_from_slice(ACTIVISION_SIGNATURES[0]);
+        assert_eq!(detect_cart_type(&rom), Ok(CartType::Fe));
+
+        let mut rom = banked_rom(0x4000);
+        rom[0x100..0x103].copy_from_slice(M_NETWORK_SIGNATURES[0]);
+        assert_eq!(detect_cart_type(&rom), Ok(CartType::E7));
+
+        let mut rom = banked_rom(0x10000);
+        rom[0x100..0x102].copy_from_slice(TIGERVISION_SIGNATURE);
+        rom[0x200..0x202].copy_from_slice(TIGERVISION_SIGNATURE);
+        assert_eq!(detect_cart_type(&rom), Ok(CartType::Tigervision));
+    }
+
+    #[test]
+    fn dpc() {
+        assert_eq!(
+            detect_cart_type(&banked_rom(DPC_ROM_SIZE)),
+            Ok(CartType::Dpc)
+        );
+    }
+
+    #[test]
+    fn unknown_size() {
+        assert!(detect_cart_type(&[0; 0x3000]).is_err());
+    }
+}
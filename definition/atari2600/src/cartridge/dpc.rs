@@ -0,0 +1,170 @@
+use serde::{Deserialize, Serialize};
+
+/// Frequency of the oscillator clocking the music mode data fetchers
+pub(super) const OSCILLATOR_FREQUENCY: u32 = 20000;
+/// Size of the graphics ROM the data fetchers read from
+pub(super) const DISPLAY_ROM_SIZE: usize = 0x800;
+/// Output levels for each combination of the three music mode fetcher flags
+const MUSIC_AMPLITUDES: [u8; 8] = [0x00, 0x04, 0x05, 0x09, 0x06, 0x0a, 0x0b, 0x0f];
+
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize)]
+struct DataFetcher {
+    top: u8,
+    bottom: u8,
+    /// 11 bits, counts down through the display ROM
+    counter: u16,
+    flag: bool,
+    /// Only the last three fetchers can be in music mode
+    music_mode: bool,
+}
+
+impl DataFetcher {
+    fn update_flag(&mut self) {
+        let low = self.counter as u8;
+
+        if low == self.top {
+            self.flag = true;
+        } else if low == self.bottom {
+            self.flag = false;
+        }
+    }
+
+    fn flag_mask(&self) -> u8 {
+        if self.flag { 0xff } else { 0x00 }
+    }
+}
+
+/// The Display Processor Chip found in Pitfall II
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(super) struct Dpc {
+    fetchers: [DataFetcher; 8],
+    random: u8,
+    /// Progress towards the next oscillator tick, scaled by the CPU frequency
+    oscillator_accumulator: u32,
+}
+
+impl Default for Dpc {
+    fn default() -> Self {
+        Self {
+            fetchers: Default::default(),
+            random: 1,
+            oscillator_accumulator: 0,
+        }
+    }
+}
+
+impl Dpc {
+    /// Handles a read from the register area, `$1000-$103F`
+    pub(super) fn read(&mut self, offset: usize, display: &[u8], avoid_side_effects: bool) -> u8 {
+        let index = offset & 0b111;
+        let function = (offset >> 3) & 0b111;
+
+        if !avoid_side_effects {
+            self.clock_random();
+        }
+
+        let fetcher = &mut self.fetchers[index];
+        fetcher.update_flag();
+
+        let flag_mask = fetcher.flag_mask();
+        let display_byte = display[DISPLAY_ROM_SIZE - 1 - usize::from(fetcher.counter)];
+
+        let data = match function {
+            0b000 if index < 4 => self.random,
+            0b000 => {
+                let amplitude_index = self.fetchers[5..]
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, fetcher)| fetcher.music_mode && fetcher.flag)
+                    .fold(0, |index, (bit, _)| index | (1 << bit));
+
+                MUSIC_AMPLITUDES[amplitude_index]
+            }
+            0b001 => display_byte,
+            0b010 => display_byte & flag_mask,
+            0b111 => flag_mask,
+            _ => 0,
+        };
+
+        let fetcher = &mut self.fetchers[index];
+
+        if !avoid_side_effects && !fetcher.music_mode {
+            fetcher.counter = fetcher.counter.wrapping_sub(1) & 0x7ff;
+        }
+
+        data
+    }
+
+    /// Handles a write to the register area, `$1040-$107F`
+    pub(super) fn write(&mut self, offset: usize, data: u8) {
+        let index = offset & 0b111;
+        let function = (offset >> 3) & 0b111;
+        let fetcher = &mut self.fetchers[index];
+
+        match function {
+            0b000 => {
+                fetcher.top = data;
+                fetcher.flag = false;
+            }
+            0b001 => {
+                fetcher.bottom = data;
+            }
+            0b010 => {
+                // Music mode fetchers reload from their top register instead
+                let low = if fetcher.music_mode {
+                    fetcher.top
+                } else {
+                    data
+                };
+
+                fetcher.counter = (fetcher.counter & 0x700) | u16::from(low);
+            }
+            0b011 => {
+                fetcher.counter = (u16::from(data & 0b111) << 8) | (fetcher.counter & 0xff);
+
+                if index >= 5 {
+                    fetcher.music_mode = data & 0b1_0000 != 0;
+                }
+            }
+            0b110 => {
+                self.random = 1;
+            }
+            _ => {}
+        }
+    }
+
+    /// Steps the music oscillator by one CPU cycle
+    pub(super) fn clock(&mut self, cpu_frequency: u32) {
+        self.oscillator_accumulator += OSCILLATOR_FREQUENCY;
+
+        while self.oscillator_accumulator >= cpu_frequency {
+            self.oscillator_accumulator -= cpu_frequency;
+
+            for fetcher in self.fetchers[5..]
+                .iter_mut()
+                .filter(|fetcher| fetcher.music_mode)
+            {
+                let low = match (fetcher.counter as u8).checked_sub(1) {
+                    _ if fetcher.top == 0 => 0,
+                    Some(low) => low,
+                    None => fetcher.top,
+                };
+
+                if low <= fetcher.bottom {
+                    fetcher.flag = false;
+                } else if low <= fetcher.top {
+                    fetcher.flag = true;
+                }
+
+                fetcher.counter = (fetcher.counter & 0x700) | u16::from(low);
+            }
+        }
+    }
+
+    /// Shifts the random number generator, which is a LFSR fed back from bits 7, 5, 4 and 3
+    fn clock_random(&mut self) {
+        let feedback = (self.random & 0b1011_1000).count_ones() as u8 & 1;
+
+        self.random = (self.random << 1) | feedback;
+    }
+}
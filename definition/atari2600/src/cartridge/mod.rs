@@ -0,0 +1,464 @@
+use std::{ops::RangeInclusive, sync::Mutex};
+
+use bytes::Bytes;
+use detect::{DPC_ROM_SIZE, detect_cart_type};
+use dpc::{DISPLAY_ROM_SIZE, Dpc};
+use fluxemu_range::ContiguousRange;
+use fluxemu_runtime::{
+    component::{Component, ComponentConfig, ComponentHandle},
+    machine::builder::{ComponentBuilder, SchedulerParticipation},
+    memory::{Address, AddressSpaceId, MemoryError},
+    path::FluxEmuPath,
+    platform::Platform,
+    program::{RomId, RomRequirement},
+    scheduler::{Frequency, Period, SynchronizationContext},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::tia;
+
+mod detect;
+mod dpc;
+
+const CARTRIDGE_WINDOW: RangeInclusive<Address> = 0x1000..=0x1fff;
+/// TIA registers, which Tigervision carts watch for writes
+const TIGERVISION_HOTSPOTS: RangeInclusive<Address> = 0x0000..=0x003f;
+/// Where the stack pointer points after a JSR or before a RTS, which
+/// Activision carts watch
+const ACTIVISION_HOTSPOTS: RangeInclusive<Address> = 0x01fe..=0x01ff;
+const SLICE_SIZE: usize = 0x400;
+const SUPERCHIP_RAM_SIZE: usize = 0x80;
+/// 1KiB that can replace the lower segment, and 4 banks of 256 bytes
+const M_NETWORK_RAM_SIZE: usize = 0x800;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum CartType {
+    /// 2KiB or 4KiB without any bank switching
+    #[default]
+    Raw,
+    /// Atari 8KiB, switched by accessing $1FF8-$1FF9
+    F8 { superchip: bool },
+    /// Atari 16KiB, switched by accessing $1FF6-$1FF9
+    F6 { superchip: bool },
+    /// Atari 32KiB, switched by accessing $1FF4-$1FFB
+    F4 { superchip: bool },
+    /// Parker Brothers 8KiB, with three 1KiB slices switched by accessing
+    /// $1FE0-$1FF7
+    E0,
+    /// Activision 8KiB, switched by watching subroutine calls and returns
+    Fe,
+    /// Tigervision 3F, with a 2KiB slice switched by writing to $003F
+    Tigervision,
+    /// M-Network 16KiB with 2KiB of RAM, switched by accessing $1FE0-$1FEB
+    E7,
+    /// Pitfall II's Display Processor Chip, with F8 switching
+    Dpc,
+}
+
+impl CartType {
+    /// Checks the ROM is a size the scheme can bank through, since a forced
+    /// scheme never went through detection
+    fn check_rom_size(self, length: usize) -> Result<(), String> {
+        let fits = match self {
+            CartType::Raw => matches!(length, 0x800 | 0x1000),
+            CartType::F8 { .. } | CartType::E0 | CartType::Fe => length == 0x2000,
+            CartType::F6 { .. } | CartType::E7 => length == 0x4000,
+            CartType::F4 { .. } => length == 0x8000,
+            // Any number of 2KiB banks
+            CartType::Tigervision => length != 0 && length.is_multiple_of(0x800),
+            // Some dumps have junk on the end
+            CartType::Dpc => length >= DPC_ROM_SIZE,
+        };
+
+        if fits {
+            Ok(())
+        } else {
+            Err(format!(
+                "A {length} byte ROM can't use the {self:?} cartridge type"
+            ))
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct BankingState {
+    /// ROM offset each 1KiB slice of the cartridge window is showing
+    slices: [usize; 4],
+    ram: Vec<u8>,
+    /// Offset into the RAM of the selected E7 256 byte bank
+    ram_bank: usize,
+    /// If the E7 lower segment is showing RAM instead of ROM
+    lower_segment_ram: bool,
+    /// If the last access was to $01FE, for Activision carts
+    stack_hotspot_accessed: bool,
+    dpc: Option<Dpc>,
+}
+
+impl BankingState {
+    fn select_4k_bank(&mut self, bank: usize) {
+        for (index, slice) in self.slices.iter_mut().enumerate() {
+            *slice = bank * 0x1000 + index * SLICE_SIZE;
+        }
+    }
+
+    fn select_2k_bank(&mut self, segment: usize, bank: usize) {
+        self.slices[segment * 2] = bank * 0x800;
+        self.slices[segment * 2 + 1] = bank * 0x800 + SLICE_SIZE;
+    }
+
+    /// Handles writes to any RAM or registers in the cartridge window
+    fn write(&mut self, cart_type: CartType, offset: usize, data: u8) {
+        match (cart_type, offset) {
+            (
+                CartType::F8 { superchip: true }
+                | CartType::F6 { superchip: true }
+                | CartType::F4 { superchip: true },
+                0x000..=0x07f,
+            ) => {
+                self.ram[offset] = data;
+            }
+            (CartType::E7, 0x000..=0x3ff) if self.lower_segment_ram => {
+                self.ram[offset] = data;
+            }
+            (CartType::E7, 0x800..=0x8ff) => {
+                self.ram[SLICE_SIZE + self.ram_bank + offset - 0x800] = data;
+            }
+            (CartType::Dpc, 0x040..=0x07f) => {
+                self.dpc.as_mut().unwrap().write(offset, data);
+            }
+            _ => {}
+        }
+    }
+
+    /// Switches banks if the cart has a hotspot at this offset
+    fn access_hotspot(&mut self, cart_type: CartType, offset: usize) {
+        match (cart_type, offset) {
+            (CartType::F8 { .. } | CartType::Dpc, 0xff8..=0xff9) => {
+                self.select_4k_bank(offset - 0xff8);
+            }
+            (CartType::F6 { .. }, 0xff6..=0xff9) => {
+                self.select_4k_bank(offset - 0xff6);
+            }
+            (CartType::F4 { .. }, 0xff4..=0xffb) => {
+                self.select_4k_bank(offset - 0xff4);
+            }
+            (CartType::E0, 0xfe0..=0xff7) => {
+                self.slices[(offset - 0xfe0) / 8] = (offset & 0b111) * SLICE_SIZE;
+            }
+            (CartType::E7, 0xfe0..=0xfe7) => {
+                let bank = offset & 0b111;
+
+                // The last bank is RAM instead
+                self.lower_segment_ram = bank == 7;
+
+                if !self.lower_segment_ram {
+                    self.select_2k_bank(0, bank);
+                }
+            }
+            (CartType::E7, 0xfe8..=0xfeb) => {
+                self.ram_bank = (offset & 0b11) * 0x100;
+            }
+            _ => {}
+        }
+    }
+
+    /// Activision carts switch banks based on the first byte on the bus after
+    /// $01FE is accessed, which is the high byte of the address being jumped or
+    /// returned to
+    fn watch_stack(&mut self, cart_type: CartType, address: Address, data: u8) {
+        if cart_type != CartType::Fe {
+            return;
+        }
+
+        if self.stack_hotspot_accessed {
+            self.stack_hotspot_accessed = false;
+
+            // $Fxxx is the first bank, $Dxxx is the second
+            self.select_4k_bank(if data & 0b0010_0000 != 0 { 0 } else { 1 });
+        } else {
+            self.stack_hotspot_accessed = address == *ACTIVISION_HOTSPOTS.start();
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Atari2600Cartridge {
+    rom: Bytes,
+    cart_type: CartType,
+    state: Mutex<BankingState>,
+    /// The component whose registers this cart watches, if it needs to
+    snooped_component: Option<ComponentHandle>,
+    cpu_frequency: Frequency,
+    timestamp: Period,
+}
+
+#[derive(Debug)]
+pub struct Atari2600CartridgeConfig {
+    pub rom: RomId,
+    pub cpu_address_space: AddressSpaceId,
+    pub cpu_frequency: Frequency,
+    pub tia: FluxEmuPath,
+    pub riot_ram: FluxEmuPath,
+    pub force_cart_type: Option<CartType>,
+}
+
+impl Component for Atari2600Cartridge {
+    fn memory_read(
+        &self,
+        address: Address,
+        address_space: AddressSpaceId,
+        avoid_side_effects: bool,
+        buffer: &mut [u8],
+    ) -> Result<(), MemoryError> {
+        let mut state = self.state.lock().unwrap();
+
+        if ACTIVISION_HOTSPOTS.contains(&address) {
+            self.snooped_component
+                .as_ref()
+                .unwrap()
+                .interact(self.timestamp, |riot_ram| {
+                    riot_ram.memory_read(address & 0xff, address_space, avoid_side_effects, buffer)
+                })?;
+
+            if !avoid_side_effects {
+                state.watch_stack(self.cart_type, address, buffer[0]);
+            }
+
+            return Ok(());
+        }
+
+        for (address, data) in
+            RangeInclusive::from_start_and_length(address, buffer.len()).zip(buffer.iter_mut())
+        {
+            let offset = address - CARTRIDGE_WINDOW.start();
+
+            *data = self.read(&mut state, offset, avoid_side_effects);
+
+            if !avoid_side_effects {
+                state.watch_stack(self.cart_type, address, *data);
+                state.access_hotspot(self.cart_type, offset);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn memory_write(
+        &mut self,
+        address: Address,
+        address_space: AddressSpaceId,
+        buffer: &[u8],
+    ) -> Result<(), MemoryError> {
+        let state = self.state.get_mut().unwrap();
+
+        if TIGERVISION_HOTSPOTS.contains(&address) {
+            state.select_2k_bank(0, usize::from(buffer[0]) % (self.rom.len() / 0x800));
+
+            // The TIA still needs to see the write
+            if tia::is_write_register(address) {
+                self.snooped_component
+                    .as_ref()
+                    .unwrap()
+                    .interact_mut(self.timestamp, |tia| {
+                        tia.memory_write(address, address_space, buffer)
+                    })?;
+            }
+
+            return Ok(());
+        }
+
+        if ACTIVISION_HOTSPOTS.contains(&address) {
+            self.snooped_component
+                .as_ref()
+                .unwrap()
+                .interact_mut(self.timestamp, |riot_ram| {
+                    riot_ram.memory_write(address & 0xff, address_space, buffer)
+                })?;
+
+            state.watch_stack(self.cart_type, address, buffer[0]);
+
+            return Ok(());
+        }
+
+        for (address, data) in
+            RangeInclusive::from_start_and_length(address, buffer.len()).zip(buffer.iter().copied())
+        {
+            let offset = address - CARTRIDGE_WINDOW.start();
+
+            state.write(self.cart_type, offset, data);
+            state.access_hotspot(self.cart_type, offset);
+        }
+
+        Ok(())
+    }
+
+    fn synchronize(&mut self, mut context: SynchronizationContext) {
+        let cpu_frequency = self.cpu_frequency.to_num();
+        let state = self.state.get_mut().unwrap();
+
+        for now in context.allocate(self.cpu_frequency.recip(), None) {
+            self.timestamp = now;
+
+            if let Some(dpc) = &mut state.dpc {
+                dpc.clock(cpu_frequency);
+            }
+        }
+    }
+
+    fn needs_work(&self, delta: Period) -> bool {
+        delta >= self.cpu_frequency.recip()
+    }
+}
+
+impl Atari2600Cartridge {
+    fn read(&self, state: &mut BankingState, offset: usize, avoid_side_effects: bool) -> u8 {
+        match (self.cart_type, offset) {
+            (
+                CartType::F8 { superchip: true }
+                | CartType::F6 { superchip: true }
+                | CartType::F4 { superchip: true },
+                0x080..=0x0ff,
+            ) => state.ram[offset - SUPERCHIP_RAM_SIZE],
+            (CartType::E7, 0x400..=0x7ff) if state.lower_segment_ram => {
+                state.ram[offset - SLICE_SIZE]
+            }
+            (CartType::E7, 0x900..=0x9ff) => {
+                state.ram[SLICE_SIZE + state.ram_bank + offset - 0x900]
+            }
+            (CartType::Dpc, 0x000..=0x03f) => state.dpc.as_mut().unwrap().read(
+                offset,
+                &self.rom[0x2000..0x2000 + DISPLAY_ROM_SIZE],
+                avoid_side_effects,
+            ),
+            _ => self.read_rom(state, offset),
+        }
+    }
+
+    fn read_rom(&self, state: &BankingState, offset: usize) -> u8 {
+        let rom_offset = state.slices[offset / SLICE_SIZE] + offset % SLICE_SIZE;
+
+        self.rom[rom_offset % self.rom.len()]
+    }
+}
+
+impl<P: Platform> ComponentConfig<P> for Atari2600CartridgeConfig {
+    type Component = Atari2600Cartridge;
+
+    fn build_component(
+        self,
+        component_builder: ComponentBuilder<'_, P, Self::Component>,
+    ) -> Result<Self::Component, Box<dyn std::error::Error>> {
+        let program_manager = component_builder.program_manager();
+
+        let rom = program_manager
+            .open(self.rom, RomRequirement::Required)
+            .unwrap();
+
+        let cart_type = match self.force_cart_type {
+            Some(cart_type) => cart_type,
+            None => detect_cart_type(&rom)?,
+        };
+
+        tracing::debug!("Using cartridge type {:?}", cart_type);
+
+        cart_type.check_rom_size(rom.len())?;
+
+        let mut state = BankingState::default();
+
+        match cart_type {
+            CartType::Raw => {
+                state.select_4k_bank(0);
+            }
+            CartType::F8 { superchip }
+            | CartType::F6 { superchip }
+            | CartType::F4 { superchip } => {
+                if superchip {
+                    state.ram = vec![0; SUPERCHIP_RAM_SIZE];
+                }
+
+                // Start in the last bank, which is where most games put their
+                // reset code
+                state.select_4k_bank(rom.len() / 0x1000 - 1);
+            }
+            CartType::E0 => {
+                for (slice, bank) in state.slices.iter_mut().zip(4..) {
+                    *slice = bank * SLICE_SIZE;
+                }
+
+                // The last slice is always the final 1KiB of the ROM
+                state.slices[3] = rom.len() - SLICE_SIZE;
+            }
+            CartType::Fe => {
+                state.select_4k_bank(0);
+            }
+            CartType::Tigervision | CartType::E7 => {
+                state.select_2k_bank(0, 0);
+                // The upper segment is always the final 2KiB of the ROM
+                state.select_2k_bank(1, rom.len() / 0x800 - 1);
+
+                if cart_type == CartType::E7 {
+                    state.ram = vec![0; M_NETWORK_RAM_SIZE];
+                }
+            }
+            CartType::Dpc => {
+                state.select_4k_bank(1);
+                state.dpc = Some(Dpc::default());
+            }
+        }
+
+        let mut component_builder =
+            component_builder.memory_map_component(self.cpu_address_space, CARTRIDGE_WINDOW);
+
+        // These schemes need to see accesses outside of the cartridge window,
+        // or need to keep time
+        let snooped_component = match cart_type {
+            CartType::Tigervision => {
+                component_builder = component_builder
+                    .memory_map_component_write(self.cpu_address_space, TIGERVISION_HOTSPOTS);
+
+                component_builder.handle(&self.tia)
+            }
+            CartType::Fe => {
+                component_builder = component_builder
+                    .memory_map_component(self.cpu_address_space, ACTIVISION_HOTSPOTS);
+
+                component_builder.handle(&self.riot_ram)
+            }
+            _ => None,
+        };
+
+        if snooped_component.is_some() || cart_type == CartType::Dpc {
+            component_builder.set_scheduler_participation(SchedulerParticipation::OnDemand);
+        }
+
+        Ok(Atari2600Cartridge {
+            rom,
+            cart_type,
+            state: Mutex::new(state),
+            snooped_component,
+            cpu_frequency: self.cpu_frequency,
+            timestamp: Period::default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn forced_types_check_rom_size() {
+        assert!(
+            CartType::F8 { superchip: false }
+                .check_rom_size(0x800)
+                .is_err()
+        );
+        assert!(CartType::E0.check_rom_size(0x800).is_err());
+        assert!(CartType::Tigervision.check_rom_size(0x400).is_err());
+        assert!(CartType::Dpc.check_rom_size(0x2000).is_err());
+
+        assert_eq!(CartType::Raw.check_rom_size(0x800), Ok(()));
+        assert_eq!(CartType::Tigervision.check_rom_size(0x10000), Ok(()));
+        assert_eq!(CartType::Dpc.check_rom_size(0x28ff), Ok(()));
+    }
+}
@@ -0,0 +1,202 @@
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    io::{Read, Write},
+    sync::{Arc, Mutex},
+};
+
+use bitvec::{prelude::Lsb0, view::BitView};
+use fluxemu_definition_misc::mos6532_riot::{Mos6532Riot, SwchbCallback};
+use fluxemu_runtime::{
+    component::{Component, ComponentConfig, ComponentVersion},
+    input::{GamepadInput, Input, VirtualGamepad, VirtualGamepadMetadata, keyboard::KeyboardInput},
+    machine::builder::{ComponentBuilder, SchedulerParticipation},
+    path::FluxEmuPath,
+    platform::Platform,
+    scheduler::{Period, SynchronizationContext},
+};
+use serde::{Deserialize, Serialize};
+
+use super::POLL_FREQUENCY;
+
+const RESET: GamepadInput = GamepadInput::Start;
+const SELECT: GamepadInput = GamepadInput::Select;
+/// Inputs which flip a switch each time they are pressed, in the same order as
+/// [`SwitchPositions::toggle`] takes them
+const TOGGLES: [GamepadInput; 3] = [
+    GamepadInput::Mode,
+    GamepadInput::LeftTrigger,
+    GamepadInput::RightTrigger,
+];
+
+/// Positions of the switches that stay where they are put
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct SwitchPositions {
+    /// Colour rather than black and white
+    color: bool,
+    /// Difficulty A rather than B, per player
+    difficulty_a: [bool; 2],
+}
+
+impl Default for SwitchPositions {
+    fn default() -> Self {
+        Self {
+            color: true,
+            difficulty_a: [false; 2],
+        }
+    }
+}
+
+impl SwitchPositions {
+    fn toggle(&mut self, index: usize) {
+        match index {
+            0 => self.color = !self.color,
+            1 | 2 => self.difficulty_a[index - 1] = !self.difficulty_a[index - 1],
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// The switches on the front of the console
+#[derive(Debug)]
+pub struct Atari2600ConsoleSwitches {
+    gamepad: Arc<VirtualGamepad>,
+    positions: Arc<Mutex<SwitchPositions>>,
+    /// Toggle inputs as of the last poll, so holding one down only flips it once
+    toggles_held: [bool; TOGGLES.len()],
+}
+
+impl Component for Atari2600ConsoleSwitches {
+    fn snapshot_version(&self) -> Option<ComponentVersion> {
+        Some(0)
+    }
+
+    fn store_snapshot(&self, mut writer: Box<dyn Write>) -> Result<(), Box<dyn std::error::Error>> {
+        let positions = *self.positions.lock().unwrap();
+
+        rmp_serde::encode::write_named(&mut writer, &positions)?;
+
+        Ok(())
+    }
+
+    fn load_snapshot(
+        &mut self,
+        version: ComponentVersion,
+        reader: Box<dyn Read>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        match version {
+            0 => {
+                *self.positions.lock().unwrap() = rmp_serde::decode::from_read(reader)?;
+
+                Ok(())
+            }
+            _ => Err(format!("Unsupported snapshot version: {version}").into()),
+        }
+    }
+
+    fn synchronize(&mut self, mut context: SynchronizationContext) {
+        for _ in context.allocate(Period::ONE / POLL_FREQUENCY, None) {
+            for (index, input) in TOGGLES.into_iter().enumerate() {
+                let held = self.gamepad.get(Input::Gamepad(input)).as_digital(None);
+
+                if held && !self.toggles_held[index] {
+                    self.positions.lock().unwrap().toggle(index);
+                }
+
+                self.toggles_held[index] = held;
+            }
+        }
+    }
+
+    fn needs_work(&self, delta: Period) -> bool {
+        delta >= Period::ONE / POLL_FREQUENCY
+    }
+}
+
+impl<P: Platform> ComponentConfig<P> for Atari2600ConsoleSwitchesConfig {
+    type Component = Atari2600ConsoleSwitches;
+
+    fn build_component(
+        self,
+        component_builder: ComponentBuilder<'_, P, Self::Component>,
+    ) -> Result<Self::Component, Box<dyn std::error::Error>> {
+        let gamepad = create_gamepad();
+        let positions = Arc::default();
+
+        let (component_builder, _) = component_builder
+            .set_scheduler_participation(SchedulerParticipation::SchedulerDriven)
+            .insert_gamepad("console", gamepad.clone());
+
+        component_builder
+            .interact_mut::<Mos6532Riot, _>(&self.mos6532_riot, {
+                let gamepad = gamepad.clone();
+                let positions = Arc::clone(&positions);
+
+                |riot| riot.install_swchb(ConsoleSwitchesSwchbCallback { gamepad, positions })
+            })
+            .unwrap();
+
+        Ok(Atari2600ConsoleSwitches {
+            gamepad,
+            positions,
+            toggles_held: Default::default(),
+        })
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Atari2600ConsoleSwitchesConfig {
+    pub mos6532_riot: FluxEmuPath,
+}
+
+#[derive(Debug)]
+struct ConsoleSwitchesSwchbCallback {
+    gamepad: Arc<VirtualGamepad>,
+    positions: Arc<Mutex<SwitchPositions>>,
+}
+
+impl SwchbCallback for ConsoleSwitchesSwchbCallback {
+    fn read_register(&self) -> u8 {
+        let positions = *self.positions.lock().unwrap();
+
+        let mut value = 0;
+        let value_bits = value.view_bits_mut::<Lsb0>();
+
+        // Reset and select pull their lines low while held
+        value_bits.set(0, !self.gamepad.get(Input::Gamepad(RESET)).as_digital(None));
+        value_bits.set(
+            1,
+            !self.gamepad.get(Input::Gamepad(SELECT)).as_digital(None),
+        );
+        value_bits.set(3, positions.color);
+        value_bits.set(6, positions.difficulty_a[0]);
+        value_bits.set(7, positions.difficulty_a[1]);
+
+        value
+    }
+
+    // Nothing on port B listens
+    fn write_register(&self, _value: u8) {}
+}
+
+fn create_gamepad() -> Arc<VirtualGamepad> {
+    let inputs = [RESET, SELECT].into_iter().chain(TOGGLES);
+
+    VirtualGamepad::new(Cow::Owned(VirtualGamepadMetadata {
+        present_inputs: inputs.clone().map(Input::Gamepad).collect(),
+        default_real2virtual_mappings: HashMap::from_iter(
+            inputs
+                .map(|input| (Input::Gamepad(input), Input::Gamepad(input)))
+                .chain(
+                    [
+                        (KeyboardInput::F1, SELECT),
+                        (KeyboardInput::F2, RESET),
+                        (KeyboardInput::F3, TOGGLES[0]),
+                        (KeyboardInput::F4, TOGGLES[1]),
+                        (KeyboardInput::F5, TOGGLES[2]),
+                    ]
+                    .map(|(real, virt)| (Input::Keyboard(real), Input::Gamepad(virt))),
+                ),
+        ),
+    }))
+}
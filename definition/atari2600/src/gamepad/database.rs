@@ -0,0 +1,77 @@
+//! Controllers games expect, since cartridges have no way of saying
+
+use super::ControllerType;
+
+const PADDLES: [ControllerType; 2] = [ControllerType::Paddles; 2];
+const KEYPADS: [ControllerType; 2] = [ControllerType::Keypad; 2];
+const DRIVING: [ControllerType; 2] = [ControllerType::Driving; 2];
+
+/// Titles as they appear in No-Intro names, without any subtitle or tags
+const CONTROLLERS: &[(&str, [ControllerType; 2])] = &[
+    ("Backgammon", PADDLES),
+    ("Basic Programming", KEYPADS),
+    ("Blackjack", PADDLES),
+    ("Brain Games", KEYPADS),
+    ("Breakout", PADDLES),
+    ("Canyon Bomber", PADDLES),
+    ("Casino", PADDLES),
+    ("Circus Atari", PADDLES),
+    ("Codebreaker", KEYPADS),
+    ("Demons to Diamonds", PADDLES),
+    ("Eggomania", PADDLES),
+    ("Hunt & Score", KEYPADS),
+    ("Indy 500", DRIVING),
+    ("Kaboom!", PADDLES),
+    ("Night Driver", PADDLES),
+    (
+        "Star Raiders",
+        [ControllerType::Joystick, ControllerType::Keypad],
+    ),
+    ("Street Racer", PADDLES),
+    ("Super Breakout", PADDLES),
+    ("Video Olympics", PADDLES),
+    ("Warlords", PADDLES),
+];
+
+/// Strips the tags and subtitle off a name like "Video Olympics - Pong Sports (USA)"
+fn title(name: &str) -> &str {
+    let name = name.split(" (").next().unwrap_or(name);
+
+    name.split(" - ").next().unwrap_or(name).trim()
+}
+
+pub(crate) fn lookup_controllers<'a>(
+    names: impl IntoIterator<Item = &'a String>,
+) -> Option<[ControllerType; 2]> {
+    names.into_iter().find_map(|name| {
+        let title = title(name);
+
+        CONTROLLERS
+            .iter()
+            .find(|(entry, _)| entry.eq_ignore_ascii_case(title))
+            .map(|(_, controllers)| *controllers)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tags_and_subtitles_are_ignored() {
+        assert_eq!(
+            lookup_controllers([&"Video Olympics - Pong Sports (USA)".to_string()]),
+            Some(PADDLES)
+        );
+        assert_eq!(
+            lookup_controllers([&"Indy 500 (USA) (Rev 1)".to_string()]),
+            Some(DRIVING)
+        );
+    }
+
+    #[test]
+    fn unknown_games() {
+        assert_eq!(lookup_controllers([&"Combat (USA)".to_string()]), None);
+        assert_eq!(lookup_controllers([]), None);
+    }
+}
@@ -0,0 +1,138 @@
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use fluxemu_runtime::{
+    input::{GamepadInput, Input, VirtualGamepad, VirtualGamepadMetadata, keyboard::KeyboardInput},
+    machine::builder::ComponentBuilder,
+    platform::Platform,
+};
+
+use super::{Atari2600Controllers, Controller};
+use crate::tia::InptCallback;
+
+/// The two bit Gray code the encoder outputs as it turns clockwise
+const GRAY_CODE: [u8; 4] = [0b00, 0b01, 0b11, 0b10];
+/// Encoder steps per poll with the stick held all the way over, which is about
+/// two turns a second
+const STEPS_PER_POLL: f32 = 0.5;
+
+#[derive(Debug, Default)]
+struct DrivingState {
+    /// Index into [`GRAY_CODE`]
+    position: usize,
+    /// Fractional steps turned since the encoder last changed
+    progress: f32,
+}
+
+/// The driving controller, a knob that spins freely with a rotary encoder
+/// rather than a pot
+#[derive(Debug)]
+pub(super) struct Driving {
+    gamepad: Arc<VirtualGamepad>,
+    state: Mutex<DrivingState>,
+}
+
+impl Driving {
+    pub(super) fn insert<'a, P: Platform>(
+        component_builder: ComponentBuilder<'a, P, Atari2600Controllers>,
+        port: usize,
+    ) -> (ComponentBuilder<'a, P, Atari2600Controllers>, Arc<Self>) {
+        let gamepad = create_gamepad();
+
+        let (component_builder, _) =
+            component_builder.insert_gamepad(&format!("driving-{port}"), gamepad.clone());
+
+        (
+            component_builder,
+            Arc::new(Self {
+                gamepad,
+                state: Mutex::default(),
+            }),
+        )
+    }
+}
+
+impl Controller for Driving {
+    fn read_swcha(&self) -> u8 {
+        let state = self.state.lock().unwrap();
+
+        // The encoder sits on the up and down lines
+        0b1100 | GRAY_CODE[state.position]
+    }
+
+    fn poll(&self) {
+        let left = self
+            .gamepad
+            .get(Input::Gamepad(GamepadInput::LeftStickLeft))
+            .as_analog();
+        let right = self
+            .gamepad
+            .get(Input::Gamepad(GamepadInput::LeftStickRight))
+            .as_analog();
+
+        let mut state = self.state.lock().unwrap();
+        state.progress += (right - left) * STEPS_PER_POLL;
+
+        while state.progress >= 1.0 {
+            state.progress -= 1.0;
+            state.position = (state.position + 1) % GRAY_CODE.len();
+        }
+
+        while state.progress <= -1.0 {
+            state.progress += 1.0;
+            state.position = (state.position + GRAY_CODE.len() - 1) % GRAY_CODE.len();
+        }
+    }
+}
+
+impl InptCallback for Driving {
+    fn pot_charge(&self, _pin: usize) -> Option<f32> {
+        None
+    }
+
+    fn fire(&self) -> bool {
+        !self
+            .gamepad
+            .get(Input::Gamepad(GamepadInput::FPadDown))
+            .as_digital(None)
+    }
+}
+
+fn create_gamepad() -> Arc<VirtualGamepad> {
+    VirtualGamepad::new(Cow::Owned(VirtualGamepadMetadata {
+        present_inputs: Vec::from_iter([
+            Input::Gamepad(GamepadInput::LeftStickLeft),
+            Input::Gamepad(GamepadInput::LeftStickRight),
+            Input::Gamepad(GamepadInput::FPadDown),
+        ]),
+        default_real2virtual_mappings: HashMap::from_iter([
+            (
+                Input::Gamepad(GamepadInput::LeftStickLeft),
+                Input::Gamepad(GamepadInput::LeftStickLeft),
+            ),
+            (
+                Input::Gamepad(GamepadInput::LeftStickRight),
+                Input::Gamepad(GamepadInput::LeftStickRight),
+            ),
+            (
+                Input::Gamepad(GamepadInput::FPadDown),
+                Input::Gamepad(GamepadInput::FPadDown),
+            ),
+            (
+                Input::Keyboard(KeyboardInput::ArrowLeft),
+                Input::Gamepad(GamepadInput::LeftStickLeft),
+            ),
+            (
+                Input::Keyboard(KeyboardInput::ArrowRight),
+                Input::Gamepad(GamepadInput::LeftStickRight),
+            ),
+            (
+                Input::Keyboard(KeyboardInput::KeyZ),
+                Input::Gamepad(GamepadInput::FPadDown),
+            ),
+        ]),
+    }))
+}
@@ -1,117 +1,61 @@
 use std::{borrow::Cow, collections::HashMap, sync::Arc};
 
 use bitvec::{prelude::Lsb0, view::BitView};
-use fluxemu_definition_misc::mos6532_riot::{Mos6532Riot, SwchaCallback};
 use fluxemu_runtime::{
-    component::{Component, ComponentConfig},
     input::{GamepadInput, Input, VirtualGamepad, VirtualGamepadMetadata, keyboard::KeyboardInput},
     machine::builder::ComponentBuilder,
-    path::FluxEmuPath,
     platform::Platform,
 };
 
-#[derive(Debug)]
-pub struct Atari2600Joystick;
-
-impl Component for Atari2600Joystick {}
+use super::{Atari2600Controllers, Controller};
+use crate::tia::InptCallback;
 
-impl<P: Platform> ComponentConfig<P> for Atari2600JoystickConfig {
-    type Component = Atari2600Joystick;
+#[derive(Debug)]
+pub(super) struct Joystick {
+    gamepad: Arc<VirtualGamepad>,
+}
 
-    fn build_component(
-        self,
-        component_builder: ComponentBuilder<'_, P, Self::Component>,
-    ) -> Result<Self::Component, Box<dyn std::error::Error>> {
-        let player1_gamepad = create_gamepad();
-        let player2_gamepad = create_gamepad();
+impl Joystick {
+    pub(super) fn insert<'a, P: Platform>(
+        component_builder: ComponentBuilder<'a, P, Atari2600Controllers>,
+        port: usize,
+    ) -> (ComponentBuilder<'a, P, Atari2600Controllers>, Arc<Self>) {
+        let gamepad = create_gamepad();
 
         let (component_builder, _) =
-            component_builder.insert_gamepad("player-0", player1_gamepad.clone());
-        let (component_builder, _) =
-            component_builder.insert_gamepad("player-1", player2_gamepad.clone());
+            component_builder.insert_gamepad(&format!("player-{port}"), gamepad.clone());
 
-        component_builder
-            .interact_mut::<Mos6532Riot, _>(&self.mos6532_riot, |riot| {
-                riot.install_swcha(JoystickSwchaCallback {
-                    gamepads: [player1_gamepad, player2_gamepad],
-                });
-            })
-            .unwrap();
-
-        Ok(Atari2600Joystick)
+        (component_builder, Arc::new(Self { gamepad }))
     }
-}
 
-#[derive(Debug)]
-pub struct Atari2600JoystickConfig {
-    pub mos6532_riot: FluxEmuPath,
-}
-
-#[derive(Debug)]
-pub struct JoystickSwchaCallback {
-    gamepads: [Arc<VirtualGamepad>; 2],
+    fn pressed(&self, input: GamepadInput) -> bool {
+        self.gamepad.get(Input::Gamepad(input)).as_digital(None)
+    }
 }
 
-impl SwchaCallback for JoystickSwchaCallback {
-    fn read_register(&self) -> u8 {
-        let mut value = 0;
+impl Controller for Joystick {
+    fn read_swcha(&self) -> u8 {
+        let mut value = 0b1111;
         let value_bits = value.view_bits_mut::<Lsb0>();
-        let (player1, player2) = value_bits.split_at_mut(4);
-
-        player1.set(
-            0,
-            self.gamepads[0]
-                .get(Input::Gamepad(GamepadInput::LeftStickUp))
-                .as_digital(None),
-        );
-        player1.set(
-            1,
-            self.gamepads[0]
-                .get(Input::Gamepad(GamepadInput::LeftStickDown))
-                .as_digital(None),
-        );
-        player1.set(
-            2,
-            self.gamepads[0]
-                .get(Input::Gamepad(GamepadInput::LeftStickLeft))
-                .as_digital(None),
-        );
-        player1.set(
-            3,
-            self.gamepads[0]
-                .get(Input::Gamepad(GamepadInput::LeftStickRight))
-                .as_digital(None),
-        );
 
-        player2.set(
-            0,
-            self.gamepads[1]
-                .get(Input::Gamepad(GamepadInput::LeftStickUp))
-                .as_digital(None),
-        );
-        player2.set(
-            1,
-            self.gamepads[1]
-                .get(Input::Gamepad(GamepadInput::LeftStickDown))
-                .as_digital(None),
-        );
-        player2.set(
-            2,
-            self.gamepads[1]
-                .get(Input::Gamepad(GamepadInput::LeftStickLeft))
-                .as_digital(None),
-        );
-        player2.set(
-            3,
-            self.gamepads[1]
-                .get(Input::Gamepad(GamepadInput::LeftStickRight))
-                .as_digital(None),
-        );
+        // Switches pull the lines low
+        value_bits.set(0, !self.pressed(GamepadInput::LeftStickUp));
+        value_bits.set(1, !self.pressed(GamepadInput::LeftStickDown));
+        value_bits.set(2, !self.pressed(GamepadInput::LeftStickLeft));
+        value_bits.set(3, !self.pressed(GamepadInput::LeftStickRight));
 
         value
     }
+}
+
+impl InptCallback for Joystick {
+    fn pot_charge(&self, _pin: usize) -> Option<f32> {
+        None
+    }
 
-    fn write_register(&self, value: u8) {}
+    fn fire(&self) -> bool {
+        !self.pressed(GamepadInput::FPadDown)
+    }
 }
 
 fn create_gamepad() -> Arc<VirtualGamepad> {
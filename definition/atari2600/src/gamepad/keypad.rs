@@ -0,0 +1,130 @@
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    sync::{
+        Arc,
+        atomic::{AtomicU8, Ordering},
+    },
+};
+
+use fluxemu_runtime::{
+    input::{Input, VirtualGamepad, VirtualGamepadMetadata, keyboard::KeyboardInput},
+    machine::builder::ComponentBuilder,
+    platform::Platform,
+};
+
+use super::{Atari2600Controllers, Controller};
+use crate::tia::InptCallback;
+
+/// Keys by row then column, as laid out on the keypad
+const KEYS: [[KeyboardInput; 3]; 4] = [
+    [
+        KeyboardInput::Digit1,
+        KeyboardInput::Digit2,
+        KeyboardInput::Digit3,
+    ],
+    [
+        KeyboardInput::Digit4,
+        KeyboardInput::Digit5,
+        KeyboardInput::Digit6,
+    ],
+    [
+        KeyboardInput::Digit7,
+        KeyboardInput::Digit8,
+        KeyboardInput::Digit9,
+    ],
+    [
+        KeyboardInput::NumpadMultiply,
+        KeyboardInput::Digit0,
+        KeyboardInput::NumpadHash,
+    ],
+];
+
+/// The 12 key keypad, which is scanned by driving one of the rows low and
+/// seeing which columns follow
+#[derive(Debug)]
+pub(super) struct Keypad {
+    gamepad: Arc<VirtualGamepad>,
+    /// Levels of the row lines, bit 0 being the top row
+    rows: AtomicU8,
+}
+
+impl Keypad {
+    pub(super) fn insert<'a, P: Platform>(
+        component_builder: ComponentBuilder<'a, P, Atari2600Controllers>,
+        port: usize,
+    ) -> (ComponentBuilder<'a, P, Atari2600Controllers>, Arc<Self>) {
+        let gamepad = create_gamepad();
+
+        let (component_builder, _) =
+            component_builder.insert_gamepad(&format!("keypad-{port}"), gamepad.clone());
+
+        (
+            component_builder,
+            Arc::new(Self {
+                gamepad,
+                rows: AtomicU8::new(0b1111),
+            }),
+        )
+    }
+
+    /// If a key in a row being driven low connects it to `column`
+    fn column_pulled_low(&self, column: usize) -> bool {
+        let rows = self.rows.load(Ordering::Relaxed);
+
+        KEYS.iter()
+            .enumerate()
+            .filter(|(row, _)| rows & (1 << row) == 0)
+            .any(|(_, keys)| {
+                self.gamepad
+                    .get(Input::Keyboard(keys[column]))
+                    .as_digital(None)
+            })
+    }
+}
+
+impl Controller for Keypad {
+    fn write_swcha(&self, value: u8) {
+        self.rows.store(value, Ordering::Relaxed);
+    }
+}
+
+impl InptCallback for Keypad {
+    fn pot_charge(&self, pin: usize) -> Option<f32> {
+        // The keypad pulls the pot lines up hard, so they charge almost instantly
+        (!self.column_pulled_low(pin)).then_some(0.0)
+    }
+
+    fn fire(&self) -> bool {
+        !self.column_pulled_low(2)
+    }
+}
+
+fn create_gamepad() -> Arc<VirtualGamepad> {
+    let keys = KEYS.iter().flatten().copied();
+
+    let numpad = [
+        (KeyboardInput::Numpad0, KeyboardInput::Digit0),
+        (KeyboardInput::Numpad1, KeyboardInput::Digit1),
+        (KeyboardInput::Numpad2, KeyboardInput::Digit2),
+        (KeyboardInput::Numpad3, KeyboardInput::Digit3),
+        (KeyboardInput::Numpad4, KeyboardInput::Digit4),
+        (KeyboardInput::Numpad5, KeyboardInput::Digit5),
+        (KeyboardInput::Numpad6, KeyboardInput::Digit6),
+        (KeyboardInput::Numpad7, KeyboardInput::Digit7),
+        (KeyboardInput::Numpad8, KeyboardInput::Digit8),
+        (KeyboardInput::Numpad9, KeyboardInput::Digit9),
+        // For keyboards without a numpad
+        (KeyboardInput::Minus, KeyboardInput::NumpadMultiply),
+        (KeyboardInput::Equal, KeyboardInput::NumpadHash),
+    ];
+
+    VirtualGamepad::new(Cow::Owned(VirtualGamepadMetadata {
+        present_inputs: keys.clone().map(Input::Keyboard).collect(),
+        default_real2virtual_mappings: HashMap::from_iter(
+            keys.map(|key| (key, key))
+                .chain(numpad)
+                .map(|(real, virt)| (Input::Keyboard(real), Input::Keyboard(virt))),
+        ),
+    }))
+}
@@ -1 +1,172 @@
-pub mod joystick;
+use std::{fmt::Debug, marker::PhantomData, sync::Arc};
+
+use driving::Driving;
+use fluxemu_definition_misc::mos6532_riot::{Mos6532Riot, SwchaCallback};
+use fluxemu_runtime::{
+    component::{Component, ComponentConfig},
+    machine::builder::{ComponentBuilder, SchedulerParticipation},
+    path::FluxEmuPath,
+    platform::Platform,
+    scheduler::{Period, SynchronizationContext},
+};
+use joystick::Joystick;
+use keypad::Keypad;
+use paddles::Paddles;
+use serde::{Deserialize, Serialize};
+use strum::{Display, EnumString};
+
+use crate::tia::{InptCallback, SupportedGraphicsApiTia, Tia, region::Region};
+
+mod console;
+mod database;
+mod driving;
+mod joystick;
+mod keypad;
+mod paddles;
+
+pub(crate) use console::Atari2600ConsoleSwitchesConfig;
+pub(crate) use database::lookup_controllers;
+
+/// How often controllers that track how long an input was held are updated
+const POLL_FREQUENCY: u128 = 60;
+
+/// What is plugged into a controller port
+#[derive(
+    Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq, Display, EnumString,
+)]
+#[strum(serialize_all = "lowercase")]
+pub enum ControllerType {
+    #[default]
+    Joystick,
+    /// A pair of paddles, which share a port
+    Paddles,
+    /// The 12 key keypad
+    Keypad,
+    /// The driving controller
+    Driving,
+}
+
+/// A controller as seen by both the RIOT and the TIA
+trait Controller: InptCallback {
+    /// The four port A lines belonging to this port, bit 3 being the highest
+    fn read_swcha(&self) -> u8 {
+        0b1111
+    }
+
+    /// What the RIOT drives onto the four port A lines belonging to this port
+    fn write_swcha(&self, _value: u8) {}
+
+    /// Called at [`POLL_FREQUENCY`]
+    fn poll(&self) {}
+}
+
+#[derive(Debug)]
+pub struct Atari2600Controllers {
+    ports: [Arc<dyn Controller>; 2],
+}
+
+impl Component for Atari2600Controllers {
+    fn synchronize(&mut self, mut context: SynchronizationContext) {
+        for _ in context.allocate(Period::ONE / POLL_FREQUENCY, None) {
+            for controller in &self.ports {
+                controller.poll();
+            }
+        }
+    }
+
+    fn needs_work(&self, delta: Period) -> bool {
+        delta >= Period::ONE / POLL_FREQUENCY
+    }
+}
+
+impl<R: Region, P: Platform<GraphicsApi: SupportedGraphicsApiTia>> ComponentConfig<P>
+    for Atari2600ControllersConfig<R>
+{
+    type Component = Atari2600Controllers;
+
+    fn build_component(
+        self,
+        mut component_builder: ComponentBuilder<'_, P, Self::Component>,
+    ) -> Result<Self::Component, Box<dyn std::error::Error>> {
+        let mut ports = Vec::with_capacity(self.ports.len());
+
+        for (port, controller_type) in self.ports.into_iter().enumerate() {
+            let controller: Arc<dyn Controller>;
+
+            (component_builder, controller) = match controller_type {
+                ControllerType::Joystick => {
+                    let (component_builder, controller) = Joystick::insert(component_builder, port);
+                    (component_builder, controller as Arc<dyn Controller>)
+                }
+                ControllerType::Paddles => {
+                    let (component_builder, controller) = Paddles::insert(component_builder, port);
+                    (component_builder, controller as Arc<dyn Controller>)
+                }
+                ControllerType::Keypad => {
+                    let (component_builder, controller) = Keypad::insert(component_builder, port);
+                    (component_builder, controller as Arc<dyn Controller>)
+                }
+                ControllerType::Driving => {
+                    let (component_builder, controller) = Driving::insert(component_builder, port);
+                    (component_builder, controller as Arc<dyn Controller>)
+                }
+            };
+
+            ports.push(controller);
+        }
+
+        let ports: [Arc<dyn Controller>; 2] = ports.try_into().unwrap();
+
+        component_builder
+            .interact_mut::<Mos6532Riot, _>(&self.mos6532_riot, {
+                let ports = ports.clone();
+
+                |riot| riot.install_swcha(ControllersSwchaCallback { ports })
+            })
+            .unwrap();
+
+        component_builder
+            .interact_mut::<Tia<R, P::GraphicsApi>, _>(&self.tia, {
+                let ports = ports.clone();
+
+                |tia| {
+                    for (port, controller) in ports.into_iter().enumerate() {
+                        tia.install_inpt(port, controller);
+                    }
+                }
+            })
+            .unwrap();
+
+        // Only the driving controller needs polling
+        if self.ports.contains(&ControllerType::Driving) {
+            component_builder.set_scheduler_participation(SchedulerParticipation::SchedulerDriven);
+        }
+
+        Ok(Atari2600Controllers { ports })
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Atari2600ControllersConfig<R: Region> {
+    pub mos6532_riot: FluxEmuPath,
+    pub tia: FluxEmuPath,
+    pub ports: [ControllerType; 2],
+    pub _phantom: PhantomData<R>,
+}
+
+/// Splits port A between the two controller ports, the left port getting the high nibble
+#[derive(Debug)]
+struct ControllersSwchaCallback {
+    ports: [Arc<dyn Controller>; 2],
+}
+
+impl SwchaCallback for ControllersSwchaCallback {
+    fn read_register(&self) -> u8 {
+        (self.ports[0].read_swcha() << 4) | (self.ports[1].read_swcha() & 0b1111)
+    }
+
+    fn write_register(&self, value: u8) {
+        self.ports[0].write_swcha(value >> 4);
+        self.ports[1].write_swcha(value & 0b1111);
+    }
+}
@@ -0,0 +1,113 @@
+use std::{borrow::Cow, collections::HashMap, sync::Arc};
+
+use bitvec::{prelude::Lsb0, view::BitView};
+use fluxemu_runtime::{
+    input::{GamepadInput, Input, VirtualGamepad, VirtualGamepadMetadata, keyboard::KeyboardInput},
+    machine::builder::ComponentBuilder,
+    platform::Platform,
+};
+
+use super::{Atari2600Controllers, Controller};
+use crate::tia::InptCallback;
+
+/// A pair of paddles, which share a port with each paddle getting a pot line
+#[derive(Debug)]
+pub(super) struct Paddles {
+    gamepads: [Arc<VirtualGamepad>; 2],
+}
+
+impl Paddles {
+    pub(super) fn insert<'a, P: Platform>(
+        component_builder: ComponentBuilder<'a, P, Atari2600Controllers>,
+        port: usize,
+    ) -> (ComponentBuilder<'a, P, Atari2600Controllers>, Arc<Self>) {
+        let gamepads = [create_gamepad(), create_gamepad()];
+
+        let (component_builder, _) =
+            component_builder.insert_gamepad(&format!("paddle-{}", port * 2), gamepads[0].clone());
+        let (component_builder, _) = component_builder
+            .insert_gamepad(&format!("paddle-{}", port * 2 + 1), gamepads[1].clone());
+
+        (component_builder, Arc::new(Self { gamepads }))
+    }
+
+    /// How far the knob is turned, with the stick resting in the middle
+    fn position(&self, paddle: usize) -> f32 {
+        let gamepad = &self.gamepads[paddle];
+
+        let left = gamepad
+            .get(Input::Gamepad(GamepadInput::LeftStickLeft))
+            .as_analog();
+        let right = gamepad
+            .get(Input::Gamepad(GamepadInput::LeftStickRight))
+            .as_analog();
+
+        // Turning clockwise lowers the resistance
+        0.5 + (left - right) / 2.0
+    }
+}
+
+impl Controller for Paddles {
+    fn read_swcha(&self) -> u8 {
+        let mut value = 0b1111;
+        let value_bits = value.view_bits_mut::<Lsb0>();
+
+        // The buttons sit on the lines a joystick would use for left and right
+        for (paddle, gamepad) in self.gamepads.iter().enumerate() {
+            value_bits.set(
+                3 - paddle,
+                !gamepad
+                    .get(Input::Gamepad(GamepadInput::FPadDown))
+                    .as_digital(None),
+            );
+        }
+
+        value
+    }
+}
+
+impl InptCallback for Paddles {
+    fn pot_charge(&self, pin: usize) -> Option<f32> {
+        Some(self.position(pin))
+    }
+
+    fn fire(&self) -> bool {
+        true
+    }
+}
+
+fn create_gamepad() -> Arc<VirtualGamepad> {
+    VirtualGamepad::new(Cow::Owned(VirtualGamepadMetadata {
+        present_inputs: Vec::from_iter([
+            Input::Gamepad(GamepadInput::LeftStickLeft),
+            Input::Gamepad(GamepadInput::LeftStickRight),
+            Input::Gamepad(GamepadInput::FPadDown),
+        ]),
+        default_real2virtual_mappings: HashMap::from_iter([
+            (
+                Input::Gamepad(GamepadInput::LeftStickLeft),
+                Input::Gamepad(GamepadInput::LeftStickLeft),
+            ),
+            (
+                Input::Gamepad(GamepadInput::LeftStickRight),
+                Input::Gamepad(GamepadInput::LeftStickRight),
+            ),
+            (
+                Input::Gamepad(GamepadInput::FPadDown),
+                Input::Gamepad(GamepadInput::FPadDown),
+            ),
+            (
+                Input::Keyboard(KeyboardInput::ArrowLeft),
+                Input::Gamepad(GamepadInput::LeftStickLeft),
+            ),
+            (
+                Input::Keyboard(KeyboardInput::ArrowRight),
+                Input::Gamepad(GamepadInput::LeftStickRight),
+            ),
+            (
+                Input::Keyboard(KeyboardInput::KeyZ),
+                Input::Gamepad(GamepadInput::FPadDown),
+            ),
+        ]),
+    }))
+}
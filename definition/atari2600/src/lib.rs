@@ -1,18 +1,24 @@
-use std::{marker::PhantomData, ops::RangeInclusive};
+use std::{marker::PhantomData, ops::RangeInclusive, str::FromStr, sync::Arc};
 
 use cartridge::Atari2600CartridgeConfig;
+pub use cartridge::CartType;
 use fluxemu_definition_misc::mos6532_riot::Mos6532RiotConfig;
 use fluxemu_definition_mos6502::{Mos6502Config, Mos6502Kind};
 use fluxemu_runtime::{
-    machine::{MachineFactory, builder::MachineBuilder},
+    graphics::software::Software,
+    machine::{Machine, MachineFactory, builder::MachineBuilder},
     memory::{Address, AddressSpaceId},
-    path::FluxEmuPath,
+    path::{FluxEmuPath, Namespace},
     platform::Platform,
-    program::Filesystem,
+    program::{Filesystem, ProgramManager, ProgramSpecification, VideoStandard},
+    scheduler::Frequency,
 };
-use gamepad::joystick::Atari2600JoystickConfig;
+pub use gamepad::ControllerType;
+use gamepad::{Atari2600ConsoleSwitchesConfig, Atari2600ControllersConfig, lookup_controllers};
+use serde::{Deserialize, Serialize};
 use strum::Display;
 use tia::{
+    Tia,
     config::TiaConfig,
     region::{Region, ntsc::Ntsc, pal::Pal, secam::Secam},
 };
@@ -30,83 +36,230 @@ enum RegionSelection {
     Secam,
 }
 
+impl RegionSelection {
+    fn video_standard(self) -> VideoStandard {
+        match self {
+            RegionSelection::Ntsc => VideoStandard::Ntsc,
+            RegionSelection::Pal => VideoStandard::Pal,
+            RegionSelection::Secam => VideoStandard::Secam,
+        }
+    }
+}
+
+/// How many frames are emulated when detecting the region
+const REGION_DETECTION_FRAMES: usize = 30;
+/// Halfway between the NTSC and PAL scanline counts
+const PAL_SCANLINE_THRESHOLD: u16 = (Ntsc::TOTAL_SCANLINES + Pal::TOTAL_SCANLINES) / 2;
+
 #[derive(Default, Debug)]
 pub struct Atari2600;
 
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+#[serde(default)]
+pub struct Atari2600Quirks {
+    /// Use this bank switching scheme instead of the one guessed from the ROM
+    pub force_cart_type: Option<CartType>,
+    /// What is plugged into each controller port, overriding what the game is
+    /// known to use
+    pub controllers: [Option<ControllerType>; 2],
+}
+
 impl<P: Platform<GraphicsApi: SupportedGraphicsApiTia>> MachineFactory<P> for Atari2600 {
-    fn construct(&self, machine: MachineBuilder<P>) -> MachineBuilder<P> {
-        // Atari 2600 CPU only has 13 address lines
-        let (machine, cpu_address_space) = machine.insert_address_space(13);
-        // For now, assume all games are ntsc
-        let region = RegionSelection::Ntsc;
-
-        let Filesystem::Single { rom_id, .. } =
-            machine.program_specification().unwrap().info.filesystem()
-        else {
-            panic!("No atari 2600 game has a structured filesystem")
-        };
-        let rom = *rom_id;
-
-        let (mut machine, _) = machine.insert_component(
-            "cartridge",
-            Atari2600CartridgeConfig {
-                rom,
-                cpu_address_space,
-                force_cart_type: None,
-            },
-        );
-
-        for source_addresses in tia_register_mirror_ranges() {
-            machine =
-                machine.memory_map_mirror(cpu_address_space, source_addresses, 0x0000..=0x003f);
-        }
+    type Quirks = Atari2600Quirks;
 
-        for source_addresses in riot_register_mirror_ranges() {
-            machine = machine.memory_map_mirror(cpu_address_space, source_addresses, 0x280..=0x29f);
-        }
+    fn construct(&self, machine: MachineBuilder<P>, quirks: Atari2600Quirks) -> MachineBuilder<P> {
+        let program_specification = machine.program_specification().unwrap();
 
-        for source_addresses in riot_ram_mirror_ranges() {
-            machine = machine.memory_map_mirror(cpu_address_space, source_addresses, 0x80..=0xff);
-        }
+        let region = match program_specification.info.video_standard() {
+            Some(VideoStandard::Ntsc) => RegionSelection::Ntsc,
+            Some(VideoStandard::Pal) => RegionSelection::Pal,
+            Some(VideoStandard::Secam) => RegionSelection::Secam,
+            video_standard => {
+                if let Some(video_standard) = video_standard {
+                    tracing::warn!(
+                        "The Atari 2600 has no {} model, detecting the region instead",
+                        video_standard
+                    );
+                }
+
+                let region = detect_region(
+                    program_specification.clone(),
+                    machine.program_manager().clone(),
+                    quirks,
+                );
+                tracing::info!("Detected region {}", region);
 
-        let (machine, mos6532_riot) = match region {
-            RegionSelection::Ntsc => common::<Ntsc, _>(cpu_address_space, machine),
-            RegionSelection::Pal => common::<Pal, _>(cpu_address_space, machine),
-            RegionSelection::Secam => common::<Secam, _>(cpu_address_space, machine),
+                // Only remembered when the database had nothing to say, so a
+                // standard the 2600 lacks is still warned about every time
+                if video_standard.is_none()
+                    && let Err(error) = machine
+                        .program_manager()
+                        .set_video_standard(program_specification, region.video_standard())
+                {
+                    tracing::warn!("Could not remember the detected region: {}", error);
+                }
+
+                region
+            }
         };
 
-        let (machine, _) =
-            machine.insert_component("joystick", Atari2600JoystickConfig { mos6532_riot });
+        let known_controllers = lookup_controllers(program_specification.info.names());
+        let controllers = std::array::from_fn(|port| {
+            quirks.controllers[port]
+                .or(known_controllers.map(|controllers| controllers[port]))
+                .unwrap_or_default()
+        });
 
-        machine
+        construct_for_region(machine, region, controllers, quirks)
     }
 }
 
+fn construct_for_region<P: Platform<GraphicsApi: SupportedGraphicsApiTia>>(
+    machine: MachineBuilder<P>,
+    region: RegionSelection,
+    controllers: [ControllerType; 2],
+    quirks: Atari2600Quirks,
+) -> MachineBuilder<P> {
+    // Atari 2600 CPU only has 13 address lines
+    let (mut machine, cpu_address_space) = machine.insert_address_space(13);
+
+    let Filesystem::Single { rom_id, .. } =
+        machine.program_specification().unwrap().info.filesystem()
+    else {
+        panic!("No atari 2600 game has a structured filesystem")
+    };
+    let rom = *rom_id;
+
+    for source_addresses in tia_register_mirror_ranges() {
+        machine = machine.memory_map_mirror(cpu_address_space, source_addresses, 0x0000..=0x003f);
+    }
+
+    for source_addresses in riot_register_mirror_ranges() {
+        machine = machine.memory_map_mirror(cpu_address_space, source_addresses, 0x280..=0x29f);
+    }
+
+    for source_addresses in riot_ram_mirror_ranges() {
+        machine = machine.memory_map_mirror(cpu_address_space, source_addresses, 0x80..=0xff);
+    }
+
+    let (
+        machine,
+        Common {
+            tia,
+            mos6532_riot,
+            cpu_frequency,
+        },
+    ) = match region {
+        RegionSelection::Ntsc => common::<Ntsc, _>(cpu_address_space, machine, controllers),
+        RegionSelection::Pal => common::<Pal, _>(cpu_address_space, machine, controllers),
+        RegionSelection::Secam => common::<Secam, _>(cpu_address_space, machine, controllers),
+    };
+
+    let (machine, _) = machine.insert_component(
+        "console_switches",
+        Atari2600ConsoleSwitchesConfig {
+            mos6532_riot: mos6532_riot.clone(),
+        },
+    );
+
+    let mut riot_ram = mos6532_riot.clone();
+    riot_ram.push(Namespace::Component, "ram");
+
+    // Inserted last so carts can watch addresses belonging to other components
+    let (machine, _) = machine.insert_component(
+        "cartridge",
+        Atari2600CartridgeConfig {
+            rom,
+            cpu_address_space,
+            cpu_frequency,
+            tia,
+            riot_ram,
+            force_cart_type: quirks.force_cart_type,
+        },
+    );
+
+    machine
+}
+
+/// Runs the game headless for a little while and checks how many scanlines
+/// it draws per frame, since PAL games draw 312 instead of 262
+fn detect_region(
+    program_specification: ProgramSpecification,
+    program_manager: Arc<ProgramManager>,
+    quirks: Atari2600Quirks,
+) -> RegionSelection {
+    let machine = construct_for_region(
+        Machine::build_test(Some(program_specification), program_manager, None, None),
+        RegionSelection::Ntsc,
+        Default::default(),
+        quirks,
+    )
+    .build(());
+
+    let tia = FluxEmuPath::from_str(":component/tia").unwrap();
+    let frame_length = Ntsc::frequency().recip()
+        * u128::from(Ntsc::TOTAL_SCANLINES)
+        * u128::from(tia::SCANLINE_LENGTH);
+
+    let mut frame_scanlines = Vec::default();
+
+    for _ in 0..REGION_DETECTION_FRAMES {
+        machine.run(frame_length);
+
+        if let Some(scanlines) = machine
+            .interact::<Tia<Ntsc, Software>, _>(&tia, Tia::last_frame_scanlines)
+            .unwrap()
+        {
+            frame_scanlines.push(scanlines);
+        }
+    }
+
+    // Games tend to do odd things while they are starting up, so go with what
+    // most frames looked like
+    frame_scanlines.sort_unstable();
+
+    match frame_scanlines.get(frame_scanlines.len() / 2) {
+        Some(scanlines) if *scanlines > PAL_SCANLINE_THRESHOLD => RegionSelection::Pal,
+        _ => RegionSelection::Ntsc,
+    }
+}
+
+/// Paths and values the region independent parts of the machine need
+struct Common {
+    tia: FluxEmuPath,
+    mos6532_riot: FluxEmuPath,
+    cpu_frequency: Frequency,
+}
+
 fn common<R: Region, P: Platform<GraphicsApi: SupportedGraphicsApiTia>>(
     cpu_address_space: AddressSpaceId,
     machine: MachineBuilder<P>,
-) -> (MachineBuilder<P>, FluxEmuPath) {
+    controllers: [ControllerType; 2],
+) -> (MachineBuilder<P>, Common) {
+    let cpu_frequency = R::frequency() / 3;
+
     let (machine, cpu) = machine.insert_component(
         "mos_6502",
         Mos6502Config {
-            frequency: R::frequency() / 3,
+            frequency: cpu_frequency,
             kind: Mos6502Kind::Mos6507,
             assigned_address_space: cpu_address_space,
             broken_ror: false,
+            cycle_accurate_bus: false,
         },
     );
 
     let (machine, mos6532_riot) = machine.insert_component(
         "mos6532_riot",
         Mos6532RiotConfig {
-            frequency: R::frequency() / 3,
+            frequency: cpu_frequency,
             registers_assigned_address: 0x280,
             ram_assigned_address: 0x80,
             assigned_address_space: cpu_address_space,
         },
     );
 
-    let (machine, _) = machine.insert_component(
+    let (machine, tia) = machine.insert_component(
         "tia",
         TiaConfig::<R> {
             cpu,
@@ -115,7 +268,25 @@ fn common<R: Region, P: Platform<GraphicsApi: SupportedGraphicsApiTia>>(
         },
     );
 
-    (machine, mos6532_riot)
+    // Controllers plug into both the RIOT and the TIA
+    let (machine, _) = machine.insert_component(
+        "controllers",
+        Atari2600ControllersConfig::<R> {
+            mos6532_riot: mos6532_riot.clone(),
+            tia: tia.clone(),
+            ports: controllers,
+            _phantom: PhantomData,
+        },
+    );
+
+    (
+        machine,
+        Common {
+            tia,
+            mos6532_riot,
+            cpu_frequency,
+        },
+    )
 }
 
 // These three functions hardcode mirror addresses instead of trying to mechanically replicate partial address decoding
@@ -0,0 +1,131 @@
+use serde::{Deserialize, Serialize};
+
+/// Colour clocks per audio clock, giving two audio clocks per scanline
+pub(crate) const AUDIO_CLOCK_DIVIDER: u16 = 114;
+/// Highest value AUDV can hold
+const MAX_VOLUME: u8 = 0b1111;
+
+const POLY4: [bool; 15] = lfsr_sequence::<15>(4, 3);
+const POLY5: [bool; 31] = lfsr_sequence::<31>(5, 3);
+const POLY9: [bool; 511] = lfsr_sequence::<511>(9, 5);
+
+/// Generates the output of a maximal length linear feedback shift register
+/// with taps at `length` and `tap`
+const fn lfsr_sequence<const SIZE: usize>(length: u32, tap: u32) -> [bool; SIZE] {
+    let mut sequence = [false; SIZE];
+    let mut state: u16 = (1 << length) - 1;
+    let mut index = 0;
+
+    while index < SIZE {
+        sequence[index] = state & 1 != 0;
+
+        let feedback = (state ^ (state >> (length - tap))) & 1;
+        state = (state >> 1) | (feedback << (length - 1));
+
+        index += 1;
+    }
+
+    sequence
+}
+
+/// The "div 31" pattern, which is the poly5 position being at one of two
+/// points in its cycle
+fn div31(position: usize) -> bool {
+    position == 0 || position == 18
+}
+
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize)]
+pub(crate) struct AudioChannel {
+    /// AUDC, selects the waveform
+    control: u8,
+    /// AUDF, divides the audio clock by this plus one
+    frequency: u8,
+    /// AUDV
+    volume: u8,
+    divider_counter: u16,
+    poly4_position: usize,
+    poly5_position: usize,
+    poly9_position: usize,
+    output: bool,
+}
+
+impl AudioChannel {
+    pub(crate) fn set_control(&mut self, data: u8) {
+        self.control = data & 0b1111;
+    }
+
+    pub(crate) fn set_frequency(&mut self, data: u8) {
+        self.frequency = data & 0b1_1111;
+    }
+
+    pub(crate) fn set_volume(&mut self, data: u8) {
+        self.volume = data & MAX_VOLUME;
+    }
+
+    /// How many audio clocks pass before the waveform generator is stepped
+    fn divider_period(&self) -> u16 {
+        let period = u16::from(self.frequency) + 1;
+
+        // The last four waveforms are additionally divided by 3
+        if self.control & 0b1100 == 0b1100 {
+            period * 3
+        } else {
+            period
+        }
+    }
+
+    /// Steps the channel by one audio clock
+    pub(crate) fn clock(&mut self) {
+        // These just hold the output high
+        if matches!(self.control, 0b0000 | 0b1011) {
+            self.output = true;
+            return;
+        }
+
+        self.divider_counter += 1;
+
+        if self.divider_counter < self.divider_period() {
+            return;
+        }
+
+        self.divider_counter = 0;
+        self.poly5_position = (self.poly5_position + 1) % POLY5.len();
+
+        // Some waveforms only advance depending on the state of the poly5 or div31
+        let advance = if self.control & 0b0010 == 0 {
+            true
+        } else if self.control & 0b0001 == 0 {
+            div31(self.poly5_position)
+        } else {
+            POLY5[self.poly5_position]
+        };
+
+        if !advance {
+            return;
+        }
+
+        if self.control & 0b0100 != 0 {
+            // Pure tones
+            self.output = !self.output;
+        } else if self.control & 0b1000 != 0 {
+            if self.control == 0b1000 {
+                self.poly9_position = (self.poly9_position + 1) % POLY9.len();
+                self.output = POLY9[self.poly9_position];
+            } else {
+                self.output = POLY5[self.poly5_position];
+            }
+        } else {
+            self.poly4_position = (self.poly4_position + 1) % POLY4.len();
+            self.output = POLY4[self.poly4_position];
+        }
+    }
+
+    /// Current output as a fraction of the loudest this channel could be
+    pub(crate) fn sample(&self) -> f32 {
+        if self.output {
+            f32::from(self.volume) / f32::from(MAX_VOLUME)
+        } else {
+            0.0
+        }
+    }
+}
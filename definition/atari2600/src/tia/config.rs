@@ -10,11 +10,13 @@ use fluxemu_runtime::{
     scheduler::Period,
 };
 use nalgebra::Point2;
+use ringbuffer::AllocRingBuffer;
 use strum::IntoEnumIterator;
 
 use super::{Tia, region::Region};
 use crate::tia::{
     InputControl,
+    audio::AUDIO_CLOCK_DIVIDER,
     backend::{SupportedGraphicsApiTia, TiaDisplayBackend},
     memory::{ReadRegisters, WriteRegisters},
 };
@@ -43,10 +45,12 @@ impl<R: Region, P: Platform<GraphicsApi: SupportedGraphicsApiTia>> ComponentConf
         self,
         component_builder: ComponentBuilder<'_, P, Self::Component>,
     ) -> Result<Self::Component, Box<dyn std::error::Error>> {
-        let (mut component_builder, _) = component_builder
+        let (component_builder, _) = component_builder
             .set_scheduler_participation(SchedulerParticipation::OnDemand)
             .insert_display("tv");
 
+        let (mut component_builder, _) = component_builder.insert_audio_channel("mono");
+
         for register in ReadRegisters::iter() {
             component_builder = component_builder.memory_map_component_read(
                 self.cpu_address_space,
@@ -61,6 +65,10 @@ impl<R: Region, P: Platform<GraphicsApi: SupportedGraphicsApiTia>> ComponentConf
             );
         }
 
+        // Lets the CPU go again once WSYNC has been waited out
+        let (component_builder, wsync_end) = component_builder
+            .insert_event("wsync_end", |component, _| component.cpu_rdy.store(true));
+
         let cpu_rdy = component_builder
             .interact::<Mos6502, _>(&self.cpu, |cpu| cpu.rdy())
             .unwrap();
@@ -71,7 +79,13 @@ impl<R: Region, P: Platform<GraphicsApi: SupportedGraphicsApiTia>> ComponentConf
             collision_matrix: HashMap::default(),
             vblank_active: false,
             cycles_waiting_for_vsync: None,
+            vsync_active: false,
+            scanlines_since_vsync: 0,
+            last_frame_scanlines: None,
             input_control: [InputControl::default(); 6],
+            inpt_callbacks: Default::default(),
+            pot_charge_start: Period::default(),
+            fire_latches: [true; 2],
             electron_beam: Point2::default(),
             missiles: Default::default(),
             ball: Default::default(),
@@ -79,8 +93,12 @@ impl<R: Region, P: Platform<GraphicsApi: SupportedGraphicsApiTia>> ComponentConf
             playfield: Default::default(),
             high_playfield_ball_priority: false,
             background_color: Default::default(),
+            audio_channels: Default::default(),
+            audio_clock_countdown: AUDIO_CLOCK_DIVIDER,
+            // Roughly two NTSC frames of samples at ~31.4 kHz
+            audio_buffer: AllocRingBuffer::new(1024),
             machine: Weak::new(),
-            my_path: component_builder.path().clone(),
+            wsync_end,
             timestamp: Period::default(),
         })
     }
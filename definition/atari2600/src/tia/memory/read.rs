@@ -65,12 +65,21 @@ impl<R: Region, G: SupportedGraphicsApiTia> Tia<R, G> {
                     [ObjectId::Missile0, ObjectId::Missile1],
                 );
             }
-            ReadRegisters::Inpt0 => {}
-            ReadRegisters::Inpt1 => {}
-            ReadRegisters::Inpt2 => {}
-            ReadRegisters::Inpt3 => {}
-            ReadRegisters::Inpt4 => {}
-            ReadRegisters::Inpt5 => {}
+            ReadRegisters::Inpt0
+            | ReadRegisters::Inpt1
+            | ReadRegisters::Inpt2
+            | ReadRegisters::Inpt3 => {
+                let index = address as usize - ReadRegisters::Inpt0 as usize;
+                let data_bits = data.view_bits_mut::<Lsb0>();
+
+                data_bits.set(7, self.pot_charged(index));
+            }
+            ReadRegisters::Inpt4 | ReadRegisters::Inpt5 => {
+                let port = address as usize - ReadRegisters::Inpt4 as usize;
+                let data_bits = data.view_bits_mut::<Lsb0>();
+
+                data_bits.set(7, self.fire_level(port));
+            }
         }
     }
 
@@ -20,6 +20,13 @@ impl<R: Region, G: SupportedGraphicsApiTia> Tia<R, G> {
     ) {
         match address {
             WriteRegisters::Vsync => {
+                if data_bits[1] && !self.vsync_active {
+                    self.last_frame_scanlines = Some(self.scanlines_since_vsync);
+                    self.scanlines_since_vsync = 0;
+                }
+
+                self.vsync_active = data_bits[1];
+
                 if data_bits[1] {
                     self.electron_beam = Point2::new(0, 0);
                     self.cycles_waiting_for_vsync = Some(SCANLINE_LENGTH * 3);
@@ -36,60 +43,47 @@ impl<R: Region, G: SupportedGraphicsApiTia> Tia<R, G> {
             WriteRegisters::Vblank => {
                 self.vblank_active = data_bits[1];
 
-                self.input_control[0] = if data_bits[7] {
+                let pot_control = if data_bits[7] {
                     InputControl::LatchedOrDumped
                 } else {
                     InputControl::Normal
                 };
 
-                self.input_control[1] = if data_bits[7] {
-                    InputControl::LatchedOrDumped
-                } else {
-                    InputControl::Normal
-                };
-
-                self.input_control[2] = if data_bits[7] {
-                    InputControl::LatchedOrDumped
-                } else {
-                    InputControl::Normal
-                };
+                // The capacitors start charging once they stop being dumped
+                if pot_control == InputControl::Normal
+                    && self.input_control[0] == InputControl::LatchedOrDumped
+                {
+                    self.pot_charge_start = self.timestamp;
+                }
 
-                self.input_control[3] = if data_bits[7] {
-                    InputControl::LatchedOrDumped
-                } else {
-                    InputControl::Normal
-                };
+                self.input_control[..4].fill(pot_control);
 
-                self.input_control[4] = if data_bits[7] {
+                let fire_control = if data_bits[6] {
                     InputControl::LatchedOrDumped
                 } else {
                     InputControl::Normal
                 };
 
-                self.input_control[4] = if data_bits[6] {
-                    InputControl::LatchedOrDumped
-                } else {
-                    InputControl::Normal
-                };
+                // Enabling the latches resets them
+                if fire_control == InputControl::LatchedOrDumped
+                    && self.input_control[4] == InputControl::Normal
+                {
+                    self.fire_latches = [true; 2];
+                }
 
-                self.input_control[5] = if data_bits[6] {
-                    InputControl::LatchedOrDumped
-                } else {
-                    InputControl::Normal
-                };
+                self.input_control[4..].fill(fire_control);
             }
             WriteRegisters::Wsync => {
+                // Halt the CPU until the beam reaches the start of the next scanline
                 let until =
-                    Period::from_num(SCANLINE_LENGTH - self.electron_beam.x) / (R::frequency() / 3);
+                    Period::from_num(SCANLINE_LENGTH - self.electron_beam.x) / R::frequency();
 
-                // The TIA runs 3 times as fast as the cpu
                 self.cpu_rdy.store(false);
 
-                self.machine.upgrade().unwrap().schedule_event::<Self>(
-                    self.timestamp + until,
-                    &self.my_path,
-                    |component, _| component.cpu_rdy.store(true),
-                );
+                self.machine
+                    .upgrade()
+                    .unwrap()
+                    .schedule_named_event(self.timestamp + until, &self.wsync_end);
             }
             WriteRegisters::Rsync => {
                 self.electron_beam.x = 0;
@@ -155,12 +149,24 @@ impl<R: Region, G: SupportedGraphicsApiTia> Tia<R, G> {
             WriteRegisters::Resbl => {
                 self.ball.position = self.electron_beam.x;
             }
-            WriteRegisters::Audc0 => {}
-            WriteRegisters::Audc1 => {}
-            WriteRegisters::Audf0 => {}
-            WriteRegisters::Audf1 => {}
-            WriteRegisters::Audv0 => {}
-            WriteRegisters::Audv1 => {}
+            WriteRegisters::Audc0 => {
+                self.audio_channels[0].set_control(data);
+            }
+            WriteRegisters::Audc1 => {
+                self.audio_channels[1].set_control(data);
+            }
+            WriteRegisters::Audf0 => {
+                self.audio_channels[0].set_frequency(data);
+            }
+            WriteRegisters::Audf1 => {
+                self.audio_channels[1].set_frequency(data);
+            }
+            WriteRegisters::Audv0 => {
+                self.audio_channels[0].set_volume(data);
+            }
+            WriteRegisters::Audv1 => {
+                self.audio_channels[1].set_volume(data);
+            }
             WriteRegisters::Grp0 => {
                 if matches!(
                     self.players[0].delay_change_graphic,
@@ -317,3 +323,77 @@ fn extract_color(data_bits: &BitSlice<u8>) -> TiaColor {
 
     TiaColor { luminance, hue }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{borrow::Cow, str::FromStr};
+
+    use fluxemu_definition_misc::memory::standard::{
+        StandardMemoryConfig, StandardMemoryInitialContents,
+    };
+    use fluxemu_runtime::{graphics::software::Software, machine::Machine, path::FluxEmuPath};
+    use rangemap::RangeInclusiveMap;
+
+    use crate::{
+        common,
+        tia::{
+            SCANLINE_LENGTH, Tia,
+            region::{Region, ntsc::Ntsc},
+        },
+    };
+
+    #[test]
+    fn wsync_resumes_cpu_at_next_scanline() {
+        let (machine, cpu_address_space) = Machine::build_test_minimal().insert_address_space(13);
+        let (machine, _) = common::<Ntsc, _>(cpu_address_space, machine, Default::default());
+
+        let mut rom = vec![0xea; 0x1000];
+        rom[..9].copy_from_slice(&[
+            0xa9, 0x01, // LDA #$01
+            0x85, 0x02, // STA WSYNC
+            0x85, 0x80, // STA $80
+            0x4c, 0x06, 0x10, // JMP $1006
+        ]);
+        rom[0xffc..0xffe].copy_from_slice(&[0x00, 0x10]);
+
+        let (machine, _) = machine.insert_component(
+            "cartridge",
+            StandardMemoryConfig {
+                readable: true,
+                writable: false,
+                assigned_range: 0x1000..=0x1fff,
+                assigned_address_space: cpu_address_space,
+                initial_contents: RangeInclusiveMap::from_iter([(
+                    0x1000..=0x1fff,
+                    StandardMemoryInitialContents::Array(Cow::Owned(rom)),
+                )]),
+                sram: false,
+            },
+        );
+        let machine = machine.build(());
+        let address_space = machine.address_spaces(cpu_address_space).unwrap();
+        let tia = FluxEmuPath::from_str(":component/tia").unwrap();
+
+        for _ in 0..SCANLINE_LENGTH * 4 {
+            machine.run(Ntsc::frequency().recip());
+
+            let mut marker = [0];
+            address_space
+                .read(0x80, machine.now(), None, &mut marker)
+                .unwrap();
+
+            if marker[0] == 0x01 {
+                let beam = machine
+                    .interact::<Tia<Ntsc, Software>, _>(&tia, |tia| tia.electron_beam)
+                    .unwrap();
+
+                // The store after the WSYNC takes 3 CPU cycles once released
+                assert_eq!(beam.y, 1);
+                assert!(beam.x <= 3 * 3, "CPU resumed at color clock {}", beam.x);
+                return;
+            }
+        }
+
+        panic!("CPU never resumed after WSYNC");
+    }
+}
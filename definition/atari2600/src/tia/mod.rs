@@ -5,6 +5,7 @@ use std::{
     sync::{Arc, Weak},
 };
 
+use audio::{AUDIO_CLOCK_DIVIDER, AudioChannel};
 pub(crate) use backend::SupportedGraphicsApiTia;
 use bitvec::{
     array::BitArray,
@@ -12,16 +13,18 @@ use bitvec::{
     view::BitView,
 };
 use color::TiaColor;
+use fluxemu_audio::FrameIterator;
 use fluxemu_definition_mos6502::RdyFlag;
 use fluxemu_runtime::{
-    component::Component,
+    component::{Component, SampleSource},
     machine::Machine,
     memory::{Address, AddressSpaceId, MemoryError},
     path::FluxEmuPath,
     scheduler::{Period, SynchronizationContext},
 };
-use nalgebra::Point2;
+use nalgebra::{Point2, SVector};
 use region::Region;
+use ringbuffer::{AllocRingBuffer, RingBuffer};
 use serde::{Deserialize, Serialize};
 
 use crate::tia::{
@@ -29,6 +32,7 @@ use crate::tia::{
     memory::{ReadRegisters, WriteRegisters},
 };
 
+mod audio;
 mod backend;
 mod color;
 pub(crate) mod config;
@@ -37,7 +41,36 @@ pub mod region;
 
 const HBLANK_LENGTH: u16 = 68;
 const VISIBLE_SCANLINE_LENGTH: u16 = 160;
-const SCANLINE_LENGTH: u16 = HBLANK_LENGTH + VISIBLE_SCANLINE_LENGTH;
+pub(crate) const SCANLINE_LENGTH: u16 = HBLANK_LENGTH + VISIBLE_SCANLINE_LENGTH;
+/// A fully turned paddle's 1MΩ against the 68nF capacitor takes about this
+/// many scanlines to charge past the input threshold
+const POT_MAX_CHARGE_SCANLINES: u16 = 380;
+
+/// If the TIA has a register at this address that can be written to
+pub(crate) fn is_write_register(address: Address) -> bool {
+    WriteRegisters::from_repr(address as u16).is_some()
+}
+
+/// Whatever is plugged into a controller port, as seen by the TIA input pins
+pub(crate) trait InptCallback: Debug + Send + Sync + 'static {
+    /// How long the capacitor on pot `pin` takes to charge past the input
+    /// threshold, as a fraction of the longest a paddle can take, or [`None`] if
+    /// the line is held low
+    fn pot_charge(&self, pin: usize) -> Option<f32>;
+
+    /// Level of the fire line, low while pressed
+    fn fire(&self) -> bool;
+}
+
+impl<T: InptCallback + ?Sized> InptCallback for Arc<T> {
+    fn pot_charge(&self, pin: usize) -> Option<f32> {
+        T::pot_charge(self, pin)
+    }
+
+    fn fire(&self) -> bool {
+        T::fire(self)
+    }
+}
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Serialize, Deserialize)]
 enum ObjectId {
@@ -114,7 +147,17 @@ pub(crate) struct Tia<R: Region, G: SupportedGraphicsApiTia> {
     collision_matrix: HashMap<ObjectId, HashSet<ObjectId>>,
     vblank_active: bool,
     cycles_waiting_for_vsync: Option<u16>,
+    vsync_active: bool,
+    /// Counted separately from the beam so frames longer than the region expects are measured
+    scanlines_since_vsync: u16,
+    last_frame_scanlines: Option<u16>,
     input_control: [InputControl; 6],
+    /// Indexed by controller port, which own INPT0-1 + INPT4 and INPT2-3 + INPT5 respectively
+    inpt_callbacks: [Option<Box<dyn InptCallback>>; 2],
+    /// When the pot capacitors were last released from being dumped
+    pot_charge_start: Period,
+    /// INPT4 and INPT5 as held by their latches, which only ever go low
+    fire_latches: [bool; 2],
     electron_beam: Point2<u16>,
     missiles: [Missile; 2],
     ball: Ball,
@@ -122,11 +165,16 @@ pub(crate) struct Tia<R: Region, G: SupportedGraphicsApiTia> {
     playfield: Playfield,
     high_playfield_ball_priority: bool,
     background_color: TiaColor,
+    audio_channels: [AudioChannel; 2],
+    /// Colour clocks until the audio channels are next clocked
+    audio_clock_countdown: u16,
+    audio_buffer: AllocRingBuffer<SVector<f32, 1>>,
     backend: Option<G::Backend<R>>,
     cpu_rdy: Arc<RdyFlag>,
     machine: Weak<Machine>,
     timestamp: Period,
-    my_path: FluxEmuPath,
+    /// Event releasing the CPU after a WSYNC
+    wsync_end: FluxEmuPath,
 }
 
 impl<R: Region, G: SupportedGraphicsApiTia> Component for Tia<R, G> {
@@ -174,10 +222,26 @@ impl<R: Region, G: SupportedGraphicsApiTia> Component for Tia<R, G> {
         self.backend.as_mut().unwrap().access_framebuffer()
     }
 
+    fn get_audio_channel(&mut self, _audio_output_path: &FluxEmuPath) -> SampleSource<'_> {
+        let sample_rate = (R::frequency() / u128::from(AUDIO_CLOCK_DIVIDER)).to_num();
+
+        SampleSource {
+            source: Box::new(self.audio_buffer.drain().repeat_last_frame()),
+            sample_rate,
+        }
+    }
+
     fn synchronize(&mut self, mut context: SynchronizationContext) {
         for now in context.allocate(R::frequency().recip(), None) {
             self.timestamp = now;
 
+            self.audio_clock_countdown = self.audio_clock_countdown.saturating_sub(1);
+
+            if self.audio_clock_countdown == 0 {
+                self.audio_clock_countdown = AUDIO_CLOCK_DIVIDER;
+                self.clock_audio();
+            }
+
             if let Some(cycles) = self.cycles_waiting_for_vsync {
                 self.cycles_waiting_for_vsync = Some(cycles.saturating_sub(1));
 
@@ -210,6 +274,8 @@ impl<R: Region, G: SupportedGraphicsApiTia> Component for Tia<R, G> {
             if self.electron_beam.x >= SCANLINE_LENGTH {
                 self.electron_beam.x = 0;
                 self.electron_beam.y += 1;
+                self.scanlines_since_vsync = self.scanlines_since_vsync.saturating_add(1);
+                self.update_fire_latches();
             }
 
             if self.electron_beam.y >= R::TOTAL_SCANLINES {
@@ -224,6 +290,81 @@ impl<R: Region, G: SupportedGraphicsApiTia> Component for Tia<R, G> {
 }
 
 impl<R: Region, G: SupportedGraphicsApiTia> Tia<R, G> {
+    /// How many scanlines were between the last two vertical syncs
+    pub(crate) fn last_frame_scanlines(&self) -> Option<u16> {
+        self.last_frame_scanlines
+    }
+
+    pub(crate) fn install_inpt(&mut self, port: usize, callback: impl InptCallback) {
+        self.inpt_callbacks[port] = Some(Box::new(callback));
+    }
+
+    /// If pot `index` has charged enough since its capacitor was released to read high
+    fn pot_charged(&self, index: usize) -> bool {
+        if self.input_control[index] == InputControl::LatchedOrDumped {
+            return false;
+        }
+
+        // Nothing to charge the capacitor with
+        let Some(callback) = &self.inpt_callbacks[index / 2] else {
+            return false;
+        };
+
+        callback.pot_charge(index % 2).is_some_and(|charge| {
+            let charge_time = R::frequency().recip()
+                * u128::from(POT_MAX_CHARGE_SCANLINES)
+                * u128::from(SCANLINE_LENGTH)
+                * Period::from_num(charge.clamp(0.0, 1.0));
+
+            self.timestamp.saturating_sub(self.pot_charge_start) >= charge_time
+        })
+    }
+
+    /// Level of the fire line of `port`, with nothing plugged in reading high
+    fn fire_line(&self, port: usize) -> bool {
+        self.inpt_callbacks[port]
+            .as_ref()
+            .is_none_or(|callback| callback.fire())
+    }
+
+    fn fire_level(&self, port: usize) -> bool {
+        let level = self.fire_line(port);
+
+        if self.input_control[4 + port] == InputControl::LatchedOrDumped {
+            level && self.fire_latches[port]
+        } else {
+            level
+        }
+    }
+
+    /// Samples the fire lines for the latches, which is done per scanline
+    /// since nobody can press a button faster than that
+    fn update_fire_latches(&mut self) {
+        for port in 0..self.fire_latches.len() {
+            if self.input_control[4 + port] == InputControl::LatchedOrDumped
+                && !self.fire_line(port)
+            {
+                self.fire_latches[port] = false;
+            }
+        }
+    }
+
+    fn clock_audio(&mut self) {
+        for channel in &mut self.audio_channels {
+            channel.clock();
+        }
+
+        // Both channels are mixed together onto the single output pin
+        let sample = self
+            .audio_channels
+            .iter()
+            .map(AudioChannel::sample)
+            .sum::<f32>()
+            / self.audio_channels.len() as f32;
+
+        self.audio_buffer.enqueue(SVector::from([sample]));
+    }
+
     fn get_rendered_color(&self) -> TiaColor {
         if self.high_playfield_ball_priority {
             // Check if in the bounds of ball
@@ -16,3 +16,7 @@ pub trait Region: Send + Sync + Debug + 'static {
 
     fn color_to_srgb(color: TiaColor) -> Srgb<u8>;
 }
+
+const fn srgb_from_hex(color: u32) -> Srgb<u8> {
+    Srgb::new((color >> 16) as u8, (color >> 8) as u8, color as u8)
+}
@@ -1,8 +1,34 @@
 use fluxemu_runtime::scheduler::Frequency;
+use palette::Srgb;
 
-use super::Region;
+use super::{Region, srgb_from_hex};
 use crate::tia::color::TiaColor;
 
+const GREYS: [u32; 8] = [
+    0x000000, 0x2b2b2b, 0x525252, 0x767676, 0x979797, 0xb6b6b6, 0xd2d2d2, 0xececec,
+];
+
+/// Indexed by hue then luminance
+#[rustfmt::skip]
+const COLOR_PALETTE: [[u32; 8]; 16] = [
+    GREYS,
+    GREYS,
+    [0x805800, 0x96711a, 0xab8732, 0xbe9c48, 0xcfaf5c, 0xdfc06f, 0xeed180, 0xfce090],
+    [0x445c00, 0x5e791a, 0x769332, 0x8cac48, 0xa0c25c, 0xb3d76f, 0xc4ea80, 0xd4fc90],
+    [0x703400, 0x89511a, 0xa06b32, 0xb68448, 0xc99a5c, 0xdcaf6f, 0xecc280, 0xfcd490],
+    [0x006414, 0x1a8035, 0x329852, 0x48b06e, 0x5cc587, 0x6fd99e, 0x80ebb4, 0x90fcc8],
+    [0x700014, 0x891a35, 0xa03252, 0xb6486e, 0xc95c87, 0xdc6f9e, 0xec80b4, 0xfc90c8],
+    [0x005c5c, 0x1a7676, 0x328e8e, 0x48a4a4, 0x5cb8b8, 0x6fcbcb, 0x80dcdc, 0x90ecec],
+    [0x70005c, 0x841a74, 0x963289, 0xa8489e, 0xb75cb0, 0xc66fc1, 0xd380d1, 0xe090e0],
+    [0x003c70, 0x195a89, 0x2f75a0, 0x448eb6, 0x57a5c9, 0x68badc, 0x79ceec, 0x88e0fc],
+    [0x580070, 0x6e1a89, 0x8332a0, 0x9648b6, 0xa75cc9, 0xb76fdc, 0xc680ec, 0xd490fc],
+    [0x002070, 0x193f89, 0x2f5aa0, 0x4474b6, 0x578bc9, 0x68a1dc, 0x79b5ec, 0x88c8fc],
+    [0x340080, 0x4a1a96, 0x5f32ab, 0x7248be, 0x835ccf, 0x936fdf, 0xa280ee, 0xb090fc],
+    [0x000088, 0x1a1a9d, 0x3232b0, 0x4848c2, 0x5c5cd2, 0x6f6fe1, 0x8080ef, 0x9090fc],
+    GREYS,
+    GREYS,
+];
+
 #[derive(Debug)]
 pub struct Pal;
 
@@ -10,10 +36,11 @@ impl Region for Pal {
     const TOTAL_SCANLINES: u16 = 312;
 
     fn frequency() -> Frequency {
-        Frequency::from_num(17734475) / 4
+        // 4/5 of the PAL colour subcarrier
+        Frequency::from_num(17734475) / 5
     }
 
-    fn color_to_srgb(color: TiaColor) -> palette::Srgb<u8> {
-        todo!()
+    fn color_to_srgb(color: TiaColor) -> Srgb<u8> {
+        srgb_from_hex(COLOR_PALETTE[color.hue as usize][color.luminance as usize])
     }
 }
@@ -1,8 +1,14 @@
 use fluxemu_runtime::scheduler::Frequency;
+use palette::Srgb;
 
-use super::Region;
+use super::{Region, srgb_from_hex};
 use crate::tia::color::TiaColor;
 
+/// SECAM consoles ignore the hue entirely and can only show 8 colours
+const COLOR_PALETTE: [u32; 8] = [
+    0x000000, 0x2121ff, 0xf03c79, 0xff50ff, 0x7fff00, 0x7fffff, 0xffff3f, 0xffffff,
+];
+
 #[derive(Debug)]
 pub struct Secam;
 
@@ -10,10 +16,10 @@ impl Region for Secam {
     const TOTAL_SCANLINES: u16 = 312;
 
     fn frequency() -> Frequency {
-        todo!()
+        Frequency::from_num(3562500)
     }
 
-    fn color_to_srgb(color: TiaColor) -> palette::Srgb<u8> {
-        todo!()
+    fn color_to_srgb(color: TiaColor) -> Srgb<u8> {
+        srgb_from_hex(COLOR_PALETTE[color.luminance as usize])
     }
 }
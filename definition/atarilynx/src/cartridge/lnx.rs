@@ -0,0 +1,87 @@
+use fluxemu_runtime::machine::graphics::DisplayRotation;
+use thiserror::Error;
+
+pub const HEADER_SIZE: usize = 64;
+const MAGIC: &[u8; 4] = b"LYNX";
+
+#[derive(Error, Debug)]
+pub enum ParsingError {
+    #[error("Bad magic {bytes:?}")]
+    BadMagic { bytes: [u8; 4] },
+    #[error("Unsupported page size {size} for bank {bank}")]
+    UnsupportedPageSize { bank: usize, size: u16 },
+    #[error("Unknown rotation {rotation}")]
+    UnknownRotation { rotation: u8 },
+}
+
+/// The header Handy prepends to cartridge dumps
+///
+/// ```text
+/// 0x00 "LYNX"
+/// 0x04 bank 0 page size, little endian
+/// 0x06 bank 1 page size, little endian
+/// 0x08 version, little endian
+/// 0x0a cartridge name, null terminated
+/// 0x2a manufacturer name, null terminated
+/// 0x3a rotation
+/// 0x3b spare
+/// ```
+#[derive(Clone, Debug)]
+pub struct Lnx {
+    /// Bytes per page of each bank, where 0 means the bank is not populated
+    pub page_sizes: [u16; 2],
+    pub version: u16,
+    pub name: String,
+    pub manufacturer: String,
+    pub rotation: DisplayRotation,
+}
+
+impl Lnx {
+    pub fn parse(bytes: &[u8; HEADER_SIZE]) -> Result<Self, ParsingError> {
+        if &bytes[0..4] != MAGIC {
+            return Err(ParsingError::BadMagic {
+                bytes: bytes[0..4].try_into().unwrap(),
+            });
+        }
+
+        let page_sizes =
+            [0, 1].map(|bank| u16::from_le_bytes([bytes[4 + bank * 2], bytes[5 + bank * 2]]));
+
+        for (bank, size) in page_sizes.into_iter().enumerate() {
+            if size != 0 && !size.is_power_of_two() {
+                return Err(ParsingError::UnsupportedPageSize { bank, size });
+            }
+        }
+
+        let version = u16::from_le_bytes([bytes[8], bytes[9]]);
+
+        let rotation = match bytes[0x3a] {
+            0 => DisplayRotation::None,
+            1 => DisplayRotation::Left,
+            2 => DisplayRotation::Right,
+            rotation => return Err(ParsingError::UnknownRotation { rotation }),
+        };
+
+        Ok(Self {
+            page_sizes,
+            version,
+            name: parse_string(&bytes[0x0a..0x2a]),
+            manufacturer: parse_string(&bytes[0x2a..0x3a]),
+            rotation,
+        })
+    }
+
+    /// Checks for the magic without parsing anything else
+    pub fn is_present(bytes: &[u8]) -> bool {
+        bytes.len() >= HEADER_SIZE && bytes.starts_with(MAGIC)
+    }
+}
+
+fn parse_string(bytes: &[u8]) -> String {
+    let end = bytes
+        .iter()
+        .position(|byte| *byte == 0)
+        .unwrap_or(bytes.len());
+
+    String::from_utf8_lossy(&bytes[..end]).trim().to_string()
+}
@@ -0,0 +1,250 @@
+use bytes::Bytes;
+use fluxemu_runtime::{
+    component::{Component, ComponentConfig},
+    machine::{builder::ComponentBuilder, graphics::DisplayRotation},
+    platform::Platform,
+    program::{RomId, RomRequirement},
+};
+use lnx::{HEADER_SIZE, Lnx};
+
+mod lnx;
+
+/// Each bank is addressed as 256 pages, selected by the shift register
+const PAGE_COUNT: usize = 256;
+/// The ripple counter addressing bytes within a page is 11 bits wide
+const COUNTER_MASK: usize = 0x7ff;
+/// What headerless dumps are assumed to use when the size does not say
+const DEFAULT_PAGE_SIZE: usize = 0x400;
+
+#[derive(Debug, Default)]
+struct Bank {
+    rom: Bytes,
+    /// Bytes per page, where 0 means nothing is connected
+    page_size: usize,
+}
+
+impl Bank {
+    fn read(&self, page: u8, counter: usize) -> u8 {
+        if self.page_size == 0 {
+            return 0xff;
+        }
+
+        let offset = usize::from(page) * self.page_size + (counter & (self.page_size - 1));
+
+        self.rom.get(offset).copied().unwrap_or(0xff)
+    }
+}
+
+/// A game cartridge
+///
+/// Carts are not on the CPU bus at all. Software shifts a page number in
+/// through Mikey's IODAT and SYSCTL1, and then reads bytes from the page
+/// through Suzy's RCART registers, each of which advances a counter
+#[derive(Debug)]
+pub struct LynxCartridge {
+    banks: [Bank; 2],
+    rotation: DisplayRotation,
+    /// The shift register holding the selected page
+    page: u8,
+    counter: usize,
+    /// The level of the IODAT line that is shifted into the page
+    address_data: bool,
+    address_strobe: bool,
+}
+
+impl Component for LynxCartridge {}
+
+impl LynxCartridge {
+    pub(crate) fn rotation(&self) -> DisplayRotation {
+        self.rotation
+    }
+
+    /// RCART0 and RCART1
+    pub(crate) fn read(&mut self, bank: usize, avoid_side_effects: bool) -> u8 {
+        let data = self.banks[bank].read(self.page, self.counter);
+
+        if !avoid_side_effects {
+            self.advance_counter();
+        }
+
+        data
+    }
+
+    /// Nothing is writable, but the counter still advances
+    pub(crate) fn write(&mut self) {
+        self.advance_counter();
+    }
+
+    pub(crate) fn set_address_data(&mut self, level: bool) {
+        self.address_data = level;
+    }
+
+    /// The strobe holds the counter at zero, and shifts a bit into the page on
+    /// its rising edge
+    pub(crate) fn set_address_strobe(&mut self, level: bool) {
+        if level {
+            self.counter = 0;
+
+            if !self.address_strobe {
+                self.page = (self.page << 1) | u8::from(self.address_data);
+            }
+        }
+
+        self.address_strobe = level;
+    }
+
+    fn advance_counter(&mut self) {
+        if !self.address_strobe {
+            self.counter = (self.counter + 1) & COUNTER_MASK;
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct LynxCartridgeConfig {
+    pub rom: RomId,
+}
+
+impl<P: Platform> ComponentConfig<P> for LynxCartridgeConfig {
+    type Component = LynxCartridge;
+
+    fn build_component(
+        self,
+        component_builder: ComponentBuilder<'_, P, Self::Component>,
+    ) -> Result<Self::Component, Box<dyn std::error::Error>> {
+        let rom = component_builder
+            .program_manager()
+            .open(self.rom, RomRequirement::Required)
+            .unwrap();
+
+        let (rom, page_sizes, rotation) = if Lnx::is_present(&rom) {
+            let header = Lnx::parse(rom[..HEADER_SIZE].try_into().unwrap())?;
+
+            tracing::info!(
+                "Loading \"{}\" by \"{}\", LNX version {}",
+                header.name,
+                header.manufacturer,
+                header.version
+            );
+
+            (
+                rom.slice(HEADER_SIZE..),
+                header.page_sizes.map(usize::from),
+                header.rotation,
+            )
+        } else {
+            // Headerless dumps only ever use the first bank, with the page
+            // size implied by the size of the dump
+            let page_size = match rom.len() / PAGE_COUNT {
+                size @ (0x100 | 0x200 | 0x400 | 0x800) => size,
+                _ => DEFAULT_PAGE_SIZE,
+            };
+
+            (rom, [page_size, 0], DisplayRotation::None)
+        };
+
+        let bank_0_size = (page_sizes[0] * PAGE_COUNT).min(rom.len());
+        let bank_1_size = (page_sizes[1] * PAGE_COUNT).min(rom.len() - bank_0_size);
+
+        tracing::debug!("Cartridge page sizes are {:?}", page_sizes);
+
+        Ok(LynxCartridge {
+            banks: [
+                Bank {
+                    rom: rom.slice(..bank_0_size),
+                    page_size: page_sizes[0],
+                },
+                Bank {
+                    rom: rom.slice(bank_0_size..bank_0_size + bank_1_size),
+                    page_size: page_sizes[1],
+                },
+            ],
+            rotation,
+            page: 0,
+            counter: 0,
+            address_data: false,
+            address_strobe: false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cartridge(rom: Vec<u8>, page_size: usize) -> LynxCartridge {
+        LynxCartridge {
+            banks: [
+                Bank {
+                    rom: Bytes::from(rom),
+                    page_size,
+                },
+                Bank::default(),
+            ],
+            rotation: DisplayRotation::None,
+            page: 0,
+            counter: 0,
+            address_data: false,
+            address_strobe: false,
+        }
+    }
+
+    fn select_page(cartridge: &mut LynxCartridge, page: u8) {
+        for bit in (0..8).rev() {
+            cartridge.set_address_data(page & (1 << bit) != 0);
+            cartridge.set_address_strobe(true);
+            cartridge.set_address_strobe(false);
+        }
+    }
+
+    #[test]
+    fn page_addressing() {
+        let rom = (0..0x200 * PAGE_COUNT).map(|offset| (offset / 0x200) as u8 ^ offset as u8);
+        let mut cartridge = cartridge(rom.collect(), 0x200);
+
+        select_page(&mut cartridge, 0x5a);
+
+        assert_eq!(cartridge.read(0, false), 0x5a);
+        assert_eq!(cartridge.read(0, false), 0x5b);
+        assert_eq!(cartridge.read(0, true), 0x58);
+        assert_eq!(cartridge.read(0, false), 0x58);
+    }
+
+    #[test]
+    fn counter_wraps_within_page() {
+        let mut cartridge = cartridge((0..=u8::MAX).cycle().take(0x100 * 2).collect(), 0x100);
+
+        select_page(&mut cartridge, 1);
+
+        for _ in 0..0x100 {
+            cartridge.read(0, false);
+        }
+
+        assert_eq!(cartridge.read(0, false), 0x00);
+    }
+
+    #[test]
+    fn missing_bank() {
+        let mut cartridge = cartridge(vec![0; 0x100], 0x100);
+
+        assert_eq!(cartridge.read(1, false), 0xff);
+    }
+
+    #[test]
+    fn lnx_header() {
+        let mut header = [0; HEADER_SIZE];
+        header[0..4].copy_from_slice(b"LYNX");
+        header[4..6].copy_from_slice(&0x400u16.to_le_bytes());
+        header[8..10].copy_from_slice(&1u16.to_le_bytes());
+        header[0x0a..0x0f].copy_from_slice(b"Gates");
+        header[0x2a..0x2d].copy_from_slice(b"Epy");
+        header[0x3a] = 2;
+
+        let header = Lnx::parse(&header).unwrap();
+
+        assert_eq!(header.page_sizes, [0x400, 0]);
+        assert_eq!(header.name, "Gates");
+        assert_eq!(header.manufacturer, "Epy");
+        assert_eq!(header.rotation, DisplayRotation::Right);
+    }
+}
@@ -0,0 +1,49 @@
+use std::{borrow::Cow, collections::HashMap, sync::Arc};
+
+use fluxemu_runtime::input::{
+    GamepadInput, Input, VirtualGamepad, VirtualGamepadMetadata, keyboard::KeyboardInput,
+};
+
+pub(crate) const UP: GamepadInput = GamepadInput::LeftStickUp;
+pub(crate) const DOWN: GamepadInput = GamepadInput::LeftStickDown;
+pub(crate) const LEFT: GamepadInput = GamepadInput::LeftStickLeft;
+pub(crate) const RIGHT: GamepadInput = GamepadInput::LeftStickRight;
+pub(crate) const OPTION_1: GamepadInput = GamepadInput::LeftTrigger;
+pub(crate) const OPTION_2: GamepadInput = GamepadInput::RightTrigger;
+/// The B button
+pub(crate) const INSIDE: GamepadInput = GamepadInput::FPadRight;
+/// The A button
+pub(crate) const OUTSIDE: GamepadInput = GamepadInput::FPadDown;
+pub(crate) const PAUSE: GamepadInput = GamepadInput::Start;
+
+/// The buttons built into the front of the Lynx
+pub(crate) fn create_gamepad() -> Arc<VirtualGamepad> {
+    let inputs = [
+        UP, DOWN, LEFT, RIGHT, OPTION_1, OPTION_2, INSIDE, OUTSIDE, PAUSE,
+    ];
+
+    let keyboard_mappings = [
+        (KeyboardInput::ArrowUp, UP),
+        (KeyboardInput::ArrowDown, DOWN),
+        (KeyboardInput::ArrowLeft, LEFT),
+        (KeyboardInput::ArrowRight, RIGHT),
+        (KeyboardInput::Digit1, OPTION_1),
+        (KeyboardInput::Digit2, OPTION_2),
+        (KeyboardInput::KeyX, INSIDE),
+        (KeyboardInput::KeyZ, OUTSIDE),
+        (KeyboardInput::Enter, PAUSE),
+    ];
+
+    VirtualGamepad::new(Cow::Owned(VirtualGamepadMetadata {
+        present_inputs: inputs.map(Input::Gamepad).to_vec(),
+        default_real2virtual_mappings: HashMap::from_iter(
+            inputs
+                .map(|input| (Input::Gamepad(input), Input::Gamepad(input)))
+                .into_iter()
+                .chain(
+                    keyboard_mappings
+                        .map(|(key, input)| (Input::Keyboard(key), Input::Gamepad(input))),
+                ),
+        ),
+    }))
+}
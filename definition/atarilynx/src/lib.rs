@@ -1,40 +1,59 @@
 use std::{ops::RangeInclusive, str::FromStr};
 
+use cartridge::LynxCartridgeConfig;
 use fluxemu_definition_misc::memory::{
     null::NullMemoryConfig,
     standard::{StandardMemoryConfig, StandardMemoryInitialContents},
 };
+use fluxemu_definition_mos6502::{Mos6502Config, Mos6502Kind};
+use fluxemu_range::ContiguousRange;
 use fluxemu_runtime::{
     machine::{MachineFactory, builder::MachineBuilder},
     memory::Address,
     platform::Platform,
-    program::{RomId, RomRequirement},
+    program::{Filesystem, RomId, RomRequirement},
+    scheduler::Frequency,
 };
 use mapctl::MapctlConfig;
-use num::rational::Ratio;
+use mikey::{MikeyConfig, SupportedGraphicsApiMikey};
 use rangemap::RangeInclusiveMap;
 
 use crate::suzy::SuzyConfig;
 
+mod cartridge;
+mod gamepad;
 mod mapctl;
 mod mikey;
 mod suzy;
 
 const SUZY_ADDRESSES: RangeInclusive<Address> = 0xfc00..=0xfcff;
 const MIKEY_ADDRESSES: RangeInclusive<Address> = 0xfd00..=0xfdff;
-const VECTOR_ADDRESSES: RangeInclusive<Address> = 0xfff8..=0xffff;
+const ROM_ADDRESSES: RangeInclusive<Address> = 0xfe00..=0xfff7;
+const VECTOR_ADDRESSES: RangeInclusive<Address> = 0xfffa..=0xffff;
 const RESERVED_MEMORY_ADDRESS: Address = 0xfff8;
 const MAPCTL_ADDRESS: Address = 0xfff9;
 
 #[derive(Debug, Default)]
 pub struct AtariLynx;
 
-impl<P: Platform> MachineFactory<P> for AtariLynx {
-    fn construct(&self, machine: MachineBuilder<P>) -> MachineBuilder<P> {
-        // 16 Mhz
-        let base_clock = Ratio::from_integer(16000000);
+impl<P: Platform<GraphicsApi: SupportedGraphicsApiMikey>> MachineFactory<P> for AtariLynx {
+    type Quirks = ();
+
+    fn construct(&self, machine: MachineBuilder<P>, _quirks: ()) -> MachineBuilder<P> {
         let (machine, cpu_address_space) = machine.insert_address_space(16);
 
+        // The 16 MHz crystal divided down
+        let (machine, cpu) = machine.insert_component(
+            "wdc_65c02",
+            Mos6502Config {
+                frequency: Frequency::from_num(4_000_000),
+                kind: Mos6502Kind::Wdc65C02,
+                assigned_address_space: cpu_address_space,
+                broken_ror: false,
+                cycle_accurate_bus: false,
+            },
+        );
+
         // A good portion of this will be initially shadowed
         let (machine, ram) = machine.insert_component(
             "ram",
@@ -70,16 +89,59 @@ impl<P: Platform> MachineFactory<P> for AtariLynx {
             )
             .unwrap();
 
-        let (machine, bootstrap) = machine.memory_register_buffer(
+        // The vectors can be mapped out separately from the rest of the ROM
+        let (machine, rom_buffer) = machine.memory_register_buffer(
             cpu_address_space,
-            "bootstrap",
-            rom.slice(0x0000..=0x1fff),
+            "rom",
+            rom.slice(..ROM_ADDRESSES.len()),
         );
 
-        let machine =
-            machine.memory_map_buffer_read(cpu_address_space, 0xfe00..=0xffff, &bootstrap);
+        let (machine, vectors_buffer) = machine.memory_register_buffer(
+            cpu_address_space,
+            "vectors",
+            rom.slice(rom.len() - VECTOR_ADDRESSES.len()..),
+        );
+
+        let machine = machine
+            .memory_map_buffer_read(cpu_address_space, ROM_ADDRESSES, &rom_buffer)
+            .memory_map_buffer_read(cpu_address_space, VECTOR_ADDRESSES, &vectors_buffer);
+
+        // The BIOS can be started without a game, it just asks for one
+        let (machine, cartridge) = match machine.program_specification() {
+            Some(program_specification) => {
+                let Filesystem::Single { rom_id, .. } = program_specification.info.filesystem()
+                else {
+                    panic!("No atari lynx game has a structured filesystem")
+                };
+                let rom = *rom_id;
 
-        let (machine, suzy) = machine.insert_component("suzy", SuzyConfig { cpu_address_space });
+                let (machine, cartridge) =
+                    machine.insert_component("cartridge", LynxCartridgeConfig { rom });
+
+                (machine, Some(cartridge))
+            }
+            None => (machine, None),
+        };
+
+        let (machine, suzy) = machine.insert_component(
+            "suzy",
+            SuzyConfig {
+                ram: ram.clone(),
+                cartridge: cartridge.clone(),
+                cpu_address_space,
+            },
+        );
+
+        let (machine, mikey) = machine.insert_component(
+            "mikey",
+            MikeyConfig {
+                cpu,
+                ram: ram.clone(),
+                suzy: suzy.clone(),
+                cartridge,
+                cpu_address_space,
+            },
+        );
 
         let (machine, _) = machine.insert_component(
             "mapctl",
@@ -87,9 +149,10 @@ impl<P: Platform> MachineFactory<P> for AtariLynx {
                 cpu_address_space,
                 ram,
                 suzy,
+                mikey,
+                rom: rom_buffer,
+                vectors: vectors_buffer,
                 reserved,
-                mikey: todo!(),
-                vector: todo!(),
             },
         );
 
@@ -14,7 +14,13 @@ use fluxemu_runtime::{
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    MAPCTL_ADDRESS, MIKEY_ADDRESSES, RESERVED_MEMORY_ADDRESS, SUZY_ADDRESSES, VECTOR_ADDRESSES,
+    MAPCTL_ADDRESS, MIKEY_ADDRESSES, RESERVED_MEMORY_ADDRESS, ROM_ADDRESSES, SUZY_ADDRESSES,
+    VECTOR_ADDRESSES,
+};
+
+const ROM_PERMISSIONS: Permissions = Permissions {
+    read: true,
+    write: false,
 };
 
 #[derive(Debug)]
@@ -56,7 +62,7 @@ impl Component for Mapctl {
             permissions: Permissions::all(),
         });
 
-        if self.status.suzy {
+        if !self.status.suzy_disable {
             remapping_commands.push(MemoryRemappingCommand::Map {
                 range: SUZY_ADDRESSES,
                 target: MapTarget::Component(self.config.suzy.clone()),
@@ -64,7 +70,7 @@ impl Component for Mapctl {
             });
         }
 
-        if self.status.mikey {
+        if !self.status.mikey_disable {
             remapping_commands.push(MemoryRemappingCommand::Map {
                 range: MIKEY_ADDRESSES,
                 target: MapTarget::Component(self.config.mikey.clone()),
@@ -72,17 +78,26 @@ impl Component for Mapctl {
             });
         }
 
+        // Writes to the ROM still go to the RAM underneath
+        if !self.status.rom_disable {
+            remapping_commands.push(MemoryRemappingCommand::Map {
+                range: ROM_ADDRESSES,
+                target: MapTarget::Memory(self.config.rom.clone()),
+                permissions: ROM_PERMISSIONS,
+            });
+        }
+
         remapping_commands.push(MemoryRemappingCommand::Map {
             range: RESERVED_MEMORY_ADDRESS..=RESERVED_MEMORY_ADDRESS,
             target: MapTarget::Component(self.config.reserved.clone()),
             permissions: Permissions::all(),
         });
 
-        if self.status.vector {
+        if !self.status.vector_disable {
             remapping_commands.push(MemoryRemappingCommand::Map {
                 range: VECTOR_ADDRESSES,
-                target: MapTarget::Component(self.config.vector.clone()),
-                permissions: Permissions::all(),
+                target: MapTarget::Memory(self.config.vectors.clone()),
+                permissions: ROM_PERMISSIONS,
             });
         }
 
@@ -103,7 +118,10 @@ pub struct MapctlConfig {
     pub ram: FluxEmuPath,
     pub suzy: FluxEmuPath,
     pub mikey: FluxEmuPath,
-    pub vector: FluxEmuPath,
+    /// Buffer holding the ROM below the vectors
+    pub rom: FluxEmuPath,
+    /// Buffer holding the last 6 bytes of the ROM
+    pub vectors: FluxEmuPath,
     pub reserved: FluxEmuPath,
     pub cpu_address_space: AddressSpaceId,
 }
@@ -121,8 +139,8 @@ impl<P: Platform> ComponentConfig<P> for MapctlConfig {
     ) -> Result<Self::Component, Box<dyn std::error::Error>> {
         let my_path = component_builder.path().clone();
 
-        let component_builder =
-            component_builder.memory_map_component(self.cpu_address_space, 0xfff9..=0xfff9);
+        let component_builder = component_builder
+            .memory_map_component(self.cpu_address_space, MAPCTL_ADDRESS..=MAPCTL_ADDRESS);
 
         let cpu_address_space = component_builder
             .get_address_space(self.cpu_address_space)
@@ -140,24 +158,24 @@ impl<P: Platform> ComponentConfig<P> for MapctlConfig {
 
 #[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize, Default)]
 pub struct MapctlStatus {
-    pub suzy: bool,
-    pub mikey: bool,
-    pub rom: bool,
-    pub vector: bool,
+    pub suzy_disable: bool,
+    pub mikey_disable: bool,
+    pub rom_disable: bool,
+    pub vector_disable: bool,
     pub reserved: u8, // 3 bits used
     pub sequential_disable: bool,
 }
 
 impl MapctlStatus {
-    /// Load from a single byte (bit 0 = suzy, bit 1 = mikey, etc.)
+    /// Load from a single byte (bit 0 = suzy disable, bit 1 = mikey disable, etc.)
     pub fn from_byte(byte: u8) -> Self {
         let byte = byte.view_bits::<Lsb0>();
 
         Self {
-            suzy: byte[0],
-            mikey: byte[1],
-            rom: byte[2],
-            vector: byte[3],
+            suzy_disable: byte[0],
+            mikey_disable: byte[1],
+            rom_disable: byte[2],
+            vector_disable: byte[3],
             reserved: byte[4..7].load::<u8>(),
             sequential_disable: byte[7],
         }
@@ -170,10 +188,10 @@ impl MapctlStatus {
         {
             let byte = byte.view_bits_mut::<Lsb0>();
 
-            byte.set(0, self.suzy);
-            byte.set(1, self.mikey);
-            byte.set(2, self.rom);
-            byte.set(3, self.vector);
+            byte.set(0, self.suzy_disable);
+            byte.set(1, self.mikey_disable);
+            byte.set(2, self.rom_disable);
+            byte.set(3, self.vector_disable);
             byte[4..7].copy_from_bitslice(&self.reserved.view_bits::<Lsb0>()[0..3]);
             byte.set(7, self.sequential_disable);
         }
@@ -0,0 +1,73 @@
+use serde::{Deserialize, Serialize};
+
+use super::timer::Timer;
+
+/// CONTROL bit which adds the waveform onto the output instead of replacing it
+const CONTROL_INTEGRATE: u8 = 0b0010_0000;
+/// CONTROL bit which enables the feedback tap on bit 7 of the shift register
+const CONTROL_TAP_7: u8 = 0b1000_0000;
+/// The shift register is 12 bits wide
+const SHIFT_REGISTER_MASK: u16 = 0xfff;
+
+/// One of the four audio channels, which is a timer clocking a linear feedback
+/// shift register
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize)]
+pub(super) struct AudioChannel {
+    pub timer: Timer,
+    /// VOLCNTRL, a signed volume
+    pub volume: i8,
+    /// FEEDBACK, which selects the rest of the shift register taps
+    pub feedback: u8,
+    /// OUTPUT
+    pub output: i8,
+    shift_register: u16,
+}
+
+impl AudioChannel {
+    pub(super) fn read_shift_low(&self) -> u8 {
+        self.shift_register as u8
+    }
+
+    pub(super) fn write_shift_low(&mut self, data: u8) {
+        self.shift_register = (self.shift_register & 0xf00) | u16::from(data);
+    }
+
+    /// OTHER, which holds the top of the shift register and the timer status
+    pub(super) fn read_other(&self) -> u8 {
+        ((self.shift_register >> 4) as u8 & 0xf0) | self.timer.read_status()
+    }
+
+    pub(super) fn write_other(&mut self, data: u8) {
+        self.shift_register = (self.shift_register & 0x0ff) | (u16::from(data & 0xf0) << 4);
+        self.timer.write_status(data);
+    }
+
+    fn taps(&self) -> u16 {
+        let feedback = u16::from(self.feedback);
+
+        (feedback & 0x3f)
+            | ((feedback & 0x40) << 4)
+            | ((feedback & 0x80) << 4)
+            | u16::from(self.timer.control & CONTROL_TAP_7)
+    }
+
+    /// Called when the channel timer borrows
+    pub(super) fn clock(&mut self) {
+        let bit = (self.shift_register & self.taps())
+            .count_ones()
+            .is_multiple_of(2);
+        self.shift_register = ((self.shift_register << 1) | u16::from(bit)) & SHIFT_REGISTER_MASK;
+
+        let step = if bit {
+            self.volume
+        } else {
+            self.volume.wrapping_neg()
+        };
+
+        self.output = if self.timer.control & CONTROL_INTEGRATE != 0 {
+            self.output.saturating_add(step)
+        } else {
+            step
+        };
+    }
+}
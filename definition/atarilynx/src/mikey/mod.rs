@@ -0,0 +1,699 @@
+use std::{
+    any::Any,
+    ops::RangeInclusive,
+    sync::{Arc, Mutex},
+};
+
+use audio::AudioChannel;
+use backend::MikeyDisplayBackend;
+pub(crate) use backend::SupportedGraphicsApiMikey;
+use fluxemu_audio::FrameIterator;
+use fluxemu_definition_misc::memory::standard::StandardMemory;
+use fluxemu_definition_mos6502::{IrqFlag, Mos6502, RdyFlag};
+use fluxemu_runtime::{
+    component::{
+        Component, ComponentConfig, LateInitializedData, SampleSource, TypedComponentHandle,
+    },
+    machine::{
+        builder::{ComponentBuilder, SchedulerParticipation},
+        graphics::DisplayRotation,
+    },
+    memory::{Address, AddressSpaceId, MemoryError},
+    path::FluxEmuPath,
+    platform::Platform,
+    scheduler::{Period, SynchronizationContext},
+};
+use nalgebra::SVector;
+use palette::Srgba;
+use ringbuffer::{AllocRingBuffer, RingBuffer};
+use timer::{CONTROL_IRQ_ENABLE, LINKED, Timer};
+use uart::Uart;
+
+use crate::{MIKEY_ADDRESSES, cartridge::LynxCartridge, suzy::Suzy};
+
+mod audio;
+mod backend;
+mod timer;
+mod uart;
+
+pub(crate) const SCREEN_WIDTH: usize = 160;
+pub(crate) const SCREEN_HEIGHT: usize = 102;
+/// Bytes of video memory per line, as each pixel is a nibble
+pub(crate) const LINE_BYTES: usize = SCREEN_WIDTH / 2;
+
+/// Mikey does everything in steps of a microsecond, which is the fastest any
+/// of its timers can be clocked
+const TICK_FREQUENCY: u128 = 1_000_000;
+/// Ticks between each audio sample
+const AUDIO_SAMPLE_DIVIDER: u64 = 32;
+
+/// 8 system timers followed by the timers inside the 4 audio channels
+const TIMER_COUNT: usize = 12;
+/// Which timer each timer clocks when it borrows, if that one is set to [`LINKED`]
+const TIMER_LINKS: [Option<usize>; TIMER_COUNT] = [
+    Some(2),
+    Some(3),
+    Some(4),
+    Some(5),
+    None,
+    Some(7),
+    None,
+    Some(8),
+    Some(9),
+    Some(10),
+    Some(11),
+    Some(1),
+];
+/// Timer 0 paces the lines
+const HORIZONTAL_TIMER: usize = 0;
+/// Timer 2 counts the lines and ends the frame
+const VERTICAL_TIMER: usize = 2;
+/// Timer 4 is the UART baud rate generator, and its interrupt is the UART's
+const UART_TIMER: usize = 4;
+
+const TIMERS: RangeInclusive<Address> = 0xfd00..=0xfd1f;
+const AUDIO: RangeInclusive<Address> = 0xfd20..=0xfd3f;
+const MSTEREO: Address = 0xfd50;
+const INTRST: Address = 0xfd80;
+const INTSET: Address = 0xfd81;
+const SYSCTL1: Address = 0xfd87;
+const MIKEYHREV: Address = 0xfd88;
+const IODIR: Address = 0xfd8a;
+const IODAT: Address = 0xfd8b;
+const SERCTL: Address = 0xfd8c;
+const SERDAT: Address = 0xfd8d;
+const CPUSLEEP: Address = 0xfd91;
+const DISPCTL: Address = 0xfd92;
+const DISPADRL: Address = 0xfd94;
+const DISPADRH: Address = 0xfd95;
+const GREEN: RangeInclusive<Address> = 0xfda0..=0xfdaf;
+const BLUERED: RangeInclusive<Address> = 0xfdb0..=0xfdbf;
+
+/// IODAT bit which is shifted into the cartridge page
+const IODAT_CART_ADDRESS_DATA: u8 = 0b0000_0010;
+/// SYSCTL1 bit which strobes the cartridge address
+const SYSCTL1_CART_ADDRESS_STROBE: u8 = 0b0000_0001;
+const DISPCTL_DMA_ENABLE: u8 = 0b0001;
+const DISPCTL_FLIP: u8 = 0b0010;
+
+/// Timers, interrupts, the LCD controller, the UART and audio
+#[derive(Debug)]
+pub struct Mikey<G: SupportedGraphicsApiMikey> {
+    backend: Option<G::Backend>,
+    timers: [Timer; 8],
+    audio_channels: [AudioChannel; 4],
+    /// MSTEREO, a bit per channel per side which silences it
+    stereo_disable: u8,
+    uart: Mutex<Uart>,
+    interrupts_pending: u8,
+    cpu_irq: Arc<IrqFlag>,
+    cpu_rdy: Arc<RdyFlag>,
+    cpu_sleeping: bool,
+    display_control: u8,
+    display_address: u16,
+    /// DISPADR as it was when the current frame started
+    frame_address: u16,
+    /// GREEN, indexed by pen
+    green: [u8; 16],
+    /// BLUERED, indexed by pen
+    blue_red: [u8; 16],
+    io_direction: u8,
+    io_data: u8,
+    ram: TypedComponentHandle<StandardMemory>,
+    suzy: TypedComponentHandle<Suzy>,
+    cartridge: Option<TypedComponentHandle<LynxCartridge>>,
+    cpu_address_space: AddressSpaceId,
+    audio_buffer: AllocRingBuffer<SVector<f32, 1>>,
+    ticks: u64,
+    timestamp: Period,
+}
+
+impl<G: SupportedGraphicsApiMikey> Component for Mikey<G> {
+    fn memory_read(
+        &self,
+        address: Address,
+        _address_space: AddressSpaceId,
+        avoid_side_effects: bool,
+        buffer: &mut [u8],
+    ) -> Result<(), MemoryError> {
+        buffer[0] = match address {
+            address if TIMERS.contains(&address) => {
+                let timer = &self.timers[(address - TIMERS.start()) / 4];
+
+                match address % 4 {
+                    0 => timer.backup,
+                    1 => timer.control,
+                    2 => timer.count,
+                    _ => timer.read_status(),
+                }
+            }
+            address if AUDIO.contains(&address) => {
+                let channel = &self.audio_channels[(address - AUDIO.start()) / 8];
+
+                match address % 8 {
+                    0 => channel.volume as u8,
+                    1 => channel.feedback,
+                    2 => channel.output as u8,
+                    3 => channel.read_shift_low(),
+                    4 => channel.timer.backup,
+                    5 => channel.timer.control,
+                    6 => channel.timer.count,
+                    _ => channel.read_other(),
+                }
+            }
+            address if GREEN.contains(&address) => self.green[address - GREEN.start()],
+            address if BLUERED.contains(&address) => self.blue_red[address - BLUERED.start()],
+            MSTEREO => self.stereo_disable,
+            INTRST | INTSET => self.interrupts_pending,
+            MIKEYHREV => 0x01,
+            IODIR => self.io_direction,
+            IODAT => self.io_data,
+            SERCTL => self.uart.lock().unwrap().read_control(),
+            SERDAT => self.uart.lock().unwrap().read_data(avoid_side_effects),
+            DISPCTL => self.display_control,
+            DISPADRL => self.display_address as u8,
+            DISPADRH => (self.display_address >> 8) as u8,
+            _ => {
+                tracing::trace!("Read from unhandled Mikey address {:04x}", address);
+
+                0xff
+            }
+        };
+
+        Ok(())
+    }
+
+    fn memory_write(
+        &mut self,
+        address: Address,
+        _address_space: AddressSpaceId,
+        buffer: &[u8],
+    ) -> Result<(), MemoryError> {
+        let data = buffer[0];
+
+        match address {
+            address if TIMERS.contains(&address) => {
+                let timer = &mut self.timers[(address - TIMERS.start()) / 4];
+
+                match address % 4 {
+                    0 => timer.backup = data,
+                    1 => timer.write_control(data),
+                    2 => timer.count = data,
+                    _ => timer.write_status(data),
+                }
+            }
+            address if AUDIO.contains(&address) => {
+                let channel = &mut self.audio_channels[(address - AUDIO.start()) / 8];
+
+                match address % 8 {
+                    0 => channel.volume = data as i8,
+                    1 => channel.feedback = data,
+                    2 => channel.output = data as i8,
+                    3 => channel.write_shift_low(data),
+                    4 => channel.timer.backup = data,
+                    5 => channel.timer.write_control(data),
+                    6 => channel.timer.count = data,
+                    _ => channel.write_other(data),
+                }
+            }
+            address if GREEN.contains(&address) => self.green[address - GREEN.start()] = data,
+            address if BLUERED.contains(&address) => {
+                self.blue_red[address - BLUERED.start()] = data;
+            }
+            MSTEREO => self.stereo_disable = data,
+            INTRST => self.interrupts_pending &= !data,
+            INTSET => self.interrupts_pending |= data,
+            IODIR => self.io_direction = data,
+            IODAT => {
+                self.io_data = data;
+
+                if let Some(cartridge) = &self.cartridge {
+                    cartridge.interact_mut(self.timestamp, |cartridge| {
+                        cartridge.set_address_data(data & IODAT_CART_ADDRESS_DATA != 0);
+                    });
+                }
+            }
+            SYSCTL1 => {
+                if let Some(cartridge) = &self.cartridge {
+                    cartridge.interact_mut(self.timestamp, |cartridge| {
+                        cartridge.set_address_strobe(data & SYSCTL1_CART_ADDRESS_STROBE != 0);
+                    });
+                }
+            }
+            SERCTL => self.uart.get_mut().unwrap().write_control(data),
+            SERDAT => self.uart.get_mut().unwrap().write_data(data),
+            CPUSLEEP => {
+                // Suzy takes the bus while the CPU sleeps, and the CPU only wakes
+                // up early if there is nothing for Suzy to do
+                let painted = self
+                    .suzy
+                    .interact_mut(self.timestamp, |suzy| suzy.paint_sprites(self.timestamp));
+
+                if !painted && self.interrupts_pending == 0 {
+                    self.cpu_rdy.store(false);
+                    self.cpu_sleeping = true;
+                }
+            }
+            DISPCTL => self.display_control = data,
+            DISPADRL => self.display_address = (self.display_address & 0xff00) | u16::from(data),
+            DISPADRH => {
+                self.display_address = (self.display_address & 0x00ff) | (u16::from(data) << 8);
+            }
+            _ => {
+                tracing::trace!(
+                    "Write to unhandled Mikey address {:04x} = {:02x}",
+                    address,
+                    data
+                );
+            }
+        }
+
+        self.update_interrupts();
+
+        Ok(())
+    }
+
+    fn access_framebuffer(&mut self, _path: &FluxEmuPath) -> &dyn Any {
+        self.backend.as_mut().unwrap().access_framebuffer()
+    }
+
+    fn get_audio_channel(&mut self, _audio_output_path: &FluxEmuPath) -> SampleSource<'_> {
+        SampleSource {
+            source: Box::new(self.audio_buffer.drain().repeat_last_frame()),
+            sample_rate: (TICK_FREQUENCY / u128::from(AUDIO_SAMPLE_DIVIDER)) as f32,
+        }
+    }
+
+    fn synchronize(&mut self, mut context: SynchronizationContext) {
+        for now in context.allocate(Period::ONE / TICK_FREQUENCY, None) {
+            self.timestamp = now;
+
+            for index in 0..TIMER_COUNT {
+                let clock_select = self.timer(index).clock_select();
+
+                if clock_select != LINKED && self.ticks.is_multiple_of(1 << clock_select) {
+                    self.clock_timer(index);
+                }
+            }
+
+            if self.ticks.is_multiple_of(AUDIO_SAMPLE_DIVIDER) {
+                self.sample_audio();
+            }
+
+            self.ticks = self.ticks.wrapping_add(1);
+            self.update_interrupts();
+        }
+    }
+
+    fn needs_work(&self, delta: Period) -> bool {
+        delta >= Period::ONE / TICK_FREQUENCY
+    }
+}
+
+impl<G: SupportedGraphicsApiMikey> Mikey<G> {
+    fn timer(&self, index: usize) -> &Timer {
+        match index {
+            0..8 => &self.timers[index],
+            _ => &self.audio_channels[index - 8].timer,
+        }
+    }
+
+    fn timer_mut(&mut self, index: usize) -> &mut Timer {
+        match index {
+            0..8 => &mut self.timers[index],
+            _ => &mut self.audio_channels[index - 8].timer,
+        }
+    }
+
+    /// Clocks a timer, rippling its borrow down the chain of linked timers
+    fn clock_timer(&mut self, mut index: usize) {
+        // Bounded as the chain loops back around on itself
+        for _ in 0..TIMER_COUNT {
+            if !self.timer_mut(index).clock() {
+                return;
+            }
+
+            self.timer_borrowed(index);
+
+            match TIMER_LINKS[index] {
+                Some(next) if self.timer(next).clock_select() == LINKED => index = next,
+                _ => return,
+            }
+        }
+    }
+
+    fn timer_borrowed(&mut self, index: usize) {
+        match index {
+            HORIZONTAL_TIMER => self.render_line(),
+            VERTICAL_TIMER => self.end_frame(),
+            UART_TIMER => self.uart.get_mut().unwrap().clock(),
+            8.. => self.audio_channels[index - 8].clock(),
+            _ => {}
+        }
+
+        if index < 8 && index != UART_TIMER && self.timers[index].control & CONTROL_IRQ_ENABLE != 0
+        {
+            self.interrupts_pending |= 1 << index;
+        }
+    }
+
+    fn update_interrupts(&mut self) {
+        if self.uart.get_mut().unwrap().interrupt() {
+            self.interrupts_pending |= 1 << UART_TIMER;
+        }
+
+        self.cpu_irq.store(self.interrupts_pending == 0);
+
+        // Any interrupt wakes the CPU, even if it is masking them
+        if self.cpu_sleeping && self.interrupts_pending != 0 {
+            self.cpu_sleeping = false;
+            self.cpu_rdy.store(true);
+        }
+    }
+
+    fn pen_color(&self, pen: u8) -> Srgba<u8> {
+        let pen = usize::from(pen);
+
+        Srgba::new(
+            (self.blue_red[pen] & 0xf) * 17,
+            (self.green[pen] & 0xf) * 17,
+            (self.blue_red[pen] >> 4) * 17,
+            0xff,
+        )
+    }
+
+    /// DMAs a line out of video memory onto the LCD
+    fn render_line(&mut self) {
+        // The vertical timer counts down to the last line, with the lines above
+        // the display being vertical blank
+        let vertical_count = usize::from(self.timers[VERTICAL_TIMER].count);
+
+        if self.display_control & DISPCTL_DMA_ENABLE == 0 || vertical_count >= SCREEN_HEIGHT {
+            return;
+        }
+
+        let row = SCREEN_HEIGHT - 1 - vertical_count;
+        let flipped = self.display_control & DISPCTL_FLIP != 0;
+        let row_offset = (row * LINE_BYTES) as u16;
+        let mut line = [0; LINE_BYTES];
+
+        // Flipped displays are read backwards from the end of the buffer
+        let start = if flipped {
+            self.frame_address
+                .wrapping_sub(row_offset)
+                .wrapping_sub(LINE_BYTES as u16 - 1)
+        } else {
+            self.frame_address.wrapping_add(row_offset)
+        };
+
+        self.ram.interact(self.timestamp, |ram| {
+            for (offset, byte) in line.iter_mut().enumerate() {
+                let address = start.wrapping_add(offset as u16);
+
+                ram.memory_read(
+                    usize::from(address),
+                    self.cpu_address_space,
+                    true,
+                    std::slice::from_mut(byte),
+                )
+                .unwrap();
+            }
+        });
+
+        if flipped {
+            line.reverse();
+        }
+
+        let colors = std::array::from_fn::<_, 16, _>(|pen| self.pen_color(pen as u8));
+
+        self.backend
+            .as_mut()
+            .unwrap()
+            .modify_staging_buffer(|mut staging_buffer| {
+                for (offset, byte) in line.into_iter().enumerate() {
+                    let pens = if flipped {
+                        [byte & 0xf, byte >> 4]
+                    } else {
+                        [byte >> 4, byte & 0xf]
+                    };
+
+                    for (half, pen) in pens.into_iter().enumerate() {
+                        staging_buffer[(offset * 2 + half, row)] = colors[usize::from(pen)];
+                    }
+                }
+            });
+    }
+
+    fn end_frame(&mut self) {
+        self.backend.as_mut().unwrap().commit_staging_buffer();
+        self.frame_address = self.display_address;
+    }
+
+    fn sample_audio(&mut self) {
+        let sample = self
+            .audio_channels
+            .iter()
+            .enumerate()
+            // Only silent if both sides are disabled
+            .filter(|(index, _)| {
+                let mask = 0x11 << index;
+                self.stereo_disable & mask != mask
+            })
+            .map(|(_, channel)| f32::from(channel.output))
+            .sum::<f32>()
+            / (self.audio_channels.len() as f32 * 128.0);
+
+        self.audio_buffer.enqueue(SVector::from([sample]));
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct MikeyConfig {
+    pub cpu: FluxEmuPath,
+    pub ram: FluxEmuPath,
+    pub suzy: FluxEmuPath,
+    pub cartridge: Option<FluxEmuPath>,
+    pub cpu_address_space: AddressSpaceId,
+}
+
+impl<P: Platform<GraphicsApi: SupportedGraphicsApiMikey>> ComponentConfig<P> for MikeyConfig {
+    type Component = Mikey<P::GraphicsApi>;
+
+    fn late_initialize(component: &mut Self::Component, data: &LateInitializedData<P>) {
+        component.backend = Some(MikeyDisplayBackend::new(
+            data.component_graphics_initialization_data.clone(),
+        ));
+    }
+
+    fn build_component(
+        self,
+        component_builder: ComponentBuilder<'_, P, Self::Component>,
+    ) -> Result<Self::Component, Box<dyn std::error::Error>> {
+        // Some games are played with the Lynx held sideways
+        let rotation = self
+            .cartridge
+            .as_ref()
+            .and_then(|cartridge| {
+                component_builder.interact::<LynxCartridge, _>(cartridge, LynxCartridge::rotation)
+            })
+            .unwrap_or_default();
+
+        let component_builder =
+            component_builder.set_scheduler_participation(SchedulerParticipation::SchedulerDriven);
+
+        let (component_builder, _) = if rotation == DisplayRotation::None {
+            component_builder.insert_display("lcd")
+        } else {
+            component_builder.insert_rotated_display("lcd", rotation)
+        };
+
+        let (component_builder, _) = component_builder.insert_audio_channel("mono");
+
+        let component_builder =
+            component_builder.memory_map_component(self.cpu_address_space, MIKEY_ADDRESSES);
+
+        let (cpu_irq, cpu_rdy) = component_builder
+            .interact::<Mos6502, _>(&self.cpu, |cpu| (cpu.irq(), cpu.rdy()))
+            .unwrap();
+
+        Ok(Mikey {
+            backend: None,
+            timers: Default::default(),
+            audio_channels: Default::default(),
+            stereo_disable: 0,
+            uart: Mutex::default(),
+            interrupts_pending: 0,
+            cpu_irq,
+            cpu_rdy,
+            cpu_sleeping: false,
+            display_control: 0,
+            display_address: 0,
+            frame_address: 0,
+            green: [0; 16],
+            blue_red: [0; 16],
+            io_direction: 0,
+            io_data: 0,
+            ram: component_builder.typed_handle(&self.ram).unwrap(),
+            suzy: component_builder.typed_handle(&self.suzy).unwrap(),
+            cartridge: self
+                .cartridge
+                .map(|cartridge| component_builder.typed_handle(&cartridge).unwrap()),
+            cpu_address_space: self.cpu_address_space,
+            // Roughly a frame of samples
+            audio_buffer: AllocRingBuffer::new(1024),
+            ticks: 0,
+            timestamp: Period::default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fluxemu_definition_misc::memory::standard::{
+        StandardMemoryConfig, StandardMemoryInitialContents,
+    };
+    use fluxemu_definition_mos6502::{Mos6502Config, Mos6502Kind};
+    use fluxemu_runtime::{graphics::software::Software, machine::Machine, scheduler::Frequency};
+    use rangemap::RangeInclusiveMap;
+
+    use super::*;
+    use crate::suzy::SuzyConfig;
+
+    /// Mikey wired to a CPU, RAM and Suzy, without any BIOS
+    fn machine() -> (Arc<Machine>, FluxEmuPath) {
+        let (machine, cpu_address_space) = Machine::build_test_minimal().insert_address_space(16);
+
+        let (machine, cpu) = machine.insert_component(
+            "wdc_65c02",
+            Mos6502Config {
+                frequency: Frequency::from_num(4_000_000),
+                kind: Mos6502Kind::Wdc65C02,
+                assigned_address_space: cpu_address_space,
+                broken_ror: false,
+                cycle_accurate_bus: false,
+            },
+        );
+
+        let (machine, ram) = machine.insert_component(
+            "ram",
+            StandardMemoryConfig {
+                readable: true,
+                writable: true,
+                assigned_range: 0x0000..=0xffff,
+                assigned_address_space: cpu_address_space,
+                initial_contents: RangeInclusiveMap::from_iter([(
+                    0x0000..=0xffff,
+                    StandardMemoryInitialContents::Value(0),
+                )]),
+                sram: false,
+            },
+        );
+
+        let (machine, suzy) = machine.insert_component(
+            "suzy",
+            SuzyConfig {
+                ram: ram.clone(),
+                cartridge: None,
+                cpu_address_space,
+            },
+        );
+
+        let (machine, mikey) = machine.insert_component(
+            "mikey",
+            MikeyConfig {
+                cpu,
+                ram,
+                suzy,
+                cartridge: None,
+                cpu_address_space,
+            },
+        );
+
+        (machine.build(()), mikey)
+    }
+
+    impl Mikey<Software> {
+        fn write(&mut self, address: Address, data: u8) {
+            self.memory_write(address, self.cpu_address_space, &[data])
+                .unwrap();
+        }
+
+        fn read(&self, address: Address) -> u8 {
+            let mut data = 0;
+
+            self.memory_read(
+                address,
+                self.cpu_address_space,
+                true,
+                std::slice::from_mut(&mut data),
+            )
+            .unwrap();
+
+            data
+        }
+    }
+
+    #[test]
+    fn linked_timers_cascade() {
+        let (machine, mikey) = machine();
+
+        machine
+            .interact_mut::<Mikey<Software>, _>(&mikey, |mikey| {
+                // Timer 1 borrows every time it is clocked, and clocks timer 3
+                mikey.write(0xfd04, 0x00);
+                mikey.write(0xfd05, 0x18);
+                mikey.write(0xfd06, 0x00);
+                // Timer 3 is a linked one shot
+                mikey.write(0xfd0c, 0x00);
+                mikey.write(0xfd0d, 0x08 | LINKED);
+                mikey.write(0xfd0e, 0x02);
+                // Timer 5 comes after timer 3 but isn't linked
+                mikey.write(0xfd15, 0x08);
+                mikey.write(0xfd16, 0x07);
+
+                for _ in 0..3 {
+                    mikey.clock_timer(1);
+                }
+
+                assert_eq!(mikey.read(0xfd0e), 0x00);
+                assert_eq!(mikey.read(0xfd0f), 0b1001, "timer 3 borrowed");
+                assert_eq!(mikey.read(0xfd16), 0x07, "timer 5 was clocked");
+
+                // Done one shots stop taking borrows
+                mikey.clock_timer(1);
+
+                assert_eq!(mikey.read(0xfd0e), 0x00);
+                assert_eq!(mikey.read(0xfd0f), 0b1000);
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn borrows_raise_interrupts() {
+        let (machine, mikey) = machine();
+
+        machine
+            .interact_mut::<Mikey<Software>, _>(&mikey, |mikey| {
+                // Timer 1 interrupts when it borrows, timer 3 linked after it doesn't
+                mikey.write(0xfd05, CONTROL_IRQ_ENABLE | 0x18);
+                mikey.write(0xfd0d, 0x08 | LINKED);
+
+                mikey.clock_timer(1);
+                mikey.update_interrupts();
+
+                assert_eq!(mikey.read(INTSET), 0b0000_0010);
+                assert!(mikey.cpu_irq.interrupt_required());
+
+                mikey.write(INTRST, 0b0000_0010);
+
+                assert_eq!(mikey.read(INTSET), 0);
+                assert!(!mikey.cpu_irq.interrupt_required());
+
+                // Software can raise them too
+                mikey.write(INTSET, 0b1000_0000);
+
+                assert!(mikey.cpu_irq.interrupt_required());
+            })
+            .unwrap();
+    }
+}
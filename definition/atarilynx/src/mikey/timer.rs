@@ -0,0 +1,121 @@
+use serde::{Deserialize, Serialize};
+
+const CONTROL_RESET_DONE: u8 = 0b0100_0000;
+const CONTROL_RELOAD: u8 = 0b0001_0000;
+const CONTROL_COUNT: u8 = 0b0000_1000;
+const CONTROL_CLOCK_SELECT: u8 = 0b0000_0111;
+pub(super) const CONTROL_IRQ_ENABLE: u8 = 0b1000_0000;
+/// Clock select value which clocks a timer off the borrow of the one before it
+pub(super) const LINKED: u8 = 0b111;
+
+/// One of the down counters inside Mikey, the audio channels each embed one
+/// of these too
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize)]
+pub(super) struct Timer {
+    /// Value loaded into the counter when it underflows
+    pub backup: u8,
+    /// CTLA, or CONTROL for the audio channels
+    pub control: u8,
+    pub count: u8,
+    pub done: bool,
+    borrow_out: bool,
+}
+
+impl Timer {
+    pub(super) fn write_control(&mut self, data: u8) {
+        self.control = data;
+
+        if data & CONTROL_RESET_DONE != 0 {
+            self.done = false;
+        }
+    }
+
+    pub(super) fn read_status(&self) -> u8 {
+        (u8::from(self.done) << 3) | u8::from(self.borrow_out)
+    }
+
+    pub(super) fn write_status(&mut self, data: u8) {
+        self.done = data & 0b1000 != 0;
+        self.borrow_out = data & 0b0001 != 0;
+    }
+
+    /// 0 through 6 are a prescaler of 1 << n microseconds, 7 is [`LINKED`]
+    pub(super) fn clock_select(&self) -> u8 {
+        self.control & CONTROL_CLOCK_SELECT
+    }
+
+    /// Steps the counter, returning if it borrowed
+    pub(super) fn clock(&mut self) -> bool {
+        let reload = self.control & CONTROL_RELOAD != 0;
+
+        // One shot timers stop once they are done
+        if self.control & CONTROL_COUNT == 0 || (self.done && !reload) {
+            self.borrow_out = false;
+            return false;
+        }
+
+        if self.count == 0 {
+            if reload {
+                self.count = self.backup;
+            }
+
+            self.done = true;
+            self.borrow_out = true;
+        } else {
+            self.count -= 1;
+            self.borrow_out = false;
+        }
+
+        self.borrow_out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timer(control: u8, count: u8) -> Timer {
+        Timer {
+            backup: 2,
+            control,
+            count,
+            ..Timer::default()
+        }
+    }
+
+    #[test]
+    fn reload() {
+        let mut timer = timer(CONTROL_COUNT | CONTROL_RELOAD, 1);
+
+        assert!(!timer.clock());
+        assert!(timer.clock());
+        assert_eq!(timer.count, 2);
+        assert_eq!(timer.read_status(), 0b1001);
+
+        // Reloading timers keep going after they are done
+        assert!(!timer.clock());
+        assert_eq!(timer.count, 1);
+    }
+
+    #[test]
+    fn one_shot() {
+        let mut timer = timer(CONTROL_COUNT, 0);
+
+        assert!(timer.clock());
+        assert!(!timer.clock());
+        assert_eq!(timer.count, 0);
+        assert_eq!(timer.read_status(), 0b1000);
+
+        // Resetting done lets it go again
+        timer.write_control(CONTROL_COUNT | CONTROL_RESET_DONE);
+        assert!(timer.clock());
+    }
+
+    #[test]
+    fn disabled() {
+        let mut timer = timer(0, 0);
+
+        assert!(!timer.clock());
+        assert!(!timer.done);
+    }
+}
@@ -0,0 +1,99 @@
+use serde::{Deserialize, Serialize};
+
+/// Timer 4 underflows it takes to shift out one frame, including the start,
+/// parity and stop bits
+const FRAME_LENGTH: u8 = 11;
+
+const SERCTL_TX_INTERRUPT_ENABLE: u8 = 0b1000_0000;
+const SERCTL_RX_INTERRUPT_ENABLE: u8 = 0b0100_0000;
+const SERCTL_RESET_ERRORS: u8 = 0b0000_1000;
+
+/// The ComLynx serial port
+///
+/// Nothing is plugged into it, but its transmit line is looped back onto its
+/// receive line like on real hardware, so everything sent is received again
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub(super) struct Uart {
+    tx_interrupt_enable: bool,
+    rx_interrupt_enable: bool,
+    /// Written to SERDAT but not yet being shifted out
+    tx_buffer: Option<u8>,
+    /// Being shifted out, with how many timer 4 underflows are left
+    tx_shifting: Option<(u8, u8)>,
+    rx_data: Option<u8>,
+    overrun: bool,
+}
+
+impl Uart {
+    pub(super) fn read_control(&self) -> u8 {
+        let mut value = 0;
+
+        if self.tx_buffer.is_none() {
+            value |= 0b1000_0000;
+        }
+
+        if self.rx_data.is_some() {
+            value |= 0b0100_0000;
+        }
+
+        if self.tx_buffer.is_none() && self.tx_shifting.is_none() {
+            value |= 0b0010_0000;
+        }
+
+        if self.overrun {
+            value |= 0b0000_1000;
+        }
+
+        value
+    }
+
+    pub(super) fn write_control(&mut self, data: u8) {
+        self.tx_interrupt_enable = data & SERCTL_TX_INTERRUPT_ENABLE != 0;
+        self.rx_interrupt_enable = data & SERCTL_RX_INTERRUPT_ENABLE != 0;
+
+        if data & SERCTL_RESET_ERRORS != 0 {
+            self.overrun = false;
+        }
+    }
+
+    pub(super) fn read_data(&mut self, avoid_side_effects: bool) -> u8 {
+        if avoid_side_effects {
+            self.rx_data.unwrap_or(0)
+        } else {
+            self.rx_data.take().unwrap_or(0)
+        }
+    }
+
+    pub(super) fn write_data(&mut self, data: u8) {
+        if self.tx_shifting.is_none() {
+            self.tx_shifting = Some((data, FRAME_LENGTH));
+        } else {
+            self.tx_buffer = Some(data);
+        }
+    }
+
+    /// Called when timer 4 underflows
+    pub(super) fn clock(&mut self) {
+        let Some((data, remaining)) = self.tx_shifting.as_mut() else {
+            return;
+        };
+
+        *remaining -= 1;
+
+        if *remaining == 0 {
+            let data = *data;
+
+            if self.rx_data.replace(data).is_some() {
+                self.overrun = true;
+            }
+
+            self.tx_shifting = self.tx_buffer.take().map(|data| (data, FRAME_LENGTH));
+        }
+    }
+
+    /// The UART interrupt is level triggered rather than latched like the timers
+    pub(super) fn interrupt(&self) -> bool {
+        (self.tx_interrupt_enable && self.tx_buffer.is_none())
+            || (self.rx_interrupt_enable && self.rx_data.is_some())
+    }
+}
@@ -0,0 +1,255 @@
+use serde::{Deserialize, Serialize};
+
+/// Suzy's multiplier and divider
+///
+/// ```text
+///     AB           EFGH
+///   * CD         /   NP
+///   ----         ------
+///   EFGH           ABCD
+/// accumulated    remainder
+///   in JKLM       in JKLM
+/// ```
+#[derive(Default, Debug, Clone, Copy, Serialize, Deserialize)]
+pub(super) struct MathUnit {
+    abcd: u32,
+    efgh: u32,
+    jklm: u32,
+    np: u16,
+    ab_negative: bool,
+    cd_negative: bool,
+    /// Set by accumulator overflow or dividing by zero
+    pub warning: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(super) struct MathMode {
+    pub signed: bool,
+    pub accumulate: bool,
+}
+
+const MATHD: usize = 0x52;
+const MATHC: usize = 0x53;
+const MATHB: usize = 0x54;
+const MATHA: usize = 0x55;
+const MATHP: usize = 0x56;
+const MATHN: usize = 0x57;
+const MATHH: usize = 0x60;
+const MATHG: usize = 0x61;
+const MATHF: usize = 0x62;
+const MATHE: usize = 0x63;
+const MATHM: usize = 0x6c;
+const MATHL: usize = 0x6d;
+const MATHK: usize = 0x6e;
+const MATHJ: usize = 0x6f;
+
+fn set_byte(value: &mut u32, index: u32, data: u8) {
+    let shift = index * 8;
+    *value = (*value & !(0xff << shift)) | (u32::from(data) << shift);
+}
+
+/// Converts to a magnitude, remembering the sign
+///
+/// The hardware gets 0x8000 and 0x0000 the wrong way round, which is kept
+fn sign_convert(value: u16) -> (u16, bool) {
+    if value.wrapping_sub(1) & 0x8000 != 0 {
+        ((value ^ 0xffff).wrapping_add(1), true)
+    } else {
+        (value, false)
+    }
+}
+
+impl MathUnit {
+    /// Takes the register's offset from the start of Suzy
+    pub(super) fn handles(offset: usize) -> bool {
+        matches!(offset, MATHD..=MATHN | MATHH..=MATHE | MATHM..=MATHJ)
+    }
+
+    pub(super) fn read(&self, offset: usize) -> u8 {
+        let (value, index) = match offset {
+            MATHD..=MATHA => (self.abcd, offset - MATHD),
+            MATHP..=MATHN => (u32::from(self.np), offset - MATHP),
+            MATHH..=MATHE => (self.efgh, offset - MATHH),
+            MATHM..=MATHJ => (self.jklm, offset - MATHM),
+            _ => unreachable!(),
+        };
+
+        (value >> (index * 8)) as u8
+    }
+
+    /// Writing some registers clears the one above them, so software can write
+    /// just the low byte, and writing the top byte starts the operation
+    pub(super) fn write(&mut self, offset: usize, data: u8, mode: MathMode) {
+        match offset {
+            MATHD => {
+                set_byte(&mut self.abcd, 0, data);
+                set_byte(&mut self.abcd, 1, 0);
+            }
+            MATHC => {
+                set_byte(&mut self.abcd, 1, data);
+
+                if mode.signed {
+                    let (cd, negative) = sign_convert(self.abcd as u16);
+                    self.abcd = (self.abcd & 0xffff_0000) | u32::from(cd);
+                    self.cd_negative = negative;
+                }
+            }
+            MATHB => {
+                set_byte(&mut self.abcd, 2, data);
+                set_byte(&mut self.abcd, 3, 0);
+            }
+            MATHA => {
+                set_byte(&mut self.abcd, 3, data);
+
+                if mode.signed {
+                    let (ab, negative) = sign_convert((self.abcd >> 16) as u16);
+                    self.abcd = (self.abcd & 0x0000_ffff) | (u32::from(ab) << 16);
+                    self.ab_negative = negative;
+                }
+
+                self.multiply(mode);
+            }
+            MATHP => self.np = u16::from(data),
+            MATHN => self.np = (self.np & 0x00ff) | (u16::from(data) << 8),
+            MATHH => {
+                set_byte(&mut self.efgh, 0, data);
+                set_byte(&mut self.efgh, 1, 0);
+            }
+            MATHG => set_byte(&mut self.efgh, 1, data),
+            MATHF => {
+                set_byte(&mut self.efgh, 2, data);
+                set_byte(&mut self.efgh, 3, 0);
+            }
+            MATHE => {
+                set_byte(&mut self.efgh, 3, data);
+                self.divide();
+            }
+            MATHM => {
+                set_byte(&mut self.jklm, 0, data);
+                set_byte(&mut self.jklm, 1, 0);
+                self.warning = false;
+            }
+            MATHL => set_byte(&mut self.jklm, 1, data),
+            MATHK => {
+                set_byte(&mut self.jklm, 2, data);
+                set_byte(&mut self.jklm, 3, 0);
+            }
+            MATHJ => set_byte(&mut self.jklm, 3, data),
+            _ => unreachable!(),
+        }
+    }
+
+    fn multiply(&mut self, mode: MathMode) {
+        self.warning = false;
+
+        // The multiply itself is always unsigned, with the sign applied afterwards
+        self.efgh = (self.abcd >> 16) * (self.abcd & 0xffff);
+
+        if mode.signed && self.ab_negative != self.cd_negative {
+            self.efgh = self.efgh.wrapping_neg();
+        }
+
+        if mode.accumulate {
+            let accumulated = self.jklm.wrapping_add(self.efgh);
+
+            // Overflow is the sign changing
+            self.warning = (accumulated ^ self.jklm) & 0x8000_0000 != 0;
+            self.jklm = accumulated;
+        }
+    }
+
+    fn divide(&mut self) {
+        // Division is always unsigned
+        if self.np == 0 {
+            self.abcd = u32::MAX;
+            self.jklm = 0;
+            self.warning = true;
+        } else {
+            self.abcd = self.efgh / u32::from(self.np);
+            self.jklm = self.efgh % u32::from(self.np);
+            self.warning = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_all(math: &mut MathUnit, writes: &[(usize, u8)], mode: MathMode) {
+        for (offset, data) in writes {
+            math.write(*offset, *data, mode);
+        }
+    }
+
+    const UNSIGNED: MathMode = MathMode {
+        signed: false,
+        accumulate: false,
+    };
+
+    #[test]
+    fn multiply() {
+        let mut math = MathUnit::default();
+
+        // 0x1234 * 0x0100
+        write_all(
+            &mut math,
+            &[(MATHD, 0x00), (MATHC, 0x01), (MATHB, 0x34), (MATHA, 0x12)],
+            UNSIGNED,
+        );
+
+        assert_eq!(math.efgh, 0x0012_3400);
+        assert_eq!(math.read(MATHF), 0x12);
+    }
+
+    #[test]
+    fn signed_multiply() {
+        let mut math = MathUnit::default();
+        let mode = MathMode {
+            signed: true,
+            accumulate: false,
+        };
+
+        // -2 * 3
+        write_all(
+            &mut math,
+            &[(MATHD, 0x03), (MATHC, 0x00), (MATHB, 0xfe), (MATHA, 0xff)],
+            mode,
+        );
+
+        assert_eq!(math.efgh as i32, -6);
+    }
+
+    #[test]
+    fn divide() {
+        let mut math = MathUnit::default();
+
+        // 1000 / 7
+        write_all(
+            &mut math,
+            &[
+                (MATHP, 0x07),
+                (MATHN, 0x00),
+                (MATHH, 0xe8),
+                (MATHG, 0x03),
+                (MATHF, 0x00),
+                (MATHE, 0x00),
+            ],
+            UNSIGNED,
+        );
+
+        assert_eq!(math.abcd, 142);
+        assert_eq!(math.jklm, 6);
+        assert!(!math.warning);
+    }
+
+    #[test]
+    fn divide_by_zero() {
+        let mut math = MathUnit::default();
+
+        write_all(&mut math, &[(MATHH, 0x01), (MATHE, 0x00)], UNSIGNED);
+
+        assert_eq!(math.abcd, u32::MAX);
+        assert!(math.warning);
+    }
+}
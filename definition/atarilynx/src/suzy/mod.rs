@@ -0,0 +1,256 @@
+use std::{ops::RangeInclusive, sync::Arc};
+
+use fluxemu_definition_misc::memory::standard::StandardMemory;
+use fluxemu_runtime::{
+    component::{Component, ComponentConfig, TypedComponentHandle},
+    input::{GamepadInput, Input, VirtualGamepad},
+    machine::builder::ComponentBuilder,
+    memory::{Address, AddressSpaceId, MemoryError},
+    path::FluxEmuPath,
+    platform::Platform,
+    scheduler::Period,
+};
+use math::{MathMode, MathUnit};
+use sprite::{Bus, PaintOptions, SPRITE_REGISTER_COUNT, SpriteEngine};
+
+use crate::{
+    SUZY_ADDRESSES,
+    cartridge::LynxCartridge,
+    gamepad::{DOWN, INSIDE, LEFT, OPTION_1, OPTION_2, OUTSIDE, PAUSE, RIGHT, UP, create_gamepad},
+};
+
+mod math;
+mod sprite;
+
+/// TMPADR through PROCADR, which are all 16 bits wide
+const SPRITE_REGISTERS: RangeInclusive<Address> = 0xfc00..=(0xfc00 + SPRITE_REGISTER_COUNT * 2 - 1);
+const SPRCTL0: Address = 0xfc80;
+const SPRCTL1: Address = 0xfc81;
+const SPRCOLL: Address = 0xfc82;
+const SUZYHREV: Address = 0xfc88;
+const SUZYBUSEN: Address = 0xfc90;
+const SPRGO: Address = 0xfc91;
+const SPRSYS: Address = 0xfc92;
+const JOYSTICK: Address = 0xfcb0;
+const SWITCHES: Address = 0xfcb1;
+const RCART: RangeInclusive<Address> = 0xfcb2..=0xfcb3;
+
+const SPRGO_GO: u8 = 0b0000_0001;
+const SPRGO_EVERON: u8 = 0b0000_0100;
+const SPRSYS_SIGNED_MATH: u8 = 0b1000_0000;
+const SPRSYS_ACCUMULATE: u8 = 0b0100_0000;
+const SPRSYS_NO_COLLIDE: u8 = 0b0010_0000;
+const SPRSYS_VERTICAL_STRETCH: u8 = 0b0001_0000;
+const SPRSYS_LEFT_HAND: u8 = 0b0000_1000;
+
+/// The sprite engine, math unit, and the joypad
+#[derive(Debug)]
+pub struct Suzy {
+    sprite_engine: SpriteEngine,
+    math: MathUnit,
+    bus_enable: bool,
+    sprite_go: bool,
+    everon: bool,
+    /// SPRSYS as last written
+    system_control: u8,
+    gamepad: Arc<VirtualGamepad>,
+    ram: TypedComponentHandle<StandardMemory>,
+    cartridge: Option<TypedComponentHandle<LynxCartridge>>,
+    cpu_address_space: AddressSpaceId,
+}
+
+impl Component for Suzy {
+    fn memory_read(
+        &self,
+        address: Address,
+        _address_space: AddressSpaceId,
+        avoid_side_effects: bool,
+        buffer: &mut [u8],
+    ) -> Result<(), MemoryError> {
+        let offset = address - SUZY_ADDRESSES.start();
+
+        buffer[0] = match address {
+            address if SPRITE_REGISTERS.contains(&address) => {
+                let register = self.sprite_engine.registers[offset / 2];
+
+                if offset.is_multiple_of(2) {
+                    register as u8
+                } else {
+                    (register >> 8) as u8
+                }
+            }
+            _ if MathUnit::handles(offset) => self.math.read(offset),
+            SPRCTL0 => self.sprite_engine.control0,
+            SPRCTL1 => self.sprite_engine.control1,
+            SPRCOLL => self.sprite_engine.collision_control,
+            SUZYHREV => 0x01,
+            SPRSYS => self.read_system_status(),
+            JOYSTICK => self.read_joystick(),
+            SWITCHES => u8::from(self.pressed(PAUSE)),
+            address if RCART.contains(&address) => match &self.cartridge {
+                // The cartridge keeps no time of its own
+                Some(cartridge) => cartridge.interact_mut(Period::default(), |cartridge| {
+                    cartridge.read(address - RCART.start(), avoid_side_effects)
+                }),
+                None => 0xff,
+            },
+            _ => {
+                tracing::trace!("Read from unhandled Suzy address {:04x}", address);
+
+                0xff
+            }
+        };
+
+        Ok(())
+    }
+
+    fn memory_write(
+        &mut self,
+        address: Address,
+        _address_space: AddressSpaceId,
+        buffer: &[u8],
+    ) -> Result<(), MemoryError> {
+        let offset = address - SUZY_ADDRESSES.start();
+        let data = buffer[0];
+
+        match address {
+            address if SPRITE_REGISTERS.contains(&address) => {
+                let register = &mut self.sprite_engine.registers[offset / 2];
+
+                // Writing the low byte clears the high byte
+                if offset.is_multiple_of(2) {
+                    *register = u16::from(data);
+                } else {
+                    *register = (*register & 0x00ff) | (u16::from(data) << 8);
+                }
+            }
+            _ if MathUnit::handles(offset) => {
+                let mode = MathMode {
+                    signed: self.system_control & SPRSYS_SIGNED_MATH != 0,
+                    accumulate: self.system_control & SPRSYS_ACCUMULATE != 0,
+                };
+
+                self.math.write(offset, data, mode);
+            }
+            SPRCTL0 => self.sprite_engine.control0 = data,
+            SPRCTL1 => self.sprite_engine.control1 = data,
+            SPRCOLL => self.sprite_engine.collision_control = data,
+            SUZYBUSEN => self.bus_enable = data & 0b1 != 0,
+            SPRGO => {
+                self.sprite_go = data & SPRGO_GO != 0;
+                self.everon = data & SPRGO_EVERON != 0;
+            }
+            SPRSYS => self.system_control = data,
+            address if RCART.contains(&address) => {
+                if let Some(cartridge) = &self.cartridge {
+                    cartridge.interact_mut(Period::default(), LynxCartridge::write);
+                }
+            }
+            _ => {
+                tracing::trace!(
+                    "Write to unhandled Suzy address {:04x} = {:02x}",
+                    address,
+                    data
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Suzy {
+    fn pressed(&self, input: GamepadInput) -> bool {
+        self.gamepad.get(Input::Gamepad(input)).as_digital(None)
+    }
+
+    fn read_system_status(&self) -> u8 {
+        let mut value = self.system_control & (SPRSYS_VERTICAL_STRETCH | SPRSYS_LEFT_HAND);
+
+        if self.math.warning {
+            value |= 0b0100_0000;
+        }
+
+        value
+    }
+
+    fn read_joystick(&self) -> u8 {
+        // The directions are reversed when the Lynx is held upside down
+        let (up, down, left, right) = if self.system_control & SPRSYS_LEFT_HAND != 0 {
+            (DOWN, UP, RIGHT, LEFT)
+        } else {
+            (UP, DOWN, LEFT, RIGHT)
+        };
+
+        [up, down, left, right, OPTION_1, OPTION_2, INSIDE, OUTSIDE]
+            .into_iter()
+            .fold(0, |value, input| {
+                (value << 1) | u8::from(self.pressed(input))
+            })
+    }
+
+    /// Runs the sprite engine over the SCB list while the CPU sleeps,
+    /// returning if it had anything to do
+    pub(crate) fn paint_sprites(&mut self, timestamp: Period) -> bool {
+        if !(self.bus_enable && self.sprite_go) {
+            return false;
+        }
+
+        let options = PaintOptions {
+            no_collide: self.system_control & SPRSYS_NO_COLLIDE != 0,
+            vertical_stretch: self.system_control & SPRSYS_VERTICAL_STRETCH != 0,
+            everon: self.everon,
+        };
+
+        self.ram.interact_mut(timestamp, |ram| {
+            self.sprite_engine.paint(
+                &mut Bus {
+                    ram,
+                    address_space: self.cpu_address_space,
+                },
+                options,
+            );
+        });
+
+        self.sprite_go = false;
+
+        true
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SuzyConfig {
+    pub ram: FluxEmuPath,
+    pub cartridge: Option<FluxEmuPath>,
+    pub cpu_address_space: AddressSpaceId,
+}
+
+impl<P: Platform> ComponentConfig<P> for SuzyConfig {
+    type Component = Suzy;
+
+    fn build_component(
+        self,
+        component_builder: ComponentBuilder<P, Self::Component>,
+    ) -> Result<Self::Component, Box<dyn std::error::Error>> {
+        let gamepad = create_gamepad();
+
+        let (component_builder, _) = component_builder
+            .memory_map_component(self.cpu_address_space, SUZY_ADDRESSES)
+            .insert_gamepad("joypad", gamepad.clone());
+
+        Ok(Suzy {
+            sprite_engine: SpriteEngine::default(),
+            math: MathUnit::default(),
+            bus_enable: false,
+            sprite_go: false,
+            everon: false,
+            system_control: 0,
+            gamepad,
+            ram: component_builder.typed_handle(&self.ram).unwrap(),
+            cartridge: self
+                .cartridge
+                .map(|cartridge| component_builder.typed_handle(&cartridge).unwrap()),
+            cpu_address_space: self.cpu_address_space,
+        })
+    }
+}
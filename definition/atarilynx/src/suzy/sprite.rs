@@ -0,0 +1,939 @@
+use fluxemu_definition_misc::memory::standard::StandardMemory;
+use fluxemu_runtime::{component::Component, memory::AddressSpaceId};
+use serde::{Deserialize, Serialize};
+
+use crate::mikey::{LINE_BYTES, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+pub(super) const TMPADR: usize = 0x00;
+pub(super) const TILTACUM: usize = 0x01;
+pub(super) const HOFF: usize = 0x02;
+pub(super) const VOFF: usize = 0x03;
+pub(super) const VIDBAS: usize = 0x04;
+pub(super) const COLLBAS: usize = 0x05;
+pub(super) const SCBNEXT: usize = 0x08;
+pub(super) const SPRDLINE: usize = 0x09;
+pub(super) const HPOSSTRT: usize = 0x0a;
+pub(super) const VPOSSTRT: usize = 0x0b;
+pub(super) const SPRHSIZ: usize = 0x0c;
+pub(super) const SPRVSIZ: usize = 0x0d;
+pub(super) const STRETCH: usize = 0x0e;
+pub(super) const TILT: usize = 0x0f;
+pub(super) const SPRDOFF: usize = 0x10;
+pub(super) const COLLOFF: usize = 0x12;
+pub(super) const VSIZACUM: usize = 0x13;
+pub(super) const HSIZOFF: usize = 0x14;
+pub(super) const VSIZOFF: usize = 0x15;
+pub(super) const SCBADR: usize = 0x16;
+/// 16 bit registers between TMPADR and PROCADR
+pub(super) const SPRITE_REGISTER_COUNT: usize = 0x18;
+
+const SPRCTL0_TYPE: u8 = 0b0000_0111;
+const SPRCTL0_VFLIP: u8 = 0b0001_0000;
+const SPRCTL0_HFLIP: u8 = 0b0010_0000;
+const SPRCTL1_START_LEFT: u8 = 0b0000_0001;
+const SPRCTL1_START_UP: u8 = 0b0000_0010;
+const SPRCTL1_SKIP: u8 = 0b0000_0100;
+const SPRCTL1_KEEP_PALETTE: u8 = 0b0000_1000;
+const SPRCTL1_LITERAL: u8 = 0b1000_0000;
+const SPRCOLL_NUMBER: u8 = 0b0000_1111;
+const SPRCOLL_DONT_COLLIDE: u8 = 0b0010_0000;
+
+/// Pen which shadow sprites don't collide with
+const SHADOW_PEN: u8 = 0x0e;
+/// Pen which boundary sprites don't draw
+const BOUNDARY_PEN: u8 = 0x0f;
+/// Sprites painted in one go before giving up, as a list that loops would
+/// otherwise hang the machine
+const MAX_SPRITES: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SpriteType {
+    BackgroundShadow,
+    BackgroundNoCollide,
+    BoundaryShadow,
+    Boundary,
+    Normal,
+    NoCollide,
+    XorShadow,
+    Shadow,
+}
+
+impl SpriteType {
+    fn from_control(control: u8) -> Self {
+        match control & SPRCTL0_TYPE {
+            0 => Self::BackgroundShadow,
+            1 => Self::BackgroundNoCollide,
+            2 => Self::BoundaryShadow,
+            3 => Self::Boundary,
+            4 => Self::Normal,
+            5 => Self::NoCollide,
+            6 => Self::XorShadow,
+            _ => Self::Shadow,
+        }
+    }
+
+    /// If the highest collision number seen is written back to the SCB
+    fn deposits_collision(self) -> bool {
+        matches!(
+            self,
+            Self::BoundaryShadow | Self::Boundary | Self::Normal | Self::XorShadow | Self::Shadow
+        )
+    }
+}
+
+/// What bits of SPRSYS and SPRGO the sprite engine cares about
+#[derive(Debug, Clone, Copy)]
+pub(super) struct PaintOptions {
+    pub no_collide: bool,
+    pub vertical_stretch: bool,
+    pub everon: bool,
+}
+
+/// Suzy's view of RAM while it has the bus
+pub(super) struct Bus<'a> {
+    pub ram: &'a mut StandardMemory,
+    pub address_space: AddressSpaceId,
+}
+
+impl Bus<'_> {
+    fn peek(&self, address: u16) -> u8 {
+        let mut data = 0;
+
+        self.ram
+            .memory_read(
+                usize::from(address),
+                self.address_space,
+                false,
+                std::slice::from_mut(&mut data),
+            )
+            .unwrap();
+
+        data
+    }
+
+    fn peek_word(&self, address: u16) -> u16 {
+        u16::from_le_bytes([self.peek(address), self.peek(address.wrapping_add(1))])
+    }
+
+    fn poke(&mut self, address: u16, data: u8) {
+        self.ram
+            .memory_write(usize::from(address), self.address_space, &[data])
+            .unwrap();
+    }
+
+    fn read_nibble(&self, base: u16, x: i32) -> u8 {
+        let byte = self.peek(base.wrapping_add((x / 2) as u16));
+
+        if x % 2 == 0 { byte >> 4 } else { byte & 0xf }
+    }
+
+    fn write_nibble(&mut self, base: u16, x: i32, data: u8) {
+        let address = base.wrapping_add((x / 2) as u16);
+        let byte = self.peek(address);
+
+        let byte = if x % 2 == 0 {
+            (byte & 0x0f) | (data << 4)
+        } else {
+            (byte & 0xf0) | data
+        };
+
+        self.poke(address, byte);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PacketType {
+    /// SPRCTL1 says the whole line is literal pixels
+    TotallyLiteral,
+    Literal,
+    Packed,
+}
+
+/// Unpacks the pixels of one line of sprite data
+struct LineDecoder {
+    address: u16,
+    shift_register: u32,
+    shift_register_bits: u32,
+    /// The line offset puts a limit on how much data can be read
+    packet_bits_left: u32,
+    repeat_count: u32,
+    packet_type: Option<PacketType>,
+    /// [`None`] once the line has ended
+    pixel: Option<u8>,
+    bits_per_pixel: u32,
+}
+
+impl LineDecoder {
+    /// Returns the decoder along with the offset to the next line
+    fn new(bus: &Bus, address: u16, bits_per_pixel: u32, totally_literal: bool) -> (Self, u16) {
+        let mut decoder = Self {
+            address,
+            shift_register: 0,
+            shift_register_bits: 0,
+            packet_bits_left: u32::MAX,
+            repeat_count: 0,
+            packet_type: None,
+            pixel: Some(0),
+            bits_per_pixel,
+        };
+
+        let offset = decoder.bits(bus, 8);
+        decoder.packet_bits_left = offset.wrapping_sub(1).wrapping_mul(8);
+
+        if totally_literal {
+            decoder.packet_type = Some(PacketType::TotallyLiteral);
+            decoder.repeat_count = decoder.packet_bits_left / bits_per_pixel;
+        }
+
+        (decoder, offset as u16)
+    }
+
+    fn bits(&mut self, bus: &Bus, count: u32) -> u32 {
+        // The hardware refuses to read the very last bit of a line
+        if self.packet_bits_left <= count {
+            return 0;
+        }
+
+        if self.shift_register_bits < count {
+            for _ in 0..3 {
+                self.shift_register =
+                    (self.shift_register << 8) | u32::from(bus.peek(self.address));
+                self.address = self.address.wrapping_add(1);
+            }
+
+            self.shift_register_bits += 24;
+        }
+
+        let value =
+            (self.shift_register >> (self.shift_register_bits - count)) & ((1 << count) - 1);
+
+        self.shift_register_bits -= count;
+        self.packet_bits_left -= count;
+
+        value
+    }
+
+    fn pixel(&mut self, bus: &Bus, pens: &[u8; 16]) -> Option<u8> {
+        if self.repeat_count == 0 {
+            if self.packet_type != Some(PacketType::TotallyLiteral) {
+                self.packet_type = Some(if self.bits(bus, 1) != 0 {
+                    PacketType::Literal
+                } else {
+                    PacketType::Packed
+                });
+            }
+
+            match self.packet_type.unwrap() {
+                PacketType::TotallyLiteral => {
+                    self.pixel = None;
+                    return None;
+                }
+                PacketType::Literal => {
+                    self.repeat_count = self.bits(bus, 4) + 1;
+                }
+                PacketType::Packed => {
+                    // A packed packet with no pixels ends the line
+                    self.repeat_count = self.bits(bus, 4);
+
+                    self.pixel = if self.repeat_count == 0 {
+                        None
+                    } else {
+                        Some(pens[self.bits(bus, self.bits_per_pixel) as usize])
+                    };
+
+                    self.repeat_count += 1;
+                }
+            }
+        }
+
+        self.pixel?;
+        self.repeat_count -= 1;
+
+        match self.packet_type.unwrap() {
+            PacketType::TotallyLiteral => {
+                let pen = self.bits(bus, self.bits_per_pixel);
+
+                // A zero for the very last pixel ends the line early
+                self.pixel = if self.repeat_count == 0 && pen == 0 {
+                    None
+                } else {
+                    Some(pens[pen as usize])
+                };
+            }
+            PacketType::Literal => {
+                self.pixel = Some(pens[self.bits(bus, self.bits_per_pixel) as usize]);
+            }
+            PacketType::Packed => {}
+        }
+
+        self.pixel
+    }
+}
+
+/// Walks the sprite control block list and draws into video memory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(super) struct SpriteEngine {
+    pub registers: [u16; SPRITE_REGISTER_COUNT],
+    pub control0: u8,
+    pub control1: u8,
+    pub collision_control: u8,
+    /// Maps the pens sprite data uses to the pens drawn
+    pens: [u8; 16],
+    /// Highest collision number the current sprite overlapped
+    collision: u8,
+    line_video_address: u16,
+    line_collision_address: u16,
+}
+
+impl Default for SpriteEngine {
+    fn default() -> Self {
+        Self {
+            registers: [0; SPRITE_REGISTER_COUNT],
+            control0: 0,
+            control1: 0,
+            collision_control: 0,
+            pens: std::array::from_fn(|pen| pen as u8),
+            collision: 0,
+            line_video_address: 0,
+            line_collision_address: 0,
+        }
+    }
+}
+
+impl SpriteEngine {
+    pub(super) fn paint(&mut self, bus: &mut Bus, options: PaintOptions) {
+        for _ in 0..MAX_SPRITES {
+            // SCBs in the zero page end the list
+            if self.registers[SCBNEXT] & 0xff00 == 0 {
+                return;
+            }
+
+            self.paint_sprite(bus, options);
+        }
+
+        tracing::warn!("Sprite list did not end after {} sprites", MAX_SPRITES);
+    }
+
+    fn load_word(&mut self, bus: &Bus, register: usize) {
+        self.registers[register] = bus.peek_word(self.registers[TMPADR]);
+        self.registers[TMPADR] = self.registers[TMPADR].wrapping_add(2);
+    }
+
+    fn load_byte(&mut self, bus: &Bus) -> u8 {
+        let data = bus.peek(self.registers[TMPADR]);
+        self.registers[TMPADR] = self.registers[TMPADR].wrapping_add(1);
+
+        data
+    }
+
+    fn paint_sprite(&mut self, bus: &mut Bus, options: PaintOptions) {
+        self.registers[SCBADR] = self.registers[SCBNEXT];
+        self.registers[TMPADR] = self.registers[SCBNEXT];
+
+        self.control0 = self.load_byte(bus);
+        self.control1 = self.load_byte(bus);
+        self.collision_control = self.load_byte(bus);
+        self.load_word(bus, SCBNEXT);
+
+        if self.control1 & SPRCTL1_SKIP != 0 {
+            return;
+        }
+
+        self.load_word(bus, SPRDLINE);
+        self.load_word(bus, HPOSSTRT);
+        self.load_word(bus, VPOSSTRT);
+
+        // How much of the rest of the SCB is present
+        let reload_depth = (self.control1 >> 4) & 0b11;
+
+        if reload_depth >= 1 {
+            self.load_word(bus, SPRHSIZ);
+            self.load_word(bus, SPRVSIZ);
+        }
+
+        if reload_depth >= 2 {
+            self.load_word(bus, STRETCH);
+        }
+
+        if reload_depth >= 3 {
+            self.load_word(bus, TILT);
+        }
+
+        if self.control1 & SPRCTL1_KEEP_PALETTE == 0 {
+            for index in 0..8 {
+                let data = self.load_byte(bus);
+
+                self.pens[index * 2] = data >> 4;
+                self.pens[index * 2 + 1] = data & 0xf;
+            }
+        }
+
+        self.collision = 0;
+        let collides = self.collides(options);
+        let ever_on_screen = self.render(bus, options, reload_depth >= 2, reload_depth >= 3);
+        let collision_depository = self.registers[SCBADR].wrapping_add(self.registers[COLLOFF]);
+
+        if collides && self.sprite_type().deposits_collision() {
+            bus.poke(collision_depository, self.collision);
+        }
+
+        if options.everon {
+            let data = bus.peek(collision_depository);
+
+            bus.poke(
+                collision_depository,
+                if ever_on_screen {
+                    data & 0x7f
+                } else {
+                    data | 0x80
+                },
+            );
+        }
+    }
+
+    fn sprite_type(&self) -> SpriteType {
+        SpriteType::from_control(self.control0)
+    }
+
+    fn collides(&self, options: PaintOptions) -> bool {
+        self.collision_control & SPRCOLL_DONT_COLLIDE == 0 && !options.no_collide
+    }
+
+    fn bits_per_pixel(&self) -> u32 {
+        u32::from(self.control0 >> 6) + 1
+    }
+
+    fn start_line(&mut self, bus: &Bus, line: i32) -> (LineDecoder, u16) {
+        let line = line.clamp(0, SCREEN_HEIGHT as i32 - 1) as u16;
+        let line_offset = line * LINE_BYTES as u16;
+
+        self.line_video_address = self.registers[VIDBAS].wrapping_add(line_offset);
+        self.line_collision_address = self.registers[COLLBAS].wrapping_add(line_offset);
+
+        LineDecoder::new(
+            bus,
+            self.registers[SPRDLINE],
+            self.bits_per_pixel(),
+            self.control1 & SPRCTL1_LITERAL != 0,
+        )
+    }
+
+    /// Draws the sprite a quadrant at a time, returning if any of it was on screen
+    fn render(&mut self, bus: &mut Bus, options: PaintOptions, stretch: bool, tilt: bool) -> bool {
+        let screen_h_start = i32::from(self.registers[HOFF] as i16);
+        let screen_h_end = screen_h_start + SCREEN_WIDTH as i32;
+        let screen_v_start = i32::from(self.registers[VOFF] as i16);
+        let screen_v_end = screen_v_start + SCREEN_HEIGHT as i32;
+        let world_h_mid = screen_h_start + 0x8000 + SCREEN_WIDTH as i32 / 2;
+        let world_v_mid = screen_v_start + 0x8000 + SCREEN_HEIGHT as i32 / 2;
+
+        let h_start = i32::from(self.registers[HPOSSTRT] as i16);
+        let v_start = i32::from(self.registers[VPOSSTRT] as i16);
+
+        // Sprites starting off screen have each quadrant checked for if it
+        // could reach the screen
+        let superclip = !(screen_h_start..screen_h_end).contains(&h_start)
+            || !(screen_v_start..screen_v_end).contains(&v_start);
+
+        // Quadrants go SE NE NW SW
+        let mut quadrant = match (
+            self.control1 & SPRCTL1_START_LEFT != 0,
+            self.control1 & SPRCTL1_START_UP != 0,
+        ) {
+            (false, false) => 0,
+            (false, true) => 1,
+            (true, true) => 2,
+            (true, false) => 3,
+        };
+
+        let mut ever_on_screen = false;
+        let mut h_quadrant_sign = 1;
+        let mut v_quadrant_sign = 1;
+
+        for quadrant_index in 0..4 {
+            let sprite_h = i32::from(self.registers[HPOSSTRT]);
+            let sprite_v = i32::from(self.registers[VPOSSTRT]);
+
+            let mut h_sign = if quadrant == 0 || quadrant == 1 {
+                1
+            } else {
+                -1
+            };
+            let mut v_sign = if quadrant == 0 || quadrant == 3 {
+                1
+            } else {
+                -1
+            };
+
+            if self.control0 & SPRCTL0_VFLIP != 0 {
+                v_sign = -v_sign;
+            }
+
+            if self.control0 & SPRCTL0_HFLIP != 0 {
+                h_sign = -h_sign;
+            }
+
+            let visible = if superclip {
+                let mut flipped_quadrant = quadrant;
+
+                if self.control0 & SPRCTL0_VFLIP != 0 {
+                    flipped_quadrant = [1, 0, 3, 2][flipped_quadrant];
+                }
+
+                if self.control0 & SPRCTL0_HFLIP != 0 {
+                    flipped_quadrant = [3, 2, 1, 0][flipped_quadrant];
+                }
+
+                let left_reachable = sprite_h >= screen_h_start || sprite_h < world_h_mid;
+                let right_reachable = sprite_h < screen_h_end || sprite_h > world_h_mid;
+                let up_reachable = sprite_v >= screen_v_start || sprite_v < world_v_mid;
+                let down_reachable = sprite_v < screen_v_end || sprite_v > world_v_mid;
+
+                match flipped_quadrant {
+                    3 => left_reachable && down_reachable,
+                    2 => left_reachable && up_reachable,
+                    1 => right_reachable && up_reachable,
+                    _ => right_reachable && down_reachable,
+                }
+            } else {
+                true
+            };
+
+            let sprite_ended = if visible {
+                // Quadrants drawn the other way from the first are nudged over by a
+                // pixel, so they don't overlap on the axis
+                if quadrant_index == 0 {
+                    h_quadrant_sign = h_sign;
+                    v_quadrant_sign = v_sign;
+                }
+
+                self.render_quadrant(
+                    bus,
+                    options,
+                    QuadrantLayout {
+                        h_sign,
+                        v_sign,
+                        h_nudge: if h_sign == h_quadrant_sign { 0 } else { h_sign },
+                        v_nudge: if v_sign == v_quadrant_sign { 0 } else { v_sign },
+                        screen_h_start,
+                        screen_v_start,
+                        stretch,
+                        tilt,
+                    },
+                    &mut ever_on_screen,
+                )
+            } else {
+                self.skip_quadrant(bus)
+            };
+
+            if sprite_ended {
+                break;
+            }
+
+            quadrant = (quadrant + 1) % 4;
+        }
+
+        ever_on_screen
+    }
+
+    /// Returns true if the sprite data ended rather than just the quadrant
+    fn skip_quadrant(&mut self, bus: &Bus) -> bool {
+        loop {
+            let (_, offset) = self.start_line(bus, 0);
+            self.registers[SPRDLINE] = self.registers[SPRDLINE].wrapping_add(offset);
+
+            match offset {
+                0 => return true,
+                1 => return false,
+                _ => {}
+            }
+        }
+    }
+
+    /// Returns true if the sprite data ended rather than just the quadrant
+    fn render_quadrant(
+        &mut self,
+        bus: &mut Bus,
+        options: PaintOptions,
+        layout: QuadrantLayout,
+        ever_on_screen: &mut bool,
+    ) -> bool {
+        let mut v_offset =
+            i32::from(self.registers[VPOSSTRT] as i16) - layout.screen_v_start + layout.v_nudge;
+
+        self.registers[TILTACUM] = 0;
+        self.registers[VSIZACUM] = if layout.v_sign == 1 {
+            self.registers[VSIZOFF]
+        } else {
+            0
+        };
+
+        loop {
+            // Vertical scaling
+            self.registers[VSIZACUM] =
+                self.registers[VSIZACUM].wrapping_add(self.registers[SPRVSIZ]);
+            let pixel_height = self.registers[VSIZACUM] >> 8;
+            self.registers[VSIZACUM] &= 0xff;
+
+            let (_, offset) = self.start_line(bus, 0);
+            self.registers[SPRDOFF] = offset;
+
+            match offset {
+                0 => return true,
+                1 => {
+                    self.registers[SPRDLINE] = self.registers[SPRDLINE].wrapping_add(offset);
+                    return false;
+                }
+                _ => {}
+            }
+
+            for _ in 0..pixel_height {
+                // Give up on the quadrant once it has left the screen
+                if (layout.v_sign == 1 && v_offset >= SCREEN_HEIGHT as i32)
+                    || (layout.v_sign == -1 && v_offset < 0)
+                {
+                    break;
+                }
+
+                if (0..SCREEN_HEIGHT as i32).contains(&v_offset) {
+                    self.render_line(bus, options, &layout, v_offset, ever_on_screen);
+                }
+
+                v_offset += layout.v_sign;
+
+                if layout.stretch {
+                    self.registers[SPRHSIZ] =
+                        self.registers[SPRHSIZ].wrapping_add(self.registers[STRETCH]);
+                }
+
+                if layout.tilt {
+                    self.registers[TILTACUM] =
+                        self.registers[TILTACUM].wrapping_add(self.registers[TILT]);
+                }
+            }
+
+            if options.vertical_stretch {
+                self.registers[SPRVSIZ] = self.registers[SPRVSIZ]
+                    .wrapping_add(self.registers[STRETCH].wrapping_mul(pixel_height));
+            }
+
+            self.registers[SPRDLINE] = self.registers[SPRDLINE].wrapping_add(offset);
+        }
+    }
+
+    fn render_line(
+        &mut self,
+        bus: &mut Bus,
+        options: PaintOptions,
+        layout: &QuadrantLayout,
+        v_offset: i32,
+        ever_on_screen: &mut bool,
+    ) {
+        // Tilt shifts the start of each line over
+        self.registers[HPOSSTRT] =
+            self.registers[HPOSSTRT].wrapping_add(((self.registers[TILTACUM] as i16) >> 8) as u16);
+        self.registers[TILTACUM] &= 0xff;
+
+        let mut h_offset =
+            i32::from(self.registers[HPOSSTRT] as i16) - layout.screen_h_start + layout.h_nudge;
+        let mut h_size_accumulator = if layout.h_sign == 1 {
+            self.registers[HSIZOFF]
+        } else {
+            0
+        };
+
+        let (mut decoder, _) = self.start_line(bus, v_offset);
+        let mut on_screen = false;
+
+        while let Some(pen) = decoder.pixel(bus, &self.pens) {
+            // Horizontal scaling
+            h_size_accumulator = h_size_accumulator.wrapping_add(self.registers[SPRHSIZ]);
+            let pixel_width = h_size_accumulator >> 8;
+            h_size_accumulator &= 0xff;
+
+            for _ in 0..pixel_width {
+                if (0..SCREEN_WIDTH as i32).contains(&h_offset) {
+                    self.process_pixel(bus, options, h_offset, pen);
+                    on_screen = true;
+                    *ever_on_screen = true;
+                } else if on_screen {
+                    break;
+                }
+
+                h_offset += layout.h_sign;
+            }
+        }
+    }
+
+    fn process_pixel(&mut self, bus: &mut Bus, options: PaintOptions, x: i32, pen: u8) {
+        let collides = self.collides(options);
+
+        let (draw, collide) = match self.sprite_type() {
+            SpriteType::BackgroundShadow => {
+                // Background sprites overwrite instead of checking for collisions
+                if collides && pen != SHADOW_PEN {
+                    bus.write_nibble(
+                        self.line_collision_address,
+                        x,
+                        self.collision_control & SPRCOLL_NUMBER,
+                    );
+                }
+
+                (true, false)
+            }
+            SpriteType::BackgroundNoCollide => (true, false),
+            SpriteType::NoCollide => (pen != 0, false),
+            SpriteType::Boundary => (pen != 0 && pen != BOUNDARY_PEN, pen != 0),
+            SpriteType::Normal => (pen != 0, pen != 0),
+            SpriteType::BoundaryShadow => (
+                pen != 0 && pen != SHADOW_PEN && pen != BOUNDARY_PEN,
+                pen != 0 && pen != SHADOW_PEN,
+            ),
+            SpriteType::Shadow | SpriteType::XorShadow => (pen != 0, pen != 0 && pen != SHADOW_PEN),
+        };
+
+        if draw {
+            let pen = if self.sprite_type() == SpriteType::XorShadow {
+                bus.read_nibble(self.line_video_address, x) ^ pen
+            } else {
+                pen
+            };
+
+            bus.write_nibble(self.line_video_address, x, pen);
+        }
+
+        if collide && collides {
+            self.collision = self
+                .collision
+                .max(bus.read_nibble(self.line_collision_address, x));
+
+            bus.write_nibble(
+                self.line_collision_address,
+                x,
+                self.collision_control & SPRCOLL_NUMBER,
+            );
+        }
+    }
+}
+
+/// Which way a quadrant is drawn
+struct QuadrantLayout {
+    h_sign: i32,
+    v_sign: i32,
+    h_nudge: i32,
+    v_nudge: i32,
+    screen_h_start: i32,
+    screen_v_start: i32,
+    stretch: bool,
+    tilt: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use fluxemu_definition_misc::memory::standard::{
+        StandardMemoryConfig, StandardMemoryInitialContents,
+    };
+    use fluxemu_runtime::machine::Machine;
+    use rangemap::RangeInclusiveMap;
+
+    use super::*;
+
+    const VIDEO: u16 = 0x8000;
+    const COLLISION: u16 = 0xa000;
+    /// Collision depositories sit right after the palette
+    const COLLISION_DEPOSITORY: u16 = 0x17;
+
+    /// A normal 4 bit sprite, which is a run of two pixels on a single line
+    /// before any scaling
+    struct Sprite {
+        address: u16,
+        position: (u16, u16),
+        size: u16,
+        pen: u8,
+        collision_number: u8,
+        skip: bool,
+    }
+
+    impl Sprite {
+        fn new(address: u16, position: (u16, u16), pen: u8) -> Self {
+            Self {
+                address,
+                position,
+                size: 0x100,
+                pen,
+                collision_number: 0,
+                skip: false,
+            }
+        }
+
+        fn scb(&self, next: u16) -> Vec<u8> {
+            // Reload depth 1 so the size is part of the SCB
+            let control1 = 0x10 | if self.skip { SPRCTL1_SKIP } else { 0 };
+            let mut scb = vec![0xc4, control1, self.collision_number];
+
+            for word in [
+                next,
+                self.address + 0x20,
+                self.position.0,
+                self.position.1,
+                self.size,
+                self.size,
+            ] {
+                scb.extend(word.to_le_bytes());
+            }
+
+            // Every pen maps to itself
+            scb.extend([0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef]);
+            scb.resize(0x20, 0);
+
+            // A packed packet of two pixels then an empty one ending the line,
+            // followed by the end of the sprite
+            scb.extend([
+                0x04,
+                0x08 | (self.pen >> 1),
+                (self.pen & 1) << 7,
+                0x00,
+                0x00,
+            ]);
+
+            scb
+        }
+    }
+
+    /// Paints the sprites as one list in order, handing back all of RAM
+    fn paint(sprites: &[Sprite]) -> Vec<u8> {
+        let (machine, address_space) = Machine::build_test_minimal().insert_address_space(16);
+
+        let (machine, ram) = machine.insert_component(
+            "ram",
+            StandardMemoryConfig {
+                readable: true,
+                writable: true,
+                assigned_range: 0x0000..=0xffff,
+                assigned_address_space: address_space,
+                initial_contents: RangeInclusiveMap::from_iter([(
+                    0x0000..=0xffff,
+                    StandardMemoryInitialContents::Value(0),
+                )]),
+                sram: false,
+            },
+        );
+        let machine = machine.build(());
+
+        machine
+            .interact_mut::<StandardMemory, _>(&ram, |ram| {
+                let mut bus = Bus { ram, address_space };
+
+                let next_addresses = sprites.iter().skip(1).map(|sprite| sprite.address);
+
+                for (sprite, next) in sprites.iter().zip(next_addresses.chain([0])) {
+                    for (offset, byte) in sprite.scb(next).into_iter().enumerate() {
+                        bus.poke(sprite.address + offset as u16, byte);
+                    }
+                }
+
+                let mut engine = SpriteEngine::default();
+                engine.registers[VIDBAS] = VIDEO;
+                engine.registers[COLLBAS] = COLLISION;
+                engine.registers[COLLOFF] = COLLISION_DEPOSITORY;
+                engine.registers[SCBNEXT] = sprites[0].address;
+
+                engine.paint(
+                    &mut bus,
+                    PaintOptions {
+                        no_collide: false,
+                        vertical_stretch: false,
+                        everon: false,
+                    },
+                );
+
+                (0..=u16::MAX)
+                    .map(|address| bus.peek(address))
+                    .collect::<Vec<_>>()
+            })
+            .unwrap()
+    }
+
+    fn pixel(ram: &[u8], base: u16, x: usize, y: usize) -> u8 {
+        let byte = ram[usize::from(base) + y * LINE_BYTES + x / 2];
+
+        if x.is_multiple_of(2) {
+            byte >> 4
+        } else {
+            byte & 0xf
+        }
+    }
+
+    #[test]
+    fn chained_scbs() {
+        let ram = paint(&[
+            Sprite::new(0x1000, (10, 5), 1),
+            Sprite {
+                skip: true,
+                ..Sprite::new(0x1100, (30, 7), 3)
+            },
+            Sprite::new(0x1200, (20, 6), 2),
+        ]);
+
+        assert_eq!(
+            [10, 11, 12].map(|x| pixel(&ram, VIDEO, x, 5)),
+            [1, 1, 0],
+            "first sprite"
+        );
+        assert_eq!(
+            [30, 31].map(|x| pixel(&ram, VIDEO, x, 7)),
+            [0, 0],
+            "skipped sprite"
+        );
+        assert_eq!(
+            [20, 21, 22].map(|x| pixel(&ram, VIDEO, x, 6)),
+            [2, 2, 0],
+            "last sprite"
+        );
+    }
+
+    #[test]
+    fn scaling() {
+        let ram = paint(&[Sprite {
+            size: 0x200,
+            ..Sprite::new(0x1000, (10, 5), 1)
+        }]);
+
+        // Doubled both ways the two pixels cover four across two lines
+        for y in [5, 6] {
+            assert_eq!(
+                (9..=14)
+                    .map(|x| pixel(&ram, VIDEO, x, y))
+                    .collect::<Vec<_>>(),
+                [0, 1, 1, 1, 1, 0],
+                "line {y}"
+            );
+        }
+
+        assert_eq!(pixel(&ram, VIDEO, 10, 7), 0);
+    }
+
+    #[test]
+    fn collision_buffer() {
+        let ram = paint(&[
+            Sprite {
+                collision_number: 3,
+                ..Sprite::new(0x1000, (10, 5), 1)
+            },
+            Sprite {
+                collision_number: 5,
+                ..Sprite::new(0x1100, (11, 5), 2)
+            },
+        ]);
+
+        assert_eq!([10, 11, 12].map(|x| pixel(&ram, VIDEO, x, 5)), [1, 2, 2]);
+        // The later sprite marks the collision buffer over the earlier one
+        assert_eq!(
+            [10, 11, 12].map(|x| pixel(&ram, COLLISION, x, 5)),
+            [3, 5, 5]
+        );
+        // Only the later sprite overlapped anything
+        assert_eq!(ram[usize::from(0x1000 + COLLISION_DEPOSITORY)], 0);
+        assert_eq!(ram[usize::from(0x1100 + COLLISION_DEPOSITORY)], 3);
+    }
+}
@@ -1,6 +1,7 @@
 use std::io::{Read, Write};
 
-use fluxemu_audio::{FrameIterator, SquareWave};
+use bitvec::{order::Msb0, view::BitView};
+use fluxemu_audio::FrameIterator;
 use fluxemu_runtime::{
     component::{Component, ComponentConfig, ComponentVersion, SampleSource},
     machine::builder::{ComponentBuilder, SchedulerParticipation},
@@ -11,13 +12,27 @@ use fluxemu_runtime::{
 use nalgebra::SVector;
 use ringbuffer::{AllocRingBuffer, RingBuffer};
 
+/// Bytes in an XO-CHIP audio pattern, which is played a bit at a time
+pub const PATTERN_SIZE: usize = 16;
+/// A square wave that sounds close to the beep of interpreters before XO-CHIP,
+/// at the default pitch
+const DEFAULT_PATTERN: [u8; PATTERN_SIZE] = [0xf0; PATTERN_SIZE];
+/// The pitch where the pattern plays at [BASE_PLAYBACK_RATE]
+const DEFAULT_PITCH: u8 = 64;
+/// Pattern bits per second at the default pitch
+const BASE_PLAYBACK_RATE: f32 = 4000.0;
+const VOLUME: f32 = 0.5;
+
 #[derive(Debug)]
 pub struct Chip8Audio {
     // The CPU will set this according to what the program wants
     timer: u8,
+    pattern: [u8; PATTERN_SIZE],
+    pitch: u8,
+    /// Where in the pattern playback is, in bits
+    pattern_position: f32,
     buffer: AllocRingBuffer<SVector<f32, 1>>,
-    wave_generator: SquareWave<f32, 1>,
-    processor_frequency: Frequency,
+    sample_rate: Frequency,
     timer_accumulator: Period,
 }
 
@@ -25,6 +40,29 @@ impl Chip8Audio {
     pub fn set(&mut self, value: u8) {
         self.timer = value;
     }
+
+    pub fn set_pattern(&mut self, pattern: [u8; PATTERN_SIZE]) {
+        self.pattern = pattern;
+    }
+
+    pub fn set_pitch(&mut self, pitch: u8) {
+        self.pitch = pitch;
+    }
+
+    #[cfg(test)]
+    pub(crate) fn pattern(&self) -> [u8; PATTERN_SIZE] {
+        self.pattern
+    }
+
+    #[cfg(test)]
+    pub(crate) fn pitch(&self) -> u8 {
+        self.pitch
+    }
+
+    /// Every 48 steps of pitch is an octave
+    fn playback_rate(&self) -> f32 {
+        BASE_PLAYBACK_RATE * 2.0f32.powf((f32::from(self.pitch) - f32::from(DEFAULT_PITCH)) / 48.0)
+    }
 }
 
 impl Component for Chip8Audio {
@@ -39,22 +77,28 @@ impl Component for Chip8Audio {
     ) -> Result<(), Box<dyn std::error::Error>> {
         assert_eq!(version, 0);
         let timer = std::array::from_mut(&mut self.timer);
+        let pitch = std::array::from_mut(&mut self.pitch);
 
         reader.read_exact(timer)?;
+        reader.read_exact(pitch)?;
+        reader.read_exact(&mut self.pattern)?;
 
         Ok(())
     }
 
     fn store_snapshot(&self, mut writer: Box<dyn Write>) -> Result<(), Box<dyn std::error::Error>> {
         let timer = std::array::from_ref(&self.timer);
+        let pitch = std::array::from_ref(&self.pitch);
 
         writer.write_all(timer)?;
+        writer.write_all(pitch)?;
+        writer.write_all(&self.pattern)?;
 
         Ok(())
     }
 
     fn get_audio_channel(&mut self, _audio_output_path: &FluxEmuPath) -> SampleSource<'_> {
-        let sample_rate = self.processor_frequency.to_num();
+        let sample_rate = self.sample_rate.to_num();
 
         SampleSource {
             source: Box::new(self.buffer.drain().repeat_last_frame()),
@@ -64,13 +108,23 @@ impl Component for Chip8Audio {
 
     fn synchronize(&mut self, mut context: SynchronizationContext) {
         let timer_period = Period::from_num(60).recip();
+        let pattern_step = self.playback_rate() / self.sample_rate.to_num::<f32>();
 
-        for _ in context.allocate(self.processor_frequency.recip(), None) {
-            if self.timer != 0 {
-                self.buffer.enqueue(self.wave_generator.next().unwrap());
-            }
+        for _ in context.allocate(self.sample_rate.recip(), None) {
+            let sample = if self.timer != 0 {
+                let bit = self.pattern.view_bits::<Msb0>()[self.pattern_position as usize];
+
+                self.pattern_position =
+                    (self.pattern_position + pattern_step) % (PATTERN_SIZE * 8) as f32;
+
+                if bit { VOLUME } else { -VOLUME }
+            } else {
+                0.0
+            };
+
+            self.buffer.enqueue(SVector::from([sample]));
 
-            self.timer_accumulator += self.processor_frequency.recip();
+            self.timer_accumulator += self.sample_rate.recip();
             while self.timer_accumulator >= timer_period {
                 self.timer = self.timer.saturating_sub(1);
                 self.timer_accumulator -= timer_period;
@@ -79,13 +133,13 @@ impl Component for Chip8Audio {
     }
 
     fn needs_work(&self, delta: Period) -> bool {
-        delta >= self.processor_frequency.recip()
+        delta >= self.sample_rate.recip()
     }
 }
 
 #[derive(Debug)]
 pub struct Chip8AudioConfig {
-    pub processor_frequency: Frequency,
+    pub sample_rate: Frequency,
 }
 
 impl<P: Platform> ComponentConfig<P> for Chip8AudioConfig {
@@ -101,9 +155,11 @@ impl<P: Platform> ComponentConfig<P> for Chip8AudioConfig {
 
         Ok(Chip8Audio {
             timer: 0,
-            buffer: AllocRingBuffer::new(440),
-            wave_generator: SquareWave::new(440.0, self.processor_frequency.to_num(), 0.5),
-            processor_frequency: self.processor_frequency,
+            pattern: DEFAULT_PATTERN,
+            pitch: DEFAULT_PITCH,
+            pattern_position: 0.0,
+            buffer: AllocRingBuffer::new(4096),
+            sample_rate: self.sample_rate,
             timer_accumulator: Period::ZERO,
         })
     }
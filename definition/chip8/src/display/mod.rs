@@ -13,10 +13,10 @@ use fluxemu_runtime::{
     platform::Platform,
     scheduler::{Period, SynchronizationContext},
 };
-use nalgebra::{DMatrix, DMatrixView, DMatrixViewMut, Point2, Vector2};
+use nalgebra::{DMatrix, DMatrixViewMut, Point2, Vector2};
 use palette::{
     Srgba,
-    named::{BLACK, WHITE},
+    named::{BLACK, DARKGRAY, LIGHTGRAY, WHITE},
 };
 use serde::{Deserialize, Serialize};
 
@@ -26,10 +26,14 @@ mod vulkan;
 
 const LORES: Vector2<u8> = Vector2::new(64, 32);
 const HIRES: Vector2<u8> = Vector2::new(128, 64);
+/// XO-CHIP has two bitplanes, which programs before it only ever draw on the
+/// first of
+pub const PLANE_COUNT: usize = 2;
 
 #[derive(Debug, Serialize, Deserialize)]
 struct Snapshot {
-    screen_buffer: DMatrix<Srgba<u8>>,
+    planes: [DMatrix<bool>; PLANE_COUNT],
+    selected_planes: u8,
     vsync_occurred: bool,
     hires: bool,
 }
@@ -40,6 +44,10 @@ pub struct Chip8Display<R: SupportedGraphicsApiChip8Display> {
     /// The cpu reads this to see if it can continue execution post draw call
     pub vsync_occurred: bool,
     hires: bool,
+    /// Bitmask of the planes that drawing, clearing and scrolling touch
+    selected_planes: u8,
+    /// What is actually on the screen, at the resolution of the framebuffer
+    planes: [DMatrix<bool>; PLANE_COUNT],
     config: Chip8DisplayConfig,
 }
 
@@ -49,109 +57,154 @@ impl<R: SupportedGraphicsApiChip8Display> Chip8Display<R> {
             self.clear_display();
         }
 
-        self.backend
-            .as_mut()
-            .unwrap()
-            .resize(if is_hires { HIRES } else { LORES }.cast());
         self.hires = is_hires;
+        let framebuffer_size = self.framebuffer_size();
+
+        for plane in &mut self.planes {
+            plane.resize_mut(framebuffer_size.x, framebuffer_size.y, false);
+        }
+
+        self.backend.as_mut().unwrap().resize(framebuffer_size);
     }
 
-    pub fn draw_supersized_sprite(&mut self, position: Point2<u8>, sprite: [u8; 32]) -> bool {
-        tracing::debug!(
-            "Drawing sprite at position {} of dimensions 16x16",
-            position,
-        );
+    pub fn select_planes(&mut self, mask: u8) {
+        self.selected_planes = mask & ((1 << PLANE_COUNT) - 1);
+    }
 
-        let screen_size = if self.hires { HIRES } else { LORES };
-        let position = Point2::new(position.x % screen_size.x, position.y % screen_size.y).cast();
-        self.vsync_occurred = false;
+    /// What is on each plane, at the resolution of the framebuffer
+    #[cfg(test)]
+    pub(crate) fn planes(&self) -> &[DMatrix<bool>; PLANE_COUNT] {
+        &self.planes
+    }
+
+    pub fn selected_plane_count(&self) -> usize {
+        self.selected_planes.count_ones() as usize
+    }
+
+    fn selected_planes_mut(&mut self) -> impl Iterator<Item = &mut DMatrix<bool>> {
+        let selected_planes = self.selected_planes;
 
+        self.planes
+            .iter_mut()
+            .enumerate()
+            .filter(move |(index, _)| selected_planes & (1 << index) != 0)
+            .map(|(_, plane)| plane)
+    }
+
+    /// The resolution programs draw in
+    fn screen_size(&self) -> Vector2<u8> {
+        if self.hires { HIRES } else { LORES }
+    }
+
+    /// How many framebuffer pixels wide and tall a single screen pixel is
+    fn pixel_scale(&self) -> usize {
+        // The original SCHIP kept the HP48 display at full resolution and drew
+        // lores pixels as 2x2 blocks
+        if self.config.half_pixel_lores_scroll && !self.hires {
+            2
+        } else {
+            1
+        }
+    }
+
+    fn framebuffer_size(&self) -> Vector2<usize> {
+        self.screen_size().cast() * self.pixel_scale()
+    }
+
+    /// XORs a pixel onto a plane, returning if it turned one off
+    fn flip_pixel(plane: &mut DMatrix<bool>, position: Point2<usize>, scale: usize) -> bool {
         let mut hit_detection = false;
 
-        self.backend
-            .as_mut()
-            .unwrap()
-            .interact_staging_buffer_mut(|mut framebuffer| {
-                for (y, sprite_row) in sprite.view_bits::<Msb0>().chunks(16).enumerate() {
-                    for (x, sprite_pixel) in sprite_row.iter().enumerate() {
-                        let position = position + Vector2::new(x, y);
-
-                        if position.x >= screen_size.x as usize
-                            || position.y >= screen_size.y as usize
-                        {
-                            continue;
-                        }
-
-                        let old_sprite_pixel =
-                            framebuffer[(position.x, position.y)] != BLACK.into();
-
-                        if *sprite_pixel && old_sprite_pixel {
-                            hit_detection = true;
-                        }
-
-                        framebuffer[(position.x, position.y)] = if *sprite_pixel ^ old_sprite_pixel
-                        {
-                            WHITE
-                        } else {
-                            BLACK
-                        }
-                        .into();
-                    }
-                }
-            });
+        for y in 0..scale {
+            for x in 0..scale {
+                let pixel = &mut plane[(position.x * scale + x, position.y * scale + y)];
+
+                hit_detection |= *pixel;
+                *pixel = !*pixel;
+            }
+        }
 
         hit_detection
     }
 
-    pub fn draw_sprite(&mut self, position: Point2<u8>, sprite: &[u8]) -> bool {
+    /// Moves the selected planes by an amount of framebuffer pixels, filling
+    /// in what was uncovered
+    ///
+    /// Lores pixels are two framebuffer pixels across with half pixel
+    /// scrolling, which is where the original SCHIP's half pixel scrolls come
+    /// from
+    pub fn scroll(&mut self, offset: Vector2<isize>) {
+        tracing::debug!("Scrolling display by {}", offset);
+
+        for plane in self.selected_planes_mut() {
+            let original = plane.clone();
+
+            for y in 0..plane.ncols() {
+                for x in 0..plane.nrows() {
+                    let source = x
+                        .checked_add_signed(-offset.x)
+                        .zip(y.checked_add_signed(-offset.y));
+
+                    plane[(x, y)] = source
+                        .and_then(|source| original.get(source).copied())
+                        .unwrap_or(false);
+                }
+            }
+        }
+    }
+
+    /// Draws a sprite 8 or 16 pixels wide on each selected plane, with the
+    /// data for each plane one after the other
+    pub fn draw_sprite(
+        &mut self,
+        position: Point2<u8>,
+        sprite: &[u8],
+        width: usize,
+        clip: bool,
+    ) -> bool {
+        let plane_count = self.selected_plane_count();
+
+        if sprite.is_empty() || plane_count == 0 {
+            return false;
+        }
+
         tracing::debug!(
-            "Drawing sprite at position {} of dimensions 8x{}",
+            "Drawing sprite at position {} of dimensions {}x{}",
             position,
-            sprite.len()
+            width,
+            sprite.len() * 8 / width / plane_count
         );
 
-        let screen_size = if self.hires { HIRES } else { LORES };
+        let screen_size = self.screen_size();
+        let scale = self.pixel_scale();
+        let position: Point2<usize> =
+            Point2::new(position.x % screen_size.x, position.y % screen_size.y).cast();
         self.vsync_occurred = false;
 
-        let position = Point2::new(position.x % screen_size.x, position.y % screen_size.y).cast();
-        let dimensions = Vector2::new(8, sprite.len());
-
-        if dimensions.min() == 0 {
-            return false;
-        }
         let mut hit_detection = false;
+        let plane_sprites = sprite.chunks(sprite.len() / plane_count);
 
-        self.backend
-            .as_mut()
-            .unwrap()
-            .interact_staging_buffer_mut(|mut framebuffer| {
-                for (y, sprite_row) in sprite.view_bits::<Msb0>().chunks(8).enumerate() {
-                    for (x, sprite_pixel) in sprite_row.iter().enumerate() {
-                        let position = position + Vector2::new(x, y);
-
-                        if position.x >= screen_size.x as usize
-                            || position.y >= screen_size.y as usize
-                        {
-                            continue;
-                        }
-
-                        let old_sprite_pixel =
-                            framebuffer[(position.x, position.y)] != BLACK.into();
-
-                        if *sprite_pixel && old_sprite_pixel {
-                            hit_detection = true;
-                        }
-
-                        framebuffer[(position.x, position.y)] = if *sprite_pixel ^ old_sprite_pixel
-                        {
-                            WHITE
-                        } else {
-                            BLACK
-                        }
-                        .into();
+        for (plane, sprite) in self.selected_planes_mut().zip(plane_sprites) {
+            for (y, sprite_row) in sprite.view_bits::<Msb0>().chunks(width).enumerate() {
+                for (x, sprite_pixel) in sprite_row.iter().enumerate() {
+                    let mut position = position + Vector2::new(x, y);
+
+                    if !clip {
+                        position.x %= screen_size.x as usize;
+                        position.y %= screen_size.y as usize;
+                    }
+
+                    if !*sprite_pixel
+                        || position.x >= screen_size.x as usize
+                        || position.y >= screen_size.y as usize
+                    {
+                        continue;
                     }
+
+                    hit_detection |= Self::flip_pixel(plane, position, scale);
                 }
-            });
+            }
+        }
 
         hit_detection
     }
@@ -159,11 +212,30 @@ impl<R: SupportedGraphicsApiChip8Display> Chip8Display<R> {
     pub fn clear_display(&mut self) {
         tracing::trace!("Clearing display");
 
+        for plane in self.selected_planes_mut() {
+            plane.fill(false);
+        }
+    }
+
+    /// Turns the planes into colors for the frontend
+    fn render(&mut self) {
+        let palette = self.config.palette;
+        let planes = &self.planes;
+
         self.backend
             .as_mut()
             .unwrap()
             .interact_staging_buffer_mut(|mut framebuffer| {
-                framebuffer.fill(BLACK.into());
+                for (index, pixel) in framebuffer.iter_mut().enumerate() {
+                    let color = planes
+                        .iter()
+                        .enumerate()
+                        .fold(0, |color, (plane_index, plane)| {
+                            color | (usize::from(plane[index]) << plane_index)
+                        });
+
+                    *pixel = palette[color];
+                }
             });
     }
 }
@@ -178,33 +250,18 @@ impl<R: SupportedGraphicsApiChip8Display> Component for Chip8Display<R> {
         let snapshot: Snapshot = rmp_serde::decode::from_read(reader)?;
 
         self.set_hires(snapshot.hires);
-
-        self.backend
-            .as_mut()
-            .unwrap()
-            .interact_staging_buffer_mut(|mut framebuffer| {
-                framebuffer.copy_from(&snapshot.screen_buffer);
-            });
-
+        self.planes = snapshot.planes;
+        self.selected_planes = snapshot.selected_planes;
         self.vsync_occurred = snapshot.vsync_occurred;
+        self.render();
 
         Ok(())
     }
 
     fn store_snapshot(&self, mut writer: Box<dyn Write>) -> Result<(), Box<dyn std::error::Error>> {
-        let screen_size = if self.hires { HIRES } else { LORES }.cast();
-
-        let mut screen_buffer = DMatrix::from_element(screen_size.x, screen_size.y, BLACK.into());
-
-        self.backend
-            .as_ref()
-            .unwrap()
-            .interact_staging_buffer(|framebuffer| {
-                screen_buffer.copy_from(&framebuffer);
-            });
-
         let snapshot = Snapshot {
-            screen_buffer,
+            planes: self.planes.clone(),
+            selected_planes: self.selected_planes,
             hires: self.hires,
             vsync_occurred: self.vsync_occurred,
         };
@@ -228,6 +285,7 @@ impl<R: SupportedGraphicsApiChip8Display> Component for Chip8Display<R> {
         }
 
         if commit_staging_buffer {
+            self.render();
             self.backend.as_mut().unwrap().commit_staging_buffer();
         }
     }
@@ -242,15 +300,35 @@ pub(crate) trait Chip8DisplayBackend: Send + Sync + Debug + 'static {
 
     fn new(initialization_data: <Self::GraphicsApi as GraphicsApi>::InitializationData) -> Self;
     fn resize(&mut self, resolution: Vector2<usize>);
-    fn interact_staging_buffer(&self, callback: impl FnOnce(DMatrixView<'_, Srgba<u8>>));
     fn interact_staging_buffer_mut(&mut self, callback: impl FnOnce(DMatrixViewMut<'_, Srgba<u8>>));
     fn commit_staging_buffer(&mut self);
     fn access_framebuffer(&mut self) -> &<Self::GraphicsApi as GraphicsApi>::FramebufferTexture;
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct Chip8DisplayConfig {
     pub clear_on_resolution_change: bool,
+    /// Scroll lores by half pixels like the original SCHIP, instead of by
+    /// whole ones like later interpreters
+    pub half_pixel_lores_scroll: bool,
+    /// The color for each combination of planes, with the first plane in the
+    /// lowest bit
+    pub palette: [Srgba<u8>; 1 << PLANE_COUNT],
+}
+
+impl Default for Chip8DisplayConfig {
+    fn default() -> Self {
+        Self {
+            clear_on_resolution_change: false,
+            half_pixel_lores_scroll: false,
+            palette: [
+                BLACK.into(),
+                WHITE.into(),
+                DARKGRAY.into(),
+                LIGHTGRAY.into(),
+            ],
+        }
+    }
 }
 
 impl<P: Platform<GraphicsApi: SupportedGraphicsApiChip8Display>> ComponentConfig<P>
@@ -262,6 +340,12 @@ impl<P: Platform<GraphicsApi: SupportedGraphicsApiChip8Display>> ComponentConfig
         component.backend = Some(Chip8DisplayBackend::new(
             data.component_graphics_initialization_data.clone(),
         ));
+
+        // Backends start out at the lores resolution
+        if component.config.half_pixel_lores_scroll {
+            let framebuffer_size = component.framebuffer_size();
+            component.backend.as_mut().unwrap().resize(framebuffer_size);
+        }
     }
 
     fn build_component(
@@ -272,9 +356,18 @@ impl<P: Platform<GraphicsApi: SupportedGraphicsApiChip8Display>> ComponentConfig
             .set_scheduler_participation(SchedulerParticipation::OnDemand)
             .insert_display("display");
 
+        let framebuffer_size =
+            LORES.cast::<usize>() * if self.half_pixel_lores_scroll { 2 } else { 1 };
+
         Ok(Chip8Display {
             backend: None,
             hires: false,
+            // Only the first plane is selected, so programs that do not know
+            // about planes do not have to
+            selected_planes: 0b01,
+            planes: std::array::from_fn(|_| {
+                DMatrix::from_element(framebuffer_size.x, framebuffer_size.y, false)
+            }),
             vsync_occurred: false,
             config: self,
         })
@@ -284,3 +377,100 @@ impl<P: Platform<GraphicsApi: SupportedGraphicsApiChip8Display>> ComponentConfig
 pub(crate) trait SupportedGraphicsApiChip8Display: GraphicsApi {
     type Backend: Chip8DisplayBackend<GraphicsApi = Self>;
 }
+
+#[cfg(test)]
+mod tests {
+    use fluxemu_runtime::graphics::software::Software;
+
+    use super::*;
+
+    fn display(half_pixel_lores_scroll: bool, hires: bool) -> Chip8Display<Software> {
+        let config = Chip8DisplayConfig {
+            half_pixel_lores_scroll,
+            ..Default::default()
+        };
+
+        let mut display = Chip8Display {
+            backend: None,
+            vsync_occurred: false,
+            hires,
+            selected_planes: 0b01,
+            planes: std::array::from_fn(|_| DMatrix::from_element(0, 0, false)),
+            config,
+        };
+        let framebuffer_size = display.framebuffer_size();
+
+        for plane in &mut display.planes {
+            plane.resize_mut(framebuffer_size.x, framebuffer_size.y, false);
+        }
+
+        display
+    }
+
+    fn lit_pixels(display: &Chip8Display<Software>, plane: usize) -> Vec<(usize, usize)> {
+        let plane = &display.planes[plane];
+
+        (0..plane.ncols())
+            .flat_map(|y| (0..plane.nrows()).map(move |x| (x, y)))
+            .filter(|&position| plane[position])
+            .collect()
+    }
+
+    #[test]
+    fn lores_scroll_moves_whole_pixels() {
+        let mut display = display(false, false);
+        display.draw_sprite(Point2::new(10, 10), &[0b1000_0000], 8, true);
+
+        display.scroll(Vector2::new(4, 0));
+        assert_eq!(lit_pixels(&display, 0), [(14, 10)]);
+
+        display.scroll(Vector2::new(-4, 3));
+        assert_eq!(lit_pixels(&display, 0), [(10, 13)]);
+
+        // Pixels scrolled off the edge are gone for good
+        display.scroll(Vector2::new(-11, 0));
+        display.scroll(Vector2::new(11, 0));
+        assert!(lit_pixels(&display, 0).is_empty());
+    }
+
+    #[test]
+    fn hires_scroll_moves_whole_pixels() {
+        let mut display = display(false, true);
+        display.draw_sprite(Point2::new(100, 60), &[0b1000_0000], 8, true);
+
+        display.scroll(Vector2::new(4, 2));
+        assert_eq!(lit_pixels(&display, 0), [(104, 62)]);
+
+        display.scroll(Vector2::new(0, 2));
+        assert!(lit_pixels(&display, 0).is_empty());
+    }
+
+    #[test]
+    fn half_pixel_lores_scroll_moves_half_pixels() {
+        let mut display = display(true, false);
+        assert_eq!(display.framebuffer_size(), Vector2::new(128, 64));
+
+        // A single lores pixel covers a 2x2 block
+        display.draw_sprite(Point2::new(1, 1), &[0b1000_0000], 8, true);
+        assert_eq!(lit_pixels(&display, 0), [(2, 2), (3, 2), (2, 3), (3, 3)]);
+
+        display.scroll(Vector2::new(0, 1));
+        assert_eq!(lit_pixels(&display, 0), [(2, 3), (3, 3), (2, 4), (3, 4)]);
+
+        display.scroll(Vector2::new(-4, 0));
+        assert!(lit_pixels(&display, 0).is_empty());
+    }
+
+    #[test]
+    fn scroll_only_touches_selected_planes() {
+        let mut display = display(false, true);
+        display.select_planes(0b11);
+        display.draw_sprite(Point2::new(0, 0), &[0b1000_0000, 0b1000_0000], 8, true);
+
+        display.select_planes(0b10);
+        display.scroll(Vector2::new(0, 5));
+
+        assert_eq!(lit_pixels(&display, 0), [(0, 0)]);
+        assert_eq!(lit_pixels(&display, 1), [(0, 5)]);
+    }
+}
@@ -2,7 +2,7 @@ use fluxemu_runtime::graphics::{
     GraphicsApi,
     software::{InitializationData, Software},
 };
-use nalgebra::{DMatrix, DMatrixView, DMatrixViewMut, Vector2};
+use nalgebra::{DMatrix, DMatrixViewMut, Vector2};
 use palette::{Srgba, named::BLACK};
 
 use super::{Chip8DisplayBackend, SupportedGraphicsApiChip8Display};
@@ -34,10 +34,6 @@ impl Chip8DisplayBackend for SoftwareState {
         self.framebuffer = self.staging_buffer.clone();
     }
 
-    fn interact_staging_buffer(&self, callback: impl FnOnce(DMatrixView<Srgba<u8>>)) {
-        callback(self.staging_buffer.as_view());
-    }
-
     fn interact_staging_buffer_mut(&mut self, callback: impl FnOnce(DMatrixViewMut<Srgba<u8>>)) {
         callback(self.staging_buffer.as_view_mut());
     }
@@ -18,7 +18,7 @@ use fluxemu_runtime::graphics::{
         },
     },
 };
-use nalgebra::{DMatrixView, DMatrixViewMut, Vector2};
+use nalgebra::{DMatrixViewMut, Vector2};
 use palette::{Srgba, named::BLACK};
 
 use super::{LORES, SupportedGraphicsApiChip8Display};
@@ -121,16 +121,6 @@ impl Chip8DisplayBackend for VulkanState {
         self.commit_staging_buffer();
     }
 
-    fn interact_staging_buffer(&self, callback: impl FnOnce(DMatrixView<'_, Srgba<u8>>)) {
-        let staging_buffer_guard = self.staging_buffer.read().unwrap();
-
-        callback(DMatrixView::from_slice(
-            &staging_buffer_guard,
-            self.current_resolution.x,
-            self.current_resolution.y,
-        ));
-    }
-
     fn interact_staging_buffer_mut(&mut self, callback: impl FnOnce(DMatrixViewMut<Srgba<u8>>)) {
         let mut staging_buffer_guard = self.staging_buffer.write().unwrap();
 
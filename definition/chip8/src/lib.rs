@@ -35,20 +35,73 @@ pub enum Chip8Mode {
     XoChip,
 }
 
+/// The behaviors CHIP-8 interpreters disagree on
+///
+/// Each is worked out from the mode unless set
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Debug, Default)]
+#[serde(default)]
+pub struct Chip8Quirks {
+    /// Start out in this mode, rather than the original
+    pub force_mode: Option<Chip8Mode>,
+    /// Shifts work on VX, rather than putting VY shifted into it
+    pub shift_in_place: Option<bool>,
+    /// Saving and restoring registers leaves the index register where it was
+    pub load_store_keeps_index: Option<bool>,
+    /// Drawing waits for the next frame
+    pub vblank_wait: Option<bool>,
+    /// Sprites are cut off at the edges of the screen, rather than wrapping
+    /// around
+    pub clip_sprites: Option<bool>,
+    /// Lores scrolls move by half a pixel like the original SCHIP, which kept
+    /// the HP48 display at full resolution
+    ///
+    /// No mode does this unless asked, since later interpreters and the games
+    /// written for them scroll by whole pixels
+    pub half_pixel_lores_scroll: bool,
+}
+
+impl Chip8Quirks {
+    fn shift_in_place(&self, mode: Chip8Mode) -> bool {
+        self.shift_in_place.unwrap_or(mode == Chip8Mode::SuperChip8)
+    }
+
+    fn load_store_keeps_index(&self, mode: Chip8Mode) -> bool {
+        self.load_store_keeps_index
+            .unwrap_or(mode == Chip8Mode::SuperChip8)
+    }
+
+    fn vblank_wait(&self, mode: Chip8Mode) -> bool {
+        self.vblank_wait.unwrap_or(mode != Chip8Mode::XoChip)
+    }
+
+    fn clip_sprites(&self, mode: Chip8Mode) -> bool {
+        self.clip_sprites.unwrap_or(mode != Chip8Mode::XoChip)
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct Chip8;
 
 impl<P: Platform<GraphicsApi: SupportedGraphicsApiChip8Display>> MachineFactory<P> for Chip8 {
-    fn construct(&self, machine: MachineBuilder<P>) -> MachineBuilder<P> {
-        let (machine, cpu_address_space) = machine.insert_address_space(12);
+    type Quirks = Chip8Quirks;
+
+    fn construct(&self, machine: MachineBuilder<P>, quirks: Chip8Quirks) -> MachineBuilder<P> {
+        // Only XO-CHIP can address more than the first 4 KiB
+        let (machine, cpu_address_space) = machine.insert_address_space(16);
         let (machine, timer) = machine.insert_default_component::<Chip8TimerConfig>("timer");
         let (machine, audio) = machine.insert_component(
             "audio",
             Chip8AudioConfig {
-                processor_frequency: Frequency::from_num(1000),
+                sample_rate: Frequency::from_num(44100),
+            },
+        );
+        let (machine, display) = machine.insert_component(
+            "display",
+            Chip8DisplayConfig {
+                half_pixel_lores_scroll: quirks.half_pixel_lores_scroll,
+                ..Default::default()
             },
         );
-        let (machine, display) = machine.insert_default_component::<Chip8DisplayConfig>("display");
         let (machine, _) = machine.insert_component(
             "cpu",
             Chip8ProcessorConfig {
@@ -57,8 +110,9 @@ impl<P: Platform<GraphicsApi: SupportedGraphicsApiChip8Display>> MachineFactory<
                 audio,
                 display,
                 frequency: Frequency::from_num(1000),
-                force_mode: None,
-                always_shr_in_place: false,
+                quirks,
+                #[cfg(feature = "jit")]
+                jit: true,
                 _phantom: PhantomData,
             },
         );
@@ -75,7 +129,7 @@ impl<P: Platform<GraphicsApi: SupportedGraphicsApiChip8Display>> MachineFactory<
             StandardMemoryConfig {
                 readable: true,
                 writable: true,
-                assigned_range: 0x000..=0xfff,
+                assigned_range: 0x0000..=0xffff,
                 assigned_address_space: cpu_address_space,
                 initial_contents: RangeInclusiveMap::from_iter([
                     (
@@ -84,7 +138,7 @@ impl<P: Platform<GraphicsApi: SupportedGraphicsApiChip8Display>> MachineFactory<
                             &CHIP8_FONT,
                         ))),
                     ),
-                    (0x200..=0xfff, StandardMemoryInitialContents::Rom(rom)),
+                    (0x200..=0xffff, StandardMemoryInitialContents::Rom(rom)),
                 ]),
                 sram: false,
             },
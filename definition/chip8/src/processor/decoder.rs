@@ -2,9 +2,13 @@ use bitvec::{field::BitField, prelude::Msb0, view::BitView};
 use nalgebra::Point2;
 
 use super::instruction::{
-    Chip8InstructionSet, InstructionSetChip8, InstructionSetSuperChip8, Register, ScrollDirection,
+    Chip8InstructionSet, InstructionSetChip8, InstructionSetSuperChip8, InstructionSetXoChip,
+    Register, ScrollDirection,
 };
 
+/// The first half of the only instruction that is 4 bytes long
+pub(super) const LONG_INSTRUCTION_PREFIX: [u8; 2] = [0xf0, 0x00];
+
 pub(super) fn decode_instruction(instruction: [u8; 2]) -> Option<Chip8InstructionSet> {
     let instruction_view = instruction.view_bits::<Msb0>();
 
@@ -21,6 +25,9 @@ pub(super) fn decode_instruction(instruction: [u8; 2]) -> Option<Chip8Instructio
                 [0x0, 0xe, 0xe] => Chip8InstructionSet::Chip8(InstructionSetChip8::Rtrn),
                 [0x0, 0xf, 0xe] => Chip8InstructionSet::SuperChip8(InstructionSetSuperChip8::Lores),
                 [0x0, 0xf, 0xf] => Chip8InstructionSet::SuperChip8(InstructionSetSuperChip8::Hires),
+                [0x0, 0xd, _] => {
+                    Chip8InstructionSet::XoChip(InstructionSetXoChip::Scru { amount: syscall[2] })
+                }
                 [0x0, 0xc, _] => {
                     Chip8InstructionSet::SuperChip8(InstructionSetSuperChip8::Scroll {
                         direction: ScrollDirection::Down { amount: syscall[2] },
@@ -33,7 +40,7 @@ pub(super) fn decode_instruction(instruction: [u8; 2]) -> Option<Chip8Instructio
                 }
                 [0x0, 0xf, 0xc] => {
                     Chip8InstructionSet::SuperChip8(InstructionSetSuperChip8::Scroll {
-                        direction: ScrollDirection::Right,
+                        direction: ScrollDirection::Left,
                     })
                 }
                 _ => return None,
@@ -71,10 +78,21 @@ pub(super) fn decode_instruction(instruction: [u8; 2]) -> Option<Chip8Instructio
             let param_1 = instruction_view[4..8].load::<u8>();
             let param_2 = instruction_view[8..12].load::<u8>();
 
-            Chip8InstructionSet::Chip8(InstructionSetChip8::Skre {
-                param_1: Register::from_repr(param_1).unwrap(),
-                param_2: Register::from_repr(param_2).unwrap(),
-            })
+            let param_1 = Register::from_repr(param_1).unwrap();
+            let param_2 = Register::from_repr(param_2).unwrap();
+
+            match instruction_view[12..16].load::<u8>() {
+                0x0 => Chip8InstructionSet::Chip8(InstructionSetChip8::Skre { param_1, param_2 }),
+                0x2 => Chip8InstructionSet::XoChip(InstructionSetXoChip::Ssub {
+                    bounds: param_1..=param_2,
+                }),
+                0x3 => Chip8InstructionSet::XoChip(InstructionSetXoChip::Rsub {
+                    bounds: param_1..=param_2,
+                }),
+                _ => {
+                    return None;
+                }
+            }
         }
         0x6 => {
             let register = instruction_view[4..8].load::<u8>();
@@ -207,6 +225,14 @@ pub(super) fn decode_instruction(instruction: [u8; 2]) -> Option<Chip8Instructio
             let register = instruction_view[4..8].load::<u8>();
 
             match instruction_view[8..16].load::<u8>() {
+                0x00 if register == 0x0 => {
+                    Chip8InstructionSet::XoChip(InstructionSetXoChip::Loadil)
+                }
+                0x01 => Chip8InstructionSet::XoChip(InstructionSetXoChip::Plane { mask: register }),
+                0x02 if register == 0x0 => Chip8InstructionSet::XoChip(InstructionSetXoChip::Audio),
+                0x3a => Chip8InstructionSet::XoChip(InstructionSetXoChip::Pitch {
+                    register: Register::from_repr(register).unwrap(),
+                }),
                 0x07 => Chip8InstructionSet::Chip8(InstructionSetChip8::Moved {
                     register: Register::from_repr(register).unwrap(),
                 }),
@@ -232,6 +258,12 @@ pub(super) fn decode_instruction(instruction: [u8; 2]) -> Option<Chip8Instructio
                 0x65 => {
                     Chip8InstructionSet::Chip8(InstructionSetChip8::Restore { count: register })
                 }
+                0x75 => Chip8InstructionSet::SuperChip8(InstructionSetSuperChip8::Srpl {
+                    amount: register,
+                }),
+                0x85 => Chip8InstructionSet::SuperChip8(InstructionSetSuperChip8::Rrpl {
+                    amount: register,
+                }),
                 _ => {
                     return None;
                 }
@@ -242,3 +274,63 @@ pub(super) fn decode_instruction(instruction: [u8; 2]) -> Option<Chip8Instructio
         }
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn superchip_scrolling() {
+        assert_eq!(
+            decode_instruction([0x00, 0xc5]),
+            Some(Chip8InstructionSet::SuperChip8(
+                InstructionSetSuperChip8::Scroll {
+                    direction: ScrollDirection::Down { amount: 5 }
+                }
+            ))
+        );
+        assert_eq!(
+            decode_instruction([0x00, 0xfb]),
+            Some(Chip8InstructionSet::SuperChip8(
+                InstructionSetSuperChip8::Scroll {
+                    direction: ScrollDirection::Right
+                }
+            ))
+        );
+        assert_eq!(
+            decode_instruction([0x00, 0xfc]),
+            Some(Chip8InstructionSet::SuperChip8(
+                InstructionSetSuperChip8::Scroll {
+                    direction: ScrollDirection::Left
+                }
+            ))
+        );
+        assert_eq!(
+            decode_instruction([0xf7, 0x75]),
+            Some(Chip8InstructionSet::SuperChip8(
+                InstructionSetSuperChip8::Srpl { amount: 7 }
+            ))
+        );
+    }
+
+    #[test]
+    fn xochip() {
+        assert_eq!(
+            decode_instruction(LONG_INSTRUCTION_PREFIX),
+            Some(Chip8InstructionSet::XoChip(InstructionSetXoChip::Loadil))
+        );
+        assert_eq!(
+            decode_instruction([0x53, 0x12]),
+            Some(Chip8InstructionSet::XoChip(InstructionSetXoChip::Ssub {
+                bounds: Register::V3..=Register::V1
+            }))
+        );
+        assert_eq!(
+            decode_instruction([0xf3, 0x01]),
+            Some(Chip8InstructionSet::XoChip(InstructionSetXoChip::Plane {
+                mask: 3
+            }))
+        );
+        assert_eq!(decode_instruction([0xf1, 0x02]), None);
+    }
+}
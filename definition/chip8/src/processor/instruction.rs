@@ -1,4 +1,4 @@
-use std::{fmt::Display, ops::Range};
+use std::{fmt::Display, ops::RangeInclusive};
 
 use fluxemu_runtime::processor::InstructionSet;
 use nalgebra::Point2;
@@ -163,17 +163,33 @@ pub enum InstructionSetSuperChip8 {
     Lores,
     Hires,
     Scroll { direction: ScrollDirection },
-    Scrd { amount: u8 },
-    Scrr,
-    Scrl,
     Srpl { amount: u8 },
     Rrpl { amount: u8 },
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum InstructionSetXoChip {
-    Ssub { bounds: Range<Register> },
-    Rsub { bounds: Range<Register> },
+    /// Save a range of registers, which goes backwards if the end is lower than
+    /// the start
+    Ssub {
+        bounds: RangeInclusive<Register>,
+    },
+    /// Restore a range of registers, in the same order as [Self::Ssub]
+    Rsub {
+        bounds: RangeInclusive<Register>,
+    },
+    Scru {
+        amount: u8,
+    },
+    /// Load a 16 bit index, which is in the word following the instruction
+    Loadil,
+    Plane {
+        mask: u8,
+    },
+    Audio,
+    Pitch {
+        register: Register,
+    },
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
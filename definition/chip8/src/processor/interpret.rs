@@ -1,21 +1,28 @@
+use std::ops::RangeInclusive;
+
 use arrayvec::ArrayVec;
 use bitvec::{
     field::BitField,
     prelude::{Lsb0, Msb0},
     view::BitView,
 };
-use nalgebra::Point2;
+use nalgebra::{Point2, Vector2};
 use rand::Rng;
 
 use super::{
     ExecutionState,
+    decoder::LONG_INSTRUCTION_PREFIX,
     input::Chip8KeyCode,
-    instruction::{Chip8InstructionSet, InstructionSetChip8},
+    instruction::{Chip8InstructionSet, InstructionSetChip8, InstructionSetXoChip, Register},
 };
 use crate::{
     CHIP8_FONT, Chip8Mode,
-    display::SupportedGraphicsApiChip8Display,
-    processor::{Chip8Processor, instruction::InstructionSetSuperChip8},
+    audio::PATTERN_SIZE,
+    display::{PLANE_COUNT, SupportedGraphicsApiChip8Display},
+    processor::{
+        Chip8Processor,
+        instruction::{InstructionSetSuperChip8, ScrollDirection},
+    },
 };
 
 // Instruction interpreting can be clean and easy due to the chip8 enforcing 1
@@ -23,7 +30,8 @@ use crate::{
 
 impl<G: SupportedGraphicsApiChip8Display> Chip8Processor<G> {
     pub(super) fn interpret_instruction(&mut self, instruction: Chip8InstructionSet) {
-        let mut mode_guard = self.mode.lock().unwrap();
+        let mode = self.mode.clone();
+        let mut mode_guard = mode.lock().unwrap();
 
         match instruction {
             Chip8InstructionSet::Chip8(InstructionSetChip8::Clr) => {
@@ -54,7 +62,7 @@ impl<G: SupportedGraphicsApiChip8Display> Chip8Processor<G> {
                 let register_value = self.state.registers.work_registers[register as usize];
 
                 if register_value == immediate {
-                    self.state.registers.program = self.state.registers.program.wrapping_add(2);
+                    self.skip_instruction();
                 }
             }
             Chip8InstructionSet::Chip8(InstructionSetChip8::Skne {
@@ -64,7 +72,7 @@ impl<G: SupportedGraphicsApiChip8Display> Chip8Processor<G> {
                 let register_value = self.state.registers.work_registers[register as usize];
 
                 if register_value != immediate {
-                    self.state.registers.program = self.state.registers.program.wrapping_add(2);
+                    self.skip_instruction();
                 }
             }
             Chip8InstructionSet::Chip8(InstructionSetChip8::Skre { param_1, param_2 }) => {
@@ -72,7 +80,7 @@ impl<G: SupportedGraphicsApiChip8Display> Chip8Processor<G> {
                 let param_2_value = self.state.registers.work_registers[param_2 as usize];
 
                 if param_1_value == param_2_value {
-                    self.state.registers.program = self.state.registers.program.wrapping_add(2);
+                    self.skip_instruction();
                 }
             }
             Chip8InstructionSet::Chip8(InstructionSetChip8::Load {
@@ -154,7 +162,7 @@ impl<G: SupportedGraphicsApiChip8Display> Chip8Processor<G> {
             Chip8InstructionSet::Chip8(InstructionSetChip8::Shr { register, value }) => {
                 let mut destination_value = self.state.registers.work_registers[register as usize];
 
-                if *mode_guard == Chip8Mode::Chip8 || self.config.always_shr_in_place {
+                if !self.config.quirks.shift_in_place(*mode_guard) {
                     destination_value = self.state.registers.work_registers[value as usize];
                 }
 
@@ -178,7 +186,7 @@ impl<G: SupportedGraphicsApiChip8Display> Chip8Processor<G> {
             Chip8InstructionSet::Chip8(InstructionSetChip8::Shl { register, value }) => {
                 let mut destination_value = self.state.registers.work_registers[register as usize];
 
-                if *mode_guard == Chip8Mode::Chip8 {
+                if !self.config.quirks.shift_in_place(*mode_guard) {
                     destination_value = self.state.registers.work_registers[value as usize];
                 }
 
@@ -192,14 +200,14 @@ impl<G: SupportedGraphicsApiChip8Display> Chip8Processor<G> {
                 let param_2_value = self.state.registers.work_registers[param_2 as usize];
 
                 if param_1_value != param_2_value {
-                    self.state.registers.program = self.state.registers.program.wrapping_add(2);
+                    self.skip_instruction();
                 }
             }
             Chip8InstructionSet::Chip8(InstructionSetChip8::Loadi { value }) => {
                 self.state.registers.index = value;
             }
             Chip8InstructionSet::Chip8(InstructionSetChip8::Jumpi { address }) => {
-                let address = if *mode_guard == Chip8Mode::Chip8 {
+                let address = if matches!(*mode_guard, Chip8Mode::Chip8 | Chip8Mode::XoChip) {
                     address.wrapping_add(u16::from(self.state.registers.work_registers[0x0]))
                 } else {
                     let register = address.view_bits::<Msb0>()[4..8].load::<u8>();
@@ -226,53 +234,36 @@ impl<G: SupportedGraphicsApiChip8Display> Chip8Processor<G> {
                     self.state.registers.work_registers[coordinates.x as usize],
                     self.state.registers.work_registers[coordinates.y as usize],
                 );
-                let mut cursor = 0;
 
                 // SuperChip8 specializes a 16x16 sprite here
-                if height == 0 && *mode_guard == Chip8Mode::SuperChip8
-                    || *mode_guard == Chip8Mode::XoChip
+                let (width, rows) = if height == 0
+                    && matches!(*mode_guard, Chip8Mode::SuperChip8 | Chip8Mode::XoChip)
                 {
-                    let mut buffer = [0; 32];
-
-                    for buffer_section in buffer.chunks_mut(2) {
-                        self.cpu_address_space
-                            .read(
-                                self.state.registers.index as usize + cursor,
-                                self.timestamp,
-                                None,
-                                buffer_section,
-                            )
-                            .unwrap();
-                        cursor += buffer_section.len();
-                    }
-
-                    self.state.registers.work_registers[0xf] =
-                        self.display.interact_mut(self.timestamp, |component| {
-                            u8::from(component.draw_supersized_sprite(position, buffer))
-                        });
+                    (16, 16)
                 } else {
-                    let mut buffer =
-                        ArrayVec::<_, 16>::from_iter(std::iter::repeat_n(0, height as usize));
-
-                    for buffer_section in buffer.chunks_mut(2) {
-                        self.cpu_address_space
-                            .read(
-                                self.state.registers.index as usize + cursor,
-                                self.timestamp,
-                                None,
-                                buffer_section,
-                            )
-                            .unwrap();
-                        cursor += buffer_section.len();
-                    }
+                    (8, usize::from(height))
+                };
 
-                    self.state.registers.work_registers[0xf] =
-                        self.display.interact_mut(self.timestamp, |component| {
-                            u8::from(component.draw_sprite(position, &buffer))
-                        });
-                }
+                // Each selected plane gets its own sprite, one after the other
+                let plane_count = self
+                    .display
+                    .interact(self.timestamp, |component| component.selected_plane_count());
+
+                let mut buffer = ArrayVec::<_, { 32 * PLANE_COUNT }>::from_iter(
+                    std::iter::repeat_n(0, rows * width / 8 * plane_count),
+                );
+                self.read_memory(self.state.registers.index, &mut buffer);
+
+                let clip = self.config.quirks.clip_sprites(*mode_guard);
 
-                self.state.execution_state = ExecutionState::AwaitingVsync;
+                self.state.registers.work_registers[0xf] =
+                    self.display.interact_mut(self.timestamp, |component| {
+                        u8::from(component.draw_sprite(position, &buffer, width, clip))
+                    });
+
+                if self.config.quirks.vblank_wait(*mode_guard) {
+                    self.state.execution_state = ExecutionState::AwaitingVsync;
+                }
             }
             Chip8InstructionSet::Chip8(InstructionSetChip8::Skpr { key }) => {
                 let key = Chip8KeyCode(self.state.registers.work_registers[key as usize]);
@@ -280,7 +271,7 @@ impl<G: SupportedGraphicsApiChip8Display> Chip8Processor<G> {
                 let key_value = self.virtual_gamepad.get(key.try_into().unwrap());
 
                 if key_value.as_digital(None) {
-                    self.state.registers.program = self.state.registers.program.wrapping_add(2);
+                    self.skip_instruction();
                 }
             }
             Chip8InstructionSet::Chip8(InstructionSetChip8::Skup { key }) => {
@@ -289,7 +280,7 @@ impl<G: SupportedGraphicsApiChip8Display> Chip8Processor<G> {
                 let key_value = self.virtual_gamepad.get(key.try_into().unwrap());
 
                 if !key_value.as_digital(None) {
-                    self.state.registers.program = self.state.registers.program.wrapping_add(2);
+                    self.skip_instruction();
                 }
             }
             Chip8InstructionSet::Chip8(InstructionSetChip8::Moved { register }) => {
@@ -356,6 +347,11 @@ impl<G: SupportedGraphicsApiChip8Display> Chip8Processor<G> {
                         ones,
                     )
                     .unwrap();
+
+                #[cfg(feature = "jit")]
+                self.invalidate_compiled_code(
+                    self.state.registers.index..=self.state.registers.index.saturating_add(2),
+                );
             }
             Chip8InstructionSet::Chip8(InstructionSetChip8::Save { count }) => {
                 for i in 0..=count {
@@ -369,8 +365,13 @@ impl<G: SupportedGraphicsApiChip8Display> Chip8Processor<G> {
                         .unwrap();
                 }
 
-                // Only the original chip8 modifies the index register for this operation
-                if *mode_guard == Chip8Mode::Chip8 {
+                #[cfg(feature = "jit")]
+                self.invalidate_compiled_code(
+                    self.state.registers.index
+                        ..=self.state.registers.index.saturating_add(count.into()),
+                );
+
+                if !self.config.quirks.load_store_keeps_index(*mode_guard) {
                     self.state.registers.index = self
                         .state
                         .registers
@@ -390,8 +391,7 @@ impl<G: SupportedGraphicsApiChip8Display> Chip8Processor<G> {
                         .unwrap();
                 }
 
-                // Only the original chip8 modifies the index register for this operation
-                if *mode_guard == Chip8Mode::Chip8 {
+                if !self.config.quirks.load_store_keeps_index(*mode_guard) {
                     self.state.registers.index = self
                         .state
                         .registers
@@ -400,7 +400,10 @@ impl<G: SupportedGraphicsApiChip8Display> Chip8Processor<G> {
                 }
             }
             Chip8InstructionSet::SuperChip8(subinstruction) => {
-                *mode_guard = Chip8Mode::SuperChip8;
+                // XO-CHIP is a superset of SCHIP
+                if *mode_guard != Chip8Mode::XoChip {
+                    *mode_guard = Chip8Mode::SuperChip8;
+                }
 
                 match subinstruction {
                     InstructionSetSuperChip8::Lores => {
@@ -413,23 +416,154 @@ impl<G: SupportedGraphicsApiChip8Display> Chip8Processor<G> {
                             component.set_hires(true);
                         });
                     }
-                    InstructionSetSuperChip8::Scroll { direction } => todo!(),
-                    InstructionSetSuperChip8::Scrd { amount } => todo!(),
-                    InstructionSetSuperChip8::Scrr => todo!(),
-                    InstructionSetSuperChip8::Scrl => todo!(),
-                    InstructionSetSuperChip8::Srpl { amount } => todo!(),
-                    InstructionSetSuperChip8::Rrpl { amount } => todo!(),
+                    InstructionSetSuperChip8::Scroll { direction } => {
+                        self.display.interact_mut(self.timestamp, |component| {
+                            component.scroll(scroll_offset(direction));
+                        });
+                    }
+                    InstructionSetSuperChip8::Srpl { amount } => {
+                        let count = usize::from(amount) + 1;
+
+                        self.state.rpl_flags[..count]
+                            .copy_from_slice(&self.state.registers.work_registers[..count]);
+                        self.state.rpl_flags_used = true;
+                    }
+                    InstructionSetSuperChip8::Rrpl { amount } => {
+                        let count = usize::from(amount) + 1;
+
+                        self.state.registers.work_registers[..count]
+                            .copy_from_slice(&self.state.rpl_flags[..count]);
+                    }
                 }
             }
-            Chip8InstructionSet::XoChip(_) => {
+            Chip8InstructionSet::XoChip(subinstruction) => {
                 *mode_guard = Chip8Mode::XoChip;
 
-                todo!()
+                match subinstruction {
+                    InstructionSetXoChip::Ssub { bounds } => {
+                        #[cfg(feature = "jit")]
+                        let written = self.state.registers.index
+                            ..=self.state.registers.index.saturating_add(
+                                (*bounds.start() as u16).abs_diff(*bounds.end() as u16),
+                            );
+
+                        for (offset, register) in register_range(bounds).enumerate() {
+                            self.cpu_address_space
+                                .write(
+                                    usize::from(self.state.registers.index) + offset,
+                                    self.timestamp,
+                                    None,
+                                    &self.state.registers.work_registers[register..=register],
+                                )
+                                .unwrap();
+                        }
+
+                        #[cfg(feature = "jit")]
+                        self.invalidate_compiled_code(written);
+                    }
+                    InstructionSetXoChip::Rsub { bounds } => {
+                        for (offset, register) in register_range(bounds).enumerate() {
+                            self.cpu_address_space
+                                .read(
+                                    usize::from(self.state.registers.index) + offset,
+                                    self.timestamp,
+                                    None,
+                                    &mut self.state.registers.work_registers[register..=register],
+                                )
+                                .unwrap();
+                        }
+                    }
+                    InstructionSetXoChip::Scru { amount } => {
+                        self.display.interact_mut(self.timestamp, |component| {
+                            component.scroll(Vector2::new(0, -isize::from(amount)));
+                        });
+                    }
+                    InstructionSetXoChip::Loadil => {
+                        let mut value = [0; 2];
+                        self.read_memory(self.state.registers.program, &mut value);
+
+                        self.state.registers.index = u16::from_be_bytes(value);
+                        self.state.registers.program = self.state.registers.program.wrapping_add(2);
+                    }
+                    InstructionSetXoChip::Plane { mask } => {
+                        self.display.interact_mut(self.timestamp, |component| {
+                            component.select_planes(mask);
+                        });
+                    }
+                    InstructionSetXoChip::Audio => {
+                        let mut pattern = [0; PATTERN_SIZE];
+                        self.read_memory(self.state.registers.index, &mut pattern);
+
+                        self.audio.interact_mut(self.timestamp, |component| {
+                            component.set_pattern(pattern);
+                        });
+                    }
+                    InstructionSetXoChip::Pitch { register } => {
+                        let register_value = self.state.registers.work_registers[register as usize];
+
+                        self.audio.interact_mut(self.timestamp, |component| {
+                            component.set_pitch(register_value);
+                        });
+                    }
+                }
             }
         }
     }
 }
 
+impl<G: SupportedGraphicsApiChip8Display> Chip8Processor<G> {
+    fn read_memory(&self, address: u16, buffer: &mut [u8]) {
+        for (cursor, buffer_section) in buffer.chunks_mut(2).enumerate() {
+            self.cpu_address_space
+                .read(
+                    usize::from(address) + cursor * 2,
+                    self.timestamp,
+                    None,
+                    buffer_section,
+                )
+                .unwrap();
+        }
+    }
+
+    /// Skips over the next instruction, which is twice as long if it is a long
+    /// index load
+    fn skip_instruction(&mut self) {
+        let mut instruction = [0; 2];
+        self.read_memory(self.state.registers.program, &mut instruction);
+
+        let length = if instruction == LONG_INSTRUCTION_PREFIX {
+            4
+        } else {
+            2
+        };
+
+        self.state.registers.program = self.state.registers.program.wrapping_add(length);
+    }
+}
+
+/// The registers in a range, going backwards if the range does
+fn register_range(bounds: RangeInclusive<Register>) -> impl Iterator<Item = usize> {
+    let (start, end) = (*bounds.start() as usize, *bounds.end() as usize);
+
+    (0..=start.abs_diff(end)).map(move |offset| {
+        if start <= end {
+            start + offset
+        } else {
+            start - offset
+        }
+    })
+}
+
+/// SCHIP scrolls are always by the same amount of framebuffer pixels, whatever
+/// the resolution
+fn scroll_offset(direction: ScrollDirection) -> Vector2<isize> {
+    match direction {
+        ScrollDirection::Left => Vector2::new(-4, 0),
+        ScrollDirection::Right => Vector2::new(4, 0),
+        ScrollDirection::Down { amount } => Vector2::new(0, isize::from(amount)),
+    }
+}
+
 #[inline]
 fn bcd_encode(value: u8) -> [u8; 3] {
     let hundreds = value / 100;
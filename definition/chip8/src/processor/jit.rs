@@ -1,223 +1,816 @@
-use super::instruction::{Chip8InstructionSet, InstructionSetChip8, Register};
-use crate::Chip8Kind;
-use crate::processor::ProcessorState;
-use cranelift::prelude::{Block, FunctionBuilder, InstBuilder, MemFlags, Value, types};
-use rangemap::RangeInclusiveMap;
-use std::{mem::offset_of, sync::Arc};
-
-impl ProcessorState {
-    #[inline]
-    pub fn get_work_register_offset(register: Register) -> u8 {
-        (offset_of!(ProcessorState, registers.work_registers) + register as usize * 2) as u8
+use std::{collections::BTreeMap, fmt::Debug, mem::offset_of, ops::RangeInclusive, sync::Mutex};
+
+use bitvec::{field::BitField, prelude::Msb0, view::BitView};
+use cranelift::{
+    jit::{JITBuilder, JITModule},
+    module::{Module, default_libcall_names},
+    prelude::{
+        AbiParam, FunctionBuilder, FunctionBuilderContext, InstBuilder, IntCC, MemFlags, Value,
+        types,
+    },
+};
+
+use super::{
+    Chip8Processor, Chip8ProcessorRegisters,
+    decoder::{LONG_INSTRUCTION_PREFIX, decode_instruction},
+    instruction::{Chip8InstructionSet, InstructionSetChip8, Register},
+};
+use crate::{CHIP8_FONT, Chip8Mode, Chip8Quirks, display::SupportedGraphicsApiChip8Display};
+
+// Only instructions that touch nothing but the registers are compiled, so a
+// block can run all at once without anything else in the machine noticing
+
+/// The most instructions a block can hold
+const MAX_BLOCK_LENGTH: usize = 32;
+/// The most bytes a block can be compiled from, which is its instructions and
+/// the first half of the one a trailing skip may jump over
+const MAX_BLOCK_SPAN: u16 = (MAX_BLOCK_LENGTH as u16 + 1) * 2;
+/// Cranelift cannot free functions one at a time, so everything is thrown out
+/// once this many blocks have been compiled
+const MAX_COMPILED_BLOCKS: usize = 4096;
+
+type BlockFunction = unsafe extern "C" fn(*mut Chip8ProcessorRegisters);
+
+/// How an instruction fits into a block
+enum Lowering {
+    /// Runs and carries on to the next instruction
+    Straight,
+    /// Decides where the program goes next, which ends the block
+    Branch,
+    /// Has to go through the interpreter, so the block ends right before it
+    Interpret,
+}
+
+impl Lowering {
+    fn of(instruction: &Chip8InstructionSet) -> Self {
+        match instruction {
+            Chip8InstructionSet::Chip8(
+                InstructionSetChip8::Load { .. }
+                | InstructionSetChip8::Add { .. }
+                | InstructionSetChip8::Move { .. }
+                | InstructionSetChip8::Or { .. }
+                | InstructionSetChip8::And { .. }
+                | InstructionSetChip8::Xor { .. }
+                | InstructionSetChip8::Addr { .. }
+                | InstructionSetChip8::Sub { .. }
+                | InstructionSetChip8::Shr { .. }
+                | InstructionSetChip8::Subn { .. }
+                | InstructionSetChip8::Shl { .. }
+                | InstructionSetChip8::Loadi { .. }
+                | InstructionSetChip8::Addi { .. }
+                | InstructionSetChip8::Font { .. },
+            ) => Self::Straight,
+            Chip8InstructionSet::Chip8(
+                InstructionSetChip8::Jump { .. }
+                | InstructionSetChip8::Jumpi { .. }
+                | InstructionSetChip8::Ske { .. }
+                | InstructionSetChip8::Skne { .. }
+                | InstructionSetChip8::Skre { .. }
+                | InstructionSetChip8::Skrne { .. },
+            ) => Self::Branch,
+            // Display, input, timers, memory, the stack, randomness, and the
+            // extensions, which can switch modes
+            _ => Self::Interpret,
+        }
     }
 }
 
+fn is_skip(instruction: &Chip8InstructionSet) -> bool {
+    matches!(
+        instruction,
+        Chip8InstructionSet::Chip8(
+            InstructionSetChip8::Ske { .. }
+                | InstructionSetChip8::Skne { .. }
+                | InstructionSetChip8::Skre { .. }
+                | InstructionSetChip8::Skrne { .. }
+        )
+    )
+}
+
 #[derive(Debug)]
-pub struct Chip8InstructionTranslator {
-    pub mode: Arc<AtomicCell<Chip8Kind>>,
+pub(super) struct CompiledBlock {
+    /// Every address the block was compiled from
+    range: RangeInclusive<u16>,
+    /// How many instructions, and so cycles, the block runs
+    pub instruction_count: usize,
+    function: BlockFunction,
+}
+
+impl CompiledBlock {
+    pub fn run(&self, registers: &mut Chip8ProcessorRegisters) {
+        // SAFETY: The function only touches the registers, and its memory is
+        // only freed after the block is gone
+        unsafe { (self.function)(registers) }
+    }
+}
+
+/// Compiles and caches runs of instructions, by the addresses they came from
+pub(super) struct Jit {
+    /// Only ever used through a mutable reference, this just makes it [Sync]
+    module: Mutex<JITModule>,
+    blocks: BTreeMap<u16, CompiledBlock>,
+    /// The mode the blocks were compiled for, since some instructions act
+    /// differently between them
+    mode: Chip8Mode,
+    compiled_count: usize,
+}
+
+impl Debug for Jit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Jit")
+            .field("blocks", &self.blocks)
+            .field("mode", &self.mode)
+            .field("compiled_count", &self.compiled_count)
+            .finish_non_exhaustive()
+    }
 }
 
-impl Chip8InstructionTranslator {
-    pub fn new(mode: Arc<AtomicCell<Chip8Kind>>) -> Chip8InstructionTranslator {
-        Chip8InstructionTranslator { mode }
+impl Default for Jit {
+    fn default() -> Self {
+        Self {
+            module: Mutex::new(create_module()),
+            blocks: BTreeMap::new(),
+            mode: Chip8Mode::default(),
+            compiled_count: 0,
+        }
     }
 }
 
-impl InstructionTranslator for Chip8InstructionTranslator {
-    type InstructionSet = Chip8InstructionSet;
+fn create_module() -> JITModule {
+    let builder = JITBuilder::with_flags(&[("opt_level", "speed")], default_libcall_names())
+        .expect("Host is not supported by Cranelift");
 
-    fn translate(
-        &self,
-        processor_state: Value,
-        function_builder: &mut FunctionBuilder,
-        instructions: RangeInclusiveMap<usize, Self::InstructionSet>,
-        blocks: &mut RangeInclusiveMap<usize, Block>,
-    ) {
-        for (cursor, instruction) in instructions {
-            let current_block = function_builder.create_block();
-            blocks.insert(cursor, current_block);
-
-            match instruction {
-                Chip8InstructionSet::Chip8(InstructionSetChip8::Add {
-                    register,
-                    immediate,
-                }) => {
-                    let register_location = ProcessorState::get_work_register_offset(register);
-
-                    let register_value = function_builder.ins().load(
-                        types::I16,
-                        MemFlags::new(),
-                        processor_state,
-                        register_location,
-                    );
-
-                    let immediate_value =
-                        function_builder.ins().iconst(types::I8, immediate as i64);
-
-                    let new_value = function_builder.ins().iadd(register_value, immediate_value);
-
-                    function_builder.ins().store(
-                        MemFlags::new(),
-                        new_value,
-                        processor_state,
-                        register_location,
-                    );
+    JITModule::new(builder)
+}
+
+impl Jit {
+    /// Finds or compiles the block starting at `address`, which there is none
+    /// of if the instruction there has to be interpreted
+    pub fn block(
+        &mut self,
+        address: u16,
+        mode: Chip8Mode,
+        quirks: &Chip8Quirks,
+        fetch: impl FnMut(u16) -> [u8; 2],
+    ) -> Option<&CompiledBlock> {
+        if mode != self.mode {
+            self.clear();
+            self.mode = mode;
+        }
+
+        if !self.blocks.contains_key(&address) {
+            let block = self.compile(address, quirks, fetch)?;
+            self.blocks.insert(address, block);
+        }
+
+        self.blocks.get(&address)
+    }
+
+    /// Drops every block compiled from any of `addresses`
+    pub fn invalidate(&mut self, addresses: RangeInclusive<u16>) {
+        let candidates = addresses.start().saturating_sub(MAX_BLOCK_SPAN - 1)..=*addresses.end();
+
+        let stale: Vec<_> = self
+            .blocks
+            .range(candidates)
+            .filter(|(_, block)| block.range.end() >= addresses.start())
+            .map(|(start, _)| *start)
+            .collect();
+
+        for start in stale {
+            tracing::debug!("Dropping block at {:#06x}, which was written over", start);
+
+            self.blocks.remove(&start);
+        }
+    }
+
+    /// Drops every block, and the memory they were compiled into
+    pub fn clear(&mut self) {
+        self.blocks.clear();
+
+        if self.compiled_count != 0 {
+            let module = std::mem::replace(self.module.get_mut().unwrap(), create_module());
+            self.compiled_count = 0;
+
+            // SAFETY: Every block made with the module is gone, and none of
+            // them can be running while we have a mutable reference
+            unsafe { module.free_memory() };
+        }
+    }
+
+    fn compile(
+        &mut self,
+        start: u16,
+        quirks: &Chip8Quirks,
+        mut fetch: impl FnMut(u16) -> [u8; 2],
+    ) -> Option<CompiledBlock> {
+        // Keeping clear of the top of memory means addresses in a block never
+        // wrap around
+        if start > u16::MAX - MAX_BLOCK_SPAN {
+            return None;
+        }
+
+        let mut instructions = Vec::new();
+        let mut cursor = start;
+        let mut skip_address = None;
+        let mut branched = false;
+
+        while instructions.len() < MAX_BLOCK_LENGTH {
+            let Some(instruction) = decode_instruction(fetch(cursor)) else {
+                break;
+            };
+
+            match Lowering::of(&instruction) {
+                Lowering::Straight => {
+                    cursor += 2;
+                    instructions.push((instruction, cursor));
                 }
-                Chip8InstructionSet::Chip8(InstructionSetChip8::Sys { .. }) => {}
-                Chip8InstructionSet::Chip8(InstructionSetChip8::Jump { .. }) => {}
-                Chip8InstructionSet::Chip8(InstructionSetChip8::Call { .. }) => {}
-                Chip8InstructionSet::Chip8(InstructionSetChip8::Ske { .. }) => {}
-                Chip8InstructionSet::Chip8(InstructionSetChip8::Skne { .. }) => {}
-                Chip8InstructionSet::Chip8(InstructionSetChip8::Skre { .. }) => {}
-                Chip8InstructionSet::Chip8(InstructionSetChip8::Load { .. }) => {}
-                Chip8InstructionSet::Chip8(InstructionSetChip8::Move { .. }) => {}
-                Chip8InstructionSet::Chip8(InstructionSetChip8::Or {
-                    destination,
-                    source,
-                }) => {
-                    let destination_location =
-                        ProcessorState::get_work_register_offset(destination);
-                    let destination_value = function_builder.ins().load(
-                        types::I16,
-                        MemFlags::new(),
-                        processor_state,
-                        destination_location,
-                    );
-
-                    let source_location = ProcessorState::get_work_register_offset(source);
-                    let source_value = function_builder.ins().load(
-                        types::I16,
-                        MemFlags::new(),
-                        processor_state,
-                        source_location,
-                    );
-
-                    let new_value = function_builder.ins().bor(destination_value, source_value);
-
-                    function_builder.ins().store(
-                        MemFlags::new(),
-                        new_value,
-                        processor_state,
-                        destination_location,
-                    );
-
-                    if self.mode.load() == Chip8Kind::Chip8 {
-                        let zero = function_builder.ins().iconst(types::I16, 0);
-
-                        function_builder.ins().store(
-                            MemFlags::new(),
-                            zero,
-                            processor_state,
-                            ProcessorState::get_work_register_offset(Register::V0),
-                        );
+                Lowering::Branch => {
+                    cursor += 2;
+
+                    if is_skip(&instruction) {
+                        let length = if fetch(cursor) == LONG_INSTRUCTION_PREFIX {
+                            4
+                        } else {
+                            2
+                        };
+
+                        skip_address = Some(cursor + length);
                     }
+
+                    instructions.push((instruction, cursor));
+                    branched = true;
+                    break;
                 }
-                Chip8InstructionSet::Chip8(InstructionSetChip8::And {
-                    destination,
-                    source,
-                }) => {
-                    let destination_location =
-                        ProcessorState::get_work_register_offset(destination);
-                    let destination_value = function_builder.ins().load(
-                        types::I16,
-                        MemFlags::new(),
-                        processor_state,
-                        destination_location,
-                    );
-
-                    let source_location = ProcessorState::get_work_register_offset(source);
-                    let source_value = function_builder.ins().load(
-                        types::I16,
-                        MemFlags::new(),
-                        processor_state,
-                        source_location,
-                    );
-
-                    let new_value = function_builder.ins().band(destination_value, source_value);
-
-                    function_builder.ins().store(
-                        MemFlags::new(),
-                        new_value,
-                        processor_state,
-                        destination_location,
-                    );
-
-                    if self.mode.load() == Chip8Kind::Chip8 {
-                        let zero = function_builder.ins().iconst(types::I16, 0);
-
-                        function_builder.ins().store(
-                            MemFlags::new(),
-                            zero,
-                            processor_state,
-                            ProcessorState::get_work_register_offset(Register::V0),
-                        );
+                Lowering::Interpret => break,
+            }
+        }
+
+        if instructions.is_empty() {
+            return None;
+        }
+
+        // A skip depends on the length of the instruction after it too
+        let end = if skip_address.is_some() {
+            cursor + 1
+        } else {
+            cursor - 1
+        };
+
+        if self.compiled_count >= MAX_COMPILED_BLOCKS {
+            self.clear();
+        }
+
+        let module = self.module.get_mut().unwrap();
+        let mut context = module.make_context();
+        let pointer_type = module.target_config().pointer_type();
+        context
+            .func
+            .signature
+            .params
+            .push(AbiParam::new(pointer_type));
+
+        let mut function_builder_context = FunctionBuilderContext::new();
+        let mut builder = FunctionBuilder::new(&mut context.func, &mut function_builder_context);
+
+        let entry = builder.create_block();
+        builder.append_block_params_for_function_params(entry);
+        builder.switch_to_block(entry);
+        builder.seal_block(entry);
+        let registers = builder.block_params(entry)[0];
+
+        let mut translator = BlockTranslator {
+            builder,
+            registers,
+            mode: self.mode,
+            quirks,
+        };
+
+        for (instruction, next_address) in &instructions {
+            translator.translate(instruction, *next_address, skip_address);
+        }
+
+        if !branched {
+            let program = translator.constant(types::I16, cursor.into());
+            translator.store_program(program);
+        }
+
+        translator.builder.ins().return_(&[]);
+        translator.builder.finalize();
+
+        let id = module
+            .declare_anonymous_function(&context.func.signature)
+            .unwrap();
+        module
+            .define_function(id, &mut context)
+            .expect("Failed to compile block");
+        module.clear_context(&mut context);
+        module.finalize_definitions().unwrap();
+
+        // SAFETY: The function was built with this signature
+        let function = unsafe {
+            std::mem::transmute::<*const u8, BlockFunction>(module.get_finalized_function(id))
+        };
+
+        self.compiled_count += 1;
+
+        tracing::debug!(
+            "Compiled block at {:#06x} of {} instructions",
+            start,
+            instructions.len()
+        );
+
+        Some(CompiledBlock {
+            range: start..=end,
+            instruction_count: instructions.len(),
+            function,
+        })
+    }
+}
+
+struct BlockTranslator<'a> {
+    builder: FunctionBuilder<'a>,
+    /// Pointer to the [Chip8ProcessorRegisters]
+    registers: Value,
+    mode: Chip8Mode,
+    quirks: &'a Chip8Quirks,
+}
+
+impl BlockTranslator<'_> {
+    fn constant(&mut self, ty: types::Type, value: i64) -> Value {
+        self.builder.ins().iconst(ty, value)
+    }
+
+    fn load_register(&mut self, register: Register) -> Value {
+        let offset = offset_of!(Chip8ProcessorRegisters, work_registers) + register as usize;
+
+        self.builder.ins().load(
+            types::I8,
+            MemFlags::trusted(),
+            self.registers,
+            offset as i32,
+        )
+    }
+
+    fn store_register(&mut self, register: Register, value: Value) {
+        let offset = offset_of!(Chip8ProcessorRegisters, work_registers) + register as usize;
+
+        self.builder
+            .ins()
+            .store(MemFlags::trusted(), value, self.registers, offset as i32);
+    }
+
+    fn load_index(&mut self) -> Value {
+        self.builder.ins().load(
+            types::I16,
+            MemFlags::trusted(),
+            self.registers,
+            offset_of!(Chip8ProcessorRegisters, index) as i32,
+        )
+    }
+
+    fn store_index(&mut self, value: Value) {
+        self.builder.ins().store(
+            MemFlags::trusted(),
+            value,
+            self.registers,
+            offset_of!(Chip8ProcessorRegisters, index) as i32,
+        );
+    }
+
+    fn store_program(&mut self, value: Value) {
+        self.builder.ins().store(
+            MemFlags::trusted(),
+            value,
+            self.registers,
+            offset_of!(Chip8ProcessorRegisters, program) as i32,
+        );
+    }
+
+    /// Sets the flag register, which the interpreter always does last
+    fn store_flag(&mut self, value: Value) {
+        self.store_register(Register::VF, value);
+    }
+
+    /// Goes to `skip_address` if `condition` is set, and `next_address`
+    /// otherwise
+    fn skip(&mut self, condition: Value, next_address: u16, skip_address: Option<u16>) {
+        let skip_address = skip_address.expect("Skip was compiled without knowing where it goes");
+
+        let taken = self.constant(types::I16, skip_address.into());
+        let not_taken = self.constant(types::I16, next_address.into());
+        let program = self.builder.ins().select(condition, taken, not_taken);
+
+        self.store_program(program);
+    }
+
+    fn translate(
+        &mut self,
+        instruction: &Chip8InstructionSet,
+        next_address: u16,
+        skip_address: Option<u16>,
+    ) {
+        let Chip8InstructionSet::Chip8(instruction) = instruction else {
+            unreachable!("Extensions are always interpreted");
+        };
+
+        match *instruction {
+            InstructionSetChip8::Load {
+                register,
+                immediate,
+            } => {
+                let value = self.constant(types::I8, immediate.into());
+                self.store_register(register, value);
+            }
+            InstructionSetChip8::Add {
+                register,
+                immediate,
+            } => {
+                let register_value = self.load_register(register);
+                let immediate = self.constant(types::I8, immediate.into());
+                let value = self.builder.ins().iadd(register_value, immediate);
+
+                self.store_register(register, value);
+            }
+            InstructionSetChip8::Move { param_1, param_2 } => {
+                let value = self.load_register(param_2);
+                self.store_register(param_1, value);
+            }
+            InstructionSetChip8::Or {
+                destination,
+                source,
+            }
+            | InstructionSetChip8::And {
+                destination,
+                source,
+            }
+            | InstructionSetChip8::Xor {
+                destination,
+                source,
+            } => {
+                let destination_value = self.load_register(destination);
+                let source_value = self.load_register(source);
+
+                let value = match instruction {
+                    InstructionSetChip8::Or { .. } => {
+                        self.builder.ins().bor(destination_value, source_value)
                     }
-                }
-                Chip8InstructionSet::Chip8(InstructionSetChip8::Xor {
-                    destination,
-                    source,
-                }) => {
-                    let destination_location =
-                        ProcessorState::get_work_register_offset(destination);
-                    let destination_value = function_builder.ins().load(
-                        types::I16,
-                        MemFlags::new(),
-                        processor_state,
-                        destination_location,
-                    );
-
-                    let source_location = ProcessorState::get_work_register_offset(source);
-                    let source_value = function_builder.ins().load(
-                        types::I16,
-                        MemFlags::new(),
-                        processor_state,
-                        source_location,
-                    );
-
-                    let new_value = function_builder.ins().bxor(destination_value, source_value);
-
-                    function_builder.ins().store(
-                        MemFlags::new(),
-                        new_value,
-                        processor_state,
-                        destination_location,
-                    );
-
-                    if self.mode.load() == Chip8Kind::Chip8 {
-                        let zero = function_builder.ins().iconst(types::I16, 0);
-
-                        function_builder.ins().store(
-                            MemFlags::new(),
-                            zero,
-                            processor_state,
-                            ProcessorState::get_work_register_offset(Register::V0),
-                        );
+                    InstructionSetChip8::And { .. } => {
+                        self.builder.ins().band(destination_value, source_value)
                     }
+                    _ => self.builder.ins().bxor(destination_value, source_value),
+                };
+
+                self.store_register(destination, value);
+
+                if self.mode == Chip8Mode::Chip8 {
+                    let zero = self.constant(types::I8, 0);
+                    self.store_flag(zero);
                 }
-                Chip8InstructionSet::Chip8(InstructionSetChip8::Addr { .. }) => {}
-                Chip8InstructionSet::Chip8(InstructionSetChip8::Sub { .. }) => {}
-                Chip8InstructionSet::Chip8(InstructionSetChip8::Shr { .. }) => {}
-                Chip8InstructionSet::Chip8(InstructionSetChip8::Subn { .. }) => {}
-                Chip8InstructionSet::Chip8(InstructionSetChip8::Shl { .. }) => {}
-                Chip8InstructionSet::Chip8(InstructionSetChip8::Skrne { .. }) => {}
-                Chip8InstructionSet::Chip8(InstructionSetChip8::Loadi { .. }) => {}
-                Chip8InstructionSet::Chip8(InstructionSetChip8::Jumpi { .. }) => {}
-                Chip8InstructionSet::Chip8(InstructionSetChip8::Rand { .. }) => {}
-                Chip8InstructionSet::Chip8(InstructionSetChip8::Draw { .. }) => {}
-                Chip8InstructionSet::Chip8(InstructionSetChip8::Skpr { .. }) => {}
-                Chip8InstructionSet::Chip8(InstructionSetChip8::Skup { .. }) => {}
-                Chip8InstructionSet::Chip8(InstructionSetChip8::Moved { .. }) => {}
-                Chip8InstructionSet::Chip8(InstructionSetChip8::Keyd { .. }) => {}
-                Chip8InstructionSet::Chip8(InstructionSetChip8::Loadd { .. }) => {}
-                Chip8InstructionSet::Chip8(InstructionSetChip8::Loads { .. }) => {}
-                Chip8InstructionSet::Chip8(InstructionSetChip8::Addi { .. }) => {}
-                Chip8InstructionSet::Chip8(InstructionSetChip8::Font { .. }) => {}
-                Chip8InstructionSet::Chip8(InstructionSetChip8::Bcd { .. }) => {}
-                Chip8InstructionSet::Chip8(InstructionSetChip8::Save { .. }) => {}
-                Chip8InstructionSet::Chip8(InstructionSetChip8::Restore { .. }) => {}
-                Chip8InstructionSet::SuperChip8(_) => {}
-                Chip8InstructionSet::XoChip(_) => {}
             }
+            InstructionSetChip8::Addr {
+                destination,
+                source,
+            } => {
+                let destination_value = self.load_register(destination);
+                let source_value = self.load_register(source);
+
+                let value = self.builder.ins().iadd(destination_value, source_value);
+                let carry =
+                    self.builder
+                        .ins()
+                        .icmp(IntCC::UnsignedLessThan, value, destination_value);
+
+                self.store_register(destination, value);
+                self.store_flag(carry);
+            }
+            InstructionSetChip8::Sub {
+                destination,
+                source,
+            }
+            | InstructionSetChip8::Subn {
+                destination,
+                source,
+            } => {
+                let destination_value = self.load_register(destination);
+                let source_value = self.load_register(source);
+
+                let (minuend, subtrahend) =
+                    if matches!(instruction, InstructionSetChip8::Sub { .. }) {
+                        (destination_value, source_value)
+                    } else {
+                        (source_value, destination_value)
+                    };
+
+                let value = self.builder.ins().isub(minuend, subtrahend);
+                let no_borrow =
+                    self.builder
+                        .ins()
+                        .icmp(IntCC::UnsignedGreaterThanOrEqual, minuend, subtrahend);
+
+                self.store_register(destination, value);
+                self.store_flag(no_borrow);
+            }
+            InstructionSetChip8::Shr { register, value } => {
+                let source = if self.quirks.shift_in_place(self.mode) {
+                    register
+                } else {
+                    value
+                };
+                let source_value = self.load_register(source);
+
+                let overflow = self.builder.ins().band_imm(source_value, 1);
+                let value = self.builder.ins().ushr_imm(source_value, 1);
+
+                self.store_register(register, value);
+                self.store_flag(overflow);
+            }
+            InstructionSetChip8::Shl { register, value } => {
+                let source = if self.quirks.shift_in_place(self.mode) {
+                    register
+                } else {
+                    value
+                };
+                let source_value = self.load_register(source);
+
+                let overflow = self.builder.ins().ushr_imm(source_value, 7);
+                let value = self.builder.ins().ishl_imm(source_value, 1);
+
+                self.store_register(register, value);
+                self.store_flag(overflow);
+            }
+            InstructionSetChip8::Loadi { value } => {
+                let value = self.constant(types::I16, value.into());
+                self.store_index(value);
+            }
+            InstructionSetChip8::Addi { register } => {
+                let index = self.load_index();
+                let register_value = self.load_register(register);
+                let register_value = self.builder.ins().uextend(types::I16, register_value);
+
+                let value = self.builder.ins().iadd(index, register_value);
+                self.store_index(value);
+            }
+            InstructionSetChip8::Font { register } => {
+                let register_value = self.load_register(register);
+                let register_value = self.builder.ins().uextend(types::I16, register_value);
+
+                let value = self
+                    .builder
+                    .ins()
+                    .imul_imm(register_value, CHIP8_FONT[0].len() as i64);
+                self.store_index(value);
+            }
+            InstructionSetChip8::Jump { address } => {
+                let address = self.constant(types::I16, address.into());
+                self.store_program(address);
+            }
+            InstructionSetChip8::Jumpi { address } => {
+                let register = if matches!(self.mode, Chip8Mode::Chip8 | Chip8Mode::XoChip) {
+                    Register::V0
+                } else {
+                    Register::from_repr(address.view_bits::<Msb0>()[4..8].load::<u8>()).unwrap()
+                };
+
+                let register_value = self.load_register(register);
+                let register_value = self.builder.ins().uextend(types::I16, register_value);
+                let address = self.constant(types::I16, address.into());
+
+                let value = self.builder.ins().iadd(address, register_value);
+                self.store_program(value);
+            }
+            InstructionSetChip8::Ske {
+                register,
+                immediate,
+            }
+            | InstructionSetChip8::Skne {
+                register,
+                immediate,
+            } => {
+                let condition = if matches!(instruction, InstructionSetChip8::Ske { .. }) {
+                    IntCC::Equal
+                } else {
+                    IntCC::NotEqual
+                };
+
+                let register_value = self.load_register(register);
+                let immediate = self.constant(types::I8, immediate.into());
+                let condition = self
+                    .builder
+                    .ins()
+                    .icmp(condition, register_value, immediate);
+
+                self.skip(condition, next_address, skip_address);
+            }
+            InstructionSetChip8::Skre { param_1, param_2 }
+            | InstructionSetChip8::Skrne { param_1, param_2 } => {
+                let condition = if matches!(instruction, InstructionSetChip8::Skre { .. }) {
+                    IntCC::Equal
+                } else {
+                    IntCC::NotEqual
+                };
+
+                let param_1_value = self.load_register(param_1);
+                let param_2_value = self.load_register(param_2);
+                let condition = self
+                    .builder
+                    .ins()
+                    .icmp(condition, param_1_value, param_2_value);
+
+                self.skip(condition, next_address, skip_address);
+            }
+            _ => unreachable!("{:?} is always interpreted", instruction),
         }
     }
 }
+
+impl<G: SupportedGraphicsApiChip8Display> Chip8Processor<G> {
+    /// Runs the compiled block at the program counter, if there is one that
+    /// fits in `budget` instructions, returning how many instructions it ran
+    pub(super) fn run_compiled_block(&mut self, budget: u64) -> Option<usize> {
+        let mode = *self.mode.lock().unwrap();
+        let jit = self.jit.as_mut()?;
+        let address_space = &self.cpu_address_space;
+        let timestamp = self.timestamp;
+
+        let block = jit.block(
+            self.state.registers.program,
+            mode,
+            &self.config.quirks,
+            |address| {
+                let mut instruction = [0; 2];
+
+                address_space
+                    .read(usize::from(address), timestamp, None, &mut instruction)
+                    .unwrap();
+
+                instruction
+            },
+        )?;
+
+        if block.instruction_count as u64 > budget {
+            return None;
+        }
+
+        block.run(&mut self.state.registers);
+
+        Some(block.instruction_count)
+    }
+
+    /// Drops any compiled code the program just wrote over
+    pub(super) fn invalidate_compiled_code(&mut self, addresses: RangeInclusive<u16>) {
+        if let Some(jit) = &mut self.jit {
+            jit.invalidate(addresses);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Range;
+
+    use fluxemu_runtime::scheduler::Period;
+    use rand::{Rng, SeedableRng, rngs::StdRng};
+
+    use crate::{
+        Chip8Mode, Chip8Quirks,
+        processor::tests::{PROGRAM_START, TestMachine},
+    };
+
+    /// Where generated programs point the index register, away from their
+    /// code
+    const DATA_START: u16 = 0x400;
+    /// The part of memory compared between machines, which is everything
+    /// generated programs can write to
+    const OBSERVED_MEMORY: Range<u16> = PROGRAM_START..DATA_START + 0x100;
+
+    #[derive(Debug, PartialEq)]
+    struct Observation {
+        work_registers: [u8; 16],
+        index: u16,
+        program: u16,
+        stack: Vec<u16>,
+        memory: Vec<u8>,
+    }
+
+    impl TestMachine {
+        fn observe(&self) -> Observation {
+            let memory = self.memory(OBSERVED_MEMORY.start, OBSERVED_MEMORY.len());
+
+            self.processor(|processor| Observation {
+                work_registers: processor.state.registers.work_registers,
+                index: processor.state.registers.index,
+                program: processor.state.registers.program,
+                stack: processor.state.stack.to_vec(),
+                memory,
+            })
+        }
+    }
+
+    /// Runs a program with and without compilation, checking they never
+    /// disagree
+    fn assert_equivalent(program: &[u8], quirks: Chip8Quirks) -> Observation {
+        let interpreted = TestMachine::new(program, quirks.clone(), false);
+        let compiled = TestMachine::new(program, quirks, true);
+
+        // Uneven steps, so blocks get cut off by the end of the budget
+        for step in 0..64 {
+            let duration = Period::from_num(step % 7 + 1) / 100;
+
+            interpreted.machine.run(duration);
+            compiled.machine.run(duration);
+
+            assert_eq!(
+                interpreted.observe(),
+                compiled.observe(),
+                "Diverged after step {step}"
+            );
+        }
+
+        compiled.observe()
+    }
+
+    /// Instructions the JIT handles mixed with ones it hands back, none of
+    /// which write to memory or leave the program
+    fn random_program(rng: &mut StdRng, length: u16) -> Vec<u8> {
+        let mut program = Vec::new();
+
+        for _ in 0..length - 2 {
+            let x = rng.random_range(0..16) << 8;
+            let y = rng.random_range(0..16) << 4;
+            let immediate = u16::from(rng.random::<u8>());
+
+            let instruction: u16 = match rng.random_range(0..20) {
+                0 => 0x6000 | x | immediate,
+                1 => 0x7000 | x | immediate,
+                2 => 0x8000 | x | y | rng.random_range(0x0..=0x7),
+                3 => 0x800e | x | y,
+                4 => 0xa000 | rng.random_range(DATA_START..DATA_START + 0x100),
+                5 => 0xf01e | x,
+                6 => 0xf029 | x,
+                7 => 0x3000 | x | rng.random_range(0..4),
+                8 => 0x4000 | x | rng.random_range(0..4),
+                9 => 0x5000 | x | y,
+                10 => 0x9000 | x | y,
+                11 => 0x1000 | (PROGRAM_START + rng.random_range(0..length) * 2),
+                12 => 0xf065 | x,
+                13 => 0xf007 | x,
+                14 => 0xf015 | x,
+                // Loads often, so skips have something to compare against
+                _ => 0x6000 | x | rng.random_range(0..4),
+            };
+
+            program.extend(instruction.to_be_bytes());
+        }
+
+        // A skip at the very end still has somewhere to go
+        for _ in 0..2 {
+            program.extend((0x1000 | PROGRAM_START).to_be_bytes());
+        }
+
+        program
+    }
+
+    #[test]
+    fn random_programs() {
+        for mode in [Chip8Mode::Chip8, Chip8Mode::SuperChip8, Chip8Mode::XoChip] {
+            for seed in 0..8 {
+                let mut rng = StdRng::seed_from_u64(seed);
+                let program = random_program(&mut rng, 128);
+
+                assert_equivalent(
+                    &program,
+                    Chip8Quirks {
+                        force_mode: Some(mode),
+                        ..Default::default()
+                    },
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn self_modifying_code() {
+        let program = [
+            0x60, 0x6b, // V0 = 0x6b
+            0x61, 0x07, // V1 = 0x07
+            0xa2, 0x0a, // I = 0x20a
+            0x6a, 0x00, // VA = 0
+            0x7a, 0x01, // VA += 1
+            0x6b, 0x05, // VB = 5, which becomes VB = 7 the eighth time around
+            0x8c, 0xb4, // VC += VB
+            0x4a, 0x08, // Skip if VA != 8
+            0xf1, 0x55, // Save V0 and V1 to I
+            0x3a, 0x10, // Skip if VA = 0x10
+            0x12, 0x08, // Jump to 0x208
+            0x60, 0x00, // V0 = 0
+            0xb2, 0x1a, // Jump to 0x21a + V0
+            0x12, 0x1a, // Jump to 0x21a
+        ];
+
+        let observation = assert_equivalent(&program, Chip8Quirks::default());
+
+        assert_eq!(observation.work_registers[0xc], 8 * 5 + 8 * 7);
+        assert_eq!(observation.program, 0x21a);
+    }
+}
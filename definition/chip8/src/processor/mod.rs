@@ -19,7 +19,7 @@ use input::{Chip8KeyCode, default_bindings, present_inputs};
 use instruction::Register;
 use serde::{Deserialize, Serialize};
 
-use super::Chip8Mode;
+use super::{Chip8Mode, Chip8Quirks};
 use crate::{
     audio::Chip8Audio,
     display::{Chip8Display, SupportedGraphicsApiChip8Display},
@@ -31,8 +31,10 @@ pub mod decoder;
 mod input;
 mod instruction;
 mod interpret;
-// #[cfg(jit)]
-// mod jit;
+#[cfg(feature = "jit")]
+mod jit;
+#[cfg(test)]
+mod tests;
 
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
 enum ExecutionState {
@@ -52,7 +54,9 @@ enum ExecutionState {
 // This is extremely complex because the chip8 cpu has a lot of non cpu
 // machinery
 
+// Compiled code reaches into this by offset
 #[derive(Debug, Deserialize, Serialize, Clone)]
+#[repr(C)]
 struct Chip8ProcessorRegisters {
     work_registers: [u8; 16],
     index: u16,
@@ -74,6 +78,11 @@ pub struct ProcessorState {
     registers: Chip8ProcessorRegisters,
     stack: ArrayVec<u16, 16>,
     execution_state: ExecutionState,
+    /// The HP48 RPL user flags, which SCHIP lets programs stash registers in
+    rpl_flags: [u8; 16],
+    /// If the program ever wrote the RPL user flags, which decides if they get
+    /// saved
+    rpl_flags_used: bool,
 }
 
 impl Default for ProcessorState {
@@ -82,6 +91,8 @@ impl Default for ProcessorState {
             stack: ArrayVec::default(),
             registers: Chip8ProcessorRegisters::default(),
             execution_state: ExecutionState::Normal,
+            rpl_flags: [0; 16],
+            rpl_flags_used: false,
         }
     }
 }
@@ -100,6 +111,8 @@ pub struct Chip8Processor<G: SupportedGraphicsApiChip8Display> {
     timer: TypedComponentHandle<Chip8Timer>,
     config: Chip8ProcessorConfig<G>,
     timestamp: Period,
+    #[cfg(feature = "jit")]
+    jit: Option<jit::Jit>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -107,6 +120,8 @@ pub struct Chip8ProcessorSnapshot {
     registers: Chip8ProcessorRegisters,
     stack: ArrayVec<u16, 16>,
     execution_state: ExecutionState,
+    #[serde(default)]
+    rpl_flags: [u8; 16],
 }
 
 impl<G: SupportedGraphicsApiChip8Display> Component for Chip8Processor<G> {
@@ -122,6 +137,13 @@ impl<G: SupportedGraphicsApiChip8Display> Component for Chip8Processor<G> {
         self.state.registers = snapshot.registers;
         self.state.stack = snapshot.stack;
         self.state.execution_state = snapshot.execution_state;
+        self.state.rpl_flags = snapshot.rpl_flags;
+
+        // Memory is restored along with us, so anything compiled is suspect
+        #[cfg(feature = "jit")]
+        if let Some(jit) = &mut self.jit {
+            jit.clear();
+        }
 
         Ok(())
     }
@@ -131,6 +153,7 @@ impl<G: SupportedGraphicsApiChip8Display> Component for Chip8Processor<G> {
             registers: self.state.registers.clone(),
             stack: self.state.stack.clone(),
             execution_state: self.state.execution_state.clone(),
+            rpl_flags: self.state.rpl_flags,
         };
 
         rmp_serde::encode::write_named(&mut writer, &snapshot)?;
@@ -142,13 +165,41 @@ impl<G: SupportedGraphicsApiChip8Display> Component for Chip8Processor<G> {
         Some(0)
     }
 
+    fn save_version(&self) -> Option<ComponentVersion> {
+        self.state.rpl_flags_used.then_some(0)
+    }
+
+    fn store_save(&self, mut writer: Box<dyn Write>) -> Result<(), Box<dyn std::error::Error>> {
+        writer.write_all(&self.state.rpl_flags)?;
+
+        Ok(())
+    }
+
     fn synchronize(&mut self, mut context: SynchronizationContext) {
-        for now in context.allocate(self.config.frequency.recip(), None) {
+        let mut quanta = context.allocate(self.config.frequency.recip(), None);
+
+        // Compiled blocks take more than one quanta at a time
+        #[cfg_attr(not(feature = "jit"), allow(clippy::while_let_on_iterator))]
+        while let Some(now) = quanta.next() {
             self.timestamp = now;
 
             'main: {
                 match &self.state.execution_state {
                     ExecutionState::Normal => {
+                        // Blocks only touch registers, so nothing can tell
+                        // one ran all at once as long as it fits in what is
+                        // left of the allocation
+                        #[cfg(feature = "jit")]
+                        if let Some(instruction_count) =
+                            self.run_compiled_block(quanta.remaining() + 1)
+                        {
+                            for now in quanta.by_ref().take(instruction_count - 1) {
+                                self.timestamp = now;
+                            }
+
+                            break 'main;
+                        }
+
                         let mut instruction = [0; 2];
 
                         self.cpu_address_space
@@ -237,8 +288,10 @@ pub struct Chip8ProcessorConfig<G: SupportedGraphicsApiChip8Display> {
     pub audio: FluxEmuPath,
     pub timer: FluxEmuPath,
     pub frequency: Frequency,
-    pub force_mode: Option<Chip8Mode>,
-    pub always_shr_in_place: bool,
+    pub quirks: Chip8Quirks,
+    /// Compile runs of simple instructions instead of interpreting them
+    #[cfg(feature = "jit")]
+    pub jit: bool,
     pub _phantom: PhantomData<fn() -> G>,
 }
 
@@ -255,8 +308,19 @@ impl<P: Platform<GraphicsApi: SupportedGraphicsApiChip8Display>> ComponentConfig
             .get_address_space(self.cpu_address_space)
             .clone();
 
-        let mode = Arc::new(Mutex::new(self.force_mode.unwrap_or(Chip8Mode::Chip8)));
-        let state = ProcessorState::default();
+        let mode = Arc::new(Mutex::new(
+            self.quirks.force_mode.unwrap_or(Chip8Mode::Chip8),
+        ));
+        let mut state = ProcessorState::default();
+
+        if let Some((mut save, version)) = component_builder.save() {
+            if version != 0 {
+                return Err("Invalid save version".into());
+            }
+
+            save.read_exact(&mut state.rpl_flags)?;
+            state.rpl_flags_used = true;
+        }
 
         let virtual_gamepad = VirtualGamepad::new(Cow::Owned(VirtualGamepadMetadata {
             present_inputs: present_inputs(),
@@ -267,6 +331,9 @@ impl<P: Platform<GraphicsApi: SupportedGraphicsApiChip8Display>> ComponentConfig
             .set_scheduler_participation(SchedulerParticipation::SchedulerDriven)
             .insert_gamepad("chip8-keypad", virtual_gamepad.clone());
 
+        #[cfg(feature = "jit")]
+        let jit = self.jit.then(jit::Jit::default);
+
         let display = component_builder.typed_handle(&self.display).unwrap();
         let audio = component_builder.typed_handle(&self.audio).unwrap();
         let timer = component_builder.typed_handle(&self.timer).unwrap();
@@ -281,6 +348,8 @@ impl<P: Platform<GraphicsApi: SupportedGraphicsApiChip8Display>> ComponentConfig
             timer,
             config: self,
             timestamp: Period::default(),
+            #[cfg(feature = "jit")]
+            jit,
         })
     }
 }
@@ -0,0 +1,376 @@
+use std::{borrow::Cow, collections::BTreeSet, marker::PhantomData, path::Path, sync::Arc};
+
+use fluxemu_definition_misc::memory::standard::{
+    StandardMemoryConfig, StandardMemoryInitialContents,
+};
+use fluxemu_runtime::{
+    graphics::software::Software,
+    machine::Machine,
+    memory::AddressSpaceId,
+    path::FluxEmuPath,
+    program::{
+        Filesystem, MachineId, OtherSystem, ProgramId, ProgramInfo, ProgramManager,
+        ProgramSpecification, RomId,
+    },
+    scheduler::{Frequency, Period},
+};
+use nalgebra::DMatrix;
+use rangemap::RangeInclusiveMap;
+
+use crate::{
+    CHIP8_FONT, Chip8Mode, Chip8Quirks,
+    audio::{Chip8Audio, Chip8AudioConfig},
+    display::{Chip8Display, Chip8DisplayConfig},
+    processor::{Chip8Processor, Chip8ProcessorConfig},
+    timer::Chip8TimerConfig,
+};
+
+pub const PROGRAM_START: u16 = 0x200;
+
+pub struct TestMachine {
+    pub machine: Arc<Machine>,
+    cpu: FluxEmuPath,
+    display: FluxEmuPath,
+    audio: FluxEmuPath,
+    cpu_address_space: AddressSpaceId,
+}
+
+impl TestMachine {
+    /// The machine [crate::Chip8] builds, with the program put straight into
+    /// memory
+    pub fn new(program: &[u8], quirks: Chip8Quirks, jit: bool) -> Self {
+        Self::build(program, quirks, jit, None)
+    }
+
+    /// Like [Self::new], but keeping saves in `save_directory` under a made up
+    /// program
+    pub fn with_saves(program: &[u8], quirks: Chip8Quirks, save_directory: &Path) -> Self {
+        Self::build(program, quirks, false, Some(save_directory))
+    }
+
+    #[cfg_attr(not(feature = "jit"), allow(unused_variables))]
+    fn build(
+        program: &[u8],
+        quirks: Chip8Quirks,
+        jit: bool,
+        save_directory: Option<&Path>,
+    ) -> Self {
+        let program_specification = save_directory.map(|_| ProgramSpecification {
+            id: ProgramId {
+                machine: MachineId::Other(OtherSystem::Chip8),
+                name: "Test".to_string(),
+            },
+            info: ProgramInfo::V0 {
+                names: BTreeSet::from(["Test".to_string()]),
+                filesystem: Filesystem::Single {
+                    rom_id: RomId([0; 20]),
+                    file_name: "test.ch8".to_string(),
+                },
+                languages: BTreeSet::default(),
+                version: None,
+                video_standard: None,
+            },
+        });
+
+        let (machine, cpu_address_space) = Machine::build_test(
+            program_specification,
+            Arc::new(ProgramManager::default()),
+            save_directory.map(Path::to_path_buf),
+            None,
+        )
+        .insert_address_space(16);
+        let (machine, timer) = machine.insert_default_component::<Chip8TimerConfig>("timer");
+        let (machine, audio) = machine.insert_component(
+            "audio",
+            Chip8AudioConfig {
+                sample_rate: Frequency::from_num(44100),
+            },
+        );
+        let (machine, display) = machine.insert_component(
+            "display",
+            Chip8DisplayConfig {
+                half_pixel_lores_scroll: quirks.half_pixel_lores_scroll,
+                ..Default::default()
+            },
+        );
+        let (machine, cpu) = machine.insert_component(
+            "cpu",
+            Chip8ProcessorConfig {
+                cpu_address_space,
+                timer,
+                audio: audio.clone(),
+                display: display.clone(),
+                frequency: Frequency::from_num(1000),
+                quirks,
+                #[cfg(feature = "jit")]
+                jit,
+                _phantom: PhantomData,
+            },
+        );
+        let program_start = usize::from(PROGRAM_START);
+        let (machine, _) = machine.insert_component(
+            "workram",
+            StandardMemoryConfig {
+                readable: true,
+                writable: true,
+                assigned_range: 0x0000..=0xffff,
+                assigned_address_space: cpu_address_space,
+                initial_contents: RangeInclusiveMap::from_iter([
+                    (
+                        0x000..=0x04f,
+                        StandardMemoryInitialContents::Array(Cow::Borrowed(bytemuck::cast_slice(
+                            &CHIP8_FONT,
+                        ))),
+                    ),
+                    (
+                        program_start..=program_start + program.len() - 1,
+                        StandardMemoryInitialContents::Array(Cow::Owned(program.to_vec())),
+                    ),
+                ]),
+                sram: false,
+            },
+        );
+
+        Self {
+            machine: machine.build(()),
+            cpu,
+            display,
+            audio,
+            cpu_address_space,
+        }
+    }
+
+    /// Runs for as long as `count` instructions take, which is short of that
+    /// many if any of them wait
+    pub fn run_instructions(&self, count: u32) {
+        self.machine.run(Period::from_num(count) / 1000);
+    }
+
+    pub fn processor<T>(&self, callback: impl FnOnce(&Chip8Processor<Software>) -> T) -> T {
+        self.machine.interact(&self.cpu, callback).unwrap()
+    }
+
+    pub fn memory(&self, address: u16, length: usize) -> Vec<u8> {
+        let mut memory = vec![0; length];
+
+        self.machine
+            .address_spaces(self.cpu_address_space)
+            .unwrap()
+            .read(address.into(), self.machine.now(), None, &mut memory)
+            .unwrap();
+
+        memory
+    }
+
+    pub fn display<T>(&self, callback: impl FnOnce(&Chip8Display<Software>) -> T) -> T {
+        self.machine.interact(&self.display, callback).unwrap()
+    }
+
+    pub fn audio<T>(&self, callback: impl FnOnce(&Chip8Audio) -> T) -> T {
+        self.machine.interact(&self.audio, callback).unwrap()
+    }
+}
+
+fn xo_chip() -> Chip8Quirks {
+    Chip8Quirks {
+        force_mode: Some(Chip8Mode::XoChip),
+        ..Default::default()
+    }
+}
+
+fn lit_pixels(plane: &DMatrix<bool>) -> Vec<(usize, usize)> {
+    (0..plane.ncols())
+        .flat_map(|y| (0..plane.nrows()).map(move |x| (x, y)))
+        .filter(|&position| plane[position])
+        .collect()
+}
+
+#[test]
+fn rpl_flags_survive_restarts() {
+    let save_directory =
+        std::env::temp_dir().join(format!("fluxemu-{}-chip8-rpl", std::process::id()));
+    let program = [
+        0x60, 0x12, // V0 = 0x12
+        0x61, 0x34, // V1 = 0x34
+        0x62, 0x56, // V2 = 0x56
+        0xf2, 0x75, // Save V0 through V2 to the RPL user flags
+        0x12, 0x08, // Loop forever
+    ];
+
+    let original = TestMachine::with_saves(&program, Chip8Quirks::default(), &save_directory);
+    original.run_instructions(8);
+    original.machine.store_save().unwrap();
+
+    // Only restoring them, so the flags have to come from the save
+    let program = [
+        0xf2, 0x85, // Load V0 through V2 from the RPL user flags
+        0xa3, 0x00, // I = 0x300
+        0xf2, 0x55, // Store V0 through V2 at I
+        0x12, 0x06, // Loop forever
+    ];
+
+    let restarted = TestMachine::with_saves(&program, Chip8Quirks::default(), &save_directory);
+    restarted.run_instructions(8);
+
+    restarted.processor(|processor| {
+        assert_eq!(processor.state.rpl_flags[..3], [0x12, 0x34, 0x56]);
+    });
+    assert_eq!(restarted.memory(0x300, 3), [0x12, 0x34, 0x56]);
+
+    let _ = std::fs::remove_dir_all(&save_directory);
+}
+
+#[test]
+fn programs_without_rpl_flags_store_no_save() {
+    let save_directory =
+        std::env::temp_dir().join(format!("fluxemu-{}-chip8-no-rpl", std::process::id()));
+    let program = [
+        0x60, 0x12, // V0 = 0x12
+        0x12, 0x02, // Loop forever
+    ];
+
+    let machine = TestMachine::with_saves(&program, Chip8Quirks::default(), &save_directory);
+    machine.run_instructions(4);
+    machine.machine.store_save().unwrap();
+
+    assert!(!save_directory.exists());
+}
+
+#[test]
+fn half_pixel_lores_scroll_quirk_reaches_the_display() {
+    let program = [
+        0x60, 0x00, // V0 = 0
+        0xf0, 0x29, // I = the font sprite for 0, which starts with 0b11110000
+        0xd0, 0x01, // Draw its first row at 0, 0
+        0x00, 0xfb, // Scroll right
+        0x12, 0x08, // Loop forever
+    ];
+
+    for (half_pixel_lores_scroll, lit) in [(false, 4..8), (true, 4..12)] {
+        let machine = TestMachine::new(
+            &program,
+            Chip8Quirks {
+                half_pixel_lores_scroll,
+                ..Default::default()
+            },
+            false,
+        );
+        // Drawing waits for the next frame
+        machine.run_instructions(40);
+
+        machine.display(|display| {
+            let plane = &display.planes()[0];
+            let row: Vec<_> = (0..16).filter(|&x| plane[(x, 0)]).collect();
+
+            assert_eq!(row, Vec::from_iter(lit), "{half_pixel_lores_scroll}");
+        });
+    }
+}
+
+#[test]
+fn sprites_draw_on_the_selected_planes() {
+    let program = [
+        0xf3, 0x01, // Select both planes
+        0xa2, 0x16, // I = 0x216
+        0xd0, 0x01, // Draw a row on each plane at 0, 0
+        0xf2, 0x01, // Select the second plane
+        0xa2, 0x18, // I = 0x218
+        0xd0, 0x01, // Draw a row on it at 0, 0
+        0xf1, 0x01, // Select the first plane
+        0x00, 0xe0, // Clear it
+        0xf0, 0x01, // Select no planes
+        0xd0, 0x01, // Draw nothing
+        0x12, 0x14, // Loop forever
+        0x80, 0x40, // A pixel for each plane
+        0x20, // A pixel for the second plane on its own
+    ];
+
+    let machine = TestMachine::new(&program, xo_chip(), false);
+    machine.run_instructions(6);
+
+    machine.display(|display| {
+        assert_eq!(lit_pixels(&display.planes()[0]), [(0, 0)]);
+        assert_eq!(lit_pixels(&display.planes()[1]), [(1, 0), (2, 0)]);
+    });
+
+    machine.run_instructions(4);
+
+    machine.display(|display| {
+        assert!(lit_pixels(&display.planes()[0]).is_empty());
+        assert_eq!(lit_pixels(&display.planes()[1]), [(1, 0), (2, 0)]);
+    });
+}
+
+#[test]
+fn long_index_load() {
+    let program = [
+        0xf0, 0x00, 0x12, 0x34, // I = 0x1234
+        0x30, 0x00, // Skip if V0 = 0, which it is
+        0xf0, 0x00, 0x43, 0x21, // Skipped whole, rather than running 0x4321
+        0x6a, 0x01, // VA = 1
+        0x12, 0x0c, // Loop forever
+    ];
+
+    let machine = TestMachine::new(&program, xo_chip(), false);
+    machine.run_instructions(4);
+
+    machine.processor(|processor| {
+        assert_eq!(processor.state.registers.index, 0x1234);
+        assert_eq!(processor.state.registers.work_registers[0xa], 1);
+        assert_eq!(processor.state.registers.program, 0x20c);
+    });
+}
+
+#[test]
+fn audio_pattern_and_pitch() {
+    let pattern: [u8; 16] = std::array::from_fn(|index| index as u8 * 0x11);
+    let mut program = vec![
+        0xa2, 0x10, // I = 0x210
+        0xf0, 0x02, // Load the audio pattern from I
+        0x60, 0x70, // V0 = 0x70
+        0xf0, 0x3a, // Set the pitch to V0
+        0x12, 0x08, // Loop forever
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // Padding up to 0x210
+    ];
+    program.extend(pattern);
+
+    let machine = TestMachine::new(&program, xo_chip(), false);
+    machine.run_instructions(5);
+
+    machine.audio(|audio| {
+        assert_eq!(audio.pattern(), pattern);
+        assert_eq!(audio.pitch(), 0x70);
+    });
+}
+
+#[test]
+fn register_ranges_go_either_way() {
+    let program = [
+        0x61, 0x01, // V1 = 1
+        0x62, 0x02, // V2 = 2
+        0x63, 0x03, // V3 = 3
+        0xa3, 0x00, // I = 0x300
+        0x51, 0x32, // Save V1 through V3 at I
+        0xa3, 0x10, // I = 0x310
+        0x53, 0x12, // Save V3 down to V1 at I
+        0xa3, 0x00, // I = 0x300
+        0x55, 0x73, // Load V5 through V7 from I
+        0xa3, 0x10, // I = 0x310
+        0x5a, 0x83, // Load VA down to V8 from I
+        0x12, 0x14, // Loop forever
+    ];
+
+    let machine = TestMachine::new(&program, xo_chip(), false);
+    machine.run_instructions(11);
+
+    assert_eq!(machine.memory(0x300, 4), [1, 2, 3, 0]);
+    assert_eq!(machine.memory(0x310, 4), [3, 2, 1, 0]);
+
+    machine.processor(|processor| {
+        let registers = &processor.state.registers;
+
+        assert_eq!(registers.work_registers[4..=0xb], [0, 1, 2, 3, 1, 2, 3, 0]);
+        // Neither moves the index register
+        assert_eq!(registers.index, 0x310);
+    });
+}
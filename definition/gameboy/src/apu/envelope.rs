@@ -0,0 +1,46 @@
+/// Fades a channel's volume in or out at a fixed rate
+#[derive(Debug, Default, Clone, Copy)]
+pub(super) struct Envelope {
+    /// NRx2, which only takes effect on trigger
+    register: u8,
+    pub volume: u8,
+    timer: u8,
+}
+
+impl Envelope {
+    pub fn write(&mut self, value: u8) {
+        self.register = value;
+    }
+
+    /// The upper 5 bits being clear turns the DAC off
+    pub fn dac_enabled(&self) -> bool {
+        self.register & 0xf8 != 0
+    }
+
+    fn period(&self) -> u8 {
+        self.register & 0b111
+    }
+
+    pub fn trigger(&mut self) {
+        self.volume = self.register >> 4;
+        self.timer = self.period();
+    }
+
+    pub fn clock(&mut self) {
+        if self.period() == 0 || self.timer == 0 {
+            return;
+        }
+
+        self.timer -= 1;
+
+        if self.timer == 0 {
+            self.timer = self.period();
+
+            if self.register & 0b1000 != 0 {
+                self.volume = (self.volume + 1).min(15);
+            } else {
+                self.volume = self.volume.saturating_sub(1);
+            }
+        }
+    }
+}
@@ -0,0 +1,40 @@
+/// Silences a channel after a set amount of frame sequencer steps
+#[derive(Debug, Clone, Copy)]
+pub(super) struct LengthCounter {
+    /// 64 for everything but the wave channel, which has 256
+    maximum: u16,
+    remaining: u16,
+    pub enabled: bool,
+}
+
+impl LengthCounter {
+    pub const fn new(maximum: u16) -> Self {
+        Self {
+            maximum,
+            remaining: 0,
+            enabled: false,
+        }
+    }
+
+    /// Games write how far along the counter starts, not how long it lasts
+    pub fn load(&mut self, value: u8) {
+        self.remaining = self.maximum - u16::from(value);
+    }
+
+    pub fn trigger(&mut self) {
+        if self.remaining == 0 {
+            self.remaining = self.maximum;
+        }
+    }
+
+    /// Returns if the channel should be disabled
+    pub fn clock(&mut self) -> bool {
+        if self.enabled && self.remaining != 0 {
+            self.remaining -= 1;
+
+            return self.remaining == 0;
+        }
+
+        false
+    }
+}
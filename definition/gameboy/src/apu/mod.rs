@@ -0,0 +1,376 @@
+use std::ops::RangeInclusive;
+
+use envelope::Envelope;
+use fluxemu_audio::FrameIterator;
+use fluxemu_runtime::{
+    component::{Component, ComponentConfig, SampleSource},
+    machine::builder::{ComponentBuilder, SchedulerParticipation},
+    memory::{Address, AddressSpaceId, MemoryError},
+    path::FluxEmuPath,
+    platform::Platform,
+    scheduler::{Period, SynchronizationContext},
+};
+use nalgebra::SVector;
+use noise::NoiseChannel;
+use pulse::PulseChannel;
+use ringbuffer::{AllocRingBuffer, RingBuffer};
+use wave::WaveChannel;
+
+use crate::MASTER_CLOCK;
+
+mod envelope;
+mod length;
+mod noise;
+mod pulse;
+mod wave;
+
+const APU_ADDRESSES: RangeInclusive<Address> = 0xff10..=0xff3f;
+const REGISTERS: RangeInclusive<Address> = 0xff10..=0xff26;
+const WAVE_RAM: RangeInclusive<Address> = 0xff30..=0xff3f;
+const NR50: Address = 0xff24;
+const NR51: Address = 0xff25;
+const NR52: Address = 0xff26;
+
+/// Bits of each register that read back as set regardless of what was
+/// written, from NR10 to NR52
+const READ_MASKS: [u8; 0x17] = [
+    0x80, 0x3f, 0x00, 0xff, 0xbf, // NR1x
+    0xff, 0x3f, 0x00, 0xff, 0xbf, // NR2x
+    0x7f, 0xff, 0x9f, 0xff, 0xbf, // NR3x
+    0xff, 0xff, 0x00, 0x00, 0xbf, // NR4x
+    0x00, 0x00, 0x70, // NR50, NR51, NR52
+];
+
+/// The APU runs off of every other clock cycle
+const TICK_FREQUENCY: u128 = MASTER_CLOCK / 2;
+/// Ticks between each step of the 512 Hz frame sequencer
+const FRAME_SEQUENCER_DIVIDER: u32 = 4096;
+/// Ticks between each audio sample
+const AUDIO_SAMPLE_DIVIDER: u32 = 48;
+
+const NRX4_TRIGGER: u8 = 0b1000_0000;
+const NRX4_LENGTH_ENABLE: u8 = 0b0100_0000;
+const NR52_POWER: u8 = 0b1000_0000;
+
+/// Two pulse channels, a wave channel and a noise channel mixed into stereo
+#[derive(Debug)]
+pub struct Apu {
+    pulse_channels: [PulseChannel; 2],
+    wave: WaveChannel,
+    noise: NoiseChannel,
+    /// Everything as last written, for reading back
+    registers: [u8; READ_MASKS.len()],
+    powered: bool,
+    frame_sequencer_step: u8,
+    ticks: u32,
+    left_channel: FluxEmuPath,
+    left_buffer: AllocRingBuffer<SVector<f32, 1>>,
+    right_buffer: AllocRingBuffer<SVector<f32, 1>>,
+}
+
+impl Apu {
+    fn channel_enabled(&self) -> [bool; 4] {
+        [
+            self.pulse_channels[0].enabled,
+            self.pulse_channels[1].enabled,
+            self.wave.enabled,
+            self.noise.enabled,
+        ]
+    }
+
+    fn write_register(&mut self, address: Address, data: u8) {
+        self.registers[address - REGISTERS.start()] = data;
+
+        match address {
+            0xff10 => {
+                if let Some(sweep) = &mut self.pulse_channels[0].sweep {
+                    sweep.write(data);
+                }
+            }
+            0xff11 | 0xff16 => self.pulse_channels[pulse_index(address)].write_length(data),
+            0xff12 | 0xff17 => {
+                let channel = &mut self.pulse_channels[pulse_index(address)];
+
+                write_envelope(&mut channel.envelope, &mut channel.enabled, data);
+            }
+            0xff13 | 0xff18 => {
+                let channel = &mut self.pulse_channels[pulse_index(address)];
+
+                channel.frequency = (channel.frequency & 0x700) | u16::from(data);
+            }
+            0xff14 | 0xff19 => {
+                let channel = &mut self.pulse_channels[pulse_index(address)];
+
+                channel.frequency = (channel.frequency & 0xff) | (u16::from(data & 0b111) << 8);
+                channel.length.enabled = data & NRX4_LENGTH_ENABLE != 0;
+
+                if data & NRX4_TRIGGER != 0 {
+                    channel.trigger();
+                }
+            }
+            0xff1a => {
+                self.wave.dac_enabled = data & 0x80 != 0;
+                self.wave.enabled &= self.wave.dac_enabled;
+            }
+            0xff1b => self.wave.length.load(data),
+            0xff1c => self.wave.write_volume(data),
+            0xff1d => self.wave.frequency = (self.wave.frequency & 0x700) | u16::from(data),
+            0xff1e => {
+                self.wave.frequency = (self.wave.frequency & 0xff) | (u16::from(data & 0b111) << 8);
+                self.wave.length.enabled = data & NRX4_LENGTH_ENABLE != 0;
+
+                if data & NRX4_TRIGGER != 0 {
+                    self.wave.trigger();
+                }
+            }
+            0xff20 => self.noise.length.load(data & 0x3f),
+            0xff21 => write_envelope(&mut self.noise.envelope, &mut self.noise.enabled, data),
+            0xff22 => self.noise.control = data,
+            0xff23 => {
+                self.noise.length.enabled = data & NRX4_LENGTH_ENABLE != 0;
+
+                if data & NRX4_TRIGGER != 0 {
+                    self.noise.trigger();
+                }
+            }
+            NR52 => {
+                let powered = data & NR52_POWER != 0;
+
+                // Turning it off clears out every register
+                if self.powered && !powered {
+                    // Apart from wave RAM
+                    let ram = self.wave.ram;
+
+                    self.pulse_channels = [PulseChannel::new(true), PulseChannel::new(false)];
+                    self.wave = WaveChannel::default();
+                    self.wave.ram = ram;
+                    self.noise = NoiseChannel::default();
+                    self.registers = [0; READ_MASKS.len()];
+                }
+
+                if !self.powered && powered {
+                    self.frame_sequencer_step = 0;
+                }
+
+                self.powered = powered;
+            }
+            _ => {}
+        }
+    }
+
+    fn clock_frame_sequencer(&mut self) {
+        let step = self.frame_sequencer_step;
+        self.frame_sequencer_step = (step + 1) % 8;
+
+        if step.is_multiple_of(2) {
+            for channel in &mut self.pulse_channels {
+                if channel.length.clock() {
+                    channel.enabled = false;
+                }
+            }
+
+            if self.wave.length.clock() {
+                self.wave.enabled = false;
+            }
+
+            if self.noise.length.clock() {
+                self.noise.enabled = false;
+            }
+        }
+
+        if step == 2 || step == 6 {
+            self.pulse_channels[0].clock_sweep();
+        }
+
+        if step == 7 {
+            for channel in &mut self.pulse_channels {
+                channel.envelope.clock();
+            }
+
+            self.noise.envelope.clock();
+        }
+    }
+
+    fn sample_audio(&mut self) {
+        let dac_enabled = [
+            self.pulse_channels[0].envelope.dac_enabled(),
+            self.pulse_channels[1].envelope.dac_enabled(),
+            self.wave.dac_enabled,
+            self.noise.envelope.dac_enabled(),
+        ];
+        let outputs = [
+            self.pulse_channels[0].output(),
+            self.pulse_channels[1].output(),
+            self.wave.output(),
+            self.noise.output(),
+        ];
+
+        // A DAC that is on but given 0 still outputs its most negative level
+        let analog = std::array::from_fn::<_, 4, _>(|index| {
+            if dac_enabled[index] && self.powered {
+                1.0 - f32::from(outputs[index]) / 7.5
+            } else {
+                0.0
+            }
+        });
+
+        let panning = self.registers[NR51 - REGISTERS.start()];
+        let volumes = self.registers[NR50 - REGISTERS.start()];
+
+        // NR51 has the right side in the lower nibble
+        let [right, left] = [0, 4].map(|shift| {
+            let volume = f32::from((volumes >> shift) & 0b111) + 1.0;
+
+            let sum = analog
+                .iter()
+                .enumerate()
+                .filter(|(index, _)| panning & (1 << (index + shift)) != 0)
+                .map(|(_, sample)| sample)
+                .sum::<f32>();
+
+            sum / 4.0 * volume / 8.0
+        });
+
+        self.left_buffer.enqueue(SVector::from([left]));
+        self.right_buffer.enqueue(SVector::from([right]));
+    }
+}
+
+impl Component for Apu {
+    fn memory_read(
+        &self,
+        address: Address,
+        _address_space: AddressSpaceId,
+        _avoid_side_effects: bool,
+        buffer: &mut [u8],
+    ) -> Result<(), MemoryError> {
+        buffer[0] = if address == NR52 {
+            let enabled = self
+                .channel_enabled()
+                .into_iter()
+                .enumerate()
+                .fold(0, |bits, (index, enabled)| {
+                    bits | (u8::from(enabled) << index)
+                });
+
+            READ_MASKS[NR52 - REGISTERS.start()] | (u8::from(self.powered) << 7) | enabled
+        } else if REGISTERS.contains(&address) {
+            let index = address - REGISTERS.start();
+
+            self.registers[index] | READ_MASKS[index]
+        } else if WAVE_RAM.contains(&address) {
+            self.wave.ram[address - WAVE_RAM.start()]
+        } else {
+            0xff
+        };
+
+        Ok(())
+    }
+
+    fn memory_write(
+        &mut self,
+        address: Address,
+        _address_space: AddressSpaceId,
+        buffer: &[u8],
+    ) -> Result<(), MemoryError> {
+        let data = buffer[0];
+
+        if WAVE_RAM.contains(&address) {
+            self.wave.ram[address - WAVE_RAM.start()] = data;
+        } else if REGISTERS.contains(&address) && (self.powered || address == NR52) {
+            self.write_register(address, data);
+        }
+
+        Ok(())
+    }
+
+    fn get_audio_channel(&mut self, audio_output_path: &FluxEmuPath) -> SampleSource<'_> {
+        let buffer = if *audio_output_path == self.left_channel {
+            &mut self.left_buffer
+        } else {
+            &mut self.right_buffer
+        };
+
+        SampleSource {
+            source: Box::new(buffer.drain().repeat_last_frame()),
+            sample_rate: (TICK_FREQUENCY / u128::from(AUDIO_SAMPLE_DIVIDER)) as f32,
+        }
+    }
+
+    fn synchronize(&mut self, mut context: SynchronizationContext) {
+        for _ in context.allocate(Period::ONE / TICK_FREQUENCY, None) {
+            if self.powered {
+                for channel in &mut self.pulse_channels {
+                    channel.tick();
+                }
+
+                self.wave.tick();
+                self.noise.tick();
+
+                if self.ticks.is_multiple_of(FRAME_SEQUENCER_DIVIDER) {
+                    self.clock_frame_sequencer();
+                }
+            }
+
+            if self.ticks.is_multiple_of(AUDIO_SAMPLE_DIVIDER) {
+                self.sample_audio();
+            }
+
+            self.ticks = self.ticks.wrapping_add(1);
+        }
+    }
+
+    fn needs_work(&self, delta: Period) -> bool {
+        delta >= Period::ONE / TICK_FREQUENCY
+    }
+}
+
+/// Channel 2 has the same layout as channel 1, just 5 registers later
+fn pulse_index(address: Address) -> usize {
+    usize::from(address >= 0xff15)
+}
+
+/// NRx2, which turns the channel off along with the DAC
+fn write_envelope(envelope: &mut Envelope, enabled: &mut bool, data: u8) {
+    envelope.write(data);
+
+    if !envelope.dac_enabled() {
+        *enabled = false;
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct ApuConfig {
+    pub cpu_address_space: AddressSpaceId,
+}
+
+impl<P: Platform> ComponentConfig<P> for ApuConfig {
+    type Component = Apu;
+
+    fn build_component(
+        self,
+        component_builder: ComponentBuilder<'_, P, Self::Component>,
+    ) -> Result<Self::Component, Box<dyn std::error::Error>> {
+        let component_builder =
+            component_builder.set_scheduler_participation(SchedulerParticipation::SchedulerDriven);
+
+        let (component_builder, left_channel) = component_builder.insert_audio_channel("left");
+        let (component_builder, _) = component_builder.insert_audio_channel("right");
+
+        component_builder.memory_map_component(self.cpu_address_space, APU_ADDRESSES);
+
+        Ok(Apu {
+            pulse_channels: [PulseChannel::new(true), PulseChannel::new(false)],
+            wave: WaveChannel::default(),
+            noise: NoiseChannel::default(),
+            registers: [0; READ_MASKS.len()],
+            powered: false,
+            frame_sequencer_step: 0,
+            ticks: 0,
+            left_channel,
+            // Roughly a frame of samples
+            left_buffer: AllocRingBuffer::new(1024),
+            right_buffer: AllocRingBuffer::new(1024),
+        })
+    }
+}
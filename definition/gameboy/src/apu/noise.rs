@@ -0,0 +1,72 @@
+use super::{envelope::Envelope, length::LengthCounter};
+
+/// Pseudo random noise out of a linear feedback shift register
+#[derive(Debug, Clone, Copy)]
+pub(super) struct NoiseChannel {
+    pub enabled: bool,
+    pub length: LengthCounter,
+    pub envelope: Envelope,
+    /// NR43
+    pub control: u8,
+    lfsr: u16,
+    timer: u32,
+}
+
+impl Default for NoiseChannel {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            length: LengthCounter::new(64),
+            envelope: Envelope::default(),
+            control: 0,
+            lfsr: 0x7fff,
+            timer: 0,
+        }
+    }
+}
+
+impl NoiseChannel {
+    pub fn trigger(&mut self) {
+        self.enabled = self.envelope.dac_enabled();
+        self.length.trigger();
+        self.envelope.trigger();
+        self.lfsr = 0x7fff;
+        self.timer = self.period();
+    }
+
+    /// In APU ticks, from the divisor code shifted by the clock shift
+    fn period(&self) -> u32 {
+        let divisor = match self.control & 0b111 {
+            0 => 4,
+            code => u32::from(code) * 8,
+        };
+
+        divisor << (self.control >> 4)
+    }
+
+    pub fn tick(&mut self) {
+        self.timer = self.timer.saturating_sub(1);
+
+        if self.timer != 0 {
+            return;
+        }
+
+        self.timer = self.period();
+
+        let feedback = (self.lfsr ^ (self.lfsr >> 1)) & 1;
+        self.lfsr = (self.lfsr >> 1) | (feedback << 14);
+
+        // Short mode also feeds back into bit 6, for a 7 bit sequence
+        if self.control & 0b1000 != 0 {
+            self.lfsr = (self.lfsr & !(1 << 6)) | (feedback << 6);
+        }
+    }
+
+    pub fn output(&self) -> u8 {
+        if self.enabled && self.lfsr & 1 == 0 {
+            self.envelope.volume
+        } else {
+            0
+        }
+    }
+}
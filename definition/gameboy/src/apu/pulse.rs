@@ -0,0 +1,158 @@
+use super::{envelope::Envelope, length::LengthCounter};
+
+/// Which steps of the 8 step cycle are high, by duty
+const DUTY_CYCLES: [u8; 4] = [0b0000_0001, 0b1000_0001, 0b1000_0111, 0b0111_1110];
+
+/// Channel 1's frequency sweep
+#[derive(Debug, Default, Clone, Copy)]
+pub(super) struct Sweep {
+    /// NR10
+    register: u8,
+    shadow_frequency: u16,
+    timer: u8,
+    enabled: bool,
+}
+
+impl Sweep {
+    pub fn write(&mut self, value: u8) {
+        self.register = value & 0x7f;
+    }
+
+    fn period(&self) -> u8 {
+        (self.register >> 4) & 0b111
+    }
+
+    fn shift(&self) -> u8 {
+        self.register & 0b111
+    }
+
+    /// Counts a period of 0 as 8
+    fn reload_timer(&mut self) {
+        self.timer = match self.period() {
+            0 => 8,
+            period => period,
+        };
+    }
+
+    /// The next frequency, which overflowing past 11 bits disables the channel
+    fn calculate(&self) -> Option<u16> {
+        let delta = self.shadow_frequency >> self.shift();
+
+        let frequency = if self.register & 0b1000 != 0 {
+            self.shadow_frequency - delta
+        } else {
+            self.shadow_frequency + delta
+        };
+
+        (frequency <= 0x7ff).then_some(frequency)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(super) struct PulseChannel {
+    pub enabled: bool,
+    pub length: LengthCounter,
+    pub envelope: Envelope,
+    /// Only channel 1 has one
+    pub sweep: Option<Sweep>,
+    duty: u8,
+    duty_position: u8,
+    pub frequency: u16,
+    timer: u16,
+}
+
+impl PulseChannel {
+    pub fn new(sweep: bool) -> Self {
+        Self {
+            enabled: false,
+            length: LengthCounter::new(64),
+            envelope: Envelope::default(),
+            sweep: sweep.then(Sweep::default),
+            duty: 0,
+            duty_position: 0,
+            frequency: 0,
+            timer: 0,
+        }
+    }
+
+    /// NRx1, which shares the duty with the length
+    pub fn write_length(&mut self, value: u8) {
+        self.duty = value >> 6;
+        self.length.load(value & 0x3f);
+    }
+
+    pub fn trigger(&mut self) {
+        self.enabled = self.envelope.dac_enabled();
+        self.length.trigger();
+        self.envelope.trigger();
+        self.timer = self.period();
+
+        if let Some(sweep) = &mut self.sweep {
+            sweep.shadow_frequency = self.frequency;
+            sweep.reload_timer();
+            sweep.enabled = sweep.period() != 0 || sweep.shift() != 0;
+
+            // The overflow check happens straight away
+            if sweep.shift() != 0 && sweep.calculate().is_none() {
+                self.enabled = false;
+            }
+        }
+    }
+
+    /// In APU ticks, which are 2 clock cycles
+    fn period(&self) -> u16 {
+        (0x800 - self.frequency) * 2
+    }
+
+    pub fn tick(&mut self) {
+        self.timer = self.timer.saturating_sub(1);
+
+        if self.timer == 0 {
+            self.timer = self.period();
+            self.duty_position = (self.duty_position + 1) % 8;
+        }
+    }
+
+    pub fn clock_sweep(&mut self) {
+        let Some(sweep) = &mut self.sweep else {
+            return;
+        };
+
+        sweep.timer = sweep.timer.saturating_sub(1);
+
+        if sweep.timer != 0 {
+            return;
+        }
+
+        sweep.reload_timer();
+
+        if !sweep.enabled || sweep.period() == 0 {
+            return;
+        }
+
+        match sweep.calculate() {
+            Some(frequency) if sweep.shift() != 0 => {
+                sweep.shadow_frequency = frequency;
+                self.frequency = frequency;
+
+                // And then again, only to check for overflow
+                if sweep.calculate().is_none() {
+                    self.enabled = false;
+                }
+            }
+            Some(_) => {}
+            None => self.enabled = false,
+        }
+    }
+
+    /// What goes into the DAC, from 0 to 15
+    pub fn output(&self) -> u8 {
+        let high = DUTY_CYCLES[usize::from(self.duty)] & (0x80 >> self.duty_position) != 0;
+
+        if self.enabled && high {
+            self.envelope.volume
+        } else {
+            0
+        }
+    }
+}
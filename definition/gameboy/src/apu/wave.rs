@@ -0,0 +1,76 @@
+use super::length::LengthCounter;
+
+/// Plays back 32 4 bit samples out of wave RAM
+#[derive(Debug, Clone, Copy)]
+pub(super) struct WaveChannel {
+    pub enabled: bool,
+    /// NR30
+    pub dac_enabled: bool,
+    pub length: LengthCounter,
+    /// NR32, as how far the samples get shifted down
+    volume_shift: u8,
+    pub frequency: u16,
+    timer: u16,
+    position: u8,
+    pub ram: [u8; 16],
+}
+
+impl Default for WaveChannel {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            dac_enabled: false,
+            length: LengthCounter::new(256),
+            volume_shift: 4,
+            frequency: 0,
+            timer: 0,
+            position: 0,
+            ram: [0; 16],
+        }
+    }
+}
+
+impl WaveChannel {
+    pub fn write_volume(&mut self, value: u8) {
+        self.volume_shift = match (value >> 5) & 0b11 {
+            0 => 4,
+            code => code - 1,
+        };
+    }
+
+    pub fn trigger(&mut self) {
+        self.enabled = self.dac_enabled;
+        self.length.trigger();
+        self.timer = self.period();
+        self.position = 0;
+    }
+
+    fn period(&self) -> u16 {
+        0x800 - self.frequency
+    }
+
+    pub fn tick(&mut self) {
+        self.timer = self.timer.saturating_sub(1);
+
+        if self.timer == 0 {
+            self.timer = self.period();
+            self.position = (self.position + 1) % 32;
+        }
+    }
+
+    pub fn output(&self) -> u8 {
+        if !self.enabled {
+            return 0;
+        }
+
+        // The high nibble plays first
+        let byte = self.ram[usize::from(self.position / 2)];
+        let sample = if self.position.is_multiple_of(2) {
+            byte >> 4
+        } else {
+            byte & 0xf
+        };
+
+        sample >> self.volume_shift
+    }
+}
@@ -0,0 +1,159 @@
+use std::{ops::RangeInclusive, sync::Weak};
+
+use bytes::Bytes;
+use fluxemu_runtime::{
+    component::{Component, ComponentConfig, LateInitializedData},
+    machine::{Machine, builder::ComponentBuilder},
+    memory::{
+        Address, AddressSpaceId, MapTarget, MemoryError, MemoryRemappingCommand, Permissions,
+    },
+    path::FluxEmuPath,
+    platform::Platform,
+};
+
+const BOOT_ADDRESSES: RangeInclusive<Address> = 0x0000..=0x00ff;
+/// BANK, which unmaps the boot program when written to
+const BOOT_DISABLE: Address = 0xff50;
+/// Where the boot program leaves off, so the write to BANK is its last
+/// instruction
+const HANDOFF_ADDRESS: usize = 0x00fe;
+
+/// Stands in for the boot ROM
+///
+/// Rather than needing a dump of it, a short program puts the machine in the
+/// state the real one leaves it in, minus the logo scroll
+#[derive(Debug)]
+pub struct Boot {
+    cartridge: FluxEmuPath,
+    cpu_address_space: AddressSpaceId,
+    finished: bool,
+    machine: Weak<Machine>,
+}
+
+impl Component for Boot {
+    fn memory_read(
+        &self,
+        _address: Address,
+        _address_space: AddressSpaceId,
+        _avoid_side_effects: bool,
+        buffer: &mut [u8],
+    ) -> Result<(), MemoryError> {
+        buffer[0] = 0xff;
+
+        Ok(())
+    }
+
+    fn memory_write(
+        &mut self,
+        _address: Address,
+        _address_space: AddressSpaceId,
+        buffer: &[u8],
+    ) -> Result<(), MemoryError> {
+        // Once it's gone the only way back is a reset
+        if buffer[0] == 0 || self.finished {
+            return Ok(());
+        }
+
+        self.finished = true;
+
+        self.machine.upgrade().unwrap().remap_address_space(
+            self.cpu_address_space,
+            vec![MemoryRemappingCommand::Map {
+                range: BOOT_ADDRESSES,
+                target: MapTarget::Component(self.cartridge.clone()),
+                permissions: Permissions::all(),
+            }],
+        );
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct BootConfig {
+    pub cartridge: FluxEmuPath,
+    pub cpu_address_space: AddressSpaceId,
+    pub cgb: bool,
+}
+
+impl<P: Platform> ComponentConfig<P> for BootConfig {
+    type Component = Boot;
+
+    fn late_initialize(component: &mut Self::Component, data: &LateInitializedData<P>) {
+        component.machine = data.machine.clone();
+    }
+
+    fn build_component(
+        self,
+        component_builder: ComponentBuilder<'_, P, Self::Component>,
+    ) -> Result<Self::Component, Box<dyn std::error::Error>> {
+        let (component_builder, program) = component_builder.memory_register_buffer(
+            self.cpu_address_space,
+            "program",
+            boot_program(self.cgb),
+        );
+
+        component_builder
+            .memory_map_buffer_read(self.cpu_address_space, BOOT_ADDRESSES, &program)
+            .memory_map_component_write(self.cpu_address_space, BOOT_DISABLE..=BOOT_DISABLE);
+
+        Ok(Boot {
+            cartridge: self.cartridge,
+            cpu_address_space: self.cpu_address_space,
+            finished: false,
+            machine: Weak::new(),
+        })
+    }
+}
+
+/// Sets up the stack, the sound and video registers, and the registers games
+/// use to tell the models apart
+fn boot_program(cgb: bool) -> Bytes {
+    let [af, bc, de, hl] = if cgb {
+        [0x1180, 0x0000, 0xff56, 0x000d]
+    } else {
+        [0x01b0, 0x0013, 0x00d8, 0x014d]
+    };
+
+    let mut program = vec![0x31, 0xfe, 0xff];
+
+    // NR52, NR50, NR51, BGP, LCDC
+    for (register, value) in [
+        (0x26, 0x80),
+        (0x24, 0x77),
+        (0x25, 0xf3),
+        (0x47, 0xfc),
+        (0x40, 0x91),
+    ] {
+        program.extend([0x3e, value, 0xe0, register]);
+    }
+
+    // Flags can only be loaded through the stack
+    program.extend([0x21, af as u8, (af >> 8) as u8, 0xe5, 0xf1]);
+
+    for (opcode, value) in [(0x01, bc), (0x11, de), (0x21, hl)] {
+        program.extend([opcode, value as u8, (value >> 8) as u8]);
+    }
+
+    program.extend([0xc3, HANDOFF_ADDRESS as u8, 0x00]);
+    program.resize(HANDOFF_ADDRESS, 0x00);
+    // LDH (BANK),A, after which execution falls through into the cartridge
+    program.extend([0xe0, BOOT_DISABLE as u8]);
+
+    Bytes::from(program)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn program_fills_the_boot_window() {
+        for cgb in [false, true] {
+            let program = boot_program(cgb);
+
+            assert_eq!(program.len(), BOOT_ADDRESSES.count());
+            assert_eq!(program[HANDOFF_ADDRESS..], [0xe0, 0x50]);
+        }
+    }
+}
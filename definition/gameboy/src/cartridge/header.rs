@@ -0,0 +1,136 @@
+use std::ops::Range;
+
+use thiserror::Error;
+
+/// Where the header sits in the ROM
+pub const HEADER: Range<usize> = 0x100..0x150;
+
+#[derive(Error, Debug)]
+pub enum ParsingError {
+    #[error("ROM of {size} bytes is too small to have a header")]
+    TooSmall { size: usize },
+    #[error("Unsupported cartridge type {cartridge_type:#04x}")]
+    UnsupportedCartridgeType { cartridge_type: u8 },
+    #[error("Unknown ROM size {code:#04x}")]
+    UnknownRomSize { code: u8 },
+    #[error("Unknown RAM size {code:#04x}")]
+    UnknownRamSize { code: u8 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapperKind {
+    /// 32KiB of ROM, with optional RAM
+    None,
+    Mbc1,
+    /// Has 512 nibbles of RAM built in
+    Mbc2,
+    Mbc3,
+    Mbc5,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CgbSupport {
+    None,
+    /// Works on both, with color when available
+    Enhanced,
+    Exclusive,
+}
+
+/// The fields of the cartridge header the hardware cares about
+///
+/// ```text
+/// 0x104 Nintendo logo
+/// 0x134 title, which is shortened by the fields after it on newer carts
+/// 0x143 CGB flag
+/// 0x147 cartridge type
+/// 0x148 ROM size
+/// 0x149 RAM size
+/// ```
+#[derive(Clone, Debug)]
+pub struct CartridgeHeader {
+    pub title: String,
+    pub cgb_support: CgbSupport,
+    pub mapper: MapperKind,
+    pub battery: bool,
+    /// The MBC3 real time clock
+    pub timer: bool,
+    pub rumble: bool,
+    pub rom_size: usize,
+    /// External RAM, not counting what the MBC2 has built in
+    pub ram_size: usize,
+}
+
+impl CartridgeHeader {
+    pub fn parse(rom: &[u8]) -> Result<Self, ParsingError> {
+        if rom.len() < HEADER.end {
+            return Err(ParsingError::TooSmall { size: rom.len() });
+        }
+
+        let cgb_support = match rom[0x143] {
+            0x80 => CgbSupport::Enhanced,
+            0xc0 => CgbSupport::Exclusive,
+            _ => CgbSupport::None,
+        };
+
+        // The CGB flag and manufacturer code took over the end of the title
+        let title_end = if cgb_support == CgbSupport::None {
+            0x144
+        } else {
+            0x13f
+        };
+
+        let cartridge_type = rom[0x147];
+        let (mapper, battery, timer, rumble) = match cartridge_type {
+            0x00 | 0x08 => (MapperKind::None, false, false, false),
+            0x09 => (MapperKind::None, true, false, false),
+            0x01 | 0x02 => (MapperKind::Mbc1, false, false, false),
+            0x03 => (MapperKind::Mbc1, true, false, false),
+            0x05 => (MapperKind::Mbc2, false, false, false),
+            0x06 => (MapperKind::Mbc2, true, false, false),
+            0x0f | 0x10 => (MapperKind::Mbc3, true, true, false),
+            0x11 | 0x12 => (MapperKind::Mbc3, false, false, false),
+            0x13 => (MapperKind::Mbc3, true, false, false),
+            0x19 | 0x1a => (MapperKind::Mbc5, false, false, false),
+            0x1b => (MapperKind::Mbc5, true, false, false),
+            0x1c | 0x1d => (MapperKind::Mbc5, false, false, true),
+            0x1e => (MapperKind::Mbc5, true, false, true),
+            cartridge_type => {
+                return Err(ParsingError::UnsupportedCartridgeType { cartridge_type });
+            }
+        };
+
+        let rom_size = match rom[0x148] {
+            code @ 0x00..=0x08 => 0x8000 << code,
+            code => return Err(ParsingError::UnknownRomSize { code }),
+        };
+
+        let ram_size = match rom[0x149] {
+            0x00 | 0x01 => 0,
+            0x02 => 0x2000,
+            0x03 => 0x8000,
+            0x04 => 0x20000,
+            0x05 => 0x10000,
+            code => return Err(ParsingError::UnknownRamSize { code }),
+        };
+
+        Ok(Self {
+            title: parse_string(&rom[0x134..title_end]),
+            cgb_support,
+            mapper,
+            battery,
+            timer,
+            rumble,
+            rom_size,
+            ram_size,
+        })
+    }
+}
+
+fn parse_string(bytes: &[u8]) -> String {
+    let end = bytes
+        .iter()
+        .position(|byte| *byte == 0)
+        .unwrap_or(bytes.len());
+
+    String::from_utf8_lossy(&bytes[..end]).trim().to_string()
+}
@@ -0,0 +1,468 @@
+use std::{
+    io::{Read, Write},
+    ops::RangeInclusive,
+};
+
+use bytes::Bytes;
+use fluxemu_runtime::{
+    component::{Component, ComponentConfig, ComponentVersion},
+    machine::builder::ComponentBuilder,
+    memory::{Address, AddressSpaceId, MemoryError},
+    platform::Platform,
+};
+use header::MapperKind;
+pub use header::{CartridgeHeader, CgbSupport};
+use rtc::Rtc;
+
+pub mod header;
+mod rtc;
+
+pub const ROM_ADDRESSES: RangeInclusive<Address> = 0x0000..=0x7fff;
+pub const RAM_ADDRESSES: RangeInclusive<Address> = 0xa000..=0xbfff;
+const ROM_BANK_SIZE: usize = 0x4000;
+const RAM_BANK_SIZE: usize = 0x2000;
+/// The MBC2 has 512 nibbles of RAM, mirrored over the whole window
+const MBC2_RAM_SIZE: usize = 0x200;
+
+/// Bank registers of each memory bank controller
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mbc {
+    None,
+    Mbc1 {
+        /// BANK1, 5 bits where 0 acts as 1
+        rom_bank: u8,
+        /// BANK2, 2 bits that go above `rom_bank` or select the RAM bank
+        upper_bank: u8,
+        /// MODE, where the upper bits also apply to the first ROM bank and RAM
+        advanced_banking: bool,
+    },
+    Mbc2 {
+        rom_bank: u8,
+    },
+    Mbc3 {
+        rom_bank: u8,
+        /// 0x00 to 0x03 select RAM, and 0x08 to 0x0c select clock registers
+        ram_bank: u8,
+    },
+    Mbc5 {
+        rom_bank: u16,
+        ram_bank: u8,
+    },
+}
+
+/// A game cartridge, and the memory bank controller inside it
+#[derive(Debug)]
+pub struct GameBoyCartridge {
+    rom: Bytes,
+    ram: Vec<u8>,
+    mbc: Mbc,
+    ram_enabled: bool,
+    battery: bool,
+    rumble: bool,
+    rtc: Option<Rtc>,
+}
+
+impl GameBoyCartridge {
+    fn rom_bank_count(&self) -> usize {
+        self.rom.len().div_ceil(ROM_BANK_SIZE).max(1)
+    }
+
+    fn read_rom(&self, bank: usize, address: Address) -> u8 {
+        let bank = bank % self.rom_bank_count();
+
+        self.rom
+            .get(bank * ROM_BANK_SIZE + (address & (ROM_BANK_SIZE - 1)))
+            .copied()
+            .unwrap_or(0xff)
+    }
+
+    /// Which ROM banks are in the lower and upper halves of the window
+    fn rom_banks(&self) -> (usize, usize) {
+        match self.mbc {
+            Mbc::None => (0, 1),
+            Mbc::Mbc1 {
+                rom_bank,
+                upper_bank,
+                advanced_banking,
+            } => {
+                let upper = usize::from(upper_bank) << 5;
+                let lower = if advanced_banking { upper } else { 0 };
+
+                (lower, upper | usize::from(rom_bank.max(1)))
+            }
+            Mbc::Mbc2 { rom_bank } | Mbc::Mbc3 { rom_bank, .. } => {
+                (0, usize::from(rom_bank.max(1)))
+            }
+            // The MBC5 can actually map bank 0 into the upper half
+            Mbc::Mbc5 { rom_bank, .. } => (0, usize::from(rom_bank)),
+        }
+    }
+
+    /// Where in RAM an address in the RAM window ends up
+    fn ram_offset(&self, address: Address) -> Option<usize> {
+        if !self.ram_enabled || self.ram.is_empty() {
+            return None;
+        }
+
+        let offset = address - RAM_ADDRESSES.start();
+
+        let bank = match self.mbc {
+            Mbc::None => 0,
+            Mbc::Mbc1 {
+                upper_bank,
+                advanced_banking,
+                ..
+            } => {
+                if advanced_banking {
+                    usize::from(upper_bank)
+                } else {
+                    0
+                }
+            }
+            Mbc::Mbc2 { .. } => return Some(offset % MBC2_RAM_SIZE),
+            Mbc::Mbc3 { ram_bank, .. } if ram_bank <= 0x03 => usize::from(ram_bank),
+            Mbc::Mbc3 { .. } => return None,
+            Mbc::Mbc5 { ram_bank, .. } => usize::from(ram_bank),
+        };
+
+        Some((bank * RAM_BANK_SIZE + offset) % self.ram.len())
+    }
+
+    /// The MBC3 clock register selected instead of RAM, if any
+    fn selected_rtc_register(&self) -> Option<u8> {
+        match self.mbc {
+            Mbc::Mbc3 { ram_bank, .. }
+                if self.ram_enabled && self.rtc.is_some() && (0x08..=0x0c).contains(&ram_bank) =>
+            {
+                Some(ram_bank)
+            }
+            _ => None,
+        }
+    }
+
+    fn write_mbc_register(&mut self, address: Address, data: u8) {
+        let ram_enable = data & 0x0f == 0x0a;
+
+        match (&mut self.mbc, address) {
+            (Mbc::None, _) => {}
+            (Mbc::Mbc1 { .. } | Mbc::Mbc3 { .. } | Mbc::Mbc5 { .. }, 0x0000..=0x1fff) => {
+                self.ram_enabled = ram_enable;
+            }
+            (Mbc::Mbc1 { rom_bank, .. }, 0x2000..=0x3fff) => *rom_bank = data & 0x1f,
+            (Mbc::Mbc1 { upper_bank, .. }, 0x4000..=0x5fff) => *upper_bank = data & 0x03,
+            (
+                Mbc::Mbc1 {
+                    advanced_banking, ..
+                },
+                0x6000..=0x7fff,
+            ) => *advanced_banking = data & 0x01 != 0,
+            // Bit 8 of the address picks between the two registers
+            (Mbc::Mbc2 { rom_bank }, 0x0000..=0x3fff) => {
+                if address & 0x100 == 0 {
+                    self.ram_enabled = ram_enable;
+                } else {
+                    *rom_bank = data & 0x0f;
+                }
+            }
+            (Mbc::Mbc3 { rom_bank, .. }, 0x2000..=0x3fff) => *rom_bank = data & 0x7f,
+            (Mbc::Mbc3 { ram_bank, .. }, 0x4000..=0x5fff) => *ram_bank = data,
+            (Mbc::Mbc3 { .. }, 0x6000..=0x7fff) => {
+                if let Some(rtc) = &mut self.rtc {
+                    rtc.write_latch(data);
+                }
+            }
+            (Mbc::Mbc5 { rom_bank, .. }, 0x2000..=0x2fff) => {
+                *rom_bank = (*rom_bank & 0x100) | u16::from(data);
+            }
+            (Mbc::Mbc5 { rom_bank, .. }, 0x3000..=0x3fff) => {
+                *rom_bank = (*rom_bank & 0x0ff) | (u16::from(data & 0x01) << 8);
+            }
+            (Mbc::Mbc5 { ram_bank, .. }, 0x4000..=0x5fff) => {
+                // Rumble carts drive the motor with bit 3 instead
+                if self.rumble {
+                    *ram_bank = data & 0x07;
+                } else {
+                    *ram_bank = data & 0x0f;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn read(&self, address: Address) -> u8 {
+        match address {
+            0x0000..=0x3fff => self.read_rom(self.rom_banks().0, address),
+            0x4000..=0x7fff => self.read_rom(self.rom_banks().1, address),
+            _ => {
+                if let Some(register) = self.selected_rtc_register() {
+                    self.rtc.as_ref().unwrap().read(register)
+                } else {
+                    match (self.ram_offset(address), self.mbc) {
+                        // Only the lower nibbles exist
+                        (Some(offset), Mbc::Mbc2 { .. }) => self.ram[offset] | 0xf0,
+                        (Some(offset), _) => self.ram[offset],
+                        (None, _) => 0xff,
+                    }
+                }
+            }
+        }
+    }
+
+    fn write(&mut self, address: Address, data: u8) {
+        if ROM_ADDRESSES.contains(&address) {
+            self.write_mbc_register(address, data);
+        } else if let Some(register) = self.selected_rtc_register() {
+            self.rtc.as_mut().unwrap().write(register, data);
+        } else if let Some(offset) = self.ram_offset(address) {
+            self.ram[offset] = data;
+        }
+    }
+}
+
+impl Component for GameBoyCartridge {
+    fn save_version(&self) -> Option<ComponentVersion> {
+        self.battery.then_some(0)
+    }
+
+    /// Saves are the raw RAM, followed by the clock in the format other
+    /// emulators use
+    fn store_save(&self, mut writer: Box<dyn Write>) -> Result<(), Box<dyn std::error::Error>> {
+        writer.write_all(&self.ram)?;
+
+        if let Some(rtc) = &self.rtc {
+            // Folding in day counter overflow is not visible to the game
+            writer.write_all(&rtc.clone().store())?;
+        }
+
+        Ok(())
+    }
+
+    fn memory_read(
+        &self,
+        address: Address,
+        _address_space: AddressSpaceId,
+        _avoid_side_effects: bool,
+        buffer: &mut [u8],
+    ) -> Result<(), MemoryError> {
+        buffer[0] = self.read(address);
+
+        Ok(())
+    }
+
+    fn memory_write(
+        &mut self,
+        address: Address,
+        _address_space: AddressSpaceId,
+        buffer: &[u8],
+    ) -> Result<(), MemoryError> {
+        self.write(address, buffer[0]);
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct GameBoyCartridgeConfig {
+    pub rom: Bytes,
+    pub header: CartridgeHeader,
+    pub cpu_address_space: AddressSpaceId,
+}
+
+impl<P: Platform> ComponentConfig<P> for GameBoyCartridgeConfig {
+    type Component = GameBoyCartridge;
+
+    fn build_component(
+        self,
+        component_builder: ComponentBuilder<'_, P, Self::Component>,
+    ) -> Result<Self::Component, Box<dyn std::error::Error>> {
+        let header = self.header;
+
+        tracing::info!(
+            "Loading \"{}\" with a {:?} mapper, {} bytes of ROM and {} bytes of RAM",
+            header.title,
+            header.mapper,
+            header.rom_size,
+            header.ram_size
+        );
+
+        if header.rom_size != self.rom.len() {
+            tracing::warn!(
+                "Header says the ROM is {} bytes, but it is {} bytes",
+                header.rom_size,
+                self.rom.len()
+            );
+        }
+
+        let mbc = match header.mapper {
+            MapperKind::None => Mbc::None,
+            MapperKind::Mbc1 => Mbc::Mbc1 {
+                rom_bank: 1,
+                upper_bank: 0,
+                advanced_banking: false,
+            },
+            MapperKind::Mbc2 => Mbc::Mbc2 { rom_bank: 1 },
+            MapperKind::Mbc3 => Mbc::Mbc3 {
+                rom_bank: 1,
+                ram_bank: 0,
+            },
+            MapperKind::Mbc5 => Mbc::Mbc5 {
+                rom_bank: 1,
+                ram_bank: 0,
+            },
+        };
+
+        let ram_size = if header.mapper == MapperKind::Mbc2 {
+            MBC2_RAM_SIZE
+        } else {
+            header.ram_size
+        };
+
+        let mut cartridge = GameBoyCartridge {
+            rom: self.rom,
+            ram: vec![0xff; ram_size],
+            mbc,
+            ram_enabled: false,
+            battery: header.battery,
+            rumble: header.rumble,
+            rtc: header.timer.then(Rtc::default),
+        };
+
+        if let Some((mut save, version)) = component_builder.save() {
+            if version != 0 {
+                return Err("Invalid save version".into());
+            }
+
+            save.read_exact(&mut cartridge.ram)?;
+
+            if let Some(rtc) = &mut cartridge.rtc {
+                let mut rtc_save = [0; rtc::SAVE_SIZE];
+
+                // Saves from emulators without clock support just lack it
+                if save.read_exact(&mut rtc_save).is_ok() {
+                    rtc.load(&rtc_save);
+                }
+            }
+        }
+
+        component_builder
+            .memory_map_component(self.cpu_address_space, ROM_ADDRESSES)
+            .memory_map_component(self.cpu_address_space, RAM_ADDRESSES);
+
+        Ok(cartridge)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cartridge(mbc: Mbc, rom_banks: usize, ram_size: usize) -> GameBoyCartridge {
+        // Every byte of the ROM holds its bank number
+        let rom = (0..rom_banks * ROM_BANK_SIZE).map(|offset| (offset / ROM_BANK_SIZE) as u8);
+
+        GameBoyCartridge {
+            rom: rom.collect(),
+            ram: vec![0; ram_size],
+            mbc,
+            ram_enabled: false,
+            battery: false,
+            rumble: false,
+            rtc: None,
+        }
+    }
+
+    #[test]
+    fn header() {
+        let mut rom = vec![0; 0x8000];
+        rom[0x134..0x139].copy_from_slice(b"TETRA");
+        rom[0x143] = 0x80;
+        rom[0x147] = 0x13;
+        rom[0x148] = 0x05;
+        rom[0x149] = 0x03;
+
+        let header = CartridgeHeader::parse(&rom).unwrap();
+
+        assert_eq!(header.title, "TETRA");
+        assert_eq!(header.cgb_support, CgbSupport::Enhanced);
+        assert_eq!(header.mapper, MapperKind::Mbc3);
+        assert!(header.battery);
+        assert!(!header.timer);
+        assert_eq!(header.rom_size, 0x100000);
+        assert_eq!(header.ram_size, 0x8000);
+    }
+
+    #[test]
+    fn mbc1_banking() {
+        let mut cartridge = cartridge(
+            Mbc::Mbc1 {
+                rom_bank: 1,
+                upper_bank: 0,
+                advanced_banking: false,
+            },
+            64,
+            0x8000,
+        );
+
+        // Bank 0 can't be put in the upper half
+        cartridge.write(0x2000, 0x00);
+        assert_eq!(cartridge.read(0x4000), 1);
+
+        cartridge.write(0x2000, 0x05);
+        cartridge.write(0x4000, 0x01);
+        assert_eq!(cartridge.read(0x4000), 0x25);
+        assert_eq!(cartridge.read(0x0000), 0);
+
+        cartridge.write(0x6000, 0x01);
+        assert_eq!(cartridge.read(0x0000), 0x20);
+
+        // RAM is disabled until enabled
+        cartridge.write(0xa000, 0x42);
+        assert_eq!(cartridge.read(0xa000), 0xff);
+
+        cartridge.write(0x0000, 0x0a);
+        cartridge.write(0xa000, 0x42);
+        assert_eq!(cartridge.ram[RAM_BANK_SIZE], 0x42);
+    }
+
+    #[test]
+    fn mbc5_banking() {
+        let mut cartridge = cartridge(
+            Mbc::Mbc5 {
+                rom_bank: 1,
+                ram_bank: 0,
+            },
+            512,
+            0,
+        );
+
+        cartridge.write(0x2000, 0x00);
+        assert_eq!(cartridge.read(0x4000), 0);
+
+        cartridge.write(0x2000, 0x03);
+        cartridge.write(0x3000, 0x01);
+        assert_eq!(cartridge.read(0x4000), 0x03);
+        assert_eq!(cartridge.rom_banks().1, 0x103);
+    }
+
+    #[test]
+    fn rtc_round_trip() {
+        let mut rtc = Rtc::default();
+
+        // Halt the clock at 1 day, 2 hours, 3 minutes and 4 seconds
+        rtc.write(0x0c, 0x40);
+        for (register, value) in [(0x08, 4), (0x09, 3), (0x0a, 2), (0x0b, 1)] {
+            rtc.write(register, value);
+        }
+
+        let save = rtc.store();
+        let mut loaded = Rtc::default();
+        loaded.load(&save);
+        loaded.write_latch(0);
+        loaded.write_latch(1);
+
+        assert_eq!(
+            [0x08, 0x09, 0x0a, 0x0b, 0x0c].map(|register| loaded.read(register)),
+            [4, 3, 2, 1, 0x40]
+        );
+    }
+}
@@ -0,0 +1,149 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+/// The day counter is 9 bits wide
+const DAY_COUNTER_LIMIT: u64 = 512;
+const DAY_HIGH: u8 = 0b0000_0001;
+const HALT: u8 = 0b0100_0000;
+const DAY_CARRY: u8 = 0b1000_0000;
+/// Seconds, minutes, hours, and the two day registers
+pub const REGISTER_COUNT: usize = 5;
+/// The RTC block emulators append to saves, as the current and latched
+/// registers padded to 4 bytes each followed by a 64 bit timestamp
+pub const SAVE_SIZE: usize = REGISTER_COUNT * 4 * 2 + 8;
+
+/// The MBC3 real time clock
+///
+/// This keeps going while the emulator is not running, so it follows the host
+/// clock rather than emulated time
+#[derive(Debug, Clone)]
+pub struct Rtc {
+    /// Unix time at which the counter was zero
+    base: u64,
+    /// What the counter is stuck at while halted
+    halted: Option<u64>,
+    day_carry: bool,
+    latched: [u8; REGISTER_COUNT],
+    /// If 0 was written to the latch register, so the next 1 latches
+    latch_armed: bool,
+}
+
+impl Default for Rtc {
+    fn default() -> Self {
+        Self {
+            base: now(),
+            halted: None,
+            day_carry: false,
+            latched: [0; REGISTER_COUNT],
+            latch_armed: false,
+        }
+    }
+}
+
+impl Rtc {
+    /// Seconds counted, folding day counter overflows into the carry
+    fn counter(&mut self) -> u64 {
+        let counter = self
+            .halted
+            .unwrap_or_else(|| now().saturating_sub(self.base));
+
+        if counter >= DAY_COUNTER_LIMIT * SECONDS_PER_DAY {
+            let counter = counter % (DAY_COUNTER_LIMIT * SECONDS_PER_DAY);
+
+            self.set_counter(counter);
+            self.day_carry = true;
+
+            return counter;
+        }
+
+        counter
+    }
+
+    fn set_counter(&mut self, counter: u64) {
+        match &mut self.halted {
+            Some(halted) => *halted = counter,
+            None => self.base = now().saturating_sub(counter),
+        }
+    }
+
+    fn registers(&mut self) -> [u8; REGISTER_COUNT] {
+        let counter = self.counter();
+        let days = counter / SECONDS_PER_DAY;
+
+        [
+            (counter % 60) as u8,
+            (counter / 60 % 60) as u8,
+            (counter / (60 * 60) % 24) as u8,
+            days as u8,
+            ((days >> 8) as u8 & DAY_HIGH)
+                | if self.halted.is_some() { HALT } else { 0 }
+                | if self.day_carry { DAY_CARRY } else { 0 },
+        ]
+    }
+
+    fn set_registers(&mut self, registers: [u8; REGISTER_COUNT]) {
+        let [seconds, minutes, hours, day_low, day_high] = registers.map(u64::from);
+        let days = day_low | ((day_high & u64::from(DAY_HIGH)) << 8);
+        let counter = seconds + minutes * 60 + hours * 60 * 60 + days * SECONDS_PER_DAY;
+
+        self.day_carry = registers[4] & DAY_CARRY != 0;
+        self.halted = (registers[4] & HALT != 0).then_some(counter);
+        self.set_counter(counter);
+    }
+
+    /// Reads one of the latched registers, selected by 0x08 to 0x0c
+    pub fn read(&self, register: u8) -> u8 {
+        self.latched[usize::from(register - 0x08)]
+    }
+
+    pub fn write(&mut self, register: u8, value: u8) {
+        let mut registers = self.registers();
+        registers[usize::from(register - 0x08)] = value;
+
+        self.set_registers(registers);
+        // Writes show up straight away
+        self.latched = self.registers();
+    }
+
+    /// Writing 0 then 1 copies the counter into the readable registers
+    pub fn write_latch(&mut self, value: u8) {
+        if self.latch_armed && value == 1 {
+            self.latched = self.registers();
+        }
+
+        self.latch_armed = value == 0;
+    }
+
+    pub fn store(&mut self) -> [u8; SAVE_SIZE] {
+        let mut save = [0; SAVE_SIZE];
+        let registers = self.registers();
+
+        for (index, value) in registers.into_iter().chain(self.latched).enumerate() {
+            save[index * 4] = value;
+        }
+
+        save[REGISTER_COUNT * 8..].copy_from_slice(&now().to_le_bytes());
+
+        save
+    }
+
+    /// Restores the clock, catching up on however long it has been since
+    pub fn load(&mut self, save: &[u8; SAVE_SIZE]) {
+        let register = |index: usize| save[index * 4];
+        let timestamp = u64::from_le_bytes(save[REGISTER_COUNT * 8..].try_into().unwrap());
+
+        self.set_registers(std::array::from_fn(register));
+        self.latched = std::array::from_fn(|index| register(REGISTER_COUNT + index));
+
+        if self.halted.is_none() {
+            self.base = self.base.saturating_sub(now().saturating_sub(timestamp));
+        }
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
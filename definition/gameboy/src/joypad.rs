@@ -0,0 +1,263 @@
+use std::{borrow::Cow, collections::HashMap, sync::Arc};
+
+use fluxemu_definition_intel8080::{Intel8080, InterruptFlags};
+use fluxemu_runtime::{
+    component::{Component, ComponentConfig},
+    input::{GamepadInput, Input, VirtualGamepad, VirtualGamepadMetadata, keyboard::KeyboardInput},
+    machine::builder::{ComponentBuilder, SchedulerParticipation},
+    memory::{Address, AddressSpaceId, MemoryError},
+    path::FluxEmuPath,
+    platform::Platform,
+    scheduler::{Period, SynchronizationContext},
+};
+
+use crate::JOYPAD_INTERRUPT;
+
+/// P1
+const JOYPAD: Address = 0xff00;
+/// How often the lines are checked for a press, which is what raises the
+/// interrupt
+const POLL_FREQUENCY: u128 = 1000;
+
+const SELECT_DIRECTIONS: u8 = 0b0001_0000;
+const SELECT_BUTTONS: u8 = 0b0010_0000;
+
+/// In the order of their lines, shared between the two rows
+const DIRECTIONS: [GamepadInput; 4] = [
+    GamepadInput::LeftStickRight,
+    GamepadInput::LeftStickLeft,
+    GamepadInput::LeftStickUp,
+    GamepadInput::LeftStickDown,
+];
+const BUTTONS: [GamepadInput; 4] = [
+    GamepadInput::FPadRight,
+    GamepadInput::FPadDown,
+    GamepadInput::Select,
+    GamepadInput::Start,
+];
+
+/// The D-pad and buttons, read as a matrix of two rows of four
+#[derive(Debug)]
+pub struct Joypad {
+    gamepad: Arc<VirtualGamepad>,
+    /// The select bits, which pull their row low when cleared
+    select: u8,
+    /// The lines as of the last poll
+    lines: u8,
+    interrupt_flags: Arc<InterruptFlags>,
+}
+
+impl Joypad {
+    /// The low nibble of P1, where a held button pulls its line low
+    fn read_lines(&self) -> u8 {
+        let mut lines = 0x0f;
+
+        for (select, row) in [(SELECT_DIRECTIONS, DIRECTIONS), (SELECT_BUTTONS, BUTTONS)] {
+            if self.select & select != 0 {
+                continue;
+            }
+
+            for (line, input) in row.into_iter().enumerate() {
+                if self.gamepad.get(Input::Gamepad(input)).as_digital(None) {
+                    lines &= !(1 << line);
+                }
+            }
+        }
+
+        lines
+    }
+
+    fn poll(&mut self) {
+        let lines = self.read_lines();
+
+        // Any line going low is what wakes the CPU from STOP
+        if self.lines & !lines != 0 {
+            self.interrupt_flags.request(JOYPAD_INTERRUPT);
+        }
+
+        self.lines = lines;
+    }
+}
+
+impl Component for Joypad {
+    fn memory_read(
+        &self,
+        _address: Address,
+        _address_space: AddressSpaceId,
+        _avoid_side_effects: bool,
+        buffer: &mut [u8],
+    ) -> Result<(), MemoryError> {
+        buffer[0] = 0xc0 | self.select | self.read_lines();
+
+        Ok(())
+    }
+
+    fn memory_write(
+        &mut self,
+        _address: Address,
+        _address_space: AddressSpaceId,
+        buffer: &[u8],
+    ) -> Result<(), MemoryError> {
+        self.select = buffer[0] & (SELECT_DIRECTIONS | SELECT_BUTTONS);
+
+        Ok(())
+    }
+
+    fn synchronize(&mut self, mut context: SynchronizationContext) {
+        for _ in context.allocate(Period::ONE / POLL_FREQUENCY, None) {
+            self.poll();
+        }
+    }
+
+    fn needs_work(&self, delta: Period) -> bool {
+        delta >= Period::ONE / POLL_FREQUENCY
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct JoypadConfig {
+    pub cpu: FluxEmuPath,
+    pub cpu_address_space: AddressSpaceId,
+}
+
+impl<P: Platform> ComponentConfig<P> for JoypadConfig {
+    type Component = Joypad;
+
+    fn build_component(
+        self,
+        component_builder: ComponentBuilder<'_, P, Self::Component>,
+    ) -> Result<Self::Component, Box<dyn std::error::Error>> {
+        let gamepad = create_gamepad();
+
+        let interrupt_flags = component_builder
+            .interact::<Intel8080, _>(&self.cpu, Intel8080::interrupt_flags)
+            .unwrap();
+
+        let (component_builder, _) = component_builder
+            .set_scheduler_participation(SchedulerParticipation::SchedulerDriven)
+            .insert_gamepad("joypad", gamepad.clone());
+
+        component_builder.memory_map_component(self.cpu_address_space, JOYPAD..=JOYPAD);
+
+        Ok(Joypad {
+            gamepad,
+            select: SELECT_DIRECTIONS | SELECT_BUTTONS,
+            lines: 0x0f,
+            interrupt_flags,
+        })
+    }
+}
+
+fn create_gamepad() -> Arc<VirtualGamepad> {
+    let inputs = DIRECTIONS.into_iter().chain(BUTTONS);
+
+    let keyboard_mappings = [
+        (KeyboardInput::ArrowRight, DIRECTIONS[0]),
+        (KeyboardInput::ArrowLeft, DIRECTIONS[1]),
+        (KeyboardInput::ArrowUp, DIRECTIONS[2]),
+        (KeyboardInput::ArrowDown, DIRECTIONS[3]),
+        // A and B
+        (KeyboardInput::KeyX, BUTTONS[0]),
+        (KeyboardInput::KeyZ, BUTTONS[1]),
+        (KeyboardInput::ShiftRight, BUTTONS[2]),
+        (KeyboardInput::Enter, BUTTONS[3]),
+    ];
+
+    VirtualGamepad::new(Cow::Owned(VirtualGamepadMetadata {
+        present_inputs: inputs.clone().map(Input::Gamepad).collect(),
+        default_real2virtual_mappings: HashMap::from_iter(
+            inputs
+                .map(|input| (Input::Gamepad(input), Input::Gamepad(input)))
+                .chain(
+                    keyboard_mappings
+                        .map(|(key, input)| (Input::Keyboard(key), Input::Gamepad(input))),
+                ),
+        ),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use fluxemu_definition_intel8080::{Intel8080Config, LR35902_INTERRUPT_REQUEST_ADDRESS};
+    use fluxemu_runtime::{input::InputState, machine::Machine, scheduler::Frequency};
+
+    use super::*;
+    use crate::MASTER_CLOCK;
+
+    #[test]
+    fn rows_and_interrupt() {
+        let (machine, address_space) = Machine::build_test_minimal().insert_address_space(16);
+
+        let (machine, cpu) = machine.insert_component(
+            "lr35902",
+            Intel8080Config::lr35902(Frequency::from_num(MASTER_CLOCK), address_space),
+        );
+        let (machine, joypad) = machine.insert_component(
+            "joypad",
+            JoypadConfig {
+                cpu,
+                cpu_address_space: address_space,
+            },
+        );
+        let machine = machine.build(());
+        let address_space = machine.address_spaces(address_space).unwrap();
+
+        let read = |address| {
+            let mut buffer = [0];
+
+            address_space
+                .read(address, Period::default(), None, &mut buffer)
+                .unwrap();
+
+            buffer[0]
+        };
+        let write = |address, value| {
+            address_space
+                .write(address, Period::default(), None, &[value])
+                .unwrap();
+        };
+        let press = |input| {
+            machine
+                .interact::<Joypad, _>(&joypad, |joypad| {
+                    joypad
+                        .gamepad
+                        .set(Input::Gamepad(input), InputState::PRESSED);
+                })
+                .unwrap();
+        };
+        // Polls, and then takes the interrupt if there was one
+        let poll = || {
+            machine
+                .interact_mut::<Joypad, _>(&joypad, Joypad::poll)
+                .unwrap();
+
+            let requested = read(LR35902_INTERRUPT_REQUEST_ADDRESS) & (1 << JOYPAD_INTERRUPT) != 0;
+            write(LR35902_INTERRUPT_REQUEST_ADDRESS, 0);
+
+            requested
+        };
+
+        // Clearing a select bit is what picks the row
+        write(JOYPAD, SELECT_BUTTONS);
+        assert!(!poll());
+
+        press(DIRECTIONS[0]);
+        press(BUTTONS[3]);
+
+        assert_eq!(read(JOYPAD), 0xc0 | SELECT_BUTTONS | 0b1110);
+        assert!(poll());
+        // Holding it doesn't interrupt again
+        assert!(!poll());
+
+        // Changing rows onto a held button is a line going low too
+        write(JOYPAD, SELECT_DIRECTIONS);
+        assert_eq!(read(JOYPAD), 0xc0 | SELECT_DIRECTIONS | 0b0111);
+        assert!(poll());
+
+        // Selecting both rows combines them, and neither reads as released
+        write(JOYPAD, 0);
+        assert_eq!(read(JOYPAD), 0xc0 | 0b0110);
+        write(JOYPAD, SELECT_DIRECTIONS | SELECT_BUTTONS);
+        assert_eq!(read(JOYPAD), 0xff);
+    }
+}
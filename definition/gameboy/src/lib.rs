@@ -0,0 +1,177 @@
+use apu::ApuConfig;
+use boot::BootConfig;
+use cartridge::{CartridgeHeader, CgbSupport, GameBoyCartridgeConfig};
+use fluxemu_definition_intel8080::Intel8080Config;
+use fluxemu_definition_misc::memory::standard::{
+    StandardMemoryConfig, StandardMemoryInitialContents,
+};
+use fluxemu_runtime::{
+    machine::{MachineFactory, builder::MachineBuilder},
+    platform::Platform,
+    program::{Filesystem, RomRequirement},
+    scheduler::Frequency,
+};
+use joypad::JoypadConfig;
+use ppu::{PpuConfig, SupportedGraphicsApiPpu};
+use rangemap::RangeInclusiveMap;
+use serial::SerialConfig;
+use timer::TimerConfig;
+use wram::WorkRamConfig;
+
+mod apu;
+mod boot;
+mod cartridge;
+mod joypad;
+mod ppu;
+mod serial;
+mod timer;
+mod wram;
+
+/// The 4 MiHz crystal, which is what the LR35902 counts its clock cycles in
+const MASTER_CLOCK: u128 = 4_194_304;
+
+/// Interrupts in the order of their bits in IF and IE
+const VBLANK_INTERRUPT: u8 = 0;
+const STAT_INTERRUPT: u8 = 1;
+const TIMER_INTERRUPT: u8 = 2;
+const SERIAL_INTERRUPT: u8 = 3;
+const JOYPAD_INTERRUPT: u8 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Model {
+    Dmg,
+    Cgb,
+}
+
+#[derive(Debug, Default)]
+pub struct GameBoy;
+
+impl<P: Platform<GraphicsApi: SupportedGraphicsApiPpu>> MachineFactory<P> for GameBoy {
+    type Quirks = ();
+
+    fn construct(&self, machine: MachineBuilder<P>, _quirks: ()) -> MachineBuilder<P> {
+        construct(machine, Model::Dmg)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct GameBoyColor;
+
+impl<P: Platform<GraphicsApi: SupportedGraphicsApiPpu>> MachineFactory<P> for GameBoyColor {
+    type Quirks = ();
+
+    fn construct(&self, machine: MachineBuilder<P>, _quirks: ()) -> MachineBuilder<P> {
+        construct(machine, Model::Cgb)
+    }
+}
+
+fn construct<P: Platform<GraphicsApi: SupportedGraphicsApiPpu>>(
+    machine: MachineBuilder<P>,
+    model: Model,
+) -> MachineBuilder<P> {
+    let (machine, cpu_address_space) = machine.insert_address_space(16);
+
+    let Filesystem::Single { rom_id, .. } =
+        machine.program_specification().unwrap().info.filesystem()
+    else {
+        panic!("No game boy game has a structured filesystem")
+    };
+    let rom = *rom_id;
+
+    let rom = machine
+        .program_manager()
+        .open(rom, RomRequirement::Required)
+        .unwrap();
+    let header = CartridgeHeader::parse(&rom).unwrap();
+
+    // Games without color support get run on the CGB's DMG compatibility mode,
+    // which for our purposes is a DMG
+    let cgb = model == Model::Cgb && header.cgb_support != CgbSupport::None;
+
+    let (machine, cpu) = machine.insert_component(
+        "lr35902",
+        Intel8080Config {
+            speed_switch: cgb,
+            ..Intel8080Config::lr35902(Frequency::from_num(MASTER_CLOCK), cpu_address_space)
+        },
+    );
+
+    let (machine, cartridge) = machine.insert_component(
+        "cartridge",
+        GameBoyCartridgeConfig {
+            rom,
+            header,
+            cpu_address_space,
+        },
+    );
+
+    // Has to go after the cartridge so it can shadow it
+    let (machine, _) = machine.insert_component(
+        "boot",
+        BootConfig {
+            cartridge,
+            cpu_address_space,
+            cgb,
+        },
+    );
+
+    let (machine, _) = machine.insert_component(
+        "wram",
+        WorkRamConfig {
+            cpu_address_space,
+            cgb,
+        },
+    );
+
+    let (machine, _) = machine.insert_component(
+        "hram",
+        StandardMemoryConfig {
+            readable: true,
+            writable: true,
+            assigned_range: 0xff80..=0xfffe,
+            assigned_address_space: cpu_address_space,
+            initial_contents: RangeInclusiveMap::from_iter([(
+                0xff80..=0xfffe,
+                StandardMemoryInitialContents::Random,
+            )]),
+            sram: false,
+        },
+    );
+
+    let (machine, _) = machine.insert_component(
+        "ppu",
+        PpuConfig {
+            cpu: cpu.clone(),
+            cpu_address_space,
+            cgb,
+        },
+    );
+
+    let (machine, _) = machine.insert_component("apu", ApuConfig { cpu_address_space });
+
+    let (machine, _) = machine.insert_component(
+        "timer",
+        TimerConfig {
+            cpu: cpu.clone(),
+            cpu_address_space,
+        },
+    );
+
+    let (machine, _) = machine.insert_component(
+        "joypad",
+        JoypadConfig {
+            cpu: cpu.clone(),
+            cpu_address_space,
+        },
+    );
+
+    let (machine, _) = machine.insert_component(
+        "serial",
+        SerialConfig {
+            cpu,
+            cpu_address_space,
+        },
+    );
+
+    machine
+}
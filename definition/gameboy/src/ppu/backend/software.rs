@@ -0,0 +1,57 @@
+use std::fmt::Debug;
+
+use fluxemu_runtime::graphics::{
+    GraphicsApi,
+    software::{InitializationData, Software},
+};
+use nalgebra::DMatrix;
+use palette::{Srgba, named::BLACK};
+
+use super::{PpuDisplayBackend, SupportedGraphicsApiPpu};
+use crate::ppu::{SCREEN_HEIGHT, SCREEN_WIDTH};
+
+pub struct SoftwareState {
+    pub staging_buffer: DMatrix<Srgba<u8>>,
+    pub framebuffer: DMatrix<Srgba<u8>>,
+}
+
+// elide the buffers
+
+impl Debug for SoftwareState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SoftwareState").finish()
+    }
+}
+
+impl PpuDisplayBackend for SoftwareState {
+    type GraphicsApi = Software;
+
+    fn new((): InitializationData) -> Self {
+        let staging_buffer = DMatrix::from_element(SCREEN_WIDTH, SCREEN_HEIGHT, BLACK.into());
+
+        SoftwareState {
+            framebuffer: staging_buffer.clone(),
+            staging_buffer,
+        }
+    }
+
+    #[inline]
+    fn modify_staging_buffer(
+        &mut self,
+        callback: impl FnOnce(nalgebra::DMatrixViewMut<'_, Srgba<u8>>),
+    ) {
+        callback(self.staging_buffer.as_view_mut());
+    }
+
+    fn commit_staging_buffer(&mut self) {
+        self.framebuffer.copy_from(&self.staging_buffer);
+    }
+
+    fn access_framebuffer(&mut self) -> &<Self::GraphicsApi as GraphicsApi>::FramebufferTexture {
+        &self.framebuffer
+    }
+}
+
+impl SupportedGraphicsApiPpu for Software {
+    type Backend = SoftwareState;
+}
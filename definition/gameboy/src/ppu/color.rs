@@ -0,0 +1,69 @@
+use palette::Srgba;
+
+/// The DMG's four shades, from lightest to darkest
+const SHADES: [u8; 4] = [0xff, 0xaa, 0x55, 0x00];
+
+const INDEX_AUTO_INCREMENT: u8 = 0b1000_0000;
+const INDEX_MASK: u8 = 0b0011_1111;
+
+/// Looks a color up in BGP, OBP0 or OBP1
+pub(super) fn dmg_color(palette: u8, color: u8) -> Srgba<u8> {
+    let shade = SHADES[usize::from((palette >> (color * 2)) & 0b11)];
+
+    Srgba::new(shade, shade, shade, 0xff)
+}
+
+/// One of the CGB's two palette memories, each of 8 palettes of 4 RGB555
+/// colors, accessed through an index and data register pair
+#[derive(Debug)]
+pub(super) struct PaletteRam {
+    data: [u8; 64],
+    /// BCPS or OCPS
+    index: u8,
+}
+
+impl Default for PaletteRam {
+    fn default() -> Self {
+        Self {
+            // The boot ROM leaves everything white
+            data: [0xff; 64],
+            index: 0,
+        }
+    }
+}
+
+impl PaletteRam {
+    pub fn read_index(&self) -> u8 {
+        0x40 | self.index
+    }
+
+    pub fn write_index(&mut self, value: u8) {
+        self.index = value & (INDEX_AUTO_INCREMENT | INDEX_MASK);
+    }
+
+    pub fn read_data(&self) -> u8 {
+        self.data[usize::from(self.index & INDEX_MASK)]
+    }
+
+    pub fn write_data(&mut self, value: u8) {
+        self.data[usize::from(self.index & INDEX_MASK)] = value;
+
+        if self.index & INDEX_AUTO_INCREMENT != 0 {
+            self.index = INDEX_AUTO_INCREMENT | ((self.index + 1) & INDEX_MASK);
+        }
+    }
+
+    pub fn color(&self, palette: u8, color: u8) -> Srgba<u8> {
+        let offset = usize::from(palette * 8 + color * 2);
+        let rgb555 = u16::from_le_bytes([self.data[offset], self.data[offset + 1]]);
+
+        // Repeat the top bits into the bottom so white is fully white
+        let [red, green, blue] = [0, 5, 10].map(|shift| {
+            let channel = ((rgb555 >> shift) & 0x1f) as u8;
+
+            (channel << 3) | (channel >> 2)
+        });
+
+        Srgba::new(red, green, blue, 0xff)
+    }
+}
@@ -0,0 +1,708 @@
+use std::{any::Any, ops::RangeInclusive, sync::Arc};
+
+use arrayvec::ArrayVec;
+use backend::PpuDisplayBackend;
+pub(crate) use backend::SupportedGraphicsApiPpu;
+use color::{PaletteRam, dmg_color};
+use fluxemu_definition_intel8080::{Intel8080, InterruptFlags, SpeedSwitch};
+use fluxemu_runtime::{
+    component::{Component, ComponentConfig, LateInitializedData},
+    machine::builder::{ComponentBuilder, SchedulerParticipation},
+    memory::{Address, AddressSpace, AddressSpaceId, MemoryError},
+    path::FluxEmuPath,
+    platform::Platform,
+    scheduler::{Period, SynchronizationContext},
+};
+use oam::{LineSprite, SPRITES_PER_LINE};
+use pipeline::Pipeline;
+
+use crate::{MASTER_CLOCK, STAT_INTERRUPT, VBLANK_INTERRUPT};
+
+mod backend;
+mod color;
+mod oam;
+mod pipeline;
+
+pub(crate) const SCREEN_WIDTH: usize = 160;
+pub(crate) const SCREEN_HEIGHT: usize = 144;
+
+const DOTS_PER_LINE: u16 = 456;
+const OAM_SCAN_DOTS: u16 = 80;
+const LINE_COUNT: u8 = 154;
+
+const VRAM_ADDRESSES: RangeInclusive<Address> = 0x8000..=0x9fff;
+const VRAM_SIZE: usize = 0x2000;
+/// OAM, followed by an unusable area that belongs to the PPU all the same
+const OAM_ADDRESSES: RangeInclusive<Address> = 0xfe00..=0xfeff;
+const OAM_SIZE: usize = 0xa0;
+const REGISTERS: RangeInclusive<Address> = 0xff40..=0xff4b;
+const LCDC: Address = 0xff40;
+const STAT: Address = 0xff41;
+const SCY: Address = 0xff42;
+const SCX: Address = 0xff43;
+const LY: Address = 0xff44;
+const LYC: Address = 0xff45;
+const DMA: Address = 0xff46;
+const BGP: Address = 0xff47;
+const OBP0: Address = 0xff48;
+const OBP1: Address = 0xff49;
+const WY: Address = 0xff4a;
+const WX: Address = 0xff4b;
+const VBK: Address = 0xff4f;
+const HDMA_ADDRESSES: RangeInclusive<Address> = 0xff51..=0xff55;
+const HDMA5: Address = 0xff55;
+const CGB_PALETTE_ADDRESSES: RangeInclusive<Address> = 0xff68..=0xff6c;
+const BCPS: Address = 0xff68;
+const BCPD: Address = 0xff69;
+const OCPS: Address = 0xff6a;
+const OCPD: Address = 0xff6b;
+const OPRI: Address = 0xff6c;
+
+pub(super) const LCDC_BACKGROUND_ENABLE: u8 = 0b0000_0001;
+pub(super) const LCDC_SPRITE_ENABLE: u8 = 0b0000_0010;
+pub(super) const LCDC_SPRITE_SIZE: u8 = 0b0000_0100;
+pub(super) const LCDC_BACKGROUND_MAP: u8 = 0b0000_1000;
+pub(super) const LCDC_TILE_DATA: u8 = 0b0001_0000;
+pub(super) const LCDC_WINDOW_ENABLE: u8 = 0b0010_0000;
+pub(super) const LCDC_WINDOW_MAP: u8 = 0b0100_0000;
+const LCDC_LCD_ENABLE: u8 = 0b1000_0000;
+
+const STAT_HBLANK_INTERRUPT: u8 = 0b0000_1000;
+const STAT_VBLANK_INTERRUPT: u8 = 0b0001_0000;
+const STAT_OAM_SCAN_INTERRUPT: u8 = 0b0010_0000;
+const STAT_LYC_INTERRUPT: u8 = 0b0100_0000;
+const STAT_INTERRUPT_ENABLES: u8 = 0b0111_1000;
+
+/// STAT's mode field
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    HBlank = 0,
+    VBlank = 1,
+    OamScan = 2,
+    Drawing = 3,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct OamDma {
+    source: u16,
+    index: u8,
+}
+
+/// The CGB's VRAM DMA, either all at once or 16 bytes each HBlank
+#[derive(Debug, Default, Clone, Copy)]
+struct Hdma {
+    source: u16,
+    /// Offset into VRAM
+    destination: u16,
+    /// 16 byte blocks left to copy
+    remaining: u8,
+    hblank_active: bool,
+}
+
+/// The pixel processing unit, which owns VRAM and OAM
+#[derive(Debug)]
+pub struct Ppu<G: SupportedGraphicsApiPpu> {
+    backend: Option<G::Backend>,
+    cgb: bool,
+    vram: Box<[[u8; VRAM_SIZE]; 2]>,
+    vram_bank: u8,
+    oam: [u8; OAM_SIZE],
+    lcdc: u8,
+    /// Only the interrupt enables, as the rest is derived
+    stat: u8,
+    scy: u8,
+    scx: u8,
+    ly: u8,
+    lyc: u8,
+    bgp: u8,
+    /// OBP0 and OBP1
+    obp: [u8; 2],
+    wy: u8,
+    wx: u8,
+    mode: Mode,
+    /// Dots into the current line
+    line_dot: u16,
+    /// The combined STAT interrupt sources, which only interrupt on rising
+    stat_line: bool,
+    /// If LY matched WY at some point this frame
+    window_y_triggered: bool,
+    /// Lines of the window drawn so far, which is unaffected by scrolling
+    window_line: u8,
+    sprites: ArrayVec<LineSprite, SPRITES_PER_LINE>,
+    pipeline: Pipeline,
+    background_palettes: PaletteRam,
+    sprite_palettes: PaletteRam,
+    /// OPRI
+    object_priority: u8,
+    dma_register: u8,
+    oam_dma: Option<OamDma>,
+    oam_dma_dots: u8,
+    hdma: Hdma,
+    hdma_registers: [u8; 4],
+    interrupt_flags: Arc<InterruptFlags>,
+    speed_switch: Arc<SpeedSwitch>,
+    cpu_address_space: Arc<AddressSpace>,
+    timestamp: Period,
+}
+
+impl<G: SupportedGraphicsApiPpu> Ppu<G> {
+    fn vram(&self, bank: u8, address: u16) -> u8 {
+        self.vram[usize::from(bank)][usize::from(address) & (VRAM_SIZE - 1)]
+    }
+
+    fn lcd_enabled(&self) -> bool {
+        self.lcdc & LCDC_LCD_ENABLE != 0
+    }
+
+    /// The CPU is locked out of VRAM while it's being drawn from
+    fn vram_accessible(&self) -> bool {
+        !self.lcd_enabled() || self.mode != Mode::Drawing
+    }
+
+    fn oam_accessible(&self) -> bool {
+        self.oam_dma.is_none()
+            && (!self.lcd_enabled() || matches!(self.mode, Mode::HBlank | Mode::VBlank))
+    }
+
+    fn tick(&mut self) {
+        self.step_oam_dma();
+
+        if !self.lcd_enabled() {
+            return;
+        }
+
+        if self.line_dot == 0 {
+            self.start_line();
+        }
+
+        match self.mode {
+            Mode::OamScan if self.line_dot == OAM_SCAN_DOTS => {
+                self.sprites = oam::scan(&self.oam, self.ly, self.sprite_height());
+                self.pipeline.reset(self.scx);
+                self.mode = Mode::Drawing;
+            }
+            Mode::Drawing => {
+                if self.render_dot() {
+                    self.start_hblank();
+                }
+            }
+            _ => {}
+        }
+
+        self.line_dot += 1;
+
+        if self.line_dot == DOTS_PER_LINE {
+            self.line_dot = 0;
+            self.ly = (self.ly + 1) % LINE_COUNT;
+        }
+
+        self.update_stat_interrupt();
+    }
+
+    fn start_line(&mut self) {
+        if self.ly == 0 {
+            self.window_y_triggered = false;
+            self.window_line = 0;
+        }
+
+        if usize::from(self.ly) < SCREEN_HEIGHT {
+            if self.ly == self.wy {
+                self.window_y_triggered = true;
+            }
+
+            self.mode = Mode::OamScan;
+        } else if usize::from(self.ly) == SCREEN_HEIGHT {
+            self.mode = Mode::VBlank;
+            self.interrupt_flags.request(VBLANK_INTERRUPT);
+            self.backend.as_mut().unwrap().commit_staging_buffer();
+        }
+    }
+
+    fn start_hblank(&mut self) {
+        self.mode = Mode::HBlank;
+
+        if self.pipeline.window {
+            self.window_line += 1;
+        }
+
+        let line = *self.pipeline.line();
+        let y = usize::from(self.ly);
+
+        self.backend
+            .as_mut()
+            .unwrap()
+            .modify_staging_buffer(|mut staging_buffer| {
+                for (x, color) in line.into_iter().enumerate() {
+                    staging_buffer[(x, y)] = color;
+                }
+            });
+
+        if self.hdma.hblank_active {
+            self.copy_hdma_block();
+        }
+    }
+
+    fn update_stat_interrupt(&mut self) {
+        let line = self.lcd_enabled()
+            && ((self.stat & STAT_LYC_INTERRUPT != 0 && self.ly == self.lyc)
+                || match self.mode {
+                    Mode::HBlank => self.stat & STAT_HBLANK_INTERRUPT != 0,
+                    Mode::VBlank => self.stat & STAT_VBLANK_INTERRUPT != 0,
+                    Mode::OamScan => self.stat & STAT_OAM_SCAN_INTERRUPT != 0,
+                    Mode::Drawing => false,
+                });
+
+        if line && !self.stat_line {
+            self.interrupt_flags.request(STAT_INTERRUPT);
+        }
+
+        self.stat_line = line;
+    }
+
+    fn write_lcdc(&mut self, value: u8) {
+        let was_enabled = self.lcd_enabled();
+        self.lcdc = value;
+
+        // Turning the LCD off resets the PPU, and it starts a fresh frame
+        // when turned back on
+        if was_enabled && !self.lcd_enabled() {
+            self.ly = 0;
+            self.line_dot = 0;
+            self.mode = Mode::HBlank;
+
+            let white = dmg_color(0, 0);
+            let backend = self.backend.as_mut().unwrap();
+
+            backend.modify_staging_buffer(|mut staging_buffer| staging_buffer.fill(white));
+            backend.commit_staging_buffer();
+        }
+    }
+
+    /// Reads for the DMAs, which go through the CPU's view of memory
+    fn dma_read(&self, address: u16) -> u8 {
+        if VRAM_ADDRESSES.contains(&usize::from(address)) {
+            return self.vram(self.vram_bank, address);
+        }
+
+        let mut buffer = [0xff];
+        let _ =
+            self.cpu_address_space
+                .read(usize::from(address), self.timestamp, None, &mut buffer);
+
+        buffer[0]
+    }
+
+    fn step_oam_dma(&mut self) {
+        let Some(mut dma) = self.oam_dma else {
+            return;
+        };
+
+        // A byte a machine cycle, which goes twice as fast in double speed
+        let dots_per_byte = if self.speed_switch.double_speed() {
+            2
+        } else {
+            4
+        };
+
+        self.oam_dma_dots += 1;
+
+        if self.oam_dma_dots < dots_per_byte {
+            return;
+        }
+
+        self.oam_dma_dots = 0;
+        self.oam[usize::from(dma.index)] = self.dma_read(dma.source + u16::from(dma.index));
+        dma.index += 1;
+
+        self.oam_dma = (usize::from(dma.index) < OAM_SIZE).then_some(dma);
+    }
+
+    fn copy_hdma_block(&mut self) {
+        for _ in 0..16 {
+            let value = self.dma_read(self.hdma.source);
+            let destination = usize::from(self.hdma.destination) & (VRAM_SIZE - 1);
+
+            self.vram[usize::from(self.vram_bank)][destination] = value;
+            self.hdma.source = self.hdma.source.wrapping_add(1);
+            self.hdma.destination = self.hdma.destination.wrapping_add(1);
+        }
+
+        self.hdma.remaining -= 1;
+
+        if self.hdma.remaining == 0 {
+            self.hdma.hblank_active = false;
+        }
+    }
+
+    fn write_hdma(&mut self, address: Address, value: u8) {
+        if address != HDMA5 {
+            self.hdma_registers[address - HDMA_ADDRESSES.start()] = value;
+
+            return;
+        }
+
+        // Clearing the top bit while one is going cancels it
+        if self.hdma.hblank_active && value & 0x80 == 0 {
+            self.hdma.hblank_active = false;
+
+            return;
+        }
+
+        let [source_high, source_low, destination_high, destination_low] = self.hdma_registers;
+
+        self.hdma = Hdma {
+            source: u16::from_be_bytes([source_high, source_low & 0xf0]),
+            destination: u16::from_be_bytes([destination_high & 0x1f, destination_low & 0xf0]),
+            remaining: (value & 0x7f) + 1,
+            hblank_active: value & 0x80 != 0,
+        };
+
+        // FIXME: The CPU should be halted while this copies
+        if !self.hdma.hblank_active {
+            while self.hdma.remaining != 0 {
+                self.copy_hdma_block();
+            }
+        } else if !self.lcd_enabled() {
+            self.copy_hdma_block();
+        }
+    }
+
+    fn read_hdma5(&self) -> u8 {
+        match self.hdma.remaining {
+            0 => 0xff,
+            remaining if self.hdma.hblank_active => remaining - 1,
+            remaining => 0x80 | (remaining - 1),
+        }
+    }
+}
+
+impl<G: SupportedGraphicsApiPpu> Component for Ppu<G> {
+    fn memory_read(
+        &self,
+        address: Address,
+        _address_space: AddressSpaceId,
+        _avoid_side_effects: bool,
+        buffer: &mut [u8],
+    ) -> Result<(), MemoryError> {
+        buffer[0] = match address {
+            address if VRAM_ADDRESSES.contains(&address) => {
+                if self.vram_accessible() {
+                    self.vram(self.vram_bank, address as u16)
+                } else {
+                    0xff
+                }
+            }
+            address if OAM_ADDRESSES.contains(&address) => {
+                let offset = address - OAM_ADDRESSES.start();
+
+                match self.oam.get(offset) {
+                    Some(value) if self.oam_accessible() => *value,
+                    Some(_) => 0xff,
+                    None => 0x00,
+                }
+            }
+            LCDC => self.lcdc,
+            STAT => {
+                let mode = if self.lcd_enabled() {
+                    self.mode as u8
+                } else {
+                    0
+                };
+
+                0x80 | self.stat | (u8::from(self.ly == self.lyc) << 2) | mode
+            }
+            SCY => self.scy,
+            SCX => self.scx,
+            LY => self.ly,
+            LYC => self.lyc,
+            DMA => self.dma_register,
+            BGP => self.bgp,
+            OBP0 => self.obp[0],
+            OBP1 => self.obp[1],
+            WY => self.wy,
+            WX => self.wx,
+            VBK => 0xfe | self.vram_bank,
+            HDMA5 => self.read_hdma5(),
+            BCPS => self.background_palettes.read_index(),
+            BCPD => self.background_palettes.read_data(),
+            OCPS => self.sprite_palettes.read_index(),
+            OCPD => self.sprite_palettes.read_data(),
+            OPRI => 0xfe | self.object_priority,
+            _ => 0xff,
+        };
+
+        Ok(())
+    }
+
+    fn memory_write(
+        &mut self,
+        address: Address,
+        _address_space: AddressSpaceId,
+        buffer: &[u8],
+    ) -> Result<(), MemoryError> {
+        let data = buffer[0];
+
+        match address {
+            address if VRAM_ADDRESSES.contains(&address) => {
+                if self.vram_accessible() {
+                    let offset = address - VRAM_ADDRESSES.start();
+
+                    self.vram[usize::from(self.vram_bank)][offset] = data;
+                }
+            }
+            address if OAM_ADDRESSES.contains(&address) => {
+                let accessible = self.oam_accessible();
+
+                if let Some(value) = self.oam.get_mut(address - OAM_ADDRESSES.start())
+                    && accessible
+                {
+                    *value = data;
+                }
+            }
+            LCDC => self.write_lcdc(data),
+            STAT => self.stat = data & STAT_INTERRUPT_ENABLES,
+            SCY => self.scy = data,
+            SCX => self.scx = data,
+            LYC => self.lyc = data,
+            DMA => {
+                self.dma_register = data;
+
+                // Anything past work RAM is its echo
+                let page = if data >= 0xe0 { data - 0x20 } else { data };
+
+                self.oam_dma = Some(OamDma {
+                    source: u16::from(page) << 8,
+                    index: 0,
+                });
+                self.oam_dma_dots = 0;
+            }
+            BGP => self.bgp = data,
+            OBP0 => self.obp[0] = data,
+            OBP1 => self.obp[1] = data,
+            WY => self.wy = data,
+            WX => self.wx = data,
+            VBK => self.vram_bank = data & 1,
+            address if HDMA_ADDRESSES.contains(&address) => self.write_hdma(address, data),
+            BCPS => self.background_palettes.write_index(data),
+            BCPD => self.background_palettes.write_data(data),
+            OCPS => self.sprite_palettes.write_index(data),
+            OCPD => self.sprite_palettes.write_data(data),
+            OPRI => self.object_priority = data & 1,
+            _ => {}
+        }
+
+        self.update_stat_interrupt();
+
+        Ok(())
+    }
+
+    fn access_framebuffer(&mut self, _path: &FluxEmuPath) -> &dyn Any {
+        self.backend.as_mut().unwrap().access_framebuffer()
+    }
+
+    fn synchronize(&mut self, mut context: SynchronizationContext) {
+        for now in context.allocate(Period::ONE / MASTER_CLOCK, None) {
+            self.timestamp = now;
+
+            self.tick();
+        }
+    }
+
+    fn needs_work(&self, delta: Period) -> bool {
+        delta >= Period::ONE / MASTER_CLOCK
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct PpuConfig {
+    pub cpu: FluxEmuPath,
+    pub cpu_address_space: AddressSpaceId,
+    pub cgb: bool,
+}
+
+impl<P: Platform<GraphicsApi: SupportedGraphicsApiPpu>> ComponentConfig<P> for PpuConfig {
+    type Component = Ppu<P::GraphicsApi>;
+
+    fn late_initialize(component: &mut Self::Component, data: &LateInitializedData<P>) {
+        component.backend = Some(PpuDisplayBackend::new(
+            data.component_graphics_initialization_data.clone(),
+        ));
+    }
+
+    fn build_component(
+        self,
+        component_builder: ComponentBuilder<'_, P, Self::Component>,
+    ) -> Result<Self::Component, Box<dyn std::error::Error>> {
+        let (interrupt_flags, speed_switch) = component_builder
+            .interact::<Intel8080, _>(&self.cpu, |cpu| (cpu.interrupt_flags(), cpu.speed_switch()))
+            .unwrap();
+
+        let cpu_address_space = component_builder
+            .get_address_space(self.cpu_address_space)
+            .clone();
+
+        let (component_builder, _) = component_builder
+            .set_scheduler_participation(SchedulerParticipation::SchedulerDriven)
+            .insert_display("lcd");
+
+        let component_builder = component_builder
+            .memory_map_component(self.cpu_address_space, VRAM_ADDRESSES)
+            .memory_map_component(self.cpu_address_space, OAM_ADDRESSES)
+            .memory_map_component(self.cpu_address_space, REGISTERS);
+
+        if self.cgb {
+            component_builder
+                .memory_map_component(self.cpu_address_space, VBK..=VBK)
+                .memory_map_component(self.cpu_address_space, HDMA_ADDRESSES)
+                .memory_map_component(self.cpu_address_space, CGB_PALETTE_ADDRESSES);
+        }
+
+        Ok(Ppu {
+            backend: None,
+            cgb: self.cgb,
+            vram: Box::new([[0; VRAM_SIZE]; 2]),
+            vram_bank: 0,
+            oam: [0; OAM_SIZE],
+            lcdc: 0,
+            stat: 0,
+            scy: 0,
+            scx: 0,
+            ly: 0,
+            lyc: 0,
+            bgp: 0,
+            obp: [0xff; 2],
+            wy: 0,
+            wx: 0,
+            mode: Mode::HBlank,
+            line_dot: 0,
+            stat_line: false,
+            window_y_triggered: false,
+            window_line: 0,
+            sprites: ArrayVec::new(),
+            pipeline: Pipeline::default(),
+            background_palettes: PaletteRam::default(),
+            sprite_palettes: PaletteRam::default(),
+            object_priority: 0,
+            dma_register: 0xff,
+            oam_dma: None,
+            oam_dma_dots: 0,
+            hdma: Hdma::default(),
+            hdma_registers: [0; 4],
+            interrupt_flags,
+            speed_switch,
+            cpu_address_space,
+            timestamp: Period::default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fluxemu_definition_intel8080::{Intel8080Config, LR35902_INTERRUPT_REQUEST_ADDRESS};
+    use fluxemu_runtime::{graphics::software::Software, machine::Machine, scheduler::Frequency};
+
+    use super::*;
+
+    #[test]
+    fn mode_timing() {
+        let (machine, address_space) = Machine::build_test_minimal().insert_address_space(16);
+
+        let (machine, cpu) = machine.insert_component(
+            "lr35902",
+            Intel8080Config::lr35902(Frequency::from_num(MASTER_CLOCK), address_space),
+        );
+        let (machine, ppu) = machine.insert_component(
+            "ppu",
+            PpuConfig {
+                cpu,
+                cpu_address_space: address_space,
+                cgb: false,
+            },
+        );
+        let machine = machine.build(());
+        let address_space = machine.address_spaces(address_space).unwrap();
+
+        let read = |address| {
+            let mut buffer = [0];
+
+            address_space
+                .read(address, Period::default(), None, &mut buffer)
+                .unwrap();
+
+            buffer[0]
+        };
+        let write = |address, value| {
+            address_space
+                .write(address, Period::default(), None, &[value])
+                .unwrap();
+        };
+        let run = |dots| {
+            machine
+                .interact_mut::<Ppu<Software>, _>(&ppu, |ppu| {
+                    for _ in 0..dots {
+                        ppu.tick();
+                    }
+                })
+                .unwrap();
+        };
+        let mode = || read(STAT) & 0b11;
+        // Takes the interrupts that have been requested so far
+        let interrupts = || {
+            let requested = read(LR35902_INTERRUPT_REQUEST_ADDRESS) & 0x1f;
+            write(LR35902_INTERRUPT_REQUEST_ADDRESS, 0);
+
+            requested
+        };
+        // From a dot into mode 3, how long it lasts altogether
+        let drawing_dots = || {
+            let mut dots = 1;
+
+            while mode() == Mode::Drawing as u8 {
+                run(1);
+                dots += 1;
+            }
+
+            dots
+        };
+
+        write(LCDC, LCDC_LCD_ENABLE | LCDC_BACKGROUND_ENABLE);
+        run(1);
+        write(STAT, STAT_HBLANK_INTERRUPT);
+        interrupts();
+
+        assert_eq!(mode(), Mode::OamScan as u8);
+        run(OAM_SCAN_DOTS - 1);
+        assert_eq!(mode(), Mode::OamScan as u8);
+        run(1);
+        assert_eq!(mode(), Mode::Drawing as u8);
+
+        // With nothing to slow it down drawing takes the shortest it can,
+        // and then the rest of the line is HBlank
+        assert_eq!(drawing_dots(), 172);
+        assert_eq!(mode(), Mode::HBlank as u8);
+        assert_eq!(interrupts(), 1 << STAT_INTERRUPT);
+
+        run(DOTS_PER_LINE - OAM_SCAN_DOTS - 172);
+        assert_eq!(read(LY), 1);
+
+        // Fine horizontal scrolling throws away pixels at the start of the
+        // line, which makes drawing that much longer
+        write(SCX, 3);
+        run(OAM_SCAN_DOTS + 1);
+        assert_eq!(drawing_dots(), 175);
+
+        run(DOTS_PER_LINE * 143 - OAM_SCAN_DOTS - 175);
+        assert_eq!(read(LY), 144);
+        interrupts();
+        run(1);
+        assert_eq!(mode(), Mode::VBlank as u8);
+        assert_eq!(interrupts(), 1 << VBLANK_INTERRUPT);
+
+        // Ten lines of VBlank end the frame
+        run(DOTS_PER_LINE * 10 - 1);
+        assert_eq!(read(LY), 0);
+        assert_eq!(mode(), Mode::VBlank as u8);
+        run(1);
+        assert_eq!(mode(), Mode::OamScan as u8);
+    }
+}
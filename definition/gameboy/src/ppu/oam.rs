@@ -0,0 +1,52 @@
+use arrayvec::ArrayVec;
+
+/// How many sprites a line can have, past which the rest are dropped
+pub(super) const SPRITES_PER_LINE: usize = 10;
+pub(super) const SPRITE_COUNT: usize = 40;
+
+pub(super) const ATTRIBUTE_CGB_PALETTE: u8 = 0b0000_0111;
+pub(super) const ATTRIBUTE_BANK: u8 = 0b0000_1000;
+pub(super) const ATTRIBUTE_DMG_PALETTE: u8 = 0b0001_0000;
+pub(super) const ATTRIBUTE_X_FLIP: u8 = 0b0010_0000;
+pub(super) const ATTRIBUTE_Y_FLIP: u8 = 0b0100_0000;
+/// Puts the background and window colors 1 to 3 in front
+pub(super) const ATTRIBUTE_PRIORITY: u8 = 0b1000_0000;
+
+/// A sprite found on the current line during the OAM scan
+#[derive(Debug, Clone, Copy)]
+pub(super) struct LineSprite {
+    /// Offset by 16, so sprites can be partially off the top
+    pub y: u8,
+    /// Offset by 8, so sprites can be partially off the left
+    pub x: u8,
+    pub tile: u8,
+    pub attributes: u8,
+    /// Where it is in OAM, which decides priority on the CGB
+    pub index: u8,
+    /// If it already went into the sprite FIFO
+    pub fetched: bool,
+}
+
+/// Picks the first sprites in OAM that overlap the line
+pub(super) fn scan(oam: &[u8], line: u8, height: u8) -> ArrayVec<LineSprite, SPRITES_PER_LINE> {
+    let line = u16::from(line) + 16;
+
+    oam.chunks_exact(4)
+        .take(SPRITE_COUNT)
+        .enumerate()
+        .filter(|(_, sprite)| {
+            let y = u16::from(sprite[0]);
+
+            (y..y + u16::from(height)).contains(&line)
+        })
+        .take(SPRITES_PER_LINE)
+        .map(|(index, sprite)| LineSprite {
+            y: sprite[0],
+            x: sprite[1],
+            tile: sprite[2],
+            attributes: sprite[3],
+            index: index as u8,
+            fetched: false,
+        })
+        .collect()
+}
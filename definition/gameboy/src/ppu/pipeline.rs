@@ -0,0 +1,424 @@
+use std::collections::VecDeque;
+
+use palette::{Srgba, named::WHITE};
+
+use super::{
+    LCDC_BACKGROUND_ENABLE, LCDC_BACKGROUND_MAP, LCDC_SPRITE_ENABLE, LCDC_SPRITE_SIZE,
+    LCDC_TILE_DATA, LCDC_WINDOW_ENABLE, LCDC_WINDOW_MAP, Ppu, SCREEN_WIDTH,
+    SupportedGraphicsApiPpu,
+    color::dmg_color,
+    oam::{
+        ATTRIBUTE_BANK, ATTRIBUTE_CGB_PALETTE, ATTRIBUTE_DMG_PALETTE, ATTRIBUTE_PRIORITY,
+        ATTRIBUTE_X_FLIP, ATTRIBUTE_Y_FLIP,
+    },
+};
+
+/// How long the background fetcher is held up for each sprite
+const SPRITE_FETCH_DOTS: u8 = 6;
+/// The first tile of each line is fetched twice with the first thrown away,
+/// less the dot mode 3 starts on that already goes unused
+const DISCARDED_FETCH_DOTS: u8 = 5;
+const TILE_WIDTH: u8 = 8;
+
+#[derive(Debug, Clone, Copy)]
+struct BackgroundPixel {
+    color: u8,
+    /// Only used on the CGB
+    palette: u8,
+    priority: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct SpritePixel {
+    color: u8,
+    /// OBP0 or OBP1 on the DMG, and one of 8 palettes on the CGB
+    palette: u8,
+    /// The background's colors 1 to 3 go in front
+    priority: bool,
+    /// OAM index, which decides overlaps on the CGB
+    index: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FetcherStep {
+    Tile,
+    DataLow,
+    DataHigh,
+    /// Waits for the background FIFO to empty
+    Push,
+}
+
+/// Mode 3, where the background fetcher feeds a FIFO that gets shifted out to
+/// the LCD a pixel a dot, with sprites merged in on top as they are reached
+#[derive(Debug)]
+pub(super) struct Pipeline {
+    background_fifo: VecDeque<BackgroundPixel>,
+    sprite_fifo: VecDeque<SpritePixel>,
+    step: FetcherStep,
+    /// Each fetch takes 2 dots, with the access on the second
+    step_second_dot: bool,
+    /// Tiles fetched so far this line, in either the background or window
+    tile_x: u8,
+    tile: u8,
+    attributes: u8,
+    /// Row within the tile, before flipping
+    tile_row: u8,
+    data: [u8; 2],
+    /// If the fetcher switched over to the window this line
+    pub window: bool,
+    /// Pixels sent to the LCD
+    x: u8,
+    /// Pixels to throw away before any are sent to the LCD
+    discard: u8,
+    /// Dots left in the fetch that starts the line and goes nowhere
+    discarded_fetch: u8,
+    /// Sprite being fetched, and how much longer it will take
+    sprite_fetch: Option<(usize, u8)>,
+    line: [Srgba<u8>; SCREEN_WIDTH],
+}
+
+impl Default for Pipeline {
+    fn default() -> Self {
+        Self {
+            background_fifo: VecDeque::with_capacity(16),
+            sprite_fifo: VecDeque::with_capacity(8),
+            step: FetcherStep::Tile,
+            step_second_dot: false,
+            tile_x: 0,
+            tile: 0,
+            attributes: 0,
+            tile_row: 0,
+            data: [0; 2],
+            window: false,
+            x: 0,
+            discard: 0,
+            discarded_fetch: 0,
+            sprite_fetch: None,
+            line: [WHITE.into(); SCREEN_WIDTH],
+        }
+    }
+}
+
+impl Pipeline {
+    /// Starts a line, with the fine horizontal scroll discarded off the front
+    pub fn reset(&mut self, scroll_x: u8) {
+        self.background_fifo.clear();
+        self.sprite_fifo.clear();
+        self.restart_fetcher();
+        self.window = false;
+        self.x = 0;
+        self.discard = scroll_x % TILE_WIDTH;
+        self.discarded_fetch = DISCARDED_FETCH_DOTS;
+        self.sprite_fetch = None;
+    }
+
+    fn restart_fetcher(&mut self) {
+        self.step = FetcherStep::Tile;
+        self.step_second_dot = false;
+        self.tile_x = 0;
+    }
+
+    pub fn line(&self) -> &[Srgba<u8>; SCREEN_WIDTH] {
+        &self.line
+    }
+}
+
+impl<G: SupportedGraphicsApiPpu> Ppu<G> {
+    /// Runs mode 3 for a dot, returning if the line is finished
+    pub(super) fn render_dot(&mut self) -> bool {
+        if self.pipeline.discarded_fetch != 0 {
+            self.pipeline.discarded_fetch -= 1;
+
+            return false;
+        }
+
+        if let Some((index, remaining)) = &mut self.pipeline.sprite_fetch {
+            *remaining -= 1;
+
+            if *remaining == 0 {
+                let index = *index;
+
+                self.pipeline.sprite_fetch = None;
+                self.fetch_sprite(index);
+            }
+
+            return false;
+        }
+
+        if let Some(index) = self.pending_sprite() {
+            // The background needs something in the FIFO to mix with first
+            if self.pipeline.background_fifo.is_empty() {
+                self.fetcher_dot();
+            } else {
+                self.sprites[index].fetched = true;
+                self.pipeline.sprite_fetch = Some((index, SPRITE_FETCH_DOTS - 1));
+            }
+
+            return false;
+        }
+
+        self.check_window();
+        self.fetcher_dot();
+        self.shift_out();
+
+        usize::from(self.pipeline.x) == SCREEN_WIDTH
+    }
+
+    /// The first sprite that starts at the current position
+    fn pending_sprite(&self) -> Option<usize> {
+        if self.lcdc & LCDC_SPRITE_ENABLE == 0 {
+            return None;
+        }
+
+        let x = u16::from(self.pipeline.x) + u16::from(TILE_WIDTH);
+
+        // Sprites hanging off the left are all reached on the first pixel
+        self.sprites
+            .iter()
+            .position(|sprite| !sprite.fetched && sprite.x != 0 && u16::from(sprite.x) <= x)
+    }
+
+    fn check_window(&mut self) {
+        let enabled = self.lcdc & LCDC_WINDOW_ENABLE != 0
+            && (self.cgb || self.lcdc & LCDC_BACKGROUND_ENABLE != 0);
+
+        if self.pipeline.window
+            || !enabled
+            || !self.window_y_triggered
+            || u16::from(self.pipeline.x) + 7 < u16::from(self.wx)
+        {
+            return;
+        }
+
+        self.pipeline.window = true;
+        self.pipeline.background_fifo.clear();
+        self.pipeline.restart_fetcher();
+
+        // Windows further left than the screen have their edge cut off
+        if self.pipeline.x == 0 {
+            self.pipeline.discard = 7u8.saturating_sub(self.wx);
+        }
+    }
+
+    fn fetcher_dot(&mut self) {
+        if self.pipeline.step == FetcherStep::Push {
+            if self.pipeline.background_fifo.is_empty() {
+                self.push_tile();
+            }
+
+            return;
+        }
+
+        if !self.pipeline.step_second_dot {
+            self.pipeline.step_second_dot = true;
+
+            return;
+        }
+
+        self.pipeline.step_second_dot = false;
+
+        self.pipeline.step = match self.pipeline.step {
+            FetcherStep::Tile => {
+                self.fetch_tile();
+                FetcherStep::DataLow
+            }
+            FetcherStep::DataLow => {
+                self.pipeline.data[0] = self.fetch_tile_data(0);
+                FetcherStep::DataHigh
+            }
+            FetcherStep::DataHigh => {
+                self.pipeline.data[1] = self.fetch_tile_data(1);
+                FetcherStep::Push
+            }
+            FetcherStep::Push => unreachable!(),
+        };
+    }
+
+    fn fetch_tile(&mut self) {
+        let (map_select, x, y) = if self.pipeline.window {
+            (LCDC_WINDOW_MAP, self.pipeline.tile_x, self.window_line)
+        } else {
+            (
+                LCDC_BACKGROUND_MAP,
+                (self.scx / TILE_WIDTH).wrapping_add(self.pipeline.tile_x) % 32,
+                self.ly.wrapping_add(self.scy),
+            )
+        };
+
+        let map = if self.lcdc & map_select != 0 {
+            0x9c00
+        } else {
+            0x9800
+        };
+        let address = map + u16::from(y / TILE_WIDTH) * 32 + u16::from(x % 32);
+
+        self.pipeline.tile = self.vram(0, address);
+        // Bank 1 of the map holds attributes
+        self.pipeline.attributes = if self.cgb { self.vram(1, address) } else { 0 };
+        self.pipeline.tile_row = y % TILE_WIDTH;
+    }
+
+    fn fetch_tile_data(&self, plane: u16) -> u8 {
+        let attributes = self.pipeline.attributes;
+        let tile = self.pipeline.tile;
+
+        let row = if attributes & ATTRIBUTE_Y_FLIP != 0 {
+            7 - self.pipeline.tile_row
+        } else {
+            self.pipeline.tile_row
+        };
+
+        // The other addressing mode has tile numbers signed around 0x9000
+        let base = if self.lcdc & LCDC_TILE_DATA != 0 {
+            0x8000 + u16::from(tile) * 16
+        } else {
+            0x9000u16.wrapping_add_signed(i16::from(tile as i8) * 16)
+        };
+
+        self.vram(
+            u8::from(attributes & ATTRIBUTE_BANK != 0),
+            base + u16::from(row) * 2 + plane,
+        )
+    }
+
+    fn push_tile(&mut self) {
+        let attributes = self.pipeline.attributes;
+        // The DMG blanks out the background and window rather than skipping them
+        let blank = !self.cgb && self.lcdc & LCDC_BACKGROUND_ENABLE == 0;
+
+        for color in tile_row_colors(self.pipeline.data, attributes & ATTRIBUTE_X_FLIP != 0) {
+            self.pipeline.background_fifo.push_back(BackgroundPixel {
+                color: if blank { 0 } else { color },
+                palette: attributes & ATTRIBUTE_CGB_PALETTE,
+                priority: attributes & ATTRIBUTE_PRIORITY != 0,
+            });
+        }
+
+        self.pipeline.tile_x = self.pipeline.tile_x.wrapping_add(1);
+        self.pipeline.step = FetcherStep::Tile;
+    }
+
+    fn fetch_sprite(&mut self, index: usize) {
+        let sprite = self.sprites[index];
+        let height = self.sprite_height();
+
+        let row = self.ly + 16 - sprite.y;
+        let row = if sprite.attributes & ATTRIBUTE_Y_FLIP != 0 {
+            height - 1 - row
+        } else {
+            row
+        };
+
+        // Tall sprites ignore the bottom bit of the tile number
+        let tile = if height == 16 {
+            sprite.tile & !1
+        } else {
+            sprite.tile
+        };
+
+        let bank = u8::from(self.cgb && sprite.attributes & ATTRIBUTE_BANK != 0);
+        let address = 0x8000 + u16::from(tile) * 16 + u16::from(row) * 2;
+        let data = [self.vram(bank, address), self.vram(bank, address + 1)];
+
+        let palette = if self.cgb {
+            sprite.attributes & ATTRIBUTE_CGB_PALETTE
+        } else {
+            u8::from(sprite.attributes & ATTRIBUTE_DMG_PALETTE != 0)
+        };
+        // OPRI picks between DMG style priority by position and CGB style by
+        // OAM index
+        let index_priority = self.cgb && self.object_priority & 1 == 0;
+        let hidden = usize::from(TILE_WIDTH.saturating_sub(sprite.x));
+
+        let colors = tile_row_colors(data, sprite.attributes & ATTRIBUTE_X_FLIP != 0);
+
+        for (position, color) in colors.into_iter().skip(hidden).enumerate() {
+            let pixel = SpritePixel {
+                color,
+                palette,
+                priority: sprite.attributes & ATTRIBUTE_PRIORITY != 0,
+                index: sprite.index,
+            };
+
+            match self.pipeline.sprite_fifo.get_mut(position) {
+                Some(existing) => {
+                    if existing.color == 0
+                        || (index_priority && color != 0 && pixel.index < existing.index)
+                    {
+                        *existing = pixel;
+                    }
+                }
+                None => self.pipeline.sprite_fifo.push_back(pixel),
+            }
+        }
+    }
+
+    fn shift_out(&mut self) {
+        let Some(background) = self.pipeline.background_fifo.pop_front() else {
+            return;
+        };
+
+        if self.pipeline.discard != 0 {
+            self.pipeline.discard -= 1;
+
+            return;
+        }
+
+        let sprite = self
+            .pipeline
+            .sprite_fifo
+            .pop_front()
+            .filter(|sprite| sprite.color != 0 && self.lcdc & LCDC_SPRITE_ENABLE != 0);
+
+        let color = match sprite {
+            Some(sprite) if self.sprite_wins(background, sprite) => {
+                if self.cgb {
+                    self.sprite_palettes.color(sprite.palette, sprite.color)
+                } else {
+                    dmg_color(self.obp[usize::from(sprite.palette)], sprite.color)
+                }
+            }
+            _ => {
+                if self.cgb {
+                    self.background_palettes
+                        .color(background.palette, background.color)
+                } else {
+                    dmg_color(self.bgp, background.color)
+                }
+            }
+        };
+
+        self.pipeline.line[usize::from(self.pipeline.x)] = color;
+        self.pipeline.x += 1;
+    }
+
+    fn sprite_wins(&self, background: BackgroundPixel, sprite: SpritePixel) -> bool {
+        if background.color == 0 {
+            return true;
+        }
+
+        // On the CGB the background enable instead takes away its priority
+        if self.cgb {
+            self.lcdc & LCDC_BACKGROUND_ENABLE == 0 || (!sprite.priority && !background.priority)
+        } else {
+            !sprite.priority
+        }
+    }
+
+    pub(super) fn sprite_height(&self) -> u8 {
+        if self.lcdc & LCDC_SPRITE_SIZE != 0 {
+            16
+        } else {
+            8
+        }
+    }
+}
+
+/// Splits a row of tile data into its colors, from left to right
+fn tile_row_colors([low, high]: [u8; 2], x_flip: bool) -> [u8; 8] {
+    std::array::from_fn(|pixel| {
+        let bit = if x_flip { pixel } else { 7 - pixel };
+
+        (((high >> bit) & 1) << 1) | ((low >> bit) & 1)
+    })
+}
@@ -0,0 +1,106 @@
+use std::{ops::RangeInclusive, sync::Arc};
+
+use fluxemu_definition_intel8080::{Intel8080, InterruptFlags};
+use fluxemu_runtime::{
+    component::{Component, ComponentConfig},
+    machine::builder::ComponentBuilder,
+    memory::{Address, AddressSpaceId, MemoryError},
+    path::FluxEmuPath,
+    platform::Platform,
+};
+
+use crate::SERIAL_INTERRUPT;
+
+const SERIAL_ADDRESSES: RangeInclusive<Address> = 0xff01..=0xff02;
+/// SB
+const DATA: Address = 0xff01;
+/// SC
+const CONTROL: Address = 0xff02;
+
+const CONTROL_TRANSFER: u8 = 0b1000_0000;
+const CONTROL_INTERNAL_CLOCK: u8 = 0b0000_0001;
+
+/// The link port, with nothing plugged into it
+///
+/// Test ROMs report their results through here, so outgoing bytes are logged
+#[derive(Debug)]
+pub struct Serial {
+    data: u8,
+    control: u8,
+    interrupt_flags: Arc<InterruptFlags>,
+}
+
+impl Component for Serial {
+    fn memory_read(
+        &self,
+        address: Address,
+        _address_space: AddressSpaceId,
+        _avoid_side_effects: bool,
+        buffer: &mut [u8],
+    ) -> Result<(), MemoryError> {
+        buffer[0] = match address {
+            DATA => self.data,
+            CONTROL => 0x7e | self.control,
+            _ => unreachable!(),
+        };
+
+        Ok(())
+    }
+
+    fn memory_write(
+        &mut self,
+        address: Address,
+        _address_space: AddressSpaceId,
+        buffer: &[u8],
+    ) -> Result<(), MemoryError> {
+        let data = buffer[0];
+
+        match address {
+            DATA => self.data = data,
+            CONTROL => {
+                self.control = data & (CONTROL_TRANSFER | CONTROL_INTERNAL_CLOCK);
+
+                // FIXME: The transfer should take 8 bit times, and an external
+                // clock should wait for a link partner that never comes
+                if self.control == CONTROL_TRANSFER | CONTROL_INTERNAL_CLOCK {
+                    tracing::debug!("Serial transfer of {:#04x}", self.data);
+
+                    // With nothing on the other end the line floats high
+                    self.data = 0xff;
+                    self.control &= !CONTROL_TRANSFER;
+                    self.interrupt_flags.request(SERIAL_INTERRUPT);
+                }
+            }
+            _ => unreachable!(),
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct SerialConfig {
+    pub cpu: FluxEmuPath,
+    pub cpu_address_space: AddressSpaceId,
+}
+
+impl<P: Platform> ComponentConfig<P> for SerialConfig {
+    type Component = Serial;
+
+    fn build_component(
+        self,
+        component_builder: ComponentBuilder<'_, P, Self::Component>,
+    ) -> Result<Self::Component, Box<dyn std::error::Error>> {
+        let interrupt_flags = component_builder
+            .interact::<Intel8080, _>(&self.cpu, Intel8080::interrupt_flags)
+            .unwrap();
+
+        component_builder.memory_map_component(self.cpu_address_space, SERIAL_ADDRESSES);
+
+        Ok(Serial {
+            data: 0,
+            control: 0,
+            interrupt_flags,
+        })
+    }
+}
@@ -0,0 +1,272 @@
+use std::{ops::RangeInclusive, sync::Arc};
+
+use fluxemu_definition_intel8080::{Intel8080, InterruptFlags, SpeedSwitch};
+use fluxemu_runtime::{
+    component::{Component, ComponentConfig},
+    machine::builder::{ComponentBuilder, SchedulerParticipation},
+    memory::{Address, AddressSpaceId, MemoryError},
+    path::FluxEmuPath,
+    platform::Platform,
+    scheduler::{Period, SynchronizationContext},
+};
+
+use crate::{MASTER_CLOCK, TIMER_INTERRUPT};
+
+const TIMER_ADDRESSES: RangeInclusive<Address> = 0xff04..=0xff07;
+const DIV: Address = 0xff04;
+const TIMA: Address = 0xff05;
+const TMA: Address = 0xff06;
+const TAC: Address = 0xff07;
+
+const TAC_ENABLE: u8 = 0b100;
+/// Which bit of the divider TIMA counts the falling edges of, by TAC clock
+/// select
+const TAC_DIVIDER_BITS: [u8; 4] = [9, 3, 5, 7];
+/// Clock cycles between TIMA overflowing and it being reloaded
+const RELOAD_DELAY: u8 = 4;
+
+/// DIV and TIMA, which both count off of the same 16 bit divider
+#[derive(Debug)]
+pub struct Timer {
+    divider: u16,
+    counter: u8,
+    modulo: u8,
+    control: u8,
+    /// Cycles until an overflowed counter gets reloaded from the modulo
+    reload: Option<u8>,
+    interrupt_flags: Arc<InterruptFlags>,
+    speed_switch: Arc<SpeedSwitch>,
+}
+
+impl Timer {
+    /// The divider bit TIMA is watching, gated by the enable
+    fn counter_signal(&self) -> bool {
+        let bit = TAC_DIVIDER_BITS[usize::from(self.control & 0b11)];
+
+        self.control & TAC_ENABLE != 0 && self.divider & (1 << bit) != 0
+    }
+
+    /// Changes the divider or TAC, which can clock TIMA as a side effect of
+    /// the signal falling
+    fn modify(&mut self, callback: impl FnOnce(&mut Self)) {
+        let before = self.counter_signal();
+        callback(self);
+
+        if before && !self.counter_signal() {
+            self.increment_counter();
+        }
+    }
+
+    fn increment_counter(&mut self) {
+        let (counter, overflowed) = self.counter.overflowing_add(1);
+        self.counter = counter;
+
+        if overflowed {
+            self.reload = Some(RELOAD_DELAY);
+        }
+    }
+
+    fn tick(&mut self) {
+        if let Some(reload) = &mut self.reload {
+            *reload -= 1;
+
+            if *reload == 0 {
+                self.reload = None;
+                self.counter = self.modulo;
+                self.interrupt_flags.request(TIMER_INTERRUPT);
+            }
+        }
+
+        self.modify(|timer| timer.divider = timer.divider.wrapping_add(1));
+    }
+}
+
+impl Component for Timer {
+    fn memory_read(
+        &self,
+        address: Address,
+        _address_space: AddressSpaceId,
+        _avoid_side_effects: bool,
+        buffer: &mut [u8],
+    ) -> Result<(), MemoryError> {
+        buffer[0] = match address {
+            DIV => (self.divider >> 8) as u8,
+            TIMA => self.counter,
+            TMA => self.modulo,
+            TAC => 0xf8 | self.control,
+            _ => unreachable!(),
+        };
+
+        Ok(())
+    }
+
+    fn memory_write(
+        &mut self,
+        address: Address,
+        _address_space: AddressSpaceId,
+        buffer: &[u8],
+    ) -> Result<(), MemoryError> {
+        let data = buffer[0];
+
+        match address {
+            DIV => self.modify(|timer| timer.divider = 0),
+            TIMA => {
+                // Writing during the delay cancels the reload
+                self.counter = data;
+                self.reload = None;
+            }
+            TMA => self.modulo = data,
+            TAC => self.modify(|timer| timer.control = data & 0b111),
+            _ => unreachable!(),
+        }
+
+        Ok(())
+    }
+
+    fn synchronize(&mut self, mut context: SynchronizationContext) {
+        // The divider is clocked off of the CPU, so it speeds up with it
+        let period = if self.speed_switch.double_speed() {
+            Period::ONE / (MASTER_CLOCK * 2)
+        } else {
+            Period::ONE / MASTER_CLOCK
+        };
+
+        for _ in context.allocate(period, None) {
+            self.tick();
+        }
+    }
+
+    fn needs_work(&self, delta: Period) -> bool {
+        delta >= Period::ONE / (MASTER_CLOCK * 2)
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct TimerConfig {
+    pub cpu: FluxEmuPath,
+    pub cpu_address_space: AddressSpaceId,
+}
+
+impl<P: Platform> ComponentConfig<P> for TimerConfig {
+    type Component = Timer;
+
+    fn build_component(
+        self,
+        component_builder: ComponentBuilder<'_, P, Self::Component>,
+    ) -> Result<Self::Component, Box<dyn std::error::Error>> {
+        let (interrupt_flags, speed_switch) = component_builder
+            .interact::<Intel8080, _>(&self.cpu, |cpu| (cpu.interrupt_flags(), cpu.speed_switch()))
+            .unwrap();
+
+        component_builder
+            .set_scheduler_participation(SchedulerParticipation::SchedulerDriven)
+            .memory_map_component(self.cpu_address_space, TIMER_ADDRESSES);
+
+        Ok(Timer {
+            // Roughly where the boot ROM leaves it
+            divider: 0xabcc,
+            counter: 0,
+            modulo: 0,
+            control: 0,
+            reload: None,
+            interrupt_flags,
+            speed_switch,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fluxemu_definition_intel8080::{Intel8080Config, LR35902_INTERRUPT_REQUEST_ADDRESS};
+    use fluxemu_runtime::{machine::Machine, scheduler::Frequency};
+
+    use super::*;
+
+    #[test]
+    fn div_and_tima() {
+        let (machine, address_space) = Machine::build_test_minimal().insert_address_space(16);
+
+        let (machine, cpu) = machine.insert_component(
+            "lr35902",
+            Intel8080Config::lr35902(Frequency::from_num(MASTER_CLOCK), address_space),
+        );
+        let (machine, timer) = machine.insert_component(
+            "timer",
+            TimerConfig {
+                cpu,
+                cpu_address_space: address_space,
+            },
+        );
+        let machine = machine.build(());
+        let address_space = machine.address_spaces(address_space).unwrap();
+
+        let read = |address| {
+            let mut buffer = [0];
+
+            address_space
+                .read(address, Period::default(), None, &mut buffer)
+                .unwrap();
+
+            buffer[0]
+        };
+        let write = |address, value| {
+            address_space
+                .write(address, Period::default(), None, &[value])
+                .unwrap();
+        };
+        let run = |cycles| {
+            machine
+                .interact_mut::<Timer, _>(&timer, |timer| {
+                    for _ in 0..cycles {
+                        timer.tick();
+                    }
+                })
+                .unwrap();
+        };
+        let interrupt_requested =
+            || read(LR35902_INTERRUPT_REQUEST_ADDRESS) & (1 << TIMER_INTERRUPT) != 0;
+
+        // DIV is the top half of the divider, and writing anything clears it
+        assert_eq!(read(DIV), 0xab);
+        write(DIV, 0x12);
+        run(255);
+        assert_eq!(read(DIV), 0x00);
+        run(1);
+        assert_eq!(read(DIV), 0x01);
+
+        write(DIV, 0);
+        write(TMA, 0xfe);
+        write(TIMA, 0xff);
+        // Counting every 16 cycles
+        write(TAC, TAC_ENABLE | 0b01);
+        write(LR35902_INTERRUPT_REQUEST_ADDRESS, 0);
+
+        run(15);
+        assert_eq!(read(TIMA), 0xff);
+        run(1);
+        assert_eq!(read(TIMA), 0x00);
+
+        // It sits at zero for a machine cycle before the reload and interrupt
+        run(3);
+        assert_eq!(read(TIMA), 0x00);
+        assert!(!interrupt_requested());
+        run(1);
+        assert_eq!(read(TIMA), 0xfe);
+        assert!(interrupt_requested());
+
+        // Writing TIMA in that machine cycle cancels the reload
+        write(LR35902_INTERRUPT_REQUEST_ADDRESS, 0);
+        write(TIMA, 0xff);
+        run(12);
+        assert_eq!(read(TIMA), 0x00);
+        write(TIMA, 0x80);
+        run(4);
+        assert_eq!(read(TIMA), 0x80);
+        assert!(!interrupt_requested());
+
+        // Clearing the divider while the watched bit is set clocks TIMA
+        run(4);
+        write(DIV, 0);
+        assert_eq!(read(TIMA), 0x81);
+    }
+}
@@ -0,0 +1,113 @@
+use std::ops::RangeInclusive;
+
+use fluxemu_runtime::{
+    component::{Component, ComponentConfig},
+    machine::builder::ComponentBuilder,
+    memory::{Address, AddressSpaceId, MemoryError},
+    platform::Platform,
+};
+
+const WRAM_ADDRESSES: RangeInclusive<Address> = 0xc000..=0xdfff;
+/// Echo RAM, which repeats the work RAM up until OAM
+const ECHO_ADDRESSES: RangeInclusive<Address> = 0xe000..=0xfdff;
+/// SVBK
+const BANK_SELECT: Address = 0xff70;
+const BANK_SIZE: usize = 0x1000;
+
+/// Work RAM, which the CGB has 8 banks of with the upper 7 switchable
+#[derive(Debug)]
+pub struct WorkRam {
+    banks: Vec<[u8; BANK_SIZE]>,
+    /// SVBK, which is only present in CGB mode
+    bank: u8,
+}
+
+impl WorkRam {
+    /// The bank in the upper half, where bank 0 selects bank 1
+    fn switchable_bank(&self) -> usize {
+        usize::from(self.bank.max(1))
+    }
+
+    fn locate(&self, address: Address) -> (usize, usize) {
+        let offset = address - WRAM_ADDRESSES.start();
+
+        if offset < BANK_SIZE {
+            (0, offset)
+        } else {
+            (self.switchable_bank(), offset - BANK_SIZE)
+        }
+    }
+}
+
+impl Component for WorkRam {
+    fn memory_read(
+        &self,
+        address: Address,
+        _address_space: AddressSpaceId,
+        _avoid_side_effects: bool,
+        buffer: &mut [u8],
+    ) -> Result<(), MemoryError> {
+        buffer[0] = if address == BANK_SELECT {
+            0xf8 | self.bank
+        } else {
+            let (bank, offset) = self.locate(address);
+
+            self.banks[bank][offset]
+        };
+
+        Ok(())
+    }
+
+    fn memory_write(
+        &mut self,
+        address: Address,
+        _address_space: AddressSpaceId,
+        buffer: &[u8],
+    ) -> Result<(), MemoryError> {
+        if address == BANK_SELECT {
+            self.bank = buffer[0] & 0x07;
+        } else {
+            let (bank, offset) = self.locate(address);
+
+            self.banks[bank][offset] = buffer[0];
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct WorkRamConfig {
+    pub cpu_address_space: AddressSpaceId,
+    pub cgb: bool,
+}
+
+impl<P: Platform> ComponentConfig<P> for WorkRamConfig {
+    type Component = WorkRam;
+
+    fn build_component(
+        self,
+        component_builder: ComponentBuilder<'_, P, Self::Component>,
+    ) -> Result<Self::Component, Box<dyn std::error::Error>> {
+        let bank_count = if self.cgb { 8 } else { 2 };
+
+        let component_builder = component_builder
+            .memory_map_component(self.cpu_address_space, WRAM_ADDRESSES)
+            .memory_mirror_map(
+                self.cpu_address_space,
+                ECHO_ADDRESSES,
+                *WRAM_ADDRESSES.start()
+                    ..=WRAM_ADDRESSES.start() + (ECHO_ADDRESSES.end() - ECHO_ADDRESSES.start()),
+            );
+
+        if self.cgb {
+            component_builder
+                .memory_map_component(self.cpu_address_space, BANK_SELECT..=BANK_SELECT);
+        }
+
+        Ok(WorkRam {
+            banks: vec![[0; BANK_SIZE]; bank_count],
+            bank: 0,
+        })
+    }
+}
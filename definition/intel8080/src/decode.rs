@@ -1,49 +1,697 @@
-use super::instruction::SingleByteArgument;
-use bitvec::field::BitField;
-use bitvec::prelude::Msb0;
-use bitvec::view::BitView;
-use fluxemu_runtime::memory::memory_access_table::MemoryAccessTable;
-use std::ops::Range;
-
-const INSTRUCTION_IDENTIFIER: Range<usize> = 0..2;
-const SECONDARY_INSTRUCTION_IDENTIFIER: Range<usize> = 5..8;
-const ARGUMENT: Range<usize> = 2..5;
-
-pub fn decode_instruction(
-    cursor: usize,
-    memory_access_table: &MemoryAccessTable,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let mut instruction_first_byte = 0;
-    memory_access_table.read(cursor, std::slice::from_mut(&mut instruction_first_byte));
-    let instruction_first_byte = instruction_first_byte.view_bits::<Msb0>();
-    let instruction_identifier = instruction_first_byte[INSTRUCTION_IDENTIFIER].load::<u8>();
-
-    match instruction_identifier {
-        0b00 => {
-            todo!()
+use crate::{
+    Intel8080Kind,
+    instruction::{
+        AluOperation, BlockOperation, Condition, Exchange, IndexRegister, Instruction, Operand,
+        Port, Register, RegisterPair, RotateOperation,
+    },
+};
+
+/// Cycles each unprefixed opcode takes, assuming any condition is not met
+const I8080_CYCLES: [u8; 256] = cycle_table(
+    [
+        [4, 10, 7, 5, 5, 5, 7, 4, 4, 10, 7, 5, 5, 5, 7, 4],
+        [4, 10, 7, 5, 5, 5, 7, 4, 4, 10, 7, 5, 5, 5, 7, 4],
+        [4, 10, 16, 5, 5, 5, 7, 4, 4, 10, 16, 5, 5, 5, 7, 4],
+        [4, 10, 13, 5, 10, 10, 10, 4, 4, 10, 13, 5, 5, 5, 7, 4],
+    ],
+    (5, 7, 7),
+    (4, 7),
+    [
+        [5, 10, 10, 10, 11, 11, 7, 11, 5, 10, 10, 10, 11, 17, 7, 11],
+        [5, 10, 10, 10, 11, 11, 7, 11, 5, 10, 10, 10, 11, 17, 7, 11],
+        [5, 10, 10, 18, 11, 11, 7, 11, 5, 5, 10, 4, 11, 17, 7, 11],
+        [5, 10, 10, 4, 11, 11, 7, 11, 5, 5, 10, 4, 11, 17, 7, 11],
+    ],
+);
+
+const Z80_CYCLES: [u8; 256] = cycle_table(
+    [
+        [4, 10, 7, 6, 4, 4, 7, 4, 4, 11, 7, 6, 4, 4, 7, 4],
+        [8, 10, 7, 6, 4, 4, 7, 4, 12, 11, 7, 6, 4, 4, 7, 4],
+        [7, 10, 16, 6, 4, 4, 7, 4, 7, 11, 16, 6, 4, 4, 7, 4],
+        [7, 10, 13, 6, 11, 11, 10, 4, 7, 11, 13, 6, 4, 4, 7, 4],
+    ],
+    (4, 7, 4),
+    (4, 7),
+    [
+        [5, 10, 10, 10, 10, 11, 7, 11, 5, 10, 10, 0, 10, 17, 7, 11],
+        [5, 10, 10, 11, 10, 11, 7, 11, 5, 4, 10, 11, 10, 0, 7, 11],
+        [5, 10, 10, 19, 10, 11, 7, 11, 5, 4, 10, 4, 10, 0, 7, 11],
+        [5, 10, 10, 4, 10, 11, 7, 11, 5, 6, 10, 4, 10, 0, 7, 11],
+    ],
+);
+
+const LR35902_CYCLES: [u8; 256] = cycle_table(
+    [
+        [4, 12, 8, 8, 4, 4, 8, 4, 20, 8, 8, 8, 4, 4, 8, 4],
+        [4, 12, 8, 8, 4, 4, 8, 4, 12, 8, 8, 8, 4, 4, 8, 4],
+        [8, 12, 8, 8, 4, 4, 8, 4, 8, 8, 8, 8, 4, 4, 8, 4],
+        [8, 12, 8, 8, 12, 12, 12, 4, 8, 8, 8, 8, 4, 4, 8, 4],
+    ],
+    (4, 8, 4),
+    (4, 8),
+    [
+        [8, 12, 12, 16, 12, 16, 8, 16, 8, 16, 12, 4, 12, 24, 8, 16],
+        [8, 12, 12, 4, 12, 16, 8, 16, 8, 16, 12, 4, 12, 4, 8, 16],
+        [12, 12, 8, 4, 4, 16, 8, 16, 16, 4, 16, 4, 4, 4, 8, 16],
+        [12, 12, 8, 4, 4, 16, 8, 16, 12, 8, 16, 4, 4, 4, 8, 16],
+    ],
+);
+
+/// Builds a cycle table from its irregular top and bottom quarters, and the
+/// regular loads and arithmetic in the middle
+///
+/// The load costs are register to register, involving memory, and HALT. The
+/// arithmetic costs are register and memory
+const fn cycle_table(
+    top: [[u8; 16]; 4],
+    (load, load_memory, halt): (u8, u8, u8),
+    (arithmetic, arithmetic_memory): (u8, u8),
+    bottom: [[u8; 16]; 4],
+) -> [u8; 256] {
+    let mut table = [0; 256];
+    let mut opcode = 0;
+
+    while opcode < 256 {
+        let row = opcode / 16;
+        let destination_memory = (opcode >> 3) & 0b111 == 0b110;
+        let source_memory = opcode & 0b111 == 0b110;
+
+        table[opcode] = match opcode >> 6 {
+            0b00 => top[row][opcode % 16],
+            0b01 if opcode == 0x76 => halt,
+            0b01 if destination_memory || source_memory => load_memory,
+            0b01 => load,
+            0b10 if source_memory => arithmetic_memory,
+            0b10 => arithmetic,
+            _ => bottom[row - 12][opcode % 16],
+        };
+
+        opcode += 1;
+    }
+
+    table
+}
+
+/// Extra cycles taken when the condition of an instruction is met
+pub fn taken_cycles(kind: Intel8080Kind, instruction: &Instruction) -> u8 {
+    match (kind, instruction) {
+        (Intel8080Kind::Intel8080, Instruction::Return(Some(_)) | Instruction::Call { .. }) => 6,
+        (Intel8080Kind::Zilog80, Instruction::Return(Some(_))) => 6,
+        (Intel8080Kind::Zilog80, Instruction::Call { .. }) => 7,
+        (Intel8080Kind::Zilog80, Instruction::JumpRelative { .. } | Instruction::Djnz(_)) => 5,
+        // Repeating block instructions go around again
+        (Intel8080Kind::Zilog80, Instruction::Block { .. }) => 5,
+        (Intel8080Kind::SharpLr35902, Instruction::Return(Some(_)) | Instruction::Call { .. }) => {
+            12
+        }
+        (
+            Intel8080Kind::SharpLr35902,
+            Instruction::Jump { .. } | Instruction::JumpRelative { .. },
+        ) => 4,
+        _ => 0,
+    }
+}
+
+/// What came out of decoding a single instruction
+#[derive(Debug, Clone, Copy)]
+pub struct Decoded {
+    pub instruction: Instruction,
+    /// Cycles taken if no condition is met
+    pub cycles: u8,
+    /// Opcode fetches, which each bump the Z80 refresh counter
+    pub fetches: u8,
+}
+
+/// Decodes the instruction at the program counter, pulling in bytes as needed
+pub fn decode_instruction(kind: Intel8080Kind, mut fetch: impl FnMut() -> u8) -> Decoded {
+    let opcode = fetch();
+
+    match (kind, opcode) {
+        (Intel8080Kind::Zilog80, 0xcb) => decode_cb(kind, None, &mut fetch),
+        (Intel8080Kind::SharpLr35902, 0xcb) => decode_cb(kind, None, &mut fetch),
+        (Intel8080Kind::Zilog80, 0xed) => decode_ed(&mut fetch),
+        (Intel8080Kind::Zilog80, 0xdd) => decode_indexed(IndexRegister::Ix, &mut fetch),
+        (Intel8080Kind::Zilog80, 0xfd) => decode_indexed(IndexRegister::Iy, &mut fetch),
+        _ => {
+            let instruction = decode_main(kind, opcode, None, &mut fetch);
+
+            Decoded {
+                instruction,
+                cycles: cycle_table_for(kind)[usize::from(opcode)],
+                fetches: 1,
+            }
         }
+    }
+}
+
+fn cycle_table_for(kind: Intel8080Kind) -> &'static [u8; 256] {
+    match kind {
+        Intel8080Kind::Intel8080 => &I8080_CYCLES,
+        Intel8080Kind::Zilog80 => &Z80_CYCLES,
+        Intel8080Kind::SharpLr35902 => &LR35902_CYCLES,
+    }
+}
+
+fn fetch_word(fetch: &mut impl FnMut() -> u8) -> u16 {
+    u16::from_le_bytes([fetch(), fetch()])
+}
+
+fn fetch_displacement(fetch: &mut impl FnMut() -> u8) -> i8 {
+    fetch() as i8
+}
+
+/// Register pairs as selected by the p field of most 16 bit instructions
+fn register_pair(p: u8, index: Option<IndexRegister>) -> RegisterPair {
+    match p {
+        0 => RegisterPair::Bc,
+        1 => RegisterPair::De,
+        2 => index.map_or(RegisterPair::Hl, RegisterPair::Index),
+        _ => RegisterPair::Sp,
+    }
+}
+
+/// Register pairs as selected by PUSH and POP
+fn register_pair_af(p: u8, index: Option<IndexRegister>) -> RegisterPair {
+    match p {
+        3 => RegisterPair::Af,
+        _ => register_pair(p, index),
+    }
+}
+
+/// Decodes the 3 bit register field, where the displacement of (IX+d) always
+/// comes straight after the opcode
+fn operand(id: u8, index: Option<IndexRegister>, fetch: &mut impl FnMut() -> u8) -> Operand {
+    if id == 0b110 && index.is_some() {
+        Operand::from_id(id, index, fetch_displacement(fetch))
+    } else {
+        Operand::from_id(id, index, 0)
+    }
+}
+
+fn hl_or_index(index: Option<IndexRegister>) -> RegisterPair {
+    index.map_or(RegisterPair::Hl, RegisterPair::Index)
+}
+
+/// Decodes the unprefixed page, which is shared by all three processors, with
+/// HL replaced by an index register if a Z80 index prefix came before it
+fn decode_main(
+    kind: Intel8080Kind,
+    opcode: u8,
+    index: Option<IndexRegister>,
+    fetch: &mut impl FnMut() -> u8,
+) -> Instruction {
+    let x = opcode >> 6;
+    let y = (opcode >> 3) & 0b111;
+    let z = opcode & 0b111;
+    let p = y >> 1;
+    let q = y & 1 != 0;
+
+    let z80 = kind == Intel8080Kind::Zilog80;
+    let lr35902 = kind == Intel8080Kind::SharpLr35902;
+
+    match x {
+        0b00 => match z {
+            0 => match y {
+                0 => Instruction::Nop,
+                1 if z80 => Instruction::Exchange(Exchange::AfShadow),
+                1 if lr35902 => Instruction::StorePairIndirect {
+                    address: fetch_word(fetch),
+                    pair: RegisterPair::Sp,
+                },
+                2 if z80 => Instruction::Djnz(fetch_displacement(fetch)),
+                2 if lr35902 => {
+                    // STOP is followed by a byte that is ignored
+                    fetch();
+
+                    Instruction::Stop
+                }
+                3 if !z80 && !lr35902 => Instruction::Nop,
+                3 => Instruction::JumpRelative {
+                    condition: None,
+                    offset: fetch_displacement(fetch),
+                },
+                _ if !z80 && !lr35902 => Instruction::Nop,
+                _ => Instruction::JumpRelative {
+                    condition: Some(Condition::from_id(y - 4)),
+                    offset: fetch_displacement(fetch),
+                },
+            },
+            1 => {
+                if q {
+                    Instruction::AddPair {
+                        destination: hl_or_index(index),
+                        source: register_pair(p, index),
+                    }
+                } else {
+                    Instruction::LoadPairImmediate {
+                        pair: register_pair(p, index),
+                        value: fetch_word(fetch),
+                    }
+                }
+            }
+            2 => match (p, q) {
+                (0, false) => Instruction::Load {
+                    destination: Operand::Indirect(RegisterPair::Bc),
+                    source: Operand::Register(Register::A),
+                },
+                (1, false) => Instruction::Load {
+                    destination: Operand::Indirect(RegisterPair::De),
+                    source: Operand::Register(Register::A),
+                },
+                (0, true) => Instruction::Load {
+                    destination: Operand::Register(Register::A),
+                    source: Operand::Indirect(RegisterPair::Bc),
+                },
+                (1, true) => Instruction::Load {
+                    destination: Operand::Register(Register::A),
+                    source: Operand::Indirect(RegisterPair::De),
+                },
+                (2, false) if lr35902 => Instruction::Load {
+                    destination: Operand::HlIncrement,
+                    source: Operand::Register(Register::A),
+                },
+                (2, true) if lr35902 => Instruction::Load {
+                    destination: Operand::Register(Register::A),
+                    source: Operand::HlIncrement,
+                },
+                (3, false) if lr35902 => Instruction::Load {
+                    destination: Operand::HlDecrement,
+                    source: Operand::Register(Register::A),
+                },
+                (3, true) if lr35902 => Instruction::Load {
+                    destination: Operand::Register(Register::A),
+                    source: Operand::HlDecrement,
+                },
+                (2, false) => Instruction::StorePairIndirect {
+                    address: fetch_word(fetch),
+                    pair: hl_or_index(index),
+                },
+                (2, true) => Instruction::LoadPairIndirect {
+                    pair: hl_or_index(index),
+                    address: fetch_word(fetch),
+                },
+                (_, false) => Instruction::Load {
+                    destination: Operand::Absolute(fetch_word(fetch)),
+                    source: Operand::Register(Register::A),
+                },
+                (_, true) => Instruction::Load {
+                    destination: Operand::Register(Register::A),
+                    source: Operand::Absolute(fetch_word(fetch)),
+                },
+            },
+            3 => {
+                if q {
+                    Instruction::DecrementPair(register_pair(p, index))
+                } else {
+                    Instruction::IncrementPair(register_pair(p, index))
+                }
+            }
+            4 => Instruction::Increment(operand(y, index, fetch)),
+            5 => Instruction::Decrement(operand(y, index, fetch)),
+            6 => Instruction::Load {
+                destination: operand(y, index, fetch),
+                source: Operand::Immediate(fetch()),
+            },
+            _ => match y {
+                0 => Instruction::RotateAccumulator(RotateOperation::Rlc),
+                1 => Instruction::RotateAccumulator(RotateOperation::Rrc),
+                2 => Instruction::RotateAccumulator(RotateOperation::Rl),
+                3 => Instruction::RotateAccumulator(RotateOperation::Rr),
+                4 => Instruction::Daa,
+                5 => Instruction::Cpl,
+                6 => Instruction::Scf,
+                _ => Instruction::Ccf,
+            },
+        },
         0b01 => {
-            let source_register = instruction_first_byte[ARGUMENT].load::<u8>();
-            let destination_register =
-                instruction_first_byte[SECONDARY_INSTRUCTION_IDENTIFIER].load::<u8>();
+            if y == 0b110 && z == 0b110 {
+                Instruction::Halt
+            } else if y == 0b110 || z == 0b110 {
+                // H and L are not replaced when the other side is (IX+d)
+                let displacement = if index.is_some() {
+                    fetch_displacement(fetch)
+                } else {
+                    0
+                };
+                let destination_index = if y == 0b110 { index } else { None };
+                let source_index = if z == 0b110 { index } else { None };
+
+                Instruction::Load {
+                    destination: Operand::from_id(y, destination_index, displacement),
+                    source: Operand::from_id(z, source_index, displacement),
+                }
+            } else {
+                Instruction::Load {
+                    destination: Operand::from_id(y, index, 0),
+                    source: Operand::from_id(z, index, 0),
+                }
+            }
+        }
+        0b10 => Instruction::Alu {
+            operation: AluOperation::from_id(y),
+            operand: operand(z, index, fetch),
+        },
+        _ => match z {
+            0 => match y {
+                4 if lr35902 => Instruction::Load {
+                    destination: Operand::HighPage(fetch()),
+                    source: Operand::Register(Register::A),
+                },
+                5 if lr35902 => Instruction::AddStackPointer(fetch_displacement(fetch)),
+                6 if lr35902 => Instruction::Load {
+                    destination: Operand::Register(Register::A),
+                    source: Operand::HighPage(fetch()),
+                },
+                7 if lr35902 => Instruction::LoadStackOffset(fetch_displacement(fetch)),
+                _ => Instruction::Return(Some(Condition::from_id(y))),
+            },
+            1 => {
+                if q {
+                    match p {
+                        0 => Instruction::Return(None),
+                        1 if z80 => Instruction::Exchange(Exchange::Shadow),
+                        1 if lr35902 => Instruction::ReturnFromInterrupt,
+                        1 => Instruction::Return(None),
+                        2 => Instruction::JumpPair(hl_or_index(index)),
+                        _ => Instruction::LoadStackPointer(hl_or_index(index)),
+                    }
+                } else {
+                    Instruction::Pop(register_pair_af(p, index))
+                }
+            }
+            2 => match y {
+                4 if lr35902 => Instruction::Load {
+                    destination: Operand::HighPageC,
+                    source: Operand::Register(Register::A),
+                },
+                5 if lr35902 => Instruction::Load {
+                    destination: Operand::Absolute(fetch_word(fetch)),
+                    source: Operand::Register(Register::A),
+                },
+                6 if lr35902 => Instruction::Load {
+                    destination: Operand::Register(Register::A),
+                    source: Operand::HighPageC,
+                },
+                7 if lr35902 => Instruction::Load {
+                    destination: Operand::Register(Register::A),
+                    source: Operand::Absolute(fetch_word(fetch)),
+                },
+                _ => Instruction::Jump {
+                    condition: Some(Condition::from_id(y)),
+                    address: fetch_word(fetch),
+                },
+            },
+            3 => match y {
+                // CB on the 8080 is a duplicate of JMP
+                0 | 1 => Instruction::Jump {
+                    condition: None,
+                    address: fetch_word(fetch),
+                },
+                2..=5 if lr35902 => Instruction::Illegal,
+                2 => Instruction::Out {
+                    source: Some(Register::A),
+                    port: Port::Immediate(fetch()),
+                },
+                3 => Instruction::In {
+                    destination: Some(Register::A),
+                    port: Port::Immediate(fetch()),
+                },
+                4 => Instruction::Exchange(Exchange::StackTop(hl_or_index(index))),
+                5 => Instruction::Exchange(Exchange::DeHl),
+                6 => Instruction::DisableInterrupts,
+                _ => Instruction::EnableInterrupts,
+            },
+            4 => match y {
+                4..=7 if lr35902 => Instruction::Illegal,
+                _ => Instruction::Call {
+                    condition: Some(Condition::from_id(y)),
+                    address: fetch_word(fetch),
+                },
+            },
+            5 => {
+                if q {
+                    match p {
+                        1..=3 if lr35902 => Instruction::Illegal,
+                        // DD, ED and FD on the 8080 are duplicates of CALL
+                        _ => Instruction::Call {
+                            condition: None,
+                            address: fetch_word(fetch),
+                        },
+                    }
+                } else {
+                    Instruction::Push(register_pair_af(p, index))
+                }
+            }
+            6 => Instruction::Alu {
+                operation: AluOperation::from_id(y),
+                operand: Operand::Immediate(fetch()),
+            },
+            _ => Instruction::Restart(y * 8),
+        },
+    }
+}
+
+fn rotate_operation(kind: Intel8080Kind, y: u8) -> RotateOperation {
+    match y {
+        0 => RotateOperation::Rlc,
+        1 => RotateOperation::Rrc,
+        2 => RotateOperation::Rl,
+        3 => RotateOperation::Rr,
+        4 => RotateOperation::Sla,
+        5 => RotateOperation::Sra,
+        6 if kind == Intel8080Kind::SharpLr35902 => RotateOperation::Swap,
+        6 => RotateOperation::Sll,
+        _ => RotateOperation::Srl,
+    }
+}
+
+/// Decodes the CB page, where DDCB and FDCB have their displacement before
+/// the opcode
+fn decode_cb(
+    kind: Intel8080Kind,
+    indexed: Option<(IndexRegister, i8)>,
+    fetch: &mut impl FnMut() -> u8,
+) -> Decoded {
+    let opcode = fetch();
+
+    let x = opcode >> 6;
+    let y = (opcode >> 3) & 0b111;
+    let z = opcode & 0b111;
+
+    let (operand, copy_to) = match indexed {
+        Some((index, displacement)) => {
+            (Operand::Indexed(index, displacement), Register::from_id(z))
+        }
+        None => (Operand::from_id(z, None, 0), None),
+    };
+
+    let instruction = match x {
+        0b00 => Instruction::Rotate {
+            operation: rotate_operation(kind, y),
+            operand,
+            copy_to,
+        },
+        0b01 => Instruction::Bit { bit: y, operand },
+        0b10 => Instruction::Reset {
+            bit: y,
+            operand,
+            copy_to,
+        },
+        _ => Instruction::Set {
+            bit: y,
+            operand,
+            copy_to,
+        },
+    };
+
+    let memory = operand.is_memory();
+    let bit = x == 0b01;
 
-            let source_register = SingleByteArgument::from_id(source_register).unwrap();
-            let destination_register = SingleByteArgument::from_id(destination_register).unwrap();
+    let cycles = match (kind, indexed.is_some(), memory, bit) {
+        (Intel8080Kind::SharpLr35902, _, true, true) => 12,
+        (Intel8080Kind::SharpLr35902, _, true, false) => 16,
+        (_, true, _, true) => 20,
+        (_, true, _, false) => 23,
+        (_, false, true, true) => 12,
+        (_, false, true, false) => 15,
+        _ => 8,
+    };
 
-            if source_register == SingleByteArgument::HlIndirect
-                && destination_register == SingleByteArgument::HlIndirect
-            {}
+    Decoded {
+        instruction,
+        cycles,
+        fetches: 2,
+    }
+}
+
+/// Decodes the Z80 ED page, where anything undefined does nothing
+fn decode_ed(fetch: &mut impl FnMut() -> u8) -> Decoded {
+    let opcode = fetch();
+
+    let x = opcode >> 6;
+    let y = (opcode >> 3) & 0b111;
+    let z = opcode & 0b111;
+    let p = y >> 1;
+    let q = y & 1 != 0;
+
+    let (instruction, cycles) = match x {
+        0b01 => match z {
+            0 => (
+                Instruction::In {
+                    destination: Register::from_id(y),
+                    port: Port::C,
+                },
+                12,
+            ),
+            1 => (
+                Instruction::Out {
+                    source: Register::from_id(y),
+                    port: Port::C,
+                },
+                12,
+            ),
+            2 => {
+                let pair = register_pair(p, None);
+
+                if q {
+                    (Instruction::AdcPair(pair), 15)
+                } else {
+                    (Instruction::SbcPair(pair), 15)
+                }
+            }
+            3 => {
+                let address = fetch_word(fetch);
+                let pair = register_pair(p, None);
+
+                if q {
+                    (Instruction::LoadPairIndirect { pair, address }, 20)
+                } else {
+                    (Instruction::StorePairIndirect { address, pair }, 20)
+                }
+            }
+            4 => (Instruction::Neg, 8),
+            5 if y == 1 => (Instruction::ReturnFromInterrupt, 14),
+            5 => (Instruction::ReturnFromNmi, 14),
+            6 => (
+                Instruction::InterruptMode([0, 0, 1, 2][usize::from(y & 0b11)]),
+                8,
+            ),
+            _ => match y {
+                0 => (
+                    Instruction::Load {
+                        destination: Operand::I,
+                        source: Operand::Register(Register::A),
+                    },
+                    9,
+                ),
+                1 => (
+                    Instruction::Load {
+                        destination: Operand::R,
+                        source: Operand::Register(Register::A),
+                    },
+                    9,
+                ),
+                2 => (
+                    Instruction::Load {
+                        destination: Operand::Register(Register::A),
+                        source: Operand::I,
+                    },
+                    9,
+                ),
+                3 => (
+                    Instruction::Load {
+                        destination: Operand::Register(Register::A),
+                        source: Operand::R,
+                    },
+                    9,
+                ),
+                4 => (Instruction::Rrd, 18),
+                5 => (Instruction::Rld, 18),
+                _ => (Instruction::Nop, 8),
+            },
+        },
+        0b10 if z <= 3 && y >= 4 => {
+            let operation = match z {
+                0 => BlockOperation::Load,
+                1 => BlockOperation::Compare,
+                2 => BlockOperation::In,
+                _ => BlockOperation::Out,
+            };
+
+            (
+                Instruction::Block {
+                    operation,
+                    decrement: y & 1 != 0,
+                    repeat: y >= 6,
+                },
+                16,
+            )
         }
-        0b10 => {
-            todo!()
+        _ => (Instruction::Nop, 8),
+    };
+
+    Decoded {
+        instruction,
+        cycles,
+        fetches: 2,
+    }
+}
+
+/// Decodes an instruction behind a DD or FD prefix
+fn decode_indexed(index: IndexRegister, fetch: &mut impl FnMut() -> u8) -> Decoded {
+    let opcode = fetch();
+
+    match opcode {
+        0xcb => {
+            let displacement = fetch_displacement(fetch);
+
+            decode_cb(Intel8080Kind::Zilog80, Some((index, displacement)), fetch)
         }
-        0b11 => {
-            todo!()
+        // Another prefix cancels this one, which then acts as a NOP
+        0xdd | 0xed | 0xfd => {
+            let decoded = match opcode {
+                0xdd => decode_indexed(IndexRegister::Ix, fetch),
+                0xfd => decode_indexed(IndexRegister::Iy, fetch),
+                _ => decode_ed(fetch),
+            };
+
+            Decoded {
+                cycles: decoded.cycles + 4,
+                fetches: decoded.fetches + 1,
+                ..decoded
+            }
         }
         _ => {
-            unreachable!()
+            let instruction = decode_main(Intel8080Kind::Zilog80, opcode, Some(index), fetch);
+            let base = Z80_CYCLES[usize::from(opcode)];
+
+            let indexed_memory = match instruction {
+                Instruction::Load {
+                    destination,
+                    source,
+                } => {
+                    matches!(destination, Operand::Indexed(..))
+                        || matches!(source, Operand::Indexed(..))
+                }
+                Instruction::Alu { operand, .. }
+                | Instruction::Increment(operand)
+                | Instruction::Decrement(operand) => matches!(operand, Operand::Indexed(..)),
+                _ => false,
+            };
+
+            let cycles = match (indexed_memory, opcode) {
+                // LD (IX+d),n overlaps fetching the immediate with the address calculation
+                (true, 0x36) => 19,
+                (true, _) => base + 12,
+                (false, _) => base + 4,
+            };
+
+            Decoded {
+                instruction,
+                cycles,
+                fetches: 2,
+            }
         }
     }
-
-    Ok(())
 }
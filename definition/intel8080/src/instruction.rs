@@ -1,4 +1,6 @@
-#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum Register {
     A,
     B,
@@ -9,39 +11,299 @@ pub enum Register {
     L,
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub enum SingleByteArgument {
-    Register(Register),
-    HlIndirect,
-}
-
-impl SingleByteArgument {
+impl Register {
+    /// Decodes the 3 bit register field, where 0b110 is not a register
     pub fn from_id(id: u8) -> Option<Self> {
         match id {
-            0b000 => Some(SingleByteArgument::Register(Register::B)),
-            0b001 => Some(SingleByteArgument::Register(Register::C)),
-            0b010 => Some(SingleByteArgument::Register(Register::D)),
-            0b011 => Some(SingleByteArgument::Register(Register::E)),
-            0b100 => Some(SingleByteArgument::Register(Register::H)),
-            0b101 => Some(SingleByteArgument::Register(Register::L)),
-            0b110 => Some(SingleByteArgument::HlIndirect),
-            0b111 => Some(SingleByteArgument::Register(Register::A)),
+            0b000 => Some(Register::B),
+            0b001 => Some(Register::C),
+            0b010 => Some(Register::D),
+            0b011 => Some(Register::E),
+            0b100 => Some(Register::H),
+            0b101 => Some(Register::L),
+            0b111 => Some(Register::A),
             _ => None,
         }
     }
 }
 
-pub enum I8080Instruction {
-    Nop,
-    Ld,
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum IndexRegister {
+    Ix,
+    Iy,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum RegisterPair {
+    Bc,
+    De,
+    Hl,
+    Sp,
+    Af,
+    Index(IndexRegister),
+}
+
+/// Where an 8 bit value comes from or goes to
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Operand {
+    Register(Register),
+    /// Memory pointed to by BC, DE or HL
+    Indirect(RegisterPair),
+    /// (IX+d) and (IY+d)
+    Indexed(IndexRegister, i8),
+    /// The undocumented halves of IX and IY, standing in for H and L
+    IndexHigh(IndexRegister),
+    IndexLow(IndexRegister),
+    Absolute(u16),
+    Immediate(u8),
+    /// LR35902 LDH, which addresses 0xff00 onwards
+    HighPage(u8),
+    /// LR35902 LD (C), which addresses 0xff00 onwards
+    HighPageC,
+    /// LR35902 (HL+)
+    HlIncrement,
+    /// LR35902 (HL-)
+    HlDecrement,
+    /// Z80 interrupt vector base
+    I,
+    /// Z80 refresh counter
+    R,
+}
+
+impl Operand {
+    /// Decodes the 3 bit register field, with HL substituted by an index
+    /// register if prefixed
+    pub fn from_id(id: u8, index: Option<IndexRegister>, displacement: i8) -> Self {
+        match (Register::from_id(id), index) {
+            (Some(Register::H), Some(index)) => Operand::IndexHigh(index),
+            (Some(Register::L), Some(index)) => Operand::IndexLow(index),
+            (Some(register), _) => Operand::Register(register),
+            (None, Some(index)) => Operand::Indexed(index, displacement),
+            (None, None) => Operand::Indirect(RegisterPair::Hl),
+        }
+    }
+
+    pub fn is_memory(&self) -> bool {
+        !matches!(
+            self,
+            Operand::Register(_)
+                | Operand::IndexHigh(_)
+                | Operand::IndexLow(_)
+                | Operand::Immediate(_)
+                | Operand::I
+                | Operand::R
+        )
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Condition {
+    NotZero,
+    Zero,
+    NoCarry,
+    Carry,
+    ParityOdd,
+    ParityEven,
+    Plus,
+    Minus,
 }
 
-pub enum Lr35902Instruction {}
+impl Condition {
+    pub fn from_id(id: u8) -> Self {
+        match id & 0b111 {
+            0b000 => Condition::NotZero,
+            0b001 => Condition::Zero,
+            0b010 => Condition::NoCarry,
+            0b011 => Condition::Carry,
+            0b100 => Condition::ParityOdd,
+            0b101 => Condition::ParityEven,
+            0b110 => Condition::Plus,
+            _ => Condition::Minus,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum AluOperation {
+    Add,
+    Adc,
+    Sub,
+    Sbc,
+    And,
+    Xor,
+    Or,
+    Cp,
+}
+
+impl AluOperation {
+    pub fn from_id(id: u8) -> Self {
+        match id & 0b111 {
+            0b000 => AluOperation::Add,
+            0b001 => AluOperation::Adc,
+            0b010 => AluOperation::Sub,
+            0b011 => AluOperation::Sbc,
+            0b100 => AluOperation::And,
+            0b101 => AluOperation::Xor,
+            0b110 => AluOperation::Or,
+            _ => AluOperation::Cp,
+        }
+    }
+}
 
-pub enum Z80Instruction {}
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum RotateOperation {
+    Rlc,
+    Rrc,
+    Rl,
+    Rr,
+    Sla,
+    Sra,
+    /// Undocumented on the Z80, shifting in a 1
+    Sll,
+    /// Takes the place of SLL on the LR35902
+    Swap,
+    Srl,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum BlockOperation {
+    Load,
+    Compare,
+    In,
+    Out,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Exchange {
+    /// EX DE,HL
+    DeHl,
+    /// EX AF,AF'
+    AfShadow,
+    /// EXX
+    Shadow,
+    /// EX (SP),HL and friends
+    StackTop(RegisterPair),
+}
 
-pub enum InstructionSet {
-    I8080(I8080Instruction),
-    Lr35902(Lr35902Instruction),
-    Z80(Z80Instruction),
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Port {
+    Immediate(u8),
+    /// The Z80 port in C, with B on the upper address lines
+    C,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Instruction {
+    Nop,
+    Halt,
+    /// LR35902 STOP
+    Stop,
+    Load {
+        destination: Operand,
+        source: Operand,
+    },
+    LoadPairImmediate {
+        pair: RegisterPair,
+        value: u16,
+    },
+    LoadPairIndirect {
+        pair: RegisterPair,
+        address: u16,
+    },
+    StorePairIndirect {
+        address: u16,
+        pair: RegisterPair,
+    },
+    LoadStackPointer(RegisterPair),
+    /// LR35902 LD HL,SP+e
+    LoadStackOffset(i8),
+    /// LR35902 ADD SP,e
+    AddStackPointer(i8),
+    Push(RegisterPair),
+    Pop(RegisterPair),
+    Exchange(Exchange),
+    Alu {
+        operation: AluOperation,
+        operand: Operand,
+    },
+    Increment(Operand),
+    Decrement(Operand),
+    IncrementPair(RegisterPair),
+    DecrementPair(RegisterPair),
+    AddPair {
+        destination: RegisterPair,
+        source: RegisterPair,
+    },
+    /// Z80 ADC HL,rr
+    AdcPair(RegisterPair),
+    /// Z80 SBC HL,rr
+    SbcPair(RegisterPair),
+    /// RLCA, RRCA, RLA and RRA, which leave most flags alone
+    RotateAccumulator(RotateOperation),
+    /// The CB page, where indexed versions also copy the result to a register
+    Rotate {
+        operation: RotateOperation,
+        operand: Operand,
+        copy_to: Option<Register>,
+    },
+    Bit {
+        bit: u8,
+        operand: Operand,
+    },
+    Reset {
+        bit: u8,
+        operand: Operand,
+        copy_to: Option<Register>,
+    },
+    Set {
+        bit: u8,
+        operand: Operand,
+        copy_to: Option<Register>,
+    },
+    Daa,
+    Cpl,
+    Neg,
+    Scf,
+    Ccf,
+    Jump {
+        condition: Option<Condition>,
+        address: u16,
+    },
+    /// JP (HL) and friends
+    JumpPair(RegisterPair),
+    JumpRelative {
+        condition: Option<Condition>,
+        offset: i8,
+    },
+    Djnz(i8),
+    Call {
+        condition: Option<Condition>,
+        address: u16,
+    },
+    Return(Option<Condition>),
+    /// RETI, which the LR35902 also enables interrupts with
+    ReturnFromInterrupt,
+    /// RETN
+    ReturnFromNmi,
+    Restart(u8),
+    DisableInterrupts,
+    EnableInterrupts,
+    InterruptMode(u8),
+    In {
+        destination: Option<Register>,
+        port: Port,
+    },
+    Out {
+        source: Option<Register>,
+        port: Port,
+    },
+    Block {
+        operation: BlockOperation,
+        decrement: bool,
+        repeat: bool,
+    },
+    Rrd,
+    Rld,
+    /// Opcodes the LR35902 does not have, which lock it up
+    Illegal,
 }
@@ -0,0 +1,954 @@
+use crate::{
+    Intel8080, Intel8080Kind,
+    instruction::{
+        AluOperation, BlockOperation, Condition, Exchange, IndexRegister, Instruction, Operand,
+        Port, Register, RegisterPair, RotateOperation,
+    },
+};
+
+// NOTE: http://www.z80.info/z80sflag.htm and https://gbdev.io/pandocs/CPU_Instruction_Set.html
+//
+// FIXME: The undocumented bits 3 and 5 of SCF and CCF do not account for
+// whether the previous instruction changed the flags
+
+impl Intel8080 {
+    /// Runs a decoded instruction, returning if its condition was met or it is
+    /// repeating, which costs extra cycles
+    pub(super) fn interpret_instruction(&mut self, instruction: Instruction) -> bool {
+        match instruction {
+            Instruction::Nop => {}
+            Instruction::Halt => {
+                if self.kind() == Intel8080Kind::SharpLr35902
+                    && !self.state.iff1
+                    && self.interrupt_flags.pending() != 0
+                {
+                    self.state.halt_bug = true;
+                } else {
+                    self.state.halted = true;
+                }
+            }
+            Instruction::Stop => {
+                if !self.switch_speed() {
+                    self.state.stopped = true;
+                }
+            }
+            Instruction::Illegal => {
+                tracing::warn!("Illegal opcode executed, locking up");
+
+                self.state.locked = true;
+            }
+            Instruction::Load {
+                destination,
+                source,
+            } => {
+                let value = self.read_operand(source);
+
+                // LD A,I and LD A,R are the only loads that touch the flags
+                if matches!(source, Operand::I | Operand::R) {
+                    self.set_sign_zero(value);
+                    self.state.flags.half_carry = false;
+                    self.state.flags.subtract = false;
+                    self.state.flags.parity_overflow = self.state.iff2;
+                }
+
+                self.write_operand(destination, value);
+            }
+            Instruction::LoadPairImmediate { pair, value } => {
+                self.set_pair(pair, value);
+            }
+            Instruction::LoadPairIndirect { pair, address } => {
+                let value = self.read_word(address);
+
+                self.set_pair(pair, value);
+                self.state.wz = address.wrapping_add(1);
+            }
+            Instruction::StorePairIndirect { address, pair } => {
+                let value = self.pair(pair);
+
+                self.write_word(address, value);
+                self.state.wz = address.wrapping_add(1);
+            }
+            Instruction::LoadStackPointer(pair) => {
+                self.state.sp = self.pair(pair);
+            }
+            Instruction::LoadStackOffset(offset) => {
+                let result = self.add_stack_offset(offset);
+
+                self.set_pair(RegisterPair::Hl, result);
+            }
+            Instruction::AddStackPointer(offset) => {
+                self.state.sp = self.add_stack_offset(offset);
+            }
+            Instruction::Push(pair) => {
+                let value = self.pair(pair);
+
+                self.push(value);
+            }
+            Instruction::Pop(pair) => {
+                let value = self.pop();
+
+                self.set_pair(pair, value);
+            }
+            Instruction::Exchange(exchange) => match exchange {
+                Exchange::DeHl => {
+                    let de = self.pair(RegisterPair::De);
+                    let hl = self.pair(RegisterPair::Hl);
+
+                    self.set_pair(RegisterPair::De, hl);
+                    self.set_pair(RegisterPair::Hl, de);
+                }
+                Exchange::AfShadow => {
+                    let af = self.pair(RegisterPair::Af);
+
+                    self.set_pair(RegisterPair::Af, self.state.shadow[0]);
+                    self.state.shadow[0] = af;
+                }
+                Exchange::Shadow => {
+                    for (index, pair) in [RegisterPair::Bc, RegisterPair::De, RegisterPair::Hl]
+                        .into_iter()
+                        .enumerate()
+                    {
+                        let value = self.pair(pair);
+
+                        self.set_pair(pair, self.state.shadow[index + 1]);
+                        self.state.shadow[index + 1] = value;
+                    }
+                }
+                Exchange::StackTop(pair) => {
+                    let stack_top = self.read_word(self.state.sp);
+                    let value = self.pair(pair);
+
+                    self.write_word(self.state.sp, value);
+                    self.set_pair(pair, stack_top);
+                    self.state.wz = stack_top;
+                }
+            },
+            Instruction::Alu { operation, operand } => {
+                let value = self.read_operand(operand);
+
+                self.alu(operation, value);
+            }
+            Instruction::Increment(operand) => {
+                let value = self.read_operand(operand);
+                let result = value.wrapping_add(1);
+
+                self.set_sign_zero(result);
+                self.state.flags.half_carry = value & 0x0f == 0x0f;
+                self.state.flags.subtract = false;
+                self.state.flags.parity_overflow = if self.kind() == Intel8080Kind::Zilog80 {
+                    value == 0x7f
+                } else {
+                    parity(result)
+                };
+
+                self.write_operand(operand, result);
+            }
+            Instruction::Decrement(operand) => {
+                let value = self.read_operand(operand);
+                let result = value.wrapping_sub(1);
+
+                self.set_sign_zero(result);
+                // The 8080 adds 0xff instead, so its auxiliary carry is the opposite
+                self.state.flags.half_carry = if self.kind() == Intel8080Kind::Intel8080 {
+                    value & 0x0f != 0
+                } else {
+                    value & 0x0f == 0
+                };
+                self.state.flags.subtract = true;
+                self.state.flags.parity_overflow = if self.kind() == Intel8080Kind::Zilog80 {
+                    value == 0x80
+                } else {
+                    parity(result)
+                };
+
+                self.write_operand(operand, result);
+            }
+            Instruction::IncrementPair(pair) => {
+                let value = self.pair(pair).wrapping_add(1);
+
+                self.set_pair(pair, value);
+            }
+            Instruction::DecrementPair(pair) => {
+                let value = self.pair(pair).wrapping_sub(1);
+
+                self.set_pair(pair, value);
+            }
+            Instruction::AddPair {
+                destination,
+                source,
+            } => {
+                let left = self.pair(destination);
+                let right = self.pair(source);
+                let result = left.wrapping_add(right);
+
+                self.state.flags.carry = left.checked_add(right).is_none();
+
+                if self.kind() != Intel8080Kind::Intel8080 {
+                    self.state.flags.half_carry = (left & 0x0fff) + (right & 0x0fff) > 0x0fff;
+                    self.state.flags.subtract = false;
+                    self.set_undocumented((result >> 8) as u8);
+                }
+
+                self.state.wz = left.wrapping_add(1);
+                self.set_pair(destination, result);
+            }
+            Instruction::AdcPair(source) => {
+                let left = self.pair(RegisterPair::Hl);
+                let right = self.pair(source);
+                let carry = u32::from(self.state.flags.carry);
+                let result = u32::from(left) + u32::from(right) + carry;
+
+                self.set_pair_arithmetic_flags(left, right, result, false);
+                self.state.flags.half_carry =
+                    u32::from(left & 0x0fff) + u32::from(right & 0x0fff) + carry > 0x0fff;
+            }
+            Instruction::SbcPair(source) => {
+                let left = self.pair(RegisterPair::Hl);
+                let right = self.pair(source);
+                let carry = u32::from(self.state.flags.carry);
+                let result = u32::from(left)
+                    .wrapping_sub(u32::from(right))
+                    .wrapping_sub(carry);
+
+                self.set_pair_arithmetic_flags(left, right, result, true);
+                self.state.flags.half_carry =
+                    u32::from(left & 0x0fff) < u32::from(right & 0x0fff) + carry;
+            }
+            Instruction::RotateAccumulator(operation) => {
+                let result = self.rotate(operation, self.state.a);
+                self.state.a = result;
+
+                match self.kind() {
+                    Intel8080Kind::Intel8080 => {}
+                    Intel8080Kind::Zilog80 => {
+                        self.state.flags.half_carry = false;
+                        self.state.flags.subtract = false;
+                        self.set_undocumented(result);
+                    }
+                    Intel8080Kind::SharpLr35902 => {
+                        self.state.flags.zero = false;
+                        self.state.flags.half_carry = false;
+                        self.state.flags.subtract = false;
+                    }
+                }
+            }
+            Instruction::Rotate {
+                operation,
+                operand,
+                copy_to,
+            } => {
+                let value = self.read_operand(operand);
+                let result = self.rotate(operation, value);
+
+                self.set_sign_zero_parity(result);
+                self.state.flags.half_carry = false;
+                self.state.flags.subtract = false;
+
+                self.write_operand(operand, result);
+
+                if let Some(register) = copy_to {
+                    self.set_register(register, result);
+                }
+            }
+            Instruction::Bit { bit, operand } => {
+                let value = self.read_operand(operand);
+                let set = value & (1 << bit) != 0;
+
+                self.state.flags.zero = !set;
+                self.state.flags.parity_overflow = !set;
+                self.state.flags.sign = bit == 7 && set;
+                self.state.flags.half_carry = true;
+                self.state.flags.subtract = false;
+
+                // Memory operands leak the internal address latch instead
+                if operand.is_memory() {
+                    self.set_undocumented((self.state.wz >> 8) as u8);
+                } else {
+                    self.set_undocumented(value);
+                }
+            }
+            Instruction::Reset {
+                bit,
+                operand,
+                copy_to,
+            } => {
+                let result = self.read_operand(operand) & !(1 << bit);
+
+                self.write_operand(operand, result);
+
+                if let Some(register) = copy_to {
+                    self.set_register(register, result);
+                }
+            }
+            Instruction::Set {
+                bit,
+                operand,
+                copy_to,
+            } => {
+                let result = self.read_operand(operand) | (1 << bit);
+
+                self.write_operand(operand, result);
+
+                if let Some(register) = copy_to {
+                    self.set_register(register, result);
+                }
+            }
+            Instruction::Daa => self.daa(),
+            Instruction::Cpl => {
+                self.state.a = !self.state.a;
+
+                if self.kind() != Intel8080Kind::Intel8080 {
+                    self.state.flags.half_carry = true;
+                    self.state.flags.subtract = true;
+                    self.set_undocumented(self.state.a);
+                }
+            }
+            Instruction::Neg => {
+                let value = self.state.a;
+                self.state.a = 0;
+
+                self.alu(AluOperation::Sub, value);
+            }
+            Instruction::Scf => {
+                self.state.flags.carry = true;
+
+                if self.kind() != Intel8080Kind::Intel8080 {
+                    self.state.flags.half_carry = false;
+                    self.state.flags.subtract = false;
+                    self.set_undocumented(self.state.a);
+                }
+            }
+            Instruction::Ccf => {
+                let carry = self.state.flags.carry;
+                self.state.flags.carry = !carry;
+
+                match self.kind() {
+                    Intel8080Kind::Intel8080 => {}
+                    Intel8080Kind::Zilog80 => {
+                        self.state.flags.half_carry = carry;
+                        self.state.flags.subtract = false;
+                        self.set_undocumented(self.state.a);
+                    }
+                    Intel8080Kind::SharpLr35902 => {
+                        self.state.flags.half_carry = false;
+                        self.state.flags.subtract = false;
+                    }
+                }
+            }
+            Instruction::Jump { condition, address } => {
+                self.state.wz = address;
+
+                if self.condition_met(condition) {
+                    self.state.pc = address;
+
+                    return condition.is_some();
+                }
+            }
+            Instruction::JumpPair(pair) => {
+                self.state.pc = self.pair(pair);
+            }
+            Instruction::JumpRelative { condition, offset } => {
+                if self.condition_met(condition) {
+                    self.state.pc = self.state.pc.wrapping_add_signed(i16::from(offset));
+                    self.state.wz = self.state.pc;
+
+                    return condition.is_some();
+                }
+            }
+            Instruction::Djnz(offset) => {
+                self.state.b = self.state.b.wrapping_sub(1);
+
+                if self.state.b != 0 {
+                    self.state.pc = self.state.pc.wrapping_add_signed(i16::from(offset));
+                    self.state.wz = self.state.pc;
+
+                    return true;
+                }
+            }
+            Instruction::Call { condition, address } => {
+                self.state.wz = address;
+
+                if self.condition_met(condition) {
+                    self.push(self.state.pc);
+                    self.state.pc = address;
+
+                    return condition.is_some();
+                }
+            }
+            Instruction::Return(condition) => {
+                if self.condition_met(condition) {
+                    self.state.pc = self.pop();
+                    self.state.wz = self.state.pc;
+
+                    return condition.is_some();
+                }
+            }
+            Instruction::ReturnFromInterrupt | Instruction::ReturnFromNmi => {
+                self.state.pc = self.pop();
+                self.state.wz = self.state.pc;
+
+                if self.kind() == Intel8080Kind::SharpLr35902 {
+                    self.state.iff1 = true;
+                } else {
+                    self.state.iff1 = self.state.iff2;
+                }
+            }
+            Instruction::Restart(address) => {
+                self.push(self.state.pc);
+                self.state.pc = u16::from(address);
+                self.state.wz = self.state.pc;
+            }
+            Instruction::DisableInterrupts => {
+                self.state.iff1 = false;
+                self.state.iff2 = false;
+            }
+            Instruction::EnableInterrupts => {
+                self.state.iff1 = true;
+                self.state.iff2 = true;
+                self.state.interrupt_delay = true;
+            }
+            Instruction::InterruptMode(mode) => {
+                self.state.interrupt_mode = mode;
+            }
+            Instruction::In { destination, port } => {
+                let port = self.port(port);
+                let value = self.port_read(port);
+                self.state.wz = port.wrapping_add(1);
+
+                // Only the Z80 IN r,(C) touches the flags
+                if port_is_c(&instruction) {
+                    self.set_sign_zero_parity(value);
+                    self.state.flags.half_carry = false;
+                    self.state.flags.subtract = false;
+                }
+
+                if let Some(register) = destination {
+                    self.set_register(register, value);
+                }
+            }
+            Instruction::Out { source, port } => {
+                let port = self.port(port);
+                let value = source.map_or(0, |register| self.register(register));
+
+                self.state.wz = match instruction {
+                    Instruction::Out {
+                        port: Port::Immediate(_),
+                        ..
+                    } => (port & 0xff00) | (port.wrapping_add(1) & 0x00ff),
+                    _ => port.wrapping_add(1),
+                };
+                self.port_write(port, value);
+            }
+            Instruction::Block {
+                operation,
+                decrement,
+                repeat,
+            } => return self.block(operation, decrement, repeat),
+            Instruction::Rrd | Instruction::Rld => {
+                let address = self.pair(RegisterPair::Hl);
+                let value = self.read(address);
+                let a = self.state.a;
+
+                let (result, a) = if instruction == Instruction::Rld {
+                    ((value << 4) | (a & 0x0f), (a & 0xf0) | (value >> 4))
+                } else {
+                    ((a << 4) | (value >> 4), (a & 0xf0) | (value & 0x0f))
+                };
+
+                self.write(address, result);
+                self.state.a = a;
+                self.set_sign_zero_parity(a);
+                self.state.flags.half_carry = false;
+                self.state.flags.subtract = false;
+                self.state.wz = address.wrapping_add(1);
+            }
+        }
+
+        false
+    }
+
+    fn condition_met(&self, condition: Option<Condition>) -> bool {
+        let flags = &self.state.flags;
+
+        match condition {
+            None => true,
+            Some(Condition::NotZero) => !flags.zero,
+            Some(Condition::Zero) => flags.zero,
+            Some(Condition::NoCarry) => !flags.carry,
+            Some(Condition::Carry) => flags.carry,
+            Some(Condition::ParityOdd) => !flags.parity_overflow,
+            Some(Condition::ParityEven) => flags.parity_overflow,
+            Some(Condition::Plus) => !flags.sign,
+            Some(Condition::Minus) => flags.sign,
+        }
+    }
+
+    fn register(&self, register: Register) -> u8 {
+        match register {
+            Register::A => self.state.a,
+            Register::B => self.state.b,
+            Register::C => self.state.c,
+            Register::D => self.state.d,
+            Register::E => self.state.e,
+            Register::H => self.state.h,
+            Register::L => self.state.l,
+        }
+    }
+
+    fn set_register(&mut self, register: Register, value: u8) {
+        match register {
+            Register::A => self.state.a = value,
+            Register::B => self.state.b = value,
+            Register::C => self.state.c = value,
+            Register::D => self.state.d = value,
+            Register::E => self.state.e = value,
+            Register::H => self.state.h = value,
+            Register::L => self.state.l = value,
+        }
+    }
+
+    fn index_register(&mut self, index: IndexRegister) -> &mut u16 {
+        match index {
+            IndexRegister::Ix => &mut self.state.ix,
+            IndexRegister::Iy => &mut self.state.iy,
+        }
+    }
+
+    pub(super) fn pair(&self, pair: RegisterPair) -> u16 {
+        let state = &self.state;
+
+        match pair {
+            RegisterPair::Bc => u16::from_be_bytes([state.b, state.c]),
+            RegisterPair::De => u16::from_be_bytes([state.d, state.e]),
+            RegisterPair::Hl => u16::from_be_bytes([state.h, state.l]),
+            RegisterPair::Sp => state.sp,
+            RegisterPair::Af => u16::from_be_bytes([state.a, state.flags.to_byte(self.kind())]),
+            RegisterPair::Index(IndexRegister::Ix) => state.ix,
+            RegisterPair::Index(IndexRegister::Iy) => state.iy,
+        }
+    }
+
+    pub(super) fn set_pair(&mut self, pair: RegisterPair, value: u16) {
+        let [high, low] = value.to_be_bytes();
+        let kind = self.kind();
+        let state = &mut self.state;
+
+        match pair {
+            RegisterPair::Bc => (state.b, state.c) = (high, low),
+            RegisterPair::De => (state.d, state.e) = (high, low),
+            RegisterPair::Hl => (state.h, state.l) = (high, low),
+            RegisterPair::Sp => state.sp = value,
+            RegisterPair::Af => {
+                state.a = high;
+                state.flags = crate::FlagRegister::from_byte(kind, low);
+            }
+            RegisterPair::Index(IndexRegister::Ix) => state.ix = value,
+            RegisterPair::Index(IndexRegister::Iy) => state.iy = value,
+        }
+    }
+
+    fn operand_address(&mut self, operand: Operand) -> u16 {
+        match operand {
+            Operand::Indirect(pair) => {
+                let address = self.pair(pair);
+
+                if pair != RegisterPair::Hl {
+                    self.state.wz = address.wrapping_add(1);
+                }
+
+                address
+            }
+            Operand::Indexed(index, displacement) => {
+                let address = self
+                    .index_register(index)
+                    .wrapping_add_signed(i16::from(displacement));
+                self.state.wz = address;
+
+                address
+            }
+            Operand::Absolute(address) => {
+                self.state.wz = address.wrapping_add(1);
+
+                address
+            }
+            Operand::HighPage(offset) => 0xff00 | u16::from(offset),
+            Operand::HighPageC => 0xff00 | u16::from(self.state.c),
+            Operand::HlIncrement => {
+                let address = self.pair(RegisterPair::Hl);
+                self.set_pair(RegisterPair::Hl, address.wrapping_add(1));
+
+                address
+            }
+            Operand::HlDecrement => {
+                let address = self.pair(RegisterPair::Hl);
+                self.set_pair(RegisterPair::Hl, address.wrapping_sub(1));
+
+                address
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn read_operand(&mut self, operand: Operand) -> u8 {
+        match operand {
+            Operand::Register(register) => self.register(register),
+            Operand::IndexHigh(index) => (*self.index_register(index) >> 8) as u8,
+            Operand::IndexLow(index) => *self.index_register(index) as u8,
+            Operand::Immediate(value) => value,
+            Operand::I => self.state.i,
+            Operand::R => self.state.r,
+            _ => {
+                let address = self.operand_address(operand);
+
+                self.read(address)
+            }
+        }
+    }
+
+    fn write_operand(&mut self, operand: Operand, value: u8) {
+        match operand {
+            Operand::Register(register) => self.set_register(register, value),
+            Operand::IndexHigh(index) => {
+                let register = self.index_register(index);
+                *register = (*register & 0x00ff) | (u16::from(value) << 8);
+            }
+            Operand::IndexLow(index) => {
+                let register = self.index_register(index);
+                *register = (*register & 0xff00) | u16::from(value);
+            }
+            Operand::Immediate(_) => unreachable!(),
+            Operand::I => self.state.i = value,
+            Operand::R => self.state.r = value,
+            _ => {
+                let address = self.operand_address(operand);
+
+                // LD (nn),A and LD (BC),A leave A in the upper byte of the latch
+                if matches!(
+                    operand,
+                    Operand::Absolute(_) | Operand::Indirect(RegisterPair::Bc | RegisterPair::De)
+                ) {
+                    self.state.wz = (u16::from(value) << 8) | (self.state.wz & 0x00ff);
+                }
+
+                self.write(address, value);
+            }
+        }
+    }
+
+    fn set_sign_zero(&mut self, value: u8) {
+        self.state.flags.sign = value & 0x80 != 0;
+        self.state.flags.zero = value == 0;
+        self.set_undocumented(value);
+    }
+
+    fn set_sign_zero_parity(&mut self, value: u8) {
+        self.set_sign_zero(value);
+        self.state.flags.parity_overflow = parity(value);
+    }
+
+    fn set_undocumented(&mut self, value: u8) {
+        self.state.flags.y = value & 0b0010_0000 != 0;
+        self.state.flags.x = value & 0b0000_1000 != 0;
+    }
+
+    fn set_pair_arithmetic_flags(&mut self, left: u16, right: u16, result: u32, subtract: bool) {
+        let truncated = result as u16;
+        let sign_changed = (left ^ truncated) & 0x8000 != 0;
+        let same_signs = (left ^ right) & 0x8000 == 0;
+
+        self.state.flags.sign = truncated & 0x8000 != 0;
+        self.state.flags.zero = truncated == 0;
+        self.state.flags.carry = result > 0xffff;
+        self.state.flags.subtract = subtract;
+        self.state.flags.parity_overflow = sign_changed && (same_signs != subtract);
+        self.set_undocumented((truncated >> 8) as u8);
+        self.state.wz = left.wrapping_add(1);
+        self.set_pair(RegisterPair::Hl, truncated);
+    }
+
+    fn add_stack_offset(&mut self, offset: i8) -> u16 {
+        let sp = self.state.sp;
+        let low = u16::from(offset as u8);
+
+        // The flags come from the unsigned addition of the low byte
+        self.state.flags.zero = false;
+        self.state.flags.subtract = false;
+        self.state.flags.half_carry = (sp & 0x0f) + (low & 0x0f) > 0x0f;
+        self.state.flags.carry = (sp & 0xff) + low > 0xff;
+
+        sp.wrapping_add_signed(i16::from(offset))
+    }
+
+    fn alu(&mut self, operation: AluOperation, value: u8) {
+        let a = self.state.a;
+        let kind = self.kind();
+
+        match operation {
+            AluOperation::Add | AluOperation::Adc => {
+                let carry = u8::from(operation == AluOperation::Adc && self.state.flags.carry);
+                let result = u16::from(a) + u16::from(value) + u16::from(carry);
+                let truncated = result as u8;
+
+                self.set_sign_zero(truncated);
+                self.state.flags.half_carry = (a & 0x0f) + (value & 0x0f) + carry > 0x0f;
+                self.state.flags.carry = result > 0xff;
+                self.state.flags.subtract = false;
+                self.state.flags.parity_overflow = if kind == Intel8080Kind::Zilog80 {
+                    (a ^ value) & 0x80 == 0 && (a ^ truncated) & 0x80 != 0
+                } else {
+                    parity(truncated)
+                };
+
+                self.state.a = truncated;
+            }
+            AluOperation::Sub | AluOperation::Sbc | AluOperation::Cp => {
+                let carry = u8::from(operation == AluOperation::Sbc && self.state.flags.carry);
+                let result = i16::from(a) - i16::from(value) - i16::from(carry);
+                let truncated = result as u8;
+                let half_borrow = (a & 0x0f) < (value & 0x0f) + carry;
+
+                self.set_sign_zero(truncated);
+                // The 8080 adds the complement, so its auxiliary carry is the
+                // opposite of a borrow
+                self.state.flags.half_carry = if kind == Intel8080Kind::Intel8080 {
+                    !half_borrow
+                } else {
+                    half_borrow
+                };
+                self.state.flags.carry = result < 0;
+                self.state.flags.subtract = true;
+                self.state.flags.parity_overflow = if kind == Intel8080Kind::Zilog80 {
+                    (a ^ value) & 0x80 != 0 && (a ^ truncated) & 0x80 != 0
+                } else {
+                    parity(truncated)
+                };
+
+                if operation == AluOperation::Cp {
+                    // Compares take the undocumented flags from the operand
+                    self.set_undocumented(value);
+                } else {
+                    self.state.a = truncated;
+                }
+            }
+            AluOperation::And | AluOperation::Xor | AluOperation::Or => {
+                let result = match operation {
+                    AluOperation::And => a & value,
+                    AluOperation::Xor => a ^ value,
+                    _ => a | value,
+                };
+
+                self.set_sign_zero_parity(result);
+                self.state.flags.half_carry = match (operation, kind) {
+                    (AluOperation::And, Intel8080Kind::Intel8080) => (a | value) & 0x08 != 0,
+                    (AluOperation::And, _) => true,
+                    _ => false,
+                };
+                self.state.flags.carry = false;
+                self.state.flags.subtract = false;
+
+                self.state.a = result;
+            }
+        }
+    }
+
+    /// Rotates and shifts, setting only the carry
+    fn rotate(&mut self, operation: RotateOperation, value: u8) -> u8 {
+        let carry = u8::from(self.state.flags.carry);
+
+        let (result, carry) = match operation {
+            RotateOperation::Rlc => (value.rotate_left(1), value & 0x80 != 0),
+            RotateOperation::Rrc => (value.rotate_right(1), value & 0x01 != 0),
+            RotateOperation::Rl => ((value << 1) | carry, value & 0x80 != 0),
+            RotateOperation::Rr => ((value >> 1) | (carry << 7), value & 0x01 != 0),
+            RotateOperation::Sla => (value << 1, value & 0x80 != 0),
+            RotateOperation::Sra => ((value >> 1) | (value & 0x80), value & 0x01 != 0),
+            RotateOperation::Sll => ((value << 1) | 0x01, value & 0x80 != 0),
+            RotateOperation::Swap => (value.rotate_left(4), false),
+            RotateOperation::Srl => (value >> 1, value & 0x01 != 0),
+        };
+
+        self.state.flags.carry = carry;
+
+        result
+    }
+
+    fn daa(&mut self) {
+        let a = self.state.a;
+        let flags = self.state.flags;
+
+        if self.kind() == Intel8080Kind::SharpLr35902 {
+            let mut result = a;
+            let mut carry = flags.carry;
+
+            if flags.subtract {
+                if flags.carry {
+                    result = result.wrapping_sub(0x60);
+                }
+
+                if flags.half_carry {
+                    result = result.wrapping_sub(0x06);
+                }
+            } else {
+                if flags.carry || a > 0x99 {
+                    result = result.wrapping_add(0x60);
+                    carry = true;
+                }
+
+                if flags.half_carry || a & 0x0f > 0x09 {
+                    result = result.wrapping_add(0x06);
+                }
+            }
+
+            self.state.a = result;
+            self.state.flags.zero = result == 0;
+            self.state.flags.half_carry = false;
+            self.state.flags.carry = carry;
+
+            return;
+        }
+
+        let mut correction = 0;
+        let mut carry = flags.carry;
+
+        if flags.half_carry || a & 0x0f > 0x09 {
+            correction |= 0x06;
+        }
+
+        if flags.carry || a > 0x99 {
+            correction |= 0x60;
+            carry = true;
+        }
+
+        // The 8080 has no subtract flag to look at
+        let result = if flags.subtract && self.kind() == Intel8080Kind::Zilog80 {
+            self.state.flags.half_carry = flags.half_carry && a & 0x0f < 0x06;
+
+            a.wrapping_sub(correction)
+        } else {
+            self.state.flags.half_carry = a & 0x0f > 0x09;
+
+            a.wrapping_add(correction)
+        };
+
+        self.set_sign_zero_parity(result);
+        self.state.flags.carry = carry;
+        self.state.a = result;
+    }
+
+    fn port(&self, port: Port) -> u16 {
+        match (port, self.kind()) {
+            // The 8080 puts the port number on both halves of the address bus
+            (Port::Immediate(port), Intel8080Kind::Intel8080) => u16::from_le_bytes([port, port]),
+            (Port::Immediate(port), _) => u16::from_le_bytes([port, self.state.a]),
+            (Port::C, _) => self.pair(RegisterPair::Bc),
+        }
+    }
+
+    /// The Z80 block instructions, returning if they are going around again
+    fn block(&mut self, operation: BlockOperation, decrement: bool, repeat: bool) -> bool {
+        let step = |value: u16| {
+            if decrement {
+                value.wrapping_sub(1)
+            } else {
+                value.wrapping_add(1)
+            }
+        };
+
+        let hl = self.pair(RegisterPair::Hl);
+        self.set_pair(RegisterPair::Hl, step(hl));
+
+        let repeating = match operation {
+            BlockOperation::Load => {
+                let value = self.read(hl);
+                let de = self.pair(RegisterPair::De);
+                self.write(de, value);
+                self.set_pair(RegisterPair::De, step(de));
+
+                let bc = self.pair(RegisterPair::Bc).wrapping_sub(1);
+                self.set_pair(RegisterPair::Bc, bc);
+
+                let n = value.wrapping_add(self.state.a);
+                self.state.flags.y = n & 0b0000_0010 != 0;
+                self.state.flags.x = n & 0b0000_1000 != 0;
+                self.state.flags.half_carry = false;
+                self.state.flags.subtract = false;
+                self.state.flags.parity_overflow = bc != 0;
+
+                bc != 0
+            }
+            BlockOperation::Compare => {
+                let value = self.read(hl);
+                let result = self.state.a.wrapping_sub(value);
+                let half_carry = (self.state.a & 0x0f) < (value & 0x0f);
+
+                let bc = self.pair(RegisterPair::Bc).wrapping_sub(1);
+                self.set_pair(RegisterPair::Bc, bc);
+
+                self.state.flags.sign = result & 0x80 != 0;
+                self.state.flags.zero = result == 0;
+                self.state.flags.half_carry = half_carry;
+                self.state.flags.subtract = true;
+                self.state.flags.parity_overflow = bc != 0;
+
+                let n = result.wrapping_sub(u8::from(half_carry));
+                self.state.flags.y = n & 0b0000_0010 != 0;
+                self.state.flags.x = n & 0b0000_1000 != 0;
+                self.state.wz = step(self.state.wz);
+
+                bc != 0 && result != 0
+            }
+            BlockOperation::In => {
+                let bc = self.pair(RegisterPair::Bc);
+                let value = self.port_read(bc);
+                self.write(hl, value);
+                self.state.wz = step(bc);
+                self.state.b = self.state.b.wrapping_sub(1);
+
+                let k = u16::from(value) + u16::from(step(u16::from(self.state.c)) as u8);
+                self.set_block_io_flags(value, k);
+
+                self.state.b != 0
+            }
+            BlockOperation::Out => {
+                let value = self.read(hl);
+                self.state.b = self.state.b.wrapping_sub(1);
+                let bc = self.pair(RegisterPair::Bc);
+                self.port_write(bc, value);
+                self.state.wz = step(bc);
+
+                let k = u16::from(value) + u16::from(self.state.l);
+                self.set_block_io_flags(value, k);
+
+                self.state.b != 0
+            }
+        };
+
+        if repeat && repeating {
+            // Go back over the instruction to run it again
+            self.state.pc = self.state.pc.wrapping_sub(2);
+            self.state.wz = self.state.pc.wrapping_add(1);
+
+            true
+        } else {
+            false
+        }
+    }
+
+    fn set_block_io_flags(&mut self, value: u8, k: u16) {
+        let b = self.state.b;
+
+        self.set_sign_zero(b);
+        self.state.flags.subtract = value & 0x80 != 0;
+        self.state.flags.half_carry = k > 0xff;
+        self.state.flags.carry = k > 0xff;
+        self.state.flags.parity_overflow = parity((k as u8 & 0x07) ^ b);
+    }
+}
+
+fn parity(value: u8) -> bool {
+    value.count_ones().is_multiple_of(2)
+}
+
+fn port_is_c(instruction: &Instruction) -> bool {
+    matches!(instruction, Instruction::In { port: Port::C, .. })
+}
@@ -1,52 +1,756 @@
+use std::{
+    io::{Read, Write},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU8, Ordering},
+    },
+};
+
+use bitvec::{prelude::Lsb0, view::BitView};
+use decode::{decode_instruction, taken_cycles};
 use fluxemu_runtime::{
-    component::{Component, ComponentConfig},
-    machine::builder::ComponentBuilder,
+    component::{Component, ComponentConfig, ComponentVersion},
+    machine::builder::{ComponentBuilder, SchedulerParticipation},
+    memory::{Address, AddressSpace, AddressSpaceCache, AddressSpaceId, MemoryError},
     platform::Platform,
+    scheduler::{Frequency, Period, SynchronizationContext},
 };
+use serde::{Deserialize, Serialize};
 
-// mod decode;
-// mod instruction;
+mod decode;
+mod instruction;
+mod interpret;
+#[cfg(test)]
+mod tests;
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub const NMI_VECTOR: u16 = 0x0066;
+/// Where the LR35902 interrupt vectors start, 8 bytes apart in priority order
+pub const LR35902_INTERRUPT_VECTOR_BASE: u16 = 0x0040;
+/// LR35902 IF
+pub const LR35902_INTERRUPT_REQUEST_ADDRESS: Address = 0xff0f;
+/// LR35902 IE
+pub const LR35902_INTERRUPT_ENABLE_ADDRESS: Address = 0xffff;
+/// KEY1 on the Game Boy Color version of the LR35902
+pub const LR35902_SPEED_SWITCH_ADDRESS: Address = 0xff4d;
+/// RST 38h, which is what a floating data bus reads as during an interrupt
+/// acknowledge
+const DEFAULT_INTERRUPT_DATA: u8 = 0xff;
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize,
+)]
 pub enum Intel8080Kind {
     #[default]
     Intel8080,
     Zilog80,
+    /// The Game Boy processor, which drops the Z80 additions and the 8080 IO
+    /// space for its own
     SharpLr35902,
 }
 
+#[derive(Copy, Clone, PartialEq, Serialize, Deserialize, Debug, Default)]
+/// Stored as individual flags, laid out by [`FlagRegister::to_byte`] according to
+/// the processor kind
+pub struct FlagRegister {
+    sign: bool,
+    zero: bool,
+    /// Undocumented Z80 copy of bit 5 of a result
+    y: bool,
+    half_carry: bool,
+    /// Undocumented Z80 copy of bit 3 of a result
+    x: bool,
+    /// Overflow on the Z80 for arithmetic, parity otherwise
+    parity_overflow: bool,
+    subtract: bool,
+    carry: bool,
+}
+
+impl FlagRegister {
+    pub fn to_byte(&self, kind: Intel8080Kind) -> u8 {
+        let mut byte = 0;
+        let bits = byte.view_bits_mut::<Lsb0>();
+
+        match kind {
+            Intel8080Kind::Intel8080 => {
+                bits.set(7, self.sign);
+                bits.set(6, self.zero);
+                bits.set(4, self.half_carry);
+                bits.set(2, self.parity_overflow);
+                // Always set
+                bits.set(1, true);
+                bits.set(0, self.carry);
+            }
+            Intel8080Kind::Zilog80 => {
+                bits.set(7, self.sign);
+                bits.set(6, self.zero);
+                bits.set(5, self.y);
+                bits.set(4, self.half_carry);
+                bits.set(3, self.x);
+                bits.set(2, self.parity_overflow);
+                bits.set(1, self.subtract);
+                bits.set(0, self.carry);
+            }
+            Intel8080Kind::SharpLr35902 => {
+                bits.set(7, self.zero);
+                bits.set(6, self.subtract);
+                bits.set(5, self.half_carry);
+                bits.set(4, self.carry);
+            }
+        }
+
+        byte
+    }
+
+    pub fn from_byte(kind: Intel8080Kind, byte: u8) -> Self {
+        let bits = byte.view_bits::<Lsb0>();
+
+        match kind {
+            Intel8080Kind::Intel8080 | Intel8080Kind::Zilog80 => Self {
+                sign: bits[7],
+                zero: bits[6],
+                y: bits[5],
+                half_carry: bits[4],
+                x: bits[3],
+                parity_overflow: bits[2],
+                subtract: bits[1],
+                carry: bits[0],
+            },
+            Intel8080Kind::SharpLr35902 => Self {
+                zero: bits[7],
+                subtract: bits[6],
+                half_carry: bits[5],
+                carry: bits[4],
+                ..Default::default()
+            },
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+struct ProcessorState {
+    a: u8,
+    flags: FlagRegister,
+    b: u8,
+    c: u8,
+    d: u8,
+    e: u8,
+    h: u8,
+    l: u8,
+    sp: u16,
+    pc: u16,
+    ix: u16,
+    iy: u16,
+    /// AF', BC', DE' and HL'
+    shadow: [u16; 4],
+    /// Interrupt vector base
+    i: u8,
+    /// Refresh counter, where only the lower 7 bits count
+    r: u8,
+    /// Internal address latch, whose upper byte leaks into the flags of
+    /// BIT n,(HL)
+    wz: u16,
+    /// INTE on the 8080 and IME on the LR35902
+    iff1: bool,
+    iff2: bool,
+    interrupt_mode: u8,
+    /// Interrupts are not taken straight after EI
+    interrupt_delay: bool,
+    halted: bool,
+    /// LR35902 STOP, which waits for a button press
+    stopped: bool,
+    /// The LR35902 locks up on illegal opcodes
+    locked: bool,
+    /// LR35902 HALT with interrupts disabled but pending makes it read the
+    /// next byte twice
+    halt_bug: bool,
+    /// Cycles left until the current instruction is done
+    cycles_remaining: u32,
+}
+
+/// The maskable interrupt line, which is level triggered and active low
 #[derive(Debug)]
-pub struct Intel8080 {
-    config: Intel8080Config,
+pub struct IrqFlag {
+    level: AtomicBool,
+    /// What the interrupting device puts on the data bus when acknowledged
+    data: AtomicU8,
+}
+
+impl Default for IrqFlag {
+    fn default() -> Self {
+        Self {
+            level: AtomicBool::new(true),
+            data: AtomicU8::new(DEFAULT_INTERRUPT_DATA),
+        }
+    }
+}
+
+impl IrqFlag {
+    pub fn store(&self, irq: bool) {
+        self.level.store(irq, Ordering::Release);
+    }
+
+    /// Sets what is read during interrupt acknowledge, which is an
+    /// instruction in 8080 and Z80 IM 0, or the low byte of the vector in Z80
+    /// IM 2
+    pub fn set_data(&self, data: u8) {
+        self.data.store(data, Ordering::Release);
+    }
+
+    pub fn interrupt_required(&self) -> bool {
+        !self.level.load(Ordering::Acquire)
+    }
+
+    fn data(&self) -> u8 {
+        self.data.load(Ordering::Acquire)
+    }
+}
+
+/// NMI is falling edge
+#[derive(Debug)]
+pub struct NmiFlag {
+    current_state: AtomicBool,
+    falling_edge_occured: AtomicBool,
+}
+
+impl Default for NmiFlag {
+    fn default() -> Self {
+        Self {
+            current_state: AtomicBool::new(true),
+            falling_edge_occured: AtomicBool::new(false),
+        }
+    }
+}
+
+impl NmiFlag {
+    pub fn store(&self, nmi: bool) {
+        if self.current_state.swap(nmi, Ordering::AcqRel) && !nmi {
+            self.falling_edge_occured.store(true, Ordering::Release);
+        }
+    }
+
+    pub fn interrupt_required(&self) -> bool {
+        self.falling_edge_occured.swap(false, Ordering::AcqRel)
+    }
+}
+
+/// The LR35902 IE and IF registers, which live inside the processor
+#[derive(Debug, Default)]
+pub struct InterruptFlags {
+    enabled: AtomicU8,
+    requested: AtomicU8,
 }
 
-impl Component for Intel8080 {}
+impl InterruptFlags {
+    /// Sets a bit in IF, where lower bits have priority
+    pub fn request(&self, interrupt: u8) {
+        self.requested.fetch_or(1 << interrupt, Ordering::AcqRel);
+    }
+
+    fn pending(&self) -> u8 {
+        self.enabled.load(Ordering::Acquire) & self.requested.load(Ordering::Acquire) & 0x1f
+    }
 
-#[derive(Default, Debug)]
+    fn acknowledge(&self, interrupt: u8) {
+        self.requested
+            .fetch_and(!(1 << interrupt), Ordering::AcqRel);
+    }
+}
+
+/// The Game Boy Color double speed mode, which is armed through KEY1 and then
+/// switched into with STOP
+#[derive(Debug, Default)]
+pub struct SpeedSwitch {
+    armed: AtomicBool,
+    double_speed: AtomicBool,
+}
+
+impl SpeedSwitch {
+    pub fn double_speed(&self) -> bool {
+        self.double_speed.load(Ordering::Acquire)
+    }
+}
+
+#[derive(Debug)]
 pub struct Intel8080Config {
+    pub frequency: Frequency,
+    pub assigned_address_space: AddressSpaceId,
+    /// Where IN and OUT go, which the LR35902 does not have
+    pub io_address_space: Option<AddressSpaceId>,
     pub kind: Intel8080Kind,
+    /// If the LR35902 has KEY1, doubling `frequency` when switched
+    pub speed_switch: bool,
 }
 
 impl Intel8080Config {
-    pub fn lr35902() -> Self {
+    pub fn lr35902(frequency: Frequency, assigned_address_space: AddressSpaceId) -> Self {
         Self {
+            frequency,
+            assigned_address_space,
+            io_address_space: None,
             kind: Intel8080Kind::SharpLr35902,
+            speed_switch: false,
         }
     }
 
-    pub fn z80() -> Self {
+    pub fn z80(
+        frequency: Frequency,
+        assigned_address_space: AddressSpaceId,
+        io_address_space: AddressSpaceId,
+    ) -> Self {
         Self {
+            frequency,
+            assigned_address_space,
+            io_address_space: Some(io_address_space),
             kind: Intel8080Kind::Zilog80,
+            speed_switch: false,
         }
     }
 
-    pub fn i8080() -> Self {
+    pub fn i8080(
+        frequency: Frequency,
+        assigned_address_space: AddressSpaceId,
+        io_address_space: AddressSpaceId,
+    ) -> Self {
         Self {
+            frequency,
+            assigned_address_space,
+            io_address_space: Some(io_address_space),
             kind: Intel8080Kind::Intel8080,
+            speed_switch: false,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Intel8080 {
+    state: ProcessorState,
+    irq: Arc<IrqFlag>,
+    nmi: Arc<NmiFlag>,
+    interrupt_flags: Arc<InterruptFlags>,
+    speed_switch: Arc<SpeedSwitch>,
+    config: Intel8080Config,
+    address_space: Arc<AddressSpace>,
+    address_space_cache: AddressSpaceCache,
+    io_address_space: Option<(Arc<AddressSpace>, AddressSpaceCache)>,
+    timestamp: Period,
+    period: Period,
+}
+
+impl Intel8080 {
+    pub fn irq(&self) -> Arc<IrqFlag> {
+        self.irq.clone()
+    }
+
+    /// The Z80 NMI line
+    pub fn nmi(&self) -> Arc<NmiFlag> {
+        self.nmi.clone()
+    }
+
+    /// The LR35902 interrupt requests
+    pub fn interrupt_flags(&self) -> Arc<InterruptFlags> {
+        self.interrupt_flags.clone()
+    }
+
+    /// The LR35902 speed, which is only ever doubled if configured with
+    /// `speed_switch`
+    pub fn speed_switch(&self) -> Arc<SpeedSwitch> {
+        self.speed_switch.clone()
+    }
+
+    pub fn address_space(&self) -> AddressSpaceId {
+        self.config.assigned_address_space
+    }
+
+    fn kind(&self) -> Intel8080Kind {
+        self.config.kind
+    }
+
+    /// Runs whatever comes next, returning how many cycles it took
+    fn step(&mut self) -> u32 {
+        if let Some(cycles) = self.service_interrupts() {
+            return cycles;
+        }
+
+        if self.state.halted || self.state.stopped || self.state.locked {
+            // The Z80 keeps fetching NOPs while halted
+            if self.kind() == Intel8080Kind::Zilog80 {
+                self.increment_refresh(1);
+            }
+
+            return 4;
+        }
+
+        self.state.interrupt_delay = false;
+
+        let kind = self.kind();
+        let mut halt_bug = std::mem::take(&mut self.state.halt_bug);
+
+        let decoded = decode_instruction(kind, || {
+            let byte = self.read(self.state.pc);
+
+            // The program counter fails to advance past the first byte
+            if !std::mem::take(&mut halt_bug) {
+                self.state.pc = self.state.pc.wrapping_add(1);
+            }
+
+            byte
+        });
+
+        if kind == Intel8080Kind::Zilog80 {
+            self.increment_refresh(decoded.fetches);
+        }
+
+        let mut cycles = u32::from(decoded.cycles);
+
+        if self.interpret_instruction(decoded.instruction) {
+            cycles += u32::from(taken_cycles(kind, &decoded.instruction));
+        }
+
+        cycles
+    }
+
+    /// Checks for interrupts at an instruction boundary, returning the cycles
+    /// taken to respond to one
+    fn service_interrupts(&mut self) -> Option<u32> {
+        match self.kind() {
+            Intel8080Kind::Intel8080 => {
+                if !self.irq.interrupt_required() || !self.state.iff1 || self.state.interrupt_delay
+                {
+                    return None;
+                }
+
+                self.state.iff1 = false;
+                self.state.halted = false;
+                self.restart_from_data_bus();
+
+                Some(11)
+            }
+            Intel8080Kind::Zilog80 => {
+                if self.nmi.interrupt_required() {
+                    self.state.halted = false;
+                    self.state.iff1 = false;
+                    self.increment_refresh(1);
+                    self.push(self.state.pc);
+                    self.state.pc = NMI_VECTOR;
+                    self.state.wz = NMI_VECTOR;
+
+                    return Some(11);
+                }
+
+                if !self.irq.interrupt_required() || !self.state.iff1 || self.state.interrupt_delay
+                {
+                    return None;
+                }
+
+                self.state.halted = false;
+                self.state.iff1 = false;
+                self.state.iff2 = false;
+                self.increment_refresh(1);
+
+                match self.state.interrupt_mode {
+                    2 => {
+                        let vector = u16::from_le_bytes([self.irq.data(), self.state.i]);
+
+                        self.push(self.state.pc);
+                        self.state.pc = self.read_word(vector);
+                        self.state.wz = self.state.pc;
+
+                        Some(19)
+                    }
+                    1 => {
+                        self.push(self.state.pc);
+                        self.state.pc = 0x0038;
+                        self.state.wz = self.state.pc;
+
+                        Some(13)
+                    }
+                    _ => {
+                        self.restart_from_data_bus();
+
+                        Some(13)
+                    }
+                }
+            }
+            Intel8080Kind::SharpLr35902 => {
+                let pending = self.interrupt_flags.pending();
+
+                if pending == 0 {
+                    return None;
+                }
+
+                // Waking up happens even if the interrupt is not taken
+                self.state.halted = false;
+                self.state.stopped = false;
+
+                if !self.state.iff1 || self.state.interrupt_delay {
+                    return None;
+                }
+
+                let interrupt = pending.trailing_zeros() as u8;
+
+                self.state.iff1 = false;
+                self.interrupt_flags.acknowledge(interrupt);
+                self.push(self.state.pc);
+                self.state.pc = LR35902_INTERRUPT_VECTOR_BASE + u16::from(interrupt) * 8;
+
+                Some(20)
+            }
+        }
+    }
+
+    /// Responds to an interrupt with whatever RST is on the data bus
+    ///
+    /// FIXME: Only RST is supported as the acknowledged instruction
+    fn restart_from_data_bus(&mut self) {
+        let data = self.irq.data();
+
+        self.push(self.state.pc);
+        self.state.pc = u16::from(data & 0b0011_1000);
+        self.state.wz = self.state.pc;
+    }
+
+    /// Switches speed if KEY1 was armed, returning if it did
+    fn switch_speed(&mut self) -> bool {
+        if !self.speed_switch.armed.swap(false, Ordering::AcqRel) {
+            return false;
+        }
+
+        let double_speed = !self.speed_switch.double_speed.load(Ordering::Acquire);
+        self.speed_switch
+            .double_speed
+            .store(double_speed, Ordering::Release);
+        self.update_period();
+
+        true
+    }
+
+    fn update_period(&mut self) {
+        self.period = if self.speed_switch.double_speed() {
+            self.config.frequency.recip() / 2
+        } else {
+            self.config.frequency.recip()
+        };
+    }
+
+    fn increment_refresh(&mut self, amount: u8) {
+        self.state.r = (self.state.r & 0x80) | (self.state.r.wrapping_add(amount) & 0x7f);
+    }
+
+    fn read(&mut self, address: u16) -> u8 {
+        // The processor can't go through the bus to reach itself
+        if self.kind() == Intel8080Kind::SharpLr35902
+            && let Some(value) = self.read_register(address as Address)
+        {
+            return value;
+        }
+
+        self.address_space
+            .read_le_value(
+                address as Address,
+                self.timestamp,
+                Some(&mut self.address_space_cache),
+            )
+            .unwrap_or(0xff)
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        if self.kind() == Intel8080Kind::SharpLr35902
+            && self.write_register(address as Address, value)
+        {
+            return;
+        }
+
+        let _ = self.address_space.write_le_value(
+            address as Address,
+            self.timestamp,
+            Some(&mut self.address_space_cache),
+            value,
+        );
+    }
+
+    fn read_word(&mut self, address: u16) -> u16 {
+        u16::from_le_bytes([self.read(address), self.read(address.wrapping_add(1))])
+    }
+
+    fn write_word(&mut self, address: u16, value: u16) {
+        let [low, high] = value.to_le_bytes();
+
+        self.write(address, low);
+        self.write(address.wrapping_add(1), high);
+    }
+
+    fn push(&mut self, value: u16) {
+        let [low, high] = value.to_le_bytes();
+
+        self.state.sp = self.state.sp.wrapping_sub(1);
+        self.write(self.state.sp, high);
+        self.state.sp = self.state.sp.wrapping_sub(1);
+        self.write(self.state.sp, low);
+    }
+
+    fn pop(&mut self) -> u16 {
+        let low = self.read(self.state.sp);
+        self.state.sp = self.state.sp.wrapping_add(1);
+        let high = self.read(self.state.sp);
+        self.state.sp = self.state.sp.wrapping_add(1);
+
+        u16::from_le_bytes([low, high])
+    }
+
+    fn port_read(&mut self, port: u16) -> u8 {
+        match &mut self.io_address_space {
+            Some((address_space, cache)) => address_space
+                .read_le_value(port as Address, self.timestamp, Some(cache))
+                .unwrap_or(0xff),
+            None => 0xff,
+        }
+    }
+
+    fn port_write(&mut self, port: u16, value: u8) {
+        if let Some((address_space, cache)) = &mut self.io_address_space {
+            let _ =
+                address_space.write_le_value(port as Address, self.timestamp, Some(cache), value);
+        }
+    }
+
+    fn read_register(&self, address: Address) -> Option<u8> {
+        match address {
+            // The unused bits read as set
+            LR35902_INTERRUPT_REQUEST_ADDRESS => {
+                Some(self.interrupt_flags.requested.load(Ordering::Acquire) | 0xe0)
+            }
+            LR35902_INTERRUPT_ENABLE_ADDRESS => {
+                Some(self.interrupt_flags.enabled.load(Ordering::Acquire))
+            }
+            LR35902_SPEED_SWITCH_ADDRESS if self.config.speed_switch => Some(
+                (u8::from(self.speed_switch.double_speed()) << 7)
+                    | 0x7e
+                    | u8::from(self.speed_switch.armed.load(Ordering::Acquire)),
+            ),
+            _ => None,
+        }
+    }
+
+    fn write_register(&self, address: Address, value: u8) -> bool {
+        match address {
+            LR35902_INTERRUPT_REQUEST_ADDRESS => {
+                self.interrupt_flags
+                    .requested
+                    .store(value & 0x1f, Ordering::Release);
+
+                true
+            }
+            LR35902_INTERRUPT_ENABLE_ADDRESS => {
+                self.interrupt_flags.enabled.store(value, Ordering::Release);
+
+                true
+            }
+            LR35902_SPEED_SWITCH_ADDRESS if self.config.speed_switch => {
+                self.speed_switch
+                    .armed
+                    .store(value & 0x01 != 0, Ordering::Release);
+
+                true
+            }
+            _ => false,
         }
     }
 }
 
+impl Component for Intel8080 {
+    fn snapshot_version(&self) -> Option<ComponentVersion> {
+        Some(0)
+    }
+
+    fn store_snapshot(&self, mut writer: Box<dyn Write>) -> Result<(), Box<dyn std::error::Error>> {
+        rmp_serde::encode::write_named(
+            &mut writer,
+            &Snapshot {
+                state: self.state.clone(),
+                interrupt_enable: self.interrupt_flags.enabled.load(Ordering::Acquire),
+                interrupt_request: self.interrupt_flags.requested.load(Ordering::Acquire),
+                double_speed: self.speed_switch.double_speed(),
+                speed_switch_armed: self.speed_switch.armed.load(Ordering::Acquire),
+            },
+        )?;
+
+        Ok(())
+    }
+
+    fn load_snapshot(
+        &mut self,
+        version: ComponentVersion,
+        reader: Box<dyn Read>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        match version {
+            0 => {
+                let snapshot: Snapshot = rmp_serde::decode::from_read(reader)?;
+
+                self.state = snapshot.state;
+                self.interrupt_flags
+                    .enabled
+                    .store(snapshot.interrupt_enable, Ordering::Release);
+                self.interrupt_flags
+                    .requested
+                    .store(snapshot.interrupt_request, Ordering::Release);
+                self.speed_switch
+                    .double_speed
+                    .store(snapshot.double_speed, Ordering::Release);
+                self.speed_switch
+                    .armed
+                    .store(snapshot.speed_switch_armed, Ordering::Release);
+                self.update_period();
+
+                Ok(())
+            }
+            other => Err(format!("Unsupported snapshot version: {other}").into()),
+        }
+    }
+
+    fn memory_read(
+        &self,
+        address: Address,
+        _address_space: AddressSpaceId,
+        _avoid_side_effects: bool,
+        buffer: &mut [u8],
+    ) -> Result<(), MemoryError> {
+        buffer[0] = self.read_register(address).unwrap();
+
+        Ok(())
+    }
+
+    fn memory_write(
+        &mut self,
+        address: Address,
+        _address_space: AddressSpaceId,
+        buffer: &[u8],
+    ) -> Result<(), MemoryError> {
+        self.write_register(address, buffer[0]);
+
+        Ok(())
+    }
+
+    /// Instructions do all of their work on their first cycle, and the rest
+    /// are waited out
+    fn synchronize(&mut self, mut context: SynchronizationContext) {
+        for now in context.allocate(self.period, None) {
+            self.timestamp = now;
+
+            if self.state.cycles_remaining != 0 {
+                self.state.cycles_remaining -= 1;
+
+                continue;
+            }
+
+            self.state.cycles_remaining = self.step() - 1;
+        }
+    }
+
+    fn needs_work(&self, delta: Period) -> bool {
+        delta >= self.period
+    }
+}
+
 impl<P: Platform> ComponentConfig<P> for Intel8080Config {
     type Component = Intel8080;
 
@@ -54,6 +758,64 @@ impl<P: Platform> ComponentConfig<P> for Intel8080Config {
         self,
         component_builder: ComponentBuilder<'_, P, Self::Component>,
     ) -> Result<Self::Component, Box<dyn std::error::Error>> {
-        todo!()
+        let address_space = component_builder
+            .get_address_space(self.assigned_address_space)
+            .clone();
+
+        let io_address_space = self.io_address_space.map(|io_address_space| {
+            let io_address_space = component_builder
+                .get_address_space(io_address_space)
+                .clone();
+            let cache = io_address_space.cache();
+
+            (io_address_space, cache)
+        });
+
+        let mut component_builder =
+            component_builder.set_scheduler_participation(SchedulerParticipation::SchedulerDriven);
+
+        if self.kind == Intel8080Kind::SharpLr35902 {
+            let speed_switch = self.speed_switch.then_some(LR35902_SPEED_SWITCH_ADDRESS);
+
+            for address in [
+                LR35902_INTERRUPT_REQUEST_ADDRESS,
+                LR35902_INTERRUPT_ENABLE_ADDRESS,
+            ]
+            .into_iter()
+            .chain(speed_switch)
+            {
+                component_builder = component_builder
+                    .memory_map_component(self.assigned_address_space, address..=address);
+            }
+        }
+
+        let state = ProcessorState {
+            // What the boot ROMs of the LR35902 leave behind is up to the machine
+            sp: 0xffff,
+            ..Default::default()
+        };
+
+        Ok(Intel8080 {
+            state,
+            irq: Arc::default(),
+            nmi: Arc::default(),
+            interrupt_flags: Arc::default(),
+            speed_switch: Arc::default(),
+            address_space_cache: address_space.cache(),
+            address_space,
+            io_address_space,
+            period: self.frequency.recip(),
+            config: self,
+            timestamp: Period::default(),
+        })
     }
 }
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Snapshot {
+    state: ProcessorState,
+    interrupt_enable: u8,
+    interrupt_request: u8,
+    double_speed: bool,
+    speed_switch_armed: bool,
+}
@@ -0,0 +1,181 @@
+use std::sync::{Arc, atomic::Ordering};
+
+use fluxemu_runtime::{machine::Machine, path::FluxEmuPath, scheduler::Period};
+
+use crate::{
+    FlagRegister, Intel8080, Intel8080Kind, LR35902_INTERRUPT_VECTOR_BASE,
+    tests::kind_test_boilerplate,
+};
+
+/// Loads `program` at the start of memory and runs it for `cycles`
+fn run_program(kind: Intel8080Kind, program: &[u8], cycles: u32) -> (Arc<Machine>, FluxEmuPath) {
+    let (machine, cpu, address_space, _) = kind_test_boilerplate(kind);
+    let address_space = machine.address_spaces(address_space).unwrap();
+
+    address_space
+        .write(0x0000, machine.now(), None, program)
+        .unwrap();
+
+    machine.run(Period::from_num(cycles));
+
+    (machine, cpu)
+}
+
+fn assert_finished(machine: &Machine, cpu: &FluxEmuPath, pc: u16) {
+    machine
+        .interact::<Intel8080, _>(cpu, |component| {
+            assert_eq!(component.state.pc, pc);
+            assert_eq!(component.state.cycles_remaining, 0);
+        })
+        .unwrap();
+}
+
+#[test]
+fn flag_layouts() {
+    let flags = FlagRegister {
+        sign: true,
+        zero: false,
+        y: true,
+        half_carry: true,
+        x: false,
+        parity_overflow: true,
+        subtract: true,
+        carry: true,
+    };
+
+    assert_eq!(flags.to_byte(Intel8080Kind::Intel8080), 0b1001_0111);
+    assert_eq!(flags.to_byte(Intel8080Kind::Zilog80), 0b1011_0111);
+    assert_eq!(flags.to_byte(Intel8080Kind::SharpLr35902), 0b0111_0000);
+}
+
+#[test]
+fn add_overflow_and_parity() {
+    // LD A,0x7f; ADD A,0x01
+    let program = [0x3e, 0x7f, 0xc6, 0x01];
+
+    for (kind, cycles, parity_overflow) in [
+        (Intel8080Kind::Intel8080, 14, false),
+        (Intel8080Kind::Zilog80, 14, true),
+    ] {
+        let (machine, cpu) = run_program(kind, &program, cycles);
+
+        assert_finished(&machine, &cpu, 0x0004);
+        machine
+            .interact::<Intel8080, _>(&cpu, |component| {
+                let flags = component.state.flags;
+
+                assert_eq!(component.state.a, 0x80);
+                assert!(flags.sign);
+                assert!(flags.half_carry);
+                assert!(!flags.carry);
+                assert_eq!(flags.parity_overflow, parity_overflow);
+            })
+            .unwrap();
+    }
+}
+
+#[test]
+fn daa_after_subtraction() {
+    // LD A,0x42; SUB 0x15; DAA
+    let program = [0x3e, 0x42, 0xd6, 0x15, 0x27];
+
+    for (kind, cycles) in [
+        (Intel8080Kind::Zilog80, 18),
+        (Intel8080Kind::SharpLr35902, 20),
+    ] {
+        let (machine, cpu) = run_program(kind, &program, cycles);
+
+        assert_finished(&machine, &cpu, 0x0005);
+        machine
+            .interact::<Intel8080, _>(&cpu, |component| {
+                assert_eq!(component.state.a, 0x27);
+                assert!(!component.state.flags.carry);
+            })
+            .unwrap();
+    }
+}
+
+#[test]
+fn djnz_loop() {
+    // LD B,3; loop: INC A; DJNZ loop
+    let program = [0x06, 0x03, 0x3c, 0x10, 0xfd];
+
+    // Two taken branches and one that falls through
+    let (machine, cpu) = run_program(Intel8080Kind::Zilog80, &program, 7 + 3 * 4 + 2 * 13 + 8);
+
+    assert_finished(&machine, &cpu, 0x0005);
+    machine
+        .interact::<Intel8080, _>(&cpu, |component| {
+            assert_eq!(component.state.a, 3);
+            assert_eq!(component.state.b, 0);
+        })
+        .unwrap();
+}
+
+#[test]
+fn ldir() {
+    // LD HL,0x0100; LD DE,0x0200; LD BC,3; LDIR
+    let program = [
+        0x21, 0x00, 0x01, 0x11, 0x00, 0x02, 0x01, 0x03, 0x00, 0xed, 0xb0,
+    ];
+
+    let (machine, cpu, address_space, _) = kind_test_boilerplate(Intel8080Kind::Zilog80);
+    let address_space = machine.address_spaces(address_space).unwrap();
+
+    address_space
+        .write(0x0000, machine.now(), None, &program)
+        .unwrap();
+    address_space
+        .write(0x0100, machine.now(), None, &[1, 2, 3])
+        .unwrap();
+
+    machine.run(Period::from_num(3 * 10 + 2 * 21 + 16));
+
+    assert_finished(&machine, &cpu, 0x000b);
+
+    let mut copied = [0; 3];
+    address_space
+        .read(0x0200, machine.now(), None, &mut copied)
+        .unwrap();
+
+    assert_eq!(copied, [1, 2, 3]);
+}
+
+#[test]
+fn lr35902_interrupt_dispatch() {
+    // EI; HALT
+    let (machine, cpu, address_space, _) = kind_test_boilerplate(Intel8080Kind::SharpLr35902);
+    let address_space = machine.address_spaces(address_space).unwrap();
+
+    address_space
+        .write(0x0000, machine.now(), None, &[0xfb, 0x76])
+        .unwrap();
+    // Enable the timer interrupt through IE
+    address_space
+        .write(0xffff, machine.now(), None, &[0b0000_0100])
+        .unwrap();
+
+    machine.run(Period::from_num(4 + 4 + 4));
+
+    machine
+        .interact::<Intel8080, _>(&cpu, |component| {
+            assert!(component.state.halted);
+
+            component.interrupt_flags().request(2);
+        })
+        .unwrap();
+
+    machine.run(Period::from_num(20));
+
+    assert_finished(&machine, &cpu, LR35902_INTERRUPT_VECTOR_BASE + 2 * 8);
+    machine
+        .interact::<Intel8080, _>(&cpu, |component| {
+            assert!(!component.state.halted);
+            assert!(!component.state.iff1);
+            assert_eq!(
+                component.interrupt_flags.requested.load(Ordering::Acquire),
+                0
+            );
+        })
+        .unwrap();
+}
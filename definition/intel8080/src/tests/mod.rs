@@ -0,0 +1,56 @@
+use std::sync::Arc;
+
+use fluxemu_definition_misc::memory::standard::{
+    StandardMemoryConfig, StandardMemoryInitialContents,
+};
+use fluxemu_runtime::{
+    machine::Machine, memory::AddressSpaceId, path::FluxEmuPath, scheduler::Frequency,
+};
+use rangemap::RangeInclusiveMap;
+
+use crate::{Intel8080Config, Intel8080Kind};
+
+mod instructions;
+mod single_step;
+
+fn memory(assigned_address_space: AddressSpaceId) -> StandardMemoryConfig {
+    StandardMemoryConfig {
+        readable: true,
+        writable: true,
+        assigned_range: 0x0000..=0xffff,
+        assigned_address_space,
+        initial_contents: RangeInclusiveMap::from_iter([(
+            0x0000..=0xffff,
+            StandardMemoryInitialContents::Value(0),
+        )]),
+        sram: false,
+    }
+}
+
+/// A processor of `kind` on a flat 64K of memory and 64K of ports, clocked
+/// once a second
+///
+/// Returns the memory address space, and then the IO one
+pub(crate) fn kind_test_boilerplate(
+    kind: Intel8080Kind,
+) -> (Arc<Machine>, FluxEmuPath, AddressSpaceId, AddressSpaceId) {
+    let (machine, cpu_address_space) = Machine::build_test_minimal().insert_address_space(16);
+    let (machine, io_address_space) = machine.insert_address_space(16);
+
+    // Memory goes first so the LR35902 can map its interrupt registers over it
+    let (machine, _) = machine.insert_component("memory", memory(cpu_address_space));
+    let (machine, _) = machine.insert_component("ports", memory(io_address_space));
+
+    let (machine, cpu) = machine.insert_component(
+        "intel8080",
+        Intel8080Config {
+            frequency: Frequency::ONE,
+            assigned_address_space: cpu_address_space,
+            io_address_space: (kind != Intel8080Kind::SharpLr35902).then_some(io_address_space),
+            kind,
+            speed_switch: false,
+        },
+    );
+
+    (machine.build(()), cpu, cpu_address_space, io_address_space)
+}
@@ -0,0 +1,319 @@
+//! Runs the per opcode single step JSON test vectors against the Z80 and the
+//! LR35902
+//!
+//! Point `FLUXEMU_8080_SINGLE_STEP_TESTS` at a local copy of the vectors, laid
+//! out as they are upstream (`z80/v1/dd cb __ 40.json`, `sm83/v1/cb 00.json`,
+//! ...). Kinds whose vectors cannot be found are skipped
+
+use std::{
+    env, fs,
+    panic::{AssertUnwindSafe, catch_unwind},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use fluxemu_runtime::{
+    machine::Machine, memory::AddressSpaceId, path::FluxEmuPath, scheduler::Period,
+};
+use serde::{Deserialize, de::IgnoredAny};
+
+use crate::{FlagRegister, Intel8080, Intel8080Kind, tests::kind_test_boilerplate};
+
+const TEST_VECTOR_DIRECTORY_VARIABLE: &str = "FLUXEMU_8080_SINGLE_STEP_TESTS";
+
+/// Registers the Z80 vectors have that the LR35902 ones do not are optional
+#[derive(Debug, Deserialize)]
+struct ProcessorState {
+    pc: u16,
+    sp: u16,
+    a: u8,
+    b: u8,
+    c: u8,
+    d: u8,
+    e: u8,
+    f: u8,
+    h: u8,
+    l: u8,
+    i: Option<u8>,
+    r: Option<u8>,
+    ix: Option<u16>,
+    iy: Option<u16>,
+    af_: Option<u16>,
+    bc_: Option<u16>,
+    de_: Option<u16>,
+    hl_: Option<u16>,
+    wz: Option<u16>,
+    im: Option<u8>,
+    iff1: Option<u8>,
+    iff2: Option<u8>,
+    /// What the LR35902 calls IFF1
+    ime: Option<u8>,
+    ram: Vec<(u16, u8)>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TestVector {
+    name: String,
+    initial: ProcessorState,
+    #[serde(rename = "final")]
+    expected: ProcessorState,
+    /// Bus activity for every cycle, only the amount of cycles is checked
+    cycles: Vec<IgnoredAny>,
+    /// Port accesses as address, value and direction
+    #[serde(default)]
+    ports: Vec<(u16, u8, String)>,
+}
+
+#[derive(Debug)]
+struct FileReport {
+    kind: Intel8080Kind,
+    name: String,
+    failed: usize,
+    total: usize,
+    first_failure: String,
+}
+
+fn vector_directory(kind: Intel8080Kind) -> Option<PathBuf> {
+    let directory = env::var_os(TEST_VECTOR_DIRECTORY_VARIABLE)?;
+
+    let subdirectory = match kind {
+        Intel8080Kind::Intel8080 => return None,
+        Intel8080Kind::Zilog80 => "z80",
+        Intel8080Kind::SharpLr35902 => "sm83",
+    };
+
+    let path = Path::new(&directory).join(subdirectory).join("v1");
+
+    path.is_dir().then_some(path)
+}
+
+/// The LR35902 vectors count machine cycles rather than clock cycles
+fn clock_cycles(kind: Intel8080Kind, vector: &TestVector) -> usize {
+    match kind {
+        Intel8080Kind::SharpLr35902 => vector.cycles.len() * 4,
+        _ => vector.cycles.len(),
+    }
+}
+
+/// Runs a single vector, returning what went wrong if anything
+///
+/// Only the memory the vector mentions is set up, so machines can be reused
+/// between vectors
+fn run_vector(
+    kind: Intel8080Kind,
+    (machine, cpu, address_space, io_address_space): &(
+        Arc<Machine>,
+        FluxEmuPath,
+        AddressSpaceId,
+        AddressSpaceId,
+    ),
+    vector: &TestVector,
+) -> Result<(), String> {
+    let address_space = machine.address_spaces(*address_space).unwrap();
+    let io_address_space = machine.address_spaces(*io_address_space).unwrap();
+
+    for (address, value) in &vector.initial.ram {
+        address_space
+            .write(*address as usize, machine.now(), None, &[*value])
+            .unwrap();
+    }
+
+    for (port, value, direction) in &vector.ports {
+        if direction == "r" {
+            io_address_space
+                .write(*port as usize, machine.now(), None, &[*value])
+                .unwrap();
+        }
+    }
+
+    machine
+        .interact_mut::<Intel8080, _>(cpu, |component| {
+            let initial = &vector.initial;
+            let state = &mut component.state;
+
+            state.pc = initial.pc;
+            state.sp = initial.sp;
+            state.a = initial.a;
+            state.flags = FlagRegister::from_byte(kind, initial.f);
+            state.b = initial.b;
+            state.c = initial.c;
+            state.d = initial.d;
+            state.e = initial.e;
+            state.h = initial.h;
+            state.l = initial.l;
+            state.i = initial.i.unwrap_or_default();
+            state.r = initial.r.unwrap_or_default();
+            state.ix = initial.ix.unwrap_or_default();
+            state.iy = initial.iy.unwrap_or_default();
+            state.shadow =
+                [initial.af_, initial.bc_, initial.de_, initial.hl_].map(Option::unwrap_or_default);
+            state.wz = initial.wz.unwrap_or_default();
+            state.interrupt_mode = initial.im.unwrap_or_default();
+            state.iff1 = initial.iff1.or(initial.ime).unwrap_or_default() != 0;
+            state.iff2 = initial.iff2.unwrap_or_default() != 0;
+            state.interrupt_delay = false;
+            state.halted = false;
+            state.stopped = false;
+            state.locked = false;
+            state.halt_bug = false;
+            state.cycles_remaining = 0;
+        })
+        .unwrap();
+
+    let cycles = clock_cycles(kind, vector);
+    machine.run(Period::from_num(cycles));
+
+    let mut mismatches = Vec::default();
+
+    machine
+        .interact::<Intel8080, _>(cpu, |component| {
+            let expected = &vector.expected;
+            let state = &component.state;
+
+            for (register, actual, expected) in [
+                ("a", Some(state.a), Some(expected.a)),
+                ("f", Some(state.flags.to_byte(kind)), Some(expected.f)),
+                ("b", Some(state.b), Some(expected.b)),
+                ("c", Some(state.c), Some(expected.c)),
+                ("d", Some(state.d), Some(expected.d)),
+                ("e", Some(state.e), Some(expected.e)),
+                ("h", Some(state.h), Some(expected.h)),
+                ("l", Some(state.l), Some(expected.l)),
+                ("i", Some(state.i), expected.i),
+                ("r", Some(state.r), expected.r),
+            ] {
+                if expected.is_some() && actual != expected {
+                    mismatches.push(format!(
+                        "{register}: expected {:#04x}, got {:#04x}",
+                        expected.unwrap(),
+                        actual.unwrap()
+                    ));
+                }
+            }
+
+            for (register, actual, expected) in [
+                ("pc", Some(state.pc), Some(expected.pc)),
+                ("sp", Some(state.sp), Some(expected.sp)),
+                ("ix", Some(state.ix), expected.ix),
+                ("iy", Some(state.iy), expected.iy),
+                ("af'", Some(state.shadow[0]), expected.af_),
+                ("bc'", Some(state.shadow[1]), expected.bc_),
+                ("de'", Some(state.shadow[2]), expected.de_),
+                ("hl'", Some(state.shadow[3]), expected.hl_),
+                ("wz", Some(state.wz), expected.wz),
+            ] {
+                if expected.is_some() && actual != expected {
+                    mismatches.push(format!(
+                        "{register}: expected {:#06x}, got {:#06x}",
+                        expected.unwrap(),
+                        actual.unwrap()
+                    ));
+                }
+            }
+
+            // Anything else means the instruction took a different amount of cycles
+            if state.cycles_remaining != 0 {
+                mismatches.push(format!(
+                    "did not finish in {cycles} cycles, {} remaining",
+                    state.cycles_remaining
+                ));
+            }
+        })
+        .unwrap();
+
+    for (address, expected) in &vector.expected.ram {
+        let mut actual = [0];
+        address_space
+            .read(*address as usize, machine.now(), None, &mut actual)
+            .unwrap();
+
+        if actual[0] != *expected {
+            mismatches.push(format!(
+                "ram[{address:#06x}]: expected {expected:#04x}, got {:#04x}",
+                actual[0]
+            ));
+        }
+    }
+
+    if mismatches.is_empty() {
+        Ok(())
+    } else {
+        Err(mismatches.join(", "))
+    }
+}
+
+fn run_file(kind: Intel8080Kind, path: &Path) -> Option<FileReport> {
+    let vectors: Vec<TestVector> = serde_json::from_slice(&fs::read(path).unwrap()).unwrap();
+
+    let mut failed = 0;
+    let mut first_failure = None;
+    let mut machine = kind_test_boilerplate(kind);
+
+    for vector in &vectors {
+        let result = catch_unwind(AssertUnwindSafe(|| run_vector(kind, &machine, vector)))
+            .unwrap_or_else(|_| {
+                // Whatever state the machine was left in can't be trusted anymore
+                machine = kind_test_boilerplate(kind);
+
+                Err("panicked".to_string())
+            });
+
+        if let Err(mismatch) = result {
+            failed += 1;
+            first_failure.get_or_insert_with(|| format!("\"{}\": {}", vector.name, mismatch));
+        }
+    }
+
+    first_failure.map(|first_failure| FileReport {
+        kind,
+        name: path.file_stem().unwrap().to_string_lossy().into_owned(),
+        failed,
+        total: vectors.len(),
+        first_failure,
+    })
+}
+
+#[test]
+fn single_step_tests() {
+    let mut reports = Vec::default();
+
+    for kind in [Intel8080Kind::Zilog80, Intel8080Kind::SharpLr35902] {
+        let Some(directory) = vector_directory(kind) else {
+            eprintln!("No single step test vectors found for {kind:?}, skipping");
+            continue;
+        };
+
+        // Prefixed opcodes are named after all of their bytes, so every file
+        // in the directory is run
+        let mut paths: Vec<_> = fs::read_dir(directory)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|extension| extension == "json")
+            })
+            .collect();
+        paths.sort();
+
+        for path in paths {
+            reports.extend(run_file(kind, &path));
+        }
+    }
+
+    if reports.is_empty() {
+        return;
+    }
+
+    let report = reports
+        .iter()
+        .map(|report| {
+            format!(
+                "{:?} {}: {}/{} failed, first was {}",
+                report.kind, report.name, report.failed, report.total, report.first_failure
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    panic!("{} opcodes had mismatches:\n{}", reports.len(), report);
+}
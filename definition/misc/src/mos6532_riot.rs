@@ -22,6 +22,22 @@ use crate::memory::standard::{StandardMemoryConfig, StandardMemoryInitialContent
 #[serde_as]
 #[derive(Serialize, Deserialize)]
 pub struct Snapshot {
+    swacnt: u8,
+    swbcnt: u8,
+    swcha_output: u8,
+    swchb_output: u8,
+    intim: u8,
+    instat: u8,
+    tim1t: Wrapping<u8>,
+    tim8t: Counter,
+    tim64t: Counter,
+    t1024t: Counter,
+}
+
+/// Snapshot from before the ports had per line data direction
+#[serde_as]
+#[derive(Serialize, Deserialize)]
+struct SnapshotV0 {
     swacnt: bool,
     swbcnt: bool,
     intim: u8,
@@ -52,8 +68,14 @@ struct Counter {
 pub struct Mos6532Riot {
     swcha: Option<Box<dyn SwchaCallback>>,
     swchb: Option<Box<dyn SwchbCallback>>,
-    swacnt: bool,
-    swbcnt: bool,
+    /// Data direction of port A, set bits are outputs
+    swacnt: u8,
+    /// Data direction of port B, set bits are outputs
+    swbcnt: u8,
+    /// Value port A drives onto the lines set as outputs
+    swcha_output: u8,
+    /// Value port B drives onto the lines set as outputs
+    swchb_output: u8,
     intim: u8,
     instat: u8,
     tim1t: Wrapping<u8>,
@@ -73,17 +95,33 @@ impl Mos6532Riot {
     pub fn install_swchb(&mut self, callback: impl SwchbCallback) {
         self.swchb = Some(Box::new(callback));
     }
+
+    /// Tells whatever is on port A what the lines set as outputs are driven to,
+    /// with the lines set as inputs pulled high
+    fn drive_port_a(&self) {
+        if let Some(handler) = &self.swcha {
+            handler.write_register(self.swcha_output | !self.swacnt);
+        }
+    }
+
+    fn drive_port_b(&self) {
+        if let Some(handler) = &self.swchb {
+            handler.write_register(self.swchb_output | !self.swbcnt);
+        }
+    }
 }
 
 impl Component for Mos6532Riot {
     fn snapshot_version(&self) -> Option<ComponentVersion> {
-        Some(0)
+        Some(1)
     }
 
     fn store_snapshot(&self, mut writer: Box<dyn Write>) -> Result<(), Box<dyn std::error::Error>> {
         let snapshot = Snapshot {
             swacnt: self.swacnt,
             swbcnt: self.swbcnt,
+            swcha_output: self.swcha_output,
+            swchb_output: self.swchb_output,
             intim: self.intim,
             instat: self.instat,
             tim1t: self.tim1t,
@@ -104,12 +142,31 @@ impl Component for Mos6532Riot {
     ) -> Result<(), Box<dyn std::error::Error>> {
         match version {
             0 => {
+                let snapshot: SnapshotV0 = rmp_serde::decode::from_read(reader)?;
+
+                // The old flags covered the whole port
+                self.swacnt = if snapshot.swacnt { 0xff } else { 0x00 };
+                self.swbcnt = if snapshot.swbcnt { 0xff } else { 0x00 };
+                self.swcha_output = 0xff;
+                self.swchb_output = 0xff;
+                self.intim = snapshot.intim;
+                self.instat = snapshot.instat;
+                self.tim1t = snapshot.tim1t;
+                self.tim8t = snapshot.tim8t;
+                self.tim64t = snapshot.tim64t;
+                self.t1024t = snapshot.t1024t;
+
+                Ok(())
+            }
+            1 => {
                 // Decode the snapshot from the file
                 let snapshot: Snapshot = rmp_serde::decode::from_read(reader)?;
 
                 // Restore state into atomics
                 self.swacnt = snapshot.swacnt;
                 self.swbcnt = snapshot.swbcnt;
+                self.swcha_output = snapshot.swcha_output;
+                self.swchb_output = snapshot.swchb_output;
                 self.intim = snapshot.intim;
                 self.instat = snapshot.instat;
                 self.tim1t = snapshot.tim1t;
@@ -145,23 +202,27 @@ impl Component for Mos6532Riot {
                         .collect(),
                     ));
                 }
-                0x0 if self.swacnt => {
-                    *buffer_section = self
+                0x0 => {
+                    let input = self
                         .swcha
                         .as_ref()
-                        .map_or(0, |handler| handler.read_register());
+                        .map_or(0xff, |handler| handler.read_register());
+
+                    *buffer_section = (input & !self.swacnt) | (self.swcha_output & self.swacnt);
                 }
                 0x1 => {
-                    *buffer_section = u8::from(self.swacnt);
+                    *buffer_section = self.swacnt;
                 }
-                0x2 if self.swbcnt => {
-                    *buffer_section = self
+                0x2 => {
+                    let input = self
                         .swchb
                         .as_ref()
-                        .map_or(0, |handler| handler.read_register());
+                        .map_or(0xff, |handler| handler.read_register());
+
+                    *buffer_section = (input & !self.swbcnt) | (self.swchb_output & self.swbcnt);
                 }
                 0x3 => {
-                    *buffer_section = u8::from(self.swbcnt);
+                    *buffer_section = self.swbcnt;
                 }
                 0x4 => {
                     *buffer_section = self.intim;
@@ -208,17 +269,21 @@ impl Component for Mos6532Riot {
             let adjusted_address = address - self.config.registers_assigned_address;
 
             match adjusted_address {
-                0x0 if !self.swacnt => {
-                    self.swcha.as_mut().unwrap().write_register(*buffer_section);
+                0x0 => {
+                    self.swcha_output = *buffer_section;
+                    self.drive_port_a();
                 }
                 0x1 => {
-                    self.swacnt = *buffer_section != 0;
+                    self.swacnt = *buffer_section;
+                    self.drive_port_a();
                 }
-                0x2 if !self.swbcnt => {
-                    self.swchb.as_mut().unwrap().write_register(*buffer_section);
+                0x2 => {
+                    self.swchb_output = *buffer_section;
+                    self.drive_port_b();
                 }
                 0x3 => {
-                    self.swbcnt = *buffer_section != 0;
+                    self.swbcnt = *buffer_section;
+                    self.drive_port_b();
                 }
                 0x14 => {
                     self.tim1t = Wrapping(*buffer_section);
@@ -306,8 +371,10 @@ impl<P: Platform> ComponentConfig<P> for Mos6532RiotConfig {
         Ok(Self::Component {
             swcha: None,
             swchb: None,
-            swacnt: false,
-            swbcnt: false,
+            swacnt: 0x00,
+            swbcnt: 0x00,
+            swcha_output: 0xff,
+            swchb_output: 0xff,
             intim: 0,
             instat: 0,
             tim1t: Wrapping(0),
@@ -27,6 +27,7 @@ fn criterion_benchmark(c: &mut Criterion) {
             assigned_address_space: cpu_address_space_id,
             kind: Mos6502Kind::Mos6502,
             broken_ror: false,
+            cycle_accurate_bus: false,
         },
     );
 
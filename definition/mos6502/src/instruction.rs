@@ -174,6 +174,38 @@ pub enum Opcode {
     Wdc65C02(Wdc65C02Opcode),
 }
 
+impl Opcode {
+    /// If this writes to its operand, either as a store or a read-modify-write
+    #[inline]
+    pub fn writes_operand(&self) -> bool {
+        matches!(
+            self,
+            Opcode::Mos6502(
+                Mos6502Opcode::Asl
+                    | Mos6502Opcode::Dcp
+                    | Mos6502Opcode::Dec
+                    | Mos6502Opcode::Inc
+                    | Mos6502Opcode::Isc
+                    | Mos6502Opcode::Lsr
+                    | Mos6502Opcode::Rla
+                    | Mos6502Opcode::Rol
+                    | Mos6502Opcode::Ror
+                    | Mos6502Opcode::Rra
+                    | Mos6502Opcode::Sax
+                    | Mos6502Opcode::Sha
+                    | Mos6502Opcode::Shs
+                    | Mos6502Opcode::Shx
+                    | Mos6502Opcode::Shy
+                    | Mos6502Opcode::Slo
+                    | Mos6502Opcode::Sre
+                    | Mos6502Opcode::Sta
+                    | Mos6502Opcode::Stx
+                    | Mos6502Opcode::Sty
+            ) | Opcode::Wdc65C02(Wdc65C02Opcode::Stz | Wdc65C02Opcode::Trb | Wdc65C02Opcode::Tsb)
+        )
+    }
+}
+
 impl Display for Opcode {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
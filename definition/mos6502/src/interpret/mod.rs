@@ -6,12 +6,11 @@ use super::{
     instruction::{Mos6502InstructionSet, Mos6502Opcode},
 };
 use crate::{
-    ExecutionStep, Mos6502,
+    ExecutionStep, IRQ_VECTOR, Mos6502,
     instruction::{AddressingMode, Mos6502AddressingMode, Opcode, Wdc65C02Opcode},
 };
 
 pub const STACK_BASE_ADDRESS: Address = 0x0100;
-const INTERRUPT_VECTOR: Address = 0xfffe;
 
 // NOTE: https://www.pagetable.com/c64ref/6502
 
@@ -72,7 +71,8 @@ impl Mos6502 {
                         self.state.a = result;
                     }
                     Mos6502Opcode::Asl => {
-                        let mut value: u8 = self.load(&instruction);
+                        let original: u8 = self.load(&instruction);
+                        let mut value = original;
                         let value_bits = value.view_bits::<Lsb0>();
 
                         let carry = value_bits[7];
@@ -89,9 +89,7 @@ impl Mos6502 {
                         {
                             self.state.a = value;
                         } else {
-                            self.state
-                                .execution_queue
-                                .push_back(ExecutionStep::StoreData(value));
+                            self.write_back(original, value);
                         }
                     }
                     Mos6502Opcode::Asr => {
@@ -112,27 +110,21 @@ impl Mos6502 {
                         let value = self.load(&instruction) as i8;
 
                         if !self.state.flags.carry {
-                            self.state
-                                .execution_queue
-                                .push_back(ExecutionStep::ModifyProgramPointer(value));
+                            self.branch(value);
                         }
                     }
                     Mos6502Opcode::Bcs => {
                         let value = self.load(&instruction) as i8;
 
                         if self.state.flags.carry {
-                            self.state
-                                .execution_queue
-                                .push_back(ExecutionStep::ModifyProgramPointer(value));
+                            self.branch(value);
                         }
                     }
                     Mos6502Opcode::Beq => {
                         let value = self.load(&instruction) as i8;
 
                         if self.state.flags.zero {
-                            self.state
-                                .execution_queue
-                                .push_back(ExecutionStep::ModifyProgramPointer(value));
+                            self.branch(value);
                         }
                     }
                     Mos6502Opcode::Bit => {
@@ -149,29 +141,43 @@ impl Mos6502 {
                         let value = self.load(&instruction) as i8;
 
                         if self.state.flags.negative {
-                            self.state
-                                .execution_queue
-                                .push_back(ExecutionStep::ModifyProgramPointer(value));
+                            self.branch(value);
                         }
                     }
                     Mos6502Opcode::Bne => {
                         let value = self.load(&instruction) as i8;
 
                         if !self.state.flags.zero {
-                            self.state
-                                .execution_queue
-                                .push_back(ExecutionStep::ModifyProgramPointer(value));
+                            self.branch(value);
                         }
                     }
                     Mos6502Opcode::Bpl => {
                         let value = self.load(&instruction) as i8;
 
                         if !self.state.flags.negative {
-                            self.state
-                                .execution_queue
-                                .push_back(ExecutionStep::ModifyProgramPointer(value));
+                            self.branch(value);
                         }
                     }
+                    Mos6502Opcode::Brk if self.config.cycle_accurate_bus => {
+                        // The byte after the opcode is padding that gets skipped over
+                        let program_bytes = (self.state.program.wrapping_add(1)).to_le_bytes();
+
+                        // https://www.nesdev.org/wiki/Status_flags
+                        let mut flags = self.state.flags;
+                        flags.undocumented = true;
+                        flags.break_ = true;
+                        self.state.flags.interrupt_disable = true;
+
+                        self.state.execution_queue.extend([
+                            ExecutionStep::PushStack(program_bytes[1]),
+                            ExecutionStep::PushStack(program_bytes[0]),
+                            ExecutionStep::PushStack(flags.to_byte()),
+                            ExecutionStep::SelectInterruptVector(IRQ_VECTOR),
+                            ExecutionStep::LoadData,
+                            ExecutionStep::LoadData,
+                            ExecutionStep::LatchToProgramPointer,
+                        ]);
+                    }
                     Mos6502Opcode::Brk => {
                         let new_stack = self.state.stack.wrapping_sub(2);
                         let program_bytes = (self.state.program.wrapping_add(2)).to_le_bytes();
@@ -205,17 +211,18 @@ impl Mos6502 {
                             flags.to_byte(),
                         );
 
+                        let vector = self.interrupt_vector(IRQ_VECTOR) as Address;
                         let program = [
                             self.address_space
                                 .read_le_value(
-                                    INTERRUPT_VECTOR,
+                                    vector,
                                     self.timestamp,
                                     Some(&mut self.address_space_cache),
                                 )
                                 .unwrap_or_default(),
                             self.address_space
                                 .read_le_value(
-                                    INTERRUPT_VECTOR + 1,
+                                    vector + 1,
                                     self.timestamp,
                                     Some(&mut self.address_space_cache),
                                 )
@@ -229,18 +236,14 @@ impl Mos6502 {
                         let value = self.load(&instruction) as i8;
 
                         if !self.state.flags.overflow {
-                            self.state
-                                .execution_queue
-                                .push_back(ExecutionStep::ModifyProgramPointer(value));
+                            self.branch(value);
                         }
                     }
                     Mos6502Opcode::Bvs => {
                         let value = self.load(&instruction) as i8;
 
                         if self.state.flags.overflow {
-                            self.state
-                                .execution_queue
-                                .push_back(ExecutionStep::ModifyProgramPointer(value));
+                            self.branch(value);
                         }
                     }
                     Mos6502Opcode::Clc => {
@@ -291,9 +294,7 @@ impl Mos6502 {
                         self.state.flags.negative = result.view_bits::<Lsb0>()[7];
                         self.state.flags.zero = result == 0;
 
-                        self.state
-                            .execution_queue
-                            .push_back(ExecutionStep::StoreData(result));
+                        self.write_back(value, result);
                     }
                     Mos6502Opcode::Dex => {
                         let result = self.state.x.wrapping_sub(1);
@@ -329,9 +330,7 @@ impl Mos6502 {
                         self.state.flags.negative = result.view_bits::<Lsb0>()[7];
                         self.state.flags.zero = result == 0;
 
-                        self.state
-                            .execution_queue
-                            .push_back(ExecutionStep::StoreData(result));
+                        self.write_back(value, result);
                     }
                     Mos6502Opcode::Inx => {
                         let result = self.state.x.wrapping_add(1);
@@ -372,11 +371,21 @@ impl Mos6502 {
                         // We load the byte BEFORE the program counter
                         let program_bytes = (self.state.program.wrapping_sub(1)).to_le_bytes();
 
-                        self.state.execution_queue.extend([
-                            ExecutionStep::PushStack(program_bytes[1]),
-                            ExecutionStep::PushStack(program_bytes[0]),
-                            ExecutionStep::AddressBusToProgramPointer,
-                        ]);
+                        if self.config.cycle_accurate_bus {
+                            // Only the low byte of the target has been fetched so far
+                            self.state.execution_queue.extend([
+                                ExecutionStep::PushStack(program_bytes[1]),
+                                ExecutionStep::PushStack(program_bytes[0]),
+                                ExecutionStep::LoadData,
+                                ExecutionStep::LatchToProgramPointer,
+                            ]);
+                        } else {
+                            self.state.execution_queue.extend([
+                                ExecutionStep::PushStack(program_bytes[1]),
+                                ExecutionStep::PushStack(program_bytes[0]),
+                                ExecutionStep::AddressBusToProgramPointer,
+                            ]);
+                        }
                     }
                     Mos6502Opcode::Las => todo!(),
                     Mos6502Opcode::Lax => {
@@ -410,11 +419,11 @@ impl Mos6502 {
                         self.state.y = value;
                     }
                     Mos6502Opcode::Lsr => {
-                        let value: u8 = self.load(&instruction);
-                        let value_bits = value.view_bits::<Lsb0>();
+                        let original: u8 = self.load(&instruction);
+                        let value_bits = original.view_bits::<Lsb0>();
 
                         let carry = value_bits[0];
-                        let value = value >> 1;
+                        let value = original >> 1;
 
                         self.state.flags.negative = false;
                         self.state.flags.carry = carry;
@@ -425,9 +434,7 @@ impl Mos6502 {
                         {
                             self.state.a = value;
                         } else {
-                            self.state
-                                .execution_queue
-                                .push_back(ExecutionStep::StoreData(value));
+                            self.write_back(original, value);
                         }
                     }
                     Mos6502Opcode::Nop => {
@@ -461,37 +468,20 @@ impl Mos6502 {
                             .push_back(ExecutionStep::PushStack(flags.to_byte()));
                     }
                     Mos6502Opcode::Pla => {
-                        self.state.stack = self.state.stack.wrapping_add(1);
-
-                        self.state.a = self
-                            .address_space
-                            .read_le_value(
-                                self.state.stack as usize + STACK_BASE_ADDRESS,
-                                self.timestamp,
-                                Some(&mut self.address_space_cache),
-                            )
-                            .unwrap_or_default();
+                        self.state.a = self.pull_stack();
 
                         self.state.flags.negative = self.state.a.view_bits::<Lsb0>()[7];
                         self.state.flags.zero = self.state.a == 0;
                     }
                     Mos6502Opcode::Plp => {
-                        self.state.stack = self.state.stack.wrapping_add(1);
-
-                        let value = self
-                            .address_space
-                            .read_le_value(
-                                self.state.stack as usize + STACK_BASE_ADDRESS,
-                                self.timestamp,
-                                Some(&mut self.address_space_cache),
-                            )
-                            .unwrap_or_default();
+                        let value = self.pull_stack();
 
                         self.state.flags = FlagRegister::from_byte(value);
                     }
                     Mos6502Opcode::Rla => todo!(),
                     Mos6502Opcode::Rol => {
-                        let mut value: u8 = self.load(&instruction);
+                        let original: u8 = self.load(&instruction);
+                        let mut value = original;
                         let value_bits = value.view_bits::<Lsb0>();
 
                         let old_carry = self.state.flags.carry;
@@ -507,13 +497,12 @@ impl Mos6502 {
                         {
                             self.state.a = value;
                         } else {
-                            self.state
-                                .execution_queue
-                                .push_back(ExecutionStep::StoreData(value));
+                            self.write_back(original, value);
                         }
                     }
                     Mos6502Opcode::Ror => {
-                        let mut value: u8 = self.load(&instruction);
+                        let original: u8 = self.load(&instruction);
+                        let mut value = original;
                         let value_bits = value.view_bits::<Lsb0>();
 
                         let old_carry = self.state.flags.carry;
@@ -529,72 +518,47 @@ impl Mos6502 {
                         {
                             self.state.a = value;
                         } else {
-                            self.state
-                                .execution_queue
-                                .push_back(ExecutionStep::StoreData(value));
+                            self.write_back(original, value);
                         }
                     }
                     Mos6502Opcode::Rra => todo!(),
                     Mos6502Opcode::Rti => {
-                        self.state.stack = self.state.stack.wrapping_add(1);
-                        let flags = self
-                            .address_space
-                            .read_le_value::<u8>(
-                                STACK_BASE_ADDRESS + self.state.stack as usize,
-                                self.timestamp,
-                                Some(&mut self.address_space_cache),
-                            )
-                            .unwrap_or_default();
-
+                        let flags = self.pull_stack();
                         self.state.flags = FlagRegister::from_byte(flags);
 
-                        self.state.stack = self.state.stack.wrapping_add(1);
-                        let program_pointer_low = self
-                            .address_space
-                            .read_le_value::<u8>(
-                                STACK_BASE_ADDRESS + self.state.stack as usize,
-                                self.timestamp,
-                                Some(&mut self.address_space_cache),
-                            )
-                            .unwrap_or_default();
-
-                        self.state.stack = self.state.stack.wrapping_add(1);
-                        let program_pointer_high = self
-                            .address_space
-                            .read_le_value::<u8>(
-                                STACK_BASE_ADDRESS + self.state.stack as usize,
-                                self.timestamp,
-                                Some(&mut self.address_space_cache),
-                            )
-                            .unwrap_or_default();
-
-                        self.state.program =
-                            u16::from_le_bytes([program_pointer_low, program_pointer_high]);
+                        if self.config.cycle_accurate_bus {
+                            self.state.execution_queue.extend([
+                                ExecutionStep::PullStack,
+                                ExecutionStep::PullStack,
+                                ExecutionStep::LatchToProgramPointer,
+                            ]);
+                        } else {
+                            let program_pointer_low = self.pull_stack();
+                            let program_pointer_high = self.pull_stack();
+
+                            self.state.program =
+                                u16::from_le_bytes([program_pointer_low, program_pointer_high]);
+                        }
                     }
                     Mos6502Opcode::Rts => {
-                        self.state.stack = self.state.stack.wrapping_add(1);
-                        let program_pointer_low = self
-                            .address_space
-                            .read_le_value::<u8>(
-                                STACK_BASE_ADDRESS + self.state.stack as usize,
-                                self.timestamp,
-                                Some(&mut self.address_space_cache),
-                            )
-                            .unwrap_or_default();
-
-                        self.state.stack = self.state.stack.wrapping_add(1);
-                        let program_pointer_high = self
-                            .address_space
-                            .read_le_value::<u8>(
-                                STACK_BASE_ADDRESS + self.state.stack as usize,
-                                self.timestamp,
-                                Some(&mut self.address_space_cache),
-                            )
-                            .unwrap_or_default();
-
-                        self.state.program =
-                            u16::from_le_bytes([program_pointer_low, program_pointer_high]);
-                        self.state.program = self.state.program.wrapping_add(1);
+                        let program_pointer_low = self.pull_stack();
+
+                        if self.config.cycle_accurate_bus {
+                            self.state.latch.push(program_pointer_low);
+
+                            // The increment spends a cycle reading the pulled address
+                            self.state.execution_queue.extend([
+                                ExecutionStep::PullStack,
+                                ExecutionStep::LatchToProgramPointer,
+                                ExecutionStep::ModifyProgramPointer(1),
+                            ]);
+                        } else {
+                            let program_pointer_high = self.pull_stack();
+
+                            self.state.program =
+                                u16::from_le_bytes([program_pointer_low, program_pointer_high]);
+                            self.state.program = self.state.program.wrapping_add(1);
+                        }
                     }
                     Mos6502Opcode::Sax => {
                         let value = self.state.a & self.state.x;
@@ -691,9 +655,7 @@ impl Mos6502 {
                 Wdc65C02Opcode::Bra => {
                     let value = self.load(&instruction) as i8;
 
-                    self.state
-                        .execution_queue
-                        .push_back(ExecutionStep::ModifyProgramPointer(value));
+                    self.branch(value);
                 }
                 Wdc65C02Opcode::Phx => {
                     self.state
@@ -752,6 +714,62 @@ impl Mos6502 {
         }
     }
 
+    #[inline]
+    pub(super) fn pull_stack(&mut self) -> u8 {
+        self.state.stack = self.state.stack.wrapping_add(1);
+
+        self.address_space
+            .read_le_value(
+                STACK_BASE_ADDRESS + self.state.stack as usize,
+                self.timestamp,
+                Some(&mut self.address_space_cache),
+            )
+            .unwrap_or_default()
+    }
+
+    /// Queue the write of a read-modify-write instruction
+    #[inline]
+    fn write_back(&mut self, original: u8, result: u8) {
+        if self.config.cycle_accurate_bus {
+            // The NMOS parts write the unmodified value back while the result
+            // is being worked out, the CMOS ones read it again instead
+            let step = if self.config.kind.original_instruction_set() {
+                ExecutionStep::DummyWrite(original)
+            } else {
+                ExecutionStep::DummyRead
+            };
+
+            self.state.execution_queue.push_back(step);
+        }
+
+        self.state
+            .execution_queue
+            .push_back(ExecutionStep::StoreData(result));
+    }
+
+    #[inline]
+    fn branch(&mut self, offset: i8) {
+        self.state
+            .execution_queue
+            .push_back(ExecutionStep::ModifyProgramPointer(offset));
+
+        let [low, high] = self.state.program.to_le_bytes();
+        let uncorrected = u16::from_le_bytes([low.wrapping_add(offset as u8), high]);
+
+        if uncorrected != self.state.program.wrapping_add_signed(i16::from(offset)) {
+            // Crossing a page fixes up the high byte a cycle late
+            if self.config.cycle_accurate_bus {
+                self.state
+                    .execution_queue
+                    .push_back(ExecutionStep::DummyReadFrom(uncorrected));
+            }
+        } else {
+            // Without a page cross the taken branch cycle does not poll for
+            // interrupts, so the operand fetch is the last chance
+            self.suppress_interrupt_polling(1);
+        }
+    }
+
     #[inline]
     fn adc(&mut self, value: u8) {
         if self.state.flags.decimal && self.config.kind.supports_decimal() {
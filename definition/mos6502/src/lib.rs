@@ -2,8 +2,8 @@ use std::{
     collections::VecDeque,
     io::{Read, Write},
     sync::{
-        Arc,
-        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicU32, Ordering},
     },
 };
 
@@ -24,7 +24,10 @@ use crate::{
         InstructionGroup, decode_group1_space_instruction, decode_group2_space_instruction,
         decode_group3_space_instruction, decode_undocumented_space_instruction,
     },
-    instruction::{AddressingMode, Mos6502AddressingMode, Wdc65C02AddressingMode},
+    instruction::{
+        AddressingMode, Mos6502AddressingMode, Mos6502Opcode, Opcode, Wdc65C02AddressingMode,
+        Wdc65C02Opcode,
+    },
     interpret::STACK_BASE_ADDRESS,
 };
 
@@ -149,6 +152,29 @@ pub enum ExecutionStep {
     ModifyProgramPointer(i8),
     /// Adding this value to the address bus
     ModifyAddressBus(AddressBusModification),
+    /// Adding this value to the address bus the way the hardware does, fixing
+    /// up the high byte a cycle late with a read from the wrong page in the
+    /// meantime
+    ///
+    /// The fixup cycle is skipped if no page was crossed, unless
+    /// `always_fixup` is set
+    IndexAddressBus {
+        register: AddressBusModification,
+        always_fixup: bool,
+    },
+    /// Pointing the address bus somewhere
+    SetAddressBus(u16),
+    /// Reading from the address bus and throwing the result away
+    DummyRead,
+    /// Reading from this address and throwing the result away
+    DummyReadFrom(u16),
+    /// Writing to the address bus without advancing it
+    DummyWrite(u8),
+    /// Pulling an item off the stack into the latch
+    PullStack,
+    /// Pointing the address bus at the vector an interrupt sequence should
+    /// jump through, letting a pending NMI take over the one given
+    SelectInterruptVector(u16),
     /// Make sure the address bus is within the zero page
     MaskAddressBusToZeroPage,
     /// Execute this instruction
@@ -242,6 +268,13 @@ pub struct Mos6502Config {
     pub assigned_address_space: AddressSpaceId,
     pub kind: Mos6502Kind,
     pub broken_ror: bool,
+    /// Perform every bus access the NMOS processor does, on the cycle it
+    /// does it, including the dummy reads and writes that have no effect on
+    /// the processor itself
+    ///
+    /// Slower, but needed for hardware where reads and writes have side
+    /// effects
+    pub cycle_accurate_bus: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -266,6 +299,33 @@ struct ProcessorState {
     pub latch: ArrayVec<u8, 2>,
     /// Next instruction that will be executed
     pub next_instruction: Option<Mos6502InstructionSet>,
+    /// Cycles executed since power on, used to figure out get/put parity
+    #[serde(default)]
+    pub cycle_counter: u64,
+    /// Cycles left that the bus is being held by DMA
+    #[serde(default)]
+    pub dma_cycles_remaining: u32,
+    /// Set once a falling edge on NMI has been noticed, until it is serviced
+    #[serde(default)]
+    pub nmi_pending: bool,
+    /// What interrupt polling saw at the end of the last two cycles, oldest
+    /// first
+    ///
+    /// Interrupts are serviced off of the older one, which makes the decision
+    /// on the second to last cycle of an instruction
+    #[serde(default)]
+    pub interrupt_polls: [InterruptPoll; 2],
+    /// Cycles coming up that do not poll for interrupts
+    #[serde(default)]
+    pub unpolled_cycles: u8,
+}
+
+/// The interrupt lines as seen at the end of a cycle
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct InterruptPoll {
+    nmi: bool,
+    /// IRQ held low and not masked
+    irq: bool,
 }
 
 impl Default for ProcessorState {
@@ -282,6 +342,11 @@ impl Default for ProcessorState {
             address_bus: 0x0000,
             latch: ArrayVec::default(),
             next_instruction: None,
+            cycle_counter: 0,
+            dma_cycles_remaining: 0,
+            nmi_pending: false,
+            interrupt_polls: Default::default(),
+            unpolled_cycles: 0,
         }
     }
 }
@@ -305,10 +370,51 @@ impl RdyFlag {
     }
 }
 
+/// A request from another component to take the bus away from the processor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DmaRequest {
+    /// Cycles spent halting the processor and doing dummy reads before the
+    /// transfer starts
+    pub setup_cycles: u32,
+    /// Cycles the transfer itself takes
+    pub transfer_cycles: u32,
+    /// If the transfer has to start on a get (even) cycle, costing an extra
+    /// alignment cycle if it would otherwise land on a put (odd) one
+    pub get_aligned: bool,
+}
+
+impl DmaRequest {
+    /// How many cycles this will stall the processor for if it starts on
+    /// `cycle`
+    pub fn stall_cycles(&self, cycle: u64) -> u32 {
+        let alignment_cycle = self.get_aligned && (cycle + u64::from(self.setup_cycles)) % 2 == 1;
+
+        self.setup_cycles + u32::from(alignment_cycle) + self.transfer_cycles
+    }
+}
+
+/// Lets other components stall the processor for a number of cycles while
+/// they master the bus
+///
+/// Requests are serviced in the order they were made
+#[derive(Debug, Default)]
+pub struct DmaController(Mutex<VecDeque<DmaRequest>>);
+
+impl DmaController {
+    pub fn request(&self, request: DmaRequest) {
+        self.0.lock().unwrap().push_back(request);
+    }
+
+    fn take(&self) -> Option<DmaRequest> {
+        self.0.lock().unwrap().pop_front()
+    }
+}
+
 #[derive(Debug)]
 pub struct Mos6502 {
     state: ProcessorState,
     rdy: Arc<RdyFlag>,
+    dma: Arc<DmaController>,
     irq: Arc<IrqFlag>,
     nmi: Arc<NmiFlag>,
     config: Mos6502Config,
@@ -323,6 +429,10 @@ impl Mos6502 {
         self.rdy.clone()
     }
 
+    pub fn dma(&self) -> Arc<DmaController> {
+        self.dma.clone()
+    }
+
     pub fn irq(&self) -> Arc<IrqFlag> {
         self.irq.clone()
     }
@@ -335,6 +445,18 @@ impl Mos6502 {
         self.config.assigned_address_space
     }
 
+    #[inline]
+    fn dummy_read(&mut self, address: u16) {
+        let _: Option<u8> = self
+            .address_space
+            .read_le_value(
+                address as Address,
+                self.timestamp,
+                Some(&mut self.address_space_cache),
+            )
+            .ok();
+    }
+
     #[inline]
     fn fetch_and_decode(&mut self) {
         let byte: u8 = self
@@ -404,6 +526,10 @@ impl Mos6502 {
     }
 
     fn push_steps_for_instruction(&mut self, instruction: &Mos6502InstructionSet) {
+        if self.config.cycle_accurate_bus && self.push_dummy_steps_for_instruction(instruction) {
+            return;
+        }
+
         if let Some(addressing_mode) = instruction.addressing_mode {
             match addressing_mode {
                 AddressingMode::Mos6502(Mos6502AddressingMode::Absolute) => {
@@ -415,19 +541,23 @@ impl Mos6502 {
                 }
                 AddressingMode::Mos6502(Mos6502AddressingMode::Immediate) => {}
                 AddressingMode::Mos6502(Mos6502AddressingMode::XIndexedAbsolute) => {
+                    let index = self.index_step(AddressBusModification::X, instruction);
+
                     self.state.execution_queue.extend([
                         ExecutionStep::LoadData,
                         ExecutionStep::LoadData,
                         ExecutionStep::LatchToAddressBus,
-                        ExecutionStep::ModifyAddressBus(AddressBusModification::X),
+                        index,
                     ]);
                 }
                 AddressingMode::Mos6502(Mos6502AddressingMode::YIndexedAbsolute) => {
+                    let index = self.index_step(AddressBusModification::Y, instruction);
+
                     self.state.execution_queue.extend([
                         ExecutionStep::LoadData,
                         ExecutionStep::LoadData,
                         ExecutionStep::LatchToAddressBus,
-                        ExecutionStep::ModifyAddressBus(AddressBusModification::Y),
+                        index,
                     ]);
                 }
                 AddressingMode::Mos6502(Mos6502AddressingMode::AbsoluteIndirect) => {
@@ -452,9 +582,11 @@ impl Mos6502 {
                     }
                 }
                 AddressingMode::Mos6502(Mos6502AddressingMode::XIndexedZeroPageIndirect) => {
+                    self.state
+                        .execution_queue
+                        .extend([ExecutionStep::LoadData, ExecutionStep::LatchToAddressBus]);
+                    self.push_unindexed_dummy_read();
                     self.state.execution_queue.extend([
-                        ExecutionStep::LoadData,
-                        ExecutionStep::LatchToAddressBus,
                         ExecutionStep::ModifyAddressBus(AddressBusModification::X),
                         ExecutionStep::MaskAddressBusToZeroPage,
                         ExecutionStep::LoadData,
@@ -464,6 +596,8 @@ impl Mos6502 {
                     ]);
                 }
                 AddressingMode::Mos6502(Mos6502AddressingMode::ZeroPageIndirectYIndexed) => {
+                    let index = self.index_step(AddressBusModification::Y, instruction);
+
                     self.state.execution_queue.extend([
                         ExecutionStep::LoadData,
                         ExecutionStep::LatchToAddressBus,
@@ -471,21 +605,25 @@ impl Mos6502 {
                         ExecutionStep::MaskAddressBusToZeroPage,
                         ExecutionStep::LoadData,
                         ExecutionStep::LatchToAddressBus,
-                        ExecutionStep::ModifyAddressBus(AddressBusModification::Y),
+                        index,
                     ]);
                 }
                 AddressingMode::Mos6502(Mos6502AddressingMode::XIndexedZeroPage) => {
+                    self.state
+                        .execution_queue
+                        .extend([ExecutionStep::LoadData, ExecutionStep::LatchToAddressBus]);
+                    self.push_unindexed_dummy_read();
                     self.state.execution_queue.extend([
-                        ExecutionStep::LoadData,
-                        ExecutionStep::LatchToAddressBus,
                         ExecutionStep::ModifyAddressBus(AddressBusModification::X),
                         ExecutionStep::MaskAddressBusToZeroPage,
                     ]);
                 }
                 AddressingMode::Mos6502(Mos6502AddressingMode::YIndexedZeroPage) => {
+                    self.state
+                        .execution_queue
+                        .extend([ExecutionStep::LoadData, ExecutionStep::LatchToAddressBus]);
+                    self.push_unindexed_dummy_read();
                     self.state.execution_queue.extend([
-                        ExecutionStep::LoadData,
-                        ExecutionStep::LatchToAddressBus,
                         ExecutionStep::ModifyAddressBus(AddressBusModification::Y),
                         ExecutionStep::MaskAddressBusToZeroPage,
                     ]);
@@ -513,6 +651,459 @@ impl Mos6502 {
     }
 }
 
+impl Mos6502 {
+    /// Queue the dummy accesses that happen before the operand is touched,
+    /// returning true if the instruction needs no other steps
+    fn push_dummy_steps_for_instruction(&mut self, instruction: &Mos6502InstructionSet) -> bool {
+        let stack_address = (STACK_BASE_ADDRESS + self.state.stack as usize) as u16;
+
+        if instruction.opcode == Opcode::Mos6502(Mos6502Opcode::Jsr) {
+            // The high byte of the target is fetched only after the return address is pushed
+            self.state.execution_queue.extend([
+                ExecutionStep::LoadData,
+                ExecutionStep::DummyReadFrom(stack_address),
+            ]);
+
+            return true;
+        }
+
+        if matches!(
+            instruction.addressing_mode,
+            None | Some(AddressingMode::Mos6502(Mos6502AddressingMode::Accumulator))
+        ) {
+            // Single byte instructions still read the byte after the opcode
+            self.state
+                .execution_queue
+                .push_back(ExecutionStep::DummyReadFrom(self.state.program));
+
+            // Pulls spend a cycle reading the stack before incrementing the pointer
+            if matches!(
+                instruction.opcode,
+                Opcode::Mos6502(
+                    Mos6502Opcode::Pla
+                        | Mos6502Opcode::Plp
+                        | Mos6502Opcode::Rti
+                        | Mos6502Opcode::Rts
+                ) | Opcode::Wdc65C02(Wdc65C02Opcode::Plx | Wdc65C02Opcode::Ply)
+            ) {
+                self.state
+                    .execution_queue
+                    .push_back(ExecutionStep::DummyReadFrom(stack_address));
+            }
+        }
+
+        false
+    }
+
+    /// The step that applies an index register to an absolute address
+    fn index_step(
+        &self,
+        register: AddressBusModification,
+        instruction: &Mos6502InstructionSet,
+    ) -> ExecutionStep {
+        if self.config.cycle_accurate_bus {
+            ExecutionStep::IndexAddressBus {
+                register,
+                // Writes can't be undone so the processor always waits for the fixup
+                always_fixup: instruction.opcode.writes_operand(),
+            }
+        } else {
+            ExecutionStep::ModifyAddressBus(register)
+        }
+    }
+
+    /// Zero page indexing reads the unindexed address while adding
+    fn push_unindexed_dummy_read(&mut self) {
+        if self.config.cycle_accurate_bus {
+            self.state
+                .execution_queue
+                .push_back(ExecutionStep::DummyRead);
+        }
+    }
+
+    /// Sample the interrupt lines at the end of a cycle
+    fn poll_interrupts(&mut self) {
+        if !self.config.kind.supports_interrupts() {
+            return;
+        }
+
+        if self.state.unpolled_cycles != 0 {
+            self.state.unpolled_cycles -= 1;
+
+            return;
+        }
+
+        self.state.nmi_pending |= self.nmi.interrupt_required();
+
+        let [_, newest] = self.state.interrupt_polls;
+        self.state.interrupt_polls = [
+            newest,
+            InterruptPoll {
+                nmi: self.state.nmi_pending,
+                irq: self.irq.interrupt_required() && !self.state.flags.interrupt_disable,
+            },
+        ];
+    }
+
+    fn acknowledge_nmi(&mut self) {
+        self.state.nmi_pending = false;
+
+        for poll in &mut self.state.interrupt_polls {
+            poll.nmi = false;
+        }
+    }
+
+    /// The vector an interrupt sequence pushing state for `vector` actually
+    /// ends up using
+    ///
+    /// An NMI noticed early enough hijacks the sequence of a BRK or IRQ, which
+    /// then jumps through the NMI vector with the state it already pushed
+    fn interrupt_vector(&mut self, vector: u16) -> u16 {
+        let [poll, _] = self.state.interrupt_polls;
+
+        if vector == IRQ_VECTOR && poll.nmi {
+            self.acknowledge_nmi();
+
+            NMI_VECTOR
+        } else {
+            vector
+        }
+    }
+
+    /// Skip polling at the end of the next `cycles` cycles
+    fn suppress_interrupt_polling(&mut self, cycles: u8) {
+        self.state.unpolled_cycles = cycles;
+    }
+
+    /// Run the steps at the front of the queue that don't touch the bus, so
+    /// state is up to date at the end of every cycle
+    fn settle(&mut self) {
+        loop {
+            let settles = match self.state.execution_queue.front() {
+                Some(
+                    ExecutionStep::LatchToAddressBus
+                    | ExecutionStep::LatchToProgramPointer
+                    | ExecutionStep::MaskAddressBusToZeroPage
+                    | ExecutionStep::ModifyAddressBus(_)
+                    | ExecutionStep::SetAddressBus(_)
+                    | ExecutionStep::SelectInterruptVector(_),
+                ) => true,
+                Some(ExecutionStep::Interpret) => !interpret_accesses_bus(
+                    self.state
+                        .next_instruction
+                        .as_ref()
+                        .expect("Interpret queued without an instruction"),
+                ),
+                _ => false,
+            };
+
+            if !settles {
+                break;
+            }
+
+            let step = self.state.execution_queue.pop_front().unwrap();
+            let consumed_cycle = self.execute_step(step);
+            debug_assert!(!consumed_cycle);
+        }
+    }
+
+    /// If the next cycle to run writes to the bus
+    fn next_cycle_writes(&self) -> bool {
+        self.state
+            .execution_queue
+            .iter()
+            .find_map(|step| match step {
+                ExecutionStep::StoreData(_)
+                | ExecutionStep::PushStack(_)
+                | ExecutionStep::DummyWrite(_) => Some(true),
+                // Not cycles of their own
+                ExecutionStep::LatchToAddressBus
+                | ExecutionStep::LatchToProgramPointer
+                | ExecutionStep::MaskAddressBusToZeroPage
+                | ExecutionStep::ModifyAddressBus(_)
+                | ExecutionStep::SetAddressBus(_)
+                | ExecutionStep::SelectInterruptVector(_) => None,
+                ExecutionStep::Interpret => self
+                    .state
+                    .next_instruction
+                    .as_ref()
+                    .map(interpret_writes_bus),
+                _ => Some(false),
+            })
+            .unwrap_or(false)
+    }
+
+    /// Run a single step, returning if it took up the cycle
+    #[inline]
+    fn execute_step(&mut self, step: ExecutionStep) -> bool {
+        match step {
+            ExecutionStep::Reset => {
+                self.state
+                    .interrupt(RESET_VECTOR, false, false, self.config.cycle_accurate_bus);
+
+                if self.config.cycle_accurate_bus {
+                    // The sequence starts with a dummy read on this very cycle
+                    return false;
+                }
+
+                return true;
+            }
+            ExecutionStep::Jammed => {
+                self.state.execution_queue.clear();
+                self.state.execution_queue.push_back(ExecutionStep::Jammed);
+
+                return true;
+            }
+            ExecutionStep::Wait => {
+                self.state.execution_queue.push_back(ExecutionStep::Wait);
+
+                return true;
+            }
+            ExecutionStep::FetchAndDecode => {
+                let [poll, _] = self.state.interrupt_polls;
+
+                let interrupt = if poll.nmi {
+                    self.acknowledge_nmi();
+
+                    Some((NMI_VECTOR, false))
+                } else if poll.irq {
+                    // NMI can still take over the vector while state is pushed
+                    Some((IRQ_VECTOR, false))
+                } else {
+                    None
+                };
+
+                match interrupt {
+                    Some((vector, break_status)) => {
+                        self.state.interrupt(
+                            vector,
+                            break_status,
+                            true,
+                            self.config.cycle_accurate_bus,
+                        );
+
+                        if self.config.cycle_accurate_bus {
+                            // The opcode fetch becomes a dummy read
+                            return false;
+                        }
+                    }
+                    None => self.fetch_and_decode(),
+                }
+
+                return true;
+            }
+            ExecutionStep::LoadData => {
+                let byte = self
+                    .address_space
+                    .read_le_value(
+                        self.state.address_bus as usize,
+                        self.timestamp,
+                        Some(&mut self.address_space_cache),
+                    )
+                    .unwrap_or_default();
+
+                self.state.latch.push(byte);
+                self.state.address_bus = self.state.address_bus.wrapping_add(1);
+
+                return true;
+            }
+            ExecutionStep::LoadDataWithoutAdvancingPage => {
+                let byte = self
+                    .address_space
+                    .read_le_value(
+                        self.state.address_bus as usize,
+                        self.timestamp,
+                        Some(&mut self.address_space_cache),
+                    )
+                    .unwrap_or_default();
+
+                self.state.latch.push(byte);
+
+                let mut address_bus_contents = self.state.address_bus.to_le_bytes();
+                address_bus_contents[0] = address_bus_contents[0].wrapping_add(1);
+                self.state.address_bus = u16::from_le_bytes(address_bus_contents);
+
+                return true;
+            }
+            ExecutionStep::LoadDataFromConstant(data) => {
+                self.state.latch.push(data);
+
+                return true;
+            }
+            ExecutionStep::StoreData(data) => {
+                let _ = self.address_space.write_le_value(
+                    self.state.address_bus as usize,
+                    self.timestamp,
+                    Some(&mut self.address_space_cache),
+                    data,
+                );
+                self.state.address_bus = self.state.address_bus.wrapping_add(1);
+
+                return true;
+            }
+            ExecutionStep::PushStack(data) => {
+                let _ = self.address_space.write_le_value(
+                    STACK_BASE_ADDRESS + self.state.stack as usize,
+                    self.timestamp,
+                    Some(&mut self.address_space_cache),
+                    data,
+                );
+                self.state.stack = self.state.stack.wrapping_sub(1);
+
+                return true;
+            }
+            ExecutionStep::LatchToAddressBus => {
+                match self.state.latch.len() {
+                    1 => {
+                        self.state.address_bus = u16::from(self.state.latch[0]);
+                    }
+                    2 => {
+                        let latch = [self.state.latch[0], self.state.latch[1]];
+                        self.state.address_bus = u16::from_le_bytes(latch);
+                    }
+                    _ => {
+                        unreachable!()
+                    }
+                }
+
+                self.state.latch.clear();
+            }
+            // only used for interrupts
+            ExecutionStep::LatchToProgramPointer => {
+                assert!(self.state.latch.len() == 2);
+
+                self.state.program = u16::from_le_bytes([self.state.latch[0], self.state.latch[1]]);
+                self.state.latch.clear();
+            }
+            ExecutionStep::AddressBusToProgramPointer => {
+                self.state.program = self.state.address_bus;
+
+                return true;
+            }
+            ExecutionStep::ModifyProgramPointer(value) => {
+                if self.config.cycle_accurate_bus {
+                    self.dummy_read(self.state.program);
+                }
+
+                self.state.program = self.state.program.wrapping_add_signed(i16::from(value));
+
+                return true;
+            }
+            ExecutionStep::MaskAddressBusToZeroPage => {
+                self.state.address_bus %= PAGE_SIZE as u16;
+            }
+            ExecutionStep::ModifyAddressBus(modification) => {
+                let modification = match modification {
+                    AddressBusModification::X => self.state.x,
+                    AddressBusModification::Y => self.state.y,
+                };
+
+                self.state.address_bus =
+                    self.state.address_bus.wrapping_add(u16::from(modification));
+            }
+            ExecutionStep::IndexAddressBus {
+                register,
+                always_fixup,
+            } => {
+                let index = match register {
+                    AddressBusModification::X => self.state.x,
+                    AddressBusModification::Y => self.state.y,
+                };
+
+                let [low, high] = self.state.address_bus.to_le_bytes();
+                let uncorrected = u16::from_le_bytes([low.wrapping_add(index), high]);
+                self.state.address_bus = self.state.address_bus.wrapping_add(u16::from(index));
+
+                if always_fixup || uncorrected != self.state.address_bus {
+                    self.dummy_read(uncorrected);
+
+                    return true;
+                }
+            }
+            ExecutionStep::SetAddressBus(address) => {
+                self.state.address_bus = address;
+            }
+            ExecutionStep::DummyRead => {
+                self.dummy_read(self.state.address_bus);
+
+                return true;
+            }
+            ExecutionStep::DummyReadFrom(address) => {
+                self.dummy_read(address);
+
+                return true;
+            }
+            ExecutionStep::DummyWrite(data) => {
+                let _ = self.address_space.write_le_value(
+                    self.state.address_bus as usize,
+                    self.timestamp,
+                    Some(&mut self.address_space_cache),
+                    data,
+                );
+
+                return true;
+            }
+            ExecutionStep::PullStack => {
+                let byte = self.pull_stack();
+                self.state.latch.push(byte);
+
+                return true;
+            }
+            ExecutionStep::SelectInterruptVector(vector) => {
+                self.state.address_bus = self.interrupt_vector(vector);
+
+                // The handler always gets to run its first instruction
+                self.state.interrupt_polls = Default::default();
+                self.suppress_interrupt_polling(2);
+            }
+            ExecutionStep::Interpret => {
+                let instruction = self.state.next_instruction.take().unwrap();
+
+                let accesses_bus = interpret_accesses_bus(&instruction);
+                self.interpret_instruction(instruction);
+
+                self.state
+                    .execution_queue
+                    .push_back(ExecutionStep::FetchAndDecode);
+
+                // Without a bus access this is not a cycle of its own, the
+                // steps it queued are
+                if !self.config.cycle_accurate_bus || accesses_bus {
+                    return true;
+                }
+            }
+        }
+
+        false
+    }
+}
+
+/// If interpreting this instruction reads or writes memory itself, instead of
+/// only queuing steps that do
+fn interpret_accesses_bus(instruction: &Mos6502InstructionSet) -> bool {
+    match instruction.opcode {
+        Opcode::Mos6502(Mos6502Opcode::Jmp | Mos6502Opcode::Jsr) => false,
+        Opcode::Mos6502(
+            Mos6502Opcode::Pla | Mos6502Opcode::Plp | Mos6502Opcode::Rti | Mos6502Opcode::Rts,
+        )
+        | Opcode::Wdc65C02(Wdc65C02Opcode::Plx | Wdc65C02Opcode::Ply) => true,
+        _ => !matches!(
+            instruction.addressing_mode,
+            None | Some(AddressingMode::Mos6502(Mos6502AddressingMode::Accumulator))
+        ),
+    }
+}
+
+/// If interpreting this instruction ends in a write to memory
+fn interpret_writes_bus(instruction: &Mos6502InstructionSet) -> bool {
+    matches!(
+        instruction.opcode,
+        Opcode::Mos6502(
+            Mos6502Opcode::Sta | Mos6502Opcode::Stx | Mos6502Opcode::Sty | Mos6502Opcode::Sax
+        )
+    ) && interpret_accesses_bus(instruction)
+}
+
 impl Component for Mos6502 {
     fn snapshot_version(&self) -> Option<ComponentVersion> {
         Some(0)
@@ -524,6 +1115,7 @@ impl Component for Mos6502 {
             &Snapshot {
                 state: self.state.clone(),
                 rdy: self.rdy.load(),
+                dma_requests: self.dma.0.lock().unwrap().clone(),
             },
         )?;
 
@@ -541,6 +1133,7 @@ impl Component for Mos6502 {
 
                 self.state = snapshot.state;
                 self.rdy.store(snapshot.rdy);
+                *self.dma.0.lock().unwrap() = snapshot.dma_requests;
 
                 Ok(())
             }
@@ -551,163 +1144,38 @@ impl Component for Mos6502 {
     fn synchronize(&mut self, mut context: SynchronizationContext) {
         for now in context.allocate(self.period, None) {
             self.timestamp = now;
+            let cycle = self.state.cycle_counter;
+            self.state.cycle_counter += 1;
+
+            // The processor can only be halted on a read, so DMA waits out
+            // any writes in progress
+            if self.state.dma_cycles_remaining == 0
+                && !self.next_cycle_writes()
+                && let Some(request) = self.dma.take()
+            {
+                self.state.dma_cycles_remaining = request.stall_cycles(cycle);
+            }
 
-            if self.rdy.load() {
-                loop {
-                    match self.state.execution_queue.pop_front().unwrap() {
-                        ExecutionStep::Reset => {
-                            self.state.interrupt(RESET_VECTOR, false, false);
-
-                            break;
-                        }
-                        ExecutionStep::Jammed => {
-                            self.state.execution_queue.clear();
-                            self.state.execution_queue.push_back(ExecutionStep::Jammed);
-
-                            break;
-                        }
-                        ExecutionStep::Wait => {
-                            self.state.execution_queue.push_back(ExecutionStep::Wait);
-
-                            break;
-                        }
-                        ExecutionStep::FetchAndDecode => {
-                            if self.config.kind.supports_interrupts() {
-                                if self.nmi.interrupt_required() {
-                                    self.state.interrupt(NMI_VECTOR, false, true);
-                                } else if self.irq.interrupt_required()
-                                    && !self.state.flags.interrupt_disable
-                                {
-                                    self.state.interrupt(IRQ_VECTOR, true, true);
-                                } else {
-                                    self.fetch_and_decode();
-                                }
-                            } else {
-                                self.fetch_and_decode();
-                            }
-
-                            break;
-                        }
-                        ExecutionStep::LoadData => {
-                            let byte = self
-                                .address_space
-                                .read_le_value(
-                                    self.state.address_bus as usize,
-                                    self.timestamp,
-                                    Some(&mut self.address_space_cache),
-                                )
-                                .unwrap_or_default();
-
-                            self.state.latch.push(byte);
-                            self.state.address_bus = self.state.address_bus.wrapping_add(1);
-
-                            break;
-                        }
-                        ExecutionStep::LoadDataWithoutAdvancingPage => {
-                            let byte = self
-                                .address_space
-                                .read_le_value(
-                                    self.state.address_bus as usize,
-                                    self.timestamp,
-                                    Some(&mut self.address_space_cache),
-                                )
-                                .unwrap_or_default();
-
-                            self.state.latch.push(byte);
-
-                            let mut address_bus_contents = self.state.address_bus.to_le_bytes();
-                            address_bus_contents[0] = address_bus_contents[0].wrapping_add(1);
-                            self.state.address_bus = u16::from_le_bytes(address_bus_contents);
-
-                            break;
-                        }
-                        ExecutionStep::LoadDataFromConstant(data) => {
-                            self.state.latch.push(data);
-
-                            break;
-                        }
-                        ExecutionStep::StoreData(data) => {
-                            let _ = self.address_space.write_le_value(
-                                self.state.address_bus as usize,
-                                self.timestamp,
-                                Some(&mut self.address_space_cache),
-                                data,
-                            );
-                            self.state.address_bus = self.state.address_bus.wrapping_add(1);
-
-                            break;
-                        }
-                        ExecutionStep::PushStack(data) => {
-                            let _ = self.address_space.write_le_value(
-                                STACK_BASE_ADDRESS + self.state.stack as usize,
-                                self.timestamp,
-                                Some(&mut self.address_space_cache),
-                                data,
-                            );
-                            self.state.stack = self.state.stack.wrapping_sub(1);
-
-                            break;
-                        }
-                        ExecutionStep::LatchToAddressBus => {
-                            match self.state.latch.len() {
-                                1 => {
-                                    self.state.address_bus = u16::from(self.state.latch[0]);
-                                }
-                                2 => {
-                                    let latch = [self.state.latch[0], self.state.latch[1]];
-                                    self.state.address_bus = u16::from_le_bytes(latch);
-                                }
-                                _ => {
-                                    unreachable!()
-                                }
-                            }
-
-                            self.state.latch.clear();
-                        }
-                        // only used for interrupts
-                        ExecutionStep::LatchToProgramPointer => {
-                            assert!(self.state.latch.len() == 2);
-
-                            self.state.program =
-                                u16::from_le_bytes([self.state.latch[0], self.state.latch[1]]);
-                            self.state.latch.clear();
-                        }
-                        ExecutionStep::AddressBusToProgramPointer => {
-                            self.state.program = self.state.address_bus;
-
-                            break;
-                        }
-                        ExecutionStep::ModifyProgramPointer(value) => {
-                            self.state.program =
-                                self.state.program.wrapping_add_signed(i16::from(value));
-
-                            break;
-                        }
-                        ExecutionStep::MaskAddressBusToZeroPage => {
-                            self.state.address_bus %= PAGE_SIZE as u16;
-                        }
-                        ExecutionStep::ModifyAddressBus(modification) => {
-                            let modification = match modification {
-                                AddressBusModification::X => self.state.x,
-                                AddressBusModification::Y => self.state.y,
-                            };
-
-                            self.state.address_bus =
-                                self.state.address_bus.wrapping_add(u16::from(modification));
-                        }
-                        ExecutionStep::Interpret => {
-                            let instruction = self.state.next_instruction.take().unwrap();
+            if self.state.dma_cycles_remaining != 0 {
+                self.state.dma_cycles_remaining -= 1;
 
-                            self.interpret_instruction(instruction);
+                continue;
+            }
 
-                            self.state
-                                .execution_queue
-                                .push_back(ExecutionStep::FetchAndDecode);
+            if self.rdy.load() {
+                loop {
+                    let step = self.state.execution_queue.pop_front().unwrap();
 
-                            break;
-                        }
+                    if self.execute_step(step) {
+                        break;
                     }
                 }
+
+                if self.config.cycle_accurate_bus {
+                    self.settle();
+                }
+
+                self.poll_interrupts();
             }
         }
     }
@@ -732,6 +1200,7 @@ impl<P: Platform> ComponentConfig<P> for Mos6502Config {
 
         Ok(Mos6502 {
             rdy: Arc::default(),
+            dma: Arc::default(),
             irq: Arc::default(),
             nmi: Arc::default(),
             state: ProcessorState::default(),
@@ -748,24 +1217,73 @@ impl<P: Platform> ComponentConfig<P> for Mos6502Config {
 pub struct Snapshot {
     state: ProcessorState,
     rdy: bool,
+    #[serde(default)]
+    dma_requests: VecDeque<DmaRequest>,
 }
 
+/// IRQ is level triggered and wired-OR, so it is held low for as long as any
+/// source pulls it low
 #[derive(Debug)]
-pub struct IrqFlag(AtomicBool);
+pub struct IrqFlag {
+    /// One bit per source currently pulling the line low
+    asserted: AtomicU32,
+    /// Bit handed out to the next source
+    next_source: AtomicU32,
+}
 
 impl Default for IrqFlag {
     fn default() -> Self {
-        Self(AtomicBool::new(true))
+        Self {
+            asserted: AtomicU32::new(0),
+            // The first bit belongs to [Self::store]
+            next_source: AtomicU32::new(1),
+        }
     }
 }
 
 impl IrqFlag {
+    /// Drive the line as the default source, for components that are the only
+    /// thing connected to it
     pub fn store(&self, irq: bool) {
-        self.0.store(irq, Ordering::Release);
+        self.store_masked(1, irq);
+    }
+
+    /// Get a source of its own to drive the line with, so it does not
+    /// release the line out from under anything else holding it low
+    pub fn source(self: &Arc<Self>) -> IrqSource {
+        let bit = self.next_source.fetch_add(1, Ordering::Relaxed);
+        assert!(bit < u32::BITS, "Too many IRQ sources");
+
+        IrqSource {
+            flag: self.clone(),
+            mask: 1 << bit,
+        }
+    }
+
+    fn store_masked(&self, mask: u32, irq: bool) {
+        // IRQ is active low
+        if irq {
+            self.asserted.fetch_and(!mask, Ordering::AcqRel);
+        } else {
+            self.asserted.fetch_or(mask, Ordering::AcqRel);
+        }
     }
 
     pub fn interrupt_required(&self) -> bool {
-        !self.0.load(Ordering::Acquire)
+        self.asserted.load(Ordering::Acquire) != 0
+    }
+}
+
+/// One of the things connected to the IRQ line
+#[derive(Debug)]
+pub struct IrqSource {
+    flag: Arc<IrqFlag>,
+    mask: u32,
+}
+
+impl IrqSource {
+    pub fn store(&self, irq: bool) {
+        self.flag.store_masked(self.mask, irq);
     }
 }
 
@@ -798,13 +1316,56 @@ impl NmiFlag {
 }
 
 impl ProcessorState {
-    pub fn interrupt(&mut self, vector: u16, break_status: bool, save_current_state: bool) {
-        let vector = vector.to_le_bytes();
+    pub fn interrupt(
+        &mut self,
+        vector: u16,
+        break_status: bool,
+        save_current_state: bool,
+        cycle_accurate_bus: bool,
+    ) {
+        // The pushed flags keep the interrupt disable flag as it was, so
+        // returning from the handler unmasks IRQ again
         let mut flags = self.flags;
-
         flags.break_ = break_status;
         flags.undocumented = true;
-        flags.interrupt_disable = true;
+
+        self.flags.interrupt_disable = true;
+        self.interrupt_polls = Default::default();
+
+        if cycle_accurate_bus {
+            self.execution_queue.extend([
+                ExecutionStep::DummyReadFrom(self.program),
+                ExecutionStep::DummyReadFrom(self.program),
+            ]);
+
+            if save_current_state {
+                let program_pointer = self.program.to_le_bytes();
+
+                self.execution_queue.extend([
+                    ExecutionStep::PushStack(program_pointer[1]),
+                    ExecutionStep::PushStack(program_pointer[0]),
+                    ExecutionStep::PushStack(flags.to_byte()),
+                ]);
+            } else {
+                // Reset goes through the motions of pushing with writes suppressed
+                for _ in 0..3 {
+                    self.execution_queue.push_back(ExecutionStep::DummyReadFrom(
+                        (STACK_BASE_ADDRESS + self.stack as usize) as u16,
+                    ));
+                    self.stack = self.stack.wrapping_sub(1);
+                }
+            }
+
+            self.execution_queue.extend([
+                ExecutionStep::SelectInterruptVector(vector),
+                ExecutionStep::LoadData,
+                ExecutionStep::LoadData,
+                ExecutionStep::LatchToProgramPointer,
+                ExecutionStep::FetchAndDecode,
+            ]);
+
+            return;
+        }
 
         if save_current_state {
             let program_pointer = self.program.to_le_bytes();
@@ -817,9 +1378,7 @@ impl ProcessorState {
         }
 
         self.execution_queue.extend([
-            ExecutionStep::LoadDataFromConstant(vector[0]),
-            ExecutionStep::LoadDataFromConstant(vector[1]),
-            ExecutionStep::LatchToAddressBus,
+            ExecutionStep::SelectInterruptVector(vector),
             ExecutionStep::LoadData,
             ExecutionStep::LoadData,
             ExecutionStep::LatchToProgramPointer,
@@ -1 +1,2 @@
 mod mos6502;
+mod single_step;
@@ -0,0 +1,468 @@
+//! Checks the cycle accurate bus mode against hand worked cycle tables
+
+use std::{
+    ops::RangeInclusive,
+    sync::{Arc, Mutex},
+};
+
+use BusAccessKind::{Read, Write};
+use fluxemu_runtime::{
+    component::{Component, ComponentConfig},
+    machine::{Machine, builder::ComponentBuilder},
+    memory::{Address, AddressSpaceId, MemoryError},
+    path::FluxEmuPath,
+    platform::Platform,
+    scheduler::{Frequency, Period},
+};
+use serde::Deserialize;
+
+use crate::{ExecutionStep, FlagRegister, Mos6502, Mos6502Config, Mos6502Kind};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum BusAccessKind {
+    Read,
+    Write,
+}
+
+/// Laid out the same way as the single step test vectors
+pub(crate) type BusAccess = (u16, u8, BusAccessKind);
+
+/// Flat 64K of memory that remembers every access with side effects
+#[derive(Debug)]
+pub(crate) struct BusRecorder {
+    memory: Vec<u8>,
+    log: Mutex<Vec<BusAccess>>,
+}
+
+impl BusRecorder {
+    pub(crate) fn take_log(&self) -> Vec<BusAccess> {
+        std::mem::take(&mut self.log.lock().unwrap())
+    }
+}
+
+impl Component for BusRecorder {
+    fn memory_read(
+        &self,
+        address: Address,
+        _address_space: AddressSpaceId,
+        avoid_side_effects: bool,
+        buffer: &mut [u8],
+    ) -> Result<(), MemoryError> {
+        buffer.copy_from_slice(&self.memory[address..address + buffer.len()]);
+
+        if !avoid_side_effects {
+            let mut log = self.log.lock().unwrap();
+
+            for (offset, value) in buffer.iter().enumerate() {
+                log.push(((address + offset) as u16, *value, BusAccessKind::Read));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn memory_write(
+        &mut self,
+        address: Address,
+        _address_space: AddressSpaceId,
+        buffer: &[u8],
+    ) -> Result<(), MemoryError> {
+        self.memory[address..address + buffer.len()].copy_from_slice(buffer);
+
+        let log = self.log.get_mut().unwrap();
+
+        for (offset, value) in buffer.iter().enumerate() {
+            log.push(((address + offset) as u16, *value, BusAccessKind::Write));
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct BusRecorderConfig {
+    assigned_address_space: AddressSpaceId,
+}
+
+impl<P: Platform> ComponentConfig<P> for BusRecorderConfig {
+    type Component = BusRecorder;
+
+    fn build_component(
+        self,
+        component_builder: ComponentBuilder<P, Self::Component>,
+    ) -> Result<Self::Component, Box<dyn std::error::Error>> {
+        const RANGE: RangeInclusive<Address> = 0x0000..=0xffff;
+
+        component_builder.memory_map_component(self.assigned_address_space, RANGE);
+
+        Ok(BusRecorder {
+            memory: vec![0; RANGE.end() + 1],
+            log: Mutex::default(),
+        })
+    }
+}
+
+pub(crate) struct BusTestMachine {
+    pub machine: Arc<Machine>,
+    pub cpu: FluxEmuPath,
+    pub recorder: FluxEmuPath,
+    pub address_space: AddressSpaceId,
+}
+
+/// A processor of `kind` on a flat 64K of recorded memory, clocked once a
+/// second
+pub(crate) fn bus_test_boilerplate(kind: Mos6502Kind, cycle_accurate_bus: bool) -> BusTestMachine {
+    let (machine, address_space) = Machine::build_test_minimal().insert_address_space(16);
+
+    let (machine, cpu) = machine.insert_component(
+        "mos6502",
+        Mos6502Config {
+            frequency: Frequency::ONE,
+            assigned_address_space: address_space,
+            kind,
+            broken_ror: false,
+            cycle_accurate_bus,
+        },
+    );
+
+    let (machine, recorder) = machine.insert_component(
+        "memory",
+        BusRecorderConfig {
+            assigned_address_space: address_space,
+        },
+    );
+
+    BusTestMachine {
+        machine: machine.build(()),
+        cpu,
+        recorder,
+        address_space,
+    }
+}
+
+#[derive(Debug, Default)]
+struct Registers {
+    program: u16,
+    stack: u8,
+    a: u8,
+    x: u8,
+    y: u8,
+    flags: u8,
+}
+
+/// Run a single instruction and check every cycle of bus activity it caused
+fn assert_bus_activity(
+    initial: Registers,
+    memory: &[(u16, u8)],
+    expected: &[BusAccess],
+    expected_program: u16,
+) {
+    let test = bus_test_boilerplate(Mos6502Kind::Mos6502, true);
+    let address_space = test.machine.address_spaces(test.address_space).unwrap();
+
+    for (address, value) in memory {
+        address_space
+            .write(*address as usize, test.machine.now(), None, &[*value])
+            .unwrap();
+    }
+
+    test.machine
+        .interact_mut::<Mos6502, _>(&test.cpu, |component| {
+            component.state.program = initial.program;
+            component.state.stack = initial.stack;
+            component.state.a = initial.a;
+            component.state.x = initial.x;
+            component.state.y = initial.y;
+            component.state.flags = FlagRegister::from_byte(initial.flags);
+            component.state.execution_queue.clear();
+            component
+                .state
+                .execution_queue
+                .push_back(ExecutionStep::FetchAndDecode);
+        })
+        .unwrap();
+
+    test.machine
+        .interact::<BusRecorder, _>(&test.recorder, |recorder| recorder.take_log())
+        .unwrap();
+
+    test.machine.run(Period::from_num(expected.len()));
+
+    let log = test
+        .machine
+        .interact::<BusRecorder, _>(&test.recorder, |recorder| recorder.take_log())
+        .unwrap();
+    assert_eq!(log, expected);
+
+    test.machine
+        .interact::<Mos6502, _>(&test.cpu, |component| {
+            assert_eq!(component.state.program, expected_program);
+            assert_eq!(
+                component.state.execution_queue.front(),
+                Some(&ExecutionStep::FetchAndDecode)
+            );
+        })
+        .unwrap();
+}
+
+#[test]
+fn implied_reads_next_byte() {
+    // inx
+    assert_bus_activity(
+        Registers {
+            program: 0x0200,
+            ..Default::default()
+        },
+        &[(0x0200, 0xe8)],
+        &[(0x0200, 0xe8, Read), (0x0201, 0x00, Read)],
+        0x0201,
+    );
+}
+
+#[test]
+fn absolute_indexed_read_without_page_cross() {
+    // lda $1200,x
+    assert_bus_activity(
+        Registers {
+            program: 0x0200,
+            x: 0x01,
+            ..Default::default()
+        },
+        &[
+            (0x0200, 0xbd),
+            (0x0201, 0x00),
+            (0x0202, 0x12),
+            (0x1201, 0x42),
+        ],
+        &[
+            (0x0200, 0xbd, Read),
+            (0x0201, 0x00, Read),
+            (0x0202, 0x12, Read),
+            (0x1201, 0x42, Read),
+        ],
+        0x0203,
+    );
+}
+
+#[test]
+fn absolute_indexed_read_with_page_cross() {
+    // lda $12f0,x
+    assert_bus_activity(
+        Registers {
+            program: 0x0200,
+            x: 0x20,
+            ..Default::default()
+        },
+        &[
+            (0x0200, 0xbd),
+            (0x0201, 0xf0),
+            (0x0202, 0x12),
+            (0x1310, 0x42),
+        ],
+        &[
+            (0x0200, 0xbd, Read),
+            (0x0201, 0xf0, Read),
+            (0x0202, 0x12, Read),
+            (0x1210, 0x00, Read),
+            (0x1310, 0x42, Read),
+        ],
+        0x0203,
+    );
+}
+
+#[test]
+fn indirect_indexed_read_with_page_cross() {
+    // lda ($10),y
+    assert_bus_activity(
+        Registers {
+            program: 0x0200,
+            y: 0x20,
+            ..Default::default()
+        },
+        &[
+            (0x0200, 0xb1),
+            (0x0201, 0x10),
+            (0x0010, 0xf0),
+            (0x0011, 0x12),
+            (0x1310, 0x42),
+        ],
+        &[
+            (0x0200, 0xb1, Read),
+            (0x0201, 0x10, Read),
+            (0x0010, 0xf0, Read),
+            (0x0011, 0x12, Read),
+            (0x1210, 0x00, Read),
+            (0x1310, 0x42, Read),
+        ],
+        0x0202,
+    );
+}
+
+#[test]
+fn absolute_indexed_store_always_fixes_up() {
+    // sta $1200,x
+    assert_bus_activity(
+        Registers {
+            program: 0x0200,
+            a: 0x99,
+            x: 0x01,
+            ..Default::default()
+        },
+        &[(0x0200, 0x9d), (0x0201, 0x00), (0x0202, 0x12)],
+        &[
+            (0x0200, 0x9d, Read),
+            (0x0201, 0x00, Read),
+            (0x0202, 0x12, Read),
+            (0x1201, 0x00, Read),
+            (0x1201, 0x99, Write),
+        ],
+        0x0203,
+    );
+}
+
+#[test]
+fn zero_page_indexed_reads_base() {
+    // lda $10,x
+    assert_bus_activity(
+        Registers {
+            program: 0x0200,
+            x: 0x05,
+            ..Default::default()
+        },
+        &[(0x0200, 0xb5), (0x0201, 0x10)],
+        &[
+            (0x0200, 0xb5, Read),
+            (0x0201, 0x10, Read),
+            (0x0010, 0x00, Read),
+            (0x0015, 0x00, Read),
+        ],
+        0x0202,
+    );
+}
+
+#[test]
+fn read_modify_write_writes_twice() {
+    // inc $10
+    assert_bus_activity(
+        Registers {
+            program: 0x0200,
+            ..Default::default()
+        },
+        &[(0x0200, 0xe6), (0x0201, 0x10), (0x0010, 0x7f)],
+        &[
+            (0x0200, 0xe6, Read),
+            (0x0201, 0x10, Read),
+            (0x0010, 0x7f, Read),
+            (0x0010, 0x7f, Write),
+            (0x0010, 0x80, Write),
+        ],
+        0x0202,
+    );
+}
+
+#[test]
+fn pull_reads_stack_before_incrementing() {
+    // pla
+    assert_bus_activity(
+        Registers {
+            program: 0x0200,
+            stack: 0xfd,
+            ..Default::default()
+        },
+        &[(0x0200, 0x68), (0x01fe, 0x55)],
+        &[
+            (0x0200, 0x68, Read),
+            (0x0201, 0x00, Read),
+            (0x01fd, 0x00, Read),
+            (0x01fe, 0x55, Read),
+        ],
+        0x0201,
+    );
+}
+
+#[test]
+fn jsr_pushes_before_fetching_high_byte() {
+    // jsr $1234
+    assert_bus_activity(
+        Registers {
+            program: 0x0200,
+            stack: 0xfd,
+            ..Default::default()
+        },
+        &[(0x0200, 0x20), (0x0201, 0x34), (0x0202, 0x12)],
+        &[
+            (0x0200, 0x20, Read),
+            (0x0201, 0x34, Read),
+            (0x01fd, 0x00, Read),
+            (0x01fd, 0x02, Write),
+            (0x01fc, 0x02, Write),
+            (0x0202, 0x12, Read),
+        ],
+        0x1234,
+    );
+}
+
+#[test]
+fn rts_reads_return_address_before_incrementing() {
+    // rts
+    assert_bus_activity(
+        Registers {
+            program: 0x0200,
+            stack: 0xfb,
+            ..Default::default()
+        },
+        &[(0x0200, 0x60), (0x01fc, 0x02), (0x01fd, 0x02)],
+        &[
+            (0x0200, 0x60, Read),
+            (0x0201, 0x00, Read),
+            (0x01fb, 0x00, Read),
+            (0x01fc, 0x02, Read),
+            (0x01fd, 0x02, Read),
+            (0x0202, 0x00, Read),
+        ],
+        0x0203,
+    );
+}
+
+#[test]
+fn taken_branch_across_page() {
+    // bne +$20
+    assert_bus_activity(
+        Registers {
+            program: 0x02f0,
+            ..Default::default()
+        },
+        &[(0x02f0, 0xd0), (0x02f1, 0x20)],
+        &[
+            (0x02f0, 0xd0, Read),
+            (0x02f1, 0x20, Read),
+            (0x02f2, 0x00, Read),
+            (0x0212, 0x00, Read),
+        ],
+        0x0312,
+    );
+}
+
+#[test]
+fn brk_skips_padding_byte() {
+    // brk
+    assert_bus_activity(
+        Registers {
+            program: 0x0200,
+            stack: 0xfd,
+            ..Default::default()
+        },
+        &[(0x0200, 0x00), (0xfffe, 0x00), (0xffff, 0x80)],
+        &[
+            (0x0200, 0x00, Read),
+            (0x0201, 0x00, Read),
+            (0x01fd, 0x02, Write),
+            (0x01fc, 0x02, Write),
+            (0x01fb, 0x30, Write),
+            (0xfffe, 0x00, Read),
+            (0xffff, 0x80, Read),
+        ],
+        0x8000,
+    );
+}
@@ -0,0 +1,116 @@
+use fluxemu_runtime::scheduler::Period;
+
+use crate::{DmaRequest, ExecutionStep, Mos6502, tests::mos6502::instruction_test_boilerplate};
+
+const OAM_DMA: DmaRequest = DmaRequest {
+    setup_cycles: 1,
+    transfer_cycles: 512,
+    get_aligned: true,
+};
+
+fn stalled_adc(already_executed_cycles: u64) {
+    let (machine, cpu, address_space) = instruction_test_boilerplate();
+    let address_space = machine.address_spaces(address_space).unwrap();
+
+    machine
+        .interact_mut::<Mos6502, _>(&cpu, |component| {
+            component.state.cycle_counter = already_executed_cycles;
+            component.state.execution_queue.clear();
+            component
+                .state
+                .execution_queue
+                .push_back(ExecutionStep::FetchAndDecode);
+            component.dma().request(OAM_DMA);
+        })
+        .unwrap();
+
+    address_space
+        .write(0x0000, machine.now(), None, &[0x69, 0x01])
+        .unwrap();
+
+    let stall_cycles = OAM_DMA.stall_cycles(already_executed_cycles);
+    machine.run(Period::from_num(stall_cycles));
+
+    machine
+        .interact::<Mos6502, _>(&cpu, |component| {
+            assert_eq!(component.state.program, 0x0);
+        })
+        .unwrap();
+
+    // Should be done in 2 cycles after the stall
+    machine.run(Period::from_num(2));
+
+    machine
+        .interact::<Mos6502, _>(&cpu, |component| {
+            assert_eq!(component.state.a, 0x01);
+            assert_eq!(component.state.program, 0x2);
+        })
+        .unwrap();
+}
+
+#[test]
+pub fn oam_dma_stall_length() {
+    // Transfer would start on a put cycle so an alignment cycle is needed
+    assert_eq!(OAM_DMA.stall_cycles(0), 514);
+    assert_eq!(OAM_DMA.stall_cycles(1), 513);
+}
+
+#[test]
+pub fn dma_stalls_processor_on_get_cycle() {
+    stalled_adc(1);
+}
+
+#[test]
+pub fn dma_stalls_processor_on_put_cycle() {
+    stalled_adc(0);
+}
+
+#[test]
+pub fn dma_waits_out_write_cycles() {
+    let (machine, cpu, address_space) = instruction_test_boilerplate();
+    let address_space = machine.address_spaces(address_space).unwrap();
+
+    // Halfway through pushing to the stack, as an interrupt or JSR would
+    machine
+        .interact_mut::<Mos6502, _>(&cpu, |component| {
+            component.state.cycle_counter = 1;
+            component.state.stack = 0xff;
+            component.state.execution_queue.clear();
+            component.state.execution_queue.extend([
+                ExecutionStep::PushStack(0x12),
+                ExecutionStep::PushStack(0x34),
+                ExecutionStep::FetchAndDecode,
+            ]);
+            component.dma().request(OAM_DMA);
+        })
+        .unwrap();
+
+    address_space
+        .write(0x0000, machine.now(), None, &[0x69, 0x01])
+        .unwrap();
+
+    machine.run(Period::from_num(2));
+
+    let mut stack = [0; 2];
+    address_space
+        .read(0x01fe, machine.now(), None, &mut stack)
+        .unwrap();
+    assert_eq!(stack, [0x34, 0x12]);
+
+    // The halt only lands on the opcode fetch after the pushes
+    machine.run(Period::from_num(OAM_DMA.stall_cycles(3)));
+
+    machine
+        .interact::<Mos6502, _>(&cpu, |component| {
+            assert_eq!(component.state.program, 0x0);
+        })
+        .unwrap();
+
+    machine.run(Period::from_num(2));
+
+    machine
+        .interact::<Mos6502, _>(&cpu, |component| {
+            assert_eq!(component.state.a, 0x01);
+        })
+        .unwrap();
+}
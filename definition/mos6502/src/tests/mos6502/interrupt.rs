@@ -0,0 +1,269 @@
+//! Checks when interrupts are taken, relative to the instructions around them
+
+use std::sync::Arc;
+
+use fluxemu_runtime::scheduler::Period;
+
+use crate::{
+    ExecutionStep, FlagRegister, IrqFlag, Mos6502, Mos6502Kind, NmiFlag,
+    tests::mos6502::bus::{BusTestMachine, bus_test_boilerplate},
+};
+
+const IRQ_HANDLER: u16 = 0x8000;
+const NMI_HANDLER: u16 = 0x9000;
+const NOP: u8 = 0xea;
+
+struct InterruptTest {
+    test: BusTestMachine,
+    irq: Arc<IrqFlag>,
+    nmi: Arc<NmiFlag>,
+}
+
+impl InterruptTest {
+    /// Runs `program` from 0x0200 with the stack at 0xfd, with both handlers
+    /// being a run of NOPs
+    fn new(program: &[u8], flags: u8) -> Self {
+        let test = bus_test_boilerplate(Mos6502Kind::Mos6502, true);
+        let address_space = test.machine.address_spaces(test.address_space).unwrap();
+        let now = test.machine.now();
+
+        address_space.write(0x0200, now, None, program).unwrap();
+        address_space
+            .write(0x0200 + program.len(), now, None, &[NOP; 16])
+            .unwrap();
+        address_space
+            .write(IRQ_HANDLER as usize, now, None, &[NOP; 16])
+            .unwrap();
+        address_space
+            .write(NMI_HANDLER as usize, now, None, &[NOP; 16])
+            .unwrap();
+        address_space
+            .write(0xfffa, now, None, &NMI_HANDLER.to_le_bytes())
+            .unwrap();
+        address_space
+            .write(0xfffe, now, None, &IRQ_HANDLER.to_le_bytes())
+            .unwrap();
+
+        let (irq, nmi) = test
+            .machine
+            .interact_mut::<Mos6502, _>(&test.cpu, move |component| {
+                component.state.program = 0x0200;
+                component.state.stack = 0xfd;
+                component.state.flags = FlagRegister::from_byte(flags);
+                component.state.execution_queue.clear();
+                component
+                    .state
+                    .execution_queue
+                    .push_back(ExecutionStep::FetchAndDecode);
+
+                (component.irq(), component.nmi())
+            })
+            .unwrap();
+
+        Self { test, irq, nmi }
+    }
+
+    fn run(&self, cycles: u32) {
+        self.test.machine.run(Period::from_num(cycles));
+    }
+
+    fn program(&self) -> u16 {
+        self.test
+            .machine
+            .interact::<Mos6502, _>(&self.test.cpu, |component| component.state.program)
+            .unwrap()
+    }
+
+    /// The return address and flags pushed by the first interrupt, if one
+    /// was taken
+    fn pushed(&self) -> Option<(u16, u8)> {
+        let stack = self
+            .test
+            .machine
+            .interact::<Mos6502, _>(&self.test.cpu, |component| component.state.stack)
+            .unwrap();
+
+        if stack == 0xfd {
+            return None;
+        }
+
+        let address_space = self
+            .test
+            .machine
+            .address_spaces(self.test.address_space)
+            .unwrap();
+        let mut buffer = [0; 3];
+        address_space
+            .read(0x01fb, self.test.machine.now(), None, &mut buffer)
+            .unwrap();
+
+        Some((u16::from_le_bytes([buffer[1], buffer[2]]), buffer[0]))
+    }
+}
+
+#[test]
+fn irq_polled_on_second_to_last_cycle() {
+    // lda $1234
+    let program = [0xad, 0x34, 0x12];
+
+    // Asserted during the third cycle, in time for the poll
+    let early = InterruptTest::new(&program, 0x00);
+    early.run(2);
+    early.irq.store(false);
+    early.run(20);
+    assert_eq!(early.pushed().map(|(address, _)| address), Some(0x0203));
+
+    // Asserted during the last cycle, so the next instruction runs first
+    let late = InterruptTest::new(&program, 0x00);
+    late.run(3);
+    late.irq.store(false);
+    late.run(20);
+    assert_eq!(late.pushed().map(|(address, _)| address), Some(0x0204));
+}
+
+#[test]
+fn irq_pushes_flags_as_they_were_and_masks_itself() {
+    let test = InterruptTest::new(&[NOP], 0x00);
+    test.irq.store(false);
+    test.run(30);
+
+    // Taken once, with the break flag clear and IRQ unmasked in the copy
+    assert_eq!(test.pushed(), Some((0x0201, 0x20)));
+    assert!((IRQ_HANDLER..IRQ_HANDLER + 16).contains(&test.program()));
+
+    test.test
+        .machine
+        .interact::<Mos6502, _>(&test.test.cpu, |component| {
+            assert_eq!(component.state.stack, 0xfa);
+            assert!(component.state.flags.interrupt_disable);
+        })
+        .unwrap();
+}
+
+#[test]
+fn cli_takes_effect_after_next_instruction() {
+    // cli
+    let test = InterruptTest::new(&[0x58], 0x04);
+    test.irq.store(false);
+    test.run(20);
+
+    assert_eq!(test.pushed().map(|(address, _)| address), Some(0x0202));
+}
+
+#[test]
+fn sei_lets_pending_irq_through() {
+    // sei
+    let test = InterruptTest::new(&[0x78], 0x00);
+    test.irq.store(false);
+    test.run(20);
+
+    assert_eq!(test.pushed(), Some((0x0201, 0x24)));
+}
+
+#[test]
+fn plp_takes_effect_after_next_instruction() {
+    // plp, pulling flags with interrupts enabled
+    let test = InterruptTest::new(&[0x28], 0x04);
+    test.test
+        .machine
+        .address_spaces(test.test.address_space)
+        .unwrap()
+        .write(0x01fe, test.test.machine.now(), None, &[0x00])
+        .unwrap();
+    test.irq.store(false);
+    test.run(20);
+
+    // The pull moved the stack pointer up by one before the interrupt pushed
+    let address_space = test
+        .test
+        .machine
+        .address_spaces(test.test.address_space)
+        .unwrap();
+    let mut buffer = [0; 2];
+    address_space
+        .read(0x01fd, test.test.machine.now(), None, &mut buffer)
+        .unwrap();
+
+    assert_eq!(u16::from_le_bytes(buffer), 0x0202);
+}
+
+#[test]
+fn taken_branch_without_page_cross_delays_irq() {
+    // bne +$00
+    let test = InterruptTest::new(&[0xd0, 0x00], 0x00);
+    test.run(1);
+    test.irq.store(false);
+    test.run(20);
+
+    assert_eq!(test.pushed().map(|(address, _)| address), Some(0x0203));
+}
+
+#[test]
+fn taken_branch_across_page_polls_before_fixup() {
+    // bne -$10, from the start of a page
+    let test = InterruptTest::new(&[0xd0, 0xf0], 0x00);
+    test.run(2);
+    test.irq.store(false);
+    test.run(20);
+
+    assert_eq!(test.pushed().map(|(address, _)| address), Some(0x01f2));
+}
+
+#[test]
+fn nmi_hijacks_brk() {
+    // brk
+    let test = InterruptTest::new(&[0x00], 0x00);
+    test.run(2);
+    test.nmi.store(false);
+    test.run(5);
+
+    // The state pushed is still the one of the BRK
+    assert_eq!(test.pushed(), Some((0x0202, 0x30)));
+    assert_eq!(test.program(), NMI_HANDLER);
+
+    // And the NMI was used up
+    test.run(20);
+    assert_eq!(
+        test.test
+            .machine
+            .interact::<Mos6502, _>(&test.test.cpu, |component| component.state.stack)
+            .unwrap(),
+        0xfa
+    );
+}
+
+#[test]
+fn late_nmi_runs_after_first_handler_instruction() {
+    // brk
+    let test = InterruptTest::new(&[0x00], 0x00);
+    test.run(5);
+    test.nmi.store(false);
+    test.run(2);
+    assert_eq!(test.program(), IRQ_HANDLER);
+
+    test.run(2);
+    assert_eq!(test.program(), IRQ_HANDLER + 1);
+
+    test.run(7);
+    assert_eq!(test.program(), NMI_HANDLER);
+}
+
+#[test]
+fn irq_held_while_any_source_asserts_it() {
+    let test = InterruptTest::new(&[NOP; 4], 0x04);
+    let first = test.irq.source();
+    let second = test.irq.source();
+
+    first.store(false);
+    second.store(false);
+    second.store(true);
+    assert!(test.irq.interrupt_required());
+
+    first.store(true);
+    assert!(!test.irq.interrupt_required());
+
+    // The default source is just another one of them
+    test.irq.store(false);
+    first.store(true);
+    assert!(test.irq.interrupt_required());
+}
@@ -11,8 +11,18 @@ use rangemap::RangeInclusiveMap;
 use crate::{Mos6502Config, Mos6502Kind};
 
 mod adc;
+pub(crate) mod bus;
+mod dma;
+mod interrupt;
 
 fn instruction_test_boilerplate() -> (Arc<Machine>, FluxEmuPath, AddressSpaceId) {
+    kind_test_boilerplate(Mos6502Kind::Mos6502)
+}
+
+/// A processor of `kind` on a flat 64K of memory, clocked once a second
+pub(crate) fn kind_test_boilerplate(
+    kind: Mos6502Kind,
+) -> (Arc<Machine>, FluxEmuPath, AddressSpaceId) {
     let (machine, cpu_address_space) = Machine::build_test_minimal().insert_address_space(16);
 
     let (machine, cpu) = machine.insert_component(
@@ -20,8 +30,9 @@ fn instruction_test_boilerplate() -> (Arc<Machine>, FluxEmuPath, AddressSpaceId)
         Mos6502Config {
             frequency: Frequency::ONE,
             assigned_address_space: cpu_address_space,
-            kind: Mos6502Kind::Mos6502,
+            kind,
             broken_ror: false,
+            cycle_accurate_bus: false,
         },
     );
 
@@ -0,0 +1,296 @@
+//! Runs the per opcode single step JSON test vectors against every kind of
+//! processor
+//!
+//! Point `FLUXEMU_6502_SINGLE_STEP_TESTS` at a local copy of the vectors, laid
+//! out as they are upstream (`6502/v1/00.json`, `nes6502/v1/00.json`, ...).
+//! Kinds whose vectors cannot be found are skipped
+//!
+//! NMOS kinds are run a second time in cycle accurate bus mode, where every
+//! cycle of bus activity has to match too
+
+use std::{
+    env, fs,
+    panic::{AssertUnwindSafe, catch_unwind},
+    path::{Path, PathBuf},
+};
+
+use fluxemu_runtime::scheduler::Period;
+use serde::Deserialize;
+
+use crate::{
+    ExecutionStep, FlagRegister, Mos6502, Mos6502Kind,
+    tests::mos6502::bus::{BusAccess, BusRecorder, BusTestMachine, bus_test_boilerplate},
+};
+
+const TEST_VECTOR_DIRECTORY_VARIABLE: &str = "FLUXEMU_6502_SINGLE_STEP_TESTS";
+/// The break and unused bits do not exist in the actual register
+const FLAG_MASK: u8 = 0b1100_1111;
+
+#[derive(Debug, Deserialize)]
+struct ProcessorState {
+    pc: u16,
+    s: u8,
+    a: u8,
+    x: u8,
+    y: u8,
+    p: u8,
+    ram: Vec<(u16, u8)>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TestVector {
+    name: String,
+    initial: ProcessorState,
+    #[serde(rename = "final")]
+    expected: ProcessorState,
+    /// Bus activity for every cycle
+    cycles: Vec<BusAccess>,
+}
+
+#[derive(Debug)]
+struct OpcodeReport {
+    kind: Mos6502Kind,
+    cycle_accurate_bus: bool,
+    opcode: u8,
+    failed: usize,
+    total: usize,
+    first_failure: String,
+}
+
+fn vector_directory(kind: Mos6502Kind) -> Option<PathBuf> {
+    let directory = env::var_os(TEST_VECTOR_DIRECTORY_VARIABLE)?;
+
+    let subdirectory = match kind {
+        // The 6507 only lacks address lines and interrupts, neither of which the vectors use
+        Mos6502Kind::Mos6502 | Mos6502Kind::Mos6507 => "6502",
+        Mos6502Kind::Ricoh2A0x => "nes6502",
+        Mos6502Kind::Wdc65C02 => "wdc65c02",
+    };
+
+    let path = Path::new(&directory).join(subdirectory).join("v1");
+
+    path.is_dir().then_some(path)
+}
+
+/// Runs a single vector, returning what went wrong if anything
+///
+/// Only the memory the vector mentions is set up, so machines can be reused
+/// between vectors
+fn run_vector(
+    test: &BusTestMachine,
+    cycle_accurate_bus: bool,
+    vector: &TestVector,
+) -> Result<(), String> {
+    let BusTestMachine {
+        machine,
+        cpu,
+        recorder,
+        address_space,
+    } = test;
+    let address_space = machine.address_spaces(*address_space).unwrap();
+
+    for (address, value) in &vector.initial.ram {
+        address_space
+            .write(*address as usize, machine.now(), None, &[*value])
+            .unwrap();
+    }
+
+    machine
+        .interact_mut::<Mos6502, _>(cpu, |component| {
+            let initial = &vector.initial;
+
+            component.state.program = initial.pc;
+            component.state.stack = initial.s;
+            component.state.a = initial.a;
+            component.state.x = initial.x;
+            component.state.y = initial.y;
+            component.state.flags = FlagRegister::from_byte(initial.p);
+            component.state.latch.clear();
+            component.state.next_instruction = None;
+            component.state.execution_queue.clear();
+            component
+                .state
+                .execution_queue
+                .push_back(ExecutionStep::FetchAndDecode);
+        })
+        .unwrap();
+
+    machine
+        .interact::<BusRecorder, _>(recorder, |recorder| recorder.take_log())
+        .unwrap();
+
+    machine.run(Period::from_num(vector.cycles.len()));
+
+    let mut mismatches = Vec::default();
+
+    if cycle_accurate_bus {
+        let log = machine
+            .interact::<BusRecorder, _>(recorder, |recorder| recorder.take_log())
+            .unwrap();
+
+        if log != vector.cycles {
+            mismatches.push(format!(
+                "bus: expected {:02x?}, got {:02x?}",
+                vector.cycles, log
+            ));
+        }
+    }
+
+    machine
+        .interact::<Mos6502, _>(cpu, |component| {
+            let expected = &vector.expected;
+            let state = &component.state;
+
+            for (register, actual, expected) in [
+                ("a", state.a, expected.a),
+                ("x", state.x, expected.x),
+                ("y", state.y, expected.y),
+                ("s", state.stack, expected.s),
+                (
+                    "p",
+                    state.flags.to_byte() & FLAG_MASK,
+                    expected.p & FLAG_MASK,
+                ),
+            ] {
+                if actual != expected {
+                    mismatches.push(format!(
+                        "{register}: expected {expected:#04x}, got {actual:#04x}"
+                    ));
+                }
+            }
+
+            if state.program != expected.pc {
+                mismatches.push(format!(
+                    "pc: expected {:#06x}, got {:#06x}",
+                    expected.pc, state.program
+                ));
+            }
+
+            // Anything else means the instruction took a different amount of cycles
+            if state.execution_queue.front() != Some(&ExecutionStep::FetchAndDecode) {
+                mismatches.push(format!(
+                    "did not finish in {} cycles, next step is {:?}",
+                    vector.cycles.len(),
+                    state.execution_queue.front()
+                ));
+            }
+        })
+        .unwrap();
+
+    for (address, expected) in &vector.expected.ram {
+        let mut actual = [0];
+        address_space
+            .read(*address as usize, machine.now(), None, &mut actual)
+            .unwrap();
+
+        if actual[0] != *expected {
+            mismatches.push(format!(
+                "ram[{address:#06x}]: expected {expected:#04x}, got {:#04x}",
+                actual[0]
+            ));
+        }
+    }
+
+    if mismatches.is_empty() {
+        Ok(())
+    } else {
+        Err(mismatches.join(", "))
+    }
+}
+
+fn run_opcode(
+    kind: Mos6502Kind,
+    cycle_accurate_bus: bool,
+    opcode: u8,
+    vectors: &[TestVector],
+) -> Option<OpcodeReport> {
+    let mut failed = 0;
+    let mut first_failure = None;
+    let mut machine = bus_test_boilerplate(kind, cycle_accurate_bus);
+
+    for vector in vectors {
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            run_vector(&machine, cycle_accurate_bus, vector)
+        }))
+        .unwrap_or_else(|_| {
+            // Whatever state the machine was left in can't be trusted anymore
+            machine = bus_test_boilerplate(kind, cycle_accurate_bus);
+
+            Err("panicked".to_string())
+        });
+
+        if let Err(mismatch) = result {
+            failed += 1;
+            first_failure.get_or_insert_with(|| format!("\"{}\": {}", vector.name, mismatch));
+        }
+    }
+
+    first_failure.map(|first_failure| OpcodeReport {
+        kind,
+        cycle_accurate_bus,
+        opcode,
+        failed,
+        total: vectors.len(),
+        first_failure,
+    })
+}
+
+#[test]
+fn single_step_tests() {
+    let mut reports = Vec::default();
+
+    for kind in [
+        Mos6502Kind::Mos6502,
+        Mos6502Kind::Mos6507,
+        Mos6502Kind::Ricoh2A0x,
+        Mos6502Kind::Wdc65C02,
+    ] {
+        let Some(directory) = vector_directory(kind) else {
+            eprintln!("No single step test vectors found for {kind:?}, skipping");
+            continue;
+        };
+
+        for opcode in 0x00..=0xff {
+            let path = directory.join(format!("{opcode:02x}.json"));
+
+            if !path.is_file() {
+                continue;
+            }
+
+            let vectors: Vec<TestVector> =
+                serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+
+            reports.extend(run_opcode(kind, false, opcode, &vectors));
+
+            if kind != Mos6502Kind::Wdc65C02 {
+                reports.extend(run_opcode(kind, true, opcode, &vectors));
+            }
+        }
+    }
+
+    if reports.is_empty() {
+        return;
+    }
+
+    let report = reports
+        .iter()
+        .map(|report| {
+            format!(
+                "{:?}{} {:02x}: {}/{} failed, first was {}",
+                report.kind,
+                if report.cycle_accurate_bus {
+                    " (cycle accurate bus)"
+                } else {
+                    ""
+                },
+                report.opcode,
+                report.failed,
+                report.total,
+                report.first_failure
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    panic!("{} opcodes had mismatches:\n{}", reports.len(), report);
+}
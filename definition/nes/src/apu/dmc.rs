@@ -0,0 +1,154 @@
+use fluxemu_definition_mos6502::DmaRequest;
+use fluxemu_runtime::memory::Address;
+
+use crate::apu::Apu;
+
+const SAMPLE_ADDRESS_BASE: u16 = 0xc000;
+
+/// Halt, dummy read, optional alignment, then the sample byte get
+const SAMPLE_FETCH: DmaRequest = DmaRequest {
+    setup_cycles: 2,
+    transfer_cycles: 1,
+    get_aligned: true,
+};
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DmcChannel {
+    pub irq_enabled: bool,
+    pub loop_sample: bool,
+    pub rate_index: u8,
+    pub output_level: u8,
+    pub sample_address: u16,
+    pub sample_length: u16,
+    pub interrupt: bool,
+    /// Timer counting down in processor cycles
+    pub timer: u16,
+    pub current_address: u16,
+    pub bytes_remaining: u16,
+    pub sample_buffer: Option<u8>,
+    pub shift_register: u8,
+    pub bits_remaining: u8,
+    pub silence: bool,
+}
+
+impl DmcChannel {
+    pub fn restart(&mut self) {
+        self.current_address = self.sample_address;
+        self.bytes_remaining = self.sample_length;
+    }
+}
+
+impl Apu {
+    pub(super) fn dmc_write(&mut self, position: Address, byte: u8) {
+        let dmc = &mut self.dmc;
+
+        match position {
+            0 => {
+                dmc.irq_enabled = (0b1000_0000 & byte) != 0;
+                dmc.loop_sample = (0b0100_0000 & byte) != 0;
+                dmc.rate_index = 0b0000_1111 & byte;
+
+                if !dmc.irq_enabled {
+                    dmc.interrupt = false;
+                }
+            }
+            1 => {
+                dmc.output_level = 0b0111_1111 & byte;
+            }
+            2 => {
+                dmc.sample_address = SAMPLE_ADDRESS_BASE + u16::from(byte) * 64;
+            }
+            3 => {
+                dmc.sample_length = u16::from(byte) * 16 + 1;
+            }
+            _ => {
+                unreachable!()
+            }
+        }
+
+        self.update_irq();
+    }
+
+    pub(super) fn dmc_set_enabled(&mut self, enabled: bool) {
+        let dmc = &mut self.dmc;
+
+        dmc.interrupt = false;
+
+        if !enabled {
+            dmc.bytes_remaining = 0;
+        } else if dmc.bytes_remaining == 0 {
+            dmc.restart();
+        }
+
+        self.update_irq();
+    }
+
+    /// Runs the DMC for a single processor cycle
+    pub(super) fn dmc_clock(&mut self) {
+        if self.dmc.sample_buffer.is_none() && self.dmc.bytes_remaining != 0 {
+            self.dmc_fetch_sample();
+        }
+
+        let dmc = &mut self.dmc;
+
+        if dmc.timer != 0 {
+            dmc.timer -= 1;
+            return;
+        }
+
+        dmc.timer = self.dmc_rates[dmc.rate_index as usize] - 1;
+
+        if !dmc.silence {
+            if dmc.shift_register & 1 != 0 {
+                if dmc.output_level <= 125 {
+                    dmc.output_level += 2;
+                }
+            } else if dmc.output_level >= 2 {
+                dmc.output_level -= 2;
+            }
+        }
+
+        dmc.shift_register >>= 1;
+        dmc.bits_remaining = dmc.bits_remaining.saturating_sub(1);
+
+        if dmc.bits_remaining == 0 {
+            dmc.bits_remaining = 8;
+
+            match dmc.sample_buffer.take() {
+                Some(sample) => {
+                    dmc.silence = false;
+                    dmc.shift_register = sample;
+                }
+                None => {
+                    dmc.silence = true;
+                }
+            }
+        }
+    }
+
+    fn dmc_fetch_sample(&mut self) {
+        // The processor gets stalled for the fetch, but the byte is read immediately since
+        // nothing else can touch the bus in the meantime
+        self.processor_dma.request(SAMPLE_FETCH);
+
+        let dmc = &mut self.dmc;
+
+        let sample = self
+            .cpu_address_space
+            .read_le_value(dmc.current_address as usize, self.timestamp, None)
+            .unwrap_or_default();
+
+        dmc.sample_buffer = Some(sample);
+        dmc.current_address = dmc.current_address.checked_add(1).unwrap_or(0x8000);
+        dmc.bytes_remaining -= 1;
+
+        if dmc.bytes_remaining == 0 {
+            if dmc.loop_sample {
+                dmc.restart();
+            } else if dmc.irq_enabled {
+                dmc.interrupt = true;
+                self.update_irq();
+            }
+        }
+    }
+}
@@ -1,15 +1,23 @@
-use std::ops::RangeInclusive;
+use std::{marker::PhantomData, ops::RangeInclusive, sync::Arc};
 
+use bitvec::{prelude::Lsb0, view::BitView};
+use fluxemu_definition_mos6502::{DmaController, IrqSource, Mos6502};
 use fluxemu_range::ContiguousRange;
 use fluxemu_runtime::{
     component::{Component, ComponentConfig},
-    machine::builder::ComponentBuilder,
-    memory::{Address, AddressSpaceId, MemoryError},
+    machine::builder::{ComponentBuilder, SchedulerParticipation},
+    memory::{Address, AddressSpace, AddressSpaceId, MemoryError},
+    path::FluxEmuPath,
     platform::Platform,
+    scheduler::{Period, SynchronizationContext},
 };
 
-use crate::apu::pulse::PulseChannel;
+use crate::{
+    apu::{dmc::DmcChannel, pulse::PulseChannel},
+    ppu::region::Region,
+};
 
+mod dmc;
 mod pulse;
 
 const PULSE_1: RangeInclusive<Address> = 0x4000..=0x4003;
@@ -24,6 +32,21 @@ const FRAME_COUNTER: Address = 0x4017;
 #[derive(Debug)]
 pub struct Apu {
     pub pulse_channels: [PulseChannel; 2],
+    pub dmc: DmcChannel,
+    dmc_rates: [u16; 16],
+    cpu_address_space: Arc<AddressSpace>,
+    processor_dma: Arc<DmaController>,
+    /// The DMC's own connection to the processor IRQ line
+    dmc_irq: IrqSource,
+    timestamp: Period,
+    period: Period,
+}
+
+impl Apu {
+    fn update_irq(&self) {
+        // IRQ is active low
+        self.dmc_irq.store(!self.dmc.interrupt);
+    }
 }
 
 impl Component for Apu {
@@ -36,7 +59,12 @@ impl Component for Apu {
     ) -> Result<(), MemoryError> {
         match address {
             STATUS => {
-                todo!()
+                let buffer_bits = buffer.view_bits_mut::<Lsb0>();
+
+                buffer_bits.set(4, self.dmc.bytes_remaining != 0);
+                buffer_bits.set(7, self.dmc.interrupt);
+
+                Ok(())
             }
             _ => {
                 unreachable!()
@@ -61,7 +89,12 @@ impl Component for Apu {
                 self.pulse_write(1, address - PULSE_2.start(), byte);
             }
 
+            if DMC.contains(&address) {
+                self.dmc_write(address - DMC.start(), byte);
+            }
+
             if CONTROL == address {
+                self.dmc_set_enabled((byte & 0b0001_0000) != 0);
                 self.pulse_channels[1].enabled = (byte & 0b0000_0010) != 0;
                 self.pulse_channels[0].enabled = (byte & 0b0000_0001) != 0;
             }
@@ -69,21 +102,48 @@ impl Component for Apu {
 
         Ok(())
     }
+
+    fn synchronize(&mut self, mut context: SynchronizationContext) {
+        for now in context.allocate(self.period, None) {
+            self.timestamp = now;
+
+            self.dmc_clock();
+        }
+    }
+
+    fn needs_work(&self, delta: Period) -> bool {
+        delta >= self.period
+    }
 }
 
 #[derive(Debug)]
-pub struct ApuConfig {
+pub struct ApuConfig<R: Region> {
     pub cpu_address_space: AddressSpaceId,
+    pub processor: FluxEmuPath,
+    pub _phantom: PhantomData<R>,
 }
 
-impl<P: Platform> ComponentConfig<P> for ApuConfig {
+impl<R: Region, P: Platform> ComponentConfig<P> for ApuConfig<R> {
     type Component = Apu;
 
     fn build_component(
         self,
         component_builder: ComponentBuilder<P, Self::Component>,
     ) -> Result<Self::Component, Box<dyn std::error::Error>> {
+        let cpu_address_space = component_builder
+            .get_address_space(self.cpu_address_space)
+            .clone();
+
+        let processor_dma = component_builder
+            .interact::<Mos6502, _>(&self.processor, Mos6502::dma)
+            .unwrap();
+
+        let dmc_irq = component_builder
+            .interact::<Mos6502, _>(&self.processor, |processor| processor.irq().source())
+            .unwrap();
+
         component_builder
+            .set_scheduler_participation(SchedulerParticipation::SchedulerDriven)
             .memory_map_component_write(self.cpu_address_space, PULSE_1)
             .memory_map_component_write(self.cpu_address_space, PULSE_2)
             .memory_map_component_write(self.cpu_address_space, TRIANGLE)
@@ -95,6 +155,13 @@ impl<P: Platform> ComponentConfig<P> for ApuConfig {
 
         Ok(Apu {
             pulse_channels: Default::default(),
+            dmc: DmcChannel::default(),
+            dmc_rates: R::DMC_RATES,
+            cpu_address_space,
+            processor_dma,
+            dmc_irq,
+            timestamp: Period::default(),
+            period: (R::master_clock() / R::PROCESSOR_CLOCK_DIVIDER).recip(),
         })
     }
 }
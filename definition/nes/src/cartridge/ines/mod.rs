@@ -9,6 +9,10 @@ pub mod expansion_device;
 pub const PRG_BANK_SIZE: usize = 16 * 1024;
 pub const CHR_BANK_SIZE: usize = 8 * 1024;
 pub const HEADER_SIZE: usize = 16;
+pub const TRAINER_SIZE: usize = 512;
+/// Size of the PRG-RAM and CHR-RAM iNES 1.0 headers imply, since they cannot
+/// express it
+pub const DEFAULT_RAM_SIZE: usize = 8 * 1024;
 
 #[derive(Error, Debug)]
 pub enum ParsingError {
@@ -74,6 +78,10 @@ pub struct INes {
     pub version: INesVersion,
     pub timing_mode: TimingMode,
     pub roms: HashMap<RomType, RangeInclusive<usize>>,
+    pub prg_ram_size: usize,
+    pub prg_nvram_size: usize,
+    pub chr_ram_size: usize,
+    pub chr_nvram_size: usize,
 }
 
 impl INes {
@@ -124,11 +132,35 @@ impl INes {
             .get(0..2)
             .ok_or(ParsingError::EarlyEOF)?
             .load::<u8>();
-        let (version, timing_mode) = match version {
+        let (version, timing_mode, ram_sizes) = match version {
             0b00 => {
                 remaining = &remaining[2..];
 
-                (INesVersion::V1, TimingMode::Ntsc)
+                // iNES 1.0 cannot say how much RAM there is, so assume the usual amounts
+                let prg_ram_size = DEFAULT_RAM_SIZE;
+                let chr_ram_size = if chr_bank_count == 0 {
+                    DEFAULT_RAM_SIZE
+                } else {
+                    0
+                };
+
+                let ram_sizes = if non_volatile_memory {
+                    RamSizes {
+                        prg_ram: 0,
+                        prg_nvram: prg_ram_size,
+                        chr_ram: chr_ram_size,
+                        chr_nvram: 0,
+                    }
+                } else {
+                    RamSizes {
+                        prg_ram: prg_ram_size,
+                        prg_nvram: 0,
+                        chr_ram: chr_ram_size,
+                        chr_nvram: 0,
+                    }
+                };
+
+                (INesVersion::V1, TimingMode::Ntsc, ram_sizes)
             }
             0b10 => {
                 remaining = &remaining[2..];
@@ -160,17 +192,26 @@ impl INes {
                 let prg_ram_shift_count = remaining[0..4].load::<u8>();
                 remaining = &remaining[4..];
 
-                if !non_volatile_memory && (prg_nvram_shift_count != 0 || prg_ram_shift_count != 0)
-                {
-                    return Err(ParsingError::DisagreeingNonVolatileMemory);
-                }
-
                 let chr_nvram_shift_count = remaining[0..4].load::<u8>();
                 remaining = &remaining[4..];
 
                 let chr_ram_shift_count = remaining[0..4].load::<u8>();
                 remaining = &remaining[4..];
 
+                // Non volatile memory without a battery to keep it alive makes no sense
+                if !non_volatile_memory
+                    && (prg_nvram_shift_count != 0 || chr_nvram_shift_count != 0)
+                {
+                    return Err(ParsingError::DisagreeingNonVolatileMemory);
+                }
+
+                let ram_sizes = RamSizes {
+                    prg_ram: ram_size_from_shift_count(prg_ram_shift_count),
+                    prg_nvram: ram_size_from_shift_count(prg_nvram_shift_count),
+                    chr_ram: ram_size_from_shift_count(chr_ram_shift_count),
+                    chr_nvram: ram_size_from_shift_count(chr_nvram_shift_count),
+                };
+
                 // Skip unused bits
                 remaining = &remaining[6..];
 
@@ -212,6 +253,7 @@ impl INes {
                         default_expansion_device,
                     },
                     timing_mode,
+                    ram_sizes,
                 )
             }
             _ => return Err(ParsingError::BadVersion { version }),
@@ -221,17 +263,20 @@ impl INes {
         let mut cursor = HEADER_SIZE;
 
         if trainer {
-            roms.insert(RomType::Trainer, cursor..=(cursor + 512 - 1));
-            cursor += 512;
+            roms.insert(RomType::Trainer, cursor..=(cursor + TRAINER_SIZE - 1));
+            cursor += TRAINER_SIZE;
         }
 
         let prg_bank_size = prg_bank_count as usize * PRG_BANK_SIZE;
         roms.insert(RomType::Prg, cursor..=(cursor + prg_bank_size - 1));
         cursor += prg_bank_size;
 
+        // Cartridges without CHR ROM use CHR-RAM instead
         let chr_bank_size = chr_bank_count as usize * CHR_BANK_SIZE;
-        roms.insert(RomType::Chr, cursor..=(cursor + chr_bank_size - 1));
-        // cursor += chr_bank_size;
+        if chr_bank_size != 0 {
+            roms.insert(RomType::Chr, cursor..=(cursor + chr_bank_size - 1));
+            // cursor += chr_bank_size;
+        }
 
         Ok(Self {
             mapper,
@@ -241,6 +286,10 @@ impl INes {
             version,
             timing_mode,
             roms,
+            prg_ram_size: ram_sizes.prg_ram,
+            prg_nvram_size: ram_sizes.prg_nvram,
+            chr_ram_size: ram_sizes.chr_ram,
+            chr_nvram_size: ram_sizes.chr_nvram,
         })
     }
 
@@ -253,9 +302,124 @@ impl INes {
     pub fn chr_bank_count(&self) -> usize {
         self.roms
             .get(&RomType::Chr)
-            .map_or(1, |rom| rom.clone().count() / CHR_BANK_SIZE)
+            .map_or(0, |rom| rom.clone().count() / CHR_BANK_SIZE)
+    }
+}
+
+struct RamSizes {
+    prg_ram: usize,
+    prg_nvram: usize,
+    chr_ram: usize,
+    chr_nvram: usize,
+}
+
+/// NES 2.0 stores RAM sizes as `64 << shift_count`, with zero meaning none at all
+fn ram_size_from_shift_count(shift_count: u8) -> usize {
+    if shift_count == 0 {
+        0
+    } else {
+        64 << shift_count
     }
 }
 
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use super::*;
+
+    fn header(bytes: [u8; 12]) -> [u8; HEADER_SIZE] {
+        let mut header = [0; HEADER_SIZE];
+        header[0..4].copy_from_slice(b"NES\x1a");
+        header[4..].copy_from_slice(&bytes);
+        header
+    }
+
+    #[test]
+    fn v1_assumes_prg_ram() {
+        let ines = INes::parse(header([2, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0])).unwrap();
+
+        assert_eq!(ines.prg_ram_size, DEFAULT_RAM_SIZE);
+        assert_eq!(ines.prg_nvram_size, 0);
+        assert_eq!(ines.chr_ram_size, 0);
+        assert_eq!(ines.chr_bank_count(), 1);
+    }
+
+    #[test]
+    fn v1_battery_makes_prg_ram_non_volatile() {
+        let ines = INes::parse(header([2, 1, 0b0000_0010, 0, 0, 0, 0, 0, 0, 0, 0, 0])).unwrap();
+
+        assert_eq!(ines.prg_ram_size, 0);
+        assert_eq!(ines.prg_nvram_size, DEFAULT_RAM_SIZE);
+    }
+
+    #[test]
+    fn v1_without_chr_rom_has_chr_ram() {
+        let ines = INes::parse(header([2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0])).unwrap();
+
+        assert_eq!(ines.chr_ram_size, DEFAULT_RAM_SIZE);
+        assert_eq!(ines.chr_bank_count(), 0);
+        assert!(!ines.roms.contains_key(&RomType::Chr));
+    }
+
+    #[test]
+    fn trainer_comes_before_prg() {
+        let ines = INes::parse(header([1, 1, 0b0000_0100, 0, 0, 0, 0, 0, 0, 0, 0, 0])).unwrap();
+
+        assert_eq!(
+            ines.roms[&RomType::Trainer],
+            HEADER_SIZE..=HEADER_SIZE + TRAINER_SIZE - 1
+        );
+        assert_eq!(
+            *ines.roms[&RomType::Prg].start(),
+            HEADER_SIZE + TRAINER_SIZE
+        );
+    }
+
+    #[test]
+    fn v2_ram_sizes() {
+        let ines = INes::parse(header([
+            2,
+            0,
+            0b0000_0010,
+            0b0000_1000,
+            0,
+            0,
+            // 8KiB PRG-NVRAM, no PRG-RAM
+            0b0111_0000,
+            // 8KiB CHR-RAM, no CHR-NVRAM
+            0b0000_0111,
+            0,
+            0,
+            0,
+            0,
+        ]))
+        .unwrap();
+
+        assert_eq!(ines.prg_ram_size, 0);
+        assert_eq!(ines.prg_nvram_size, 8 * 1024);
+        assert_eq!(ines.chr_ram_size, 8 * 1024);
+        assert_eq!(ines.chr_nvram_size, 0);
+    }
+
+    #[test]
+    fn v2_non_volatile_memory_requires_battery() {
+        let result = INes::parse(header([
+            2,
+            0,
+            0,
+            0b0000_1000,
+            0,
+            0,
+            0b0111_0000,
+            0,
+            0,
+            0,
+            0,
+            0,
+        ]));
+
+        assert!(matches!(
+            result,
+            Err(ParsingError::DisagreeingNonVolatileMemory)
+        ));
+    }
+}
@@ -65,17 +65,20 @@ impl<P: Platform> ComponentConfig<P> for NRomConfig<'_> {
             }
         };
 
-        let (component_builder, chr) = component_builder.memory_register_buffer(
-            self.config.ppu_address_space,
-            "chr",
-            self.config.chr.clone(),
-        );
+        // Otherwise the cartridge has CHR-RAM there
+        if let Some(chr) = &self.config.chr {
+            let (component_builder, chr) = component_builder.memory_register_buffer(
+                self.config.ppu_address_space,
+                "chr",
+                chr.clone(),
+            );
 
-        component_builder.memory_map_buffer_read(
-            self.config.ppu_address_space,
-            0x0000..=0x1fff,
-            &chr,
-        );
+            component_builder.memory_map_buffer_read(
+                self.config.ppu_address_space,
+                0x0000..=0x1fff,
+                &chr,
+            );
+        }
 
         Ok(NRom)
     }
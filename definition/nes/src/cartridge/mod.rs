@@ -1,14 +1,27 @@
+use std::{borrow::Cow, ops::RangeInclusive};
+
 use bytes::Bytes;
+use fluxemu_definition_misc::memory::standard::{
+    StandardMemoryConfig, StandardMemoryInitialContents,
+};
 use fluxemu_runtime::{
     component::{Component, ComponentConfig},
     machine::builder::ComponentBuilder,
-    memory::AddressSpaceId,
+    memory::{Address, AddressSpaceId},
     platform::Platform,
 };
+use rangemap::RangeInclusiveMap;
 use serde::{Deserialize, Serialize};
 
 use crate::cartridge::mapper::{Mapper, mmc1::Mmc1Config, nrom::NRomConfig};
 
+/// Where PRG-RAM lives in the CPU address space
+const PRG_RAM_WINDOW: RangeInclusive<Address> = 0x6000..=0x7fff;
+/// Where CHR-RAM lives in the PPU address space
+const CHR_RAM_WINDOW: RangeInclusive<Address> = 0x0000..=0x1fff;
+/// Where trainers get loaded in the CPU address space
+const TRAINER_LOCATION: Address = 0x7000;
+
 pub mod ines;
 pub mod mapper;
 
@@ -21,14 +34,22 @@ impl Component for NesCartridge {}
 pub struct NesCartridgeConfig {
     pub cpu_address_space: AddressSpaceId,
     pub ppu_address_space: AddressSpaceId,
-    pub chr: Bytes,
+    /// CHR ROM, if the cartridge has no CHR-RAM
+    pub chr: Option<Bytes>,
     pub prg: Bytes,
+    pub trainer: Option<Bytes>,
     pub mapper: Mapper,
+    pub prg_ram_size: usize,
+    pub prg_nvram_size: usize,
+    pub chr_ram_size: usize,
+    pub chr_nvram_size: usize,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+#[serde(default)]
 pub struct NesCartridgeQuirks {
-    pub force_mapper: Mapper,
+    /// Use this mapper instead of the one in the header
+    pub force_mapper: Option<Mapper>,
 }
 
 impl<P: Platform> ComponentConfig<P> for NesCartridgeConfig {
@@ -38,6 +59,43 @@ impl<P: Platform> ComponentConfig<P> for NesCartridgeConfig {
         self,
         component_builder: ComponentBuilder<'_, P, Self::Component>,
     ) -> Result<Self::Component, Box<dyn std::error::Error>> {
+        let mut prg_ram_size = self.prg_ram_size;
+        let prg_nvram_size = self.prg_nvram_size;
+
+        // The trainer needs somewhere to live
+        if self.trainer.is_some() {
+            let window_size = PRG_RAM_WINDOW.clone().count();
+
+            prg_ram_size = prg_ram_size.max(window_size.saturating_sub(prg_nvram_size));
+        }
+
+        // Battery backed memory goes first so it stays in the same place for saves
+        let component_builder = insert_ram(
+            component_builder,
+            self.cpu_address_space,
+            PRG_RAM_WINDOW,
+            [
+                ("prg-nvram", prg_nvram_size, true),
+                ("prg-ram", prg_ram_size, false),
+            ],
+            self.trainer.as_ref(),
+        );
+
+        let component_builder = if self.chr.is_none() {
+            insert_ram(
+                component_builder,
+                self.ppu_address_space,
+                CHR_RAM_WINDOW,
+                [
+                    ("chr-nvram", self.chr_nvram_size, true),
+                    ("chr-ram", self.chr_ram_size, false),
+                ],
+                None,
+            )
+        } else {
+            component_builder
+        };
+
         match self.mapper {
             Mapper::NRom => {
                 component_builder
@@ -57,3 +115,76 @@ impl<P: Platform> ComponentConfig<P> for NesCartridgeConfig {
         Ok(NesCartridge)
     }
 }
+
+/// Inserts RAM chips packed from the start of `window`, mirroring them over
+/// whatever space is left
+///
+/// RAM larger than the window is clamped, banking it in is up to the mapper
+fn insert_ram<'a, P: Platform>(
+    mut component_builder: ComponentBuilder<'a, P, NesCartridge>,
+    address_space: AddressSpaceId,
+    window: RangeInclusive<Address>,
+    chips: [(&str, usize, bool); 2],
+    trainer: Option<&Bytes>,
+) -> ComponentBuilder<'a, P, NesCartridge> {
+    let mut cursor = *window.start();
+
+    for (name, size, sram) in chips {
+        let size = size.min(window.end() + 1 - cursor);
+
+        if size == 0 {
+            continue;
+        }
+
+        let assigned_range = cursor..=(cursor + size - 1);
+        let mut initial_contents = RangeInclusiveMap::from_iter([(
+            assigned_range.clone(),
+            StandardMemoryInitialContents::Random,
+        )]);
+
+        if let Some(trainer) = trainer {
+            let trainer_range = TRAINER_LOCATION..=(TRAINER_LOCATION + trainer.len() - 1);
+
+            if assigned_range.contains(trainer_range.start())
+                && assigned_range.contains(trainer_range.end())
+            {
+                initial_contents.insert(
+                    trainer_range,
+                    StandardMemoryInitialContents::Array(Cow::Owned(trainer.to_vec())),
+                );
+            }
+        }
+
+        component_builder = component_builder
+            .insert_child_component(
+                name,
+                StandardMemoryConfig {
+                    readable: true,
+                    writable: true,
+                    assigned_range,
+                    assigned_address_space: address_space,
+                    initial_contents,
+                    sram,
+                },
+            )
+            .0;
+
+        cursor += size;
+    }
+
+    let populated_size = cursor - window.start();
+
+    if populated_size != 0 {
+        for mirror_start in (cursor..=*window.end()).step_by(populated_size) {
+            let mirror_end = (mirror_start + populated_size - 1).min(*window.end());
+
+            component_builder = component_builder.memory_mirror_map(
+                address_space,
+                mirror_start..=mirror_end,
+                *window.start()..=(window.start() + (mirror_end - mirror_start)),
+            );
+        }
+    }
+
+    component_builder
+}
@@ -0,0 +1,135 @@
+use std::{borrow::Cow, collections::HashMap, marker::PhantomData, sync::Arc};
+
+use bitvec::{prelude::Lsb0, view::BitView};
+use fluxemu_runtime::{
+    component::{Component, ComponentConfig, ComponentHandle},
+    input::{
+        GamepadInput, Input, VirtualGamepad, VirtualGamepadMetadata, keyboard::KeyboardInput,
+        pointer::PointerInput,
+    },
+    machine::builder::{ComponentBuilder, SchedulerParticipation},
+    memory::{Address, AddressSpaceId, MemoryError},
+    path::FluxEmuPath,
+    platform::Platform,
+    scheduler::{Period, SynchronizationContext},
+};
+
+use crate::ppu::{
+    Ppu, backend::SupportedGraphicsApiPpu, light_sensor::LightSensor, region::Region,
+};
+
+const PORT_0: Address = 0x4016;
+const TRIGGER: Input = Input::Pointer(PointerInput::Primary);
+
+#[derive(Debug)]
+pub struct NesZapper {
+    gamepad: Arc<VirtualGamepad>,
+    light_sensor: Arc<LightSensor>,
+    ppu: ComponentHandle,
+    timestamp: Period,
+    period: Period,
+}
+
+impl Component for NesZapper {
+    fn memory_read(
+        &self,
+        _address: Address,
+        _address_space: AddressSpaceId,
+        _avoid_side_effects: bool,
+        buffer: &mut [u8],
+    ) -> Result<(), MemoryError> {
+        // Make sure the PPU has drawn up to the point in time we are being read at
+        self.ppu.interact(self.timestamp, |_| {});
+
+        let buffer_bits = buffer.view_bits_mut::<Lsb0>();
+
+        // Light sense is active low
+        buffer_bits.set(3, !self.light_sensor.light_detected());
+        buffer_bits.set(4, self.gamepad.get(TRIGGER).as_digital(None));
+
+        Ok(())
+    }
+
+    fn synchronize(&mut self, mut context: SynchronizationContext) {
+        for now in context.allocate(self.period, None) {
+            self.timestamp = now;
+        }
+    }
+
+    fn needs_work(&self, delta: Period) -> bool {
+        delta >= self.period
+    }
+}
+
+#[derive(Debug)]
+pub struct NesZapperConfig<R: Region> {
+    pub cpu_address_space: AddressSpaceId,
+    pub port: u8,
+    pub ppu: FluxEmuPath,
+    pub _phantom: PhantomData<R>,
+}
+
+impl<R: Region, P: Platform<GraphicsApi: SupportedGraphicsApiPpu>> ComponentConfig<P>
+    for NesZapperConfig<R>
+{
+    type Component = NesZapper;
+
+    fn build_component(
+        self,
+        component_builder: ComponentBuilder<'_, P, Self::Component>,
+    ) -> Result<Self::Component, Box<dyn std::error::Error>> {
+        let gamepad = create_gamepad();
+        let light_sensor = LightSensor::new(gamepad.clone());
+
+        component_builder
+            .interact_mut::<Ppu<R, P::GraphicsApi>, _>(&self.ppu, {
+                let light_sensor = light_sensor.clone();
+
+                |ppu| ppu.attach_light_sensor(light_sensor)
+            })
+            .ok_or("The zapper requires a PPU to observe")?;
+
+        let ppu = component_builder.handle(&self.ppu).unwrap();
+
+        let (component_builder, _) = component_builder
+            .set_scheduler_participation(SchedulerParticipation::OnDemand)
+            .insert_gamepad(&format!("zapper-{}", self.port), gamepad.clone());
+
+        let register_location = PORT_0 + self.port as usize;
+        component_builder.memory_map_component_read(
+            self.cpu_address_space,
+            register_location..=register_location,
+        );
+
+        Ok(NesZapper {
+            gamepad,
+            light_sensor,
+            ppu,
+            timestamp: Period::default(),
+            // The PPU pixel clock is as fine as the light sensor could possibly care about
+            period: (R::master_clock() / 4).recip(),
+        })
+    }
+}
+
+fn create_gamepad() -> Arc<VirtualGamepad> {
+    let present_inputs = Vec::from_iter([
+        Input::Pointer(PointerInput::X),
+        Input::Pointer(PointerInput::Y),
+        TRIGGER,
+    ]);
+
+    VirtualGamepad::new(Cow::Owned(VirtualGamepadMetadata {
+        default_real2virtual_mappings: HashMap::from_iter(
+            present_inputs
+                .iter()
+                .copied()
+                .map(|input| (input, input))
+                .chain([
+                    (Input::Gamepad(GamepadInput::RightTrigger), TRIGGER),
+                    (Input::Keyboard(KeyboardInput::KeyZ), TRIGGER),
+                ]),
+        ),
+        present_inputs,
+    }))
+}
@@ -1,7 +1,7 @@
 use std::marker::PhantomData;
 
-pub use cartridge::ines::INes;
 use cartridge::{NesCartridgeConfig, ines::TimingMode};
+pub use cartridge::{NesCartridgeQuirks, ines::INes};
 use fluxemu_definition_misc::memory::standard::{
     StandardMemoryConfig, StandardMemoryInitialContents,
 };
@@ -9,6 +9,7 @@ use fluxemu_definition_mos6502::{Mos6502Config, Mos6502Kind};
 use fluxemu_runtime::{
     machine::{MachineFactory, builder::MachineBuilder},
     memory::AddressSpaceId,
+    path::FluxEmuPath,
     platform::Platform,
     program::{Filesystem, RomRequirement},
 };
@@ -21,7 +22,7 @@ use crate::{
         ines::{INesVersion, Mirroring, RomType, expansion_device::DefaultExpansionDevice},
         mapper::Mapper,
     },
-    gamepad::controller::NesControllerConfig,
+    gamepad::{controller::NesControllerConfig, zapper::NesZapperConfig},
     ppu::{
         BACKGROUND_PALETTE_BASE_ADDRESS, NAMETABLE_ADDRESSES,
         backend::SupportedGraphicsApiPpu,
@@ -38,7 +39,13 @@ mod ppu;
 pub struct Nes;
 
 impl<G: SupportedGraphicsApiPpu, P: Platform<GraphicsApi = G>> MachineFactory<P> for Nes {
-    fn construct(&self, machine: MachineBuilder<P>) -> MachineBuilder<P> {
+    type Quirks = NesCartridgeQuirks;
+
+    fn construct(
+        &self,
+        machine: MachineBuilder<P>,
+        quirks: NesCartridgeQuirks,
+    ) -> MachineBuilder<P> {
         let (machine, cpu_address_space) = machine.insert_address_space(16);
         let (machine, ppu_address_space) = machine.insert_address_space(14);
 
@@ -82,23 +89,35 @@ impl<G: SupportedGraphicsApiPpu, P: Platform<GraphicsApi = G>> MachineFactory<P>
             .unwrap();
         let header = INes::parse(rom[0..16].try_into().unwrap()).unwrap();
 
+        // Some dumps have the wrong mapper in their header
         #[allow(clippy::zero_prefixed_literal)]
-        let mapper = match header.mapper {
+        let mapper = quirks.force_mapper.unwrap_or_else(|| match header.mapper {
             000 => Mapper::NRom,
             001 | 155 => Mapper::Mmc1,
             _ => {
                 unreachable!()
             }
-        };
+        });
 
         let (machine, _) = machine.insert_component(
             "cartridge",
             NesCartridgeConfig {
                 cpu_address_space,
                 ppu_address_space,
-                chr: rom.slice(header.roms[&RomType::Chr].clone()),
+                chr: header
+                    .roms
+                    .get(&RomType::Chr)
+                    .map(|range| rom.slice(range.clone())),
                 prg: rom.slice(header.roms[&RomType::Prg].clone()),
+                trainer: header
+                    .roms
+                    .get(&RomType::Trainer)
+                    .map(|range| rom.slice(range.clone())),
                 mapper,
+                prg_ram_size: header.prg_ram_size,
+                prg_nvram_size: header.prg_nvram_size,
+                chr_ram_size: header.chr_ram_size,
+                chr_nvram_size: header.chr_nvram_size,
             },
         );
 
@@ -129,82 +148,6 @@ impl<G: SupportedGraphicsApiPpu, P: Platform<GraphicsApi = G>> MachineFactory<P>
         }
         .unwrap_or(DefaultExpansionDevice::StandardControllers { swapped: false });
 
-        let machine = match default_expansion_device {
-            DefaultExpansionDevice::StandardControllers { .. } => {
-                let (machine, _) = machine.insert_component(
-                    "standard-nes-controller-0",
-                    NesControllerConfig {
-                        cpu_address_space,
-                        controller_index: 0,
-                    },
-                );
-
-                /*
-                let (machine, _) = machine.insert_component(
-                    "standard-nes-controller-1",
-                    NesControllerConfig {
-                        cpu_address_space,
-                        controller_index: 1,
-                    },
-                );
-                */
-
-                machine
-            }
-            DefaultExpansionDevice::FourScore => todo!(),
-            DefaultExpansionDevice::SimpleFamiconFourPlayerAdaptor => todo!(),
-            DefaultExpansionDevice::VsSystem { address } => todo!(),
-            DefaultExpansionDevice::VsZapper => todo!(),
-            DefaultExpansionDevice::Zapper => todo!(),
-            DefaultExpansionDevice::DualZapper => todo!(),
-            DefaultExpansionDevice::BandaiHyperShotLightgun => todo!(),
-            DefaultExpansionDevice::PowerPad { upside } => todo!(),
-            DefaultExpansionDevice::FamilyTrainer { upside } => todo!(),
-            DefaultExpansionDevice::ArkanoidVaus { kind } => todo!(),
-            DefaultExpansionDevice::DualArkanoidVausFamicomPlusDataRecorder => todo!(),
-            DefaultExpansionDevice::KonamiHyperShotController => todo!(),
-            DefaultExpansionDevice::CoconutsPachinkoController => todo!(),
-            DefaultExpansionDevice::ExcitingBoxingPunchingBag => todo!(),
-            DefaultExpansionDevice::JissenMahjongController => todo!(),
-            DefaultExpansionDevice::PartyTap => todo!(),
-            DefaultExpansionDevice::OekaKidsTablet => todo!(),
-            DefaultExpansionDevice::SunsoftBarcodeBattler => todo!(),
-            DefaultExpansionDevice::MiraclePianoKeyboard => todo!(),
-            DefaultExpansionDevice::PokkunMoguraa => todo!(),
-            DefaultExpansionDevice::TopRider => todo!(),
-            DefaultExpansionDevice::DoubleFisted => todo!(),
-            DefaultExpansionDevice::Famicom3dSystem => todo!(),
-            DefaultExpansionDevice::ドレミッコKeyboard => todo!(),
-            DefaultExpansionDevice::Rob { mode } => todo!(),
-            DefaultExpansionDevice::FamiconDataRecorder => machine,
-            DefaultExpansionDevice::AsciiTurboFile => todo!(),
-            DefaultExpansionDevice::IgsStorageBattleBox => todo!(),
-            DefaultExpansionDevice::FamilyBasicKeyBoardPlusFamiconDataRecorder => todo!(),
-            DefaultExpansionDevice::东达PECKeyboard => todo!(),
-            DefaultExpansionDevice::普澤Bit79Keyboard => todo!(),
-            DefaultExpansionDevice::小霸王Keyboard { mouse } => todo!(),
-            DefaultExpansionDevice::SnesMouse => todo!(),
-            DefaultExpansionDevice::Multicart => todo!(),
-            DefaultExpansionDevice::SnesControllers => todo!(),
-            DefaultExpansionDevice::RacerMateBicycle => todo!(),
-            DefaultExpansionDevice::UForce => todo!(),
-            DefaultExpansionDevice::CityPatrolmanLightgun => todo!(),
-            DefaultExpansionDevice::SharpC1CassetteInterface => todo!(),
-            DefaultExpansionDevice::ExcaliburSudokuPad => todo!(),
-            DefaultExpansionDevice::ABLPinball => todo!(),
-            DefaultExpansionDevice::GoldenNuggetCasino => todo!(),
-            DefaultExpansionDevice::科达Keyboard => todo!(),
-            DefaultExpansionDevice::PortTestController => todo!(),
-            DefaultExpansionDevice::BandaiMultiGamePlayerGamepad => todo!(),
-            DefaultExpansionDevice::VenomTvDanceMat => todo!(),
-            DefaultExpansionDevice::LgTvRemoteControl => todo!(),
-            DefaultExpansionDevice::FamicomNetworkController => todo!(),
-            DefaultExpansionDevice::KingFishingController => todo!(),
-            DefaultExpansionDevice::CroakyKaraokeController => todo!(),
-            DefaultExpansionDevice::科王Keyboard => todo!(),
-            DefaultExpansionDevice::泽诚Keyboard => todo!(),
-        };
-
         /*
         let (machine, _) = machine.insert_component(
             "forced-execution-vector",
@@ -227,7 +170,7 @@ impl<G: SupportedGraphicsApiPpu, P: Platform<GraphicsApi = G>> MachineFactory<P>
         match header.timing_mode {
             // FIXME: Implementing Multi as NTSC for now
             TimingMode::Ntsc | TimingMode::Multi => {
-                let processor_frequency = Ntsc::master_clock() / 12;
+                let processor_frequency = Ntsc::master_clock() / Ntsc::PROCESSOR_CLOCK_DIVIDER;
 
                 let (machine, processor) = machine.insert_component(
                     "mos_6502",
@@ -236,22 +179,35 @@ impl<G: SupportedGraphicsApiPpu, P: Platform<GraphicsApi = G>> MachineFactory<P>
                         assigned_address_space: cpu_address_space,
                         kind: Mos6502Kind::Ricoh2A0x,
                         broken_ror: false,
+                        cycle_accurate_bus: false,
                     },
                 );
 
-                let (machine, _) = machine.insert_component(
+                let (machine, ppu) = machine.insert_component(
                     "ppu",
                     PpuConfig::<Ntsc> {
                         ppu_address_space,
                         cpu_address_space,
-                        processor,
+                        processor: processor.clone(),
                         _phantom: PhantomData,
                     },
                 );
 
-                let (machine, _) = machine.insert_component("apu", ApuConfig { cpu_address_space });
+                let (machine, _) = machine.insert_component(
+                    "apu",
+                    ApuConfig::<Ntsc> {
+                        cpu_address_space,
+                        processor,
+                        _phantom: PhantomData,
+                    },
+                );
 
-                machine
+                setup_expansion_device::<Ntsc, _>(
+                    machine,
+                    cpu_address_space,
+                    ppu,
+                    default_expansion_device,
+                )
             }
             TimingMode::Pal => todo!(),
             TimingMode::Dendy => todo!(),
@@ -259,6 +215,132 @@ impl<G: SupportedGraphicsApiPpu, P: Platform<GraphicsApi = G>> MachineFactory<P>
     }
 }
 
+/// Plug in what the cartridge header says should be in the controller ports
+fn setup_expansion_device<R: Region, P: Platform<GraphicsApi: SupportedGraphicsApiPpu>>(
+    machine: MachineBuilder<P>,
+    cpu_address_space: AddressSpaceId,
+    ppu: FluxEmuPath,
+    default_expansion_device: DefaultExpansionDevice,
+) -> MachineBuilder<P> {
+    match default_expansion_device {
+        DefaultExpansionDevice::StandardControllers { .. } => {
+            let (machine, _) = machine.insert_component(
+                "standard-nes-controller-0",
+                NesControllerConfig {
+                    cpu_address_space,
+                    controller_index: 0,
+                },
+            );
+
+            /*
+            let (machine, _) = machine.insert_component(
+                "standard-nes-controller-1",
+                NesControllerConfig {
+                    cpu_address_space,
+                    controller_index: 1,
+                },
+            );
+            */
+
+            machine
+        }
+        DefaultExpansionDevice::FourScore => todo!(),
+        DefaultExpansionDevice::SimpleFamiconFourPlayerAdaptor => todo!(),
+        DefaultExpansionDevice::VsSystem { address } => todo!(),
+        DefaultExpansionDevice::VsZapper => todo!(),
+        DefaultExpansionDevice::Zapper => {
+            let (machine, _) = machine.insert_component(
+                "standard-nes-controller-0",
+                NesControllerConfig {
+                    cpu_address_space,
+                    controller_index: 0,
+                },
+            );
+
+            let (machine, _) = machine.insert_component(
+                "zapper-1",
+                NesZapperConfig::<R> {
+                    cpu_address_space,
+                    port: 1,
+                    ppu,
+                    _phantom: PhantomData,
+                },
+            );
+
+            machine
+        }
+        DefaultExpansionDevice::DualZapper => {
+            let (machine, _) = machine.insert_component(
+                "zapper-0",
+                NesZapperConfig::<R> {
+                    cpu_address_space,
+                    port: 0,
+                    ppu: ppu.clone(),
+                    _phantom: PhantomData,
+                },
+            );
+
+            let (machine, _) = machine.insert_component(
+                "zapper-1",
+                NesZapperConfig::<R> {
+                    cpu_address_space,
+                    port: 1,
+                    ppu,
+                    _phantom: PhantomData,
+                },
+            );
+
+            machine
+        }
+        DefaultExpansionDevice::BandaiHyperShotLightgun => todo!(),
+        DefaultExpansionDevice::PowerPad { upside } => todo!(),
+        DefaultExpansionDevice::FamilyTrainer { upside } => todo!(),
+        DefaultExpansionDevice::ArkanoidVaus { kind } => todo!(),
+        DefaultExpansionDevice::DualArkanoidVausFamicomPlusDataRecorder => todo!(),
+        DefaultExpansionDevice::KonamiHyperShotController => todo!(),
+        DefaultExpansionDevice::CoconutsPachinkoController => todo!(),
+        DefaultExpansionDevice::ExcitingBoxingPunchingBag => todo!(),
+        DefaultExpansionDevice::JissenMahjongController => todo!(),
+        DefaultExpansionDevice::PartyTap => todo!(),
+        DefaultExpansionDevice::OekaKidsTablet => todo!(),
+        DefaultExpansionDevice::SunsoftBarcodeBattler => todo!(),
+        DefaultExpansionDevice::MiraclePianoKeyboard => todo!(),
+        DefaultExpansionDevice::PokkunMoguraa => todo!(),
+        DefaultExpansionDevice::TopRider => todo!(),
+        DefaultExpansionDevice::DoubleFisted => todo!(),
+        DefaultExpansionDevice::Famicom3dSystem => todo!(),
+        DefaultExpansionDevice::ドレミッコKeyboard => todo!(),
+        DefaultExpansionDevice::Rob { mode } => todo!(),
+        DefaultExpansionDevice::FamiconDataRecorder => machine,
+        DefaultExpansionDevice::AsciiTurboFile => todo!(),
+        DefaultExpansionDevice::IgsStorageBattleBox => todo!(),
+        DefaultExpansionDevice::FamilyBasicKeyBoardPlusFamiconDataRecorder => todo!(),
+        DefaultExpansionDevice::东达PECKeyboard => todo!(),
+        DefaultExpansionDevice::普澤Bit79Keyboard => todo!(),
+        DefaultExpansionDevice::小霸王Keyboard { mouse } => todo!(),
+        DefaultExpansionDevice::SnesMouse => todo!(),
+        DefaultExpansionDevice::Multicart => todo!(),
+        DefaultExpansionDevice::SnesControllers => todo!(),
+        DefaultExpansionDevice::RacerMateBicycle => todo!(),
+        DefaultExpansionDevice::UForce => todo!(),
+        DefaultExpansionDevice::CityPatrolmanLightgun => todo!(),
+        DefaultExpansionDevice::SharpC1CassetteInterface => todo!(),
+        DefaultExpansionDevice::ExcaliburSudokuPad => todo!(),
+        DefaultExpansionDevice::ABLPinball => todo!(),
+        DefaultExpansionDevice::GoldenNuggetCasino => todo!(),
+        DefaultExpansionDevice::科达Keyboard => todo!(),
+        DefaultExpansionDevice::PortTestController => todo!(),
+        DefaultExpansionDevice::BandaiMultiGamePlayerGamepad => todo!(),
+        DefaultExpansionDevice::VenomTvDanceMat => todo!(),
+        DefaultExpansionDevice::LgTvRemoteControl => todo!(),
+        DefaultExpansionDevice::FamicomNetworkController => todo!(),
+        DefaultExpansionDevice::KingFishingController => todo!(),
+        DefaultExpansionDevice::CroakyKaraokeController => todo!(),
+        DefaultExpansionDevice::科王Keyboard => todo!(),
+        DefaultExpansionDevice::泽诚Keyboard => todo!(),
+    }
+}
+
 fn setup_ppu_nametables<P: Platform>(
     machine: MachineBuilder<P>,
     ppu_address_space: AddressSpaceId,
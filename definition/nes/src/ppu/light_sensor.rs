@@ -0,0 +1,89 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicU64, Ordering},
+};
+
+use crossbeam::atomic::AtomicCell;
+use fluxemu_runtime::input::{Input, VirtualGamepad, pointer::PointerInput};
+use nalgebra::{Point2, Vector2};
+use palette::Srgb;
+
+/// How far away from the aimed position the sensor can pick up light, in pixels
+const SENSOR_RADIUS: u16 = 2;
+/// Scanlines the photodiode stays triggered after seeing light
+const SENSOR_DECAY_SCANLINES: u64 = 20;
+/// Minimum luma for a pixel to register as light
+const SENSOR_LUMA_THRESHOLD: f32 = 0.5;
+
+/// Shared between the PPU and a light gun, lets the light gun observe what
+/// the PPU is drawing
+#[derive(Debug)]
+pub struct LightSensor {
+    gamepad: Arc<VirtualGamepad>,
+    /// Scanline counted from power on that light last hit the sensor
+    last_hit_scanline: AtomicCell<Option<u64>>,
+    /// Scanline counted from power on that the PPU is drawing
+    current_scanline: AtomicU64,
+}
+
+impl LightSensor {
+    pub fn new(gamepad: Arc<VirtualGamepad>) -> Arc<Self> {
+        Arc::new(Self {
+            gamepad,
+            last_hit_scanline: AtomicCell::new(None),
+            current_scanline: AtomicU64::new(0),
+        })
+    }
+
+    /// Where the light gun is aimed in the display, given its dimensions
+    pub(crate) fn aim(&self, display_size: Vector2<u16>) -> Point2<u16> {
+        let x = self
+            .gamepad
+            .get(Input::Pointer(PointerInput::X))
+            .as_analog();
+        let y = self
+            .gamepad
+            .get(Input::Pointer(PointerInput::Y))
+            .as_analog();
+
+        Point2::new(
+            (x * f32::from(display_size.x - 1)).round() as u16,
+            (y * f32::from(display_size.y - 1)).round() as u16,
+        )
+    }
+
+    /// Called by the PPU for every pixel it draws near where the sensor is
+    /// aimed
+    #[inline]
+    pub(crate) fn observe(&self, aim: Point2<u16>, position: Point2<u16>, pixel: Srgb<u8>) {
+        if aim.x.abs_diff(position.x) > SENSOR_RADIUS || aim.y.abs_diff(position.y) > SENSOR_RADIUS
+        {
+            return;
+        }
+
+        let pixel = pixel.into_format::<f32>();
+        let luma = 0.299 * pixel.red + 0.587 * pixel.green + 0.114 * pixel.blue;
+
+        if luma >= SENSOR_LUMA_THRESHOLD {
+            self.last_hit_scanline
+                .store(Some(self.current_scanline.load(Ordering::Acquire)));
+        }
+    }
+
+    /// Called by the PPU every time it moves onto a new scanline
+    #[inline]
+    pub(crate) fn advance_scanline(&self) {
+        self.current_scanline.fetch_add(1, Ordering::AcqRel);
+    }
+
+    /// If the sensor is currently seeing light
+    pub fn light_detected(&self) -> bool {
+        let current_scanline = self.current_scanline.load(Ordering::Acquire);
+
+        self.last_hit_scanline
+            .load()
+            .is_some_and(|last_hit_scanline| {
+                current_scanline - last_hit_scanline < SENSOR_DECAY_SCANLINES
+            })
+    }
+}
@@ -10,7 +10,7 @@ use std::{
 
 use arrayvec::ArrayVec;
 use bitvec::{array::BitArray, field::BitField, prelude::Lsb0, view::BitView};
-use fluxemu_definition_mos6502::{Mos6502, NmiFlag, RdyFlag};
+use fluxemu_definition_mos6502::{DmaController, DmaRequest, Mos6502, NmiFlag};
 use fluxemu_range::ContiguousRange;
 use fluxemu_runtime::{
     component::{Component, ComponentConfig, LateInitializedData},
@@ -31,6 +31,7 @@ use strum::FromRepr;
 use crate::ppu::{
     backend::{PpuDisplayBackend, SupportedGraphicsApiPpu},
     background::{BackgroundPipelineState, BackgroundState, SpritePipelineState},
+    light_sensor::LightSensor,
     oam::{OamSprite, OamState, SpriteEvaluationState},
     region::Region,
     state::{State, VramAddressPointerContents},
@@ -39,6 +40,7 @@ use crate::ppu::{
 pub mod backend;
 mod background;
 mod color;
+pub mod light_sensor;
 mod oam;
 pub mod region;
 mod state;
@@ -96,13 +98,14 @@ pub struct Ppu<R: Region, G: SupportedGraphicsApiPpu> {
     backend: Option<G::Backend<R>>,
     cpu_address_space: Arc<AddressSpace>,
     ppu_address_space: Arc<AddressSpace>,
-    processor_rdy: Arc<RdyFlag>,
+    processor_dma: Arc<DmaController>,
     processor_nmi: Arc<NmiFlag>,
     ppu_address_space_cache: AddressSpaceCache,
-    my_path: FluxEmuPath,
     machine: Weak<Machine>,
     timestamp: Period,
     period: Period,
+    /// Light gun sensors and where they were aimed at the start of the scanline
+    light_sensors: Vec<(Arc<LightSensor>, Point2<u16>)>,
 }
 
 impl<R: Region, P: Platform<GraphicsApi: SupportedGraphicsApiPpu>> ComponentConfig<P>
@@ -130,7 +133,6 @@ impl<R: Region, P: Platform<GraphicsApi: SupportedGraphicsApiPpu>> ComponentConf
         let cpu_address_space = component_builder
             .get_address_space(self.cpu_address_space)
             .clone();
-        let my_path = component_builder.path().clone();
 
         let (component_builder, _) = component_builder
             .set_scheduler_participation(SchedulerParticipation::OnDemand)
@@ -140,9 +142,8 @@ impl<R: Region, P: Platform<GraphicsApi: SupportedGraphicsApiPpu>> ComponentConf
             .interact::<Mos6502, _>(&self.processor, Mos6502::nmi)
             .unwrap();
 
-        // Install the rdy source
-        let processor_rdy = component_builder
-            .interact::<Mos6502, _>(&self.processor, Mos6502::rdy)
+        let processor_dma = component_builder
+            .interact::<Mos6502, _>(&self.processor, Mos6502::dma)
             .unwrap();
 
         let total_screen_time =
@@ -155,7 +156,7 @@ impl<R: Region, P: Platform<GraphicsApi: SupportedGraphicsApiPpu>> ComponentConf
         let vblank_end_from_initial_position =
             (Period::from_num(TOTAL_SCANLINE_LENGTH) * 261 + Period::from_num(1)) / frequency;
 
-        component_builder
+        let (component_builder, vblank_start) = component_builder
             .memory_map_component_write(
                 self.cpu_address_space,
                 CpuAccessibleRegister::PpuCtrl as usize..=CpuAccessibleRegister::PpuCtrl as usize,
@@ -194,18 +195,22 @@ impl<R: Region, P: Platform<GraphicsApi: SupportedGraphicsApiPpu>> ComponentConf
                 self.cpu_address_space,
                 CpuAccessibleRegister::OamDma as usize..=CpuAccessibleRegister::OamDma as usize,
             )
-            .schedule_repeating_event(
-                // x: 1, y: 241
-                vblank_start_from_initial_position,
-                framerate,
-                Ppu::vblank_start,
-            )
-            .schedule_repeating_event(
-                // x: 1, y: 261
-                vblank_end_from_initial_position,
-                framerate,
-                Ppu::vblank_end,
-            );
+            .insert_event("vblank_start", Ppu::vblank_start);
+        let (component_builder, vblank_end) =
+            component_builder.insert_event("vblank_end", Ppu::vblank_end);
+
+        let (component_builder, _) = component_builder.schedule_repeating_named_event(
+            &vblank_start,
+            // x: 1, y: 241
+            vblank_start_from_initial_position,
+            framerate,
+        );
+        component_builder.schedule_repeating_named_event(
+            &vblank_end,
+            // x: 1, y: 261
+            vblank_end_from_initial_position,
+            framerate,
+        );
 
         Ok(Ppu {
             state: State {
@@ -249,19 +254,24 @@ impl<R: Region, P: Platform<GraphicsApi: SupportedGraphicsApiPpu>> ComponentConf
             },
             backend: None,
             cpu_address_space,
-            processor_rdy,
+            processor_dma,
             processor_nmi,
             ppu_address_space_cache: ppu_address_space.cache(),
             ppu_address_space,
-            my_path,
             machine: Weak::new(),
             timestamp: Period::default(),
             period: frequency.recip(),
+            light_sensors: Vec::default(),
         })
     }
 }
 
 impl<R: Region, G: SupportedGraphicsApiPpu> Ppu<R, G> {
+    /// Let a light gun observe the pixels this PPU draws
+    pub fn attach_light_sensor(&mut self, light_sensor: Arc<LightSensor>) {
+        self.light_sensors.push((light_sensor, Point2::origin()));
+    }
+
     fn vblank_start(&mut self, _timestamp: Period) {
         self.state.entered_vblank.store(true, Ordering::Release);
 
@@ -458,17 +468,12 @@ impl<R: Region, G: SupportedGraphicsApiPpu> Component for Ppu<R, G> {
                 CpuAccessibleRegister::OamDma => {
                     let page = u16::from(*buffer) << 8;
 
-                    self.processor_rdy.store(false);
-
-                    // TODO: Extract to constant or extract from cpu directly within the config builder
-                    let processor_frequency = R::master_clock() / 12;
-
-                    // Make sure the cpu wakes up eventually
-                    self.machine.upgrade().unwrap().schedule_event::<Self>(
-                        self.timestamp + (processor_frequency.recip() * 514),
-                        &self.my_path,
-                        |component, _| component.processor_rdy.store(true),
-                    );
+                    // One halt cycle, then 256 get/put pairs
+                    self.processor_dma.request(DmaRequest {
+                        setup_cycles: 1,
+                        transfer_cycles: 512,
+                        get_aligned: true,
+                    });
 
                     // Read off OAM data immediately, this is done for performance and should not
                     // have any side effects
@@ -632,6 +637,14 @@ impl<R: Region, G: SupportedGraphicsApiPpu> Component for Ppu<R, G> {
                     })
                     .unwrap_or(BLACK);
 
+                    for (light_sensor, aim) in &self.light_sensors {
+                        light_sensor.observe(
+                            *aim,
+                            Point2::new(scanline_position_x, self.state.cycle_counter.y),
+                            pixel,
+                        );
+                    }
+
                     backend.modify_staging_buffer(|mut staging_buffer_guard| {
                         staging_buffer_guard[(
                             scanline_position_x as usize,
@@ -741,6 +754,12 @@ impl<R: Region, G: SupportedGraphicsApiPpu> Component for Ppu<R, G> {
             if self.state.cycle_counter.x >= TOTAL_SCANLINE_LENGTH {
                 self.state.cycle_counter.x = 0;
                 self.state.cycle_counter.y += 1;
+
+                for (light_sensor, aim) in &mut self.light_sensors {
+                    light_sensor.advance_scanline();
+                    *aim = light_sensor
+                        .aim(Vector2::new(VISIBLE_SCANLINE_LENGTH, R::VISIBLE_SCANLINES));
+                }
             }
 
             if self.state.cycle_counter.y >= R::TOTAL_SCANLINES {
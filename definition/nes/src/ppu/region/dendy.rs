@@ -9,6 +9,10 @@ pub struct Dendy;
 impl Region for Dendy {
     const VBLANK_LENGTH: u16 = 0;
     const VISIBLE_SCANLINES: u16 = 0;
+    const PROCESSOR_CLOCK_DIVIDER: u128 = 15;
+    const DMC_RATES: [u16; 16] = [
+        428, 380, 340, 320, 286, 254, 226, 214, 190, 160, 142, 128, 106, 84, 72, 54,
+    ];
 
     fn master_clock() -> Frequency {
         todo!()
@@ -14,6 +14,10 @@ pub trait Region: Send + Sync + Debug + 'static {
     const VBLANK_LENGTH: u16;
     const TOTAL_SCANLINES: u16 =
         Self::VISIBLE_SCANLINES + Self::VBLANK_LENGTH + DUMMY_SCANLINE_COUNT;
+    /// What the master clock is divided by to get the processor clock
+    const PROCESSOR_CLOCK_DIVIDER: u128;
+    /// DMC timer periods in processor cycles
+    const DMC_RATES: [u16; 16];
 
     fn master_clock() -> Frequency;
     fn color_to_srgb(color: PpuColor) -> Srgb<u8>;
@@ -98,6 +98,10 @@ pub struct Ntsc;
 impl Region for Ntsc {
     const VISIBLE_SCANLINES: u16 = 240;
     const VBLANK_LENGTH: u16 = 20;
+    const PROCESSOR_CLOCK_DIVIDER: u128 = 12;
+    const DMC_RATES: [u16; 16] = [
+        428, 380, 340, 320, 286, 254, 226, 214, 190, 160, 142, 128, 106, 84, 72, 54,
+    ];
 
     #[inline]
     fn master_clock() -> Frequency {
@@ -8,6 +8,10 @@ pub struct Pal;
 impl Region for Pal {
     const VBLANK_LENGTH: u16 = 0;
     const VISIBLE_SCANLINES: u16 = 0;
+    const PROCESSOR_CLOCK_DIVIDER: u128 = 16;
+    const DMC_RATES: [u16; 16] = [
+        398, 354, 316, 298, 276, 236, 210, 198, 176, 148, 132, 118, 98, 78, 66, 50,
+    ];
 
     fn master_clock() -> Frequency {
         Frequency::from_num(17734475) / 4
@@ -0,0 +1,286 @@
+//! Runs well known public domain test ROMs against the NES
+//!
+//! Point `FLUXEMU_NES_TEST_ROMS` at a directory containing the ROMs, laid out
+//! as they are in their upstream archives. Tests whose ROM cannot be found are
+//! skipped
+
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process,
+    str::FromStr,
+    sync::Arc,
+};
+
+use fluxemu_definition_mos6502::Mos6502;
+use fluxemu_definition_nes::Nes;
+use fluxemu_runtime::{
+    machine::{Machine, MachineFactory},
+    memory::AddressSpace,
+    path::FluxEmuPath,
+    program::ProgramManager,
+    scheduler::Period,
+};
+
+const TEST_ROM_DIRECTORY_VARIABLE: &str = "FLUXEMU_NES_TEST_ROMS";
+/// How much emulated time a test ROM gets to report a result
+const TIME_LIMIT_SECONDS: u128 = 60;
+/// How much emulated time passes between checking on a test ROM
+const STEPS_PER_SECOND: u128 = 10;
+
+const BLARGG_STATUS: usize = 0x6000;
+const BLARGG_SIGNATURE: usize = 0x6001;
+const BLARGG_TEXT: usize = 0x6004;
+const BLARGG_MAGIC: [u8; 3] = [0xde, 0xb0, 0x61];
+const BLARGG_RUNNING: u8 = 0x80;
+const BLARGG_NEEDS_RESET: u8 = 0x81;
+
+const NESTEST: &str = "nestest.nes";
+/// Where nestest starts when run without a display
+const NESTEST_AUTOMATION_ENTRY: u16 = 0xc000;
+/// The final `RTS` of the automated tests
+const NESTEST_AUTOMATION_EXIT: u16 = 0xc66e;
+
+fn test_rom_path(relative_path: &str) -> Option<PathBuf> {
+    let Some(directory) = env::var_os(TEST_ROM_DIRECTORY_VARIABLE) else {
+        eprintln!("{TEST_ROM_DIRECTORY_VARIABLE} is not set, skipping {relative_path}");
+        return None;
+    };
+
+    let path = Path::new(&directory).join(relative_path);
+
+    if !path.is_file() {
+        eprintln!("Could not find {}, skipping", path.display());
+        return None;
+    }
+
+    Some(path)
+}
+
+fn open_machine(rom: &Path) -> Arc<Machine> {
+    let program_manager = Arc::new(ProgramManager::default());
+    let program_specification = program_manager
+        .identify_program_from_paths([rom.to_path_buf()])
+        .unwrap()
+        .expect("Could not identify test ROM");
+
+    let machine = Machine::build_test(Some(program_specification), program_manager, None, None);
+
+    Nes.construct(machine, Default::default()).build(())
+}
+
+fn cpu_address_space(machine: &Machine) -> Arc<AddressSpace> {
+    let address_space = machine
+        .interact::<Mos6502, _>(
+            &FluxEmuPath::from_str(":component/mos_6502").unwrap(),
+            Mos6502::address_space,
+        )
+        .unwrap();
+
+    machine.address_spaces(address_space).unwrap().clone()
+}
+
+fn read(machine: &Machine, address_space: &AddressSpace, address: usize, buffer: &mut [u8]) {
+    address_space
+        .read(address, machine.now(), None, buffer)
+        .unwrap();
+}
+
+fn read_byte(machine: &Machine, address_space: &AddressSpace, address: usize) -> u8 {
+    let mut byte = [0];
+    read(machine, address_space, address, &mut byte);
+    byte[0]
+}
+
+/// Runs the machine until `finished` says it is done, panicking if the time
+/// limit is reached first
+fn run_until(machine: &Machine, mut finished: impl FnMut() -> bool) {
+    let step = Period::from_num(1) / STEPS_PER_SECOND;
+
+    for _ in 0..TIME_LIMIT_SECONDS * STEPS_PER_SECOND {
+        machine.run(step);
+
+        if finished() {
+            return;
+        }
+    }
+
+    panic!("Test ROM did not finish within {TIME_LIMIT_SECONDS} seconds");
+}
+
+/// Runs a ROM using blargg's `$6000` status protocol
+fn run_blargg(relative_path: &str) {
+    let Some(rom) = test_rom_path(relative_path) else {
+        return;
+    };
+
+    run_blargg_rom(&rom);
+}
+
+fn run_blargg_rom(rom: &Path) {
+    let machine = open_machine(rom);
+    let address_space = cpu_address_space(&machine);
+
+    let mut status = BLARGG_RUNNING;
+    run_until(&machine, || {
+        let mut signature = [0; 3];
+        read(&machine, &address_space, BLARGG_SIGNATURE, &mut signature);
+
+        if signature != BLARGG_MAGIC {
+            return false;
+        }
+
+        status = read_byte(&machine, &address_space, BLARGG_STATUS);
+
+        assert_ne!(
+            status, BLARGG_NEEDS_RESET,
+            "Test ROM asked for a reset, which the harness cannot do"
+        );
+
+        status != BLARGG_RUNNING
+    });
+
+    let mut text = Vec::default();
+    for address in BLARGG_TEXT..BLARGG_STATUS + 0x1000 {
+        match read_byte(&machine, &address_space, address) {
+            0 => break,
+            byte => text.push(byte),
+        }
+    }
+
+    assert_eq!(
+        status,
+        0,
+        "{} failed:\n{}",
+        rom.display(),
+        String::from_utf8_lossy(&text)
+    );
+}
+
+#[test]
+fn nestest() {
+    let Some(rom) = test_rom_path(NESTEST) else {
+        return;
+    };
+
+    // Patch the ROM to start the automated tests and to jam once they are done,
+    // so nothing but the results are left to look at
+    let mut bytes = fs::read(&rom).unwrap();
+    let prg_start = 16;
+    let prg_length = usize::from(bytes[4]) * 16 * 1024;
+    let prg_offset = |address: u16| prg_start + (usize::from(address) - 0x8000) % prg_length;
+
+    bytes[prg_offset(0xfffc)..=prg_offset(0xfffd)]
+        .copy_from_slice(&NESTEST_AUTOMATION_ENTRY.to_le_bytes());
+    // JAM
+    bytes[prg_offset(NESTEST_AUTOMATION_EXIT)] = 0x02;
+
+    let patched_rom = env::temp_dir().join(format!("fluxemu-{}-automated.nes", process::id()));
+    fs::write(&patched_rom, bytes).unwrap();
+
+    let machine = open_machine(&patched_rom);
+    let address_space = cpu_address_space(&machine);
+
+    // Results are only written on failure, so clear out whatever the RAM started as
+    address_space
+        .write(0x0002, machine.now(), None, &[0, 0])
+        .unwrap();
+
+    machine.run(Period::from_num(1));
+    let _ = fs::remove_file(&patched_rom);
+
+    let official = read_byte(&machine, &address_space, 0x0002);
+    let unofficial = read_byte(&machine, &address_space, 0x0003);
+
+    assert_eq!(official, 0, "Official opcode test {official:#04x} failed");
+    assert_eq!(
+        unofficial, 0,
+        "Unofficial opcode test {unofficial:#04x} failed"
+    );
+}
+
+/// Makes sure the harness itself works without needing any test ROMs
+#[test]
+fn blargg_protocol() {
+    #[rustfmt::skip]
+    let program = [
+        // Write the signature
+        0xa9, BLARGG_MAGIC[0], 0x8d, 0x01, 0x60, // LDA #$DE; STA $6001
+        0xa9, BLARGG_MAGIC[1], 0x8d, 0x02, 0x60, // LDA #$B0; STA $6002
+        0xa9, BLARGG_MAGIC[2], 0x8d, 0x03, 0x60, // LDA #$61; STA $6003
+        // Empty text, then report success
+        0xa9, 0x00, 0x8d, 0x04, 0x60,            // LDA #$00; STA $6004
+        0x8d, 0x00, 0x60,                        // STA $6000
+        0x4c, 0x17, 0x80,                        // JMP $8017
+    ];
+
+    let mut bytes = Vec::from_iter(*b"NES\x1a");
+    // One PRG bank, one CHR bank, NROM
+    bytes.extend([1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+
+    let mut prg = vec![0xea; 16 * 1024];
+    prg[..program.len()].copy_from_slice(&program);
+    // Reset vector
+    prg[0x3ffc..=0x3ffd].copy_from_slice(&0x8000u16.to_le_bytes());
+    bytes.extend(prg);
+    bytes.extend(vec![0; 8 * 1024]);
+
+    let rom = env::temp_dir().join(format!("fluxemu-{}-blargg-protocol.nes", process::id()));
+    fs::write(&rom, bytes).unwrap();
+
+    run_blargg_rom(&rom);
+    let _ = fs::remove_file(&rom);
+}
+
+macro_rules! blargg_tests {
+    ($($name:ident => $path:literal),* $(,)?) => {
+        $(
+            #[test]
+            fn $name() {
+                run_blargg($path);
+            }
+        )*
+    };
+}
+
+blargg_tests! {
+    instr_basics => "instr_test-v5/rom_singles/01-basics.nes",
+    instr_implied => "instr_test-v5/rom_singles/02-implied.nes",
+    instr_immediate => "instr_test-v5/rom_singles/03-immediate.nes",
+    instr_zero_page => "instr_test-v5/rom_singles/04-zero_page.nes",
+    instr_zp_xy => "instr_test-v5/rom_singles/05-zp_xy.nes",
+    instr_absolute => "instr_test-v5/rom_singles/06-absolute.nes",
+    instr_abs_xy => "instr_test-v5/rom_singles/07-abs_xy.nes",
+    instr_ind_x => "instr_test-v5/rom_singles/08-ind_x.nes",
+    instr_ind_y => "instr_test-v5/rom_singles/09-ind_y.nes",
+    instr_branches => "instr_test-v5/rom_singles/10-branches.nes",
+    instr_stack => "instr_test-v5/rom_singles/11-stack.nes",
+    instr_jmp_jsr => "instr_test-v5/rom_singles/12-jmp_jsr.nes",
+    instr_rts => "instr_test-v5/rom_singles/13-rts.nes",
+    instr_rti => "instr_test-v5/rom_singles/14-rti.nes",
+    instr_brk => "instr_test-v5/rom_singles/15-brk.nes",
+    instr_special => "instr_test-v5/rom_singles/16-special.nes",
+    cpu_dummy_reads => "cpu_dummy_reads/cpu_dummy_reads.nes",
+    cpu_interrupts_cli_latency => "cpu_interrupts_v2/rom_singles/1-cli_latency.nes",
+    ppu_vbl_basics => "ppu_vbl_nmi/rom_singles/01-vbl_basics.nes",
+    ppu_vbl_set_time => "ppu_vbl_nmi/rom_singles/02-vbl_set_time.nes",
+    ppu_vbl_clear_time => "ppu_vbl_nmi/rom_singles/03-vbl_clear_time.nes",
+    ppu_nmi_control => "ppu_vbl_nmi/rom_singles/04-nmi_control.nes",
+    ppu_nmi_timing => "ppu_vbl_nmi/rom_singles/05-nmi_timing.nes",
+    ppu_suppression => "ppu_vbl_nmi/rom_singles/06-suppression.nes",
+    ppu_nmi_on_timing => "ppu_vbl_nmi/rom_singles/07-nmi_on_timing.nes",
+    ppu_nmi_off_timing => "ppu_vbl_nmi/rom_singles/08-nmi_off_timing.nes",
+    ppu_even_odd_frames => "ppu_vbl_nmi/rom_singles/09-even_odd_frames.nes",
+    ppu_even_odd_timing => "ppu_vbl_nmi/rom_singles/10-even_odd_timing.nes",
+    ppu_open_bus => "ppu_open_bus/ppu_open_bus.nes",
+    oam_read => "oam_read/oam_read.nes",
+    sprdma_and_dmc_dma => "sprdma_and_dmc_dma/sprdma_and_dmc_dma.nes",
+    apu_len_ctr => "apu_test/rom_singles/1-len_ctr.nes",
+    apu_len_table => "apu_test/rom_singles/2-len_table.nes",
+    apu_irq_flag => "apu_test/rom_singles/3-irq_flag.nes",
+    apu_jitter => "apu_test/rom_singles/4-jitter.nes",
+    apu_len_timing => "apu_test/rom_singles/5-len_timing.nes",
+    apu_irq_flag_timing => "apu_test/rom_singles/6-irq_flag_timing.nes",
+    apu_dmc_basics => "apu_test/rom_singles/7-dmc_basics.nes",
+    apu_dmc_rates => "apu_test/rom_singles/8-dmc_rates.nes",
+}
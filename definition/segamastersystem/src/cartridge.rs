@@ -0,0 +1,277 @@
+use std::{
+    io::{Read, Write},
+    ops::RangeInclusive,
+};
+
+use bytes::Bytes;
+use fluxemu_definition_misc::memory::standard::StandardMemory;
+use fluxemu_runtime::{
+    component::{Component, ComponentConfig, ComponentVersion, TypedComponentHandle},
+    machine::builder::ComponentBuilder,
+    memory::{Address, AddressSpaceId, MemoryError},
+    path::FluxEmuPath,
+    platform::Platform,
+    scheduler::Period,
+};
+
+pub const CARTRIDGE_ADDRESSES: RangeInclusive<Address> = 0x0000..=0xbfff;
+/// The mapper registers, which sit on top of the end of the work RAM mirror
+pub const MAPPER_REGISTERS: RangeInclusive<Address> = 0xfffc..=0xffff;
+const RAM_WINDOW: RangeInclusive<Address> = 0x8000..=0xbfff;
+const BANK_SIZE: usize = 0x4000;
+/// The first KiB is never paged, so the interrupt vectors stay put
+const FIXED_SIZE: usize = 0x400;
+/// Two banks of cartridge RAM, which is the most any game has
+const RAM_SIZE: usize = BANK_SIZE * 2;
+/// Copiers tacked a header onto some dumps, which throws off the banks
+const COPIER_HEADER_SIZE: usize = 0x200;
+
+const CONTROL_RAM_BANK: u8 = 0b0000_0100;
+const CONTROL_RAM_ENABLE: u8 = 0b0000_1000;
+
+/// A game cartridge with the standard Sega mapper
+///
+/// It pages three 16 KiB windows of ROM, and can swap the last one out for
+/// battery backed RAM
+#[derive(Debug)]
+pub struct SegaCartridge {
+    rom: Bytes,
+    ram: Box<[u8; RAM_SIZE]>,
+    /// 0xfffc, the RAM control register
+    control: u8,
+    /// 0xfffd to 0xffff, the ROM bank in each window
+    banks: [u8; 3],
+    /// If the game ever turned the RAM on, which decides if it gets saved
+    ram_used: bool,
+    /// The registers are write only, so the work RAM keeps a copy that games
+    /// read back
+    work_ram: TypedComponentHandle<StandardMemory>,
+}
+
+impl SegaCartridge {
+    fn bank_count(&self) -> usize {
+        self.rom.len().div_ceil(BANK_SIZE).max(1)
+    }
+
+    fn ram_enabled(&self) -> bool {
+        self.control & CONTROL_RAM_ENABLE != 0
+    }
+
+    fn read(&self, address: Address) -> u8 {
+        if RAM_WINDOW.contains(&address) && self.ram_enabled() {
+            return self.ram[self.ram_offset(address)];
+        }
+
+        let bank = if address < FIXED_SIZE {
+            0
+        } else {
+            usize::from(self.banks[address / BANK_SIZE]) % self.bank_count()
+        };
+
+        self.rom
+            .get(bank * BANK_SIZE + (address % BANK_SIZE))
+            .copied()
+            .unwrap_or(0xff)
+    }
+
+    fn write(&mut self, address: Address, data: u8) {
+        if MAPPER_REGISTERS.contains(&address) {
+            match address - MAPPER_REGISTERS.start() {
+                0 => {
+                    self.control = data;
+                    self.ram_used |= self.ram_enabled();
+                }
+                index => self.banks[index - 1] = data,
+            }
+        } else if RAM_WINDOW.contains(&address) && self.ram_enabled() {
+            let offset = self.ram_offset(address);
+
+            self.ram[offset] = data;
+        }
+    }
+
+    fn ram_offset(&self, address: Address) -> usize {
+        let bank = usize::from(self.control & CONTROL_RAM_BANK != 0);
+
+        bank * BANK_SIZE + (address - RAM_WINDOW.start())
+    }
+}
+
+impl Component for SegaCartridge {
+    fn save_version(&self) -> Option<ComponentVersion> {
+        self.ram_used.then_some(0)
+    }
+
+    fn store_save(&self, mut writer: Box<dyn Write>) -> Result<(), Box<dyn std::error::Error>> {
+        writer.write_all(self.ram.as_slice())?;
+
+        Ok(())
+    }
+
+    fn memory_read(
+        &self,
+        address: Address,
+        _address_space: AddressSpaceId,
+        _avoid_side_effects: bool,
+        buffer: &mut [u8],
+    ) -> Result<(), MemoryError> {
+        buffer[0] = self.read(address);
+
+        Ok(())
+    }
+
+    fn memory_write(
+        &mut self,
+        address: Address,
+        address_space: AddressSpaceId,
+        buffer: &[u8],
+    ) -> Result<(), MemoryError> {
+        self.write(address, buffer[0]);
+
+        if MAPPER_REGISTERS.contains(&address) {
+            // Out of the mirror and into the RAM proper
+            self.work_ram.interact_mut(Period::default(), |work_ram| {
+                work_ram.memory_write(address & 0xdfff, address_space, buffer)
+            })?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct SegaCartridgeConfig {
+    pub rom: Bytes,
+    pub work_ram: FluxEmuPath,
+    pub cpu_address_space: AddressSpaceId,
+}
+
+impl<P: Platform> ComponentConfig<P> for SegaCartridgeConfig {
+    type Component = SegaCartridge;
+
+    fn build_component(
+        self,
+        component_builder: ComponentBuilder<'_, P, Self::Component>,
+    ) -> Result<Self::Component, Box<dyn std::error::Error>> {
+        let rom = if self.rom.len() % BANK_SIZE == COPIER_HEADER_SIZE {
+            tracing::warn!("Stripping a copier header off of the ROM");
+
+            self.rom.slice(COPIER_HEADER_SIZE..)
+        } else {
+            self.rom
+        };
+
+        tracing::info!("Loading a cartridge with {} bytes of ROM", rom.len());
+
+        let mut cartridge = SegaCartridge {
+            rom,
+            ram: Box::new([0; RAM_SIZE]),
+            control: 0,
+            banks: [0, 1, 2],
+            ram_used: false,
+            work_ram: component_builder.typed_handle(&self.work_ram).unwrap(),
+        };
+
+        if let Some((mut save, version)) = component_builder.save() {
+            if version != 0 {
+                return Err("Invalid save version".into());
+            }
+
+            save.read_exact(cartridge.ram.as_mut_slice())?;
+            cartridge.ram_used = true;
+        }
+
+        component_builder
+            .memory_map_component(self.cpu_address_space, CARTRIDGE_ADDRESSES)
+            .memory_map_component_write(self.cpu_address_space, MAPPER_REGISTERS);
+
+        Ok(cartridge)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fluxemu_definition_misc::memory::standard::{
+        StandardMemoryConfig, StandardMemoryInitialContents,
+    };
+    use fluxemu_runtime::machine::Machine;
+    use rangemap::RangeInclusiveMap;
+
+    use super::*;
+
+    #[test]
+    fn banking() {
+        let (machine, address_space) = Machine::build_test_minimal().insert_address_space(16);
+
+        let (machine, work_ram) = machine.insert_component(
+            "work_ram",
+            StandardMemoryConfig {
+                readable: true,
+                writable: true,
+                assigned_range: 0xc000..=0xdfff,
+                assigned_address_space: address_space,
+                initial_contents: RangeInclusiveMap::from_iter([(
+                    0xc000..=0xdfff,
+                    StandardMemoryInitialContents::Value(0),
+                )]),
+                sram: false,
+            },
+        );
+        let machine = machine.memory_map_mirror(address_space, 0xe000..=0xffff, 0xc000..=0xdfff);
+
+        // Each bank is filled with its own number
+        let rom = Bytes::from_iter((0..8).flat_map(|bank| [bank; BANK_SIZE]));
+
+        let (machine, _) = machine.insert_component(
+            "cartridge",
+            SegaCartridgeConfig {
+                rom,
+                work_ram,
+                cpu_address_space: address_space,
+            },
+        );
+        let machine = machine.build(());
+        let address_space = machine.address_spaces(address_space).unwrap();
+
+        let read = |address| {
+            let mut buffer = [0];
+
+            address_space
+                .read(address, Period::default(), None, &mut buffer)
+                .unwrap();
+
+            buffer[0]
+        };
+        let write = |address, value| {
+            address_space
+                .write(address, Period::default(), None, &[value])
+                .unwrap();
+        };
+
+        assert_eq!([read(0x0000), read(0x4000), read(0x8000)], [0, 1, 2]);
+
+        write(0xfffd, 5);
+        write(0xfffe, 6);
+        write(0xffff, 7);
+
+        // The first KiB stays on the first bank
+        assert_eq!(read(0x0000), 0);
+        assert_eq!([read(0x0400), read(0x4000), read(0x8000)], [5, 6, 7]);
+        // Games read back what they wrote from RAM
+        assert_eq!([read(0xdffe), read(0xffff)], [6, 7]);
+
+        // Out of range banks wrap around
+        write(0xffff, 11);
+        assert_eq!(read(0x8000), 3);
+
+        write(0xfffc, CONTROL_RAM_ENABLE);
+        write(0x8000, 0xaa);
+        assert_eq!(read(0x8000), 0xaa);
+
+        write(0xfffc, CONTROL_RAM_ENABLE | CONTROL_RAM_BANK);
+        assert_eq!(read(0x8000), 0);
+
+        write(0xfffc, 0);
+        assert_eq!(read(0x8000), 3);
+    }
+}
@@ -0,0 +1,350 @@
+use std::{borrow::Cow, collections::HashMap, ops::RangeInclusive, sync::Arc};
+
+use fluxemu_definition_intel8080::{Intel8080, NmiFlag};
+use fluxemu_runtime::{
+    component::{Component, ComponentConfig},
+    input::{GamepadInput, Input, VirtualGamepad, VirtualGamepadMetadata, keyboard::KeyboardInput},
+    machine::builder::{ComponentBuilder, SchedulerParticipation},
+    memory::{Address, AddressSpaceId, MemoryError},
+    path::FluxEmuPath,
+    platform::Platform,
+    scheduler::{Period, SynchronizationContext},
+};
+
+use crate::Model;
+
+/// Memory control on even ports and I/O control on odd ones
+const CONTROL_PORTS: RangeInclusive<Address> = 0x00..=0x3f;
+/// Port A on even ports and port B on odd ones
+const CONTROLLER_PORTS: RangeInclusive<Address> = 0xc0..=0xff;
+/// The Start button and region, then the link port registers
+const GAME_GEAR_PORTS: RangeInclusive<Address> = 0x00..=0x05;
+/// What the link port registers read as with nothing plugged in
+const GAME_GEAR_LINK_DEFAULTS: [u8; 5] = [0x7f, 0xff, 0x00, 0xff, 0x00];
+/// How often the pause button is checked
+const POLL_FREQUENCY: u128 = 1000;
+
+const GAME_GEAR_EXPORT: u8 = 0b0100_0000;
+const GAME_GEAR_START: u8 = 0b1000_0000;
+const PORT_B_RESET: u8 = 0b0001_0000;
+
+/// Each controller's lines, in the order of their bits in port A
+const PAD_INPUTS: [GamepadInput; 6] = [
+    GamepadInput::LeftStickUp,
+    GamepadInput::LeftStickDown,
+    GamepadInput::LeftStickLeft,
+    GamepadInput::LeftStickRight,
+    // Buttons 1 and 2, on TL and TR
+    GamepadInput::FPadDown,
+    GamepadInput::FPadRight,
+];
+/// Pause on the Master System and Start on the Game Gear
+const START: GamepadInput = GamepadInput::Start;
+/// The Master System's reset button
+const RESET: GamepadInput = GamepadInput::Select;
+
+/// The controller ports, and the buttons on the console itself
+#[derive(Debug)]
+pub struct Controllers {
+    model: Model,
+    /// The Game Gear only has the one built in
+    gamepads: Vec<Arc<VirtualGamepad>>,
+    /// Port 0x3f, which can turn TR and TH into outputs
+    io_control: u8,
+    pause_pressed: bool,
+    nmi: Arc<NmiFlag>,
+}
+
+impl Controllers {
+    fn pressed(&self, player: usize, input: GamepadInput) -> bool {
+        self.gamepads
+            .get(player)
+            .is_some_and(|gamepad| gamepad.get(Input::Gamepad(input)).as_digital(None))
+    }
+
+    /// The six lines of a controller, where a held button pulls its line low
+    fn lines(&self, player: usize) -> u8 {
+        PAD_INPUTS
+            .into_iter()
+            .enumerate()
+            .fold(0x3f, |lines, (line, input)| {
+                if self.pressed(player, input) {
+                    lines & !(1 << line)
+                } else {
+                    lines
+                }
+            })
+    }
+
+    /// TR and TH read back what is driven on them when they are outputs,
+    /// which is how games tell an export console from a Japanese one
+    fn override_output(&self, value: u8, direction_bit: u8, value_bit: u8) -> u8 {
+        if self.io_control & (1 << direction_bit) != 0 {
+            return value;
+        }
+
+        let level = (self.io_control >> (direction_bit + 4)) & 1;
+
+        (value & !(1 << value_bit)) | (level << value_bit)
+    }
+
+    fn read_port_a(&self) -> u8 {
+        let first = self.lines(0);
+        let second = self.lines(1);
+
+        let value = first | (second << 6);
+
+        self.override_output(value, 0, 5)
+    }
+
+    fn read_port_b(&self) -> u8 {
+        let second = self.lines(1);
+
+        // Player 2's directions are split across the ports
+        let mut value = (second >> 2) | 0b1110_0000;
+
+        if !(self.model == Model::MasterSystem && self.pressed(0, RESET)) {
+            value |= PORT_B_RESET;
+        }
+
+        let value = self.override_output(value, 1, 6);
+        let value = self.override_output(value, 2, 3);
+
+        self.override_output(value, 3, 7)
+    }
+
+    fn read_game_gear(&self, address: Address) -> u8 {
+        match address {
+            0x00 => {
+                let start = if self.pressed(0, START) {
+                    0
+                } else {
+                    GAME_GEAR_START
+                };
+
+                // NTSC, which is the bit under it being clear
+                start | GAME_GEAR_EXPORT
+            }
+            _ => GAME_GEAR_LINK_DEFAULTS[address - 1],
+        }
+    }
+}
+
+impl Component for Controllers {
+    fn memory_read(
+        &self,
+        address: Address,
+        _address_space: AddressSpaceId,
+        _avoid_side_effects: bool,
+        buffer: &mut [u8],
+    ) -> Result<(), MemoryError> {
+        buffer[0] = if self.model == Model::GameGear && GAME_GEAR_PORTS.contains(&address) {
+            self.read_game_gear(address)
+        } else if address.is_multiple_of(2) {
+            self.read_port_a()
+        } else {
+            self.read_port_b()
+        };
+
+        Ok(())
+    }
+
+    fn memory_write(
+        &mut self,
+        address: Address,
+        _address_space: AddressSpaceId,
+        buffer: &[u8],
+    ) -> Result<(), MemoryError> {
+        if self.model == Model::GameGear && GAME_GEAR_PORTS.contains(&address) {
+            // FIXME: Nothing is ever on the other end of the link port
+            return Ok(());
+        }
+
+        if !address.is_multiple_of(2) {
+            self.io_control = buffer[0];
+        } else {
+            // FIXME: There is no BIOS for this to swap out, so the cartridge is
+            // always the one enabled
+            tracing::debug!("Memory control set to {:#04x}", buffer[0]);
+        }
+
+        Ok(())
+    }
+
+    fn synchronize(&mut self, mut context: SynchronizationContext) {
+        for _ in context.allocate(Period::ONE / POLL_FREQUENCY, None) {
+            let pressed = self.pressed(0, START);
+
+            // NMI is edge triggered, so holding pause only pauses once
+            if pressed != self.pause_pressed {
+                self.nmi.store(!pressed);
+                self.pause_pressed = pressed;
+            }
+        }
+    }
+
+    fn needs_work(&self, delta: Period) -> bool {
+        delta >= Period::ONE / POLL_FREQUENCY
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct ControllersConfig {
+    pub cpu: FluxEmuPath,
+    pub io_address_space: AddressSpaceId,
+    pub model: Model,
+}
+
+impl<P: Platform> ComponentConfig<P> for ControllersConfig {
+    type Component = Controllers;
+
+    fn build_component(
+        self,
+        mut component_builder: ComponentBuilder<'_, P, Self::Component>,
+    ) -> Result<Self::Component, Box<dyn std::error::Error>> {
+        let nmi = component_builder
+            .interact::<Intel8080, _>(&self.cpu, Intel8080::nmi)
+            .unwrap();
+
+        let player_count = match self.model {
+            Model::MasterSystem => 2,
+            Model::GameGear => 1,
+        };
+
+        let mut gamepads = Vec::new();
+
+        for player in 0..player_count {
+            let gamepad = create_gamepad(player == 0);
+
+            (component_builder, _) =
+                component_builder.insert_gamepad(&format!("player-{player}"), gamepad.clone());
+
+            gamepads.push(gamepad);
+        }
+
+        component_builder = component_builder
+            .memory_map_component_write(self.io_address_space, CONTROL_PORTS)
+            .memory_map_component_read(self.io_address_space, CONTROLLER_PORTS);
+
+        match self.model {
+            // The pause button goes straight to NMI, so it has to be watched
+            Model::MasterSystem => {
+                component_builder
+                    .set_scheduler_participation(SchedulerParticipation::SchedulerDriven);
+            }
+            Model::GameGear => {
+                component_builder.memory_map_component_read(self.io_address_space, GAME_GEAR_PORTS);
+            }
+        }
+
+        Ok(Controllers {
+            model: self.model,
+            gamepads,
+            // Everything starts as an input
+            io_control: 0xff,
+            pause_pressed: false,
+            nmi,
+        })
+    }
+}
+
+/// The console buttons are put on the first controller, since the frontend
+/// has nowhere else for them
+fn create_gamepad(console_buttons: bool) -> Arc<VirtualGamepad> {
+    let inputs = PAD_INPUTS.into_iter().chain(
+        console_buttons
+            .then_some([START, RESET])
+            .into_iter()
+            .flatten(),
+    );
+
+    let keyboard_mappings = [
+        (KeyboardInput::ArrowUp, PAD_INPUTS[0]),
+        (KeyboardInput::ArrowDown, PAD_INPUTS[1]),
+        (KeyboardInput::ArrowLeft, PAD_INPUTS[2]),
+        (KeyboardInput::ArrowRight, PAD_INPUTS[3]),
+        (KeyboardInput::KeyZ, PAD_INPUTS[4]),
+        (KeyboardInput::KeyX, PAD_INPUTS[5]),
+        (KeyboardInput::Enter, START),
+        (KeyboardInput::ShiftRight, RESET),
+    ];
+
+    VirtualGamepad::new(Cow::Owned(VirtualGamepadMetadata {
+        present_inputs: inputs.clone().map(Input::Gamepad).collect(),
+        default_real2virtual_mappings: HashMap::from_iter(
+            inputs
+                .map(|input| (Input::Gamepad(input), Input::Gamepad(input)))
+                .chain(
+                    keyboard_mappings
+                        .into_iter()
+                        .filter(|(_, input)| console_buttons || PAD_INPUTS.contains(input))
+                        .map(|(key, input)| (Input::Keyboard(key), Input::Gamepad(input))),
+                ),
+        ),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use fluxemu_definition_intel8080::Intel8080Config;
+    use fluxemu_runtime::{input::InputState, machine::Machine, scheduler::Frequency};
+
+    use super::*;
+    use crate::{CPU_DIVIDER, MASTER_CLOCK};
+
+    /// Reads the Start port and then port A, with some buttons held
+    fn read_game_gear(pressed: &[GamepadInput]) -> [u8; 2] {
+        let (machine, cpu_address_space) = Machine::build_test_minimal().insert_address_space(16);
+        let (machine, io_address_space) = machine.insert_address_space(8);
+
+        let (machine, cpu) = machine.insert_component(
+            "z80",
+            Intel8080Config::z80(
+                Frequency::from_num(MASTER_CLOCK) / CPU_DIVIDER,
+                cpu_address_space,
+                io_address_space,
+            ),
+        );
+
+        let (machine, controllers) = machine.insert_component(
+            "controllers",
+            ControllersConfig {
+                cpu,
+                io_address_space,
+                model: Model::GameGear,
+            },
+        );
+        let machine = machine.build(());
+
+        machine
+            .interact::<Controllers, _>(&controllers, |controllers| {
+                for input in pressed {
+                    controllers.gamepads[0].set(Input::Gamepad(*input), InputState::PRESSED);
+                }
+            })
+            .unwrap();
+
+        let address_space = machine.address_spaces(io_address_space).unwrap();
+
+        [0x00, 0xdc].map(|address| {
+            let mut buffer = [0];
+
+            address_space
+                .read(address, Period::default(), None, &mut buffer)
+                .unwrap();
+
+            buffer[0]
+        })
+    }
+
+    #[test]
+    fn game_gear_start() {
+        assert_eq!(read_game_gear(&[]), [0xc0, 0xff]);
+        // Start is on its own port, not the controller lines
+        assert_eq!(read_game_gear(&[START]), [0x40, 0xff]);
+        assert_eq!(
+            read_game_gear(&[START, GamepadInput::FPadDown]),
+            [0x40, 0xef]
+        );
+    }
+}
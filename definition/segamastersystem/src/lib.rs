@@ -0,0 +1,149 @@
+use std::ops::RangeInclusive;
+
+use cartridge::SegaCartridgeConfig;
+use controller::ControllersConfig;
+use fluxemu_definition_intel8080::Intel8080Config;
+use fluxemu_definition_misc::memory::standard::{
+    StandardMemoryConfig, StandardMemoryInitialContents,
+};
+use fluxemu_runtime::{
+    machine::{MachineFactory, builder::MachineBuilder},
+    memory::Address,
+    platform::Platform,
+    program::{Filesystem, RomRequirement},
+    scheduler::Frequency,
+};
+use psg::PsgConfig;
+use rangemap::RangeInclusiveMap;
+use vdp::{SupportedGraphicsApiVdp, VdpConfig};
+
+mod cartridge;
+mod controller;
+mod psg;
+mod vdp;
+
+/// The NTSC master clock, which everything else is divided down from
+const MASTER_CLOCK: u128 = 53_693_175;
+/// Master clocks per Z80 clock
+const CPU_DIVIDER: u128 = 15;
+
+const WORK_RAM_ADDRESSES: RangeInclusive<Address> = 0xc000..=0xdfff;
+const WORK_RAM_MIRROR_ADDRESSES: RangeInclusive<Address> = 0xe000..=0xffff;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Model {
+    MasterSystem,
+    GameGear,
+}
+
+#[derive(Debug, Default)]
+pub struct MasterSystem;
+
+impl<P: Platform<GraphicsApi: SupportedGraphicsApiVdp>> MachineFactory<P> for MasterSystem {
+    type Quirks = ();
+
+    fn construct(&self, machine: MachineBuilder<P>, _quirks: ()) -> MachineBuilder<P> {
+        construct(machine, Model::MasterSystem)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct GameGear;
+
+impl<P: Platform<GraphicsApi: SupportedGraphicsApiVdp>> MachineFactory<P> for GameGear {
+    type Quirks = ();
+
+    fn construct(&self, machine: MachineBuilder<P>, _quirks: ()) -> MachineBuilder<P> {
+        construct(machine, Model::GameGear)
+    }
+}
+
+fn construct<P: Platform<GraphicsApi: SupportedGraphicsApiVdp>>(
+    machine: MachineBuilder<P>,
+    model: Model,
+) -> MachineBuilder<P> {
+    let (machine, cpu_address_space) = machine.insert_address_space(16);
+    // Only the low byte of the port gets decoded
+    let (machine, io_address_space) = machine.insert_address_space(8);
+
+    let Filesystem::Single { rom_id, .. } =
+        machine.program_specification().unwrap().info.filesystem()
+    else {
+        panic!("No master system game has a structured filesystem")
+    };
+    let rom = *rom_id;
+
+    let rom = machine
+        .program_manager()
+        .open(rom, RomRequirement::Required)
+        .unwrap();
+
+    let (machine, cpu) = machine.insert_component(
+        "z80",
+        Intel8080Config::z80(
+            Frequency::from_num(MASTER_CLOCK) / CPU_DIVIDER,
+            cpu_address_space,
+            io_address_space,
+        ),
+    );
+
+    let (machine, work_ram) = machine.insert_component(
+        "work_ram",
+        StandardMemoryConfig {
+            readable: true,
+            writable: true,
+            assigned_range: WORK_RAM_ADDRESSES,
+            assigned_address_space: cpu_address_space,
+            initial_contents: RangeInclusiveMap::from_iter([(
+                WORK_RAM_ADDRESSES,
+                StandardMemoryInitialContents::Random,
+            )]),
+            sram: false,
+        },
+    );
+    let machine = machine.memory_map_mirror(
+        cpu_address_space,
+        WORK_RAM_MIRROR_ADDRESSES,
+        WORK_RAM_ADDRESSES,
+    );
+
+    // Has to go after the work RAM so the mapper registers sit on top of it
+    let (machine, _) = machine.insert_component(
+        "cartridge",
+        SegaCartridgeConfig {
+            rom,
+            work_ram,
+            cpu_address_space,
+        },
+    );
+
+    let (machine, _) = machine.insert_component(
+        "vdp",
+        VdpConfig {
+            cpu: cpu.clone(),
+            io_address_space,
+            model,
+        },
+    );
+
+    let (machine, _) = machine.insert_component(
+        "controllers",
+        ControllersConfig {
+            cpu,
+            io_address_space,
+            model,
+        },
+    );
+
+    // Has to go after the controllers so the Game Gear stereo register sits on
+    // top of the control ports
+    let (machine, _) = machine.insert_component(
+        "psg",
+        PsgConfig {
+            io_address_space,
+            model,
+        },
+    );
+
+    machine
+}
@@ -0,0 +1,353 @@
+use std::ops::RangeInclusive;
+
+use fluxemu_audio::FrameIterator;
+use fluxemu_runtime::{
+    component::{Component, ComponentConfig, SampleSource},
+    machine::builder::{ComponentBuilder, SchedulerParticipation},
+    memory::{Address, AddressSpaceId, MemoryError},
+    path::FluxEmuPath,
+    platform::Platform,
+    scheduler::{Period, SynchronizationContext},
+};
+use nalgebra::SVector;
+use ringbuffer::{AllocRingBuffer, RingBuffer};
+
+use crate::{CPU_DIVIDER, MASTER_CLOCK, Model, vdp::COUNTER_PORTS};
+
+/// The Game Gear's stereo control register
+const STEREO_PORT: RangeInclusive<Address> = 0x06..=0x06;
+
+/// The PSG counts down once every 16 CPU clocks
+const TICK_DIVIDER: u128 = CPU_DIVIDER * 16;
+/// Ticks between each audio sample
+const AUDIO_SAMPLE_DIVIDER: u32 = 5;
+
+const LATCH: u8 = 0b1000_0000;
+const LATCH_CHANNEL: u8 = 0b0110_0000;
+const LATCH_VOLUME: u8 = 0b0001_0000;
+const NOISE_WHITE: u8 = 0b0000_0100;
+/// The shift register is 16 bits with the output coming off the bottom
+const NOISE_RESET: u16 = 0x8000;
+/// Bits 0 and 3 are what get fed back on the Sega variant
+const NOISE_TAPS: u16 = 0x0009;
+/// Fully attenuated
+const SILENT: u8 = 0x0f;
+
+#[derive(Debug, Default)]
+struct ToneChannel {
+    /// 10 bits
+    period: u16,
+    counter: u16,
+    output: bool,
+}
+
+impl ToneChannel {
+    fn tick(&mut self) {
+        self.counter = self.counter.saturating_sub(1);
+
+        if self.counter == 0 {
+            self.counter = self.period;
+            self.output = !self.output;
+        }
+    }
+
+    fn output(&self) -> bool {
+        // Too high to hear, so games use this to hold the output up and play
+        // samples through the volume
+        self.period <= 1 || self.output
+    }
+}
+
+#[derive(Debug)]
+struct NoiseChannel {
+    control: u8,
+    counter: u16,
+    /// Halves the rate the shift register moves at
+    flip_flop: bool,
+    shift_register: u16,
+}
+
+impl NoiseChannel {
+    fn write(&mut self, data: u8) {
+        self.control = data & 0b111;
+        self.shift_register = NOISE_RESET;
+    }
+
+    fn tick(&mut self, tone_2_period: u16) {
+        self.counter = self.counter.saturating_sub(1);
+
+        if self.counter != 0 {
+            return;
+        }
+
+        self.counter = match self.control & 0b11 {
+            0 => 0x10,
+            1 => 0x20,
+            2 => 0x40,
+            _ => tone_2_period,
+        };
+        self.flip_flop = !self.flip_flop;
+
+        if self.flip_flop {
+            let feedback = if self.control & NOISE_WHITE != 0 {
+                (self.shift_register & NOISE_TAPS).count_ones() % 2 == 1
+            } else {
+                self.shift_register & 1 != 0
+            };
+
+            self.shift_register = (self.shift_register >> 1) | (u16::from(feedback) << 15);
+        }
+    }
+
+    fn output(&self) -> bool {
+        self.shift_register & 1 != 0
+    }
+}
+
+/// The SN76489 inside the VDP, with three square waves and a noise channel
+#[derive(Debug)]
+pub struct Psg {
+    tones: [ToneChannel; 3],
+    noise: NoiseChannel,
+    /// 4 bit attenuation of each channel, in steps of 2 dB
+    attenuation: [u8; 4],
+    /// The register that data bytes go to, as the channel and volume bits of
+    /// the last latch byte
+    latched: u8,
+    /// Which channels go to the right, and shifted up 4 which go to the left
+    stereo: u8,
+    ticks: u32,
+    left_channel: FluxEmuPath,
+    left_buffer: AllocRingBuffer<SVector<f32, 1>>,
+    right_buffer: AllocRingBuffer<SVector<f32, 1>>,
+}
+
+impl Psg {
+    fn write(&mut self, data: u8) {
+        if data & LATCH != 0 {
+            self.latched = data & (LATCH_CHANNEL | LATCH_VOLUME);
+        }
+
+        let channel = usize::from(self.latched >> 5);
+
+        if self.latched & LATCH_VOLUME != 0 {
+            self.attenuation[channel] = data & 0x0f;
+        } else if channel == 3 {
+            self.noise.write(data);
+        } else {
+            let tone = &mut self.tones[channel];
+
+            tone.period = if data & LATCH != 0 {
+                (tone.period & 0x3f0) | u16::from(data & 0x0f)
+            } else {
+                (tone.period & 0x00f) | (u16::from(data & 0x3f) << 4)
+            };
+        }
+    }
+
+    fn sample_audio(&mut self) {
+        let outputs = [
+            self.tones[0].output(),
+            self.tones[1].output(),
+            self.tones[2].output(),
+            self.noise.output(),
+        ];
+
+        let analog = std::array::from_fn::<_, 4, _>(|index| {
+            let attenuation = self.attenuation[index];
+
+            if attenuation == SILENT {
+                return 0.0;
+            }
+
+            let volume = 10.0f32.powf(-f32::from(attenuation) / 10.0);
+
+            if outputs[index] { volume } else { -volume }
+        });
+
+        let [right, left] = [0, 4].map(|shift| {
+            analog
+                .iter()
+                .enumerate()
+                .filter(|(index, _)| self.stereo & (1 << (index + shift)) != 0)
+                .map(|(_, sample)| sample)
+                .sum::<f32>()
+                / 4.0
+        });
+
+        self.left_buffer.enqueue(SVector::from([left]));
+        self.right_buffer.enqueue(SVector::from([right]));
+    }
+}
+
+impl Component for Psg {
+    fn memory_write(
+        &mut self,
+        address: Address,
+        _address_space: AddressSpaceId,
+        buffer: &[u8],
+    ) -> Result<(), MemoryError> {
+        if STEREO_PORT.contains(&address) {
+            self.stereo = buffer[0];
+        } else {
+            self.write(buffer[0]);
+        }
+
+        Ok(())
+    }
+
+    fn get_audio_channel(&mut self, audio_output_path: &FluxEmuPath) -> SampleSource<'_> {
+        let buffer = if *audio_output_path == self.left_channel {
+            &mut self.left_buffer
+        } else {
+            &mut self.right_buffer
+        };
+
+        SampleSource {
+            source: Box::new(buffer.drain().repeat_last_frame()),
+            sample_rate: (MASTER_CLOCK / TICK_DIVIDER) as f32 / AUDIO_SAMPLE_DIVIDER as f32,
+        }
+    }
+
+    fn synchronize(&mut self, mut context: SynchronizationContext) {
+        for _ in context.allocate(Period::from_num(TICK_DIVIDER) / MASTER_CLOCK, None) {
+            for tone in &mut self.tones {
+                tone.tick();
+            }
+
+            self.noise.tick(self.tones[2].period);
+
+            if self.ticks.is_multiple_of(AUDIO_SAMPLE_DIVIDER) {
+                self.sample_audio();
+            }
+
+            self.ticks = self.ticks.wrapping_add(1);
+        }
+    }
+
+    fn needs_work(&self, delta: Period) -> bool {
+        delta >= Period::from_num(TICK_DIVIDER) / MASTER_CLOCK
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct PsgConfig {
+    pub io_address_space: AddressSpaceId,
+    pub model: Model,
+}
+
+impl<P: Platform> ComponentConfig<P> for PsgConfig {
+    type Component = Psg;
+
+    fn build_component(
+        self,
+        component_builder: ComponentBuilder<'_, P, Self::Component>,
+    ) -> Result<Self::Component, Box<dyn std::error::Error>> {
+        let component_builder =
+            component_builder.set_scheduler_participation(SchedulerParticipation::SchedulerDriven);
+
+        // The Master System is mono, but the Game Gear's headphones are not
+        let (component_builder, left_channel) = component_builder.insert_audio_channel("left");
+        let (component_builder, _) = component_builder.insert_audio_channel("right");
+
+        let component_builder =
+            component_builder.memory_map_component_write(self.io_address_space, COUNTER_PORTS);
+
+        if self.model == Model::GameGear {
+            component_builder.memory_map_component_write(self.io_address_space, STEREO_PORT);
+        }
+
+        Ok(Psg {
+            tones: Default::default(),
+            noise: NoiseChannel {
+                control: 0,
+                counter: 0,
+                flip_flop: false,
+                shift_register: NOISE_RESET,
+            },
+            // Everything starts silent
+            attenuation: [SILENT; 4],
+            latched: 0,
+            stereo: 0xff,
+            ticks: 0,
+            left_channel,
+            left_buffer: AllocRingBuffer::new(1024),
+            right_buffer: AllocRingBuffer::new(1024),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn noise_channel(control: u8) -> NoiseChannel {
+        let mut noise = NoiseChannel {
+            control: 0,
+            counter: 0,
+            flip_flop: false,
+            shift_register: 0,
+        };
+
+        noise.write(control);
+
+        noise
+    }
+
+    #[test]
+    fn periodic_noise() {
+        let mut noise = noise_channel(0);
+
+        // The slowest fixed rate moves the register every 32 ticks, and the
+        // bit put in at the top comes out after 16 moves
+        let outputs: Vec<_> = (0..1024)
+            .map(|_| {
+                noise.tick(0);
+                noise.output()
+            })
+            .collect();
+
+        assert!(!outputs[447]);
+        assert!(outputs[448..480].iter().all(|output| *output));
+        assert!(!outputs[480]);
+        assert_eq!(outputs.iter().filter(|output| **output).count(), 64);
+
+        // The last rate follows tone channel 2 instead
+        let mut noise = noise_channel(0b11);
+
+        for _ in 0..14 * 10 {
+            noise.tick(5);
+        }
+
+        assert!(!noise.output());
+        noise.tick(5);
+        assert!(noise.output());
+    }
+
+    #[test]
+    fn white_noise() {
+        let mut noise = noise_channel(NOISE_WHITE);
+        let mut shifts = 0;
+
+        loop {
+            for _ in 0..32 {
+                noise.tick(0);
+            }
+
+            shifts += 1;
+
+            if noise.shift_register == NOISE_RESET {
+                break;
+            }
+        }
+
+        // Tapping bits 0 and 3 doesn't give the longest sequence 16 bits could
+        assert_eq!(shifts, 57337);
+
+        // Writing the register starts the sequence over
+        noise.tick(0);
+        assert_ne!(noise.shift_register, NOISE_RESET);
+        noise.write(NOISE_WHITE);
+        assert_eq!(noise.shift_register, NOISE_RESET);
+    }
+}
@@ -0,0 +1,27 @@
+use std::fmt::Debug;
+
+use fluxemu_runtime::graphics::GraphicsApi;
+use nalgebra::{DMatrixViewMut, Vector2};
+use palette::Srgba;
+
+pub mod software;
+#[cfg(feature = "vulkan")]
+pub mod vulkan;
+
+pub(crate) trait VdpDisplayBackend: Send + Sync + Debug + Sized + 'static {
+    type GraphicsApi: GraphicsApi;
+
+    /// The Game Gear shows less of the picture than the Master System, so
+    /// the size is only known once the machine is
+    fn new(
+        initialization_data: <Self::GraphicsApi as GraphicsApi>::InitializationData,
+        screen_size: Vector2<usize>,
+    ) -> Self;
+    fn modify_staging_buffer(&mut self, callback: impl FnOnce(DMatrixViewMut<'_, Srgba<u8>>));
+    fn commit_staging_buffer(&mut self);
+    fn access_framebuffer(&mut self) -> &<Self::GraphicsApi as GraphicsApi>::FramebufferTexture;
+}
+
+pub(crate) trait SupportedGraphicsApiVdp: GraphicsApi {
+    type Backend: VdpDisplayBackend<GraphicsApi = Self>;
+}
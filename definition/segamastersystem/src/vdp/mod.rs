@@ -0,0 +1,593 @@
+use std::{
+    any::Any,
+    ops::RangeInclusive,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicU8, Ordering},
+    },
+};
+
+use arrayvec::ArrayVec;
+pub(crate) use backend::SupportedGraphicsApiVdp;
+use backend::VdpDisplayBackend;
+use fluxemu_definition_intel8080::{Intel8080, IrqFlag};
+use fluxemu_runtime::{
+    component::{Component, ComponentConfig, LateInitializedData},
+    machine::builder::{ComponentBuilder, SchedulerParticipation},
+    memory::{Address, AddressSpaceId, MemoryError},
+    path::FluxEmuPath,
+    platform::Platform,
+    scheduler::{Period, SynchronizationContext},
+};
+use nalgebra::Vector2;
+use palette::Srgba;
+use render::{LINE_WIDTH, LineRenderer};
+
+use crate::{MASTER_CLOCK, Model};
+
+mod backend;
+mod render;
+
+/// Reading gives the V and H counters, and writing goes to the PSG
+pub(crate) const COUNTER_PORTS: RangeInclusive<Address> = 0x40..=0x7f;
+/// Data on even ports, control on odd ones
+const VDP_PORTS: RangeInclusive<Address> = 0x80..=0xbf;
+
+const VRAM_SIZE: usize = 0x4000;
+const REGISTER_COUNT: usize = 11;
+/// The Game Gear has 12 bit colors, taking twice the CRAM
+const CRAM_SIZE: usize = 64;
+
+/// Master clocks per pixel
+const PIXEL_DIVIDER: u128 = 10;
+const PIXELS_PER_LINE: u16 = 342;
+const ACTIVE_LINES: u16 = 192;
+/// NTSC
+const LINES_PER_FRAME: u16 = 262;
+/// Where in the line the counters and interrupts are processed, which is the
+/// start of the horizontal blank
+const LINE_INTERRUPT_PIXEL: u16 = 256;
+
+/// The Game Gear LCD only shows the middle of the picture
+const GAME_GEAR_SCREEN_OFFSET: Vector2<usize> = Vector2::new(48, 24);
+const GAME_GEAR_SCREEN_SIZE: Vector2<usize> = Vector2::new(160, 144);
+
+const STATUS_FRAME_INTERRUPT: u8 = 0b1000_0000;
+const STATUS_SPRITE_OVERFLOW: u8 = 0b0100_0000;
+const STATUS_SPRITE_COLLISION: u8 = 0b0010_0000;
+
+const R0_LINE_INTERRUPT_ENABLE: u8 = 0b0001_0000;
+const R1_FRAME_INTERRUPT_ENABLE: u8 = 0b0010_0000;
+
+const CODE_VRAM_READ: u8 = 0;
+const CODE_REGISTER_WRITE: u8 = 2;
+const CODE_CRAM_WRITE: u8 = 3;
+
+/// The CPU's view of the VDP, which reading changes
+#[derive(Debug, Default)]
+struct Port {
+    /// 14 bits into VRAM or CRAM
+    address: u16,
+    /// What the data port is pointed at
+    code: u8,
+    /// The first byte of a control word, waiting for the second
+    latch: Option<u8>,
+    /// Data port reads come out of this, and then refill it
+    read_buffer: u8,
+}
+
+/// The Master System's video display processor, derived from the TMS9918 but
+/// only emulated in the mode 4 every game uses
+#[derive(Debug)]
+pub struct Vdp<G: SupportedGraphicsApiVdp> {
+    backend: Option<G::Backend>,
+    model: Model,
+    vram: Box<[u8; VRAM_SIZE]>,
+    cram: [u8; CRAM_SIZE],
+    /// The Game Gear writes colors a byte at a time but latches the first
+    cram_latch: u8,
+    registers: [u8; REGISTER_COUNT],
+    port: Mutex<Port>,
+    status: AtomicU8,
+    line_interrupt_pending: AtomicBool,
+    line_counter: u8,
+    /// Latched from R9 at the start of each frame
+    vertical_scroll: u8,
+    line: u16,
+    pixel: u16,
+    irq: Arc<IrqFlag>,
+}
+
+impl<G: SupportedGraphicsApiVdp> Vdp<G> {
+    fn update_irq(&self) {
+        let frame = self.status.load(Ordering::Acquire) & STATUS_FRAME_INTERRUPT != 0
+            && self.registers[1] & R1_FRAME_INTERRUPT_ENABLE != 0;
+        let line = self.line_interrupt_pending.load(Ordering::Acquire)
+            && self.registers[0] & R0_LINE_INTERRUPT_ENABLE != 0;
+
+        // Active low
+        self.irq.store(!(frame || line));
+    }
+
+    fn v_counter(&self) -> u8 {
+        // The counter jumps back partway through the vertical blank so it
+        // fits in a byte
+        if self.line <= 0xda {
+            self.line as u8
+        } else {
+            (self.line - 6) as u8
+        }
+    }
+
+    fn h_counter(&self) -> u8 {
+        // FIXME: This should be latched when TH changes, not read live
+        let counter = self.pixel / 2;
+
+        if counter <= 0x93 {
+            counter as u8
+        } else {
+            (counter + 0x55) as u8
+        }
+    }
+
+    fn read_data(&self, avoid_side_effects: bool) -> u8 {
+        let mut port = self.port.lock().unwrap();
+        let data = port.read_buffer;
+
+        if !avoid_side_effects {
+            port.read_buffer = self.vram[usize::from(port.address)];
+            port.address = (port.address + 1) % VRAM_SIZE as u16;
+            port.latch = None;
+        }
+
+        data
+    }
+
+    fn read_status(&self, avoid_side_effects: bool) -> u8 {
+        // The low bits are not driven
+        let status = self.status.load(Ordering::Acquire) | 0x1f;
+
+        if !avoid_side_effects {
+            self.status.store(0, Ordering::Release);
+            self.line_interrupt_pending.store(false, Ordering::Release);
+            self.port.lock().unwrap().latch = None;
+            self.update_irq();
+        }
+
+        status
+    }
+
+    fn write_data(&mut self, data: u8) {
+        let port = self.port.get_mut().unwrap();
+        let address = port.address;
+
+        port.latch = None;
+        port.read_buffer = data;
+        port.address = (address + 1) % VRAM_SIZE as u16;
+
+        if port.code != CODE_CRAM_WRITE {
+            self.vram[usize::from(address)] = data;
+            return;
+        }
+
+        match self.model {
+            Model::MasterSystem => self.cram[usize::from(address) % 32] = data,
+            Model::GameGear => {
+                let address = usize::from(address) % CRAM_SIZE;
+
+                if address.is_multiple_of(2) {
+                    self.cram_latch = data;
+                } else {
+                    self.cram[address - 1] = self.cram_latch;
+                    self.cram[address] = data & 0x0f;
+                }
+            }
+        }
+    }
+
+    fn write_control(&mut self, data: u8) {
+        let port = self.port.get_mut().unwrap();
+
+        let Some(low) = port.latch.take() else {
+            port.latch = Some(data);
+            port.address = (port.address & 0x3f00) | u16::from(data);
+
+            return;
+        };
+
+        port.address = u16::from_le_bytes([low, data & 0x3f]);
+        port.code = data >> 6;
+
+        match port.code {
+            CODE_VRAM_READ => {
+                port.read_buffer = self.vram[usize::from(port.address)];
+                port.address = (port.address + 1) % VRAM_SIZE as u16;
+            }
+            CODE_REGISTER_WRITE => {
+                if let Some(register) = self.registers.get_mut(usize::from(data & 0x0f)) {
+                    *register = low;
+                }
+
+                self.update_irq();
+            }
+            _ => {}
+        }
+    }
+
+    fn color(&self, index: u8) -> Srgba<u8> {
+        let index = usize::from(index);
+
+        match self.model {
+            Model::MasterSystem => {
+                let color = self.cram[index];
+                let [red, green, blue] = [0, 2, 4].map(|shift| ((color >> shift) & 0b11) * 0x55);
+
+                Srgba::new(red, green, blue, 0xff)
+            }
+            Model::GameGear => {
+                let color = u16::from_le_bytes([self.cram[index * 2], self.cram[index * 2 + 1]]);
+                let [red, green, blue] =
+                    [0, 4, 8].map(|shift| ((color >> shift) & 0x0f) as u8 * 0x11);
+
+                Srgba::new(red, green, blue, 0xff)
+            }
+        }
+    }
+
+    fn draw_line(&mut self) {
+        let line = LineRenderer {
+            vram: &self.vram,
+            registers: &self.registers,
+            vertical_scroll: self.vertical_scroll,
+        }
+        .render(self.line as u8);
+
+        let mut status = 0;
+
+        if line.sprite_overflow {
+            status |= STATUS_SPRITE_OVERFLOW;
+        }
+
+        if line.sprite_collision {
+            status |= STATUS_SPRITE_COLLISION;
+        }
+
+        self.status.fetch_or(status, Ordering::AcqRel);
+
+        let (offset, size) = match self.model {
+            Model::MasterSystem => (
+                Vector2::zeros(),
+                Vector2::new(LINE_WIDTH, usize::from(ACTIVE_LINES)),
+            ),
+            Model::GameGear => (GAME_GEAR_SCREEN_OFFSET, GAME_GEAR_SCREEN_SIZE),
+        };
+
+        let Some(y) = usize::from(self.line)
+            .checked_sub(offset.y)
+            .filter(|y| *y < size.y)
+        else {
+            return;
+        };
+
+        let colors: ArrayVec<_, LINE_WIDTH> = line.colors[offset.x..offset.x + size.x]
+            .iter()
+            .map(|index| self.color(*index))
+            .collect();
+
+        self.backend
+            .as_mut()
+            .unwrap()
+            .modify_staging_buffer(|mut staging_buffer| {
+                for (x, color) in colors.into_iter().enumerate() {
+                    staging_buffer[(x, y)] = color;
+                }
+            });
+    }
+
+    fn end_line(&mut self) {
+        if self.line <= ACTIVE_LINES {
+            let (counter, underflowed) = self.line_counter.overflowing_sub(1);
+
+            if underflowed {
+                self.line_counter = self.registers[10];
+                self.line_interrupt_pending.store(true, Ordering::Release);
+            } else {
+                self.line_counter = counter;
+            }
+        } else {
+            self.line_counter = self.registers[10];
+        }
+
+        if self.line == ACTIVE_LINES {
+            self.status
+                .fetch_or(STATUS_FRAME_INTERRUPT, Ordering::AcqRel);
+            self.backend.as_mut().unwrap().commit_staging_buffer();
+        }
+
+        self.update_irq();
+    }
+
+    fn tick(&mut self) {
+        if self.pixel == 0 {
+            if self.line == 0 {
+                self.vertical_scroll = self.registers[9];
+            }
+
+            if self.line < ACTIVE_LINES {
+                self.draw_line();
+            }
+        }
+
+        if self.pixel == LINE_INTERRUPT_PIXEL {
+            self.end_line();
+        }
+
+        self.pixel += 1;
+
+        if self.pixel == PIXELS_PER_LINE {
+            self.pixel = 0;
+            self.line = (self.line + 1) % LINES_PER_FRAME;
+        }
+    }
+}
+
+impl<G: SupportedGraphicsApiVdp> Component for Vdp<G> {
+    fn memory_read(
+        &self,
+        address: Address,
+        _address_space: AddressSpaceId,
+        avoid_side_effects: bool,
+        buffer: &mut [u8],
+    ) -> Result<(), MemoryError> {
+        let odd = !address.is_multiple_of(2);
+
+        buffer[0] = if COUNTER_PORTS.contains(&address) {
+            if odd {
+                self.h_counter()
+            } else {
+                self.v_counter()
+            }
+        } else if odd {
+            self.read_status(avoid_side_effects)
+        } else {
+            self.read_data(avoid_side_effects)
+        };
+
+        Ok(())
+    }
+
+    fn memory_write(
+        &mut self,
+        address: Address,
+        _address_space: AddressSpaceId,
+        buffer: &[u8],
+    ) -> Result<(), MemoryError> {
+        if !address.is_multiple_of(2) {
+            self.write_control(buffer[0]);
+        } else {
+            self.write_data(buffer[0]);
+        }
+
+        Ok(())
+    }
+
+    fn access_framebuffer(&mut self, _path: &FluxEmuPath) -> &dyn Any {
+        self.backend.as_mut().unwrap().access_framebuffer()
+    }
+
+    fn synchronize(&mut self, mut context: SynchronizationContext) {
+        for _ in context.allocate(Period::from_num(PIXEL_DIVIDER) / MASTER_CLOCK, None) {
+            self.tick();
+        }
+    }
+
+    fn needs_work(&self, delta: Period) -> bool {
+        delta >= Period::from_num(PIXEL_DIVIDER) / MASTER_CLOCK
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct VdpConfig {
+    pub cpu: FluxEmuPath,
+    pub io_address_space: AddressSpaceId,
+    pub model: Model,
+}
+
+impl<P: Platform<GraphicsApi: SupportedGraphicsApiVdp>> ComponentConfig<P> for VdpConfig {
+    type Component = Vdp<P::GraphicsApi>;
+
+    fn late_initialize(component: &mut Self::Component, data: &LateInitializedData<P>) {
+        let screen_size = match component.model {
+            Model::MasterSystem => Vector2::new(LINE_WIDTH, usize::from(ACTIVE_LINES)),
+            Model::GameGear => GAME_GEAR_SCREEN_SIZE,
+        };
+
+        component.backend = Some(VdpDisplayBackend::new(
+            data.component_graphics_initialization_data.clone(),
+            screen_size,
+        ));
+    }
+
+    fn build_component(
+        self,
+        component_builder: ComponentBuilder<'_, P, Self::Component>,
+    ) -> Result<Self::Component, Box<dyn std::error::Error>> {
+        let irq = component_builder
+            .interact::<Intel8080, _>(&self.cpu, Intel8080::irq)
+            .unwrap();
+
+        let display = match self.model {
+            Model::MasterSystem => "tv",
+            Model::GameGear => "lcd",
+        };
+
+        let (component_builder, _) = component_builder
+            .set_scheduler_participation(SchedulerParticipation::SchedulerDriven)
+            .insert_display(display);
+
+        component_builder
+            .memory_map_component_read(self.io_address_space, COUNTER_PORTS)
+            .memory_map_component(self.io_address_space, VDP_PORTS);
+
+        Ok(Vdp {
+            backend: None,
+            model: self.model,
+            vram: Box::new([0; VRAM_SIZE]),
+            cram: [0; CRAM_SIZE],
+            cram_latch: 0,
+            registers: [0; REGISTER_COUNT],
+            port: Mutex::default(),
+            status: AtomicU8::new(0),
+            line_interrupt_pending: AtomicBool::new(false),
+            line_counter: 0xff,
+            vertical_scroll: 0,
+            line: 0,
+            pixel: 0,
+            irq,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fluxemu_definition_intel8080::Intel8080Config;
+    use fluxemu_runtime::{graphics::software::Software, machine::Machine, scheduler::Frequency};
+
+    use super::*;
+    use crate::CPU_DIVIDER;
+
+    const CODE_VRAM_WRITE: u8 = 1;
+
+    fn machine(model: Model) -> (Arc<Machine>, FluxEmuPath) {
+        let (machine, cpu_address_space) = Machine::build_test_minimal().insert_address_space(16);
+        let (machine, io_address_space) = machine.insert_address_space(8);
+
+        let (machine, cpu) = machine.insert_component(
+            "z80",
+            Intel8080Config::z80(
+                Frequency::from_num(MASTER_CLOCK) / CPU_DIVIDER,
+                cpu_address_space,
+                io_address_space,
+            ),
+        );
+
+        let (machine, vdp) = machine.insert_component(
+            "vdp",
+            VdpConfig {
+                cpu,
+                io_address_space,
+                model,
+            },
+        );
+
+        (machine.build(()), vdp)
+    }
+
+    impl Vdp<Software> {
+        fn set_register(&mut self, register: u8, value: u8) {
+            self.write_control(value);
+            self.write_control((CODE_REGISTER_WRITE << 6) | register);
+        }
+
+        fn upload(&mut self, code: u8, address: u16, data: &[u8]) {
+            let [low, high] = address.to_le_bytes();
+
+            self.write_control(low);
+            self.write_control((code << 6) | high);
+
+            for byte in data {
+                self.write_data(*byte);
+            }
+        }
+
+        fn run_until(&mut self, line: u16, pixel: u16) {
+            while (self.line, self.pixel) != (line, pixel) {
+                self.tick();
+            }
+        }
+    }
+
+    #[test]
+    fn line_interrupt_counter() {
+        let (machine, vdp) = machine(Model::MasterSystem);
+
+        machine
+            .interact_mut::<Vdp<Software>, _>(&vdp, |vdp| {
+                vdp.set_register(0, R0_LINE_INTERRUPT_ENABLE);
+                vdp.set_register(10, 3);
+
+                // The counter is only loaded from R10 outside the active
+                // display
+                vdp.run_until(ACTIVE_LINES + 1, 0);
+                vdp.run_until(0, 0);
+                vdp.read_status(false);
+
+                // Underflowing at the end of every fourth line
+                for line in [3, 7, 11] {
+                    vdp.run_until(line, LINE_INTERRUPT_PIXEL);
+                    assert!(!vdp.irq.interrupt_required(), "line {line}");
+
+                    vdp.tick();
+                    assert!(vdp.irq.interrupt_required(), "line {line}");
+
+                    // Reading the status acknowledges it
+                    vdp.read_status(false);
+                    assert!(!vdp.irq.interrupt_required());
+                }
+
+                // Disabling it masks the interrupt but leaves it pending
+                vdp.set_register(0, 0);
+                vdp.run_until(16, 0);
+                assert!(!vdp.irq.interrupt_required());
+
+                vdp.set_register(0, R0_LINE_INTERRUPT_ENABLE);
+                assert!(vdp.irq.interrupt_required());
+
+                // Changing R10 takes effect on the next reload
+                vdp.read_status(false);
+                vdp.set_register(10, 0);
+                vdp.run_until(20, 0);
+                assert!(vdp.irq.interrupt_required());
+                vdp.read_status(false);
+
+                vdp.run_until(20, LINE_INTERRUPT_PIXEL + 1);
+                assert!(vdp.irq.interrupt_required());
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn game_gear_crop() {
+        let (machine, vdp) = machine(Model::GameGear);
+
+        machine
+            .interact_mut::<Vdp<Software>, _>(&vdp, |vdp| {
+                vdp.set_register(1, 0b0100_0000);
+                // Name table at 0x3800, sprites at 0x3f00
+                vdp.set_register(2, 0x0e);
+                vdp.set_register(5, 0x7e);
+
+                // The background is red, and pattern 1 is solid white
+                vdp.upload(CODE_CRAM_WRITE, 0, &[0x0f, 0x00]);
+                vdp.upload(CODE_CRAM_WRITE, 17 * 2, &[0xff, 0x0f]);
+                vdp.upload(CODE_VRAM_WRITE, 32, &[0xff, 0x00, 0x00, 0x00].repeat(8));
+
+                // One sprite on the top left corner of the LCD, then the end of the
+                // sprite list
+                vdp.upload(CODE_VRAM_WRITE, 0x3f00, &[23, 0xd0]);
+                vdp.upload(CODE_VRAM_WRITE, 0x3f80, &[48, 1]);
+
+                vdp.run_until(ACTIVE_LINES + 1, 0);
+
+                let framebuffer = &vdp.backend.as_ref().unwrap().framebuffer;
+                let red = Srgba::new(0xff, 0x00, 0x00, 0xff);
+                let white = Srgba::new(0xff, 0xff, 0xff, 0xff);
+
+                assert_eq!(framebuffer.shape(), (160, 144));
+                assert_eq!([framebuffer[(0, 0)], framebuffer[(7, 7)]], [white; 2]);
+                assert_eq!([framebuffer[(8, 0)], framebuffer[(0, 8)]], [red; 2]);
+                assert_eq!(framebuffer[(159, 143)], red);
+            })
+            .unwrap();
+    }
+}
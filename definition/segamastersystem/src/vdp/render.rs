@@ -0,0 +1,219 @@
+use arrayvec::ArrayVec;
+
+use super::{REGISTER_COUNT, VRAM_SIZE};
+
+pub(super) const LINE_WIDTH: usize = 256;
+/// Scrolling wraps around the name table, which is 28 tiles tall
+const NAME_TABLE_HEIGHT: u16 = 28 * 8;
+const SPRITE_COUNT: usize = 64;
+const SPRITES_PER_LINE: usize = 8;
+/// A Y coordinate of this ends the sprite list, in the 192 line mode
+const SPRITE_LIST_END: u8 = 0xd0;
+/// Sprites use the second half of CRAM
+const SPRITE_PALETTE: u8 = 16;
+
+const R0_SHIFT_SPRITES: u8 = 0b0000_1000;
+const R0_HIDE_LEFT_COLUMN: u8 = 0b0010_0000;
+const R0_LOCK_TOP_ROWS: u8 = 0b0100_0000;
+const R0_LOCK_RIGHT_COLUMNS: u8 = 0b1000_0000;
+const R1_ZOOM_SPRITES: u8 = 0b0000_0001;
+const R1_TALL_SPRITES: u8 = 0b0000_0010;
+const R1_DISPLAY_ENABLE: u8 = 0b0100_0000;
+
+const NAME_PATTERN: u16 = 0x01ff;
+const NAME_X_FLIP: u16 = 0x0200;
+const NAME_Y_FLIP: u16 = 0x0400;
+const NAME_SPRITE_PALETTE: u16 = 0x0800;
+const NAME_PRIORITY: u16 = 0x1000;
+
+/// A line as CRAM indices, and what the sprites did while drawing it
+#[derive(Debug)]
+pub(super) struct Line {
+    pub colors: [u8; LINE_WIDTH],
+    pub sprite_overflow: bool,
+    pub sprite_collision: bool,
+}
+
+/// Everything that goes into drawing a line in mode 4
+#[derive(Debug)]
+pub(super) struct LineRenderer<'a> {
+    pub vram: &'a [u8; VRAM_SIZE],
+    pub registers: &'a [u8; REGISTER_COUNT],
+    /// Vertical scroll only takes effect at the start of the frame
+    pub vertical_scroll: u8,
+}
+
+impl LineRenderer<'_> {
+    pub fn render(&self, line: u8) -> Line {
+        let backdrop = SPRITE_PALETTE | (self.registers[7] & 0x0f);
+        let mut output = Line {
+            colors: [backdrop; LINE_WIDTH],
+            sprite_overflow: false,
+            sprite_collision: false,
+        };
+
+        if self.registers[1] & R1_DISPLAY_ENABLE == 0 {
+            return output;
+        }
+
+        let sprites = self.render_sprites(line, &mut output);
+
+        for (x, color) in output.colors.iter_mut().enumerate() {
+            let (background, priority) = self.background_pixel(x as u8, line);
+
+            *color = match sprites[x] {
+                // High priority tiles only cover sprites where they are not
+                // transparent
+                Some(sprite) if !(priority && background & 0x0f != 0) => sprite,
+                _ => background,
+            };
+        }
+
+        if self.registers[0] & R0_HIDE_LEFT_COLUMN != 0 {
+            output.colors[..8].fill(backdrop);
+        }
+
+        output
+    }
+
+    fn name_table_address(&self) -> usize {
+        usize::from(self.registers[2] & 0x0e) << 10
+    }
+
+    fn sprite_table_address(&self) -> usize {
+        usize::from(self.registers[5] & 0x7e) << 7
+    }
+
+    fn sprite_pattern_base(&self) -> u16 {
+        u16::from(self.registers[6] & 0x04) << 6
+    }
+
+    /// A 4 bit color out of the planar pattern data
+    fn pattern_pixel(&self, pattern: u16, row: u8, column: u8) -> u8 {
+        let address = usize::from(pattern) * 32 + usize::from(row) * 4;
+
+        (0..4).fold(0, |color, plane| {
+            let bit = (self.vram[(address + plane) % VRAM_SIZE] >> (7 - column)) & 1;
+
+            color | (bit << plane)
+        })
+    }
+
+    /// The CRAM index of the background at a point, and if it has priority
+    fn background_pixel(&self, x: u8, line: u8) -> (u8, bool) {
+        let horizontal_scroll = if self.registers[0] & R0_LOCK_TOP_ROWS != 0 && line < 16 {
+            0
+        } else {
+            self.registers[8]
+        };
+        let vertical_scroll = if self.registers[0] & R0_LOCK_RIGHT_COLUMNS != 0 && x >= 24 * 8 {
+            0
+        } else {
+            self.vertical_scroll
+        };
+
+        let column = x.wrapping_sub(horizontal_scroll);
+        let row = (u16::from(line) + u16::from(vertical_scroll)) % NAME_TABLE_HEIGHT;
+
+        let entry_address =
+            self.name_table_address() + usize::from(row / 8) * 64 + usize::from(column / 8) * 2;
+        let entry = u16::from_le_bytes([
+            self.vram[entry_address % VRAM_SIZE],
+            self.vram[(entry_address + 1) % VRAM_SIZE],
+        ]);
+
+        let mut tile_row = (row % 8) as u8;
+        let mut tile_column = column % 8;
+
+        if entry & NAME_Y_FLIP != 0 {
+            tile_row = 7 - tile_row;
+        }
+
+        if entry & NAME_X_FLIP != 0 {
+            tile_column = 7 - tile_column;
+        }
+
+        let mut color = self.pattern_pixel(entry & NAME_PATTERN, tile_row, tile_column);
+
+        if entry & NAME_SPRITE_PALETTE != 0 {
+            color |= SPRITE_PALETTE;
+        }
+
+        (color, entry & NAME_PRIORITY != 0)
+    }
+
+    /// Draws the sprites on a line, where the first one in the table wins
+    fn render_sprites(&self, line: u8, output: &mut Line) -> [Option<u8>; LINE_WIDTH] {
+        let mut pixels = [None; LINE_WIDTH];
+
+        let zoom = u16::from(self.registers[1] & R1_ZOOM_SPRITES != 0) + 1;
+        let tall = self.registers[1] & R1_TALL_SPRITES != 0;
+        let height = if tall { 16 } else { 8 } * zoom;
+
+        let table = self.sprite_table_address();
+        let mut sprites = ArrayVec::<_, SPRITES_PER_LINE>::new();
+
+        for index in 0..SPRITE_COUNT {
+            let y = self.vram[table + index];
+
+            if y == SPRITE_LIST_END {
+                break;
+            }
+
+            // Sprites start a line below their coordinate, and ones near the
+            // bottom wrap around to the top
+            let row = u16::from(line.wrapping_sub(y).wrapping_sub(1));
+
+            if row >= height {
+                continue;
+            }
+
+            if sprites.is_full() {
+                output.sprite_overflow = true;
+                break;
+            }
+
+            sprites.push((index, row / zoom));
+        }
+
+        for (index, row) in sprites {
+            let x = self.vram[table + 0x80 + index * 2];
+            let mut pattern = u16::from(self.vram[table + 0x81 + index * 2]);
+
+            if tall {
+                pattern &= !1;
+            }
+
+            pattern = self.sprite_pattern_base() + pattern + row / 8;
+
+            let x = if self.registers[0] & R0_SHIFT_SPRITES != 0 {
+                i16::from(x) - 8
+            } else {
+                i16::from(x)
+            };
+
+            for offset in 0..8 * zoom {
+                let Ok(screen_x) = usize::try_from(x + offset as i16) else {
+                    continue;
+                };
+
+                if screen_x >= LINE_WIDTH {
+                    break;
+                }
+
+                let color = self.pattern_pixel(pattern, (row % 8) as u8, (offset / zoom) as u8);
+
+                if color == 0 {
+                    continue;
+                }
+
+                match pixels[screen_x] {
+                    Some(_) => output.sprite_collision = true,
+                    None => pixels[screen_x] = Some(SPRITE_PALETTE | color),
+                }
+            }
+        }
+
+        pixels
+    }
+}
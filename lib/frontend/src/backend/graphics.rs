@@ -1,7 +1,9 @@
 use std::fmt::Debug;
 
 use egui::FullOutput;
-use fluxemu_runtime::{graphics::GraphicsApi, machine::Machine, platform::Platform};
+use fluxemu_runtime::{
+    graphics::GraphicsApi, machine::Machine, path::FluxEmuPath, platform::Platform,
+};
 use nalgebra::Vector2;
 
 use crate::environment::Environment;
@@ -39,6 +41,14 @@ pub trait GraphicsRuntime<P: Platform>: Debug + Sized + 'static {
 
     fn max_texture_side_size(&self) -> u32;
 
+    /// How much of the surface a machine display took up on the last frame,
+    /// from the top left corner in physical pixels
+    ///
+    /// [None] if it was stretched across all of it
+    fn presented_display_size(&self, _display_path: &FluxEmuPath) -> Option<Vector2<f32>> {
+        None
+    }
+
     /// Notification that the render surface resized
     fn display_resized(&mut self) {}
 }
@@ -0,0 +1,407 @@
+use std::collections::HashMap;
+
+use egui::{Color32, Mesh, Pos2, Rect, TextureId, epaint::Vertex};
+use fluxemu_runtime::{
+    graphics::{GraphicsApi, software::Software},
+    machine::{Machine, graphics::DisplayRotation},
+    path::FluxEmuPath,
+};
+use nalgebra::{DMatrixViewMut, Point2, Scalar, Vector2};
+use palette::{
+    Srgba,
+    cast::{ComponentOrder, Packed},
+};
+
+/// Undoing how a machine display is mounted so it shows upright
+pub trait DisplayRotationExt: Copy {
+    /// Size of a display once turned upright
+    fn presented_size(self, size: Vector2<usize>) -> Vector2<usize>;
+
+    /// Where a pixel of a display ends up once turned upright
+    fn presented_pixel(self, size: Vector2<usize>, pixel: Point2<usize>) -> Point2<usize>;
+
+    /// Maps a normalized position on the upright display back onto the
+    /// display itself
+    fn display_position(self, presented_position: Point2<f32>) -> Point2<f32>;
+}
+
+impl DisplayRotationExt for DisplayRotation {
+    fn presented_size(self, size: Vector2<usize>) -> Vector2<usize> {
+        match self {
+            DisplayRotation::None => size,
+            DisplayRotation::Left | DisplayRotation::Right => Vector2::new(size.y, size.x),
+        }
+    }
+
+    fn presented_pixel(self, size: Vector2<usize>, pixel: Point2<usize>) -> Point2<usize> {
+        match self {
+            DisplayRotation::None => pixel,
+            // Turn it clockwise
+            DisplayRotation::Left => Point2::new(size.y - 1 - pixel.y, pixel.x),
+            // Turn it counterclockwise
+            DisplayRotation::Right => Point2::new(pixel.y, size.x - 1 - pixel.x),
+        }
+    }
+
+    fn display_position(self, presented_position: Point2<f32>) -> Point2<f32> {
+        match self {
+            DisplayRotation::None => presented_position,
+            DisplayRotation::Left => Point2::new(presented_position.y, 1.0 - presented_position.x),
+            DisplayRotation::Right => Point2::new(1.0 - presented_position.y, presented_position.x),
+        }
+    }
+}
+
+/// Where a display was presented on a surface and how it was turned upright
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DisplayViewport {
+    /// Size in surface pixels, starting from the top left corner
+    pub size: Vector2<f32>,
+    pub rotation: DisplayRotation,
+}
+
+impl DisplayViewport {
+    /// Maps a position on the surface onto the display, normalized to its size
+    /// and held to its edges
+    pub fn display_position(&self, surface_position: Point2<f32>) -> Point2<f32> {
+        let presented_position = surface_position
+            .coords
+            .component_div(&self.size)
+            .map(|axis| axis.clamp(0.0, 1.0));
+
+        self.rotation.display_position(presented_position.into())
+    }
+}
+
+/// The rotation the frontend has to undo for a machine display
+pub fn display_rotation(machine: &Machine, display_path: &FluxEmuPath) -> DisplayRotation {
+    machine
+        .display_rotations
+        .get(display_path)
+        .copied()
+        .unwrap_or_default()
+}
+
+/// A mesh covering `rect` that shows the display in `texture_id` upright, for
+/// graphics apis that present displays through egui
+pub fn display_mesh(rotation: DisplayRotation, texture_id: TextureId, rect: Rect) -> Mesh {
+    let mut mesh = Mesh::with_texture(texture_id);
+
+    for corner in [
+        rect.left_top(),
+        rect.right_top(),
+        rect.right_bottom(),
+        rect.left_bottom(),
+    ] {
+        let normalized = (corner - rect.min) / rect.size();
+        let uv = rotation.display_position(Point2::new(normalized.x, normalized.y));
+
+        mesh.vertices.push(Vertex {
+            pos: corner,
+            uv: Pos2::new(uv.x, uv.y),
+            color: Color32::WHITE,
+        });
+    }
+
+    mesh.add_triangle(0, 1, 2);
+    mesh.add_triangle(0, 2, 3);
+
+    mesh
+}
+
+/// Draw every display of a software rendered machine, stretched across the
+/// surface and turned upright
+///
+/// Returns how much of the surface each display took up
+pub fn draw_software_displays<P: ComponentOrder<Srgba<u8>, u32> + Scalar>(
+    mut surface: DMatrixViewMut<Packed<P, u32>>,
+    integer_scaling: bool,
+    machine: &Machine,
+) -> HashMap<FluxEmuPath, Vector2<f32>> {
+    let surface_size = Vector2::new(surface.nrows(), surface.ncols());
+    let mut presented_sizes = HashMap::new();
+
+    for display_path in machine.displays.iter() {
+        let rotation = display_rotation(machine, display_path);
+
+        machine
+            .interact_dyn_mut(display_path, |component| {
+                let display = component.access_framebuffer(display_path);
+
+                let display: &<Software as GraphicsApi>::FramebufferTexture =
+                    display.downcast_ref().unwrap();
+
+                let display_size = Vector2::new(display.nrows(), display.ncols());
+                let presented_size = rotation.presented_size(display_size);
+
+                let scaling = if integer_scaling {
+                    surface_size.component_div(&presented_size).cast::<f32>()
+                } else {
+                    surface_size
+                        .cast::<f32>()
+                        .component_div(&presented_size.cast::<f32>())
+                };
+
+                presented_sizes.insert(
+                    display_path.clone(),
+                    presented_size.cast::<f32>().component_mul(&scaling),
+                );
+
+                // Iterate over each pixel in the display component buffer
+                for x in 0..display.nrows() {
+                    for y in 0..display.ncols() {
+                        let source_pixel = display[(x, y)];
+                        let position = rotation.presented_pixel(display_size, Point2::new(x, y));
+
+                        let dest_start = position
+                            .coords
+                            .cast::<f32>()
+                            .component_mul(&scaling)
+                            .try_cast::<usize>()
+                            .unwrap()
+                            .zip_map(&surface_size, std::cmp::min);
+
+                        let dest_end = position
+                            .coords
+                            .cast::<f32>()
+                            .add_scalar(1.0)
+                            .component_mul(&scaling)
+                            .try_cast::<usize>()
+                            .unwrap()
+                            .zip_map(&surface_size, std::cmp::min);
+
+                        // Fill the destination pixels with the source pixel
+                        let mut destination_pixels = surface.view_mut(
+                            (dest_start.x, dest_start.y),
+                            (dest_end.x - dest_start.x, dest_end.y - dest_start.y),
+                        );
+
+                        destination_pixels.fill(Packed::pack(source_pixel));
+                    }
+                }
+            })
+            .unwrap();
+    }
+
+    presented_sizes
+}
+
+#[cfg(test)]
+mod tests {
+    use fluxemu_runtime::{
+        component::{Component, ComponentConfig},
+        machine::builder::ComponentBuilder,
+        platform::Platform,
+    };
+    use nalgebra::DMatrix;
+    use palette::rgb::channels::Rgba;
+
+    use super::*;
+
+    const RED: Srgba<u8> = Srgba::new(255, 0, 0, 255);
+    const BLUE: Srgba<u8> = Srgba::new(0, 0, 255, 255);
+
+    #[derive(Debug)]
+    struct TestDisplay {
+        framebuffer: DMatrix<Srgba<u8>>,
+    }
+
+    impl Component for TestDisplay {
+        fn access_framebuffer(&mut self, _path: &FluxEmuPath) -> &dyn std::any::Any {
+            &self.framebuffer
+        }
+    }
+
+    #[derive(Debug)]
+    struct TestDisplayConfig {
+        rotation: DisplayRotation,
+    }
+
+    impl<P: Platform> ComponentConfig<P> for TestDisplayConfig {
+        type Component = TestDisplay;
+
+        fn build_component(
+            self,
+            component_builder: ComponentBuilder<P, Self::Component>,
+        ) -> Result<Self::Component, Box<dyn std::error::Error>> {
+            component_builder.insert_rotated_display("display", self.rotation);
+
+            // Two pixels side by side, red on the left
+            Ok(TestDisplay {
+                framebuffer: DMatrix::from_column_slice(2, 1, &[RED, BLUE]),
+            })
+        }
+    }
+
+    fn present(
+        rotation: DisplayRotation,
+        surface_size: Vector2<usize>,
+    ) -> (DMatrix<Srgba<u8>>, Vector2<f32>) {
+        let (machine, _) = Machine::build_test_minimal()
+            .insert_component("test_display", TestDisplayConfig { rotation });
+        let machine = machine.build(());
+
+        let mut surface = DMatrix::from_element(
+            surface_size.x,
+            surface_size.y,
+            Packed::<Rgba, u32>::pack(Srgba::new(0, 0, 0, 255)),
+        );
+        let presented_sizes = draw_software_displays(surface.as_view_mut(), true, &machine);
+        let presented_size = *presented_sizes.values().next().unwrap();
+
+        (surface.map(|pixel| pixel.unpack()), presented_size)
+    }
+
+    #[test]
+    fn presented_pixels_follow_rotation() {
+        let size = Vector2::new(3, 2);
+
+        for (rotation, presented_size, top_left, bottom_right) in [
+            (
+                DisplayRotation::None,
+                Vector2::new(3, 2),
+                Point2::new(0, 0),
+                Point2::new(2, 1),
+            ),
+            (
+                DisplayRotation::Left,
+                Vector2::new(2, 3),
+                Point2::new(1, 0),
+                Point2::new(0, 2),
+            ),
+            (
+                DisplayRotation::Right,
+                Vector2::new(2, 3),
+                Point2::new(0, 2),
+                Point2::new(1, 0),
+            ),
+        ] {
+            assert_eq!(rotation.presented_size(size), presented_size);
+            assert_eq!(
+                rotation.presented_pixel(size, Point2::new(0, 0)),
+                top_left,
+                "{rotation:?}"
+            );
+            assert_eq!(
+                rotation.presented_pixel(size, Point2::new(2, 1)),
+                bottom_right,
+                "{rotation:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn display_positions_undo_presented_pixels() {
+        let size = Vector2::new(4, 2);
+
+        for rotation in [
+            DisplayRotation::None,
+            DisplayRotation::Left,
+            DisplayRotation::Right,
+        ] {
+            let presented_size = rotation.presented_size(size).cast::<f32>();
+
+            for x in 0..size.x {
+                for y in 0..size.y {
+                    let presented = rotation.presented_pixel(size, Point2::new(x, y));
+                    // Sample the center of the presented pixel
+                    let presented_position = presented
+                        .cast::<f32>()
+                        .coords
+                        .add_scalar(0.5)
+                        .component_div(&presented_size);
+
+                    let position = rotation
+                        .display_position(presented_position.into())
+                        .coords
+                        .component_mul(&size.cast())
+                        .map(|axis| axis.floor() as usize);
+
+                    assert_eq!(position, Vector2::new(x, y), "{rotation:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn display_mesh_samples_rotated_corners() {
+        let rect = Rect::from_min_size(Pos2::ZERO, egui::vec2(100.0, 50.0));
+        let mesh = display_mesh(DisplayRotation::Left, TextureId::User(0), rect);
+
+        assert_eq!(mesh.texture_id, TextureId::User(0));
+        assert_eq!(mesh.indices.len(), 6);
+        // The bottom left of the display is what ends up in the top left
+        assert_eq!(mesh.vertices[0].pos, rect.left_top());
+        assert_eq!(mesh.vertices[0].uv, Pos2::new(0.0, 1.0));
+        assert_eq!(mesh.vertices[1].pos, rect.right_top());
+        assert_eq!(mesh.vertices[1].uv, Pos2::new(0.0, 0.0));
+    }
+
+    #[test]
+    fn upright_display_is_presented_as_is() {
+        let (surface, _) = present(DisplayRotation::None, Vector2::new(4, 2));
+
+        assert_eq!(surface[(0, 0)], RED);
+        assert_eq!(surface[(1, 1)], RED);
+        assert_eq!(surface[(2, 0)], BLUE);
+        assert_eq!(surface[(3, 1)], BLUE);
+    }
+
+    #[test]
+    fn rotated_displays_are_presented_upright() {
+        // Turned clockwise the left pixel ends up on top
+        let (surface, _) = present(DisplayRotation::Left, Vector2::new(2, 4));
+
+        assert_eq!(surface[(0, 0)], RED);
+        assert_eq!(surface[(1, 1)], RED);
+        assert_eq!(surface[(0, 2)], BLUE);
+        assert_eq!(surface[(1, 3)], BLUE);
+
+        // Turned counterclockwise it ends up on the bottom
+        let (surface, _) = present(DisplayRotation::Right, Vector2::new(2, 4));
+
+        assert_eq!(surface[(0, 0)], BLUE);
+        assert_eq!(surface[(1, 1)], BLUE);
+        assert_eq!(surface[(0, 2)], RED);
+        assert_eq!(surface[(1, 3)], RED);
+    }
+
+    #[test]
+    fn integer_scaling_leaves_part_of_the_surface() {
+        let (surface, presented_size) = present(DisplayRotation::None, Vector2::new(5, 3));
+
+        // Each axis is scaled by a whole number on its own
+        assert_eq!(presented_size, Vector2::new(4.0, 3.0));
+        assert_eq!(surface[(3, 2)], BLUE);
+        assert_eq!(surface[(4, 0)], Srgba::new(0, 0, 0, 255));
+    }
+
+    #[test]
+    fn viewport_maps_surface_positions_onto_the_display() {
+        let viewport = DisplayViewport {
+            size: Vector2::new(200.0, 100.0),
+            rotation: DisplayRotation::None,
+        };
+
+        assert_eq!(
+            viewport.display_position(Point2::new(50.0, 25.0)),
+            Point2::new(0.25, 0.25)
+        );
+        // Past the edge of the display, such as the letterboxing
+        assert_eq!(
+            viewport.display_position(Point2::new(300.0, 50.0)),
+            Point2::new(1.0, 0.5)
+        );
+
+        // The top right of a display turned clockwise was its top left
+        let viewport = DisplayViewport {
+            rotation: DisplayRotation::Left,
+            ..viewport
+        };
+
+        assert_eq!(
+            viewport.display_position(Point2::new(200.0, 0.0)),
+            Point2::new(0.0, 0.0)
+        );
+    }
+}
@@ -1,12 +1,12 @@
 use std::{
-    collections::{BTreeMap, BTreeSet},
+    collections::{BTreeMap, BTreeSet, HashMap},
     io::{Read, Write},
     path::PathBuf,
     sync::LazyLock,
 };
 
 use audio::AudioSettings;
-use fluxemu_runtime::input::Input;
+use fluxemu_runtime::{input::Input, program::ProgramId};
 use ron::ser::PrettyConfig;
 use serde::{Deserialize, Serialize};
 use serde_inline_default::serde_inline_default;
@@ -64,6 +64,10 @@ pub struct Environment {
     #[serde(default)]
     /// Audio settings
     pub audio_settings: AudioSettings,
+    #[serde(default)]
+    /// Quirks the user picked for programs instead of the ones from the
+    /// database, as RON
+    pub quirks: HashMap<ProgramId, String>,
     #[serde_inline_default(Environment::default().file_browser_home_directory)]
     /// The folder that the gui will show initially
     pub file_browser_home_directory: PathBuf,
@@ -103,6 +107,7 @@ impl Default for Environment {
             hotkeys: Default::default(),
             graphics_setting: Default::default(),
             audio_settings: Default::default(),
+            quirks: Default::default(),
             file_browser_home_directory: STORAGE_DIRECTORY.clone(),
             log_location: STORAGE_DIRECTORY.join("log"),
             database_location: STORAGE_DIRECTORY.join("database.redb"),
@@ -7,19 +7,20 @@ use std::{
 
 use fluxemu_runtime::{
     graphics::GraphicsApi,
-    input::{RealGamepad, RealGamepadId},
+    input::{Input, InputState, RealGamepad, RealGamepadId, pointer::PointerInput},
     machine::{Machine, graphics::GraphicsRequirements},
     persistence::SnapshotSlot,
     program::{ProgramManager, ProgramSpecification},
 };
-use nalgebra::Vector2;
+use nalgebra::{Point2, Vector2};
 use ringbuffer::{ConstGenericRingBuffer, RingBuffer};
 use rustc_hash::FxBuildHasher;
 
 use crate::{
-    EguiWindowingIntegration, GraphicsRuntime, Hotkey, MachineFactories, PlatformExt,
-    WindowingHandle,
+    DisplayViewport, EguiWindowingIntegration, GraphicsRuntime, Hotkey, MachineFactories,
+    PlatformExt, WindowingHandle,
     backend::AudioRuntime,
+    display_rotation,
     environment::Environment,
     gui::{GuiState, MenuOutput},
 };
@@ -49,7 +50,7 @@ struct GamepadData {
 /// Frontend for the emulator
 pub struct Frontend<P: PlatformExt> {
     // Current machine
-    machine: Option<Arc<Machine>>,
+    pub(crate) machine: Option<Arc<Machine>>,
     /// Gamepads connected
     gamepads: HashMap<RealGamepadId, GamepadData, FxBuildHasher>,
     /// The rom manager in use
@@ -63,7 +64,7 @@ pub struct Frontend<P: PlatformExt> {
     /// The size the window was last time we checked
     previous_window_size: Vector2<u32>,
     /// Factories to construct a machine
-    machine_factories: MachineFactories<P>,
+    pub(crate) machine_factories: MachineFactories<P>,
     /// Data that the machine simulator needs when it's possible to build it
     pending_machine_resources: Option<PendingMachineResources>,
     /// The runtime for audio
@@ -211,6 +212,62 @@ impl<P: PlatformExt> Frontend<P> {
         self.gamepads.get(&id).map(|data| data.gamepad.clone())
     }
 
+    /// Feed an absolute pointer position, in physical window pixels, into a
+    /// real gamepad
+    ///
+    /// This maps the position into display space, undoing however the display
+    /// was scaled and turned when it was presented
+    pub fn set_pointer_position(&mut self, id: RealGamepadId, position: Point2<f32>) {
+        let Some(gamepad_data) = self.gamepads.get(&id) else {
+            return;
+        };
+
+        if self.previous_window_size.min() == 0 {
+            return;
+        }
+
+        // Pointers aim at the first display
+        let display = self
+            .machine
+            .as_ref()
+            .and_then(|machine| Some((machine, machine.displays.iter().next()?)));
+
+        let viewport = DisplayViewport {
+            size: display
+                .and_then(|(_, display_path)| {
+                    self.windowing_context
+                        .as_ref()?
+                        .graphics_runtime
+                        .presented_display_size(display_path)
+                })
+                .unwrap_or_else(|| self.previous_window_size.cast()),
+            rotation: display
+                .map(|(machine, display_path)| display_rotation(machine, display_path))
+                .unwrap_or_default(),
+        };
+
+        let position = viewport.display_position(position);
+
+        gamepad_data.gamepad.set(
+            Input::Pointer(PointerInput::X),
+            InputState::Analog(position.x),
+        );
+        gamepad_data.gamepad.set(
+            Input::Pointer(PointerInput::Y),
+            InputState::Analog(position.y),
+        );
+    }
+
+    /// Write the save of the running machine, so the program gets it back
+    /// next time
+    pub fn store_save(&self) {
+        if let Some(machine) = self.machine.as_ref()
+            && let Err(error) = machine.store_save()
+        {
+            tracing::error!("Failed to store save: {}", error);
+        }
+    }
+
     /// Redraw for the runtime
     pub fn redraw(&mut self) {
         if !self.in_focus {
@@ -286,10 +343,12 @@ impl<P: PlatformExt> Frontend<P> {
             windowing_handle.unwrap_or_else(|| old_windowing_context.map(|w| w.handle).unwrap())
         };
 
+        self.store_save();
         self.machine = None;
         self.audio_runtime.set_machine(None);
 
         let machine_id = program_specification.id.machine;
+        let quirks_override = self.environment.quirks.get(&program_specification.id);
 
         let machine_builder = Machine::build(
             Some(program_specification),
@@ -298,7 +357,9 @@ impl<P: PlatformExt> Frontend<P> {
             Some(self.environment.snapshot_directory.clone()),
         );
 
-        let machine_builder = self.machine_factories.construct_machine(machine_builder);
+        let machine_builder = self
+            .machine_factories
+            .construct_machine(machine_builder, quirks_override.map(String::as_str));
 
         let GraphicsRequirements {
             required_features,
@@ -188,6 +188,20 @@ impl<P: PlatformExt> Frontend<P> {
             }
             MenuItem::Options => {
                 self.gui.options_state.run(ui, &mut self.environment);
+
+                if let Some(program_specification) = self
+                    .machine
+                    .as_ref()
+                    .and_then(|machine| machine.program_specification.as_ref())
+                {
+                    self.gui.options_state.run_quirks(
+                        ui,
+                        &mut self.environment,
+                        &self.program_manager,
+                        &self.machine_factories,
+                        &program_specification.id,
+                    );
+                }
             }
             MenuItem::Controller => {
                 self.gui.gamepad_config_state.run(ui);
@@ -1,12 +1,29 @@
 use std::fs::File;
 
-use egui::{ComboBox, RichText, Ui};
+use egui::{ComboBox, RichText, TextEdit, Ui};
+use fluxemu_runtime::{
+    platform::Platform,
+    program::{ProgramId, ProgramManager},
+};
 use strum::IntoEnumIterator;
 
-use crate::environment::{ENVIRONMENT_LOCATION, Environment, graphics::GraphicsApi};
+use crate::{
+    MachineFactories,
+    environment::{ENVIRONMENT_LOCATION, Environment, graphics::GraphicsApi},
+};
 
 #[derive(Debug, Default)]
-pub struct OptionsState {}
+pub struct OptionsState {
+    quirks_editor: Option<QuirksEditor>,
+}
+
+/// The quirks of the running program, as they are being edited
+#[derive(Debug)]
+struct QuirksEditor {
+    program_id: ProgramId,
+    text: String,
+    error: Option<String>,
+}
 
 impl OptionsState {
     pub fn run(&mut self, ui: &mut Ui, environment: &mut Environment) {
@@ -40,4 +57,71 @@ impl OptionsState {
 
         ui.checkbox(&mut environment.graphics_setting.vsync, "VSync");
     }
+
+    /// Lets the user override the quirks of the running program, which take
+    /// effect the next time it is loaded
+    pub fn run_quirks<P: Platform>(
+        &mut self,
+        ui: &mut Ui,
+        environment: &mut Environment,
+        program_manager: &ProgramManager,
+        machine_factories: &MachineFactories<P>,
+        program_id: &ProgramId,
+    ) {
+        let editor = match &mut self.quirks_editor {
+            Some(editor) if editor.program_id == *program_id => editor,
+            editor => editor.insert(QuirksEditor {
+                program_id: program_id.clone(),
+                text: machine_factories.quirks(
+                    program_manager,
+                    program_id,
+                    environment.quirks.get(program_id).map(String::as_str),
+                ),
+                error: None,
+            }),
+        };
+
+        ui.separator();
+        ui.heading(format!("Quirks for {program_id}"));
+
+        ui.add(
+            TextEdit::multiline(&mut editor.text)
+                .code_editor()
+                .desired_width(f32::INFINITY),
+        );
+
+        ui.horizontal(|ui| {
+            if ui
+                .button("Apply")
+                .on_hover_text("Takes effect the next time the program is loaded")
+                .clicked()
+            {
+                match machine_factories.validate_quirks(program_id.machine, &editor.text) {
+                    Ok(()) => {
+                        environment
+                            .quirks
+                            .insert(program_id.clone(), editor.text.clone());
+                        editor.error = None;
+                    }
+                    Err(error) => {
+                        editor.error = Some(error.to_string());
+                    }
+                }
+            }
+
+            if ui
+                .button("Reset")
+                .on_hover_text("Go back to the quirks from the database")
+                .clicked()
+            {
+                environment.quirks.remove(program_id);
+                editor.text = machine_factories.quirks(program_manager, program_id, None);
+                editor.error = None;
+            }
+        });
+
+        if let Some(error) = &editor.error {
+            ui.label(RichText::new(error).color(egui::Color32::RED));
+        }
+    }
 }
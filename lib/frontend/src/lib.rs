@@ -1,4 +1,5 @@
 mod backend;
+mod display;
 pub mod environment;
 mod frontend;
 mod gui;
@@ -7,6 +8,7 @@ mod machine_factories;
 mod platform;
 
 pub use backend::*;
+pub use display::*;
 pub use frontend::*;
 pub use gui::software_rendering as gui_software_rendering;
 pub use hotkey::*;
@@ -1,13 +1,97 @@
 use std::{collections::HashMap, fmt::Debug};
 
 use fluxemu_runtime::{
-    machine::{MachineFactory, builder::MachineBuilder},
+    machine::{MachineFactory, Quirks, builder::MachineBuilder},
     platform::Platform,
-    program::MachineId,
+    program::{MachineId, ProgramId, ProgramManager},
 };
+use ron::ser::PrettyConfig;
+
+/// [MachineFactory] with its quirks type erased, so factories for different
+/// machines can live together
+///
+/// Quirk overrides are RON, the same as the environment they are stored in
+trait ErasedMachineFactory<P: Platform>: Send + Sync + 'static {
+    fn construct(
+        &self,
+        machine_builder: MachineBuilder<P>,
+        quirks_override: Option<&str>,
+    ) -> MachineBuilder<P>;
+
+    fn quirks(
+        &self,
+        program_manager: &ProgramManager,
+        program_id: &ProgramId,
+        quirks_override: Option<&str>,
+    ) -> String;
+
+    fn validate_quirks(&self, quirks: &str) -> Result<(), ron::error::SpannedError>;
+}
+
+impl<P: Platform, M: MachineFactory<P>> ErasedMachineFactory<P> for M {
+    fn construct(
+        &self,
+        machine_builder: MachineBuilder<P>,
+        quirks_override: Option<&str>,
+    ) -> MachineBuilder<P> {
+        let quirks = match machine_builder.program_specification() {
+            Some(program_specification) => resolve_quirks(
+                machine_builder.program_manager(),
+                &program_specification.id,
+                quirks_override,
+            ),
+            None => M::Quirks::default(),
+        };
+
+        tracing::debug!("Constructing machine with quirks {:?}", quirks);
+
+        MachineFactory::construct(self, machine_builder, quirks)
+    }
+
+    fn quirks(
+        &self,
+        program_manager: &ProgramManager,
+        program_id: &ProgramId,
+        quirks_override: Option<&str>,
+    ) -> String {
+        let quirks: M::Quirks = resolve_quirks(program_manager, program_id, quirks_override);
+
+        ron::ser::to_string_pretty(&quirks, PrettyConfig::new()).unwrap()
+    }
+
+    fn validate_quirks(&self, quirks: &str) -> Result<(), ron::error::SpannedError> {
+        ron::from_str::<M::Quirks>(quirks).map(|_| ())
+    }
+}
+
+/// The user's override if it is valid, then the database's entry, then the
+/// defaults
+fn resolve_quirks<Q: Quirks>(
+    program_manager: &ProgramManager,
+    program_id: &ProgramId,
+    quirks_override: Option<&str>,
+) -> Q {
+    if let Some(quirks_override) = quirks_override {
+        match ron::from_str(quirks_override) {
+            Ok(quirks) => return quirks,
+            Err(error) => {
+                tracing::error!("Ignoring invalid quirks override for {program_id}: {error}");
+            }
+        }
+    }
+
+    match program_manager.quirks(program_id) {
+        Ok(quirks) => quirks.unwrap_or_default(),
+        Err(error) => {
+            tracing::error!("Failed to look up quirks for {program_id}: {error}");
+
+            Q::default()
+        }
+    }
+}
 
 /// Factory storage for frontend machine generation automation
-pub struct MachineFactories<P: Platform>(HashMap<MachineId, Box<dyn MachineFactory<P>>>);
+pub struct MachineFactories<P: Platform>(HashMap<MachineId, Box<dyn ErasedMachineFactory<P>>>);
 
 impl<P: Platform> Debug for MachineFactories<P> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -21,14 +105,42 @@ impl<P: Platform> MachineFactories<P> {
         self.0.insert(system, Box::new(M::default()));
     }
 
-    /// Spit out a machine based upon the factories
-    pub fn construct_machine(&self, machine_builder: MachineBuilder<P>) -> MachineBuilder<P> {
-        let system = machine_builder.machine_id().unwrap();
-
+    fn get(&self, system: MachineId) -> &dyn ErasedMachineFactory<P> {
         self.0
             .get(&system)
             .unwrap_or_else(|| panic!("No factory for system {system:?}"))
-            .construct(machine_builder)
+            .as_ref()
+    }
+
+    /// Spit out a machine based upon the factories
+    pub fn construct_machine(
+        &self,
+        machine_builder: MachineBuilder<P>,
+        quirks_override: Option<&str>,
+    ) -> MachineBuilder<P> {
+        let system = machine_builder.machine_id().unwrap();
+
+        self.get(system).construct(machine_builder, quirks_override)
+    }
+
+    /// The quirks a program would be constructed with, as RON
+    pub fn quirks(
+        &self,
+        program_manager: &ProgramManager,
+        program_id: &ProgramId,
+        quirks_override: Option<&str>,
+    ) -> String {
+        self.get(program_id.machine)
+            .quirks(program_manager, program_id, quirks_override)
+    }
+
+    /// Checks if a quirks override is usable for a machine
+    pub fn validate_quirks(
+        &self,
+        system: MachineId,
+        quirks: &str,
+    ) -> Result<(), ron::error::SpannedError> {
+        self.get(system).validate_quirks(quirks)
     }
 }
 
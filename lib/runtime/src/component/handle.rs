@@ -7,7 +7,10 @@ use std::{
 use crate::{
     component::Component,
     machine::builder::SchedulerParticipation,
-    scheduler::{EventManager, Period, PreemptionSignal, SynchronizationContext},
+    scheduler::{
+        ComponentId, EventManager, Period, PreemptionSignal, SynchronizationContext, accessing,
+        synchronizing,
+    },
 };
 
 #[derive(Debug)]
@@ -60,6 +63,34 @@ impl ComponentHandle {
         }
     }
 
+    pub(crate) fn id(&self) -> ComponentId {
+        ComponentId(Arc::as_ptr(&self.inner).cast::<()>() as usize)
+    }
+
+    /// How far this component has been synchronized, if the scheduler times it
+    pub(crate) fn updated_timestamp(&self) -> Option<Period> {
+        self.inner
+            .read()
+            .unwrap()
+            .synchronization_data
+            .as_ref()
+            .map(|synchronization_data| synchronization_data.updated_timestamp)
+    }
+
+    /// Move this component to a point in time without running it, such as
+    /// when restoring a snapshot
+    pub(crate) fn set_updated_timestamp(&self, timestamp: Period) {
+        if let Some(synchronization_data) = &mut self.inner.write().unwrap().synchronization_data {
+            synchronization_data.updated_timestamp = timestamp;
+        }
+    }
+
+    /// Let go of a panic that unwound through this component, for when its
+    /// state is about to be replaced anyway
+    pub(crate) fn clear_poison(&self) {
+        self.inner.clear_poison();
+    }
+
     /// Interact immutably with a component
     #[inline]
     pub fn interact<T>(
@@ -67,6 +98,8 @@ impl ComponentHandle {
         current_timestamp: Period,
         callback: impl FnOnce(&dyn Component) -> T,
     ) -> T {
+        accessing(self.id());
+
         let guard = self.inner.read().unwrap();
 
         if let Some(SynchronizationData {
@@ -92,6 +125,8 @@ impl ComponentHandle {
         current_timestamp: Period,
         callback: impl FnOnce(&mut dyn Component) -> T,
     ) -> T {
+        accessing(self.id());
+
         let mut guard = self.inner.write().unwrap();
         let mut delta;
         let mut last_attempted_allocation = None;
@@ -118,7 +153,7 @@ impl ComponentHandle {
                     interrupt: &synchronization_data.interrupt,
                 };
 
-                guard_inner.component.synchronize(context);
+                synchronizing(self.id(), || guard_inner.component.synchronize(context));
 
                 // Prevent bad synchronization logic from spinning forever
                 let last_attempted_allocation = last_attempted_allocation.take().expect(
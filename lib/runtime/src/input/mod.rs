@@ -1,9 +1,12 @@
 use keyboard::KeyboardInput;
+use pointer::PointerInput;
 use serde::{Deserialize, Serialize};
 use strum::{EnumIter, IntoEnumIterator};
 
 /// Keyboard enums
 pub mod keyboard;
+/// Pointer enums
+pub mod pointer;
 /// Real gamepad
 mod real_gamepad;
 /// Virtual gamepad
@@ -19,6 +22,8 @@ pub enum Input {
     Gamepad(GamepadInput),
     /// Input is for a keyboardish device
     Keyboard(KeyboardInput),
+    /// Input is for a pointing device
+    Pointer(PointerInput),
 }
 
 impl Input {
@@ -27,6 +32,7 @@ impl Input {
         GamepadInput::iter()
             .map(Input::Gamepad)
             .chain(KeyboardInput::iter().map(Input::Keyboard))
+            .chain(PointerInput::iter().map(Input::Pointer))
     }
 }
 
@@ -0,0 +1,22 @@
+use serde::{Deserialize, Serialize};
+use strum::EnumIter;
+
+#[non_exhaustive]
+#[derive(
+    Serialize, Deserialize, Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, EnumIter,
+)]
+/// Inputs that a pointing device (mouse, touchscreen, light gun, etc) could
+/// give
+///
+/// Positions are absolute and given as analog values from 0.0 to 1.0 across
+/// the display, with the origin at the top left
+pub enum PointerInput {
+    /// Horizontal position
+    X,
+    /// Vertical position
+    Y,
+    /// Primary button, or a touch being held down
+    Primary,
+    /// Secondary button
+    Secondary,
+}
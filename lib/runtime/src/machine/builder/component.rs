@@ -1,5 +1,6 @@
 use std::{
     any::Any,
+    cell::RefCell,
     collections::{HashMap, HashSet},
     io::Read,
     marker::PhantomData,
@@ -17,20 +18,22 @@ use crate::{
     input::VirtualGamepad,
     machine::{
         builder::{MachineBuilder, PartialEvent, SchedulerParticipation},
-        graphics::GraphicsRequirements,
+        graphics::{DisplayRotation, GraphicsRequirements},
     },
     memory::{
         Address, AddressSpace, AddressSpaceId, MapTarget, MemoryRemappingCommand, Permissions,
     },
     path::{FluxEmuPath, Namespace},
+    persistence::save_location,
     platform::Platform,
     program::ProgramManager,
-    scheduler::{EventType, Frequency, Period, PreemptionSignal},
+    scheduler::{EventHandle, EventType, Frequency, NamedEventCallback, Period, PreemptionSignal},
 };
 
 /// Overall data extracted from components needed for machine initialization
 pub(super) struct ComponentMetadata<P: Platform> {
     pub displays: HashSet<FluxEmuPath>,
+    pub display_rotations: HashMap<FluxEmuPath, DisplayRotation>,
     pub graphics_requirements: GraphicsRequirements<P::GraphicsApi>,
     pub audio_outputs: HashSet<FluxEmuPath>,
     pub gamepads: HashMap<FluxEmuPath, Arc<VirtualGamepad>>,
@@ -39,12 +42,16 @@ pub(super) struct ComponentMetadata<P: Platform> {
     pub scheduler_participation: SchedulerParticipation,
     pub events: Vec<PartialEvent>,
     pub preemption_signal: Arc<PreemptionSignal>,
+    /// Components reached into while building, which may have handed out
+    /// state they share with this one
+    pub links: RefCell<HashSet<FluxEmuPath>>,
 }
 
 impl<P: Platform> ComponentMetadata<P> {
     pub fn new<B: ComponentConfig<P>>() -> Self {
         Self {
             displays: Default::default(),
+            display_rotations: Default::default(),
             graphics_requirements: Default::default(),
             audio_outputs: Default::default(),
             gamepads: Default::default(),
@@ -56,6 +63,7 @@ impl<P: Platform> ComponentMetadata<P> {
             scheduler_participation: SchedulerParticipation::None,
             events: Vec::default(),
             preemption_signal: Arc::default(),
+            links: RefCell::default(),
         }
     }
 }
@@ -80,8 +88,18 @@ impl<'a, P: Platform, C: Component> ComponentBuilder<'a, P, C> {
         &self.machine_builder.address_spaces[&address_space].address_space
     }
 
+    /// The save this component stored the last time the program ran, if any
     pub fn save(&self) -> Option<(Box<dyn Read>, ComponentVersion)> {
-        None
+        let (rom_id, rom_name) =
+            save_location(self.machine_builder.program_specification.as_ref()?)?;
+
+        self.machine_builder
+            .save_manager
+            .get(rom_id, rom_name, self.path.clone())
+            .unwrap_or_else(|error| {
+                tracing::error!("Failed to read save for {}: {}", self.path, error);
+                None
+            })
     }
 
     pub fn set_scheduler_participation(
@@ -98,6 +116,8 @@ impl<'a, P: Platform, C: Component> ComponentBuilder<'a, P, C> {
         path: &FluxEmuPath,
         callback: impl FnOnce(&C2) -> T,
     ) -> Option<T> {
+        self.link(path);
+
         self.machine_builder
             .registry
             .interact_without_synchronization(path, callback)
@@ -108,11 +128,25 @@ impl<'a, P: Platform, C: Component> ComponentBuilder<'a, P, C> {
         path: &FluxEmuPath,
         callback: impl FnOnce(&mut C2) -> T,
     ) -> Option<T> {
+        self.link(path);
+
         self.machine_builder
             .registry
             .interact_mut_without_synchronization(path, callback)
     }
 
+    /// Anything picked up from another component while building, such as an
+    /// interrupt line, could be shared behind the scheduler's back, so the two
+    /// have to always run together
+    fn link(&self, path: &FluxEmuPath) {
+        let path = match path.namespace() {
+            Namespace::Resource => path.parent(),
+            _ => Some(path.clone()),
+        };
+
+        self.component_metadata.links.borrow_mut().extend(path);
+    }
+
     pub fn typed_handle<C2: Component>(
         &self,
         path: &FluxEmuPath,
@@ -197,6 +231,22 @@ impl<'a, P: Platform, C: Component> ComponentBuilder<'a, P, C> {
         (self, resource_path)
     }
 
+    /// Insert a display that is not mounted upright
+    pub fn insert_rotated_display(
+        self,
+        name: &str,
+        rotation: DisplayRotation,
+    ) -> (Self, FluxEmuPath) {
+        let (component_builder, resource_path) = self.insert_display(name);
+
+        component_builder
+            .component_metadata
+            .display_rotations
+            .insert(resource_path.clone(), rotation);
+
+        (component_builder, resource_path)
+    }
+
     pub fn insert_gamepad(self, name: &str, gamepad: Arc<VirtualGamepad>) -> (Self, FluxEmuPath) {
         let mut resource_path = self.path.clone();
         resource_path.push(Namespace::Resource, name);
@@ -389,12 +439,88 @@ impl<'a, P: Platform, C: Component> ComponentBuilder<'a, P, C> {
         self
     }
 
+    /// Register a kind of event for this component, which can then be
+    /// scheduled by the returned path
+    ///
+    /// Unlike events scheduled with closures these are plain data, so they
+    /// survive being stored in snapshots.
+    pub fn insert_event(
+        self,
+        name: &str,
+        callback: impl Fn(&mut C, Period) + Send + Sync + 'static,
+    ) -> (Self, FluxEmuPath) {
+        let mut resource_path = self.path.clone();
+        resource_path.push(Namespace::Resource, name);
+
+        let callback: NamedEventCallback = Arc::new(move |component, timestamp| {
+            let component = (component as &mut dyn Any).downcast_mut().unwrap();
+
+            callback(component, timestamp);
+        });
+
+        self.machine_builder
+            .scheduler
+            .event_queue
+            .register_named(resource_path.clone(), callback);
+
+        (self, resource_path)
+    }
+
+    /// Schedule an event registered with [Self::insert_event]
+    pub fn schedule_named_event(self, path: &FluxEmuPath, time: Period) -> (Self, EventHandle) {
+        self.push_named_event(path, time, None)
+    }
+
+    /// Schedule an event registered with [Self::insert_event] to fire every
+    /// `frequency` starting at `time`
+    pub fn schedule_repeating_named_event(
+        self,
+        path: &FluxEmuPath,
+        time: Period,
+        frequency: Frequency,
+    ) -> (Self, EventHandle) {
+        self.push_named_event(path, time, Some(frequency))
+    }
+
+    fn push_named_event(
+        self,
+        path: &FluxEmuPath,
+        time: Period,
+        frequency: Option<Frequency>,
+    ) -> (Self, EventHandle) {
+        assert_eq!(
+            path.parent().as_ref(),
+            Some(self.path),
+            "Components can only schedule their own events while building"
+        );
+
+        let event_manager = &self.machine_builder.scheduler.event_queue;
+        let id = event_manager.allocate_id();
+        let handle = EventHandle::new(id, event_manager);
+
+        self.component_metadata.events.push(PartialEvent {
+            id,
+            ty: EventType::Named {
+                path: path.clone(),
+                frequency,
+            },
+            time,
+        });
+
+        (self, handle)
+    }
+
     pub fn schedule_event(
         self,
         time: Period,
         callback: impl FnOnce(&mut C, Period) + Send + Sync + 'static,
-    ) -> Self {
+    ) -> (Self, EventHandle) {
+        let event_manager = &self.machine_builder.scheduler.event_queue;
+        let id = event_manager.allocate_id();
+        let handle = EventHandle::new(id, event_manager);
+
         self.component_metadata.events.push(PartialEvent {
+            id,
             ty: EventType::Once {
                 callback: Box::new(move |component, timestamp| {
                     let component = (component as &mut dyn Any).downcast_mut().unwrap();
@@ -405,7 +531,7 @@ impl<'a, P: Platform, C: Component> ComponentBuilder<'a, P, C> {
             time,
         });
 
-        self
+        (self, handle)
     }
 
     pub fn schedule_repeating_event(
@@ -413,8 +539,13 @@ impl<'a, P: Platform, C: Component> ComponentBuilder<'a, P, C> {
         time: Period,
         frequency: Frequency,
         mut callback: impl FnMut(&mut C, Period) + Send + Sync + 'static,
-    ) -> Self {
+    ) -> (Self, EventHandle) {
+        let event_manager = &self.machine_builder.scheduler.event_queue;
+        let id = event_manager.allocate_id();
+        let handle = EventHandle::new(id, event_manager);
+
         self.component_metadata.events.push(PartialEvent {
+            id,
             ty: EventType::Repeating {
                 callback: Box::new(move |component, timestamp| {
                     let component = (component as &mut dyn Any).downcast_mut().unwrap();
@@ -426,6 +557,6 @@ impl<'a, P: Platform, C: Component> ComponentBuilder<'a, P, C> {
             time,
         });
 
-        self
+        (self, handle)
     }
 }
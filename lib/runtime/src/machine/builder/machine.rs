@@ -1,5 +1,4 @@
 use std::{
-    cmp::Reverse,
     collections::{HashMap, HashSet},
     marker::PhantomData,
     ops::RangeInclusive,
@@ -31,7 +30,7 @@ use crate::{
     persistence::{SaveManager, SnapshotManager},
     platform::Platform,
     program::{MachineId, ProgramManager, ProgramSpecification},
-    scheduler::{QueuedEvent, Scheduler},
+    scheduler::{ExecutionMode, Scheduler},
 };
 
 /// Builder to produce a machine, definition crates will want to use this
@@ -94,6 +93,13 @@ impl<P: Platform> MachineBuilder<P> {
         &self.program_manager
     }
 
+    /// Choose how the machine runs its scheduler driven components
+    pub fn execution_mode(mut self, execution_mode: ExecutionMode) -> Self {
+        self.scheduler.set_execution_mode(execution_mode);
+
+        self
+    }
+
     #[inline]
     fn insert_component_with_path<B: ComponentConfig<P>>(&mut self, path: FluxEmuPath, config: B) {
         let mut component_metadata = ComponentMetadata::new::<B>();
@@ -320,6 +326,7 @@ impl<P: Platform> MachineBuilder<P> {
         let mut audio_outputs = HashSet::new();
         let mut component_initializers = HashMap::new();
         let mut displays = HashSet::default();
+        let mut display_rotations = HashMap::default();
         let mut preemption_signals = Vec::default();
 
         for (path, component_metadata) in self.component_metadata.drain(..) {
@@ -327,6 +334,7 @@ impl<P: Platform> MachineBuilder<P> {
 
             component_initializers.insert(path.clone(), component_metadata.late_initializer);
             displays.extend(component_metadata.displays);
+            display_rotations.extend(component_metadata.display_rotations);
             virtual_gamepads.extend(component_metadata.gamepads);
             audio_outputs.extend(component_metadata.audio_outputs);
 
@@ -343,15 +351,23 @@ impl<P: Platform> MachineBuilder<P> {
                     .register_driven_component(path, component_handle.clone());
             }
 
-            for PartialEvent { ty, time } in component_metadata.events {
-                self.scheduler.event_queue.queue(QueuedEvent {
-                    component: component_handle.clone(),
-                    ty,
-                    time: Reverse(time),
-                });
+            for link in component_metadata.links.into_inner() {
+                if let Some(target) = self.registry.handle(&link) {
+                    self.scheduler.link_components(&component_handle, &target);
+                }
+            }
+
+            for PartialEvent { id, ty, time } in component_metadata.events {
+                self.scheduler
+                    .event_queue
+                    .insert(id, component_handle.clone(), ty, time);
             }
         }
 
+        self.scheduler
+            .event_queue
+            .set_preemption_signals(preemption_signals);
+
         for AddressSpaceInfo {
             address_space,
             memory_map_queue,
@@ -372,11 +388,11 @@ impl<P: Platform> MachineBuilder<P> {
             virtual_gamepads,
             registry: self.registry,
             displays,
+            display_rotations,
             save_manager: self.save_manager,
             snapshot_manager: self.snapshot_manager,
             program_specification: self.program_specification,
             audio_outputs,
-            preemption_signals,
         });
 
         let late_initialized_data = LateInitializedData::<P> {
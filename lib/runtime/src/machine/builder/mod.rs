@@ -2,7 +2,7 @@ use std::sync::Arc;
 
 use crate::{
     memory::{AddressSpace, MemoryRemappingCommand},
-    scheduler::{EventType, Period},
+    scheduler::{EventId, EventType, Period},
 };
 
 mod component;
@@ -27,6 +27,7 @@ pub enum SchedulerParticipation {
 }
 
 struct PartialEvent {
+    id: EventId,
     ty: EventType,
     time: Period,
 }
@@ -39,3 +39,16 @@ impl<G: GraphicsApi> Default for GraphicsRequirements<G> {
         }
     }
 }
+
+/// How a display is mounted relative to how it is normally viewed, which the
+/// frontend should undo when presenting it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum DisplayRotation {
+    /// Upright
+    #[default]
+    None,
+    /// Rotated 90 degrees counterclockwise
+    Left,
+    /// Rotated 90 degrees clockwise
+    Right,
+}
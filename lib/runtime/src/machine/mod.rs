@@ -4,7 +4,6 @@
 
 use std::{
     any::Any,
-    cmp::Reverse,
     collections::{HashMap, HashSet},
     fmt::Debug,
     path::PathBuf,
@@ -20,13 +19,13 @@ use serde::{Serialize, de::DeserializeOwned};
 use crate::{
     component::{Component, ComponentHandle, TypedComponentHandle},
     input::VirtualGamepad,
-    machine::{builder::MachineBuilder, registry::ComponentRegistry},
+    machine::{builder::MachineBuilder, graphics::DisplayRotation, registry::ComponentRegistry},
     memory::{AddressSpace, AddressSpaceId, MemoryRemappingCommand},
     path::FluxEmuPath,
-    persistence::{SaveManager, SnapshotManager},
+    persistence::{SaveManager, SnapshotManager, SnapshotSlot, save_location},
     platform::{Platform, TestPlatform},
-    program::{ProgramManager, ProgramSpecification},
-    scheduler::{EventType, Frequency, Period, PreemptionSignal, QueuedEvent, Scheduler},
+    program::{ProgramManager, ProgramSpecification, RomId},
+    scheduler::{EventHandle, EventType, Frequency, Period, Scheduler, changing_shared_state},
 };
 
 /// Machine builder
@@ -51,15 +50,14 @@ where
     pub(crate) registry: ComponentRegistry,
     /// All displays this machine has
     pub displays: HashSet<FluxEmuPath>,
+    /// How the frontend should rotate displays that are not mounted upright
+    pub display_rotations: HashMap<FluxEmuPath, DisplayRotation>,
     /// All audio outputs this machine has
     pub audio_outputs: HashSet<FluxEmuPath>,
     /// The program that this machine was set up with, if any
     pub program_specification: Option<ProgramSpecification>,
-    #[allow(unused)]
     save_manager: SaveManager,
-    #[allow(unused)]
     snapshot_manager: SnapshotManager,
-    preemption_signals: Vec<Arc<PreemptionSignal>>,
 }
 
 impl Machine {
@@ -105,45 +103,49 @@ impl Machine {
         address_space_id: AddressSpaceId,
         commands: impl IntoIterator<Item = MemoryRemappingCommand>,
     ) {
+        changing_shared_state();
+
         let address_space = &self.address_spaces[&address_space_id];
         address_space.remap(commands, &self.registry);
     }
 
+    /// Closures cannot go into snapshots, so loading one drops the event. Use
+    /// [Self::schedule_named_event] for anything that has to survive that
     pub fn schedule_event<C: Component>(
         &self,
         time: Period,
         path: &FluxEmuPath,
         callback: impl FnOnce(&mut C, Period) + Send + Sync + 'static,
-    ) {
+    ) -> EventHandle {
         let component = self.registry.handle(path).unwrap();
 
-        self.scheduler.event_queue.queue(QueuedEvent {
+        self.scheduler.event_queue.schedule(
             component,
-            ty: EventType::Once {
+            EventType::Once {
                 callback: Box::new(move |component, timestamp| {
                     let component = (component as &mut dyn Any).downcast_mut().unwrap();
 
                     callback(component, timestamp);
                 }),
             },
-            time: Reverse(time),
-        });
-
-        self.interrupt_in_flight_synchronization();
+            time,
+        )
     }
 
+    /// Closures cannot go into snapshots, so loading one drops the event. Use
+    /// [Self::schedule_named_event] for anything that has to survive that
     pub fn schedule_repeating_event<C: Component>(
         &self,
         time: Period,
         frequency: Frequency,
         path: &FluxEmuPath,
         mut callback: impl FnMut(&mut C, Period) + Send + Sync + 'static,
-    ) {
+    ) -> EventHandle {
         let component = self.registry.handle(path).unwrap();
 
-        self.scheduler.event_queue.queue(QueuedEvent {
+        self.scheduler.event_queue.schedule(
             component,
-            ty: EventType::Repeating {
+            EventType::Repeating {
                 callback: Box::new(move |component, timestamp| {
                     let component = (component as &mut dyn Any).downcast_mut().unwrap();
 
@@ -151,25 +153,86 @@ impl Machine {
                 }),
                 frequency,
             },
-            time: Reverse(time),
-        });
+            time,
+        )
+    }
+
+    /// Schedule an event registered with
+    /// [builder::ComponentBuilder::insert_event]
+    pub fn schedule_named_event(&self, time: Period, path: &FluxEmuPath) -> EventHandle {
+        self.push_named_event(time, None, path)
+    }
+
+    /// Schedule an event registered with
+    /// [builder::ComponentBuilder::insert_event] to fire every `frequency`
+    /// starting at `time`
+    pub fn schedule_repeating_named_event(
+        &self,
+        time: Period,
+        frequency: Frequency,
+        path: &FluxEmuPath,
+    ) -> EventHandle {
+        self.push_named_event(time, Some(frequency), path)
+    }
+
+    fn push_named_event(
+        &self,
+        time: Period,
+        frequency: Option<Frequency>,
+        path: &FluxEmuPath,
+    ) -> EventHandle {
+        let component = self.registry.handle(&path.parent().unwrap()).unwrap();
+
+        self.scheduler.event_queue.schedule(
+            component,
+            EventType::Named {
+                path: path.clone(),
+                frequency,
+            },
+            time,
+        )
+    }
+
+    /// Write the save of every component that has one, so they get it back
+    /// the next time the program runs
+    pub fn store_save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let Some((rom_id, rom_name)) = self.program_specification.as_ref().and_then(save_location)
+        else {
+            return Ok(());
+        };
+
+        self.save_manager.write(rom_id, rom_name, &self.registry)
+    }
 
-        self.interrupt_in_flight_synchronization();
+    /// Store the state of every component, and where they all are in time,
+    /// into a snapshot slot
+    pub fn store_snapshot(
+        &self,
+        rom_id: RomId,
+        rom_name: &str,
+        slot: SnapshotSlot,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.snapshot_manager.write(rom_id, rom_name, slot, self)
     }
 
-    fn interrupt_in_flight_synchronization(&self) {
-        for signal in &self.preemption_signals {
-            signal.event_occured();
-        }
+    /// Restore a snapshot taken with [Self::store_snapshot], if the slot has
+    /// one
+    pub fn load_snapshot(
+        &self,
+        rom_id: RomId,
+        rom_name: &str,
+        slot: SnapshotSlot,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.snapshot_manager.read(rom_id, rom_name, slot, self)
     }
 
     pub fn run_duration(&self, allocated_time: Duration) {
         let allocated_time = Period::from_f32(allocated_time.as_secs_f32()).unwrap_or_default();
-        self.scheduler.run(allocated_time);
+        self.scheduler.run(allocated_time, &self.registry);
     }
 
     pub fn run(&self, allocated_time: Period) {
-        self.scheduler.run(allocated_time);
+        self.scheduler.run(allocated_time, &self.registry);
     }
 
     pub fn now(&self) -> Period {
@@ -239,9 +302,25 @@ impl Machine {
 
 /// Helper trait representing a fully constructed machine
 pub trait MachineFactory<P: Platform>: Send + Sync + 'static {
+    /// Per program adjustments for programs that rely on behavior the machine
+    /// does not have by default
+    type Quirks: Quirks;
+
     /// Construct a new machine given the parameters
-    fn construct(&self, machine_builder: MachineBuilder<P>) -> MachineBuilder<P>;
+    fn construct(
+        &self,
+        machine_builder: MachineBuilder<P>,
+        quirks: Self::Quirks,
+    ) -> MachineBuilder<P>;
 }
 
-pub trait Quirks: Serialize + DeserializeOwned + Debug + Clone + Default + 'static {}
-impl<T: Serialize + DeserializeOwned + Debug + Clone + Default + 'static> Quirks for T {}
+/// Quirks are looked up per program from the database, and can be overridden
+/// by the user
+pub trait Quirks:
+    Serialize + DeserializeOwned + Debug + Clone + Default + Send + Sync + 'static
+{
+}
+impl<T: Serialize + DeserializeOwned + Debug + Clone + Default + Send + Sync + 'static> Quirks
+    for T
+{
+}
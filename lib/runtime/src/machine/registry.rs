@@ -60,6 +60,12 @@ impl ComponentRegistry {
         );
     }
 
+    pub(crate) fn handles(&self) -> impl Iterator<Item = (&FluxEmuPath, &ComponentHandle)> {
+        self.components
+            .iter()
+            .map(|(path, info)| (path, &info.component))
+    }
+
     pub(crate) fn interact_all(&self, mut callback: impl FnMut(&FluxEmuPath, &dyn Component)) {
         self.components.iter().for_each(|(path, info)| {
             info.component
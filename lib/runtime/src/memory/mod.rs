@@ -2,7 +2,10 @@ use std::{
     fmt::Debug,
     hash::{Hash, Hasher},
     ops::RangeInclusive,
-    sync::Arc,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
 };
 
 use arc_swap::{ArcSwap, Cache};
@@ -13,12 +16,16 @@ use fluxemu_range::RangeIntersection;
 use nohash::IsEnabled;
 use rangemap::RangeInclusiveMap;
 use thiserror::Error;
+pub use watch::MemoryWatch;
+
+use watch::WriteTracker;
 
 use crate::{component::ComponentHandle, machine::registry::ComponentRegistry, path::FluxEmuPath};
 
 mod commit;
 mod overlapping;
 mod read;
+mod watch;
 mod write;
 
 pub type Address = usize;
@@ -32,6 +39,8 @@ pub struct AddressSpace {
     id: AddressSpaceId,
     members: Arc<ArcSwap<Members>>,
     resources: scc::HashMap<FluxEmuPath, Bytes>,
+    write_tracker: WriteTracker,
+    generation: AtomicU64,
 }
 
 impl AddressSpace {
@@ -49,6 +58,8 @@ impl AddressSpace {
                 write: MemoryMappingTable::new(address_space_width),
             }))),
             resources: scc::HashMap::default(),
+            write_tracker: WriteTracker::default(),
+            generation: AtomicU64::new(0),
         }
     }
 
@@ -165,6 +176,8 @@ impl AddressSpace {
 
             members
         });
+
+        self.generation.fetch_add(1, Ordering::AcqRel);
     }
 
     pub fn id(&self) -> AddressSpaceId {
@@ -0,0 +1,144 @@
+use std::{
+    ops::RangeInclusive,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use fluxemu_range::{ContiguousRange, RangeIntersection};
+use rustc_hash::FxBuildHasher;
+
+use super::AddressSpace;
+use crate::memory::{Address, ComputedTablePageTarget, overlapping::Item};
+
+/// Granularity at which writes are tracked
+const GRANULE_SIZE: Address = 0x100;
+
+/// Per granule write counters, only populated for granules someone is watching
+#[derive(Debug, Default)]
+pub(super) struct WriteTracker {
+    /// Fast path so address spaces nobody watches pay for a single load per write
+    active: AtomicBool,
+    versions: scc::HashMap<Address, u64, FxBuildHasher>,
+}
+
+impl WriteTracker {
+    /// Bump every watched granule in the (translated) range
+    #[inline]
+    pub(super) fn record(&self, range: RangeInclusive<Address>) {
+        if !self.active.load(Ordering::Acquire) {
+            return;
+        }
+
+        for granule in granules(range) {
+            self.versions
+                .update_sync(&granule, |_, version| *version += 1);
+        }
+    }
+}
+
+/// A snapshot of the state of a region of memory, used to tell if it has been
+/// written to or remapped since
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryWatch {
+    generation: u64,
+    versions: Vec<(Address, u64)>,
+}
+
+impl MemoryWatch {
+    /// The mapping generation this watch was taken under
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+}
+
+impl AddressSpace {
+    /// Counter that changes every time this address space is remapped
+    #[inline]
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    /// The mask of valid addresses in this address space
+    #[inline]
+    pub fn width_mask(&self) -> Address {
+        self.width_mask
+    }
+
+    /// Start tracking writes to whatever is currently mapped for reading in
+    /// `range`
+    ///
+    /// Mirrors are followed so a write through any alias of the memory is
+    /// noticed. Read only buffers can never be written and are not tracked.
+    pub fn watch(&self, range: RangeInclusive<Address>) -> MemoryWatch {
+        assert!(
+            range.start() <= range.end() && *range.end() <= self.width_mask,
+            "Range {range:#04x?} is invalid for this address space"
+        );
+
+        // Activate tracking and read the generation first so nothing that
+        // happens while the watch is being taken goes unnoticed
+        self.write_tracker.active.store(true, Ordering::Release);
+        let generation = self.generation();
+        let members = self.members.load();
+        let mut versions = Vec::new();
+
+        for Item {
+            entry_assigned_range,
+            target,
+        } in members.read.overlapping(range.clone())
+        {
+            let ComputedTablePageTarget::Component { mirror_start, .. } = target else {
+                continue;
+            };
+
+            let intersection = entry_assigned_range.intersection(&range);
+            let offset = intersection.start() - entry_assigned_range.start();
+            let operation_base = mirror_start.unwrap_or(*entry_assigned_range.start());
+            let translated =
+                RangeInclusive::from_start_and_length(operation_base + offset, intersection.len());
+
+            for granule in granules(translated) {
+                let version = *self
+                    .write_tracker
+                    .versions
+                    .entry_sync(granule)
+                    .or_insert(0)
+                    .get();
+
+                versions.push((granule, version));
+            }
+        }
+
+        versions.sort_unstable();
+        versions.dedup();
+
+        MemoryWatch {
+            generation,
+            versions,
+        }
+    }
+
+    /// Check if nothing covered by `watch` has been written or remapped since
+    /// it was taken
+    #[inline]
+    pub fn is_unchanged(&self, watch: &MemoryWatch) -> bool {
+        watch.generation == self.generation()
+            && watch.versions.iter().all(|(granule, version)| {
+                self.write_tracker
+                    .versions
+                    .read_sync(granule, |_, current| current == version)
+                    .unwrap_or(false)
+            })
+    }
+
+    /// Notify watchers that memory changed without going through this address
+    /// space, such as a DMA transfer or an internal bank switch
+    ///
+    /// The range is in terms of the addresses the component is accessed with
+    pub fn mark_modified(&self, range: RangeInclusive<Address>) {
+        self.write_tracker.record(range);
+    }
+}
+
+fn granules(range: RangeInclusive<Address>) -> RangeInclusive<Address> {
+    (range.start() / GRANULE_SIZE)..=(range.end() / GRANULE_SIZE)
+}
@@ -70,6 +70,12 @@ impl AddressSpace {
                                 )
                             },
                         )?;
+
+                        self.write_tracker
+                            .record(RangeInclusive::from_start_and_length(
+                                operation_base + offset,
+                                adjusted_buffer.len(),
+                            ));
                     }
                     ComputedTablePageTarget::Memory(_) => {
                         unreachable!()
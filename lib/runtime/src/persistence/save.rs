@@ -10,8 +10,10 @@ use ron::ser::PrettyConfig;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    component::ComponentVersion, machine::registry::ComponentRegistry, path::FluxEmuPath,
-    program::RomId,
+    component::ComponentVersion,
+    machine::registry::ComponentRegistry,
+    path::FluxEmuPath,
+    program::{Filesystem, ProgramSpecification, RomId},
 };
 
 pub const SAVE_METADATA_FILE_NAME: &str = "metadata.ron";
@@ -28,6 +30,15 @@ pub struct ComponentSaveInfo {
     pub version: ComponentVersion,
 }
 
+/// The rom and name a program's saves are filed under, which only programs
+/// made of a single rom have
+pub(crate) fn save_location(program_specification: &ProgramSpecification) -> Option<(RomId, &str)> {
+    match program_specification.info.filesystem() {
+        Filesystem::Single { rom_id, .. } => Some((*rom_id, &program_specification.id.name)),
+        Filesystem::Complex(_) => None,
+    }
+}
+
 #[derive(Debug)]
 pub struct SaveManager {
     save_directory: Option<PathBuf>,
@@ -8,10 +8,14 @@ use std::{
 use flate2::{Compression, read::ZlibDecoder, write::ZlibEncoder};
 use ron::ser::PrettyConfig;
 use serde::{Deserialize, Serialize};
+use serde_with::{DisplayFromStr, serde_as};
 
 use crate::{
-    component::ComponentVersion, machine::registry::ComponentRegistry, path::FluxEmuPath,
+    component::ComponentVersion,
+    machine::Machine,
+    path::FluxEmuPath,
     program::RomId,
+    scheduler::{EventSnapshot, Period},
 };
 
 pub const SNAPSHOT_METADATA_FILE_NAME: &str = "metadata.ron";
@@ -23,6 +27,23 @@ pub struct SnapshotMetadata {
     pub components: HashMap<FluxEmuPath, ComponentSnapshotInfo>,
     /// compression always implies zlib compression
     pub compressed: bool,
+    /// Where the machine was in time, missing from snapshots taken before
+    /// this was recorded
+    #[serde(default)]
+    pub timing: Option<SnapshotTiming>,
+}
+
+#[serde_as]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SnapshotTiming {
+    /// Machine time the snapshot was taken at
+    #[serde_as(as = "DisplayFromStr")]
+    pub now: Period,
+    /// How far each component the scheduler times had been synchronized
+    #[serde_as(as = "HashMap<_, DisplayFromStr>")]
+    pub updated_timestamps: HashMap<FluxEmuPath, Period>,
+    /// Named events that were still pending
+    pub events: Vec<EventSnapshot>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -45,7 +66,7 @@ impl SnapshotManager {
         rom_id: RomId,
         rom_name: &str,
         slot: SnapshotSlot,
-        registry: &ComponentRegistry,
+        machine: &Machine,
     ) -> Result<(), Box<dyn std::error::Error>> {
         let snapshot_directory = match &self.snapshot_directory {
             Some(snapshot_directory) => snapshot_directory,
@@ -64,7 +85,7 @@ impl SnapshotManager {
         let metadata_path = snapshot_directory.join(SNAPSHOT_METADATA_FILE_NAME);
         let metadata: SnapshotMetadata = ron::de::from_reader(File::open(metadata_path)?)?;
 
-        registry.interact_all_mut(|path, component| {
+        machine.registry.interact_all_mut(|path, component| {
             let component_info = match metadata.components.get(path) {
                 Some(info) => info,
                 None => return,
@@ -88,6 +109,27 @@ impl SnapshotManager {
                 .unwrap();
         });
 
+        // Without timing there is nothing better than carrying on from the
+        // current time with the current events
+        let Some(timing) = metadata.timing else {
+            tracing::warn!("Snapshot has no timing information, keeping the current timing");
+            return Ok(());
+        };
+
+        // Put everything back where it was in time, so components resume
+        // exactly where they left off relative to each other
+        for (path, handle) in machine.registry.handles() {
+            if let Some(timestamp) = timing.updated_timestamps.get(path) {
+                handle.set_updated_timestamp(*timestamp);
+            }
+        }
+
+        machine
+            .scheduler
+            .event_queue
+            .restore(timing.events, &machine.registry);
+        machine.scheduler.set_now(timing.now);
+
         Ok(())
     }
 
@@ -96,7 +138,7 @@ impl SnapshotManager {
         rom_id: RomId,
         rom_name: &str,
         slot: SnapshotSlot,
-        registry: &ComponentRegistry,
+        machine: &Machine,
     ) -> Result<(), Box<dyn std::error::Error>> {
         let snapshot_directory = match &self.snapshot_directory {
             Some(snapshot_directory) => snapshot_directory,
@@ -111,7 +153,7 @@ impl SnapshotManager {
 
         let mut component_metadata = HashMap::default();
 
-        registry.interact_all(|path, component| {
+        machine.registry.interact_all(|path, component| {
             let version = component.snapshot_version();
 
             if let Some(version) = version {
@@ -124,7 +166,7 @@ impl SnapshotManager {
             return Ok(());
         }
 
-        registry.interact_all(|path, component| {
+        machine.registry.interact_all(|path, component| {
             // Only write the ones that declared versions
             if component_metadata.contains_key(path) {
                 let mut snapshot_file_path = snapshot_directory.clone();
@@ -148,6 +190,17 @@ impl SnapshotManager {
             &SnapshotMetadata {
                 components: component_metadata,
                 compressed: true,
+                timing: Some(SnapshotTiming {
+                    now: machine.now(),
+                    updated_timestamps: machine
+                        .registry
+                        .handles()
+                        .filter_map(|(path, handle)| {
+                            Some((path.clone(), handle.updated_timestamp()?))
+                        })
+                        .collect(),
+                    events: machine.scheduler.event_queue.snapshot(),
+                }),
             },
             PrettyConfig::default(),
         )?;
@@ -1,3 +1,5 @@
 mod instruction;
+/// Block discovery and caching for processors that lower instructions into closures
+pub mod recompiler;
 
 pub use instruction::InstructionSet;
@@ -0,0 +1,250 @@
+use std::{fmt::Debug, ops::RangeInclusive};
+
+use rustc_hash::FxHashMap;
+
+use crate::{
+    memory::{Address, AddressSpace, MemoryWatch},
+    processor::InstructionSet,
+    scheduler::Period,
+};
+
+#[cfg(test)]
+mod tests;
+
+/// Longest block discovery will produce by default
+pub const DEFAULT_MAX_BLOCK_LENGTH: usize = 64;
+
+/// What a lowered instruction wants the block runner to do next
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flow {
+    /// Fall through to the next instruction in the block
+    Continue,
+    /// Control left the straight line path, such as a taken branch
+    Exit,
+}
+
+/// A single instruction turned into a closure over processor state
+pub type LoweredInstruction<S> = Box<dyn Fn(&mut S) -> Flow + Send + Sync>;
+
+/// An instruction along with where it was found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedInstruction<I: InstructionSet> {
+    pub instruction: I,
+    pub address: Address,
+    /// Length in bytes
+    pub length: usize,
+}
+
+/// Implemented by processors that want their code run through [BlockCache]
+///
+/// The processor's interpreter is the reference these lowerings are expected to
+/// match exactly
+pub trait InstructionLowering: Send + Sync + 'static {
+    type InstructionSet: InstructionSet;
+    /// What lowered instructions operate on, usually the register file
+    type State: 'static;
+
+    /// Decode the instruction at `address`
+    ///
+    /// This must not cause side effects, use [AddressSpace::read_pure]
+    fn decode(
+        &self,
+        address_space: &AddressSpace,
+        address: Address,
+        timestamp: Period,
+    ) -> Option<DecodedInstruction<Self::InstructionSet>>;
+
+    /// If block discovery should stop after this instruction
+    ///
+    /// Anything that can change the program counter non sequentially, or
+    /// depends on state that is not visible to the lowering, belongs here
+    fn ends_block(&self, instruction: &Self::InstructionSet) -> bool;
+
+    /// If this instruction may write memory, and so possibly the block itself
+    fn writes_memory(&self, instruction: &Self::InstructionSet) -> bool {
+        let _ = instruction;
+
+        true
+    }
+
+    fn lower(
+        &self,
+        instruction: &DecodedInstruction<Self::InstructionSet>,
+    ) -> LoweredInstruction<Self::State>;
+}
+
+struct CompiledInstruction<S> {
+    function: LoweredInstruction<S>,
+    writes_memory: bool,
+}
+
+/// A straight line run of lowered instructions
+pub struct CompiledBlock<S> {
+    range: RangeInclusive<Address>,
+    instructions: Vec<CompiledInstruction<S>>,
+    watch: MemoryWatch,
+}
+
+impl<S> CompiledBlock<S> {
+    /// Run at most `budget` instructions, returning how many were executed
+    ///
+    /// Execution stops early when an instruction exits the block or a write
+    /// makes the block stale, so the caller should look the next block up
+    /// again rather than assuming the block ran to completion
+    #[inline]
+    pub fn run(&self, state: &mut S, address_space: &AddressSpace, budget: usize) -> usize {
+        for (executed, instruction) in self.instructions.iter().take(budget).enumerate() {
+            if (instruction.function)(state) == Flow::Exit
+                || (instruction.writes_memory && !address_space.is_unchanged(&self.watch))
+            {
+                return executed + 1;
+            }
+        }
+
+        self.instructions.len().min(budget)
+    }
+
+    /// Addresses the block was decoded from
+    pub fn range(&self) -> RangeInclusive<Address> {
+        self.range.clone()
+    }
+
+    pub fn instruction_count(&self) -> usize {
+        self.instructions.len()
+    }
+}
+
+impl<S> Debug for CompiledBlock<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CompiledBlock")
+            .field("range", &self.range)
+            .field("instruction_count", &self.instructions.len())
+            .finish()
+    }
+}
+
+/// Cache of compiled blocks keyed by their start address
+///
+/// Blocks are thrown away when the address space is remapped, or when a write
+/// lands on memory they were decoded from
+pub struct BlockCache<L: InstructionLowering> {
+    lowering: L,
+    blocks: FxHashMap<Address, CompiledBlock<L::State>>,
+    generation: Option<u64>,
+    max_block_length: usize,
+}
+
+impl<L: InstructionLowering> BlockCache<L> {
+    pub fn new(lowering: L) -> Self {
+        Self {
+            lowering,
+            blocks: FxHashMap::default(),
+            generation: None,
+            max_block_length: DEFAULT_MAX_BLOCK_LENGTH,
+        }
+    }
+
+    pub fn with_max_block_length(mut self, max_block_length: usize) -> Self {
+        assert_ne!(max_block_length, 0);
+
+        self.max_block_length = max_block_length;
+        self
+    }
+
+    pub fn lowering(&self) -> &L {
+        &self.lowering
+    }
+
+    /// Drop every compiled block
+    pub fn clear(&mut self) {
+        self.blocks.clear();
+    }
+
+    /// Get the block starting at `address`, compiling it if needed
+    ///
+    /// Returns [None] if not even a single instruction could be decoded there,
+    /// in which case the interpreter should handle it
+    pub fn block(
+        &mut self,
+        address_space: &AddressSpace,
+        address: Address,
+        timestamp: Period,
+    ) -> Option<&CompiledBlock<L::State>> {
+        let generation = address_space.generation();
+
+        if self.generation != Some(generation) {
+            self.blocks.clear();
+            self.generation = Some(generation);
+        }
+
+        let stale = self
+            .blocks
+            .get(&address)
+            .is_some_and(|block| !address_space.is_unchanged(&block.watch));
+
+        if stale {
+            self.blocks.remove(&address);
+        }
+
+        if !self.blocks.contains_key(&address) {
+            let block = self.compile(address_space, address, timestamp)?;
+            self.blocks.insert(address, block);
+        }
+
+        self.blocks.get(&address)
+    }
+
+    fn compile(
+        &self,
+        address_space: &AddressSpace,
+        start: Address,
+        timestamp: Period,
+    ) -> Option<CompiledBlock<L::State>> {
+        let width_mask = address_space.width_mask();
+        let mut instructions = Vec::new();
+        let mut address = start;
+        let mut end = start;
+
+        while instructions.len() < self.max_block_length {
+            let Some(decoded) = self.lowering.decode(address_space, address, timestamp) else {
+                break;
+            };
+
+            end = address + decoded.length.max(1) - 1;
+
+            instructions.push(CompiledInstruction {
+                function: self.lowering.lower(&decoded),
+                writes_memory: self.lowering.writes_memory(&decoded.instruction),
+            });
+
+            // Blocks never wrap around the top of the address space
+            if self.lowering.ends_block(&decoded.instruction) || end >= width_mask {
+                break;
+            }
+
+            address = end + 1;
+        }
+
+        if instructions.is_empty() {
+            return None;
+        }
+
+        let range = start..=end.min(width_mask);
+
+        Some(CompiledBlock {
+            watch: address_space.watch(range.clone()),
+            range,
+            instructions,
+        })
+    }
+}
+
+impl<L: InstructionLowering + Debug> Debug for BlockCache<L> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BlockCache")
+            .field("lowering", &self.lowering)
+            .field("blocks", &self.blocks.len())
+            .field("generation", &self.generation)
+            .finish()
+    }
+}
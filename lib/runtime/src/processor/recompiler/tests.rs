@@ -0,0 +1,477 @@
+use std::{ops::RangeInclusive, sync::Arc};
+
+use fluxemu_range::ContiguousRange;
+
+use super::{BlockCache, DecodedInstruction, Flow, InstructionLowering, LoweredInstruction};
+use crate::{
+    component::{Component, ComponentConfig},
+    machine::{Machine, builder::ComponentBuilder},
+    memory::{
+        Address, AddressSpace, AddressSpaceId, MapTarget, MemoryError, MemoryRemappingCommand,
+        Permissions,
+    },
+    path::FluxEmuPath,
+    platform::Platform,
+    processor::InstructionSet,
+    scheduler::Period,
+};
+
+/// Tiny accumulator machine used to check the recompiler against an interpreter
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ToyInstruction {
+    Load(u8),
+    Add(u8),
+    Store(u16),
+    Jump(u16),
+    JumpNotZero(u16),
+    Decrement,
+}
+
+impl InstructionSet for ToyInstruction {
+    type Opcode = u8;
+    type AddressingMode = ();
+}
+
+#[derive(Debug)]
+struct ToyState {
+    accumulator: u8,
+    program: u16,
+    address_space: Arc<AddressSpace>,
+}
+
+fn decode(address_space: &AddressSpace, address: Address) -> Option<(ToyInstruction, usize)> {
+    let mut bytes = [0; 3];
+    address_space
+        .read_pure(address, Period::default(), None, &mut bytes)
+        .ok()?;
+    let operand = u16::from_le_bytes([bytes[1], bytes[2]]);
+
+    Some(match bytes[0] {
+        0x01 => (ToyInstruction::Load(bytes[1]), 2),
+        0x02 => (ToyInstruction::Add(bytes[1]), 2),
+        0x03 => (ToyInstruction::Store(operand), 3),
+        0x04 => (ToyInstruction::Jump(operand), 3),
+        0x05 => (ToyInstruction::JumpNotZero(operand), 3),
+        0x06 => (ToyInstruction::Decrement, 1),
+        _ => return None,
+    })
+}
+
+/// The oracle
+fn interpret(state: &mut ToyState) {
+    let (instruction, length) = decode(&state.address_space, state.program as Address).unwrap();
+    let next = state.program.wrapping_add(length as u16);
+
+    state.program = next;
+
+    match instruction {
+        ToyInstruction::Load(value) => state.accumulator = value,
+        ToyInstruction::Add(value) => state.accumulator = state.accumulator.wrapping_add(value),
+        ToyInstruction::Store(address) => state
+            .address_space
+            .write_le_value(
+                address as Address,
+                Period::default(),
+                None,
+                state.accumulator,
+            )
+            .unwrap(),
+        ToyInstruction::Jump(address) => state.program = address,
+        ToyInstruction::JumpNotZero(address) => {
+            if state.accumulator != 0 {
+                state.program = address;
+            }
+        }
+        ToyInstruction::Decrement => state.accumulator = state.accumulator.wrapping_sub(1),
+    }
+}
+
+#[derive(Debug)]
+struct ToyLowering;
+
+impl InstructionLowering for ToyLowering {
+    type InstructionSet = ToyInstruction;
+    type State = ToyState;
+
+    fn decode(
+        &self,
+        address_space: &AddressSpace,
+        address: Address,
+        _timestamp: Period,
+    ) -> Option<DecodedInstruction<Self::InstructionSet>> {
+        let (instruction, length) = decode(address_space, address)?;
+
+        Some(DecodedInstruction {
+            instruction,
+            address,
+            length,
+        })
+    }
+
+    fn ends_block(&self, instruction: &Self::InstructionSet) -> bool {
+        matches!(
+            instruction,
+            ToyInstruction::Jump(_) | ToyInstruction::JumpNotZero(_)
+        )
+    }
+
+    fn writes_memory(&self, instruction: &Self::InstructionSet) -> bool {
+        matches!(instruction, ToyInstruction::Store(_))
+    }
+
+    fn lower(
+        &self,
+        instruction: &DecodedInstruction<Self::InstructionSet>,
+    ) -> LoweredInstruction<Self::State> {
+        let next = (instruction.address + instruction.length) as u16;
+
+        match instruction.instruction {
+            ToyInstruction::Load(value) => Box::new(move |state| {
+                state.accumulator = value;
+                state.program = next;
+                Flow::Continue
+            }),
+            ToyInstruction::Add(value) => Box::new(move |state| {
+                state.accumulator = state.accumulator.wrapping_add(value);
+                state.program = next;
+                Flow::Continue
+            }),
+            ToyInstruction::Store(address) => Box::new(move |state| {
+                state
+                    .address_space
+                    .write_le_value(
+                        address as Address,
+                        Period::default(),
+                        None,
+                        state.accumulator,
+                    )
+                    .unwrap();
+                state.program = next;
+                Flow::Continue
+            }),
+            ToyInstruction::Jump(address) => Box::new(move |state| {
+                state.program = address;
+                Flow::Exit
+            }),
+            ToyInstruction::JumpNotZero(address) => Box::new(move |state| {
+                if state.accumulator != 0 {
+                    state.program = address;
+                    Flow::Exit
+                } else {
+                    state.program = next;
+                    Flow::Continue
+                }
+            }),
+            ToyInstruction::Decrement => Box::new(move |state| {
+                state.accumulator = state.accumulator.wrapping_sub(1);
+                state.program = next;
+                Flow::Continue
+            }),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct TestMemory {
+    base: Address,
+    bytes: Vec<u8>,
+}
+
+impl Component for TestMemory {
+    fn memory_read(
+        &self,
+        address: Address,
+        _address_space: AddressSpaceId,
+        _avoid_side_effects: bool,
+        buffer: &mut [u8],
+    ) -> Result<(), MemoryError> {
+        let start = address - self.base;
+        buffer.copy_from_slice(
+            &self.bytes[RangeInclusive::from_start_and_length(start, buffer.len())],
+        );
+
+        Ok(())
+    }
+
+    fn memory_write(
+        &mut self,
+        address: Address,
+        _address_space: AddressSpaceId,
+        buffer: &[u8],
+    ) -> Result<(), MemoryError> {
+        let start = address - self.base;
+        self.bytes[RangeInclusive::from_start_and_length(start, buffer.len())]
+            .copy_from_slice(buffer);
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct TestMemoryConfig {
+    address_space: AddressSpaceId,
+    range: RangeInclusive<Address>,
+    contents: Vec<u8>,
+    mapped: bool,
+}
+
+impl<P: Platform> ComponentConfig<P> for TestMemoryConfig {
+    type Component = TestMemory;
+
+    fn build_component(
+        self,
+        component_builder: ComponentBuilder<P, Self::Component>,
+    ) -> Result<Self::Component, Box<dyn std::error::Error>> {
+        if self.mapped {
+            component_builder.memory_map_component(self.address_space, self.range.clone());
+        }
+
+        let mut bytes = self.contents;
+        bytes.resize(self.range.len(), 0);
+
+        Ok(TestMemory {
+            base: *self.range.start(),
+            bytes,
+        })
+    }
+}
+
+const RAM: RangeInclusive<Address> = 0x0000..=0x0fff;
+
+struct TestMachine {
+    machine: Arc<Machine>,
+    address_space: AddressSpaceId,
+    ram: FluxEmuPath,
+    spare_bank: FluxEmuPath,
+}
+
+impl TestMachine {
+    /// `program` is mapped at zero, `spare` is built over the same range but
+    /// left unmapped, and 0x1000 mirrors the program
+    fn new(program: &[u8], spare: &[u8]) -> Self {
+        let (machine, address_space) = Machine::build_test_minimal().insert_address_space(16);
+        let (machine, ram) = machine.insert_component(
+            "ram",
+            TestMemoryConfig {
+                address_space,
+                range: RAM,
+                contents: program.to_vec(),
+                mapped: true,
+            },
+        );
+        let (machine, spare_bank) = machine.insert_component(
+            "spare",
+            TestMemoryConfig {
+                address_space,
+                range: RAM,
+                contents: spare.to_vec(),
+                mapped: false,
+            },
+        );
+        let machine = machine
+            .memory_map_mirror(address_space, 0x1000..=0x1fff, RAM)
+            .build(());
+
+        Self {
+            machine,
+            address_space,
+            ram,
+            spare_bank,
+        }
+    }
+
+    fn state(&self) -> ToyState {
+        ToyState {
+            accumulator: 0,
+            program: 0,
+            address_space: self.address_space().clone(),
+        }
+    }
+
+    fn address_space(&self) -> &Arc<AddressSpace> {
+        self.machine.address_spaces(self.address_space).unwrap()
+    }
+
+    fn memory(&self) -> Vec<u8> {
+        let mut memory = vec![0; 0x200];
+        self.address_space()
+            .read_pure(0, Period::default(), None, &mut memory)
+            .unwrap();
+        memory
+    }
+
+    fn swap_banks(&self) {
+        self.machine.remap_address_space(
+            self.address_space,
+            [MemoryRemappingCommand::Map {
+                range: RAM,
+                target: MapTarget::Component(self.spare_bank.clone()),
+                permissions: Permissions {
+                    read: true,
+                    write: true,
+                },
+            }],
+        );
+    }
+}
+
+/// Run `count` instructions through the cache, leaving anything that can't be
+/// compiled to the interpreter like a real processor would
+fn run_compiled(cache: &mut BlockCache<ToyLowering>, state: &mut ToyState, count: usize) {
+    let mut remaining = count;
+
+    while remaining != 0 {
+        let address_space = state.address_space.clone();
+
+        match cache.block(&address_space, state.program as Address, Period::default()) {
+            Some(block) => remaining -= block.run(state, &address_space, remaining),
+            None => {
+                interpret(state);
+                remaining -= 1;
+            }
+        }
+    }
+}
+
+fn assert_matches_interpreter(program: &[u8]) -> ToyState {
+    let oracle = TestMachine::new(program, &[]);
+    let compiled = TestMachine::new(program, &[]);
+
+    let mut oracle_state = oracle.state();
+    let mut compiled_state = compiled.state();
+    let mut cache = BlockCache::new(ToyLowering).with_max_block_length(4);
+
+    // Uneven step sizes so blocks get entered and left at awkward points
+    for step in 0..64 {
+        let count = (step * 7) % 5 + 1;
+
+        for _ in 0..count {
+            interpret(&mut oracle_state);
+        }
+        run_compiled(&mut cache, &mut compiled_state, count);
+
+        assert_eq!(
+            (oracle_state.accumulator, oracle_state.program),
+            (compiled_state.accumulator, compiled_state.program),
+            "diverged at step {step}"
+        );
+        assert_eq!(
+            oracle.memory(),
+            compiled.memory(),
+            "diverged at step {step}"
+        );
+    }
+
+    compiled_state
+}
+
+#[test]
+fn counting_loop() {
+    assert_matches_interpreter(&[
+        0x01, 0x0a, // load 10
+        0x03, 0x00, 0x01, // store 0x100
+        0x06, // decrement
+        0x02, 0x00, // add 0
+        0x06, // decrement
+        0x02, 0x01, // add 1
+        0x05, 0x02, 0x00, // jump to 0x2 if not zero
+        0x04, 0x00, 0x00, // jump to 0x0
+    ]);
+}
+
+#[test]
+fn self_modifying_code() {
+    // Doubles the add immediate every iteration, stale code would keep adding 1
+    let program = [
+        0x01, 0x01, // load 1
+        0x02, 0x01, // add 1
+        0x03, 0x03, 0x00, // store into the add immediate
+        0x04, 0x02, 0x00, // jump to 0x2
+    ];
+    assert_matches_interpreter(&program);
+
+    let machine = TestMachine::new(&program, &[]);
+    let mut state = machine.state();
+    let mut cache = BlockCache::new(ToyLowering);
+
+    run_compiled(&mut cache, &mut state, 1 + 3 * 3);
+    assert_eq!(state.accumulator, 8);
+}
+
+#[test]
+fn mirrored_write_invalidates() {
+    let program = [
+        0x01, 0x01, // load 1
+        0x02, 0x01, // add 1
+        0x03, 0x03, 0x10, // store into the add immediate through the mirror
+        0x04, 0x02, 0x00, // jump to 0x2
+    ];
+    assert_matches_interpreter(&program);
+
+    let machine = TestMachine::new(&program, &[]);
+    let mut state = machine.state();
+    let mut cache = BlockCache::new(ToyLowering);
+
+    run_compiled(&mut cache, &mut state, 1 + 3 * 3);
+    assert_eq!(state.accumulator, 8);
+}
+
+#[test]
+fn remap_invalidates() {
+    let machine = TestMachine::new(
+        &[
+            0x01, 0x01, // load 1
+            0x04, 0x00, 0x00, // jump to 0x0
+        ],
+        &[
+            0x01, 0x02, // load 2
+            0x04, 0x00, 0x00, // jump to 0x0
+        ],
+    );
+    let mut state = machine.state();
+    let mut cache = BlockCache::new(ToyLowering);
+
+    run_compiled(&mut cache, &mut state, 4);
+    assert_eq!(state.accumulator, 1);
+
+    machine.swap_banks();
+
+    run_compiled(&mut cache, &mut state, 2);
+    assert_eq!(state.accumulator, 2);
+}
+
+#[test]
+fn external_modification() {
+    let machine = TestMachine::new(
+        &[
+            0x01, 0x01, // load 1
+            0x04, 0x00, 0x00, // jump to 0x0
+        ],
+        &[],
+    );
+    let mut state = machine.state();
+    let mut cache = BlockCache::new(ToyLowering);
+
+    run_compiled(&mut cache, &mut state, 2);
+    assert_eq!(state.accumulator, 1);
+
+    // Poke memory behind the address space's back, like DMA would
+    machine
+        .machine
+        .registry
+        .interact_mut::<TestMemory, _>(&machine.ram, Period::default(), |memory| {
+            memory.bytes[1] = 3
+        })
+        .unwrap();
+
+    run_compiled(&mut cache, &mut state, 2);
+    assert_eq!(
+        state.accumulator, 1,
+        "cache should not notice without being told"
+    );
+
+    machine.address_space().mark_modified(0x0001..=0x0001);
+
+    run_compiled(&mut cache, &mut state, 2);
+    assert_eq!(state.accumulator, 3);
+}
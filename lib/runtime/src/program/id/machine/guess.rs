@@ -22,7 +22,7 @@ static MAGIC_TABLE: LazyLock<HashMap<MachineId, Vec<MagicTableEntry>>> = LazyLoc
         .or_default()
         .extend([MagicTableEntry {
             bytes: &[0xce, 0xed, 0x66, 0x66, 0xcc, 0x0d, 0x00, 0x0b],
-            offset: 0x134,
+            offset: 0x104,
         }]);
 
     table
@@ -126,7 +126,8 @@ fn guess_by_extension(rom: &Path) -> Option<MachineId> {
                 NintendoSystem::SuperNintendoEntertainmentSystem,
             )),
             "n64" | "z64" => Some(MachineId::Nintendo(NintendoSystem::Nintendo64)),
-            "md" => Some(MachineId::Sega(SegaSystem::MasterSystem)),
+            "sms" => Some(MachineId::Sega(SegaSystem::MasterSystem)),
+            "md" => Some(MachineId::Sega(SegaSystem::Genesis)),
             "gg" => Some(MachineId::Sega(SegaSystem::GameGear)),
             "ch8" | "c8" => Some(MachineId::Other(OtherSystem::Chip8)),
             "a26" => Some(MachineId::Atari(AtariSystem::Atari2600)),
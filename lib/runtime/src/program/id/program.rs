@@ -6,7 +6,7 @@ use serde::{Deserialize, Serialize};
 use crate::program::MachineId;
 
 /// A identifier for a program
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct ProgramId {
     /// The machine this program was produced for
     pub machine: MachineId,
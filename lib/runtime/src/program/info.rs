@@ -4,6 +4,7 @@ use fluxemu_locale::Iso639Alpha3;
 use redb::{Key, TypeName, Value};
 use serde::{Deserialize, Serialize};
 use serde_with::{DisplayFromStr, serde_as};
+use strum::{Display, EnumString};
 use versions::Versioning;
 
 use crate::program::RomId;
@@ -24,6 +25,30 @@ pub enum Filesystem {
     Complex(BTreeMap<RomId, BTreeSet<String>>),
 }
 
+/// Television standard a program was made for
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Display,
+    EnumString,
+)]
+#[strum(serialize_all = "lowercase")]
+#[allow(missing_docs)]
+pub enum VideoStandard {
+    Ntsc,
+    Pal,
+    Secam,
+    Dendy,
+}
+
 #[serde_as]
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
 /// Information about a program, for the database
@@ -48,6 +73,13 @@ pub enum ProgramInfo {
         /// The version or revision of the program
         #[serde_as(as = "Option<DisplayFromStr>")]
         version: Option<Versioning>,
+        /// The television standard the program expects, if it only supports
+        /// one and the machine cannot tell on its own
+        ///
+        /// Added to this version after it shipped, so databases written before
+        /// it load as [None] instead of needing a new version
+        #[serde(default)]
+        video_standard: Option<VideoStandard>,
     },
 }
 
@@ -66,6 +98,20 @@ impl ProgramInfo {
         }
     }
 
+    /// Returns the television standard of the program, if known
+    pub fn video_standard(&self) -> Option<VideoStandard> {
+        match self {
+            ProgramInfo::V0 { video_standard, .. } => *video_standard,
+        }
+    }
+
+    /// Overrides the television standard of the program
+    pub fn set_video_standard(&mut self, new_video_standard: Option<VideoStandard>) {
+        match self {
+            ProgramInfo::V0 { video_standard, .. } => *video_standard = new_video_standard,
+        }
+    }
+
     /// Converts this to the latest version
     pub fn mitigate(self) -> Self {
         match self {
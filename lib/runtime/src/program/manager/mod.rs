@@ -11,13 +11,17 @@ use std::{
 use bytes::Bytes;
 use cfg_if::cfg_if;
 use redb::{
-    Database, MultimapTableDefinition, ReadableDatabase, ReadableMultimapTable,
-    backends::InMemoryBackend,
+    Database, MultimapTableDefinition, ReadableDatabase, ReadableMultimapTable, ReadableTable,
+    TableDefinition, TableError, backends::InMemoryBackend,
 };
 use rustc_hash::FxBuildHasher;
 
-use crate::program::{
-    MachineId, ProgramId, ProgramInfo, ProgramSpecification, RomId, info::Filesystem,
+use crate::{
+    machine::Quirks,
+    program::{
+        MachineId, ProgramId, ProgramInfo, ProgramSpecification, RomId, VideoStandard,
+        info::Filesystem,
+    },
 };
 
 /// Program id -> Program info mapping
@@ -26,6 +30,11 @@ pub const PROGRAM_INFORMATION_TABLE: MultimapTableDefinition<ProgramId, ProgramI
 /// Hash -> Program id reverse mapping
 pub const HASH_ALIAS_TABLE: MultimapTableDefinition<RomId, ProgramId> =
     MultimapTableDefinition::new("hash_alias");
+/// Program id -> Quirks mapping
+///
+/// Every machine has its own quirks type, so they are stored serialized
+pub const PROGRAM_QUIRKS_TABLE: TableDefinition<ProgramId, &[u8]> =
+    TableDefinition::new("program_quirks");
 
 static DATABASE_CACHE: LazyLock<scc::HashMap<PathBuf, Weak<Database>>> =
     LazyLock::new(Default::default);
@@ -54,6 +63,9 @@ impl Default for ProgramManager {
         database_transaction
             .open_multimap_table(HASH_ALIAS_TABLE)
             .unwrap();
+        database_transaction
+            .open_table(PROGRAM_QUIRKS_TABLE)
+            .unwrap();
         database_transaction.commit().unwrap();
 
         Self {
@@ -96,6 +108,9 @@ impl ProgramManager {
             database_transaction
                 .open_multimap_table(HASH_ALIAS_TABLE)
                 .unwrap();
+            database_transaction
+                .open_table(PROGRAM_QUIRKS_TABLE)
+                .unwrap();
             database_transaction.commit().unwrap();
 
             let database = Arc::new(database);
@@ -227,6 +242,7 @@ impl ProgramManager {
                     },
                     languages: BTreeSet::default(),
                     version: None,
+                    video_standard: None,
                     names: BTreeSet::from_iter([extensionless_file_name]),
                 },
             }));
@@ -321,6 +337,7 @@ impl ProgramManager {
                             },
                             languages: Default::default(),
                             version: None,
+                            video_standard: None,
                         },
                         id: ProgramId { machine, name },
                     })
@@ -374,6 +391,89 @@ impl ProgramManager {
             internal_database_transaction.commit()?;
         }
 
+        // Older databases will not have any quirks
+        let external_quirks_table =
+            match external_database_transaction.open_table(PROGRAM_QUIRKS_TABLE) {
+                Ok(table) => table,
+                Err(TableError::TableDoesNotExist(_)) => return Ok(()),
+                Err(error) => return Err(error.into()),
+            };
+
+        let internal_database_transaction = self.database().begin_write()?;
+        let mut internal_quirks_table =
+            internal_database_transaction.open_table(PROGRAM_QUIRKS_TABLE)?;
+
+        for item in external_quirks_table.iter()? {
+            let (program_id, quirks) = item?;
+
+            internal_quirks_table.insert(program_id.value(), quirks.value())?;
+        }
+
+        drop(internal_quirks_table);
+        internal_database_transaction.commit()?;
+
+        Ok(())
+    }
+
+    /// Looks up the quirks a program needs, if the database knows of any
+    pub fn quirks<Q: Quirks>(
+        &self,
+        program_id: &ProgramId,
+    ) -> Result<Option<Q>, Box<dyn std::error::Error>> {
+        let read_transaction = self.database().begin_read()?;
+        let quirks_table = read_transaction.open_table(PROGRAM_QUIRKS_TABLE)?;
+
+        let Some(quirks) = quirks_table.get(program_id)? else {
+            return Ok(None);
+        };
+
+        Ok(Some(rmp_serde::from_slice(quirks.value())?))
+    }
+
+    /// Records the quirks a program needs in the database
+    pub fn set_quirks<Q: Quirks>(
+        &self,
+        program_id: &ProgramId,
+        quirks: &Q,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let quirks = rmp_serde::to_vec_named(quirks)?;
+
+        let write_transaction = self.database().begin_write()?;
+        let mut quirks_table = write_transaction.open_table(PROGRAM_QUIRKS_TABLE)?;
+        quirks_table.insert(program_id, quirks.as_slice())?;
+
+        drop(quirks_table);
+        write_transaction.commit()?;
+
+        Ok(())
+    }
+
+    /// Records the television standard a program turned out to use, so
+    /// machines that have to work it out only do so once
+    pub fn set_video_standard(
+        &self,
+        program_specification: &ProgramSpecification,
+        video_standard: VideoStandard,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut info = program_specification.info.clone();
+        info.set_video_standard(Some(video_standard));
+
+        let write_transaction = self.database().begin_write()?;
+        let mut program_info_table =
+            write_transaction.open_multimap_table(PROGRAM_INFORMATION_TABLE)?;
+        program_info_table.remove(&program_specification.id, &program_specification.info)?;
+        program_info_table.insert(&program_specification.id, &info)?;
+        drop(program_info_table);
+
+        // Programs identified by their file name alone aren't in the database
+        // yet, so they need a way to be found again
+        if let Filesystem::Single { rom_id, .. } = info.filesystem() {
+            let mut hash_alias_table = write_transaction.open_multimap_table(HASH_ALIAS_TABLE)?;
+            hash_alias_table.insert(rom_id, &program_specification.id)?;
+        }
+
+        write_transaction.commit()?;
+
         Ok(())
     }
 
@@ -428,3 +528,99 @@ pub enum RomRequirement {
     /// Machine can not boot without this ROM
     Required,
 }
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::program::{AtariSystem, NintendoSystem, OtherSystem};
+
+    #[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+    struct TestQuirks {
+        shift_in_place: bool,
+    }
+
+    #[test]
+    fn quirks_round_trip() {
+        let program_manager = ProgramManager::default();
+        let program_id = ProgramId {
+            machine: MachineId::Other(OtherSystem::Chip8),
+            name: "Test".to_string(),
+        };
+        let quirks = TestQuirks {
+            shift_in_place: true,
+        };
+
+        program_manager.set_quirks(&program_id, &quirks).unwrap();
+
+        assert_eq!(
+            program_manager.quirks::<TestQuirks>(&program_id).unwrap(),
+            Some(quirks)
+        );
+        assert_eq!(
+            program_manager
+                .quirks::<TestQuirks>(&ProgramId {
+                    machine: MachineId::Nintendo(NintendoSystem::GameBoy),
+                    ..program_id
+                })
+                .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn video_standard_is_remembered() {
+        let program_manager = ProgramManager::default();
+        let rom_id = RomId([1; 20]);
+        let program_specification = ProgramSpecification {
+            id: ProgramId {
+                machine: MachineId::Atari(AtariSystem::Atari2600),
+                name: "Test".to_string(),
+            },
+            info: ProgramInfo::V0 {
+                names: BTreeSet::from(["Test".to_string()]),
+                filesystem: Filesystem::Single {
+                    rom_id,
+                    file_name: "Test.a26".to_string(),
+                },
+                languages: BTreeSet::default(),
+                version: None,
+                video_standard: None,
+            },
+        };
+
+        // Not in the database to begin with
+        assert!(
+            program_manager
+                .identify_program([rom_id])
+                .unwrap()
+                .is_none()
+        );
+
+        program_manager
+            .set_video_standard(&program_specification, VideoStandard::Pal)
+            .unwrap();
+        let identified = program_manager.identify_program([rom_id]).unwrap().unwrap();
+
+        assert_eq!(identified.id, program_specification.id);
+        assert_eq!(identified.info.video_standard(), Some(VideoStandard::Pal));
+
+        // Replaces what it said before rather than adding another entry
+        program_manager
+            .set_video_standard(&identified, VideoStandard::Secam)
+            .unwrap();
+
+        let read_transaction = program_manager.database().begin_read().unwrap();
+        let program_info_table = read_transaction
+            .open_multimap_table(PROGRAM_INFORMATION_TABLE)
+            .unwrap();
+        let infos: Vec<_> = program_info_table
+            .get(&program_specification.id)
+            .unwrap()
+            .map(|info| info.unwrap().value().video_standard())
+            .collect();
+
+        assert_eq!(infos, [Some(VideoStandard::Secam)]);
+    }
+}
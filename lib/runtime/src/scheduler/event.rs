@@ -1,84 +1,526 @@
 use std::{
     cmp::Reverse,
-    collections::BinaryHeap,
+    collections::{BinaryHeap, HashMap},
     fmt::Debug,
     sync::{
-        Mutex,
-        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, OnceLock, Weak,
+        atomic::{AtomicBool, AtomicU64, Ordering},
     },
 };
 
+use rustc_hash::FxBuildHasher;
+use serde::{Deserialize, Serialize};
+use serde_with::{DisplayFromStr, serde_as};
+
 use crate::{
     component::{Component, ComponentHandle},
-    scheduler::{Frequency, Period},
+    machine::registry::ComponentRegistry,
+    path::FluxEmuPath,
+    scheduler::{Frequency, Period, changing_shared_state, speculating},
 };
 
-#[derive(Debug, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub(crate) struct EventId(u64);
+
+pub(crate) type NamedEventCallback = Arc<dyn Fn(&mut dyn Component, Period) + Send + Sync>;
+
+#[allow(clippy::type_complexity)]
+enum EventCallback {
+    Once(Box<dyn FnOnce(&mut dyn Component, Period) + Send + Sync>),
+    Repeating(Box<dyn FnMut(&mut dyn Component, Period) + Send + Sync>),
+    Named {
+        path: FluxEmuPath,
+        callback: NamedEventCallback,
+    },
+}
+
+/// An event that can still fire, or be rescheduled to fire again
+struct EventRecord {
+    component: ComponentHandle,
+    /// Taken out while the event is firing
+    callback: Option<EventCallback>,
+    frequency: Option<Frequency>,
+    /// When the event is due next, if it is scheduled at all
+    time: Option<Period>,
+    /// Bumped whenever the event is cancelled or moved, so entries left in
+    /// the queue from before can be recognized and skipped
+    generation: u64,
+}
+
+/// A pending named event, as stored in snapshots
+#[serde_as]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EventSnapshot {
+    id: EventId,
+    /// The event kind, as returned when it was inserted
+    pub path: FluxEmuPath,
+    #[serde_as(as = "DisplayFromStr")]
+    pub time: Period,
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    pub frequency: Option<Frequency>,
+}
+
+#[derive(Default)]
 pub struct EventManager {
     event_queue: Mutex<BinaryHeap<QueuedEvent>>,
+    events: Mutex<HashMap<EventId, EventRecord, FxBuildHasher>>,
+    /// Event kinds components registered, by path
+    named: Mutex<HashMap<FluxEmuPath, NamedEventCallback, FxBuildHasher>>,
+    next_id: AtomicU64,
+    preemption_signals: OnceLock<Vec<Arc<PreemptionSignal>>>,
 }
 
 impl EventManager {
-    pub fn queue(&self, event: QueuedEvent) {
+    pub(crate) fn allocate_id(&self) -> EventId {
+        EventId(self.next_id.fetch_add(1, Ordering::Relaxed))
+    }
+
+    /// Set who to tell when the queue changes under in flight synchronizations
+    pub(crate) fn set_preemption_signals(&self, preemption_signals: Vec<Arc<PreemptionSignal>>) {
+        self.preemption_signals
+            .set(preemption_signals)
+            .expect("Preemption signals already set");
+    }
+
+    pub(crate) fn register_named(&self, path: FluxEmuPath, callback: NamedEventCallback) {
+        let existing = self.named.lock().unwrap().insert(path.clone(), callback);
+
+        assert!(existing.is_none(), "Event {path} registered twice");
+    }
+
+    /// Schedule an event under an id from [Self::allocate_id]
+    pub(crate) fn insert(
+        &self,
+        id: EventId,
+        component: ComponentHandle,
+        ty: EventType,
+        time: Period,
+    ) {
+        changing_shared_state();
+
+        let (callback, frequency) = match ty {
+            EventType::Once { callback } => (EventCallback::Once(callback), None),
+            EventType::Repeating {
+                frequency,
+                callback,
+            } => (EventCallback::Repeating(callback), Some(frequency)),
+            EventType::Named { path, frequency } => {
+                let callback = self
+                    .named
+                    .lock()
+                    .unwrap()
+                    .get(&path)
+                    .unwrap_or_else(|| panic!("Event {path} was never registered"))
+                    .clone();
+
+                (EventCallback::Named { path, callback }, frequency)
+            }
+        };
+
+        self.events.lock().unwrap().insert(
+            id,
+            EventRecord {
+                component,
+                callback: Some(callback),
+                frequency,
+                time: Some(time),
+                generation: 0,
+            },
+        );
+
+        self.queue(QueuedEvent {
+            id,
+            generation: 0,
+            time: Reverse(time),
+        });
+    }
+
+    pub(crate) fn schedule(
+        self: &Arc<Self>,
+        component: ComponentHandle,
+        ty: EventType,
+        time: Period,
+    ) -> EventHandle {
+        let id = self.allocate_id();
+        self.insert(id, component, ty, time);
+
+        EventHandle::new(id, self)
+    }
+
+    fn queue(&self, event: QueuedEvent) {
         self.event_queue.lock().unwrap().push(event);
+
+        for signal in self.preemption_signals.get().into_iter().flatten() {
+            signal.event_occured();
+        }
+    }
+
+    /// If the queue entry still refers to the current schedule of its event
+    fn is_current(&self, event: &QueuedEvent) -> bool {
+        self.events
+            .lock()
+            .unwrap()
+            .get(&event.id)
+            .is_some_and(|record| record.generation == event.generation)
     }
 
     pub fn consume_events(&self, upto: Period) {
+        // Firing cannot be taken back, so check before the queue is locked
+        if speculating() && self.next_event().is_some_and(|time| time <= upto) {
+            changing_shared_state();
+        }
+
         let mut queue_guard = self.event_queue.lock().unwrap();
 
         while let Some(event) = queue_guard.peek() {
+            if !self.is_current(event) {
+                queue_guard.pop();
+                continue;
+            }
+
             if upto < event.time.0 {
                 break;
             }
+
             let event = queue_guard.pop().unwrap();
 
             drop(queue_guard);
 
-            match event.ty {
-                EventType::Once { callback } => {
-                    event.component.interact_mut(event.time.0, |component| {
-                        callback(component, event.time.0);
-                    });
-                    queue_guard = self.event_queue.lock().unwrap();
-                }
-                EventType::Repeating {
-                    frequency,
-                    mut callback,
-                } => {
-                    event.component.interact_mut(event.time.0, |component| {
-                        callback(component, event.time.0);
-                    });
-                    queue_guard = self.event_queue.lock().unwrap();
-
-                    queue_guard.push(QueuedEvent {
-                        component: event.component,
-                        ty: EventType::Repeating {
-                            frequency,
-                            callback,
-                        },
-                        time: Reverse(event.time.0 + frequency.recip()),
-                    });
-                }
+            self.fire(event);
+
+            queue_guard = self.event_queue.lock().unwrap();
+        }
+    }
+
+    fn fire(&self, event: QueuedEvent) {
+        let time = event.time.0;
+
+        let Some((component, callback)) = self
+            .events
+            .lock()
+            .unwrap()
+            .get_mut(&event.id)
+            .filter(|record| record.generation == event.generation)
+            .and_then(|record| Some((record.component.clone(), record.callback.take()?)))
+        else {
+            return;
+        };
+
+        // The callback may well cancel or reschedule its own event, so no locks
+        // can be held here
+        let callback = match callback {
+            EventCallback::Once(callback) => {
+                component.interact_mut(time, |component| {
+                    callback(component, time);
+                });
+
+                None
+            }
+            EventCallback::Repeating(mut callback) => {
+                component.interact_mut(time, |component| {
+                    callback(component, time);
+                });
+
+                Some(EventCallback::Repeating(callback))
+            }
+            EventCallback::Named { path, callback } => {
+                component.interact_mut(time, |component| {
+                    callback(component, time);
+                });
+
+                Some(EventCallback::Named { path, callback })
             }
+        };
+
+        let mut events = self.events.lock().unwrap();
+        let Some(record) = events.get_mut(&event.id) else {
+            return;
+        };
+
+        let Some(frequency) = record.frequency else {
+            events.remove(&event.id);
+            return;
+        };
+        record.callback = callback;
+
+        // Moved or cancelled from the callback, which takes priority
+        if record.generation != event.generation {
+            return;
         }
+
+        let next_time = time + frequency.recip();
+        record.time = Some(next_time);
+
+        let next = QueuedEvent {
+            id: event.id,
+            generation: record.generation,
+            time: Reverse(next_time),
+        };
+        drop(events);
+
+        self.event_queue.lock().unwrap().push(next);
     }
 
     #[inline]
     pub fn next_event(&self) -> Option<Period> {
-        let queue_guard = self.event_queue.lock().unwrap();
+        let mut queue_guard = self.event_queue.lock().unwrap();
+
+        while let Some(next_event) = queue_guard.peek() {
+            if self.is_current(next_event) {
+                return Some(next_event.time.0);
+            }
 
-        if let Some(next_event) = queue_guard.peek() {
-            return Some(next_event.time.0);
+            queue_guard.pop();
         }
 
         None
     }
+
+    fn cancel(&self, id: EventId) {
+        changing_shared_state();
+
+        let mut events = self.events.lock().unwrap();
+
+        let Some(record) = events.get_mut(&id) else {
+            return;
+        };
+
+        // One shot events can never come back, so there is no point keeping
+        // them around
+        if record.frequency.is_none() {
+            events.remove(&id);
+            return;
+        }
+
+        record.generation += 1;
+        record.time = None;
+    }
+
+    fn reschedule(&self, id: EventId, time: Period) -> bool {
+        changing_shared_state();
+
+        let generation = {
+            let mut events = self.events.lock().unwrap();
+
+            let Some(record) = events.get_mut(&id) else {
+                return false;
+            };
+
+            // A one shot event that is firing right now is as good as gone
+            if record.frequency.is_none() && record.callback.is_none() {
+                return false;
+            }
+
+            record.generation += 1;
+            record.time = Some(time);
+            record.generation
+        };
+
+        self.queue(QueuedEvent {
+            id,
+            generation,
+            time: Reverse(time),
+        });
+
+        true
+    }
+
+    fn set_frequency(&self, id: EventId, frequency: Frequency) -> bool {
+        changing_shared_state();
+
+        match self
+            .events
+            .lock()
+            .unwrap()
+            .get_mut(&id)
+            .and_then(|record| record.frequency.as_mut())
+        {
+            Some(current) => {
+                *current = frequency;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Describe every pending named event, in the order they will fire
+    ///
+    /// Events scheduled with closures cannot be described and are left out
+    pub(crate) fn snapshot(&self) -> Vec<EventSnapshot> {
+        let mut events: Vec<_> = self
+            .events
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(|(id, record)| match &record.callback {
+                Some(EventCallback::Named { path, .. }) => Some(EventSnapshot {
+                    id: *id,
+                    path: path.clone(),
+                    time: record.time?,
+                    frequency: record.frequency,
+                }),
+                _ => None,
+            })
+            .collect();
+
+        events.sort_by_key(|event| (event.time, event.id));
+        events
+    }
+
+    /// Replace every event with the named ones from a snapshot
+    ///
+    /// Events scheduled with closures cannot be stored, so they belonged to
+    /// the timeline being replaced and are dropped. Named events keep their
+    /// identity where they still line up with the snapshot, so handles taken
+    /// while building the machine stay valid
+    pub(crate) fn restore(&self, snapshot: Vec<EventSnapshot>, registry: &ComponentRegistry) {
+        let mut pending = Vec::new();
+
+        {
+            let mut events = self.events.lock().unwrap();
+
+            events.retain(|_, record| {
+                !matches!(
+                    record.callback,
+                    Some(EventCallback::Once(_) | EventCallback::Repeating(_))
+                )
+            });
+
+            for record in events.values_mut() {
+                if matches!(record.callback, Some(EventCallback::Named { .. })) {
+                    record.generation += 1;
+                    record.time = None;
+                }
+            }
+
+            for event in snapshot {
+                let existing = events.get_mut(&event.id).filter(|record| {
+                    matches!(
+                        &record.callback,
+                        Some(EventCallback::Named { path, .. }) if *path == event.path
+                    )
+                });
+
+                match existing {
+                    Some(record) => {
+                        record.frequency = event.frequency;
+                        record.time = Some(event.time);
+                        pending.push((event.id, record.generation, event.time));
+                    }
+                    None => pending.push(self.restore_record(&mut events, event, registry)),
+                }
+            }
+
+            // One shot events the snapshot did not have are over, same as if
+            // they had been cancelled
+            events.retain(|_, record| record.time.is_some() || record.frequency.is_some());
+        }
+
+        for (id, generation, time) in pending {
+            self.queue(QueuedEvent {
+                id,
+                generation,
+                time: Reverse(time),
+            });
+        }
+    }
+
+    fn restore_record(
+        &self,
+        events: &mut HashMap<EventId, EventRecord, FxBuildHasher>,
+        event: EventSnapshot,
+        registry: &ComponentRegistry,
+    ) -> (EventId, u64, Period) {
+        let component = event
+            .path
+            .parent()
+            .and_then(|path| registry.handle(&path))
+            .unwrap_or_else(|| panic!("Event {} has no component", event.path));
+        let callback = self
+            .named
+            .lock()
+            .unwrap()
+            .get(&event.path)
+            .unwrap_or_else(|| panic!("Event {} was never registered", event.path))
+            .clone();
+
+        // Keep the id the snapshot had if nothing else took it since
+        let id = if events.contains_key(&event.id) {
+            self.allocate_id()
+        } else {
+            self.next_id.fetch_max(event.id.0 + 1, Ordering::Relaxed);
+            event.id
+        };
+
+        events.insert(
+            id,
+            EventRecord {
+                component,
+                callback: Some(EventCallback::Named {
+                    path: event.path,
+                    callback,
+                }),
+                frequency: event.frequency,
+                time: Some(event.time),
+                generation: 0,
+            },
+        );
+
+        (id, 0, event.time)
+    }
+}
+
+/// Refers to a scheduled event, so it can be cancelled or moved later on
+#[derive(Debug, Clone)]
+pub struct EventHandle {
+    id: EventId,
+    event_manager: Weak<EventManager>,
+}
+
+impl EventHandle {
+    pub(crate) fn new(id: EventId, event_manager: &Arc<EventManager>) -> Self {
+        Self {
+            id,
+            event_manager: Arc::downgrade(event_manager),
+        }
+    }
+
+    /// Stop the event from firing
+    ///
+    /// Repeating events can be brought back with [Self::reschedule], one shot
+    /// events are gone for good
+    pub fn cancel(&self) {
+        if let Some(event_manager) = self.event_manager.upgrade() {
+            event_manager.cancel(self.id);
+        }
+    }
+
+    /// Make the event fire next at `time` instead of whenever it was going to,
+    /// which also re-arms a cancelled repeating event
+    ///
+    /// Repeating events carry on at their frequency from there. Returns false
+    /// if there is nothing to reschedule, as one shot events are gone once
+    /// they fire or are cancelled.
+    pub fn reschedule(&self, time: Period) -> bool {
+        self.event_manager
+            .upgrade()
+            .is_some_and(|event_manager| event_manager.reschedule(self.id, time))
+    }
+
+    /// Change how often a repeating event fires, starting after it next fires
+    ///
+    /// Returns false if this is not a repeating event
+    pub fn set_frequency(&self, frequency: Frequency) -> bool {
+        self.event_manager
+            .upgrade()
+            .is_some_and(|event_manager| event_manager.set_frequency(self.id, frequency))
+    }
 }
 
-pub(crate) struct QueuedEvent {
-    pub component: ComponentHandle,
-    pub ty: EventType,
-    pub time: Reverse<Period>,
+struct QueuedEvent {
+    id: EventId,
+    generation: u64,
+    time: Reverse<Period>,
 }
 
 // NOTE: These operations are purely so our minheap schedule timeline works
@@ -111,6 +553,26 @@ impl Debug for QueuedEvent {
     }
 }
 
+impl Debug for EventManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventManager")
+            .field("event_queue", &self.event_queue)
+            .field("events", &self.events)
+            .field("next_id", &self.next_id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Debug for EventRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventRecord")
+            .field("frequency", &self.frequency)
+            .field("time", &self.time)
+            .field("generation", &self.generation)
+            .finish()
+    }
+}
+
 #[allow(clippy::type_complexity)]
 pub(crate) enum EventType {
     Once {
@@ -120,6 +582,10 @@ pub(crate) enum EventType {
         frequency: Frequency,
         callback: Box<dyn FnMut(&mut dyn Component, Period) + Send + Sync>,
     },
+    Named {
+        path: FluxEmuPath,
+        frequency: Option<Frequency>,
+    },
 }
 
 #[derive(Debug, Default)]
@@ -1,19 +1,25 @@
 use std::{
     collections::HashMap,
     fmt::Debug,
-    sync::Arc,
+    sync::{Arc, Mutex, OnceLock},
     thread::sleep,
     time::{Duration, Instant},
 };
 
 use crossbeam::atomic::AtomicCell;
-pub(crate) use event::{EventManager, EventType, PreemptionSignal, QueuedEvent};
+pub use event::{EventHandle, EventSnapshot};
+pub(crate) use event::{EventId, EventManager, EventType, NamedEventCallback, PreemptionSignal};
 use fixed::{FixedU128, types::extra::U64};
+pub use parallel::ExecutionMode;
+pub(crate) use parallel::{
+    ComponentId, accessing, changing_shared_state, speculating, synchronizing,
+};
 use rustc_hash::FxBuildHasher;
 
-use crate::{component::ComponentHandle, path::FluxEmuPath};
+use crate::{component::ComponentHandle, machine::registry::ComponentRegistry, path::FluxEmuPath};
 
 mod event;
+mod parallel;
 #[cfg(test)]
 mod tests;
 
@@ -31,6 +37,11 @@ pub(crate) struct Scheduler {
     pub event_queue: Arc<EventManager>,
     driven: HashMap<FluxEmuPath, DrivenComponent, FxBuildHasher>,
     now: AtomicCell<Period>,
+    execution_mode: ExecutionMode,
+    interactions: Mutex<parallel::InteractionGraph>,
+    /// If every component can be snapshotted, so a quantum can be undone
+    can_roll_back: OnceLock<bool>,
+    checkpoint: Mutex<parallel::Checkpoint>,
 }
 
 impl Scheduler {
@@ -39,19 +50,44 @@ impl Scheduler {
             event_queue: Arc::default(),
             driven: HashMap::default(),
             now: AtomicCell::default(),
+            execution_mode: ExecutionMode::default(),
+            interactions: Mutex::default(),
+            can_roll_back: OnceLock::new(),
+            checkpoint: Mutex::default(),
+        }
+    }
+
+    pub fn set_execution_mode(&mut self, execution_mode: ExecutionMode) {
+        if let ExecutionMode::Parallel { quantum } = execution_mode {
+            assert_ne!(quantum, Period::ZERO, "Parallel quantum cannot be zero");
         }
+
+        self.execution_mode = execution_mode;
     }
 
     pub fn register_driven_component(&mut self, path: FluxEmuPath, component: ComponentHandle) {
         self.driven.insert(path, DrivenComponent { component });
     }
 
-    pub fn run(&self, allocated_time: Period) {
-        let mut now = self.now.load();
+    /// Always run two components in the same group, for state they share
+    /// outside of their handles
+    pub fn link_components(&mut self, source: &ComponentHandle, target: &ComponentHandle) {
+        self.interactions
+            .get_mut()
+            .unwrap()
+            .link(source.id(), target.id());
+    }
+
+    pub fn run(&self, allocated_time: Period, registry: &ComponentRegistry) {
+        let start = self.now.load();
+        let now = start + allocated_time;
 
-        now += allocated_time;
         self.now.store(now);
-        self.update_driver_components(now);
+
+        match self.execution_mode {
+            ExecutionMode::Sequential => self.update_driver_components(now),
+            ExecutionMode::Parallel { quantum } => self.run_parallel(start, now, quantum, registry),
+        }
     }
 
     pub fn update_driver_components(&self, now: Period) {
@@ -63,6 +99,10 @@ impl Scheduler {
     pub fn now(&self) -> Period {
         self.now.load()
     }
+
+    pub fn set_now(&self, now: Period) {
+        self.now.store(now);
+    }
 }
 
 pub type Period = FixedU128<U64>;
@@ -146,6 +186,14 @@ pub struct QuantaIterator<'b, 'a> {
     context: &'b mut SynchronizationContext<'a>,
 }
 
+impl QuantaIterator<'_, '_> {
+    /// How many quanta are left, unless a new event cuts the allocation short
+    #[inline]
+    pub fn remaining(&self) -> u64 {
+        self.budget
+    }
+}
+
 impl Iterator for QuantaIterator<'_, '_> {
     type Item = Period;
 
@@ -0,0 +1,503 @@
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    io::{Cursor, Read, Write},
+    panic::{AssertUnwindSafe, catch_unwind, resume_unwind},
+    rc::Rc,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use rayon::iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator};
+use rustc_hash::{FxBuildHasher, FxHashSet};
+
+use super::{Period, Scheduler};
+use crate::{
+    component::{ComponentHandle, ComponentVersion},
+    machine::registry::ComponentRegistry,
+};
+
+/// How the scheduler runs the components it drives
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExecutionMode {
+    /// One after another on the calling thread
+    #[default]
+    Sequential,
+    /// Components that have not been seen interacting with each other run
+    /// side by side on the rayon pool, meeting up every `quantum`
+    ///
+    /// Interactions are discovered while running, starting with a sequential
+    /// quantum. Running apart is speculative: the components the groups have
+    /// been seen with are checkpointed first, and as soon as a group reaches
+    /// for a component outside of its own, or touches events or memory maps,
+    /// the quantum is rolled back and run again in order. Components always
+    /// see each other at the same point in time, and quanta with an event due
+    /// run in order to begin with.
+    ///
+    /// Checkpointing snapshots those components before every quantum run
+    /// apart, so this only beats [ExecutionMode::Sequential] when the groups
+    /// have more work to split up per quantum than it takes to snapshot them.
+    /// Longer quanta spread that cost out further.
+    ///
+    /// Rolling back relies on snapshots, so everything runs in order unless
+    /// every component supports them. State shared outside of handles, such
+    /// as an interrupt line, is only known about if it was picked up with
+    /// [crate::machine::builder::ComponentBuilder::interact].
+    Parallel { quantum: Period },
+}
+
+/// Identity of a component handle, stable for as long as the machine lives
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct ComponentId(pub(crate) usize);
+
+type Interactions = FxHashSet<(ComponentId, ComponentId)>;
+
+thread_local! {
+    /// The component whose synchronization is running on this thread
+    static SYNCHRONIZING: Cell<Option<ComponentId>> = const { Cell::new(None) };
+    /// Accesses between components seen on this thread, if being recorded
+    static INTERACTIONS: RefCell<Option<Interactions>> = const { RefCell::new(None) };
+    /// The group this thread is running apart from the others, if any
+    static SPECULATING: RefCell<Option<(Arc<Speculation>, usize)>> = const { RefCell::new(None) };
+}
+
+/// Run `callback` as the synchronization of `component`
+#[inline]
+pub(crate) fn synchronizing<T>(component: ComponentId, callback: impl FnOnce() -> T) -> T {
+    let outer = SYNCHRONIZING.replace(Some(component));
+    let result = callback();
+    SYNCHRONIZING.set(outer);
+
+    result
+}
+
+/// Note that a component is about to be accessed
+///
+/// Unwinds back to the scheduler if the component belongs to a group other
+/// than the one running on this thread
+#[inline]
+pub(crate) fn accessing(target: ComponentId) {
+    if let Some(source) = SYNCHRONIZING.get()
+        && source != target
+    {
+        INTERACTIONS.with_borrow_mut(|interactions| {
+            if let Some(interactions) = interactions {
+                interactions.insert((source, target));
+            }
+        });
+    }
+
+    SPECULATING.with_borrow(|speculating| {
+        if let Some((speculation, group)) = speculating
+            && !speculation.may_access(*group, target)
+        {
+            speculation.conflict();
+        }
+    });
+}
+
+/// If this thread is running a group apart from the others
+#[inline]
+pub(crate) fn speculating() -> bool {
+    SPECULATING.with_borrow(Option::is_some)
+}
+
+/// Note that something every component can see is about to change, which
+/// cannot be taken back if this thread is running apart from the others
+#[inline]
+pub(crate) fn changing_shared_state() {
+    SPECULATING.with_borrow(|speculating| {
+        if let Some((speculation, _)) = speculating {
+            speculation.conflict();
+        }
+    });
+}
+
+fn record_interactions(
+    speculating: Option<(Arc<Speculation>, usize)>,
+    callback: impl FnOnce(),
+) -> Interactions {
+    let recording = Recording::start(speculating);
+    callback();
+
+    recording.finish()
+}
+
+/// Thread state from before a recording, put back even when a conflict
+/// unwinds through it
+struct Recording {
+    synchronizing: Option<ComponentId>,
+    interactions: Option<Interactions>,
+    speculating: Option<(Arc<Speculation>, usize)>,
+}
+
+impl Recording {
+    fn start(speculating: Option<(Arc<Speculation>, usize)>) -> Self {
+        // Rayon may run this inside of another recording while waiting on work
+        Self {
+            synchronizing: SYNCHRONIZING.take(),
+            interactions: INTERACTIONS.replace(Some(Interactions::default())),
+            speculating: SPECULATING.replace(speculating),
+        }
+    }
+
+    fn finish(self) -> Interactions {
+        INTERACTIONS.take().unwrap_or_default()
+    }
+}
+
+impl Drop for Recording {
+    fn drop(&mut self) {
+        SYNCHRONIZING.set(self.synchronizing);
+        INTERACTIONS.set(self.interactions.take());
+        SPECULATING.set(self.speculating.take());
+    }
+}
+
+/// Unwinds a group out of a quantum it cannot finish apart from the others
+struct Conflict;
+
+/// Who may touch what while groups run apart
+#[derive(Debug)]
+struct Speculation {
+    /// The set each component already in the interaction graph belongs to
+    sets: HashMap<ComponentId, ComponentId, FxBuildHasher>,
+    /// Which group runs each set with driven components in it
+    owners: HashMap<ComponentId, usize, FxBuildHasher>,
+    conflicted: AtomicBool,
+}
+
+impl Speculation {
+    /// The group running the set a component is in, if any
+    fn owner(&self, component: ComponentId) -> Option<usize> {
+        let set = self.sets.get(&component).copied().unwrap_or(component);
+
+        self.owners.get(&set).copied()
+    }
+
+    fn may_access(&self, group: usize, target: ComponentId) -> bool {
+        // Another group already gave up, so this quantum is being redone
+        if self.conflicted.load(Ordering::Relaxed) {
+            return false;
+        }
+
+        // Components no group has been seen with were not checkpointed, so
+        // reaching one for the first time has to be learned in order
+        self.owner(target) == Some(group)
+    }
+
+    fn conflict(&self) -> ! {
+        self.conflicted.store(true, Ordering::Relaxed);
+
+        // Not a panic, so the panic hook stays quiet
+        resume_unwind(Box::new(Conflict))
+    }
+}
+
+/// Which components have been seen interacting, as a disjoint set
+#[derive(Debug, Default)]
+pub(crate) struct InteractionGraph {
+    parents: HashMap<ComponentId, ComponentId, FxBuildHasher>,
+    /// If a quantum has been run to discover interactions yet
+    learned: bool,
+}
+
+impl InteractionGraph {
+    fn root(&mut self, component: ComponentId) -> ComponentId {
+        let parent = *self.parents.entry(component).or_insert(component);
+
+        if parent == component {
+            return component;
+        }
+
+        let root = self.root(parent);
+        self.parents.insert(component, root);
+
+        root
+    }
+
+    /// Always run two components together, for state they share outside of
+    /// their handles
+    pub(crate) fn link(&mut self, source: ComponentId, target: ComponentId) {
+        let source = self.root(source);
+        let target = self.root(target);
+
+        if source != target {
+            self.parents.insert(source, target);
+        }
+    }
+
+    fn merge(&mut self, interactions: impl IntoIterator<Item = (ComponentId, ComponentId)>) {
+        for (source, target) in interactions {
+            self.link(source, target);
+        }
+
+        self.learned = true;
+    }
+
+    /// Split `components` into groups that can run apart from each other
+    pub(crate) fn groups<'a>(
+        &mut self,
+        components: impl IntoIterator<Item = &'a ComponentHandle>,
+    ) -> Vec<Vec<&'a ComponentHandle>> {
+        if !self.learned {
+            return vec![components.into_iter().collect()];
+        }
+
+        let mut groups: HashMap<_, Vec<_>, FxBuildHasher> = HashMap::default();
+
+        for component in components {
+            groups
+                .entry(self.root(component.id()))
+                .or_default()
+                .push(component);
+        }
+
+        groups.into_values().collect()
+    }
+
+    fn speculation(&mut self, groups: &[Vec<&ComponentHandle>]) -> Speculation {
+        let owners = groups
+            .iter()
+            .enumerate()
+            .map(|(index, group)| (self.root(group[0].id()), index))
+            .collect();
+
+        let components: Vec<_> = self.parents.keys().copied().collect();
+        let sets = components
+            .into_iter()
+            .map(|component| (component, self.root(component)))
+            .collect();
+
+        Speculation {
+            sets,
+            owners,
+            conflicted: AtomicBool::default(),
+        }
+    }
+}
+
+/// Where a snapshot is written to and read back from, handing the buffer
+/// back afterwards so the next checkpoint can reuse it
+#[derive(Default, Clone)]
+struct CheckpointBuffer(Rc<RefCell<Cursor<Vec<u8>>>>);
+
+impl CheckpointBuffer {
+    fn new(buffer: Vec<u8>) -> Self {
+        Self(Rc::new(RefCell::new(Cursor::new(buffer))))
+    }
+
+    fn into_inner(self) -> Vec<u8> {
+        self.0.take().into_inner()
+    }
+}
+
+impl Write for CheckpointBuffer {
+    fn write(&mut self, buffer: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().write(buffer)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Read for CheckpointBuffer {
+    fn read(&mut self, buffer: &mut [u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().read(buffer)
+    }
+}
+
+#[derive(Debug)]
+struct CheckpointedComponent {
+    handle: ComponentHandle,
+    version: ComponentVersion,
+    state: Vec<u8>,
+    updated_timestamp: Option<Period>,
+}
+
+/// The components a quantum run apart could change, as they were before it
+/// ran
+///
+/// Kept around between quanta so the buffers are only allocated once
+#[derive(Debug, Default)]
+pub(crate) struct Checkpoint {
+    components: Vec<CheckpointedComponent>,
+    spare_buffers: Vec<Vec<u8>>,
+}
+
+impl Checkpoint {
+    /// How many components the last checkpoint covered
+    #[cfg(test)]
+    pub(crate) fn len(&self) -> usize {
+        self.components.len()
+    }
+
+    fn take(&mut self, registry: &ComponentRegistry, speculation: &Speculation) {
+        self.spare_buffers
+            .extend(self.components.drain(..).map(|component| component.state));
+
+        for (path, handle) in registry.handles() {
+            // Nothing outside of the groups can be touched without a conflict
+            if speculation.owner(handle.id()).is_none() {
+                continue;
+            }
+
+            let mut state = self.spare_buffers.pop().unwrap_or_default();
+            state.clear();
+            let buffer = CheckpointBuffer::new(state);
+
+            let version = handle.interact_without_synchronization(|component| {
+                component
+                    .store_snapshot(Box::new(buffer.clone()))
+                    .unwrap_or_else(|error| panic!("Could not checkpoint {path}: {error}"));
+
+                component.snapshot_version().unwrap()
+            });
+
+            self.components.push(CheckpointedComponent {
+                handle: handle.clone(),
+                version,
+                state: buffer.into_inner(),
+                updated_timestamp: handle.updated_timestamp(),
+            });
+        }
+    }
+
+    fn restore(&mut self) {
+        for component in &mut self.components {
+            // Whatever the conflict unwound through is about to be replaced
+            component.handle.clear_poison();
+
+            let buffer = CheckpointBuffer::new(std::mem::take(&mut component.state));
+
+            component
+                .handle
+                .interact_mut_without_synchronization(|inner| {
+                    inner
+                        .load_snapshot(component.version, Box::new(buffer.clone()))
+                        .expect("Could not roll back component");
+                });
+
+            component.state = buffer.into_inner();
+
+            if let Some(updated_timestamp) = component.updated_timestamp {
+                component.handle.set_updated_timestamp(updated_timestamp);
+            }
+        }
+    }
+}
+
+impl Scheduler {
+    pub(super) fn run_parallel(
+        &self,
+        mut barrier: Period,
+        target: Period,
+        quantum: Period,
+        registry: &ComponentRegistry,
+    ) {
+        while barrier < target {
+            barrier = (barrier + quantum).min(target);
+
+            let (learned, groups) = {
+                let mut interactions = self.interactions.lock().unwrap();
+                let groups =
+                    interactions.groups(self.driven.values().map(|driven| &driven.component));
+
+                (interactions.learned, groups)
+            };
+
+            // Once everything is known to run together there is nothing
+            // left to learn
+            if learned && groups.len() <= 1 {
+                self.update_driver_components(barrier);
+                continue;
+            }
+
+            // Events cannot be taken back, so quanta they fire in run in order
+            let event_due = self
+                .event_queue
+                .next_event()
+                .is_some_and(|time| time <= barrier);
+
+            let speculated = if groups.len() > 1 && !event_due && self.can_roll_back(registry) {
+                let speculation = self.interactions.lock().unwrap().speculation(&groups);
+                let mut checkpoint = self.checkpoint.lock().unwrap();
+                checkpoint.take(registry, &speculation);
+                let interactions = speculate(groups, speculation, barrier);
+
+                if interactions.is_none() {
+                    checkpoint.restore();
+                }
+
+                interactions
+            } else {
+                None
+            };
+
+            let interactions = speculated.unwrap_or_else(|| {
+                record_interactions(None, || self.update_driver_components(barrier))
+            });
+
+            self.interactions.lock().unwrap().merge(interactions);
+        }
+    }
+
+    fn can_roll_back(&self, registry: &ComponentRegistry) -> bool {
+        *self.can_roll_back.get_or_init(|| {
+            let can_roll_back = registry.handles().all(|(_, handle)| {
+                handle.interact_without_synchronization(|component| {
+                    component.snapshot_version().is_some()
+                })
+            });
+
+            if !can_roll_back {
+                tracing::warn!(
+                    "Not every component supports snapshots, so components cannot run in parallel"
+                );
+            }
+
+            can_roll_back
+        })
+    }
+}
+
+/// Run each group up to `barrier` on its own thread, giving up if any of them
+/// reach outside of their group
+fn speculate(
+    groups: Vec<Vec<&ComponentHandle>>,
+    speculation: Speculation,
+    barrier: Period,
+) -> Option<Interactions> {
+    let speculation = Arc::new(speculation);
+
+    groups
+        .into_par_iter()
+        .enumerate()
+        .map(|(index, group)| {
+            catch_unwind(AssertUnwindSafe(|| {
+                record_interactions(Some((speculation.clone(), index)), || {
+                    for component in group {
+                        component.interact_mut(barrier, |_| {});
+                    }
+                })
+            }))
+            .map_err(|payload| {
+                if !payload.is::<Conflict>() {
+                    resume_unwind(payload)
+                }
+            })
+            .ok()
+        })
+        .reduce(
+            || Some(Interactions::default()),
+            |first, second| {
+                let mut first = first?;
+                first.extend(second?);
+
+                Some(first)
+            },
+        )
+}
@@ -1,14 +1,27 @@
 use crate::{
-    component::{Component, ComponentConfig},
+    component::{Component, ComponentConfig, ComponentVersion, TypedComponentHandle},
     machine::{
         Machine,
         builder::{ComponentBuilder, SchedulerParticipation},
     },
+    path::{FluxEmuPath, Namespace},
+    persistence::{SNAPSHOT_METADATA_FILE_NAME, SnapshotMetadata},
     platform::Platform,
-    scheduler::{Period, SynchronizationContext},
+    program::{ProgramManager, RomId},
+    scheduler::{EventHandle, ExecutionMode, Frequency, Period, SynchronizationContext},
 };
 use num::FromPrimitive;
-use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use std::{
+    any::Any,
+    env,
+    fs::{read_to_string, remove_dir_all, write},
+    io::{Read, Write},
+    path::Path,
+    process,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
 #[test]
 fn basic_operation() {
@@ -175,3 +188,568 @@ fn basic_events() {
             assert_eq!(component.event_counter, 1000);
         });
 }
+
+#[derive(Debug)]
+struct CountingComponent {
+    counter: u32,
+    /// Checked to always be exactly in step with this component
+    other: Option<TypedComponentHandle<CountingComponent>>,
+    /// When to start checking on the other component
+    watch_from: Period,
+}
+
+impl Component for CountingComponent {
+    fn snapshot_version(&self) -> Option<ComponentVersion> {
+        Some(0)
+    }
+
+    fn store_snapshot(&self, mut writer: Box<dyn Write>) -> Result<(), Box<dyn std::error::Error>> {
+        rmp_serde::encode::write(&mut writer, &self.counter)?;
+
+        Ok(())
+    }
+
+    fn load_snapshot(
+        &mut self,
+        _version: ComponentVersion,
+        reader: Box<dyn Read>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.counter = rmp_serde::decode::from_read(reader)?;
+
+        Ok(())
+    }
+
+    fn synchronize(&mut self, mut context: SynchronizationContext) {
+        for now in context.allocate(Period::ONE / 1000, None) {
+            self.counter += 1;
+
+            if let Some(other) = &self.other
+                && now >= self.watch_from
+            {
+                assert_eq!(other.interact(now, |other| other.counter), self.counter);
+            }
+        }
+    }
+
+    fn needs_work(&self, delta: Period) -> bool {
+        delta >= Period::ONE / 1000
+    }
+}
+
+#[derive(Debug)]
+struct CountingComponentConfig {
+    scheduler_participation: SchedulerParticipation,
+    other: Option<FluxEmuPath>,
+    watch_from: Period,
+}
+
+impl<P: Platform> ComponentConfig<P> for CountingComponentConfig {
+    type Component = CountingComponent;
+
+    fn build_component(
+        self,
+        component_builder: ComponentBuilder<P, Self::Component>,
+    ) -> Result<Self::Component, Box<dyn std::error::Error>> {
+        let other = self
+            .other
+            .map(|path| component_builder.typed_handle(&path).unwrap());
+
+        component_builder.set_scheduler_participation(self.scheduler_participation);
+
+        Ok(CountingComponent {
+            counter: 0,
+            other,
+            watch_from: self.watch_from,
+        })
+    }
+}
+
+#[test]
+fn parallel_groups_interacting_components() {
+    let (machine, independent) = Machine::build_test_minimal()
+        .execution_mode(ExecutionMode::Parallel {
+            quantum: Period::ONE / 100,
+        })
+        .insert_component(
+            "independent",
+            CountingComponentConfig {
+                scheduler_participation: SchedulerParticipation::SchedulerDriven,
+                other: None,
+                watch_from: Period::ZERO,
+            },
+        );
+    let (machine, observed) = machine.insert_component(
+        "observed",
+        CountingComponentConfig {
+            scheduler_participation: SchedulerParticipation::OnDemand,
+            other: None,
+            watch_from: Period::ZERO,
+        },
+    );
+    let (machine, observer) = machine.insert_component(
+        "observer",
+        CountingComponentConfig {
+            scheduler_participation: SchedulerParticipation::SchedulerDriven,
+            other: Some(observed.clone()),
+            watch_from: Period::ZERO,
+        },
+    );
+    let machine = machine.build(());
+
+    machine.run_duration(Duration::from_secs(1));
+
+    for path in [&independent, &observed, &observer] {
+        machine
+            .registry
+            .interact_without_synchronization::<CountingComponent, _>(path, |component| {
+                assert_eq!(component.counter, 1000);
+            });
+    }
+
+    let handle = |path| machine.registry.handle(path).unwrap();
+    let (independent, observed, observer) =
+        (handle(&independent), handle(&observed), handle(&observer));
+
+    let groups =
+        machine
+            .scheduler
+            .interactions
+            .lock()
+            .unwrap()
+            .groups([&independent, &observed, &observer]);
+
+    assert_eq!(groups.len(), 2);
+    assert!(groups.iter().any(|group| {
+        group.len() == 2
+            && group
+                .iter()
+                .any(|component| component.id() == observed.id())
+            && group
+                .iter()
+                .any(|component| component.id() == observer.id())
+    }));
+}
+
+#[test]
+fn parallel_checkpoints_only_cover_groups() {
+    let (machine, _) = Machine::build_test_minimal()
+        .execution_mode(ExecutionMode::Parallel {
+            quantum: Period::ONE / 100,
+        })
+        .insert_component(
+            "independent",
+            CountingComponentConfig {
+                scheduler_participation: SchedulerParticipation::SchedulerDriven,
+                other: None,
+                watch_from: Period::ZERO,
+            },
+        );
+    let (machine, observed) = machine.insert_component(
+        "observed",
+        CountingComponentConfig {
+            scheduler_participation: SchedulerParticipation::OnDemand,
+            other: None,
+            watch_from: Period::ZERO,
+        },
+    );
+    let (machine, _) = machine.insert_component(
+        "observer",
+        CountingComponentConfig {
+            scheduler_participation: SchedulerParticipation::SchedulerDriven,
+            other: Some(observed),
+            watch_from: Period::ZERO,
+        },
+    );
+    // Never touched by anything the scheduler runs
+    let (machine, idle) = machine.insert_component(
+        "idle",
+        CountingComponentConfig {
+            scheduler_participation: SchedulerParticipation::OnDemand,
+            other: None,
+            watch_from: Period::ZERO,
+        },
+    );
+    let machine = machine.build(());
+
+    machine.run(Period::ONE / 10);
+
+    assert_eq!(machine.scheduler.checkpoint.lock().unwrap().len(), 3);
+    machine
+        .registry
+        .interact_without_synchronization::<CountingComponent, _>(&idle, |component| {
+            assert_eq!(component.counter, 0);
+        });
+}
+
+#[test]
+fn parallel_groups_meeting_mid_quantum_stay_in_step() {
+    // Driven components that touch each other only work out when the one
+    // being touched goes second, which these names happen to give
+    let (machine, watched) = Machine::build_test_minimal()
+        .execution_mode(ExecutionMode::Parallel {
+            quantum: Period::ONE / 100,
+        })
+        .insert_component(
+            "watched",
+            CountingComponentConfig {
+                scheduler_participation: SchedulerParticipation::SchedulerDriven,
+                other: None,
+                watch_from: Period::ZERO,
+            },
+        );
+    // Leaves the other alone at first, so they learn to run apart, then
+    // starts checking on it halfway into a quantum
+    let (machine, watcher) = machine.insert_component(
+        "watcher",
+        CountingComponentConfig {
+            scheduler_participation: SchedulerParticipation::SchedulerDriven,
+            other: Some(watched.clone()),
+            watch_from: Period::ONE / 2 + Period::ONE / 200,
+        },
+    );
+    let machine = machine.build(());
+
+    let handle = |path| machine.registry.handle(path).unwrap();
+    let (watched, watcher) = (handle(&watched), handle(&watcher));
+    let groups = || {
+        machine
+            .scheduler
+            .interactions
+            .lock()
+            .unwrap()
+            .groups([&watched, &watcher])
+            .len()
+    };
+
+    machine.run(Period::ONE / 2);
+    assert_eq!(groups(), 2);
+
+    machine.run(Period::ONE / 2);
+    assert_eq!(groups(), 1);
+
+    for handle in [&watched, &watcher] {
+        handle.interact_without_synchronization(|component| {
+            let component = (component as &dyn Any)
+                .downcast_ref::<CountingComponent>()
+                .unwrap();
+
+            assert_eq!(component.counter, 1000);
+        });
+    }
+}
+
+/// A single scheduler driven component to hang events off of
+fn event_test_machine() -> (Arc<Machine>, FluxEmuPath) {
+    let (machine, path) = Machine::build_test_minimal().insert_component(
+        "test",
+        CountingComponentConfig {
+            scheduler_participation: SchedulerParticipation::SchedulerDriven,
+            other: None,
+            watch_from: Period::ZERO,
+        },
+    );
+
+    (machine.build(()), path)
+}
+
+#[test]
+fn cancelled_event_does_not_fire() {
+    let (machine, path) = event_test_machine();
+    let fired = Arc::new(Mutex::new(Vec::new()));
+
+    let handle = machine.schedule_event::<CountingComponent>(Period::ONE / 2, &path, {
+        let fired = fired.clone();
+        move |_, time| fired.lock().unwrap().push(time)
+    });
+    handle.cancel();
+
+    machine.run(Period::ONE);
+    assert!(fired.lock().unwrap().is_empty());
+
+    // A cancelled one shot event is gone for good
+    assert!(!handle.reschedule(Period::ONE + Period::ONE / 2));
+    machine.run(Period::ONE);
+    assert!(fired.lock().unwrap().is_empty());
+}
+
+#[test]
+fn cancelled_repeating_event_can_be_rearmed() {
+    let (machine, path) = event_test_machine();
+    let fired = Arc::new(Mutex::new(Vec::new()));
+
+    let handle = machine.schedule_repeating_event::<CountingComponent>(
+        Period::ONE / 4,
+        Frequency::from_num(2),
+        &path,
+        {
+            let fired = fired.clone();
+            move |_, time| fired.lock().unwrap().push(time)
+        },
+    );
+    handle.cancel();
+
+    machine.run(Period::ONE);
+    assert!(fired.lock().unwrap().is_empty());
+
+    assert!(handle.reschedule(Period::ONE + Period::ONE / 4));
+    machine.run(Period::ONE);
+    assert_eq!(
+        *fired.lock().unwrap(),
+        [
+            Period::ONE + Period::ONE / 4,
+            Period::ONE + Period::ONE * 3 / 4
+        ]
+    );
+}
+
+#[test]
+fn rescheduled_event_fires_once_at_new_time() {
+    let (machine, path) = event_test_machine();
+    let fired = Arc::new(Mutex::new(Vec::new()));
+
+    let handle = machine.schedule_event::<CountingComponent>(Period::ONE / 4, &path, {
+        let fired = fired.clone();
+        move |_, time| fired.lock().unwrap().push(time)
+    });
+    assert!(handle.reschedule(Period::ONE * 3 / 4));
+
+    machine.run(Period::ONE / 2);
+    assert!(fired.lock().unwrap().is_empty());
+
+    machine.run(Period::ONE / 2);
+    assert_eq!(*fired.lock().unwrap(), [Period::ONE * 3 / 4]);
+}
+
+#[test]
+fn repeating_event_frequency_change() {
+    let (machine, path) = event_test_machine();
+    let fired = Arc::new(Mutex::new(Vec::new()));
+
+    let handle = machine.schedule_repeating_event::<CountingComponent>(
+        Period::ONE / 8,
+        Frequency::from_num(8),
+        &path,
+        {
+            let fired = fired.clone();
+            move |_, time| fired.lock().unwrap().push(time)
+        },
+    );
+
+    machine.run(Period::ONE * 9 / 16);
+    assert_eq!(fired.lock().unwrap().len(), 4);
+
+    // The occurrence already queued at 5/8 stays, the ones after it speed up
+    assert!(handle.set_frequency(Frequency::from_num(16)));
+    machine.run(Period::ONE * 15 / 32);
+
+    let fired = fired.lock().unwrap();
+    assert_eq!(fired.len(), 11);
+    assert_eq!(fired[4], Period::ONE * 5 / 8);
+    assert_eq!(fired[5], Period::ONE * 11 / 16);
+}
+
+#[test]
+fn repeating_event_can_cancel_and_move_itself() {
+    let (machine, path) = event_test_machine();
+    let fired = Arc::new(Mutex::new(Vec::new()));
+    let own_handle = Arc::new(Mutex::new(None::<EventHandle>));
+
+    let handle = machine.schedule_repeating_event::<CountingComponent>(
+        Period::ONE / 8,
+        Frequency::from_num(8),
+        &path,
+        {
+            let fired = fired.clone();
+            let own_handle = own_handle.clone();
+
+            move |_, time| {
+                let mut fired = fired.lock().unwrap();
+                fired.push(time);
+
+                let own_handle = own_handle.lock().unwrap();
+                let own_handle = own_handle.as_ref().unwrap();
+
+                match fired.len() {
+                    // Skip ahead, which the usual requeue must not undo
+                    2 => assert!(own_handle.reschedule(Period::ONE / 2)),
+                    4 => own_handle.cancel(),
+                    _ => {}
+                }
+            }
+        },
+    );
+    *own_handle.lock().unwrap() = Some(handle.clone());
+
+    machine.run(Period::ONE * 2);
+    assert_eq!(
+        *fired.lock().unwrap(),
+        [
+            Period::ONE / 8,
+            Period::ONE / 4,
+            Period::ONE / 2,
+            Period::ONE * 5 / 8
+        ]
+    );
+
+    // Re-arming carries on at the usual frequency
+    assert!(handle.reschedule(Period::ONE * 3));
+    machine.run(Period::ONE + Period::ONE * 3 / 16);
+    assert_eq!(
+        fired.lock().unwrap()[4..],
+        [Period::ONE * 3, Period::ONE * 25 / 8]
+    );
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct TickingComponent {
+    counter: u32,
+    ticks: u32,
+}
+
+impl Component for TickingComponent {
+    fn snapshot_version(&self) -> Option<ComponentVersion> {
+        Some(0)
+    }
+
+    fn store_snapshot(&self, mut writer: Box<dyn Write>) -> Result<(), Box<dyn std::error::Error>> {
+        rmp_serde::encode::write_named(&mut writer, self)?;
+
+        Ok(())
+    }
+
+    fn load_snapshot(
+        &mut self,
+        _version: ComponentVersion,
+        reader: Box<dyn Read>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        *self = rmp_serde::decode::from_read(reader)?;
+
+        Ok(())
+    }
+
+    fn synchronize(&mut self, mut context: SynchronizationContext) {
+        for _ in context.allocate(Period::ONE / 1000, None) {
+            self.counter += 1;
+        }
+    }
+
+    fn needs_work(&self, delta: Period) -> bool {
+        delta >= Period::ONE / 1000
+    }
+}
+
+#[derive(Debug)]
+struct TickingComponentConfig;
+
+impl<P: Platform> ComponentConfig<P> for TickingComponentConfig {
+    type Component = TickingComponent;
+
+    fn build_component(
+        self,
+        component_builder: ComponentBuilder<P, Self::Component>,
+    ) -> Result<Self::Component, Box<dyn std::error::Error>> {
+        let (component_builder, tick) = component_builder
+            .set_scheduler_participation(SchedulerParticipation::SchedulerDriven)
+            .insert_event("tick", |component, _| component.ticks += 1);
+        let (component_builder, _) =
+            component_builder.insert_event("bonus", |component, _| component.ticks += 100);
+
+        component_builder.schedule_repeating_named_event(
+            &tick,
+            Period::ONE / 8,
+            Frequency::from_num(8),
+        );
+
+        Ok(TickingComponent::default())
+    }
+}
+
+fn snapshot_test_machine(snapshot_directory: &Path) -> (Arc<Machine>, FluxEmuPath) {
+    let (machine, path) = Machine::build_test(
+        None,
+        Arc::new(ProgramManager::default()),
+        None,
+        Some(snapshot_directory.to_path_buf()),
+    )
+    .insert_component("test", TickingComponentConfig);
+
+    (machine.build(()), path)
+}
+
+fn ticking_state(machine: &Machine, path: &FluxEmuPath) -> (u32, u32) {
+    machine
+        .registry
+        .interact_without_synchronization::<TickingComponent, _>(path, |component| {
+            (component.counter, component.ticks)
+        })
+        .unwrap()
+}
+
+#[test]
+fn snapshot_restores_timing() {
+    let snapshot_directory = env::temp_dir().join(format!("fluxemu-{}-snapshot", process::id()));
+    let build = || snapshot_test_machine(&snapshot_directory);
+    let state = ticking_state;
+    let rom_id = RomId([0; 20]);
+
+    let (original, path) = build();
+    original.run(Period::ONE * 3 / 16);
+
+    let mut bonus = path.clone();
+    bonus.push(Namespace::Resource, "bonus");
+    original.schedule_named_event(Period::ONE / 2, &bonus);
+
+    original.store_snapshot(rom_id, "test", 0).unwrap();
+    original.run(Period::ONE);
+
+    let (restored, _) = build();
+    // Closures cannot be stored, so whatever they were waiting for is gone
+    // once the snapshot is loaded
+    restored.schedule_event::<TickingComponent>(Period::ONE / 4, &path, |component, _| {
+        component.ticks += 1000
+    });
+    restored.load_snapshot(rom_id, "test", 0).unwrap();
+    let _ = remove_dir_all(&snapshot_directory);
+
+    assert_eq!(restored.now(), Period::ONE * 3 / 16);
+    assert_eq!(state(&restored, &path), (187, 1));
+
+    // Both the repeating tick and the one off bonus carry on as they would have
+    restored.run(Period::ONE);
+    assert_eq!(state(&restored, &path), state(&original, &path));
+    assert_eq!(state(&restored, &path), (1187, 109));
+}
+
+#[test]
+fn snapshot_without_timing_keeps_current_timing() {
+    let snapshot_directory =
+        env::temp_dir().join(format!("fluxemu-{}-untimed-snapshot", process::id()));
+    let rom_id = RomId([0; 20]);
+
+    let (original, path) = snapshot_test_machine(&snapshot_directory);
+    original.run(Period::ONE * 3 / 16);
+    original.store_snapshot(rom_id, "test", 0).unwrap();
+
+    // As written before snapshots recorded timing
+    let metadata_path = snapshot_directory
+        .join(rom_id.to_string())
+        .join("test")
+        .join("0")
+        .join(SNAPSHOT_METADATA_FILE_NAME);
+    let mut metadata: SnapshotMetadata =
+        ron::from_str(&read_to_string(&metadata_path).unwrap()).unwrap();
+    metadata.timing = None;
+    write(&metadata_path, ron::to_string(&metadata).unwrap()).unwrap();
+
+    let (restored, _) = snapshot_test_machine(&snapshot_directory);
+    restored.run(Period::ONE * 9 / 16);
+    restored.load_snapshot(rom_id, "test", 0).unwrap();
+    let _ = remove_dir_all(&snapshot_directory);
+
+    assert_eq!(restored.now(), Period::ONE * 9 / 16);
+    assert_eq!(ticking_state(&restored, &path), (187, 1));
+
+    // The repeating tick keeps going from where this machine had it
+    restored.run(Period::ONE / 4);
+    assert_eq!(ticking_state(&restored, &path), (437, 3));
+}
@@ -1,15 +1,16 @@
-use std::fmt::Debug;
+use std::{collections::HashMap, fmt::Debug};
 
 use egui::FullOutput;
 use fluxemu_frontend::{
-    GraphicsRuntime, WindowingHandle, environment::Environment,
+    GraphicsRuntime, WindowingHandle, draw_software_displays, environment::Environment,
     gui_software_rendering::SoftwareEguiRenderer,
 };
 use fluxemu_runtime::{
     graphics::{GraphicsApi, software::Software},
     machine::Machine,
+    path::FluxEmuPath,
 };
-use nalgebra::{DMatrixViewMut, Point2, Vector2};
+use nalgebra::{DMatrixViewMut, Vector2};
 use palette::{cast::Packed, named::BLACK, rgb::channels::Argb};
 use softbuffer::{Context, Surface};
 
@@ -20,6 +21,8 @@ pub struct SoftwareGraphicsRuntime {
     display_api_handle: WinitWindow,
     egui_renderer: SoftwareEguiRenderer,
     previously_recorded_size: Vector2<u16>,
+    /// How much of the surface each display took up last frame
+    presented_display_sizes: HashMap<FluxEmuPath, Vector2<f32>>,
 }
 
 impl Debug for SoftwareGraphicsRuntime {
@@ -56,6 +59,7 @@ impl GraphicsRuntime<DesktopPlatform<Software, Self>> for SoftwareGraphicsRuntim
             display_api_handle,
             egui_renderer: SoftwareEguiRenderer::default(),
             previously_recorded_size: window_dimensions.cast(),
+            presented_display_sizes: HashMap::new(),
         })
     }
 
@@ -84,14 +88,9 @@ impl GraphicsRuntime<DesktopPlatform<Software, Self>> for SoftwareGraphicsRuntim
 
         let integer_scaling = environment.graphics_setting.integer_scaling;
 
-        if let Some(machine) = machine {
-            Self::draw_component_displays(
-                surface_buffer_view,
-                integer_scaling,
-                machine,
-                self.previously_recorded_size,
-            );
-        }
+        self.presented_display_sizes = machine
+            .map(|machine| draw_software_displays(surface_buffer_view, integer_scaling, machine))
+            .unwrap_or_default();
 
         let surface_buffer_view = DMatrixViewMut::from_slice(
             bytemuck::cast_slice_mut(surface_buffer.as_mut()),
@@ -122,108 +121,8 @@ impl GraphicsRuntime<DesktopPlatform<Software, Self>> for SoftwareGraphicsRuntim
         // We can do whatever we want forever
         u32::MAX
     }
-}
 
-impl SoftwareGraphicsRuntime {
-    fn draw_component_displays(
-        mut surface_buffer_view: DMatrixViewMut<Packed<Argb, u32>>,
-        integer_scaling: bool,
-        machine: &Machine,
-        previously_recorded_size: Vector2<u16>,
-    ) {
-        for display_path in machine.displays.iter() {
-            machine
-                .interact_dyn_mut(display_path, |component| {
-                    let display = component.access_framebuffer(display_path);
-
-                    let display: &<Software as GraphicsApi>::FramebufferTexture =
-                        display.downcast_ref().unwrap();
-
-                    if integer_scaling {
-                        let component_display_buffer_size =
-                            Vector2::new(display.nrows(), display.ncols()).cast::<u16>();
-
-                        let scaling = previously_recorded_size
-                            .component_div(&component_display_buffer_size)
-                            .cast::<usize>();
-
-                        // Iterate over each pixel in the display component buffer
-                        for x in 0..display.nrows() {
-                            for y in 0..display.ncols() {
-                                let position = Point2::new(x, y);
-                                let source_pixel = display[(position.x, position.y)];
-
-                                let dest_start = position.coords.component_mul(&scaling).zip_map(
-                                    &previously_recorded_size.cast::<usize>(),
-                                    std::cmp::min,
-                                );
-
-                                let dest_end = position
-                                    .coords
-                                    .add_scalar(1)
-                                    .component_mul(&scaling)
-                                    .zip_map(
-                                        &previously_recorded_size.cast::<usize>(),
-                                        std::cmp::min,
-                                    );
-
-                                let mut destination_pixels = surface_buffer_view.view_mut(
-                                    (dest_start.x, dest_start.y),
-                                    (dest_end.x - dest_start.x, dest_end.y - dest_start.y),
-                                );
-
-                                destination_pixels.fill(Packed::pack(source_pixel));
-                            }
-                        }
-                    } else {
-                        let component_display_buffer_size =
-                            Vector2::new(display.nrows(), display.ncols()).cast::<u16>();
-
-                        let scaling = previously_recorded_size
-                            .cast::<f32>()
-                            .component_div(&component_display_buffer_size.cast::<f32>());
-
-                        // Iterate over each pixel in the display component buffer
-                        for x in 0..display.nrows() {
-                            for y in 0..display.ncols() {
-                                let position = Point2::new(x, y);
-                                let source_pixel = display[(position.x, position.y)];
-
-                                let dest_start = position
-                                    .coords
-                                    .cast::<f32>()
-                                    .component_mul(&scaling)
-                                    .try_cast::<usize>()
-                                    .unwrap()
-                                    .zip_map(
-                                        &previously_recorded_size.cast::<usize>(),
-                                        |dest_dim, window_dim| dest_dim.min(window_dim),
-                                    );
-
-                                let dest_end = position
-                                    .coords
-                                    .cast::<f32>()
-                                    .add_scalar(1.0)
-                                    .component_mul(&scaling)
-                                    .try_cast::<usize>()
-                                    .unwrap()
-                                    .zip_map(
-                                        &previously_recorded_size.cast::<usize>(),
-                                        |dest_dim, window_dim| dest_dim.min(window_dim),
-                                    );
-
-                                // Fill the destination pixels with the source pixel
-                                let mut destination_pixels = surface_buffer_view.view_mut(
-                                    (dest_start.x, dest_start.y),
-                                    (dest_end.x - dest_start.x, dest_end.y - dest_start.y),
-                                );
-
-                                destination_pixels.fill(Packed::pack(source_pixel));
-                            }
-                        }
-                    }
-                })
-                .unwrap();
-        }
+    fn presented_display_size(&self, display_path: &FluxEmuPath) -> Option<Vector2<f32>> {
+        self.presented_display_sizes.get(display_path).copied()
     }
 }
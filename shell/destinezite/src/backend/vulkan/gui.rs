@@ -282,6 +282,32 @@ impl VulkanEguiRenderer {
         }
     }
 
+    /// Make an image that does not come from egui drawable by meshes using
+    /// `texture_id`
+    pub fn set_user_texture(&mut self, texture_id: TextureId, image: Arc<Image>) {
+        if self
+            .textures
+            .get(&texture_id)
+            .is_some_and(|(existing_image, _)| Arc::ptr_eq(existing_image, &image))
+        {
+            return;
+        }
+
+        let image_view =
+            ImageView::new(image.clone(), ImageViewCreateInfo::from_image(&image)).unwrap();
+        let layout = self.pipeline.layout().set_layouts()[1].clone();
+
+        let descriptor_set = DescriptorSet::new(
+            self.descriptor_set_allocator.clone(),
+            layout,
+            [WriteDescriptorSet::image_view(0, image_view)],
+            [],
+        )
+        .unwrap();
+
+        self.textures.insert(texture_id, (image, descriptor_set));
+    }
+
     pub fn render(
         &mut self,
         context: &egui::Context,
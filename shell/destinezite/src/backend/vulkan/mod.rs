@@ -4,7 +4,9 @@ use std::sync::{
 };
 
 use create::{create_vulkan_instance, create_vulkan_swapchain, select_vulkan_device};
-use fluxemu_frontend::{GraphicsRuntime, WindowingHandle, environment::Environment};
+use fluxemu_frontend::{
+    GraphicsRuntime, WindowingHandle, display_mesh, display_rotation, environment::Environment,
+};
 use fluxemu_runtime::{
     graphics::{
         GraphicsApi,
@@ -33,7 +35,7 @@ use fluxemu_runtime::{
             },
         },
     },
-    machine::Machine,
+    machine::{Machine, graphics::DisplayRotation},
     shader::{ShaderCache, SpirvShader},
 };
 use gui::VulkanEguiRenderer;
@@ -239,7 +241,7 @@ impl GraphicsRuntime<DesktopPlatform<Vulkan, Self>> for VulkanGraphicsRuntime {
     fn redraw(
         &mut self,
         egui_context: &egui::Context,
-        full_output: egui::FullOutput,
+        mut full_output: egui::FullOutput,
         machine: Option<&Machine>,
         environment: &Environment,
     ) {
@@ -263,7 +265,16 @@ impl GraphicsRuntime<DesktopPlatform<Vulkan, Self>> for VulkanGraphicsRuntime {
         .unwrap();
 
         if let Some(machine) = machine {
-            for display_path in machine.displays.iter() {
+            let screen_rect = egui::Rect::from_min_size(
+                egui::Pos2::ZERO,
+                egui::vec2(window_dimensions.x as f32, window_dimensions.y as f32)
+                    / full_output.pixels_per_point,
+            );
+            let mut rotated_displays = Vec::new();
+
+            for (index, display_path) in machine.displays.iter().enumerate() {
+                let rotation = display_rotation(machine, display_path);
+
                 machine
                     .interact_dyn_mut(display_path, |component| {
                         let display = component.access_framebuffer(display_path);
@@ -271,17 +282,38 @@ impl GraphicsRuntime<DesktopPlatform<Vulkan, Self>> for VulkanGraphicsRuntime {
                         let display: &<Vulkan as GraphicsApi>::FramebufferTexture =
                             display.downcast_ref().unwrap();
 
-                        command_buffer
-                            .blit_image(BlitImageInfo {
-                                src_image_layout: ImageLayout::TransferSrcOptimal,
-                                dst_image_layout: ImageLayout::TransferDstOptimal,
-                                filter: Filter::Nearest,
-                                ..BlitImageInfo::images(display.clone(), swapchain_image.clone())
-                            })
-                            .unwrap();
+                        if rotation == DisplayRotation::None {
+                            command_buffer
+                                .blit_image(BlitImageInfo {
+                                    src_image_layout: ImageLayout::TransferSrcOptimal,
+                                    dst_image_layout: ImageLayout::TransferDstOptimal,
+                                    filter: Filter::Nearest,
+                                    ..BlitImageInfo::images(
+                                        display.clone(),
+                                        swapchain_image.clone(),
+                                    )
+                                })
+                                .unwrap();
+                        } else {
+                            // Blits cannot turn images, so draw it as a textured quad under the gui
+                            let texture_id = egui::TextureId::User(index as u64);
+                            self.gui_renderer
+                                .set_user_texture(texture_id, display.clone());
+
+                            rotated_displays.push(egui::epaint::ClippedShape {
+                                clip_rect: screen_rect,
+                                shape: egui::Shape::mesh(display_mesh(
+                                    rotation,
+                                    texture_id,
+                                    screen_rect,
+                                )),
+                            });
+                        }
                     })
                     .unwrap();
             }
+
+            full_output.shapes.splice(0..0, rotated_displays);
         }
 
         self.gui_renderer.render(
@@ -1,12 +1,14 @@
 use fluxemu_definition_atari2600::Atari2600;
 use fluxemu_definition_atarilynx::AtariLynx;
 use fluxemu_definition_chip8::Chip8;
+use fluxemu_definition_gameboy::{GameBoy, GameBoyColor};
 use fluxemu_definition_nes::Nes;
+use fluxemu_definition_segamastersystem::{GameGear, MasterSystem};
 use fluxemu_frontend::MachineFactories;
 use fluxemu_runtime::{
     graphics::software::Software,
     platform::Platform,
-    program::{AtariSystem, MachineId, NintendoSystem, OtherSystem},
+    program::{AtariSystem, MachineId, NintendoSystem, OtherSystem, SegaSystem},
 };
 
 #[cfg(feature = "vulkan")]
@@ -18,9 +20,13 @@ pub fn get_vulkan_factories<
     factories.insert_factory::<Atari2600>(MachineId::Atari(AtariSystem::Atari2600));
     factories.insert_factory::<AtariLynx>(MachineId::Atari(AtariSystem::Lynx));
     factories.insert_factory::<Chip8>(MachineId::Other(OtherSystem::Chip8));
+    factories.insert_factory::<GameBoy>(MachineId::Nintendo(NintendoSystem::GameBoy));
+    factories.insert_factory::<GameBoyColor>(MachineId::Nintendo(NintendoSystem::GameBoyColor));
     factories.insert_factory::<Nes>(MachineId::Nintendo(
         NintendoSystem::NintendoEntertainmentSystem,
     ));
+    factories.insert_factory::<MasterSystem>(MachineId::Sega(SegaSystem::MasterSystem));
+    factories.insert_factory::<GameGear>(MachineId::Sega(SegaSystem::GameGear));
 
     factories
 }
@@ -31,9 +37,13 @@ pub fn get_software_factories<P: Platform<GraphicsApi = Software>>() -> MachineF
     factories.insert_factory::<Atari2600>(MachineId::Atari(AtariSystem::Atari2600));
     factories.insert_factory::<AtariLynx>(MachineId::Atari(AtariSystem::Lynx));
     factories.insert_factory::<Chip8>(MachineId::Other(OtherSystem::Chip8));
+    factories.insert_factory::<GameBoy>(MachineId::Nintendo(NintendoSystem::GameBoy));
+    factories.insert_factory::<GameBoyColor>(MachineId::Nintendo(NintendoSystem::GameBoyColor));
     factories.insert_factory::<Nes>(MachineId::Nintendo(
         NintendoSystem::NintendoEntertainmentSystem,
     ));
+    factories.insert_factory::<MasterSystem>(MachineId::Sega(SegaSystem::MasterSystem));
+    factories.insert_factory::<GameGear>(MachineId::Sega(SegaSystem::GameGear));
 
     factories
 }
@@ -1,7 +1,7 @@
 use std::ffi::OsString;
 
 use clap::{Parser, Subcommand};
-use fluxemu_runtime::program::MachineId;
+use fluxemu_runtime::program::{MachineId, VideoStandard};
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
@@ -18,5 +18,7 @@ pub enum CliAction {
         roms: Vec<OsString>,
         #[clap(short, long)]
         forced_machine_id: Option<MachineId>,
+        #[clap(long)]
+        forced_video_standard: Option<VideoStandard>,
     },
 }
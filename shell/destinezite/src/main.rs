@@ -83,6 +83,7 @@ fn main() {
             CliAction::Run {
                 roms,
                 forced_machine_id,
+                forced_video_standard,
             } => {
                 let main_rom_as_path = PathBuf::from(roms[0].clone());
                 let mut program_specification = if main_rom_as_path.is_file() {
@@ -101,6 +102,12 @@ fn main() {
                     program_specification.id.machine = forced_machine_id;
                 }
 
+                if let Some(forced_video_standard) = forced_video_standard {
+                    program_specification
+                        .info
+                        .set_video_standard(Some(forced_video_standard));
+                }
+
                 program_specification
             }
         };
@@ -11,18 +11,18 @@ use fluxemu_runtime::{
     graphics::GraphicsApi,
     input::{
         GamepadInput, Input, InputState, RealGamepad, RealGamepadId, RealGamepadMetadata,
-        keyboard::KeyboardInput,
+        keyboard::KeyboardInput, pointer::PointerInput,
     },
     platform::Platform,
     program::{ProgramManager, ProgramSpecification},
 };
 use gilrs::{EventType, Gilrs, GilrsBuilder};
-use nalgebra::Vector2;
+use nalgebra::{Point2, Vector2};
 use strum::IntoEnumIterator;
 use uuid::Uuid;
 use winit::{
     application::ApplicationHandler,
-    event::WindowEvent,
+    event::{MouseButton, TouchPhase, WindowEvent},
     event_loop::{ActiveEventLoop, EventLoop},
     raw_window_handle::{
         DisplayHandle, HandleError, HasDisplayHandle, HasWindowHandle, WindowHandle,
@@ -215,6 +215,44 @@ impl<G: GraphicsApi, GR: GraphicsRuntime<Self, WindowingHandle = WinitWindow>> A
                         .set(input, state);
                 }
             }
+            WindowEvent::CursorMoved {
+                device_id: _,
+                position,
+            } => {
+                self.frontend.set_pointer_position(
+                    KEYBOARD_ID,
+                    Point2::new(position.x as f32, position.y as f32),
+                );
+            }
+            WindowEvent::MouseInput {
+                device_id: _,
+                state,
+                button,
+            } => {
+                let input = match button {
+                    MouseButton::Left => PointerInput::Primary,
+                    MouseButton::Right => PointerInput::Secondary,
+                    _ => return,
+                };
+
+                self.frontend.get_gamepad(KEYBOARD_ID).unwrap().set(
+                    Input::Pointer(input),
+                    InputState::Digital(state.is_pressed()),
+                );
+            }
+            WindowEvent::Touch(touch) => {
+                self.frontend.set_pointer_position(
+                    KEYBOARD_ID,
+                    Point2::new(touch.location.x as f32, touch.location.y as f32),
+                );
+
+                let pressed = matches!(touch.phase, TouchPhase::Started | TouchPhase::Moved);
+
+                self.frontend.get_gamepad(KEYBOARD_ID).unwrap().set(
+                    Input::Pointer(PointerInput::Primary),
+                    InputState::Digital(pressed),
+                );
+            }
             WindowEvent::RedrawRequested => {
                 while let Some(ev) = self.gilrs_context.next_event() {
                     let gilrs_gamepad = self.gilrs_context.gamepad(ev.id);
@@ -269,6 +307,8 @@ impl<G: GraphicsApi, GR: GraphicsRuntime<Self, WindowingHandle = WinitWindow>> A
     }
 
     fn exiting(&mut self, _event_loop: &ActiveEventLoop) {
+        self.frontend.store_save();
+
         let mut environment_file = File::create(ENVIRONMENT_LOCATION.deref()).unwrap();
         self.frontend
             .environment
@@ -317,10 +357,13 @@ impl<G: GraphicsApi, GR: GraphicsRuntime<Self, WindowingHandle = WinitWindow>>
         }
 
         let gamepad = RealGamepad::new(RealGamepadMetadata {
-            name: Cow::Borrowed("Keyboard"),
+            name: Cow::Borrowed("Keyboard and Mouse"),
             // We really can't make any assumptions about what the keyboard has so lets say "All of
             // them"
-            present_inputs: KeyboardInput::iter().map(Input::Keyboard).collect(),
+            present_inputs: KeyboardInput::iter()
+                .map(Input::Keyboard)
+                .chain(PointerInput::iter().map(Input::Pointer))
+                .collect(),
         });
         frontend.insert_gamepad(KEYBOARD_ID, gamepad.clone());
 
@@ -9,7 +9,7 @@ use std::{
 use fluxemu_locale::{Iso639Alpha2, Iso639Alpha3};
 use fluxemu_runtime::program::{
     Filesystem, HASH_ALIAS_TABLE, MachineId, PROGRAM_INFORMATION_TABLE, ProgramId, ProgramInfo,
-    ProgramManager, RomId,
+    ProgramManager, RomId, VideoStandard,
 };
 use serde::{Deserialize, Deserializer};
 use serde_with::{DisplayFromStr, serde_as};
@@ -70,6 +70,7 @@ fn get_data_in_parentheses(input: &str) -> Vec<String> {
 
 struct NameMetadataExtractor {
     pub languages: BTreeSet<Iso639Alpha3>,
+    pub video_standard: Option<VideoStandard>,
 }
 
 impl FromStr for NameMetadataExtractor {
@@ -77,6 +78,7 @@ impl FromStr for NameMetadataExtractor {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let mut languages = BTreeSet::new();
+        let mut video_standard = None;
 
         // Split the string into parts based on parentheses
         let parts = get_data_in_parentheses(s);
@@ -95,10 +97,17 @@ impl FromStr for NameMetadataExtractor {
                 if let Some(lang) = LANGUAGE_OVERRIDES.get(part) {
                     languages.insert(lang.to_alpha3());
                 }
+
+                if let Ok(standard) = VideoStandard::from_str(part) {
+                    video_standard = Some(standard);
+                }
             }
         }
 
-        Ok(NameMetadataExtractor { languages })
+        Ok(NameMetadataExtractor {
+            languages,
+            video_standard,
+        })
     }
 }
 
@@ -166,6 +175,7 @@ pub fn import(
                 filesystem,
                 languages: name_metadata_extractor.languages,
                 version: None,
+                video_standard: name_metadata_extractor.video_standard,
             };
 
             program_information.insert(program_id.clone(), info)?;